@@ -1,7 +1,7 @@
 // An example of an echo server using fixed buffers for reading and writing TCP streams.
 // A buffer registry size of two is created, to allow a maximum of two simultaneous connections.
 
-use std::{env, iter, net::SocketAddr};
+use std::{env, net::SocketAddr};
 
 use tokio_uring::{
     buf::{fixed::FixedBufRegistry, BoundedBuf},
@@ -34,7 +34,7 @@ async fn accept_loop(listen_addr: SocketAddr) {
     );
 
     // Other iterators may be passed to FixedBufRegistry::new also.
-    let registry = FixedBufRegistry::new(iter::repeat(vec![0; 4096]).take(POOL_SIZE));
+    let registry = FixedBufRegistry::new(std::iter::repeat_n(vec![0; 4096], POOL_SIZE));
 
     // Register the buffers with the kernel, asserting the syscall passed.
 
@@ -85,7 +85,7 @@ async fn echo_handler(stream: TcpStream, peer: SocketAddr, registry: FixedBufReg
 
             let (res, nslice) = stream.write_fixed_all(fbuf1.slice(..read)).await;
 
-            let _ = res.unwrap();
+            res.unwrap();
             println!("peer {} all {} bytes ping-ponged", peer, read);
             n += read;
 
@@ -0,0 +1,35 @@
+/// The address of the buffer of an operation, as submitted to the kernel.
+///
+/// With the `debug-buffers` feature, the address is recorded on submission
+/// and compared to the address of the buffer when the operation completes,
+/// catching `IoBuf` implementations whose memory moves with the value.
+/// Without the feature, it takes no space and checks nothing.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct SubmittedPtr {
+    #[cfg(feature = "debug-buffers")]
+    ptr: Option<*const u8>,
+}
+
+impl SubmittedPtr {
+    /// Records the address submitted to the kernel.
+    #[allow(unused_variables)]
+    pub(crate) fn set(&mut self, ptr: *const u8) {
+        #[cfg(feature = "debug-buffers")]
+        {
+            self.ptr = Some(ptr);
+        }
+    }
+
+    /// Panics if the buffer returned by the operation is not at the
+    /// submitted address.
+    #[allow(unused_variables)]
+    pub(crate) fn check(&self, ptr: *const u8) {
+        #[cfg(feature = "debug-buffers")]
+        if let Some(submitted) = self.ptr {
+            assert_eq!(
+                submitted, ptr,
+                "the buffer has moved while owned by the kernel"
+            );
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use libc::iovec;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Returns the limit on locked memory of the process, in bytes, or `None`
+/// if it is unlimited.
+///
+/// Buffers registered with the kernel are charged against this limit
+/// (`RLIMIT_MEMLOCK`), unless the process has the `CAP_IPC_LOCK`
+/// capability. Compare it with the [`memlock_usage`] of a collection of
+/// buffers before registering it.
+///
+/// [`memlock_usage`]: super::FixedBufRegistry::memlock_usage
+pub fn memlock_limit() -> io::Result<Option<u64>> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if limit.rlim_cur == libc::RLIM_INFINITY {
+        Ok(None)
+    } else {
+        Ok(Some(limit.rlim_cur))
+    }
+}
+
+/// The error of a buffer registration exceeding the limit on locked memory.
+///
+/// Registering buffers fails with an [`io::Error`] of kind
+/// [`OutOfMemory`] when the kernel fails to charge the memory of the
+/// buffers. If the limit on locked memory is the likely cause, the error
+/// wraps a `MemlockExceeded`, which can be retrieved with
+/// [`io::Error::get_ref`] and reports the amounts involved.
+///
+/// [`OutOfMemory`]: io::ErrorKind::OutOfMemory
+#[derive(Debug)]
+pub struct MemlockExceeded {
+    required: u64,
+    limit: u64,
+}
+
+impl MemlockExceeded {
+    /// Returns the amount of locked memory the registration required, in
+    /// bytes.
+    pub fn required(&self) -> u64 {
+        self.required
+    }
+
+    /// Returns the limit on locked memory of the process, in bytes.
+    ///
+    /// Memory locked by other registrations and by `mlock` counts towards
+    /// the limit too.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+impl fmt::Display for MemlockExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "registering buffers requires {} bytes of locked memory, \
+             the RLIMIT_MEMLOCK limit is {} bytes",
+            self.required, self.limit
+        )
+    }
+}
+
+impl Error for MemlockExceeded {}
+
+/// Returns the amount of locked memory the kernel charges for registering
+/// the buffers: each buffer is charged for the whole pages it spans.
+pub(crate) fn usage(iovecs: &[iovec]) -> u64 {
+    let page_size = page_size();
+    iovecs
+        .iter()
+        .filter(|iov| iov.iov_len > 0)
+        .map(|iov| {
+            let start = iov.iov_base as usize / page_size;
+            let end = (iov.iov_base as usize + iov.iov_len).div_ceil(page_size);
+            ((end - start) * page_size) as u64
+        })
+        .sum()
+}
+
+/// Explains the failure of registering the buffers with `ENOMEM`, if the
+/// limit on locked memory is the likely cause.
+pub(crate) fn registration_error(e: io::Error, iovecs: &[iovec]) -> io::Error {
+    if e.raw_os_error() != Some(libc::ENOMEM) {
+        return e;
+    }
+    match memlock_limit() {
+        Ok(Some(limit)) => io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            MemlockExceeded {
+                required: usage(iovecs),
+                limit,
+            },
+        ),
+        _ => e,
+    }
+}
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
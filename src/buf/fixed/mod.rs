@@ -21,8 +21,21 @@ pub use handle::FixedBuf;
 mod buffers;
 pub(crate) use buffers::FixedBuffers;
 
+mod memlock;
+pub(crate) use memlock::registration_error;
+pub use memlock::{memlock_limit, MemlockExceeded};
+
+mod numa;
+pub use numa::current_numa_node;
+
+#[cfg(feature = "debug-buffers")]
+mod poison;
+
 mod pool;
 pub use pool::FixedBufPool;
 
 mod registry;
 pub use registry::FixedBufRegistry;
+
+mod sharded;
+pub use sharded::ShardedBufPool;
@@ -0,0 +1,62 @@
+use libc::iovec;
+use std::io;
+use std::ptr;
+
+// Not exported by the libc crate.
+const MPOL_BIND: libc::c_int = 2;
+const MPOL_MF_MOVE: libc::c_uint = 1 << 1;
+
+/// Returns the NUMA node of the CPU the current thread is running on.
+///
+/// A runtime pinned to the CPUs of a node can allocate its buffers on the
+/// node, see [`FixedBufPool::bind_to_numa_node`]. If the thread is not
+/// pinned, it can move to another node later.
+///
+/// [`FixedBufPool::bind_to_numa_node`]: super::FixedBufPool::bind_to_numa_node
+pub fn current_numa_node() -> io::Result<u32> {
+    let mut cpu: libc::c_uint = 0;
+    let mut node: libc::c_uint = 0;
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_getcpu,
+            &mut cpu as *mut libc::c_uint,
+            &mut node as *mut libc::c_uint,
+            ptr::null_mut::<libc::c_void>(),
+        )
+    };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(node)
+}
+
+/// Binds the memory pages of the buffers to a NUMA node, moving the pages
+/// which have been allocated elsewhere.
+pub(crate) fn bind(iovecs: &[iovec], node: u32) -> io::Result<()> {
+    let bits = u64::BITS as usize;
+    let mut nodemask = vec![0u64; node as usize / bits + 1];
+    nodemask[node as usize / bits] |= 1 << (node as usize % bits);
+
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+    for iov in iovecs.iter().filter(|iov| iov.iov_len > 0) {
+        // The whole pages spanned by the buffer are bound
+        let start = iov.iov_base as usize / page_size * page_size;
+        let end = (iov.iov_base as usize + iov.iov_len).div_ceil(page_size) * page_size;
+        let res = unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                start as *mut libc::c_void,
+                end - start,
+                MPOL_BIND,
+                nodemask.as_ptr(),
+                // The kernel expects one more than the number of bits
+                nodemask.len() * bits + 1,
+                MPOL_MF_MOVE,
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
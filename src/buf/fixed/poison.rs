@@ -0,0 +1,32 @@
+use libc::iovec;
+use std::ptr;
+use std::slice;
+
+// The byte the uninitialized part of a free buffer is filled with.
+const POISON: u8 = 0xa5;
+
+// Fills the part of the buffer past its initialized length with POISON.
+//
+// Safety: iovec must refer to a buffer not accessed by the application or
+// an operation in flight.
+pub(super) unsafe fn poison(iovec: &iovec, init_len: usize) {
+    let ptr = (iovec.iov_base as *mut u8).add(init_len);
+    ptr::write_bytes(ptr, POISON, iovec.iov_len - init_len);
+}
+
+// Panics if the part of the buffer past its initialized length has been
+// written to since the buffer was poisoned, that is, while it was free.
+//
+// Safety: the buffer must have been poisoned with the same init_len when
+// it was last checked in.
+pub(super) unsafe fn check(iovec: &iovec, init_len: usize, index: usize) {
+    let ptr = (iovec.iov_base as *const u8).add(init_len);
+    let tail = slice::from_raw_parts(ptr, iovec.iov_len - init_len);
+    if let Some(pos) = tail.iter().position(|&b| b != POISON) {
+        panic!(
+            "fixed buffer {} was written to at offset {} after it was checked in",
+            index,
+            init_len + pos
+        );
+    }
+}
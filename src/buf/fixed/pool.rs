@@ -12,6 +12,7 @@ use std::mem;
 use std::ptr;
 use std::rc::Rc;
 use std::slice;
+use tokio::sync::Notify;
 
 /// A dynamic collection of I/O buffers pre-registered with the kernel.
 ///
@@ -161,6 +162,47 @@ impl FixedBufPool {
         }
     }
 
+    /// Returns `true` if the pool belongs to the runtime of the current
+    /// thread.
+    pub(crate) fn is_current_runtime(&self) -> bool {
+        self.driver.is_current()
+    }
+
+    /// Returns the amount of locked memory, in bytes, the kernel charges
+    /// for registering the buffers.
+    ///
+    /// Each buffer is charged for the whole memory pages it spans. The
+    /// registration fails if the charge exceeds [`memlock_limit`], along
+    /// with the memory locked by other registrations of the process.
+    ///
+    /// [`memlock_limit`]: super::memlock_limit
+    pub fn memlock_usage(&self) -> u64 {
+        super::memlock::usage(self.inner.borrow().iovecs())
+    }
+
+    /// Binds the memory of the buffers to a NUMA node.
+    ///
+    /// On a multi-socket machine running a runtime per NUMA node, buffers
+    /// allocated on the node of the runtime thread avoid memory traffic
+    /// between the nodes. The node of the current thread is returned by
+    /// [`current_numa_node`]. Pages of the buffers allocated on another
+    /// node are moved. Memory pages are bound as a whole, so the pages
+    /// shared with other allocations are bound too; capacities in multiples
+    /// of the page size avoid this.
+    ///
+    /// This method should be called before the buffers are registered: the
+    /// pages of registered buffers are pinned and cannot be moved.
+    ///
+    /// [`current_numa_node`]: super::current_numa_node
+    ///
+    /// # Errors
+    ///
+    /// Fails if the kernel does not support NUMA policies, the node does not
+    /// exist, or the process is not allowed to use it.
+    pub fn bind_to_numa_node(&self, node: u32) -> io::Result<()> {
+        super::numa::bind(self.inner.borrow().iovecs(), node)
+    }
+
     /// Registers the buffers with the kernel.
     ///
     /// This method must be called in the context of a `tokio-uring` runtime.
@@ -180,6 +222,12 @@ impl FixedBufPool {
     /// If a collection of buffers is currently registered in the context
     /// of the `tokio-uring` runtime this call is made in, the function returns
     /// an error.
+    ///
+    /// If the buffers exceed the limit on locked memory, the error wraps
+    /// a [`MemlockExceeded`] reporting the [`memlock_usage`] and the limit.
+    ///
+    /// [`MemlockExceeded`]: super::MemlockExceeded
+    /// [`memlock_usage`]: Self::memlock_usage
     pub fn register(&self) -> io::Result<()> {
         self.driver
             .upgrade()
@@ -229,6 +277,99 @@ impl FixedBufPool {
             unsafe { FixedBuf::new(registry, data) }
         })
     }
+
+    /// Installs this pool as the default pool of the current runtime,
+    /// returning the pool installed before, if any.
+    ///
+    /// The pooled I/O methods, such as [`File::read_at_pooled`], check
+    /// buffers out of the default pool for the duration of an operation,
+    /// so that the application does not need to pass a pool around. The
+    /// pool should be [registered] for the methods to use it.
+    ///
+    /// [`File::read_at_pooled`]: crate::fs::File::read_at_pooled
+    /// [registered]: Self::register
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pool was not created in the runtime of the current
+    /// thread.
+    pub fn install_default(&self) -> Option<FixedBufPool> {
+        assert!(
+            self.is_current_runtime(),
+            "the pool belongs to another runtime"
+        );
+        let handle = CONTEXT.with(|x| x.handle().expect("Not in a runtime context"));
+        handle.set_default_pool(Some(self.clone()))
+    }
+
+    /// Returns the default pool of the current runtime, if one has been
+    /// installed with [`install_default`].
+    ///
+    /// [`install_default`]: Self::install_default
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub fn default_pool() -> Option<FixedBufPool> {
+        CONTEXT.with(|x| x.handle().expect("Not in a runtime context").default_pool())
+    }
+
+    /// Removes the default pool of the current runtime, returning it.
+    ///
+    /// The buffers stay registered, and the pooled I/O methods fail until
+    /// another pool is installed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub fn uninstall_default() -> Option<FixedBufPool> {
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .set_default_pool(None)
+        })
+    }
+
+    // Checks out a buffer of at least `len` bytes from the default pool,
+    // with no initialized data, waiting for one to be checked in if none is
+    // free.
+    pub(crate) async fn next_default(len: usize) -> io::Result<FixedBuf> {
+        let pool = Self::default_pool().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no default fixed buffer pool is installed",
+            )
+        })?;
+        pool.next_fit(len).await
+    }
+
+    async fn next_fit(&self, len: usize) -> io::Result<FixedBuf> {
+        let checked_in = {
+            let inner = self.inner.borrow();
+            if inner.max_cap() < len {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no buffer in the pool is large enough",
+                ));
+            }
+            Rc::clone(&inner.checked_in)
+        };
+        loop {
+            let notified = checked_in.notified();
+            tokio::pin!(notified);
+            // Register for the wakeup before looking for a buffer, so that
+            // a check-in in between is not missed
+            notified.as_mut().enable();
+            let data = self.inner.borrow_mut().try_next_fit(len);
+            if let Some(data) = data {
+                let registry = Rc::clone(&self.inner);
+                // Safety: the validity of buffer data is ensured by
+                // Inner::try_next
+                return Ok(unsafe { FixedBuf::new(registry, data) });
+            }
+            notified.await;
+        }
+    }
 }
 
 // Internal state shared by FixedBufPool and FixedBuf handles.
@@ -244,6 +385,8 @@ struct Inner {
     orig_cap: usize,
     // Table of head indices of the free buffer lists in each size bucket.
     free_buf_head_by_cap: HashMap<usize, u16>,
+    // Wakes the tasks waiting for a buffer to be checked in.
+    checked_in: Rc<Notify>,
 }
 
 // State information of a buffer in the registry,
@@ -287,16 +430,26 @@ impl Inner {
             mem::forget(buf);
         }
         debug_assert_eq!(iovecs.len(), states.len());
+        #[cfg(feature = "debug-buffers")]
+        for (iovec, state) in iovecs.iter().zip(&states) {
+            if let BufState::Free { init_len, .. } = *state {
+                // Safety: the buffers are not shared yet
+                unsafe { super::poison::poison(iovec, init_len) };
+            }
+        }
 
         // Safety: Vec::as_mut_ptr never returns null
         let raw_bufs = unsafe { ptr::NonNull::new_unchecked(iovecs.as_mut_ptr()) };
         let orig_cap = iovecs.capacity();
         mem::forget(iovecs);
+        #[cfg(feature = "metrics")]
+        crate::metrics::fixed_bufs_allocated(states.len());
         Inner {
             raw_bufs,
             states,
             orig_cap,
             free_buf_head_by_cap,
+            checked_in: Rc::new(Notify::new()),
         }
     }
 
@@ -309,7 +462,15 @@ impl Inner {
 
         let (init_len, next) = match *state {
             BufState::Free { init_len, next } => {
+                // Safety: the index is inside the array of buffers, which
+                // has been poisoned when the buffer was last checked in
+                #[cfg(feature = "debug-buffers")]
+                unsafe {
+                    super::poison::check(&*self.raw_bufs.as_ptr().add(index), init_len, index)
+                };
                 *state = BufState::CheckedOut;
+                #[cfg(feature = "metrics")]
+                crate::metrics::fixed_buf_checked_out();
                 (init_len, next)
             }
             BufState::CheckedOut => panic!("buffer is checked out"),
@@ -338,13 +499,41 @@ impl Inner {
         })
     }
 
+    // Checks out a free buffer of the smallest capacity not less than `len`,
+    // with no initialized data.
+    fn try_next_fit(&mut self, len: usize) -> Option<CheckedOutBuf> {
+        let cap = self
+            .free_buf_head_by_cap
+            .keys()
+            .copied()
+            .filter(|&cap| cap >= len)
+            .min()?;
+        self.try_next(cap).map(|data| CheckedOutBuf {
+            init_len: 0,
+            ..data
+        })
+    }
+
+    // Returns the capacity of the largest buffer, free or not.
+    fn max_cap(&self) -> usize {
+        self.iovecs()
+            .iter()
+            .map(|iovec| iovec.iov_len)
+            .max()
+            .unwrap_or(0)
+    }
+
     fn check_in_internal(&mut self, index: u16, init_len: usize) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::fixed_buf_checked_in();
         let cap = self.iovecs()[index as usize].iov_len;
         let state = &mut self.states[index as usize];
-        debug_assert!(
-            matches!(state, BufState::CheckedOut),
-            "the buffer must be checked out"
-        );
+        if cfg!(any(debug_assertions, feature = "debug-buffers")) {
+            assert!(
+                matches!(state, BufState::CheckedOut),
+                "the buffer must be checked out"
+            );
+        }
 
         // Link the buffer as the new head of the free list for its capacity.
         // Recently checked in buffers will be first to be reused,
@@ -352,6 +541,14 @@ impl Inner {
         let next = self.free_buf_head_by_cap.insert(cap, index);
 
         *state = BufState::Free { init_len, next };
+        self.checked_in.notify_waiters();
+
+        // Safety: the handle of the buffer is being dropped, and the kernel
+        // is done with the buffer
+        #[cfg(feature = "debug-buffers")]
+        unsafe {
+            super::poison::poison(&self.iovecs()[index as usize], init_len)
+        };
     }
 }
 
@@ -370,6 +567,8 @@ impl FixedBuffers for Inner {
 
 impl Drop for Inner {
     fn drop(&mut self) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::fixed_bufs_released(self.states.len());
         let iovecs = unsafe {
             Vec::from_raw_parts(self.raw_bufs.as_ptr(), self.states.len(), self.orig_cap)
         };
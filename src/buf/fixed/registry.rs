@@ -115,6 +115,41 @@ impl FixedBufRegistry {
         }
     }
 
+    /// Returns the amount of locked memory, in bytes, the kernel charges
+    /// for registering the buffers.
+    ///
+    /// Each buffer is charged for the whole memory pages it spans. The
+    /// registration fails if the charge exceeds [`memlock_limit`], along
+    /// with the memory locked by other registrations of the process.
+    ///
+    /// [`memlock_limit`]: super::memlock_limit
+    pub fn memlock_usage(&self) -> u64 {
+        super::memlock::usage(self.inner.borrow().iovecs())
+    }
+
+    /// Binds the memory of the buffers to a NUMA node.
+    ///
+    /// On a multi-socket machine running a runtime per NUMA node, buffers
+    /// allocated on the node of the runtime thread avoid memory traffic
+    /// between the nodes. The node of the current thread is returned by
+    /// [`current_numa_node`]. Pages of the buffers allocated on another
+    /// node are moved. Memory pages are bound as a whole, so the pages
+    /// shared with other allocations are bound too; capacities in multiples
+    /// of the page size avoid this.
+    ///
+    /// This method should be called before the buffers are registered: the
+    /// pages of registered buffers are pinned and cannot be moved.
+    ///
+    /// [`current_numa_node`]: super::current_numa_node
+    ///
+    /// # Errors
+    ///
+    /// Fails if the kernel does not support NUMA policies, the node does not
+    /// exist, or the process is not allowed to use it.
+    pub fn bind_to_numa_node(&self, node: u32) -> io::Result<()> {
+        super::numa::bind(self.inner.borrow().iovecs(), node)
+    }
+
     /// Registers the buffers with the kernel.
     ///
     /// This method must be called in the context of a `tokio-uring` runtime.
@@ -134,6 +169,12 @@ impl FixedBufRegistry {
     /// If a collection of buffers is currently registered in the context
     /// of the `tokio-uring` runtime this call is made in, the function returns
     /// an error.
+    ///
+    /// If the buffers exceed the limit on locked memory, the error wraps
+    /// a [`MemlockExceeded`] reporting the [`memlock_usage`] and the limit.
+    ///
+    /// [`MemlockExceeded`]: super::MemlockExceeded
+    /// [`memlock_usage`]: Self::memlock_usage
     pub fn register(&self) -> io::Result<()> {
         self.driver
             .upgrade()
@@ -222,11 +263,20 @@ impl Inner {
             mem::forget(buf);
         }
         debug_assert_eq!(iovecs.len(), states.len());
+        #[cfg(feature = "debug-buffers")]
+        for (iovec, state) in iovecs.iter().zip(&states) {
+            if let BufState::Free { init_len, .. } = *state {
+                // Safety: the buffers are not shared yet
+                unsafe { super::poison::poison(iovec, init_len) };
+            }
+        }
 
         // Safety: Vec::as_mut_ptr never returns null
         let raw_bufs = unsafe { ptr::NonNull::new_unchecked(iovecs.as_mut_ptr()) };
         let orig_cap = iovecs.capacity();
         mem::forget(iovecs);
+        #[cfg(feature = "metrics")]
+        crate::metrics::fixed_bufs_allocated(states.len());
         Inner {
             raw_bufs,
             states,
@@ -240,10 +290,18 @@ impl Inner {
     fn check_out(&mut self, index: usize) -> Option<CheckedOutBuf> {
         let state = self.states.get_mut(index)?;
         let BufState::Free { init_len } = *state else {
-            return None
+            return None;
+        };
+        // Safety: the index is inside the array of buffers, which has been
+        // poisoned when the buffer was last checked in
+        #[cfg(feature = "debug-buffers")]
+        unsafe {
+            super::poison::check(&*self.raw_bufs.as_ptr().add(index), init_len, index)
         };
 
         *state = BufState::CheckedOut;
+        #[cfg(feature = "metrics")]
+        crate::metrics::fixed_buf_checked_out();
 
         // Safety: the allocated array under the pointer is valid
         // for the lifetime of self, the index is inside the array
@@ -259,15 +317,26 @@ impl Inner {
     }
 
     fn check_in_internal(&mut self, index: u16, init_len: usize) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::fixed_buf_checked_in();
         let state = self
             .states
             .get_mut(index as usize)
             .expect("invalid buffer index");
-        debug_assert!(
-            matches!(state, BufState::CheckedOut),
-            "the buffer must be checked out"
-        );
+        if cfg!(any(debug_assertions, feature = "debug-buffers")) {
+            assert!(
+                matches!(state, BufState::CheckedOut),
+                "the buffer must be checked out"
+            );
+        }
         *state = BufState::Free { init_len };
+
+        // Safety: the handle of the buffer is being dropped, and the kernel
+        // is done with the buffer
+        #[cfg(feature = "debug-buffers")]
+        unsafe {
+            super::poison::poison(&self.iovecs()[index as usize], init_len)
+        };
     }
 }
 
@@ -286,6 +355,8 @@ impl FixedBuffers for Inner {
 
 impl Drop for Inner {
     fn drop(&mut self) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::fixed_bufs_released(self.states.len());
         let iovecs = unsafe {
             Vec::from_raw_parts(self.raw_bufs.as_ptr(), self.states.len(), self.orig_cap)
         };
@@ -0,0 +1,136 @@
+use super::{current_numa_node, FixedBuf, FixedBufPool};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::iter;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+thread_local! {
+    // Pools created on this thread, by the id of their sharded pool.
+    static SHARDS: RefCell<HashMap<u64, FixedBufPool>> = RefCell::new(HashMap::new());
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A buffer pool sharded across runtimes, with a pool of registered buffers
+/// per runtime.
+///
+/// An application running a `tokio-uring` runtime on each of several
+/// threads cannot share a [`FixedBufPool`] between them: buffers are
+/// registered with the io-uring instance of a runtime. A `ShardedBufPool`
+/// can be shared instead. It holds the sizing policy of the pools, and
+/// the [`local`] method returns the pool of the current runtime, which is
+/// allocated and registered on first use. Each runtime has its own pool, so
+/// taking buffers from it involves no synchronization between the threads.
+///
+/// The pool of a runtime is registered as its fixed buffers, so no other
+/// buffer collection can be registered with the runtime.
+///
+/// [`local`]: ShardedBufPool::local
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::buf::fixed::ShardedBufPool;
+/// use tokio_uring::fs::File;
+///
+/// // 64 buffers of 4 KiB and 8 buffers of 1 MiB per runtime
+/// let mut pool = ShardedBufPool::new([(4096, 64), (1 << 20, 8)]);
+/// pool.numa_local(true);
+///
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let pool = pool.clone();
+///         std::thread::spawn(move || {
+///             tokio_uring::start(async move {
+///                 let file = File::open("data.bin").await?;
+///                 let buf = pool.try_next(4096)?.expect("no buffer available");
+///                 let (res, _buf) = file.read_fixed_at(buf, 0).await;
+///                 res?;
+///                 Ok::<_, std::io::Error>(())
+///             })
+///         })
+///     })
+///     .collect();
+///
+/// for worker in workers {
+///     worker.join().unwrap().unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShardedBufPool {
+    id: u64,
+    tiers: Arc<[(usize, usize)]>,
+    numa_local: bool,
+}
+
+impl ShardedBufPool {
+    /// Creates a sharded pool whose per-runtime pools are made of the given
+    /// tiers of buffers.
+    ///
+    /// Each tier is a pair of a buffer capacity and the number of buffers
+    /// with that capacity.
+    pub fn new(tiers: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        ShardedBufPool {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            tiers: tiers.into_iter().collect(),
+            numa_local: false,
+        }
+    }
+
+    /// Sets whether the buffers of a runtime are bound to the NUMA node of
+    /// the thread which allocates them.
+    ///
+    /// Binding to the node is only effective if the runtime threads are
+    /// pinned to the CPUs of a node. See
+    /// [`FixedBufPool::bind_to_numa_node`]. The setting applies to the
+    /// clones made after it is changed.
+    pub fn numa_local(&mut self, enable: bool) -> &mut Self {
+        self.numa_local = enable;
+        self
+    }
+
+    /// Returns the pool of the runtime of the current thread, allocating and
+    /// registering it if this is the first use of the pool by the runtime.
+    ///
+    /// This method must be called in the context of a `tokio-uring` runtime.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the buffers cannot be bound to the NUMA node, or registered
+    /// with the runtime, e.g. because other buffers are registered with it.
+    pub fn local(&self) -> io::Result<FixedBufPool> {
+        SHARDS.with(|shards| {
+            let mut shards = shards.borrow_mut();
+            if let Some(pool) = shards.get(&self.id) {
+                if pool.is_current_runtime() {
+                    return Ok(pool.clone());
+                }
+            }
+            // Forget the pools of the runtimes which have been dropped
+            shards.retain(|_, pool| pool.is_current_runtime());
+
+            let pool = FixedBufPool::new(self.tiers.iter().flat_map(|&(capacity, count)| {
+                iter::repeat_with(move || Vec::with_capacity(capacity)).take(count)
+            }));
+            if self.numa_local {
+                pool.bind_to_numa_node(current_numa_node()?)?;
+            }
+            pool.register()?;
+            shards.insert(self.id, pool.clone());
+            Ok(pool)
+        })
+    }
+
+    /// Returns a buffer of the given capacity from the pool of the current
+    /// runtime, if one is available.
+    ///
+    /// See [`local`] and [`FixedBufPool::try_next`].
+    ///
+    /// [`local`]: ShardedBufPool::local
+    pub fn try_next(&self, cap: usize) -> io::Result<Option<FixedBuf>> {
+        Ok(self.local()?.try_next(cap))
+    }
+}
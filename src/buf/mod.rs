@@ -6,6 +6,8 @@
 
 pub mod fixed;
 
+pub mod provided;
+
 mod io_buf;
 pub use io_buf::IoBuf;
 
@@ -18,6 +20,17 @@ pub use slice::Slice;
 mod bounded;
 pub use bounded::{BoundedBuf, BoundedBufMut};
 
+mod debug;
+pub(crate) use debug::SubmittedPtr;
+
+#[cfg(feature = "zerocopy")]
+pub mod view;
+
+#[cfg(feature = "stable-deref")]
+mod owned;
+#[cfg(feature = "stable-deref")]
+pub use owned::OwnedBuf;
+
 pub(crate) fn deref(buf: &impl IoBuf) -> &[u8] {
     // Safety: the `IoBuf` trait is marked as unsafe and is expected to be
     // implemented correctly.
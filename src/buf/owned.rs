@@ -0,0 +1,125 @@
+use crate::buf::{IoBuf, IoBufMut};
+use stable_deref_trait::StableDeref;
+use std::ops::{Deref, DerefMut};
+
+/// A buffer owning a byte container whose contents do not move.
+///
+/// Containers implementing [`StableDeref`], such as boxed slices,
+/// reference-counted slices, or the allocations of arena allocators and the
+/// memory maps of crates which implement the trait, can be wrapped in an
+/// `OwnedBuf` to be passed to io-uring operations, without an unsafe
+/// implementation of [`IoBuf`] for them. Containers which can be mutated
+/// through [`DerefMut`] can also be read into.
+///
+/// All the bytes of the container are initialized, so operations reading
+/// into the buffer fill the container from its start, and leave the rest
+/// of the container unchanged if they read fewer bytes.
+///
+/// Requires the `stable-deref` feature.
+///
+/// [`StableDeref`]: stable_deref_trait::StableDeref
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use tokio_uring::buf::OwnedBuf;
+/// use tokio_uring::fs::File;
+///
+/// tokio_uring::start(async {
+///     let contents: Arc<[u8]> = Arc::from(&b"shared contents"[..]);
+///     let file = File::create("copy.txt").await.unwrap();
+///     let (res, _) = file.write_all_at(OwnedBuf::new(contents.clone()), 0).await;
+///     res.unwrap();
+/// });
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OwnedBuf<T> {
+    inner: T,
+}
+
+impl<T> OwnedBuf<T>
+where
+    T: StableDeref<Target = [u8]>,
+{
+    /// Wraps the container in a buffer.
+    pub fn new(inner: T) -> OwnedBuf<T> {
+        OwnedBuf { inner }
+    }
+
+    /// Returns a reference to the container.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the container.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwraps the container.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> From<T> for OwnedBuf<T>
+where
+    T: StableDeref<Target = [u8]>,
+{
+    fn from(inner: T) -> OwnedBuf<T> {
+        OwnedBuf::new(inner)
+    }
+}
+
+impl<T> Deref for OwnedBuf<T>
+where
+    T: StableDeref<Target = [u8]>,
+{
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for OwnedBuf<T>
+where
+    T: StableDeref<Target = [u8]> + DerefMut,
+{
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.inner
+    }
+}
+
+// Safety: the contents of a `StableDeref` container stay at the same
+// address while the container is moved, and all of them are initialized.
+unsafe impl<T> IoBuf for OwnedBuf<T>
+where
+    T: StableDeref<Target = [u8]> + Unpin + 'static,
+{
+    fn stable_ptr(&self) -> *const u8 {
+        self.inner.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn bytes_total(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+unsafe impl<T> IoBufMut for OwnedBuf<T>
+where
+    T: StableDeref<Target = [u8]> + DerefMut + Unpin + 'static,
+{
+    fn stable_mut_ptr(&mut self) -> *mut u8 {
+        self.inner.as_mut_ptr()
+    }
+
+    unsafe fn set_init(&mut self, _pos: usize) {
+        // All the bytes are initialized
+    }
+}
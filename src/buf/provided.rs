@@ -0,0 +1,609 @@
+//! Buffers provided to the kernel for operations to pick from.
+//!
+//! A [`BufRing`] is a group of buffers registered with the kernel, which
+//! operations submitted with buffer selection draw from as data arrives.
+//! Unlike a buffer passed to an operation, a provided buffer is only taken
+//! when there is data to put in it, so that many operations waiting for
+//! data, or a multishot operation receiving a stream of it, do not each tie
+//! up a buffer.
+//!
+//! The buffer filled by an operation is returned as a [`ProvidedBuf`],
+//! which is given back to the kernel when it is dropped.
+//!
+//! Conversely, a [`SendRing`] is a queue of buffers of data to send, which
+//! a send submitted with buffer selection transmits together.
+
+use crate::buf::IoBuf;
+use crate::runtime::driver::{Handle, WeakHandle};
+use crate::runtime::CONTEXT;
+use io_uring::types::BufRingEntry;
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::pin;
+use std::rc::Rc;
+use std::slice;
+use std::sync::atomic::{AtomicU16, Ordering};
+use tokio::sync::Notify;
+
+// Operation of `io_uring_register` unregistering a buffer ring.
+const IORING_UNREGISTER_PBUF_RING: libc::c_uint = 23;
+
+/// A ring of buffers registered with the kernel as a buffer group.
+///
+/// Created with [`BufRing::builder`]. The ring holds a number of buffers
+/// of the same size, allocated together, and identified to the kernel by
+/// the group ID given to the builder, which must not be used by another
+/// ring of the same runtime.
+///
+/// The ring can be cloned, and the clones refer to the same buffers. It is
+/// unregistered once all clones, and all the [`ProvidedBuf`] taken from it,
+/// have been dropped.
+///
+/// When all of the buffers are taken, operations drawing from the ring fail
+/// with `ENOBUFS`, or stop in the case of a multishot operation.
+#[derive(Clone)]
+pub struct BufRing {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    ring: Ring,
+    buf_len: usize,
+    // Dropped after the ring, once it is unregistered
+    bufs: Bufs,
+    // Number of buffers held as `ProvidedBuf`
+    taken: Cell<usize>,
+    // Set while a task waits for a buffer to be given back
+    starved: Cell<bool>,
+    returned: Notify,
+}
+
+/// Configures and registers a [`BufRing`].
+///
+/// Created with [`BufRing::builder`].
+#[derive(Clone, Debug)]
+pub struct Builder {
+    bgid: u16,
+    entries: u16,
+    buf_len: usize,
+}
+
+impl Builder {
+    /// Sets the number of buffers, 64 by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entries` is not a power of two, or is above 32768.
+    pub fn entries(&mut self, entries: u16) -> &mut Self {
+        assert!(
+            entries.is_power_of_two() && entries <= 32768,
+            "the number of buffers must be a power of two, up to 32768"
+        );
+        self.entries = entries;
+        self
+    }
+
+    /// Sets the size of each buffer, 4096 bytes by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf_len` is 0 or exceeds `u32::MAX`.
+    pub fn buf_len(&mut self, buf_len: usize) -> &mut Self {
+        assert!(
+            buf_len > 0 && buf_len <= u32::MAX as usize,
+            "the buffer size must be between 1 and u32::MAX"
+        );
+        self.buf_len = buf_len;
+        self
+    }
+
+    /// Allocates the buffers and registers the ring with the runtime of the
+    /// current thread.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the kernel does not support buffer rings, which requires
+    /// Linux 5.19, or if the group ID is already in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub fn build(&self) -> io::Result<BufRing> {
+        let handle = CONTEXT.with(|x| x.handle().expect("Not in a runtime context"));
+
+        let ring = Ring::new(&handle, self.bgid, self.entries)?;
+        let layout = Layout::from_size_align(
+            usize::from(self.entries) * self.buf_len,
+            ring.layout.align(),
+        )
+        .map_err(io::Error::other)?;
+        let ptr = unsafe { alloc::alloc(layout) };
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        let mut inner = Inner {
+            ring,
+            buf_len: self.buf_len,
+            bufs: Bufs { ptr, layout },
+            taken: Cell::new(0),
+            starved: Cell::new(false),
+            returned: Notify::new(),
+        };
+        for bid in 0..self.entries {
+            inner.push(bid);
+        }
+        // Safety: the buffers stay allocated as long as the ring
+        unsafe { inner.ring.register(&handle)? };
+
+        Ok(BufRing {
+            inner: Rc::new(inner),
+        })
+    }
+}
+
+// The entries of a ring of buffers shared with the kernel, registered as a
+// buffer group.
+struct Ring {
+    driver: WeakHandle,
+    ring_fd: RawFd,
+    bgid: u16,
+    // Mask of the entry indices, one less than the number of entries
+    mask: u16,
+    entries: *mut BufRingEntry,
+    layout: Layout,
+    // Local copy of the tail of the ring, shared with the kernel
+    tail: Cell<u16>,
+    // Index of the next entry the kernel consumes, advanced as completions
+    // report the buffers taken from the ring
+    head: Cell<u16>,
+}
+
+impl Ring {
+    // Allocates the entries of a ring, which is registered with `register`
+    // once the buffers are pushed.
+    fn new(handle: &Handle, bgid: u16, entries: u16) -> io::Result<Ring> {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        // The kernel requires the ring to be page aligned
+        let layout = Layout::from_size_align(
+            usize::from(entries) * std::mem::size_of::<BufRingEntry>(),
+            page_size,
+        )
+        .map_err(io::Error::other)?;
+        let ring = unsafe { alloc::alloc_zeroed(layout) } as *mut BufRingEntry;
+        if ring.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        Ok(Ring {
+            driver: handle.into(),
+            ring_fd: -1,
+            bgid,
+            mask: entries - 1,
+            entries: ring,
+            layout,
+            tail: Cell::new(0),
+            head: Cell::new(0),
+        })
+    }
+
+    // Registers the ring with the runtime of `handle`.
+    //
+    // Safety: the buffers pushed to the ring must stay valid until the ring
+    // is dropped.
+    unsafe fn register(&mut self, handle: &Handle) -> io::Result<()> {
+        let (entries, bgid) = (self.mask + 1, self.bgid);
+        let addr = self.entries as u64;
+        // Safety: the ring stays allocated until it is unregistered, or the
+        // runtime is dropped
+        let ring_fd = handle.with_ring(|uring| {
+            uring.submitter().register_buf_ring(addr, entries, bgid)?;
+            Ok::<_, io::Error>(uring.as_raw_fd())
+        })??;
+        self.ring_fd = ring_fd;
+        Ok(())
+    }
+
+    // Adds a buffer at the tail of the ring, making it available to the
+    // kernel.
+    fn push(&self, addr: u64, len: u32, bid: u16) {
+        let tail = self.tail.get();
+        unsafe {
+            let entry = &mut *self.entries.add(usize::from(tail & self.mask));
+            entry.set_addr(addr);
+            entry.set_len(len);
+            entry.set_bid(bid);
+        }
+        let tail = tail.wrapping_add(1);
+        self.tail.set(tail);
+        // Safety: the tail field overlays the first entry, which is
+        // initialized; the kernel reads it concurrently
+        unsafe {
+            let tail_ptr = BufRingEntry::tail(self.entries) as *const AtomicU16;
+            (*tail_ptr).store(tail, Ordering::Release);
+        }
+    }
+
+    // Returns the ID of the buffer of the `i`th entry from the head of the
+    // ring. The entry must have been consumed by the kernel, and not yet
+    // accounted for with `advance`, so that it is not pushed again.
+    fn bid(&self, i: usize) -> u16 {
+        let index = self.head.get().wrapping_add(i as u16) & self.mask;
+        unsafe { (*self.entries.add(usize::from(index))).bid() }
+    }
+
+    // Accounts for `count` entries consumed by the kernel.
+    fn advance(&self, count: usize) {
+        self.head.set(self.head.get().wrapping_add(count as u16));
+    }
+
+    // Returns the number of entries pushed which the kernel has not
+    // consumed, as far as the completions have reported.
+    fn len(&self) -> usize {
+        usize::from(self.tail.get().wrapping_sub(self.head.get()))
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        // The runtime may be busy completing the operation which dropped
+        // the last reference, so the ring is unregistered directly. If the
+        // runtime is gone, so is the registration.
+        if self.ring_fd >= 0 && self.driver.upgrade().is_some() {
+            let arg = BufReg {
+                ring_addr: 0,
+                ring_entries: 0,
+                bgid: self.bgid,
+                pad: 0,
+                resv: [0; 3],
+            };
+            unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_register,
+                    self.ring_fd,
+                    IORING_UNREGISTER_PBUF_RING,
+                    &arg as *const BufReg,
+                    1,
+                );
+            }
+        }
+        unsafe { alloc::dealloc(self.entries as *mut u8, self.layout) };
+    }
+}
+
+impl BufRing {
+    /// Returns a builder of a ring registered as the buffer group `bgid`.
+    pub fn builder(bgid: u16) -> Builder {
+        Builder {
+            bgid,
+            entries: 64,
+            buf_len: 4096,
+        }
+    }
+
+    /// Returns the group ID of the ring.
+    pub fn bgid(&self) -> u16 {
+        self.inner.ring.bgid
+    }
+
+    /// Returns the size of each buffer.
+    pub fn buf_len(&self) -> usize {
+        self.inner.buf_len
+    }
+
+    /// Takes the buffer the kernel has picked for an operation, as reported
+    /// in the flags of its completion, with `len` bytes of data. Returns
+    /// `None` if the completion does not carry a buffer.
+    pub(crate) fn take(&self, flags: u32, len: usize) -> Option<ProvidedBuf> {
+        let bid = io_uring::cqueue::buffer_select(flags)?;
+        debug_assert!(len <= self.inner.buf_len);
+        self.inner.ring.advance(1);
+        self.inner.taken.set(self.inner.taken.get() + 1);
+        Some(ProvidedBuf {
+            ring: self.clone(),
+            bid,
+            len,
+        })
+    }
+
+    /// Takes the buffers the kernel has filled for a bundle receive, with
+    /// `len` bytes of data in total, starting with the buffer reported in
+    /// the flags of its completion.
+    ///
+    /// The kernel fills consecutive entries of the ring, each buffer in
+    /// full but the last, and only reports the ID of the first buffer: the
+    /// IDs of the others are read from the entries following it.
+    pub(crate) fn take_bundle(&self, flags: u32, len: usize) -> Vec<ProvidedBuf> {
+        let inner = &*self.inner;
+        // No data is reported along with no buffers consumed
+        let first = match io_uring::cqueue::buffer_select(flags) {
+            Some(bid) if len > 0 => bid,
+            _ => return Vec::new(),
+        };
+        let count = len.div_ceil(inner.buf_len);
+        let bufs: Vec<ProvidedBuf> = (0..count)
+            .map(|i| ProvidedBuf {
+                ring: self.clone(),
+                bid: inner.ring.bid(i),
+                len: (len - i * inner.buf_len).min(inner.buf_len),
+            })
+            .collect();
+        debug_assert_eq!(bufs[0].bid, first);
+        inner.ring.advance(count);
+        inner.taken.set(inner.taken.get() + count);
+        bufs
+    }
+
+    /// Waits until the ring has a buffer which is not held as a
+    /// [`ProvidedBuf`], for an operation which has failed with `ENOBUFS` to
+    /// be submitted again.
+    pub(crate) async fn replenished(&self) {
+        let inner = &*self.inner;
+        loop {
+            let mut returned = pin!(inner.returned.notified());
+            returned.as_mut().enable();
+            if inner.taken.get() <= usize::from(inner.ring.mask) {
+                return;
+            }
+            inner.starved.set(true);
+            returned.await;
+        }
+    }
+
+    /// Gives the buffer of a completion which is not going to be used back
+    /// to the kernel.
+    pub(crate) fn recycle(&self, flags: u32) {
+        if let Some(bid) = io_uring::cqueue::buffer_select(flags) {
+            self.inner.ring.advance(1);
+            self.inner.push(bid);
+        }
+    }
+}
+
+impl Inner {
+    // Gives a buffer back to the kernel.
+    fn push(&self, bid: u16) {
+        let addr = unsafe { self.bufs.ptr.add(usize::from(bid) * self.buf_len) };
+        self.ring.push(addr as u64, self.buf_len as u32, bid);
+    }
+}
+
+// The memory of the buffers of a ring.
+struct Bufs {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl Drop for Bufs {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, self.layout) };
+    }
+}
+
+// The kernel's `struct io_uring_buf_reg`.
+#[repr(C)]
+struct BufReg {
+    ring_addr: u64,
+    ring_entries: u32,
+    bgid: u16,
+    pad: u16,
+    resv: [u64; 3],
+}
+
+impl fmt::Debug for BufRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufRing")
+            .field("bgid", &self.inner.ring.bgid)
+            .field("entries", &(usize::from(self.inner.ring.mask) + 1))
+            .field("buf_len", &self.inner.buf_len)
+            .finish()
+    }
+}
+
+/// A buffer of a [`BufRing`], filled by an operation.
+///
+/// The buffer dereferences to the data the operation has put in it, and is
+/// given back to the kernel for reuse when dropped. Holding on to buffers
+/// leaves fewer for the operations drawing from the ring.
+pub struct ProvidedBuf {
+    ring: BufRing,
+    bid: u16,
+    len: usize,
+}
+
+impl ProvidedBuf {
+    /// Returns the ID of the buffer within its ring.
+    pub fn bid(&self) -> u16 {
+        self.bid
+    }
+}
+
+impl Deref for ProvidedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // Safety: the kernel has written `len` bytes to the buffer, and does
+        // not use it until it is pushed back to the ring
+        unsafe { slice::from_raw_parts(self.stable_ptr(), self.len) }
+    }
+}
+
+unsafe impl IoBuf for ProvidedBuf {
+    fn stable_ptr(&self) -> *const u8 {
+        let inner = &self.ring.inner;
+        unsafe { inner.bufs.ptr.add(usize::from(self.bid) * inner.buf_len) }
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len
+    }
+
+    fn bytes_total(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for ProvidedBuf {
+    fn drop(&mut self) {
+        let inner = &self.ring.inner;
+        inner.push(self.bid);
+        inner.taken.set(inner.taken.get() - 1);
+        if inner.starved.replace(false) {
+            inner.returned.notify_waiters();
+        }
+    }
+}
+
+impl fmt::Debug for ProvidedBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProvidedBuf")
+            .field("bid", &self.bid)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// A ring of buffers queued to be sent, registered with the kernel as a
+/// buffer group.
+///
+/// The data pushed to the ring is sent by [`TcpStream::send_bundle`] or
+/// [`UnixStream::send_bundle`], which transmit all of the buffers queued
+/// with a single operation, as a bundle. A queue of small responses is so
+/// sent without an operation for each, nor copying them together.
+///
+/// The group ID must not be used by another ring of the same runtime. The
+/// ring can be cloned, and the clones refer to the same queue. It is
+/// unregistered once all clones have been dropped.
+///
+/// [`TcpStream::send_bundle`]: crate::net::TcpStream::send_bundle
+/// [`UnixStream::send_bundle`]: crate::net::UnixStream::send_bundle
+#[derive(Clone)]
+pub struct SendRing {
+    inner: Rc<SendInner>,
+}
+
+struct SendInner {
+    ring: Ring,
+    // The data of the queued buffers, by buffer ID
+    bufs: RefCell<Vec<Option<Vec<u8>>>>,
+    // IDs of the buffers not queued
+    free: RefCell<Vec<u16>>,
+}
+
+impl SendRing {
+    /// Creates a ring of up to `entries` queued buffers, and registers it
+    /// with the runtime of the current thread as the buffer group `bgid`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the kernel does not support buffer rings, or if the group
+    /// ID is already in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entries` is not a power of two, or is above 32768, or if
+    /// called outside of a runtime context.
+    pub fn new(bgid: u16, entries: u16) -> io::Result<SendRing> {
+        assert!(
+            entries.is_power_of_two() && entries <= 32768,
+            "the number of buffers must be a power of two, up to 32768"
+        );
+        let handle = CONTEXT.with(|x| x.handle().expect("Not in a runtime context"));
+
+        let mut ring = Ring::new(&handle, bgid, entries)?;
+        // Safety: the ring is empty
+        unsafe { ring.register(&handle)? };
+        Ok(SendRing {
+            inner: Rc::new(SendInner {
+                ring,
+                bufs: RefCell::new(vec![None; usize::from(entries)]),
+                free: RefCell::new((0..entries).rev().collect()),
+            }),
+        })
+    }
+
+    /// Returns the group ID of the ring.
+    pub fn bgid(&self) -> u16 {
+        self.inner.ring.bgid
+    }
+
+    /// Queues `data` to be sent after the buffers already queued.
+    ///
+    /// Returns the data back if the ring is full. Empty data is not queued.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is longer than `u32::MAX` bytes.
+    pub fn push(&self, data: Vec<u8>) -> Result<(), Vec<u8>> {
+        assert!(
+            data.len() <= u32::MAX as usize,
+            "a buffer can be up to u32::MAX bytes"
+        );
+        if data.is_empty() {
+            return Ok(());
+        }
+        let inner = &*self.inner;
+        let bid = match inner.free.borrow_mut().pop() {
+            Some(bid) => bid,
+            None => return Err(data),
+        };
+        // The data stays put when moved into the ring
+        inner
+            .ring
+            .push(data.as_ptr() as u64, data.len() as u32, bid);
+        inner.bufs.borrow_mut()[usize::from(bid)] = Some(data);
+        Ok(())
+    }
+
+    /// Returns the number of buffers queued, including those of a send in
+    /// progress.
+    pub fn len(&self) -> usize {
+        self.inner.ring.len()
+    }
+
+    /// Returns `true` if no buffer is queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the buffers a bundle send has consumed to send `len` bytes,
+    /// from the head of the ring.
+    ///
+    /// Returns the rest of the data of the last buffer, if the send has
+    /// stopped within it: the kernel consumes that buffer all the same.
+    pub(crate) fn complete_send(&self, len: usize) -> Option<Vec<u8>> {
+        let inner = &*self.inner;
+        let mut bufs = inner.bufs.borrow_mut();
+        let mut free = inner.free.borrow_mut();
+        let mut rest = len;
+        let mut count = 0;
+        let mut unsent = None;
+        while rest > 0 {
+            let bid = inner.ring.bid(count);
+            count += 1;
+            let mut data = bufs[usize::from(bid)].take().expect("buffer not queued");
+            free.push(bid);
+            if data.len() > rest {
+                data.drain(..rest);
+                unsent = Some(data);
+                rest = 0;
+            } else {
+                rest -= data.len();
+            }
+        }
+        inner.ring.advance(count);
+        unsent
+    }
+}
+
+impl fmt::Debug for SendRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendRing")
+            .field("bgid", &self.inner.ring.bgid)
+            .field("entries", &(usize::from(self.inner.ring.mask) + 1))
+            .field("queued", &self.len())
+            .finish()
+    }
+}
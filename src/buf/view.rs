@@ -0,0 +1,121 @@
+//! Typed views of the contents of buffers, with the traits of the
+//! [`zerocopy`] crate.
+//!
+//! Storage engines and other code parsing binary formats read the data into
+//! a buffer, then interpret it as `#[repr(C)]` structures. The functions of
+//! this module check that the initialized bytes of a buffer have the size
+//! and the alignment of the type, and return a reference to them as the
+//! type, without copying.
+//!
+//! The types must implement the traits of `zerocopy` 0.8, typically
+//! derived. The alignment of a buffer depends on its allocation: `Vec<u8>`
+//! only guarantees an alignment of 1, although allocators usually align
+//! allocations to 16 bytes. Types with the [`Unaligned`] trait, such as
+//! those made of the byte order aware integers of `zerocopy`, can be viewed
+//! in any buffer.
+//!
+//! This module requires the `zerocopy` feature.
+//!
+//! [`zerocopy`]: https://docs.rs/zerocopy/0.8
+//! [`Unaligned`]: zerocopy::Unaligned
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tokio_uring::buf::view;
+//! use tokio_uring::fs::File;
+//! use zerocopy::{FromBytes, Immutable, KnownLayout, Unaligned};
+//! use zerocopy::little_endian::{U32, U64};
+//!
+//! #[derive(FromBytes, Immutable, KnownLayout, Unaligned)]
+//! #[repr(C)]
+//! struct Header {
+//!     magic: U32,
+//!     version: U32,
+//!     len: U64,
+//! }
+//!
+//! tokio_uring::start(async {
+//!     let file = File::open("data.db").await.unwrap();
+//!     let (res, buf) = file.read_at(Vec::with_capacity(4096), 0).await;
+//!     res.unwrap();
+//!
+//!     let (header, rest) = view::view_prefix::<Header>(&buf).unwrap();
+//!     println!("version {}, {} bytes", header.version, header.len);
+//! });
+//! ```
+
+use crate::buf::{BoundedBuf, BoundedBufMut};
+use std::io;
+use zerocopy::error::{CastError, ConvertError};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+/// Returns the initialized bytes of the buffer as a `T`.
+///
+/// A slice type, such as `[Record]`, can be viewed in a buffer holding a
+/// whole number of elements.
+///
+/// # Errors
+///
+/// Fails with an error of the kind [`InvalidData`] if the buffer is not
+/// aligned for `T`, or its size does not match the size of `T`.
+///
+/// [`InvalidData`]: std::io::ErrorKind::InvalidData
+pub fn view<T>(buf: &impl BoundedBuf) -> io::Result<&T>
+where
+    T: FromBytes + Immutable + KnownLayout + ?Sized,
+{
+    T::ref_from_bytes(bytes(buf)).map_err(cast_error)
+}
+
+/// Returns the first bytes of the buffer as a `T`, along with the rest of
+/// the initialized bytes.
+///
+/// This parses a header followed by other data. If `T` is a slice type, the
+/// view holds as many elements as fit in the buffer.
+///
+/// # Errors
+///
+/// Fails with an error of the kind [`InvalidData`] if the buffer is not
+/// aligned for `T`, or is too short for it.
+///
+/// [`InvalidData`]: std::io::ErrorKind::InvalidData
+pub fn view_prefix<T>(buf: &impl BoundedBuf) -> io::Result<(&T, &[u8])>
+where
+    T: FromBytes + Immutable + KnownLayout + ?Sized,
+{
+    T::ref_from_prefix(bytes(buf)).map_err(cast_error)
+}
+
+/// Returns the initialized bytes of the buffer as a mutable `T`, to build
+/// the data of a write in place.
+///
+/// # Errors
+///
+/// Fails like [`view`].
+pub fn view_mut<T>(buf: &mut impl BoundedBufMut) -> io::Result<&mut T>
+where
+    T: FromBytes + IntoBytes + KnownLayout + ?Sized,
+{
+    T::mut_from_bytes(bytes_mut(buf)).map_err(cast_error)
+}
+
+fn bytes(buf: &impl BoundedBuf) -> &[u8] {
+    // Safety: the buffer traits guarantee the initialized bytes are valid
+    unsafe { std::slice::from_raw_parts(buf.stable_ptr(), buf.bytes_init()) }
+}
+
+fn bytes_mut(buf: &mut impl BoundedBufMut) -> &mut [u8] {
+    let len = buf.bytes_init();
+    // Safety: the buffer traits guarantee the initialized bytes are valid
+    unsafe { std::slice::from_raw_parts_mut(buf.stable_mut_ptr(), len) }
+}
+
+fn cast_error<S, T: ?Sized + KnownLayout>(e: CastError<S, T>) -> io::Error {
+    let msg = match e {
+        ConvertError::Alignment(_) => "buffer is not aligned for the type",
+        ConvertError::Size(_) => "buffer size does not match the type",
+        ConvertError::Validity(never) => match never {},
+    };
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
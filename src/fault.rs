@@ -0,0 +1,213 @@
+//! Fault injection for testing error handling.
+//!
+//! A runtime started with [`Builder::fault_injection`] makes a fraction of
+//! the operations fail, or transfer fewer bytes than requested, according
+//! to a [`FaultPolicy`]. The operations are otherwise performed as usual,
+//! so that an application can be run against real files and sockets while
+//! its retry and error paths are exercised.
+//!
+//! A failed operation is not submitted to the kernel: it completes with
+//! the chosen error right away. A short read or write is submitted with a
+//! smaller length, so that the kernel transfers fewer bytes than the buffer
+//! holds, as it may do for stream sockets and pipes.
+//!
+//! This module requires the `test-util` feature.
+//!
+//! [`Builder::fault_injection`]: crate::Builder::fault_injection
+//!
+//! # Examples
+//!
+//! ```
+//! use tokio_uring::fault::FaultPolicy;
+//!
+//! let mut policy = FaultPolicy::new();
+//! policy.fail(0.1, &[libc::EIO, libc::EAGAIN]).short_io(0.2).seed(42);
+//!
+//! tokio_uring::builder()
+//!     .fault_injection(policy)
+//!     .start(async {
+//!         // Run the code under test, which must cope with the faults
+//!     });
+//! ```
+
+use crate::runtime::driver::SqeHeader;
+use io_uring::{opcode, squeue};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Which operations fail or are shortened, and how.
+///
+/// Each operation is considered independently, with the probabilities set
+/// by [`fail`] and [`short_io`]. The choices are made by a pseudo-random
+/// generator; with a [`seed`], a test submitting the same operations in the
+/// same order gets the same faults on every run.
+///
+/// [`fail`]: FaultPolicy::fail
+/// [`short_io`]: FaultPolicy::short_io
+/// [`seed`]: FaultPolicy::seed
+#[derive(Clone, Debug, Default)]
+pub struct FaultPolicy {
+    fail_rate: f64,
+    errnos: Vec<i32>,
+    short_rate: f64,
+    opcodes: Option<Vec<u8>>,
+    seed: Option<u64>,
+}
+
+impl FaultPolicy {
+    /// Creates a policy injecting no faults.
+    pub fn new() -> FaultPolicy {
+        FaultPolicy::default()
+    }
+
+    /// Fails operations with the given probability, with an error chosen
+    /// at random from `errnos`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` is not between 0 and 1, or if `errnos` is
+    /// empty.
+    pub fn fail(&mut self, probability: f64, errnos: &[i32]) -> &mut Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "probability must be between 0 and 1"
+        );
+        assert!(!errnos.is_empty(), "at least one error code is required");
+        self.fail_rate = probability;
+        self.errnos = errnos.to_vec();
+        self
+    }
+
+    /// Shortens reads and writes with the given probability, to a length
+    /// of at least 1 byte chosen at random below the length requested.
+    ///
+    /// This applies to reads, writes, sends and receives from a single
+    /// buffer, including those using fixed buffers, on files, pipes and
+    /// stream sockets. Operations on datagram sockets, where a short
+    /// transfer would truncate the datagram, on registered files, and
+    /// vectored operations are not shortened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` is not between 0 and 1.
+    pub fn short_io(&mut self, probability: f64) -> &mut Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "probability must be between 0 and 1"
+        );
+        self.short_rate = probability;
+        self
+    }
+
+    /// Injects faults only into operations with the given opcodes, e.g.
+    /// `io_uring::opcode::Read::CODE`.
+    ///
+    /// By default, faults are injected into all operations.
+    pub fn opcodes(&mut self, opcodes: &[u8]) -> &mut Self {
+        self.opcodes = Some(opcodes.to_vec());
+        self
+    }
+
+    /// Seeds the pseudo-random generator choosing the faults.
+    ///
+    /// By default, the generator is seeded from the clock, so that the
+    /// faults differ between runs.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Injects the faults of a policy into the operations of a runtime.
+pub(crate) struct Injector {
+    policy: FaultPolicy,
+    // State of the xorshift64* generator, never 0
+    state: u64,
+}
+
+impl Injector {
+    pub(crate) fn new(policy: &FaultPolicy) -> Injector {
+        let seed = policy.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        Injector {
+            policy: policy.clone(),
+            state: seed | 1,
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // Returns `true` with the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    /// Injects a fault into the operation of an entry. Returns the error
+    /// code the operation must fail with, if any, or shortens the entry for
+    /// a short read or write.
+    pub(crate) fn inject(&mut self, sqe: &mut squeue::Entry) -> Option<i32> {
+        let header = SqeHeader::read(sqe);
+        if let Some(opcodes) = &self.policy.opcodes {
+            if !opcodes.contains(&header.opcode) {
+                return None;
+            }
+        }
+
+        if self.chance(self.policy.fail_rate) {
+            let i = self.next() as usize % self.policy.errnos.len();
+            return Some(self.policy.errnos[i]);
+        }
+
+        let shortens = matches!(
+            header.opcode,
+            opcode::Read::CODE
+                | opcode::Write::CODE
+                | opcode::ReadFixed::CODE
+                | opcode::WriteFixed::CODE
+                | opcode::Send::CODE
+                | opcode::Recv::CODE
+        );
+        if shortens
+            && header.len > 1
+            && self.chance(self.policy.short_rate)
+            && is_byte_stream(&header)
+        {
+            let len = 1 + (self.next() % u64::from(header.len - 1)) as u32;
+            SqeHeader::set_len(sqe, len);
+        }
+        None
+    }
+}
+
+// Returns whether the operation is on a file or a stream socket, which may
+// transfer fewer bytes than requested. A datagram shortened would be
+// truncated instead. The type of a registered file cannot be looked up, so
+// operations on those are not shortened.
+fn is_byte_stream(header: &SqeHeader) -> bool {
+    if header.flags & squeue::Flags::FIXED_FILE.bits() != 0 {
+        return false;
+    }
+    let mut ty: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            header.fd,
+            libc::SOL_SOCKET,
+            libc::SO_TYPE,
+            &mut ty as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if res == 0 {
+        ty == libc::SOCK_STREAM
+    } else {
+        std::io::Error::last_os_error().raw_os_error() == Some(libc::ENOTSOCK)
+    }
+}
@@ -0,0 +1,320 @@
+use crate::fs::{File, SequentialReader, SequentialWriter};
+use std::io;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+
+/// How a file is going to be accessed, summarizing the settings tuned for
+/// it.
+///
+/// Set with [`OpenOptions::access_pattern`], which passes the pattern to the
+/// kernel as a hint for its page cache, and used by [`BufferedFile`] to
+/// pick the size of its buffers and how far it reads ahead.
+///
+/// [`OpenOptions::access_pattern`]: crate::fs::OpenOptions::access_pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccessPattern {
+    /// The file is read or written from start to end, such as a log or a
+    /// file being copied.
+    ///
+    /// The kernel reads ahead more of the file, and a [`BufferedFile`]
+    /// keeps 4 reads or writes of 64 KiB in flight.
+    #[default]
+    Sequential,
+
+    /// The file is accessed at offsets in no particular order, such as the
+    /// pages of a database.
+    ///
+    /// The kernel does not read ahead of the accessed pages, and a
+    /// [`BufferedFile`] reads only the data asked for, at the cursor, and
+    /// writes 8 KiB at a time.
+    Random,
+}
+
+impl AccessPattern {
+    // Returns the number of operations kept in flight, and the size of
+    // each.
+    fn defaults(self) -> (usize, usize) {
+        match self {
+            AccessPattern::Sequential => (4, 64 * 1024),
+            AccessPattern::Random => (1, 8 * 1024),
+        }
+    }
+
+    // Passes the pattern to the kernel as advice for the whole file.
+    pub(crate) fn advise(self, file: &File) -> io::Result<()> {
+        let advice = match self {
+            AccessPattern::Sequential => libc::POSIX_FADV_SEQUENTIAL,
+            AccessPattern::Random => libc::POSIX_FADV_RANDOM,
+        };
+        match unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, advice) } {
+            0 => Ok(()),
+            errno => Err(io::Error::from_raw_os_error(errno)),
+        }
+    }
+}
+
+/// A file read and written through buffers at a cursor, like a
+/// `tokio::fs::File` wrapped in buffered adapters.
+///
+/// Created with [`File::open_buffered`], or from an open file with
+/// [`BufferedFile::new`]. Reads are served from chunks read ahead of the
+/// cursor with a [`SequentialReader`], or with a read of just the data asked
+/// for if the [`AccessPattern`] is random. Writes are gathered into chunks
+/// written with a [`SequentialWriter`]. The depth and the size of the
+/// chunks are picked for the access pattern.
+///
+/// Data written is staged until a chunk is full; [`flush`] writes the rest,
+/// and waits for all writes to complete. A read after writes flushes them
+/// first, and a write after reads discards the data read ahead. Data which
+/// has not been flushed when the file is dropped is lost.
+///
+/// [`File::open_buffered`]: File::open_buffered
+/// [`flush`]: BufferedFile::flush
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::{AccessPattern, File, OpenOptions};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let mut out = File::open_buffered(
+///             "copy.txt",
+///             OpenOptions::new().write(true).create(true).truncate(true),
+///         )
+///         .await?;
+///         let mut src = File::open_buffered(
+///             "foo.txt",
+///             OpenOptions::new()
+///                 .read(true)
+///                 .access_pattern(AccessPattern::Sequential),
+///         )
+///         .await?;
+///
+///         let mut buf = [0; 1024];
+///         loop {
+///             let n = src.read(&mut buf).await?;
+///             if n == 0 {
+///                 break;
+///             }
+///             out.write_all(&buf[..n]).await?;
+///         }
+///         out.flush().await?;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct BufferedFile {
+    file: File,
+    access: AccessPattern,
+    depth: usize,
+    chunk_size: usize,
+
+    // Offset of the cursor
+    pos: u64,
+
+    // Reads ahead of the cursor, and the chunk it returned last, consumed
+    // up to `chunk_off`
+    reader: Option<SequentialReader>,
+    chunk: Vec<u8>,
+    chunk_off: usize,
+
+    // Data written up to the cursor, not yet passed to the writer
+    staged: Vec<u8>,
+    writer: Option<SequentialWriter>,
+}
+
+impl BufferedFile {
+    /// Wraps an open file, with the cursor at the start of the file and the
+    /// buffers picked for the access pattern.
+    pub fn new(file: File, access: AccessPattern) -> BufferedFile {
+        let (depth, chunk_size) = access.defaults();
+        BufferedFile {
+            file,
+            access,
+            depth,
+            chunk_size,
+            pos: 0,
+            reader: None,
+            chunk: Vec::new(),
+            chunk_off: 0,
+            staged: Vec::new(),
+            writer: None,
+        }
+    }
+
+    /// Sets the maximum number of reads or writes kept in flight, in place
+    /// of the default for the access pattern.
+    ///
+    /// Values less than 1 are treated as 1.
+    pub fn depth(&mut self, depth: usize) -> &mut Self {
+        self.depth = depth.max(1);
+        if let Some(reader) = &mut self.reader {
+            reader.depth(self.depth);
+        }
+        if let Some(writer) = &mut self.writer {
+            writer.depth(self.depth);
+        }
+        self
+    }
+
+    /// Sets the size of the chunks read and written, in place of the
+    /// default for the access pattern. With a random access pattern, only
+    /// the writes are made in chunks.
+    ///
+    /// Values less than 1 are treated as 1.
+    pub fn chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+        self.chunk_size = chunk_size.max(1);
+        if let Some(reader) = &mut self.reader {
+            reader.chunk_size(self.chunk_size);
+        }
+        self
+    }
+
+    /// Returns the access pattern the buffers are picked for.
+    pub fn access_pattern(&self) -> AccessPattern {
+        self.access
+    }
+
+    /// Returns the offset of the cursor in the file.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the underlying file.
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+
+    /// Reads some data at the cursor into `buf`, returning the number of
+    /// bytes read, or 0 at the end of the file.
+    pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.flush().await?;
+
+        if self.access == AccessPattern::Random {
+            let (res, chunk) = self
+                .file
+                .read_at(Vec::with_capacity(buf.len()), self.pos)
+                .await;
+            let n = res?;
+            buf[..n].copy_from_slice(&chunk[..n]);
+            self.pos += n as u64;
+            return Ok(n);
+        }
+
+        if self.chunk_off == self.chunk.len() {
+            let reader = match &mut self.reader {
+                Some(reader) => reader,
+                None => {
+                    let mut reader = SequentialReader::new(&self.file, self.pos);
+                    reader.depth(self.depth).chunk_size(self.chunk_size);
+                    self.reader.insert(reader)
+                }
+            };
+            match reader.read().await? {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.chunk_off = 0;
+                }
+                None => {
+                    // Read again the next time, in case the file has grown
+                    self.reader = None;
+                    return Ok(0);
+                }
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.chunk_off);
+        buf[..n].copy_from_slice(&self.chunk[self.chunk_off..self.chunk_off + n]);
+        self.chunk_off += n;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    /// Reads from the cursor to the end of the file, appending the data to
+    /// `buf` and returning the number of bytes read.
+    pub async fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let start = buf.len();
+        let mut chunk = vec![0; self.chunk_size];
+        loop {
+            match self.read(&mut chunk).await? {
+                0 => return Ok(buf.len() - start),
+                n => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+
+    /// Writes all of `data` at the cursor.
+    ///
+    /// The data is staged, and written once a chunk is full or on
+    /// [`flush`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of a previously submitted write.
+    ///
+    /// [`flush`]: BufferedFile::flush
+    pub async fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.reader.take().is_some() {
+            self.chunk.clear();
+            self.chunk_off = 0;
+        }
+
+        self.staged.extend_from_slice(data);
+        self.pos += data.len() as u64;
+        while self.staged.len() >= self.chunk_size {
+            let rest = self.staged.split_off(self.chunk_size);
+            let chunk = mem::replace(&mut self.staged, rest);
+            self.submit(chunk).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the staged data, and waits for all writes to complete.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        if !self.staged.is_empty() {
+            let chunk = mem::take(&mut self.staged);
+            self.submit(chunk).await?;
+        }
+        if let Some(writer) = &mut self.writer {
+            writer.flush().await?;
+            self.writer = None;
+        }
+        Ok(())
+    }
+
+    /// Moves the cursor to the offset `pos`, flushing the data written
+    /// and discarding the data read ahead.
+    pub async fn seek(&mut self, pos: u64) -> io::Result<()> {
+        self.flush().await?;
+        self.reader = None;
+        self.chunk.clear();
+        self.chunk_off = 0;
+        self.pos = pos;
+        Ok(())
+    }
+
+    /// Flushes the data written, and returns the underlying file.
+    pub async fn into_inner(mut self) -> io::Result<File> {
+        self.flush().await?;
+        Ok(self.file)
+    }
+
+    // Passes a chunk of the data written before the staged data to the
+    // writer.
+    async fn submit(&mut self, chunk: Vec<u8>) -> io::Result<()> {
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => {
+                let start = self.pos - (self.staged.len() + chunk.len()) as u64;
+                let mut writer = SequentialWriter::new(&self.file, start);
+                writer.depth(self.depth);
+                self.writer.insert(writer)
+            }
+        };
+        writer.write(chunk).await
+    }
+}
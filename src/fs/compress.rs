@@ -0,0 +1,389 @@
+use crate::fs::{SequentialReader, SequentialWriter};
+use std::io::{self, Write};
+use std::mem;
+
+// Compressed data is passed to the writer in buffers of at least this size.
+const WRITE_CHUNK: usize = 64 * 1024;
+
+/// A compression format of [`DecompressReader`] and [`CompressWriter`].
+///
+/// The formats are available with the features of the crates implementing
+/// them: `flate2` for gzip and zlib, and `zstd` for Zstandard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// The gzip format. Concatenated gzip members, as written by appending
+    /// to a compressed log, are decompressed as a single stream.
+    #[cfg(feature = "flate2")]
+    Gzip,
+
+    /// The zlib format.
+    #[cfg(feature = "flate2")]
+    Zlib,
+
+    /// The Zstandard format.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+/// Decompresses the chunks of a [`SequentialReader`] as they are read.
+///
+/// Each chunk completed by the reader is fed to the decoder as it is, and
+/// the data decoded from it is returned by [`read`]. The reads of the
+/// following chunks stay in flight while a chunk is decompressed.
+///
+/// Requires the `flate2` or the `zstd` feature.
+///
+/// [`read`]: DecompressReader::read
+///
+/// # Examples
+///
+/// Counting the lines of a compressed log:
+///
+/// ```no_run
+/// use tokio_uring::fs::{Compression, DecompressReader, File, SequentialReader};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let file = File::open("app.log.gz").await?;
+///         let mut reader = SequentialReader::new(&file, 0);
+///         reader.depth(8);
+///
+///         let mut reader = DecompressReader::new(reader, Compression::Gzip)?;
+///         let mut lines = 0;
+///         while let Some(data) = reader.read().await? {
+///             lines += data.iter().filter(|&&b| b == b'\n').count();
+///         }
+///         println!("{} lines", lines);
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct DecompressReader {
+    reader: SequentialReader,
+    decoder: Decoder,
+    finished: bool,
+}
+
+enum Decoder {
+    #[cfg(feature = "flate2")]
+    Gzip(flate2::write::MultiGzDecoder<Vec<u8>>),
+    #[cfg(feature = "flate2")]
+    Zlib(ZlibDecoder),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::zio::Writer<Vec<u8>, zstd::stream::raw::Decoder<'static>>),
+}
+
+impl DecompressReader {
+    /// Creates a reader decompressing the data read by `reader`, in the
+    /// given format.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the decoder cannot be created.
+    pub fn new(reader: SequentialReader, format: Compression) -> io::Result<DecompressReader> {
+        let decoder = match format {
+            #[cfg(feature = "flate2")]
+            Compression::Gzip => Decoder::Gzip(flate2::write::MultiGzDecoder::new(Vec::new())),
+            #[cfg(feature = "flate2")]
+            Compression::Zlib => Decoder::Zlib(ZlibDecoder {
+                inner: flate2::Decompress::new(true),
+                out: Vec::new(),
+                ended: false,
+            }),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Decoder::Zstd(zstd::stream::zio::Writer::new(
+                Vec::new(),
+                zstd::stream::raw::Decoder::new()?,
+            )),
+        };
+        Ok(DecompressReader {
+            reader,
+            decoder,
+            finished: false,
+        })
+    }
+
+    /// Returns the next piece of decompressed data, or `None` at the end
+    /// of the file.
+    ///
+    /// The data is what was decoded from one or more chunks of the file,
+    /// and is never empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the read of a chunk, or of the decoder if the
+    /// data is not in the format. A zlib or Zstandard stream cut short, as
+    /// in a truncated file, fails at the end of the file with an error of
+    /// the [`UnexpectedEof`] kind, while a gzip member cut short fails the
+    /// check of its trailer, with an error of the [`InvalidInput`] kind.
+    ///
+    /// [`UnexpectedEof`]: io::ErrorKind::UnexpectedEof
+    /// [`InvalidInput`]: io::ErrorKind::InvalidInput
+    pub async fn read(&mut self) -> io::Result<Option<Vec<u8>>> {
+        while !self.finished {
+            match self.reader.read().await? {
+                Some(chunk) => self.decoder.write(&chunk)?,
+                None => {
+                    self.decoder.finish()?;
+                    self.finished = true;
+                }
+            }
+            let data = self.decoder.take();
+            if !data.is_empty() {
+                return Ok(Some(data));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> SequentialReader {
+        self.reader
+    }
+}
+
+impl Decoder {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "flate2")]
+            Decoder::Gzip(d) => d.write_all(data),
+            #[cfg(feature = "flate2")]
+            Decoder::Zlib(d) => d.write_all(data),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(d) => d.write_all(data),
+        }
+    }
+
+    // Ends the stream at the end of the data, failing if it is incomplete.
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "flate2")]
+            Decoder::Gzip(d) => d.try_finish(),
+            #[cfg(feature = "flate2")]
+            Decoder::Zlib(d) => d.finish(),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(d) => d.finish(),
+        }
+    }
+
+    // Takes the data decoded so far.
+    fn take(&mut self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "flate2")]
+            Decoder::Gzip(d) => mem::take(d.get_mut()),
+            #[cfg(feature = "flate2")]
+            Decoder::Zlib(d) => mem::take(&mut d.out),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(d) => mem::take(d.writer_mut()),
+        }
+    }
+}
+
+// The zlib writer of flate2 finishes a stream cut short without an error,
+// the end of the stream is tracked here instead.
+#[cfg(feature = "flate2")]
+struct ZlibDecoder {
+    inner: flate2::Decompress,
+    out: Vec<u8>,
+    ended: bool,
+}
+
+#[cfg(feature = "flate2")]
+impl ZlibDecoder {
+    fn write_all(&mut self, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() && !self.ended {
+            self.out.reserve(WRITE_CHUNK);
+            let before = self.inner.total_in();
+            let status =
+                self.inner
+                    .decompress_vec(data, &mut self.out, flate2::FlushDecompress::None)?;
+            data = &data[(self.inner.total_in() - before) as usize..];
+            self.ended = status == flate2::Status::StreamEnd;
+        }
+        if !data.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "data after the end of the zlib stream",
+            ));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.ended {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "zlib stream cut short",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Compresses data written to a [`SequentialWriter`].
+///
+/// The data passed to [`write`] is encoded into a buffer, which is handed
+/// over to the writer once it holds 64 KiB of compressed data, so that the
+/// writes of the compressed file are large and kept in flight while more
+/// data is compressed. [`finish`] ends the compressed stream and flushes
+/// the writer.
+///
+/// Requires the `flate2` or the `zstd` feature.
+///
+/// [`write`]: CompressWriter::write
+/// [`finish`]: CompressWriter::finish
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::{Compression, CompressWriter, File, SequentialWriter};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let file = File::create("events.zst").await?;
+///         let writer = SequentialWriter::new(&file, 0);
+///
+///         let mut writer = CompressWriter::new(writer, Compression::Zstd, 3)?;
+///         for i in 0..1000 {
+///             writer.write(format!("event {}\n", i).as_bytes()).await?;
+///         }
+///         writer.finish().await?;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct CompressWriter {
+    writer: SequentialWriter,
+    encoder: Encoder,
+}
+
+enum Encoder {
+    #[cfg(feature = "flate2")]
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    #[cfg(feature = "flate2")]
+    Zlib(flate2::write::ZlibEncoder<Vec<u8>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl CompressWriter {
+    /// Creates a writer compressing the data written to `writer`, in the
+    /// given format and at the given level.
+    ///
+    /// The level ranges from 0 to 9 for gzip and zlib, and from 1 to 22 for
+    /// Zstandard, with 0 selecting its default level; a higher level
+    /// compresses better, and more slowly.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the encoder cannot be created, e.g. for an invalid level.
+    pub fn new(
+        writer: SequentialWriter,
+        format: Compression,
+        level: u32,
+    ) -> io::Result<CompressWriter> {
+        #[cfg(feature = "flate2")]
+        let flate_level = || {
+            if level > 9 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid compression level",
+                ));
+            }
+            Ok(flate2::Compression::new(level))
+        };
+        let encoder = match format {
+            #[cfg(feature = "flate2")]
+            Compression::Gzip => {
+                Encoder::Gzip(flate2::write::GzEncoder::new(Vec::new(), flate_level()?))
+            }
+            #[cfg(feature = "flate2")]
+            Compression::Zlib => {
+                Encoder::Zlib(flate2::write::ZlibEncoder::new(Vec::new(), flate_level()?))
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                Encoder::Zstd(zstd::stream::write::Encoder::new(Vec::new(), level as i32)?)
+            }
+        };
+        Ok(CompressWriter { writer, encoder })
+    }
+
+    /// Compresses `data`, submitting a write of the compressed data once
+    /// enough of it has been buffered.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the encoder, or of a previously submitted
+    /// write.
+    pub async fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.encoder.write(data)?;
+        if self.encoder.buffered() >= WRITE_CHUNK {
+            self.writer.write(self.encoder.take()).await?;
+        }
+        Ok(())
+    }
+
+    /// Ends the compressed stream, writes the rest of the compressed data,
+    /// and waits for all writes to complete with
+    /// [`SequentialWriter::flush`].
+    ///
+    /// Returns the underlying writer, which has written the whole stream.
+    pub async fn finish(mut self) -> io::Result<SequentialWriter> {
+        self.encoder.finish()?;
+        let rest = self.encoder.take();
+        if !rest.is_empty() {
+            self.writer.write(rest).await?;
+        }
+        self.writer.flush().await?;
+        Ok(self.writer)
+    }
+}
+
+impl Encoder {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "flate2")]
+            Encoder::Gzip(e) => e.write_all(data),
+            #[cfg(feature = "flate2")]
+            Encoder::Zlib(e) => e.write_all(data),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.write_all(data),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "flate2")]
+            Encoder::Gzip(e) => e.try_finish(),
+            #[cfg(feature = "flate2")]
+            Encoder::Zlib(e) => e.try_finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.do_finish(),
+        }
+    }
+
+    fn buffered(&mut self) -> usize {
+        match self {
+            #[cfg(feature = "flate2")]
+            Encoder::Gzip(e) => e.get_ref().len(),
+            #[cfg(feature = "flate2")]
+            Encoder::Zlib(e) => e.get_ref().len(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.get_ref().len(),
+        }
+    }
+
+    // Takes the data encoded so far.
+    fn take(&mut self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "flate2")]
+            Encoder::Gzip(e) => mem::take(e.get_mut()),
+            #[cfg(feature = "flate2")]
+            Encoder::Zlib(e) => mem::take(e.get_mut()),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => mem::take(e.get_mut()),
+        }
+    }
+}
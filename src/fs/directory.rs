@@ -1,7 +1,126 @@
+use crate::fs::OpenOptions;
+use crate::io::SharedFd;
 use crate::runtime::driver::op::Op;
+use std::fmt;
 use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::path::Path;
 
+/// An open directory on the filesystem.
+///
+/// A directory needs to be synced to make changes to its entries, such as
+/// files created or renamed in it, durable. This is commonly forgotten after
+/// the file itself has been synced with [`File::sync_all`].
+///
+/// Like files, directories are closed asynchronously when dropped. Call
+/// [`close`] to wait for the directory to be closed.
+///
+/// [`File::sync_all`]: crate::fs::File::sync_all
+/// [`close`]: Dir::close
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::{self, Dir};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         fs::rename("data/state.tmp", "data/state").await?;
+///
+///         // Persist the rename
+///         let dir = Dir::open("data").await?;
+///         dir.sync_all().await?;
+///         dir.close().await?;
+///
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct Dir {
+    fd: SharedFd,
+}
+
+impl Dir {
+    /// Opens a directory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `path` does not exist or is not
+    /// a directory.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Dir> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_DIRECTORY)
+            .open(path)
+            .await?;
+        Ok(Dir {
+            fd: file.into_shared_fd(),
+        })
+    }
+
+    pub(crate) fn into_shared_fd(self) -> SharedFd {
+        self.fd
+    }
+
+    /// Attempts to sync the directory entries and metadata to disk.
+    pub async fn sync_all(&self) -> io::Result<()> {
+        Op::fsync(&self.fd)?.await
+    }
+
+    /// Closes the directory.
+    ///
+    /// The method completes once the close operation has completed,
+    /// guaranteeing that resources associated with the directory have been
+    /// released.
+    pub async fn close(self) -> io::Result<()> {
+        self.fd.close().await;
+        Ok(())
+    }
+}
+
+impl AsRawFd for Dir {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.raw_fd()
+    }
+}
+
+impl fmt::Debug for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dir")
+            .field("fd", &self.fd.raw_fd())
+            .finish()
+    }
+}
+
+/// Syncs a directory to disk, making changes to its entries durable.
+///
+/// This is a shorthand for opening the directory with [`Dir::open`], calling
+/// [`Dir::sync_all`] and closing it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let file = fs::File::create("data/new_file").await?;
+///         file.sync_all().await?;
+///         file.close().await?;
+///
+///         // Make sure the new directory entry is persisted as well
+///         fs::sync_dir("data").await?;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn sync_dir(path: impl AsRef<Path>) -> io::Result<()> {
+    let dir = Dir::open(path).await?;
+    dir.sync_all().await?;
+    dir.close().await
+}
+
 /// Removes an empty directory.
 ///
 /// # Examples
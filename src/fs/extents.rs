@@ -0,0 +1,144 @@
+use crate::io::SharedFd;
+use std::convert::TryFrom;
+use std::future::{poll_fn, Future};
+use std::io;
+use std::ops::Range;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::task::JoinHandle;
+
+/// Returns the offset of the next data or hole at or after `offset`, or
+/// `None` if `offset` is at or past the end of the file.
+///
+/// There is no `lseek` operation in io_uring, and the lookup may have to
+/// read the extent tree of the file from the device, so it is made on the
+/// blocking thread pool.
+pub(crate) async fn seek(
+    fd: &SharedFd,
+    offset: u64,
+    whence: libc::c_int,
+) -> io::Result<Option<u64>> {
+    let fd = duplicate(fd)?;
+    blocking(move || restoring_offset(fd.as_raw_fd(), |fd| lseek(fd, offset, whence))).await
+}
+
+// Duplicates the descriptor for a lookup on the blocking thread pool, so
+// that it remains open if the file is closed before the lookup completes.
+fn duplicate(fd: &SharedFd) -> io::Result<OwnedFd> {
+    let fd = syscall!(fcntl(fd.raw_fd(), libc::F_DUPFD_CLOEXEC, 0))?;
+    // Safety: the descriptor has just been created
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> io::Result<T> {
+    crate::spawn_blocking(f).await.map_err(io::Error::other)?
+}
+
+// Calls `f`, then moves the offset of the file back to where it was: the
+// lookups move the offset, which is shared by the duplicates of the
+// descriptor.
+fn restoring_offset<T>(fd: RawFd, f: impl FnOnce(RawFd) -> io::Result<T>) -> io::Result<T> {
+    let pos = syscall!(lseek64(fd, 0, libc::SEEK_CUR))?;
+    let res = f(fd);
+    syscall!(lseek64(fd, pos, libc::SEEK_SET))?;
+    res
+}
+
+fn lseek(fd: RawFd, offset: u64, whence: libc::c_int) -> io::Result<Option<u64>> {
+    let offset = i64::try_from(offset)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "offset too large for file"))?;
+    match syscall!(lseek64(fd, offset, whence)) {
+        Ok(pos) => Ok(Some(pos as u64)),
+        Err(e) if e.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// Returns the first extent of data at or after `pos`.
+fn next_extent(fd: RawFd, pos: u64) -> io::Result<Option<Range<u64>>> {
+    let start = match lseek(fd, pos, libc::SEEK_DATA)? {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    // The file may have been truncated in between
+    Ok(lseek(fd, start, libc::SEEK_HOLE)?.map(|end| start..end))
+}
+
+/// The data extents of a sparse file.
+///
+/// Returned by [`File::extents`]. Each item is the byte range of a run of
+/// data, with the holes between the runs skipped. The ranges are found
+/// with the `SEEK_DATA` and `SEEK_HOLE` modes of `lseek`, so a file system
+/// which does not track holes reports the whole file as one extent.
+///
+/// The lookups are made on the blocking thread pool, and leave the offset
+/// of the file where it was. With the `stream` feature, `Extents` also
+/// implements `futures_core::Stream`.
+///
+/// [`File::extents`]: crate::fs::File::extents
+#[derive(Debug)]
+pub struct Extents {
+    fd: Arc<OwnedFd>,
+    pos: Option<u64>,
+    lookup: Option<JoinHandle<io::Result<Option<Range<u64>>>>>,
+}
+
+impl Extents {
+    pub(crate) fn new(fd: &SharedFd) -> io::Result<Extents> {
+        Ok(Extents {
+            fd: Arc::new(duplicate(fd)?),
+            pos: Some(0),
+            lookup: None,
+        })
+    }
+
+    /// Returns the next extent, or `None` past the last one.
+    ///
+    /// If the future is dropped before it resolves, the lookup in progress
+    /// is resumed by the next call.
+    pub async fn next(&mut self) -> Option<io::Result<Range<u64>>> {
+        poll_fn(|cx| self.poll_next_extent(cx)).await
+    }
+
+    fn poll_next_extent(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Range<u64>>>> {
+        let lookup = match &mut self.lookup {
+            Some(lookup) => lookup,
+            None => {
+                let pos = match self.pos.take() {
+                    Some(pos) => pos,
+                    None => return Poll::Ready(None),
+                };
+                let fd = self.fd.clone();
+                self.lookup.insert(crate::spawn_blocking(move || {
+                    restoring_offset(fd.as_raw_fd(), |fd| next_extent(fd, pos))
+                }))
+            }
+        };
+        let res = ready!(Pin::new(lookup).poll(cx));
+        self.lookup = None;
+        match res.map_err(io::Error::other).and_then(|res| res) {
+            Ok(Some(extent)) => {
+                self.pos = Some(extent.end);
+                Poll::Ready(Some(Ok(extent)))
+            }
+            Ok(None) => Poll::Ready(None),
+            Err(e) => Poll::Ready(Some(Err(e))),
+        }
+    }
+}
+
+/// Yields the extents of [`Extents::next`].
+///
+/// Requires the `stream` feature.
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Extents {
+    type Item = io::Result<Range<u64>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next_extent(cx)
+    }
+}
@@ -1,11 +1,15 @@
-use crate::buf::fixed::FixedBuf;
+use crate::buf::fixed::{FixedBuf, FixedBufPool};
 use crate::buf::{BoundedBuf, BoundedBufMut, IoBuf, IoBufMut, Slice};
-use crate::fs::OpenOptions;
-use crate::io::SharedFd;
+use crate::fs::range_lock::RangeLocks;
+use crate::fs::{BufferedFile, Extents, Metadata, Mmap, OpenOptions, RangeGuard};
+use crate::io::{Read, SharedFd};
 
 use crate::runtime::driver::op::Op;
+use io_uring::types;
+use std::collections::VecDeque;
 use std::fmt;
 use std::io;
+use std::ops::Range;
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::path::Path;
 
@@ -56,6 +60,9 @@ use std::path::Path;
 pub struct File {
     /// Open file descriptor
     fd: SharedFd,
+
+    /// Ranges locked with `lock_range`
+    locks: RangeLocks,
 }
 
 impl File {
@@ -118,8 +125,97 @@ impl File {
             .await
     }
 
+    /// Opens a file with the options specified by `opts`, to be read and
+    /// written through buffers at a cursor.
+    ///
+    /// The buffers are picked for the [`access_pattern`] set in the options,
+    /// or for sequential access if it is not set: chunks are read ahead of
+    /// the cursor for a file accessed sequentially, and nothing for one
+    /// accessed at random. See [`BufferedFile`] for the details.
+    ///
+    /// [`access_pattern`]: OpenOptions::access_pattern
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::{File, OpenOptions};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let mut f = File::open_buffered("foo.txt", OpenOptions::new().read(true)).await?;
+    ///
+    ///         let mut contents = Vec::new();
+    ///         f.read_to_end(&mut contents).await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn open_buffered(
+        path: impl AsRef<Path>,
+        opts: &OpenOptions,
+    ) -> io::Result<BufferedFile> {
+        let file = opts.open(path).await?;
+        Ok(BufferedFile::new(file, opts.access.unwrap_or_default()))
+    }
+
+    /// Opens a file in read-only mode and queries its metadata.
+    ///
+    /// The open and the metadata query are submitted to the kernel together
+    /// as linked operations, which saves a round trip compared to calling
+    /// [`open`] followed by [`metadata`]. This is useful, for example, in
+    /// a static file server which needs the size of the file to send it.
+    ///
+    /// The metadata is queried by the path after the file has been opened.
+    /// If the path is concurrently replaced by another file, the metadata may
+    /// not describe the opened file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `path` does not already exist.
+    /// Other errors may also be returned according to [`OpenOptions::open`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let (f, metadata) = File::open_with_metadata("foo.txt").await?;
+    ///         let (res, buf) = f.read_exact_at(vec![0; metadata.len() as usize], 0).await;
+    ///         res?;
+    ///
+    ///         // Close the file
+    ///         f.close().await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// [`open`]: File::open
+    /// [`metadata`]: File::metadata
+    pub async fn open_with_metadata(path: impl AsRef<Path>) -> io::Result<(File, Metadata)> {
+        let (open, statx) = Op::open_with_statx(path.as_ref(), OpenOptions::new().read(true))?;
+        // Await both operations before checking the results, so that neither
+        // is left in flight.
+        let file = open.await;
+        let statx = statx.await;
+        Ok((file?, Metadata::from_statx(statx?)))
+    }
+
     pub(crate) fn from_shared_fd(fd: SharedFd) -> File {
-        File { fd }
+        File {
+            fd,
+            locks: RangeLocks::default(),
+        }
+    }
+
+    pub(crate) fn into_shared_fd(self) -> SharedFd {
+        self.fd
+    }
+
+    pub(crate) fn shared_fd(&self) -> &SharedFd {
+        &self.fd
     }
 
     /// Converts a [`std::fs::File`][std] to a [`tokio_uring::fs::File`][file].
@@ -177,10 +273,103 @@ impl File {
     /// ```
     pub async fn read_at<T: BoundedBufMut>(&self, buf: T, pos: u64) -> crate::BufResult<usize, T> {
         // Submit the read operation
-        let op = Op::read_at(&self.fd, buf, pos).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::read_at(&fd, buf, pos).unwrap();
         op.await
     }
 
+    /// Like [`read_at`], with flags changing how the kernel performs the
+    /// read, e.g. to bypass the page cache with [`RwFlags::DONTCACHE`].
+    ///
+    /// [`read_at`]: File::read_at
+    ///
+    /// # Errors
+    ///
+    /// Fails with `EOPNOTSUPP` if the kernel or the file system does not
+    /// support one of the flags.
+    pub async fn read_at_with<T: BoundedBufMut>(
+        &self,
+        buf: T,
+        pos: u64,
+        flags: RwFlags,
+    ) -> crate::BufResult<usize, T> {
+        let fd = self.fd.acquire().await;
+        let op = Op::read_at_with(&fd, buf, pos, flags.0).unwrap();
+        op.await
+    }
+
+    /// Read multiple byte ranges of the file concurrently.
+    ///
+    /// Each range is given as an offset and a length. The reads for all
+    /// ranges are submitted to the kernel together, before waiting for any of
+    /// them, which is much faster than reading the ranges one after another,
+    /// e.g. for a batch of lookups in a database file.
+    /// With a limit set by [`set_max_in_flight`], no more reads are
+    /// submitted at a time than the limit allows.
+    ///
+    /// # Return
+    ///
+    /// The method returns the result of each read, in the order of the
+    /// ranges. As with [`read_at`], the data read for a range may be shorter
+    /// than requested, e.g. if the range extends past the end of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let f = File::open("foo.txt").await?;
+    ///
+    ///         let results = f.read_ranges(vec![(0, 16), (4096, 16), (65536, 16)]).await;
+    ///         for res in results {
+    ///             println!("The bytes: {:?}", res?);
+    ///         }
+    ///
+    ///         // Close the file
+    ///         f.close().await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// [`read_at`]: File::read_at
+    /// [`set_max_in_flight`]: File::set_max_in_flight
+    pub async fn read_ranges(&self, ranges: Vec<(u64, usize)>) -> Vec<io::Result<Vec<u8>>> {
+        async fn finish(op: io::Result<Op<Read<Vec<u8>>>>) -> io::Result<Vec<u8>> {
+            let (res, buf) = op?.await;
+            res.map(|_| buf)
+        }
+
+        // The reads created before waiting for any of them are submitted
+        // at once, rather than as the flush policy would
+        crate::runtime::submit_together(async move {
+            let mut results = Vec::with_capacity(ranges.len());
+            // Submit all reads before waiting for any of them, unless the
+            // limit on operations in flight is reached, in which case the
+            // reads submitted first are waited for to make room
+            let mut ops = VecDeque::with_capacity(ranges.len());
+            for (pos, len) in ranges {
+                let fd = loop {
+                    if let Some(fd) = self.fd.try_acquire() {
+                        break fd;
+                    }
+                    match ops.pop_front() {
+                        Some(op) => results.push(finish(op).await),
+                        None => break self.fd.acquire().await,
+                    }
+                };
+                ops.push_back(Op::read_at(&fd, Vec::with_capacity(len), pos));
+            }
+            for op in ops {
+                results.push(finish(op).await);
+            }
+            results
+        })
+        .await
+    }
+
     /// Read some bytes at the specified offset from the file into the specified
     /// array of buffers, returning how many bytes were read.
     ///
@@ -232,10 +421,102 @@ impl File {
         pos: u64,
     ) -> crate::BufResult<usize, Vec<T>> {
         // Submit the read operation
-        let op = Op::readv_at(&self.fd, bufs, pos).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::readv_at(&fd, bufs, pos).unwrap();
         op.await
     }
 
+    /// Read the exact number of bytes required to fill the buffers at the
+    /// specified offset from the file.
+    ///
+    /// Like [`readv_at`], the data is read into the uninitialized capacity of
+    /// each buffer in turn. Short reads are continued from where they end,
+    /// which can be in the middle of a buffer, until all the buffers are
+    /// full.
+    ///
+    /// # Return
+    ///
+    /// The method returns the operation result and the same array of buffers
+    /// passed as an argument.
+    ///
+    /// # Errors
+    ///
+    /// If this function encounters an "end of file" before completely filling
+    /// the buffers, it returns an error of the kind
+    /// [`ErrorKind::UnexpectedEof`]. The buffers are returned on error, with
+    /// the data read so far marked as initialized.
+    ///
+    /// If this function encounters any form of I/O or other error, an error
+    /// variant will be returned. The buffers are returned on error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let f = File::open("foo.txt").await?;
+    ///         let header = Vec::<u8>::with_capacity(16);
+    ///         let body = Vec::<u8>::with_capacity(4096);
+    ///
+    ///         // Read exactly 4112 bytes
+    ///         let (res, buffers) = f.readv_exact_at(vec![header, body], 0).await;
+    ///         res?;
+    ///
+    ///         println!("The header: {:?}", buffers[0]);
+    ///
+    ///         // Close the file
+    ///         f.close().await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// [`readv_at`]: File::readv_at
+    /// [`ErrorKind::UnexpectedEof`]: std::io::ErrorKind::UnexpectedEof
+    pub async fn readv_exact_at<T: IoBufMut>(
+        &self,
+        mut bufs: Vec<T>,
+        mut pos: u64,
+    ) -> crate::BufResult<(), Vec<T>> {
+        let mut remaining: usize = bufs.iter().map(|b| b.bytes_total() - b.bytes_init()).sum();
+        if pos.checked_add(remaining as u64).is_none() {
+            return (
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "buffer too large for file",
+                )),
+                bufs,
+            );
+        }
+
+        // Each read advances the initialized length of the buffers it fills,
+        // so the next one continues where it ended.
+        while remaining != 0 {
+            let (res, returned) = self.readv_at(bufs, pos).await;
+            bufs = returned;
+            match res {
+                Ok(0) => {
+                    return (
+                        Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "failed to fill whole buffer",
+                        )),
+                        bufs,
+                    )
+                }
+                Ok(n) => {
+                    pos += n as u64;
+                    remaining -= n;
+                }
+                Err(e) => return (Err(e), bufs),
+            };
+        }
+
+        (Ok(()), bufs)
+    }
+
     /// Write data from buffers into this file at the specified offset,
     /// returning how many bytes were written.
     ///
@@ -288,10 +569,97 @@ impl File {
         buf: Vec<T>,
         pos: u64,
     ) -> crate::BufResult<usize, Vec<T>> {
-        let op = Op::writev_at(&self.fd, buf, pos).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::writev_at(&fd, buf, pos).unwrap();
         op.await
     }
 
+    /// Attempts to write the entire contents of the buffers into this file at
+    /// the specified offset.
+    ///
+    /// This method will continuously call [`writev_at`] until there is no
+    /// more data to be written or an error is returned. A short write is
+    /// resumed from where it ended, which can be in the middle of a buffer.
+    /// This method will not return until all buffers have been successfully
+    /// written or an error occurs.
+    ///
+    /// # Return
+    ///
+    /// The method returns the operation result and the same array of buffers
+    /// passed in as an argument.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error that [`writev_at`] returns.
+    /// If a write returns `0` bytes, an error of the kind
+    /// [`ErrorKind::WriteZero`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let file = File::create("foo.txt").await?;
+    ///
+    ///         // Writes the whole byte string
+    ///         let bufs = vec!["some".to_owned().into_bytes(), " bytes".to_owned().into_bytes()];
+    ///         let (res, _) = file.writev_all_at(bufs, 0).await;
+    ///         res?;
+    ///
+    ///         // Close the file
+    ///         file.close().await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// [`writev_at`]: File::writev_at
+    /// [`ErrorKind::WriteZero`]: std::io::ErrorKind::WriteZero
+    pub async fn writev_all_at<T: IoBuf>(
+        &self,
+        mut bufs: Vec<T>,
+        mut pos: u64,
+    ) -> crate::BufResult<(), Vec<T>> {
+        let total: usize = bufs.iter().map(|b| b.bytes_init()).sum();
+        if pos.checked_add(total as u64).is_none() {
+            return (
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "buffer too large for file",
+                )),
+                bufs,
+            );
+        }
+
+        let mut written = 0;
+        while written != total {
+            let fd = self.fd.acquire().await;
+            let op = Op::writev_at_skip(&fd, bufs, written, pos).unwrap();
+            let (res, returned) = op.await;
+            bufs = returned;
+            match res {
+                Ok(0) => {
+                    return (
+                        Err(io::Error::new(
+                            io::ErrorKind::WriteZero,
+                            "failed to write whole buffer",
+                        )),
+                        bufs,
+                    )
+                }
+                Ok(n) => {
+                    pos += n as u64;
+                    written += n;
+                }
+                Err(e) => return (Err(e), bufs),
+            };
+        }
+
+        (Ok(()), bufs)
+    }
+
     /// Read the exact number of bytes required to fill `buf` at the specified
     /// offset from the file.
     ///
@@ -390,6 +758,98 @@ impl File {
         (Ok(()), buf.into_inner())
     }
 
+    /// Computes the digest of `len` bytes of the file, starting at `offset`,
+    /// with the hash function `D`.
+    ///
+    /// The range is read in chunks, and the read of each chunk is in flight
+    /// while the previous one is hashed, so that reading and hashing
+    /// overlap rather than taking turns.
+    ///
+    /// Requires the `digest` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of a read. If the file ends before the end
+    /// of the range, an error of the kind [`ErrorKind::UnexpectedEof`] is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sha2::Sha256;
+    /// use tokio_uring::fs::File;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let f = File::open("foo.txt").await?;
+    ///         let len = f.metadata().await?.len();
+    ///         let digest = f.hash_range::<Sha256>(0, len).await?;
+    ///         println!("{:x}", digest);
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// [`ErrorKind::UnexpectedEof`]: std::io::ErrorKind::UnexpectedEof
+    #[cfg(feature = "digest")]
+    pub async fn hash_range<D: digest::Digest>(
+        &self,
+        offset: u64,
+        len: u64,
+    ) -> io::Result<digest::Output<D>> {
+        const CHUNK_SIZE: u64 = 256 * 1024;
+
+        let end = offset.checked_add(len).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "range too large for file")
+        })?;
+        let buf_size = len.min(CHUNK_SIZE) as usize;
+        let handle =
+            crate::runtime::CONTEXT.with(|x| x.handle().expect("Not in a runtime context"));
+        let mut hasher = D::new();
+        let mut pos = offset;
+        let mut spare = Vec::with_capacity(buf_size);
+        // The chunk read last, hashed while the next one is read
+        let mut filled: Option<Vec<u8>> = None;
+
+        while pos < end {
+            let want = (end - pos).min(CHUNK_SIZE) as usize;
+            let fd = self.fd.acquire().await;
+            // The read is submitted right away, rather than as the flush
+            // policy would, to be in flight while the last chunk is hashed.
+            // If the submission fails, the read completes with the error,
+            // or is left queued for the next submission.
+            handle.begin_batch();
+            let op = Op::read_at(&fd, spare.slice(..want), pos);
+            let _ = handle.end_batch();
+            let op = op?;
+            spare = match filled.take() {
+                Some(mut buf) => {
+                    hasher.update(&buf);
+                    buf.clear();
+                    buf
+                }
+                None => Vec::with_capacity(buf_size),
+            };
+
+            let (res, slice) = op.await;
+            match res? {
+                0 => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "file ended before the end of the range",
+                    ))
+                }
+                n => pos += n as u64,
+            }
+            filled = Some(slice.into_inner());
+        }
+
+        if let Some(buf) = filled {
+            hasher.update(&buf);
+        }
+        Ok(hasher.finalize())
+    }
+
     /// Like [`read_at`], but using a pre-mapped buffer
     /// registered with [`FixedBufRegistry`].
     ///
@@ -435,10 +895,58 @@ impl File {
         T: BoundedBufMut<BufMut = FixedBuf>,
     {
         // Submit the read operation
-        let op = Op::read_fixed_at(&self.fd, buf, pos).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::read_fixed_at(&fd, buf, pos).unwrap();
         op.await
     }
 
+    /// Reads up to `len` bytes at the specified offset into a buffer of the
+    /// runtime's default pool, returning the buffer with the data read.
+    ///
+    /// The read is performed as by [`read_fixed_at`], with a buffer checked
+    /// out of the pool installed by [`FixedBufPool::install_default`]. The
+    /// buffer is the smallest of the free buffers able to hold `len` bytes;
+    /// if there is none, the method waits for one to be checked in. The
+    /// buffer returns to the pool once the returned handle is dropped.
+    ///
+    /// [`read_fixed_at`]: Self::read_fixed_at
+    /// [`FixedBufPool::install_default`]: crate::buf::fixed::FixedBufPool::install_default
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`NotFound`] if no default pool is installed, and with
+    /// [`InvalidInput`] if no buffer of the pool can hold `len` bytes.
+    ///
+    /// [`NotFound`]: io::ErrorKind::NotFound
+    /// [`InvalidInput`]: io::ErrorKind::InvalidInput
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::buf::fixed::FixedBufPool;
+    /// use tokio_uring::fs::File;
+    /// use std::iter;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let pool = FixedBufPool::new(iter::repeat_with(|| Vec::with_capacity(4096)).take(16));
+    ///         pool.register()?;
+    ///         pool.install_default();
+    ///
+    ///         let f = File::open("foo.txt").await?;
+    ///         let buf = f.read_at_pooled(4096, 0).await?;
+    ///         println!("The bytes: {:?}", &buf[..]);
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn read_at_pooled(&self, len: usize, pos: u64) -> io::Result<FixedBuf> {
+        let buf = FixedBufPool::next_default(len).await?;
+        let (res, slice) = self.read_fixed_at(buf.slice(..len), pos).await;
+        res?;
+        Ok(slice.into_inner())
+    }
+
     /// Write a buffer into this file at the specified offset, returning how
     /// many bytes were written.
     ///
@@ -486,13 +994,109 @@ impl File {
     ///
     /// [`Ok(n)`]: Ok
     pub async fn write_at<T: BoundedBuf>(&self, buf: T, pos: u64) -> crate::BufResult<usize, T> {
-        let op = Op::write_at(&self.fd, buf, pos).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::write_at(&fd, buf, pos).unwrap();
         op.await
     }
 
-    /// Attempts to write an entire buffer into this file at the specified offset.
+    /// Like [`write_at`], with flags changing how the kernel performs the
+    /// write, e.g. to make the data durable with [`RwFlags::DSYNC`], or to
+    /// keep it out of the page cache with [`RwFlags::DONTCACHE`].
     ///
-    /// This method will continuously call [`write_at`] until there is no more data
+    /// [`write_at`]: File::write_at
+    ///
+    /// # Errors
+    ///
+    /// Fails with `EOPNOTSUPP` if the kernel or the file system does not
+    /// support one of the flags.
+    ///
+    /// # Examples
+    ///
+    /// Ingesting a large file without evicting the rest of the page cache:
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::{File, RwFlags};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let file = File::create("ingest.dat").await?;
+    ///
+    ///         let mut pos = 0;
+    ///         for chunk in std::iter::repeat(vec![7; 1 << 20]).take(64) {
+    ///             let (res, _) = file.write_all_at_with(chunk, pos, RwFlags::DONTCACHE).await;
+    ///             res?;
+    ///             pos += 1 << 20;
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn write_at_with<T: BoundedBuf>(
+        &self,
+        buf: T,
+        pos: u64,
+        flags: RwFlags,
+    ) -> crate::BufResult<usize, T> {
+        let fd = self.fd.acquire().await;
+        let op = Op::write_at_with(&fd, buf, pos, flags.0).unwrap();
+        op.await
+    }
+
+    /// Write a buffer at the end of the file, returning how many bytes were
+    /// written.
+    ///
+    /// The file should be opened in append mode with [`OpenOptions::append`].
+    /// The write is then submitted without a position, and the kernel
+    /// atomically appends the data to the file, even if other tasks or
+    /// processes append to the same file concurrently. This is not possible
+    /// with [`write_at`], as the end of the file may have moved by the time
+    /// the write is performed.
+    ///
+    /// If the file is not in append mode, the data is written at the current
+    /// file position, which is advanced by the number of bytes written.
+    ///
+    /// # Return
+    ///
+    /// The method returns the operation result and the same buffer value passed
+    /// in as an argument. As with [`write_at`], the write may be partial; for
+    /// appends to be atomic, the remaining data should not be appended with
+    /// another call.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::OpenOptions;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let file = OpenOptions::new()
+    ///             .append(true)
+    ///             .create(true)
+    ///             .open("app.log")
+    ///             .await?;
+    ///
+    ///         let (res, _) = file.append(&b"started\n"[..]).await;
+    ///         res?;
+    ///
+    ///         // Close the file
+    ///         file.close().await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// [`write_at`]: File::write_at
+    pub async fn append<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<usize, T> {
+        // An offset of -1 makes the kernel use the current file position,
+        // which is the end of the file in append mode.
+        let fd = self.fd.acquire().await;
+        let op = Op::write_at(&fd, buf, u64::MAX).unwrap();
+        op.await
+    }
+
+    /// Attempts to write an entire buffer into this file at the specified offset.
+    ///
+    /// This method will continuously call [`write_at`] until there is no more data
     /// to be written or an error is returned.
     /// This method will not return until the entire buffer has been successfully
     /// written or an error occurs.
@@ -532,11 +1136,134 @@ impl File {
     ///
     /// [`write_at`]: File::write_at
     pub async fn write_all_at<T>(&self, buf: T, pos: u64) -> crate::BufResult<(), T>
+    where
+        T: BoundedBuf,
+    {
+        self.write_all_at_with(buf, pos, RwFlags::empty()).await
+    }
+
+    /// Like [`write_all_at`], with flags changing how the kernel performs
+    /// each write, as for [`write_at_with`].
+    ///
+    /// [`write_all_at`]: File::write_all_at
+    /// [`write_at_with`]: File::write_at_with
+    pub async fn write_all_at_with<T>(
+        &self,
+        buf: T,
+        pos: u64,
+        flags: RwFlags,
+    ) -> crate::BufResult<(), T>
+    where
+        T: BoundedBuf,
+    {
+        let orig_bounds = buf.bounds();
+        let (res, buf) = self.write_all_slice_at(buf.slice_full(), pos, flags).await;
+        (res, T::from_buf_bounds(buf, orig_bounds))
+    }
+
+    /// Writes an entire buffer at `pos`, and makes the file durable, with
+    /// the write linked to an `fsync` in a single submission.
+    ///
+    /// This is equivalent to [`write_all_at`] followed by [`sync_all`],
+    /// saving a round trip between the runtime and the kernel: the sync is
+    /// started by the kernel as soon as the write completes. If the write
+    /// is short, which cancels the linked sync, the rest of the data is
+    /// written and the file synced separately.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of the writes or the sync. The buffer is
+    /// returned in all cases.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::OpenOptions;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let wal = OpenOptions::new().write(true).create(true).open("wal").await?;
+    ///
+    ///         let (res, _) = wal.write_all_sync_at(&b"commit 1\n"[..], 0).await;
+    ///         res?;
+    ///         println!("committed");
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// [`write_all_at`]: File::write_all_at
+    /// [`sync_all`]: File::sync_all
+    pub async fn write_all_sync_at<T>(&self, buf: T, pos: u64) -> crate::BufResult<(), T>
+    where
+        T: BoundedBuf,
+    {
+        self.write_all_synced_at(buf, pos, types::FsyncFlags::empty())
+            .await
+    }
+
+    /// Like [`write_all_sync_at`], with the write linked to an `fdatasync`,
+    /// as done by [`sync_data`].
+    ///
+    /// [`write_all_sync_at`]: File::write_all_sync_at
+    /// [`sync_data`]: File::sync_data
+    pub async fn write_all_datasync_at<T>(&self, buf: T, pos: u64) -> crate::BufResult<(), T>
+    where
+        T: BoundedBuf,
+    {
+        self.write_all_synced_at(buf, pos, types::FsyncFlags::DATASYNC)
+            .await
+    }
+
+    async fn write_all_synced_at<T>(
+        &self,
+        buf: T,
+        pos: u64,
+        flags: types::FsyncFlags,
+    ) -> crate::BufResult<(), T>
     where
         T: BoundedBuf,
     {
         let orig_bounds = buf.bounds();
-        let (res, buf) = self.write_all_slice_at(buf.slice_full(), pos).await;
+        let buf = buf.slice_full();
+        let len = buf.bytes_init();
+
+        let fd = self.fd.acquire().await;
+        let (write, sync) = Op::write_at_sync(&fd, buf, pos, flags).unwrap();
+        let (res, slice) = write.await;
+        let synced = sync.await;
+        drop(fd);
+
+        let (res, buf) = match res {
+            Ok(n) if n == len => (synced, slice.into_inner()),
+            Ok(0) if len != 0 => (
+                Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                )),
+                slice.into_inner(),
+            ),
+            // The write was short, which canceled the linked sync
+            Ok(n) => match self
+                .write_all_slice_at(slice.slice(n..), pos + n as u64, RwFlags::empty())
+                .await
+            {
+                (Ok(()), buf) => {
+                    let fd = self.fd.acquire().await;
+                    let synced = if flags.contains(types::FsyncFlags::DATASYNC) {
+                        Op::datasync(&fd)
+                    } else {
+                        Op::fsync(&fd)
+                    };
+                    match synced {
+                        Ok(op) => (op.await, buf),
+                        Err(e) => (Err(e), buf),
+                    }
+                }
+                (Err(e), buf) => (Err(e), buf),
+            },
+            Err(e) => (Err(e), slice.into_inner()),
+        };
         (res, T::from_buf_bounds(buf, orig_bounds))
     }
 
@@ -544,6 +1271,7 @@ impl File {
         &self,
         mut buf: Slice<T>,
         mut pos: u64,
+        flags: RwFlags,
     ) -> crate::BufResult<(), T> {
         if pos.checked_add(buf.bytes_init() as u64).is_none() {
             return (
@@ -556,7 +1284,7 @@ impl File {
         }
 
         while buf.bytes_init() != 0 {
-            let (res, slice) = self.write_at(buf, pos).await;
+            let (res, slice) = self.write_at_with(buf, pos, flags).await;
             match res {
                 Ok(0) => {
                     return (
@@ -628,10 +1356,37 @@ impl File {
     where
         T: BoundedBuf<Buf = FixedBuf>,
     {
-        let op = Op::write_fixed_at(&self.fd, buf, pos).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::write_fixed_at(&fd, buf, pos).unwrap();
         op.await
     }
 
+    /// Writes the data at the specified offset from a buffer of the
+    /// runtime's default pool, returning how many bytes were written.
+    ///
+    /// The data is copied to a buffer checked out of the pool installed by
+    /// [`FixedBufPool::install_default`], as for [`read_at_pooled`], and
+    /// written as by [`write_fixed_at`]. The buffer returns to the pool once
+    /// the write has completed.
+    ///
+    /// [`FixedBufPool::install_default`]: crate::buf::fixed::FixedBufPool::install_default
+    /// [`read_at_pooled`]: Self::read_at_pooled
+    /// [`write_fixed_at`]: Self::write_fixed_at
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`NotFound`] if no default pool is installed, and with
+    /// [`InvalidInput`] if no buffer of the pool can hold the data.
+    ///
+    /// [`NotFound`]: io::ErrorKind::NotFound
+    /// [`InvalidInput`]: io::ErrorKind::InvalidInput
+    pub async fn write_at_pooled(&self, data: &[u8], pos: u64) -> io::Result<usize> {
+        let mut buf = FixedBufPool::next_default(data.len()).await?;
+        buf.put_slice(data);
+        let (res, _) = self.write_fixed_at(buf, pos).await;
+        res
+    }
+
     /// Attempts to write an entire buffer into this file at the specified offset.
     ///
     /// This method will continuously call [`write_fixed_at`] until there is no more data
@@ -732,7 +1487,8 @@ impl File {
     /// }
     /// ```
     pub async fn sync_all(&self) -> io::Result<()> {
-        Op::fsync(&self.fd)?.await
+        let fd = self.fd.acquire().await;
+        Op::fsync(&fd)?.await
     }
 
     /// Attempts to sync file data to disk.
@@ -769,7 +1525,284 @@ impl File {
     /// }
     /// ```
     pub async fn sync_data(&self) -> io::Result<()> {
-        Op::datasync(&self.fd)?.await
+        let fd = self.fd.acquire().await;
+        Op::datasync(&fd)?.await
+    }
+
+    /// Queries metadata about the underlying file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let f = File::open("foo.txt").await?;
+    ///         let metadata = f.metadata().await?;
+    ///         println!("{} bytes", metadata.len());
+    ///
+    ///         // Close the file
+    ///         f.close().await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn metadata(&self) -> io::Result<Metadata> {
+        let fd = self.fd.acquire().await;
+        let statx = Op::statx(Some(&fd), Path::new(""), libc::AT_EMPTY_PATH)?.await?;
+        Ok(Metadata::from_statx(statx))
+    }
+
+    /// Manipulates the allocated disk space of a byte range of the file.
+    ///
+    /// With empty `flags`, the space for the range is allocated, extending
+    /// the file if the range goes past its end. Other operations, such as
+    /// punching holes in sparse files, are selected with [`FallocateFlags`].
+    ///
+    /// Not all file systems support all operations; an error is returned if
+    /// the requested operation is not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::{FallocateFlags, OpenOptions};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let f = OpenOptions::new().write(true).open("disk.img").await?;
+    ///
+    ///         // Release the storage of the first megabyte, keeping the file size
+    ///         f.fallocate(0, 1024 * 1024, FallocateFlags::PUNCH_HOLE).await?;
+    ///
+    ///         // Close the file
+    ///         f.close().await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn fallocate(&self, offset: u64, len: u64, flags: FallocateFlags) -> io::Result<()> {
+        let fd = self.fd.acquire().await;
+        Op::fallocate(&fd, offset, len, flags.0)?.await
+    }
+
+    /// Maps `len` bytes of the file at `offset` into memory, read-only.
+    ///
+    /// The returned [`Mmap`] can be passed to write operations like any
+    /// other buffer, so that large immutable files can be served without
+    /// reading them into buffers. The offset does not need to be aligned to
+    /// a page. The file must have been opened for reading.
+    ///
+    /// Mapping is a system call made on the current thread; the contents
+    /// are loaded from the file when the pages are first accessed, which
+    /// may block the thread. Use [`Mmap::advise`] to have them read ahead.
+    ///
+    /// # Safety
+    ///
+    /// The mapping shares the pages of the file, so that the contents it
+    /// dereferences to change when the file is written to. The caller must
+    /// ensure that the range is neither modified nor truncated, by this or
+    /// any other process, while the mapping exists: a modification changes
+    /// the bytes behind a shared reference, and accessing the pages past the
+    /// end of a truncated file raises `SIGBUS`, which terminates the process.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::{Advice, File};
+    /// use tokio_uring::net::TcpStream;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let f = File::open("asset.bin").await?;
+    ///         let len = f.metadata().await?.len() as usize;
+    ///         // Safety: the asset is not modified while it is served
+    ///         let map = unsafe { f.map_readonly(0, len)? };
+    ///         map.advise(Advice::Sequential)?;
+    ///
+    ///         let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await?;
+    ///         let (res, _) = stream.write_all(map).await;
+    ///         res?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub unsafe fn map_readonly(&self, offset: u64, len: usize) -> io::Result<Mmap> {
+        Mmap::map(self.fd.raw_fd(), offset, len)
+    }
+
+    /// Returns the offset of the first byte of data at or after `offset`,
+    /// skipping holes in a sparse file.
+    ///
+    /// Returns `None` if there is no data past `offset`, which may be in a
+    /// hole at the end of the file or at or beyond its end.
+    ///
+    /// There is no `lseek` operation in io_uring, and the lookup may have to
+    /// read the file system metadata from the device, so it is made on the
+    /// blocking thread pool. `lseek` moves the offset of the file, which is
+    /// shared with the duplicates of its descriptor; it is moved back once
+    /// the lookup is done. The positioned reads and writes of `File` do not
+    /// use the offset.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let f = File::open("disk.img").await?;
+    ///         match f.seek_data(0).await? {
+    ///             Some(pos) => println!("the data starts at {}", pos),
+    ///             None => println!("the file is empty"),
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn seek_data(&self, offset: u64) -> io::Result<Option<u64>> {
+        super::extents::seek(&self.fd, offset, libc::SEEK_DATA).await
+    }
+
+    /// Returns the offset of the first hole at or after `offset` in a sparse
+    /// file.
+    ///
+    /// The end of the file counts as a hole, so the offset of the end is
+    /// returned if there are no holes past `offset`. Returns `None` if
+    /// `offset` is at or beyond the end of the file.
+    ///
+    /// Like [`seek_data`], the lookup is made on the blocking thread pool,
+    /// and leaves the offset of the file where it was.
+    ///
+    /// [`seek_data`]: File::seek_data
+    pub async fn seek_hole(&self, offset: u64) -> io::Result<Option<u64>> {
+        super::extents::seek(&self.fd, offset, libc::SEEK_HOLE).await
+    }
+
+    /// Returns the byte ranges of the file that hold data, skipping the
+    /// holes of a sparse file.
+    ///
+    /// This lets tools copying sparse files, such as images of virtual
+    /// machine disks, read only the allocated ranges. The extents are looked
+    /// up through a duplicate of the file's descriptor, so they can be
+    /// iterated after the file is closed. See [`Extents`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let f = File::open("disk.img").await?;
+    ///         let mut extents = f.extents()?;
+    ///         while let Some(extent) = extents.next().await {
+    ///             let extent = extent?;
+    ///             let buf = Vec::with_capacity((extent.end - extent.start) as usize);
+    ///             let (res, buf) = f.read_exact_at(buf, extent.start).await;
+    ///             res?;
+    ///             println!("{} bytes of data at {}", buf.len(), extent.start);
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub fn extents(&self) -> io::Result<Extents> {
+        Extents::new(&self.fd)
+    }
+
+    /// Limits the number of operations on the file in flight at a time, or
+    /// removes the limit with `None`.
+    ///
+    /// Once the limit is reached, the methods of the file wait for one of
+    /// the operations in flight to complete before submitting another. This
+    /// bounds the operations, and the buffers they hold, one task can queue
+    /// on a file, leaving room in the submission queue and the device for
+    /// the other tasks. An operation counts against the limit until its
+    /// future completes, or, if the future is dropped, until the kernel is
+    /// done with it.
+    ///
+    /// The limit applies to the file and to the handles sharing its
+    /// descriptor. Operations in flight when the limit is changed are not
+    /// counted against the new limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the limit is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let file = Rc::new(File::create("log").await?);
+    ///         file.set_max_in_flight(Some(32));
+    ///
+    ///         // At most 32 of the writes are in flight at a time
+    ///         for i in 0..1000u64 {
+    ///             let file = Rc::clone(&file);
+    ///             tokio_uring::spawn(async move {
+    ///                 let (res, _) = file.write_at(vec![b'x'; 512], i * 512).await;
+    ///                 res.unwrap();
+    ///             });
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub fn set_max_in_flight(&self, max: Option<usize>) {
+        self.fd.set_max_in_flight(max)
+    }
+
+    /// Locks a range of the file against the other tasks of the runtime,
+    /// waiting until no part of it is locked.
+    ///
+    /// The lock is held until the returned guard is dropped. Requests for
+    /// overlapping ranges are granted in the order they were made, while
+    /// disjoint ranges are locked independently. This lets tasks sharing
+    /// the file, e.g. through an `Rc`, write to regions of it without
+    /// interleaving their writes.
+    ///
+    /// The lock is advisory and in-process: it does not stop operations
+    /// performed without locking, nor other processes. An empty range
+    /// locks the byte at its start.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::File;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let file = Rc::new(File::create("pages.db").await?);
+    ///
+    ///         for page in 0..16u64 {
+    ///             let file = Rc::clone(&file);
+    ///             tokio_uring::spawn(async move {
+    ///                 let range = page * 4096..(page + 1) * 4096;
+    ///                 let _guard = file.lock_range(range.clone()).await;
+    ///                 let (res, _) = file.write_all_at(vec![page as u8; 4096], range.start).await;
+    ///                 res.unwrap();
+    ///             });
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn lock_range(&self, range: Range<u64>) -> RangeGuard<'_> {
+        self.locks.lock(range).await
+    }
+
+    /// Locks a range of the file like [`lock_range`], if it can be locked
+    /// right away, returning `None` otherwise.
+    ///
+    /// [`lock_range`]: File::lock_range
+    pub fn try_lock_range(&self, range: Range<u64>) -> Option<RangeGuard<'_>> {
+        self.locks.try_lock(range)
     }
 
     /// Closes the file.
@@ -863,3 +1896,194 @@ pub async fn remove_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
 pub async fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
     Op::rename_at(from.as_ref(), to.as_ref(), 0)?.await
 }
+
+/// Flags selecting the operation performed by [`File::fallocate`].
+///
+/// Flags can be combined with the `|` operator where the operations allow
+/// it, e.g. `ZERO_RANGE | KEEP_SIZE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FallocateFlags(i32);
+
+impl FallocateFlags {
+    /// Do not change the size of the file, even if the range extends past
+    /// its end. Space allocated past the end is still reserved.
+    pub const KEEP_SIZE: FallocateFlags = FallocateFlags(libc::FALLOC_FL_KEEP_SIZE);
+
+    /// Deallocate the range, creating a hole in the file. Reading from the
+    /// hole returns zeroes. The size of the file is not changed; this flag
+    /// includes `KEEP_SIZE`, which the kernel requires with it.
+    pub const PUNCH_HOLE: FallocateFlags =
+        FallocateFlags(libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE);
+
+    /// Zero the range, preferably by converting it to unwritten extents
+    /// rather than writing zeroes. The file grows if the range extends past
+    /// its end, unless combined with `KEEP_SIZE`.
+    pub const ZERO_RANGE: FallocateFlags = FallocateFlags(libc::FALLOC_FL_ZERO_RANGE);
+
+    /// Remove the range from the file without leaving a hole, shifting the
+    /// data after it towards the start. The range usually has to be aligned
+    /// to the block size of the file system.
+    pub const COLLAPSE_RANGE: FallocateFlags = FallocateFlags(libc::FALLOC_FL_COLLAPSE_RANGE);
+
+    /// Insert a hole of the size of the range at its offset, shifting the
+    /// data after it towards the end. The range usually has to be aligned
+    /// to the block size of the file system.
+    pub const INSERT_RANGE: FallocateFlags = FallocateFlags(libc::FALLOC_FL_INSERT_RANGE);
+
+    /// Returns the set with no flags, which allocates the range.
+    pub const fn empty() -> FallocateFlags {
+        FallocateFlags(0)
+    }
+
+    /// Returns `true` if all flags in `other` are set in `self`.
+    pub const fn contains(self, other: FallocateFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for FallocateFlags {
+    type Output = FallocateFlags;
+
+    fn bitor(self, rhs: FallocateFlags) -> FallocateFlags {
+        FallocateFlags(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for FallocateFlags {
+    fn bitor_assign(&mut self, rhs: FallocateFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+// Not defined by the version of `libc` the crate requires.
+const RWF_DONTCACHE: i32 = 0x80;
+
+/// Flags changing how a single read or write is performed, passed to
+/// [`File::read_at_with`] and [`File::write_at_with`] like the flags of
+/// `preadv2` and `pwritev2`.
+///
+/// Flags can be combined with the `|` operator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RwFlags(i32);
+
+impl RwFlags {
+    /// Write the data durably, like a write followed by `fdatasync`, for
+    /// this write only.
+    pub const DSYNC: RwFlags = RwFlags(libc::RWF_DSYNC);
+
+    /// Write the data and the metadata durably, like a write followed by
+    /// `fsync`, for this write only.
+    pub const SYNC: RwFlags = RwFlags(libc::RWF_SYNC);
+
+    /// Fail with `EAGAIN` rather than wait, if the data is not in the page
+    /// cache or the write would block.
+    pub const NOWAIT: RwFlags = RwFlags(libc::RWF_NOWAIT);
+
+    /// Perform buffered I/O, but drop the pages from the page cache once
+    /// the operation has completed, as done for `RWF_DONTCACHE`.
+    ///
+    /// Unlike `O_DIRECT`, the buffer and the offset need not be aligned,
+    /// and the data is still read from the cache if it is there. This keeps
+    /// bulk transfers from evicting the data cached for other uses.
+    /// Supported since Linux 6.14, by the file systems which opt in.
+    pub const DONTCACHE: RwFlags = RwFlags(RWF_DONTCACHE);
+
+    /// Returns the set with no flags, which performs the operation as usual.
+    pub const fn empty() -> RwFlags {
+        RwFlags(0)
+    }
+
+    /// Returns `true` if all flags in `other` are set in `self`.
+    pub const fn contains(self, other: RwFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for RwFlags {
+    type Output = RwFlags;
+
+    fn bitor(self, rhs: RwFlags) -> RwFlags {
+        RwFlags(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for RwFlags {
+    fn bitor_assign(&mut self, rhs: RwFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Flags modifying the behavior of [`rename_with`].
+///
+/// Flags can be combined with the `|` operator, though the kernel rejects
+/// combinations that make no sense, such as `NOREPLACE | EXCHANGE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RenameFlags(u32);
+
+impl RenameFlags {
+    /// Do not overwrite the destination. If it already exists, the rename
+    /// fails with an error of the kind [`io::ErrorKind::AlreadyExists`].
+    pub const NOREPLACE: RenameFlags = RenameFlags(libc::RENAME_NOREPLACE);
+
+    /// Atomically exchange the source and the destination, which must both
+    /// exist. They may be of different types, e.g. a file and a directory.
+    pub const EXCHANGE: RenameFlags = RenameFlags(libc::RENAME_EXCHANGE);
+
+    /// Returns the set with no flags, which makes [`rename_with`] behave
+    /// like [`rename`].
+    pub const fn empty() -> RenameFlags {
+        RenameFlags(0)
+    }
+
+    /// Returns `true` if all flags in `other` are set in `self`.
+    pub const fn contains(self, other: RenameFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for RenameFlags {
+    type Output = RenameFlags;
+
+    fn bitor(self, rhs: RenameFlags) -> RenameFlags {
+        RenameFlags(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for RenameFlags {
+    fn bitor_assign(&mut self, rhs: RenameFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Renames a file or directory to a new name, with the behavior modified by
+/// `flags`.
+///
+/// This is the `renameat2(2)` counterpart of [`rename`]. With
+/// [`RenameFlags::NOREPLACE`], a file can be moved into place only if the
+/// destination does not exist yet. [`RenameFlags::EXCHANGE`] atomically swaps
+/// two files, e.g. to replace a configuration file and keep the previous one.
+///
+/// Not all file systems support these flags; an error is returned if the
+/// requested behavior is not supported.
+///
+/// # Example
+///
+/// ```no_run
+/// use tokio_uring::fs::{rename_with, RenameFlags};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         // Swap the new configuration in, keeping the old one in config.new
+///         rename_with("config.new", "config", RenameFlags::EXCHANGE).await?;
+///         Ok::<(), std::io::Error>(())
+///     })?;
+///     Ok(())
+/// }
+/// ```
+pub async fn rename_with(
+    from: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    flags: RenameFlags,
+) -> io::Result<()> {
+    Op::rename_at(from.as_ref(), to.as_ref(), flags.0)?.await
+}
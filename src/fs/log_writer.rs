@@ -0,0 +1,276 @@
+use crate::fs::File;
+use crate::io::SharedFd;
+use crate::runtime::driver::op::Op;
+use std::cell::RefCell;
+use std::io;
+use std::pin::pin;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Appends records to a log file, committing them to disk in groups.
+///
+/// Records passed to [`append`] are collected and written together with a
+/// single vectored write, linked with a sync of the file data. The
+/// [`append`] futures of all records in the group resolve once the group has
+/// been synced. While a group is being committed, new records are collected
+/// for the next group, so the number of syncs adapts to the load. A
+/// [`commit interval`] can be set to further delay commits and collect
+/// larger groups.
+///
+/// The writer can be cloned to append records from multiple tasks.
+///
+/// If a commit fails, the log is in an unknown state: the error is returned
+/// for all records in the group and for all records appended later.
+///
+/// [`append`]: LogWriter::append
+/// [`commit interval`]: LogWriter::commit_interval
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::{LogWriter, OpenOptions};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let file = OpenOptions::new()
+///             .write(true)
+///             .create(true)
+///             .open("wal.log")
+///             .await?;
+///         let len = file.metadata().await?.len();
+///
+///         let log = LogWriter::new(&file, len);
+///
+///         let tasks: Vec<_> = (0..10)
+///             .map(|i| {
+///                 let log = log.clone();
+///                 tokio_uring::spawn(async move {
+///                     let record = format!("record {}\n", i).into_bytes();
+///                     log.append(record).await
+///                 })
+///             })
+///             .collect();
+///
+///         for task in tasks {
+///             let offset = task.await.unwrap()?;
+///             println!("record committed at offset {}", offset);
+///         }
+///
+///         Ok(())
+///     })
+/// }
+/// ```
+#[derive(Clone)]
+pub struct LogWriter {
+    inner: Rc<RefCell<Inner>>,
+
+    // Notified when a group has been committed, or has failed
+    committed: Rc<Notify>,
+}
+
+struct Inner {
+    fd: SharedFd,
+
+    // Offset at which the next record is appended.
+    pos: u64,
+
+    // Records of the group being collected, and the offset of the first one.
+    pending: Vec<Vec<u8>>,
+    pending_pos: u64,
+
+    // Number of the group being collected. Groups are numbered from 1.
+    group: u64,
+
+    // Number of the last group committed to disk.
+    committed: u64,
+
+    // Whether the commit task is running.
+    committing: bool,
+
+    // Error of a failed commit.
+    error: Option<io::Error>,
+
+    interval: Duration,
+}
+
+impl LogWriter {
+    /// Creates a writer appending records to the file at the offset `pos`,
+    /// which is normally the current length of the file.
+    ///
+    /// The writer keeps the file open until it, and all its clones, are
+    /// dropped.
+    pub fn new(file: &File, pos: u64) -> LogWriter {
+        LogWriter {
+            inner: Rc::new(RefCell::new(Inner {
+                fd: file.shared_fd().clone(),
+                pos,
+                pending: Vec::new(),
+                pending_pos: pos,
+                group: 1,
+                committed: 0,
+                committing: false,
+                error: None,
+                interval: Duration::ZERO,
+            })),
+            committed: Rc::new(Notify::new()),
+        }
+    }
+
+    /// Sets the time a commit is delayed by to collect more records.
+    ///
+    /// The default is zero, meaning that records are committed as soon as
+    /// the previous commit has completed. The setting is shared by all
+    /// clones of the writer.
+    pub fn commit_interval(&self, interval: Duration) -> &Self {
+        self.inner.borrow_mut().interval = interval;
+        self
+    }
+
+    /// Returns the offset at which the next record will be appended.
+    pub fn position(&self) -> u64 {
+        self.inner.borrow().pos
+    }
+
+    /// Appends a record to the log, resolving once it has been committed
+    /// to disk.
+    ///
+    /// Returns the offset in the file at which the record has been written.
+    /// Records are written in the order of the calls to `append`.
+    ///
+    /// If the future is dropped before it resolves, the record is still
+    /// committed.
+    pub async fn append(&self, record: Vec<u8>) -> io::Result<u64> {
+        let (group, offset) = {
+            let mut inner = self.inner.borrow_mut();
+            if let Some(e) = &inner.error {
+                return Err(copy_error(e));
+            }
+
+            let offset = inner.pos;
+            inner.pos += record.len() as u64;
+            inner.pending.push(record);
+
+            if !inner.committing {
+                inner.committing = true;
+                crate::spawn(commit(self.inner.clone(), self.committed.clone()));
+            }
+
+            (inner.group, offset)
+        };
+
+        loop {
+            let mut committed = pin!(self.committed.notified());
+            committed.as_mut().enable();
+            {
+                let inner = self.inner.borrow();
+                if inner.committed >= group {
+                    return Ok(offset);
+                }
+                if let Some(e) = &inner.error {
+                    return Err(copy_error(e));
+                }
+            }
+            committed.await;
+        }
+    }
+}
+
+// Commits the collected groups until there are no more records.
+async fn commit(inner: Rc<RefCell<Inner>>, committed: Rc<Notify>) {
+    loop {
+        let interval = inner.borrow().interval;
+        if !interval.is_zero() {
+            tokio::time::sleep(interval).await;
+        }
+
+        let (fd, records, pos, group) = {
+            let mut inner = inner.borrow_mut();
+            if inner.pending.is_empty() {
+                inner.committing = false;
+                return;
+            }
+
+            let records = std::mem::take(&mut inner.pending);
+            let pos = inner.pending_pos;
+            inner.pending_pos = inner.pos;
+            let group = inner.group;
+            inner.group += 1;
+            (inner.fd.clone(), records, pos, group)
+        };
+
+        let res = write_and_sync(&fd, records, pos).await;
+
+        let mut inner = inner.borrow_mut();
+        let failed = res.is_err();
+        match res {
+            Ok(()) => inner.committed = group,
+            Err(e) => inner.error = Some(e),
+        }
+        committed.notify_waiters();
+        if failed {
+            inner.committing = false;
+            return;
+        }
+    }
+}
+
+// Writes the records, with vectored writes of up to `UIO_MAXIOV` records,
+// the last of which is linked with a sync of the file data.
+async fn write_and_sync(fd: &SharedFd, mut records: Vec<Vec<u8>>, mut pos: u64) -> io::Result<()> {
+    loop {
+        let rest = records.split_off(records.len().min(libc::UIO_MAXIOV as usize));
+        let len: usize = records.iter().map(Vec::len).sum();
+        if !rest.is_empty() {
+            write_all(fd, records, 0, pos).await?;
+            records = rest;
+            pos += len as u64;
+            continue;
+        }
+
+        let (write, sync) = Op::writev_at_datasync(fd, records, pos)?;
+        let (res, records) = write.await;
+        let synced = sync.await;
+        let n = res?;
+        if n == len {
+            return synced;
+        }
+
+        // The write was short, which cancels the linked sync. Write the
+        // rest of the data and sync separately.
+        write_all(fd, records, n, pos).await?;
+        return Op::datasync(fd)?.await;
+    }
+}
+
+// Writes the records at `pos`, past the first `written` bytes.
+async fn write_all(
+    fd: &SharedFd,
+    mut records: Vec<Vec<u8>>,
+    mut written: usize,
+    pos: u64,
+) -> io::Result<()> {
+    let len: usize = records.iter().map(Vec::len).sum();
+    while written < len {
+        let (res, bufs) = Op::writev_at_skip(fd, records, written, pos + written as u64)?.await;
+        records = bufs;
+        match res {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ));
+            }
+            Ok(n) => written += n,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn copy_error(e: &io::Error) -> io::Error {
+    match e.raw_os_error() {
+        Some(code) => io::Error::from_raw_os_error(code),
+        None => io::Error::new(e.kind(), e.to_string()),
+    }
+}
@@ -0,0 +1,299 @@
+use crate::runtime::driver::op::Op;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Metadata information about a file.
+///
+/// This structure is returned from the [`metadata`] or [`symlink_metadata`]
+/// function or the [`File::metadata`] or [`DirEntry::metadata`] method and
+/// represents known metadata about a file such as its permissions, size,
+/// modification times, etc.
+///
+/// The accessors mirror those of [`std::fs::Metadata`], including the ones
+/// provided on Unix by [`std::os::unix::fs::MetadataExt`].
+///
+/// [`File::metadata`]: crate::fs::File::metadata
+/// [`DirEntry::metadata`]: crate::fs::DirEntry::metadata
+#[derive(Clone)]
+pub struct Metadata {
+    statx: libc::statx,
+}
+
+impl Metadata {
+    pub(crate) fn from_statx(statx: libc::statx) -> Metadata {
+        Metadata { statx }
+    }
+
+    /// Returns the file type for this metadata.
+    pub fn file_type(&self) -> FileType {
+        FileType {
+            mode: self.statx.stx_mode as u32,
+        }
+    }
+
+    /// Returns `true` if this metadata is for a directory.
+    pub fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    /// Returns `true` if this metadata is for a regular file.
+    pub fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+
+    /// Returns `true` if this metadata is for a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.file_type().is_symlink()
+    }
+
+    /// Returns the size of the file, in bytes, this metadata is for.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        self.statx.stx_size
+    }
+
+    /// Returns the permissions of the file this metadata is for.
+    pub fn permissions(&self) -> Permissions {
+        Permissions {
+            mode: self.statx.stx_mode as u32,
+        }
+    }
+
+    /// Returns the last modification time listed in this metadata.
+    pub fn modified(&self) -> io::Result<SystemTime> {
+        self.timestamp(libc::STATX_MTIME, &self.statx.stx_mtime, "modification")
+    }
+
+    /// Returns the last access time of this metadata.
+    pub fn accessed(&self) -> io::Result<SystemTime> {
+        self.timestamp(libc::STATX_ATIME, &self.statx.stx_atime, "access")
+    }
+
+    /// Returns the creation time listed in this metadata.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of the kind [`io::ErrorKind::Unsupported`] if the
+    /// filesystem does not record the creation time.
+    pub fn created(&self) -> io::Result<SystemTime> {
+        self.timestamp(libc::STATX_BTIME, &self.statx.stx_btime, "creation")
+    }
+
+    /// Returns the ID of the device containing the file.
+    pub fn dev(&self) -> u64 {
+        libc::makedev(self.statx.stx_dev_major, self.statx.stx_dev_minor)
+    }
+
+    /// Returns the inode number.
+    pub fn ino(&self) -> u64 {
+        self.statx.stx_ino
+    }
+
+    /// Returns the rights applied to this file, including the file type bits.
+    pub fn mode(&self) -> u32 {
+        self.statx.stx_mode as u32
+    }
+
+    /// Returns the number of hard links pointing to this file.
+    pub fn nlink(&self) -> u64 {
+        self.statx.stx_nlink as u64
+    }
+
+    /// Returns the user ID of the owner of this file.
+    pub fn uid(&self) -> u32 {
+        self.statx.stx_uid
+    }
+
+    /// Returns the group ID of the owner of this file.
+    pub fn gid(&self) -> u32 {
+        self.statx.stx_gid
+    }
+
+    /// Returns the device ID of this file, if it is a special one.
+    pub fn rdev(&self) -> u64 {
+        libc::makedev(self.statx.stx_rdev_major, self.statx.stx_rdev_minor)
+    }
+
+    /// Returns the block size for filesystem I/O.
+    pub fn blksize(&self) -> u64 {
+        self.statx.stx_blksize as u64
+    }
+
+    /// Returns the number of 512-byte blocks allocated to the file.
+    pub fn blocks(&self) -> u64 {
+        self.statx.stx_blocks
+    }
+
+    fn timestamp(
+        &self,
+        mask: u32,
+        ts: &libc::statx_timestamp,
+        what: &str,
+    ) -> io::Result<SystemTime> {
+        if self.statx.stx_mask & mask == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} time is not available for the filesystem", what),
+            ));
+        }
+
+        let nsec = Duration::from_nanos(ts.tv_nsec as u64);
+        let time = if ts.tv_sec >= 0 {
+            UNIX_EPOCH + Duration::from_secs(ts.tv_sec as u64) + nsec
+        } else {
+            UNIX_EPOCH - Duration::from_secs(ts.tv_sec.unsigned_abs()) + nsec
+        };
+        Ok(time)
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metadata")
+            .field("file_type", &self.file_type())
+            .field("permissions", &self.permissions())
+            .field("len", &self.len())
+            .field("modified", &self.modified())
+            .field("accessed", &self.accessed())
+            .field("created", &self.created())
+            .finish()
+    }
+}
+
+/// A structure representing a type of file with accessors for each file type.
+///
+/// It is returned by the [`Metadata::file_type`] method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileType {
+    mode: u32,
+}
+
+impl FileType {
+    /// Tests whether this file type represents a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is(libc::S_IFDIR)
+    }
+
+    /// Tests whether this file type represents a regular file.
+    pub fn is_file(&self) -> bool {
+        self.is(libc::S_IFREG)
+    }
+
+    /// Tests whether this file type represents a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.is(libc::S_IFLNK)
+    }
+
+    /// Tests whether this file type represents a block device.
+    pub fn is_block_device(&self) -> bool {
+        self.is(libc::S_IFBLK)
+    }
+
+    /// Tests whether this file type represents a character device.
+    pub fn is_char_device(&self) -> bool {
+        self.is(libc::S_IFCHR)
+    }
+
+    /// Tests whether this file type represents a FIFO.
+    pub fn is_fifo(&self) -> bool {
+        self.is(libc::S_IFIFO)
+    }
+
+    /// Tests whether this file type represents a socket.
+    pub fn is_socket(&self) -> bool {
+        self.is(libc::S_IFSOCK)
+    }
+
+    fn is(&self, mode: libc::mode_t) -> bool {
+        self.mode & libc::S_IFMT == mode
+    }
+}
+
+/// Representation of the various permissions on a file.
+///
+/// It is returned by the [`Metadata::permissions`] method, and can be
+/// converted into [`std::fs::Permissions`] to be applied to a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Permissions {
+    mode: u32,
+}
+
+impl Permissions {
+    /// Creates a new instance from the given Unix permission bits.
+    pub fn from_mode(mode: u32) -> Permissions {
+        Permissions { mode }
+    }
+
+    /// Returns the underlying raw `st_mode` bits that contain the standard
+    /// Unix permissions for this file.
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Sets the underlying raw bits for this set of permissions.
+    pub fn set_mode(&mut self, mode: u32) {
+        self.mode = mode;
+    }
+
+    /// Returns `true` if these permissions describe a file that is not
+    /// writable by anyone.
+    pub fn readonly(&self) -> bool {
+        self.mode & 0o222 == 0
+    }
+
+    /// Modifies the write bits of these permissions, making the file
+    /// read-only if `readonly` is `true`, and writable by everyone otherwise.
+    pub fn set_readonly(&mut self, readonly: bool) {
+        if readonly {
+            self.mode &= !0o222;
+        } else {
+            self.mode |= 0o222;
+        }
+    }
+}
+
+impl From<Permissions> for std::fs::Permissions {
+    fn from(perm: Permissions) -> Self {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::Permissions::from_mode(perm.mode)
+    }
+}
+
+/// Queries the file system metadata for a path, following symbolic links.
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let metadata = tokio_uring::fs::metadata("foo.txt").await?;
+///         println!("{} bytes", metadata.len());
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn metadata(path: impl AsRef<Path>) -> io::Result<Metadata> {
+    let statx = Op::statx(None, path.as_ref(), 0)?.await?;
+    Ok(Metadata::from_statx(statx))
+}
+
+/// Queries the file system metadata for a path, without following symbolic
+/// links.
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let metadata = tokio_uring::fs::symlink_metadata("foo.txt").await?;
+///         println!("symlink: {}", metadata.is_symlink());
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn symlink_metadata(path: impl AsRef<Path>) -> io::Result<Metadata> {
+    let statx = Op::statx(None, path.as_ref(), libc::AT_SYMLINK_NOFOLLOW)?.await?;
+    Ok(Metadata::from_statx(statx))
+}
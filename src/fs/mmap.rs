@@ -0,0 +1,190 @@
+use crate::buf::IoBuf;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::os::unix::io::RawFd;
+use std::ptr::NonNull;
+
+/// A read-only memory mapping of a range of a file.
+///
+/// Returned by [`File::map_readonly`]. The mapping dereferences to the
+/// contents of the range, and implements [`IoBuf`], so it can be passed to
+/// write operations, such as sending a large immutable asset to a socket,
+/// without copying the contents into a buffer first. The mapping stays
+/// valid after the file is closed, and is unmapped when dropped.
+///
+/// The pages of the mapping are loaded from the file on first access. The
+/// kernel is told how the mapping will be accessed with [`advise`].
+///
+/// The mapping shares the pages of the file: see the safety requirements
+/// of [`File::map_readonly`] on modifying the file while it is mapped.
+///
+/// [`File::map_readonly`]: crate::fs::File::map_readonly
+/// [`advise`]: Mmap::advise
+pub struct Mmap {
+    // The start of the mapping, aligned to a page
+    base: NonNull<u8>,
+    // The offset of the requested range in the mapping
+    offset: usize,
+    len: usize,
+}
+
+// Safety: the mapping is owned by the value, and the caller of
+// `File::map_readonly` ensures the mapped range is not modified.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+/// The expected access pattern of a memory mapping, set with
+/// [`Mmap::advise`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Advice {
+    /// No particular access pattern. This is the default.
+    Normal,
+    /// The pages are accessed in random order, so that reading ahead is
+    /// not useful.
+    Random,
+    /// The pages are accessed in sequential order, so that they can be read
+    /// ahead aggressively and freed soon after access.
+    Sequential,
+    /// The pages will be accessed soon, so that reading them can start now.
+    WillNeed,
+    /// The pages will not be accessed soon, so that they can be freed.
+    DontNeed,
+}
+
+impl Advice {
+    fn as_raw(self) -> libc::c_int {
+        match self {
+            Advice::Normal => libc::MADV_NORMAL,
+            Advice::Random => libc::MADV_RANDOM,
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::WillNeed => libc::MADV_WILLNEED,
+            Advice::DontNeed => libc::MADV_DONTNEED,
+        }
+    }
+}
+
+impl Mmap {
+    pub(crate) fn map(fd: RawFd, offset: u64, len: usize) -> io::Result<Mmap> {
+        let page = page_size();
+        let delta = (offset % page as u64) as usize;
+        let start = libc::off64_t::try_from(offset - delta as u64).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "offset too large for file")
+        })?;
+
+        // An empty mapping is not allowed, so a page is mapped for an empty range
+        let map_len = delta.checked_add(len.max(1)).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "length too large for mapping")
+        })?;
+        let ptr = unsafe {
+            libc::mmap64(
+                std::ptr::null_mut(),
+                map_len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                fd,
+                start,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Mmap {
+            base: NonNull::new(ptr.cast()).unwrap(),
+            offset: delta,
+            len,
+        })
+    }
+
+    /// Returns the length of the mapped range.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tells the kernel how the mapping will be accessed, so that it can
+    /// read ahead or free the pages accordingly.
+    pub fn advise(&self, advice: Advice) -> io::Result<()> {
+        self.advise_range(0, self.len, advice)
+    }
+
+    /// Like [`advise`], but for the `len` bytes at `offset` in the mapped
+    /// range only.
+    ///
+    /// [`advise`]: Mmap::advise
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of the bounds of the mapping.
+    pub fn advise_range(&self, offset: usize, len: usize, advice: Advice) -> io::Result<()> {
+        let end = offset.checked_add(len).expect("range overflows");
+        assert!(end <= self.len, "range out of the bounds of the mapping");
+
+        // The start of the range must be aligned to a page
+        let start = self.offset + offset;
+        let aligned = start - start % page_size();
+        let ptr = unsafe { self.base.as_ptr().add(aligned) };
+        syscall!(madvise(
+            ptr.cast(),
+            self.offset + end - aligned,
+            advice.as_raw(),
+        ))?;
+        Ok(())
+    }
+
+    fn map_len(&self) -> usize {
+        self.offset + self.len.max(1)
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.base.as_ptr().add(self.offset), self.len) }
+    }
+}
+
+impl AsRef<[u8]> for Mmap {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+unsafe impl IoBuf for Mmap {
+    fn stable_ptr(&self) -> *const u8 {
+        unsafe { self.base.as_ptr().add(self.offset) }
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len
+    }
+
+    fn bytes_total(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.base.as_ptr().cast(), self.map_len());
+        }
+    }
+}
+
+impl fmt::Debug for Mmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mmap")
+            .field("ptr", &self.stable_ptr())
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
@@ -1,12 +1,60 @@
 //! Filesystem manipulation operations.
 
+mod buffered;
+pub use buffered::{AccessPattern, BufferedFile};
+
+#[cfg(any(feature = "flate2", feature = "zstd"))]
+mod compress;
+#[cfg(any(feature = "flate2", feature = "zstd"))]
+pub use compress::{CompressWriter, Compression, DecompressReader};
+
 mod directory;
-pub use directory::remove_dir;
+pub use directory::{remove_dir, sync_dir, Dir};
+
+mod extents;
+pub use extents::Extents;
 
 mod file;
 pub use file::remove_file;
 pub use file::rename;
+pub use file::rename_with;
+pub use file::FallocateFlags;
 pub use file::File;
+pub use file::RenameFlags;
+pub use file::RwFlags;
+
+mod log_writer;
+pub use log_writer::LogWriter;
+
+mod metadata;
+pub use metadata::{metadata, symlink_metadata, FileType, Metadata, Permissions};
+
+mod mmap;
+pub use mmap::{Advice, Mmap};
 
 mod open_options;
 pub use open_options::OpenOptions;
+
+mod pipe;
+pub use pipe::{mkfifo, pipe, PipeReader, PipeWriter};
+
+mod range_lock;
+pub use range_lock::RangeGuard;
+
+mod read;
+pub use read::{read, read_to_string};
+
+mod read_chunks;
+pub use read_chunks::ReadChunks;
+
+mod read_dir;
+pub use read_dir::{read_dir, DirEntry, ReadDir};
+
+mod sequential;
+pub use sequential::{SequentialReader, SequentialWriter};
+
+mod watch;
+pub use watch::{watch, WatchEvent, WatchEventKind, Watcher};
+
+mod write;
+pub use write::{write, write_atomic};
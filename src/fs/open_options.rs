@@ -1,4 +1,4 @@
-use crate::fs::File;
+use crate::fs::{AccessPattern, File};
 
 use crate::runtime::driver::op::Op;
 use std::io;
@@ -54,6 +54,13 @@ use std::path::Path;
 ///     })
 /// }
 /// ```
+///
+/// The `O_NONBLOCK` flag, set with [`custom_flags`], only applies to opening
+/// the file: it allows a FIFO to be opened without waiting for the other
+/// end. It is cleared once the file is open, so that the operations on the
+/// file wait to be ready rather than fail with `EAGAIN`.
+///
+/// [`custom_flags`]: OpenOptionsExt::custom_flags
 #[derive(Debug, Clone)]
 pub struct OpenOptions {
     read: bool,
@@ -64,6 +71,7 @@ pub struct OpenOptions {
     create_new: bool,
     pub(crate) mode: libc::mode_t,
     pub(crate) custom_flags: libc::c_int,
+    pub(crate) access: Option<AccessPattern>,
 }
 
 impl OpenOptions {
@@ -97,6 +105,7 @@ impl OpenOptions {
             create_new: false,
             mode: 0o666,
             custom_flags: 0,
+            access: None,
         }
     }
 
@@ -282,6 +291,34 @@ impl OpenOptions {
         self
     }
 
+    /// Sets how the file is going to be accessed.
+    ///
+    /// Once the file is open, the pattern is passed to the kernel as a hint
+    /// for reading ahead of the accessed data, as with `posix_fadvise`.
+    /// [`File::open_buffered`] also picks its buffers for the pattern,
+    /// defaulting to [`AccessPattern::Sequential`] if it is not set.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::fs::{AccessPattern, OpenOptions};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let file = OpenOptions::new()
+    ///             .read(true)
+    ///             .access_pattern(AccessPattern::Random)
+    ///             .open("index.db")
+    ///             .await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub fn access_pattern(&mut self, access: AccessPattern) -> &mut OpenOptions {
+        self.access = Some(access);
+        self
+    }
+
     /// Opens a file at `path` with the options specified by `self`.
     ///
     /// # Errors
@@ -333,7 +370,11 @@ impl OpenOptions {
     /// [`Other`]: io::ErrorKind::Other
     /// [`PermissionDenied`]: io::ErrorKind::PermissionDenied
     pub async fn open(&self, path: impl AsRef<Path>) -> io::Result<File> {
-        Op::open(path.as_ref(), self)?.await
+        let file = Op::open(path.as_ref(), self)?.await?;
+        if let Some(access) = self.access {
+            access.advise(&file)?;
+        }
+        Ok(file)
     }
 
     pub(crate) fn access_mode(&self) -> io::Result<libc::c_int> {
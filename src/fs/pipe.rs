@@ -0,0 +1,274 @@
+use crate::buf::provided::BufRing;
+use crate::buf::{BoundedBuf, BoundedBufMut};
+use crate::fs::{File, ReadChunks};
+use crate::io::{SharedFd, Socket};
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::path::Path;
+
+/// Creates a named pipe (FIFO) at a path, with the permission bits of `mode`
+/// masked by the umask of the process.
+///
+/// The pipe is opened for reading with [`PipeReader::open`], and for writing
+/// with [`PipeWriter::open`]. Each open waits for the other end to be
+/// opened, so the two ends are opened by different tasks or processes.
+///
+/// There is no io_uring operation creating a FIFO, so the system call is
+/// made on the blocking thread pool.
+///
+/// # Errors
+///
+/// Fails with [`AlreadyExists`] if the path exists.
+///
+/// [`AlreadyExists`]: io::ErrorKind::AlreadyExists
+pub async fn mkfifo(path: impl AsRef<Path>, mode: u32) -> io::Result<()> {
+    let path = CString::new(path.as_ref().as_os_str().as_bytes())?;
+    crate::spawn_blocking(move || syscall!(mkfifo(path.as_ptr(), mode)).map(drop))
+        .await
+        .map_err(io::Error::other)?
+}
+
+// Opens a FIFO with the system call on the blocking thread pool, waiting
+// for the other end. The kernel first tries the open on the ring without
+// blocking, which does not wait for a writer, and fails with ENXIO without
+// a reader.
+async fn open_blocking(path: &Path, flags: libc::c_int) -> io::Result<OwnedFd> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    crate::spawn_blocking(move || {
+        let fd = syscall!(open(path.as_ptr(), flags | libc::O_CLOEXEC))?;
+        // Safety: the descriptor has just been opened
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Creates an anonymous pipe, returning its reading and writing ends.
+pub fn pipe() -> io::Result<(PipeReader, PipeWriter)> {
+    let mut fds = [0; 2];
+    syscall!(pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC))?;
+    Ok((
+        PipeReader::from_shared_fd(SharedFd::new(fds[0])),
+        PipeWriter::from_shared_fd(SharedFd::new(fds[1])),
+    ))
+}
+
+/// The reading end of a pipe or a FIFO.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::{self, PipeReader};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         fs::mkfifo("/tmp/events", 0o600).await?;
+///
+///         // Wait for a producer to open the FIFO
+///         let reader = PipeReader::open("/tmp/events").await?;
+///         let mut buf = vec![0; 4096];
+///         loop {
+///             let (res, b) = reader.read(buf).await;
+///             let n = res?;
+///             if n == 0 {
+///                 // The producer has closed the FIFO
+///                 break;
+///             }
+///             println!("{:?}", &b[..n]);
+///             buf = b;
+///         }
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct PipeReader {
+    inner: Socket,
+}
+
+impl PipeReader {
+    /// Opens a FIFO for reading.
+    ///
+    /// Opening a FIFO waits until it is opened for writing, so that reads do
+    /// not see the end of the data before there is a writer. The open on
+    /// the ring would not wait, so the system call is made on the blocking
+    /// thread pool. It does not block the runtime, but the FIFO must be
+    /// opened for writing by another task or process.
+    ///
+    /// If the future is dropped while the open waits, the open goes on,
+    /// counting as a reader of the FIFO and holding a thread of the pool,
+    /// until a writer opens it; the file is then closed.
+    ///
+    /// To open the FIFO without waiting for a writer, open it with
+    /// [`OpenOptions`] and the `O_NONBLOCK` custom flag, and convert the file
+    /// with [`from_file`].
+    ///
+    /// [`OpenOptions`]: crate::fs::OpenOptions
+    /// [`from_file`]: PipeReader::from_file
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<PipeReader> {
+        let fd = open_blocking(path.as_ref(), libc::O_RDONLY).await?;
+        Ok(PipeReader::from_shared_fd(SharedFd::new(fd.into_raw_fd())))
+    }
+
+    /// Converts an open file of a pipe or a FIFO into its reading end.
+    pub fn from_file(file: File) -> PipeReader {
+        PipeReader::from_shared_fd(file.into_shared_fd())
+    }
+
+    fn from_shared_fd(fd: SharedFd) -> PipeReader {
+        PipeReader {
+            inner: Socket::from_shared_fd(fd),
+        }
+    }
+
+    /// Reads some data from the pipe into the buffer, returning the original
+    /// buffer and quantity of data read.
+    ///
+    /// The read waits for data to be written to the pipe. It returns 0 at
+    /// the end of the data, once all the writers have closed the pipe.
+    pub async fn read<T: BoundedBufMut>(&self, buf: T) -> crate::BufResult<usize, T> {
+        self.inner.read(buf).await
+    }
+
+    /// Reads the data written to the pipe as a stream of chunks, into the
+    /// buffers of `ring`.
+    ///
+    /// A single multishot read is kept in flight, rather than submitting a
+    /// read for each chunk, which suits tailing a log or a stream of
+    /// events. See [`ReadChunks`] for the details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::buf::provided::BufRing;
+    /// use tokio_uring::fs::PipeReader;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let ring = BufRing::builder(0).entries(16).buf_len(4096).build()?;
+    ///         let reader = PipeReader::open("/tmp/events").await?;
+    ///         let mut chunks = reader.read_multi(&ring);
+    ///         while let Some(chunk) = chunks.next().await {
+    ///             println!("{:?}", &chunk?[..]);
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub fn read_multi(&self, ring: &BufRing) -> ReadChunks {
+        ReadChunks::new(self.inner.fd.clone(), ring)
+    }
+
+    /// Closes the pipe.
+    ///
+    /// The method completes once the close operation has completed,
+    /// guaranteeing that resources associated with the pipe have been
+    /// released.
+    pub async fn close(self) -> io::Result<()> {
+        self.inner.fd.close().await;
+        Ok(())
+    }
+}
+
+impl AsRawFd for PipeReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+/// The writing end of a pipe or a FIFO.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::PipeWriter;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         // Wait for a consumer to open the FIFO
+///         let writer = PipeWriter::open("/tmp/events").await?;
+///         let (res, _) = writer.write_all(&b"started\n"[..]).await;
+///         res?;
+///         writer.close().await?;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct PipeWriter {
+    inner: Socket,
+}
+
+impl PipeWriter {
+    /// Opens a FIFO for writing.
+    ///
+    /// Opening a FIFO waits until it is opened for reading. The open on the
+    /// ring would fail with `ENXIO` instead, so the system call is made on
+    /// the blocking thread pool. It does not block the runtime, but the
+    /// FIFO must be opened for reading by another task or process.
+    ///
+    /// If the future is dropped while the open waits, the open goes on,
+    /// counting as a writer of the FIFO and holding a thread of the pool,
+    /// until a reader opens it; the file is then closed.
+    ///
+    /// To fail instead if there is no reader, open the FIFO with
+    /// [`OpenOptions`] and the `O_NONBLOCK` custom flag, which fails with
+    /// `ENXIO`, and convert the file with [`from_file`].
+    ///
+    /// [`OpenOptions`]: crate::fs::OpenOptions
+    /// [`from_file`]: PipeWriter::from_file
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<PipeWriter> {
+        let fd = open_blocking(path.as_ref(), libc::O_WRONLY).await?;
+        Ok(PipeWriter::from_shared_fd(SharedFd::new(fd.into_raw_fd())))
+    }
+
+    /// Converts an open file of a pipe or a FIFO into its writing end.
+    pub fn from_file(file: File) -> PipeWriter {
+        PipeWriter::from_shared_fd(file.into_shared_fd())
+    }
+
+    fn from_shared_fd(fd: SharedFd) -> PipeWriter {
+        PipeWriter {
+            inner: Socket::from_shared_fd(fd),
+        }
+    }
+
+    /// Writes some data from the buffer to the pipe, returning the original
+    /// buffer and quantity of data written.
+    ///
+    /// The write waits for room in the pipe. It fails with `EPIPE` if there
+    /// are no readers.
+    pub async fn write<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<usize, T> {
+        self.inner.write(buf).await
+    }
+
+    /// Attempts to write an entire buffer to the pipe.
+    ///
+    /// This method will continuously call [`write`] until there is no more
+    /// data to be written or an error is returned.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error that [`write`] returns.
+    ///
+    /// [`write`]: Self::write
+    pub async fn write_all<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<(), T> {
+        self.inner.write_all(buf).await
+    }
+
+    /// Closes the pipe.
+    ///
+    /// The method completes once the close operation has completed,
+    /// guaranteeing that resources associated with the pipe have been
+    /// released.
+    pub async fn close(self) -> io::Result<()> {
+        self.inner.fd.close().await;
+        Ok(())
+    }
+}
+
+impl AsRawFd for PipeWriter {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
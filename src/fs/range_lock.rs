@@ -0,0 +1,131 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::ops::Range;
+use std::pin::pin;
+use tokio::sync::Notify;
+
+// The ranges of a file locked by the tasks of the runtime, and the ranges
+// waited for.
+#[derive(Default)]
+pub(crate) struct RangeLocks {
+    held: RefCell<Vec<(u64, Range<u64>)>>,
+
+    // Waiting requests in the order they were made
+    waiting: RefCell<Vec<(u64, Range<u64>)>>,
+    next_id: Cell<u64>,
+    released: Notify,
+}
+
+impl RangeLocks {
+    pub(crate) async fn lock(&self, range: Range<u64>) -> RangeGuard<'_> {
+        if let Some(guard) = self.try_lock(range.clone()) {
+            return guard;
+        }
+        let id = self.next_id();
+
+        // Removes the request if the future is dropped while waiting
+        struct Waiting<'a>(&'a RangeLocks, u64);
+        impl Drop for Waiting<'_> {
+            fn drop(&mut self) {
+                let mut waiting = self.0.waiting.borrow_mut();
+                if let Some(i) = waiting.iter().position(|(id, _)| *id == self.1) {
+                    waiting.remove(i);
+                    drop(waiting);
+                    // A request queued behind this one may be granted now
+                    self.0.released.notify_waiters();
+                }
+            }
+        }
+
+        self.waiting.borrow_mut().push((id, range.clone()));
+        let waiting = Waiting(self, id);
+        loop {
+            let mut released = pin!(self.released.notified());
+            released.as_mut().enable();
+            if self.is_grantable(id, &range) {
+                break;
+            }
+            released.await;
+        }
+        // The request is granted, and no longer waiting
+        drop(waiting);
+        self.grant(id, range)
+    }
+
+    pub(crate) fn try_lock(&self, range: Range<u64>) -> Option<RangeGuard<'_>> {
+        if overlaps(&self.held.borrow(), &range) || overlaps(&self.waiting.borrow(), &range) {
+            return None;
+        }
+        let id = self.next_id();
+        Some(self.grant(id, range))
+    }
+
+    // A request is granted if its range is not held, and not waited for by
+    // an earlier request.
+    fn is_grantable(&self, id: u64, range: &Range<u64>) -> bool {
+        let waiting = self.waiting.borrow();
+        let mut earlier = waiting.iter().take_while(|(other, _)| *other != id);
+        !overlaps(&self.held.borrow(), range) && !earlier.any(|(_, r)| intersect(r, range))
+    }
+
+    fn next_id(&self) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    fn grant(&self, id: u64, range: Range<u64>) -> RangeGuard<'_> {
+        self.held.borrow_mut().push((id, range.clone()));
+        RangeGuard {
+            locks: self,
+            id,
+            range,
+        }
+    }
+}
+
+fn overlaps(ranges: &[(u64, Range<u64>)], range: &Range<u64>) -> bool {
+    ranges.iter().any(|(_, r)| intersect(r, range))
+}
+
+// Empty ranges are treated as ranges of a single byte, so that they are
+// still exclusive with the ranges containing their offset.
+fn intersect(a: &Range<u64>, b: &Range<u64>) -> bool {
+    a.start < b.end.max(b.start.saturating_add(1)) && b.start < a.end.max(a.start.saturating_add(1))
+}
+
+/// A range of a file locked with [`File::lock_range`], unlocked when the
+/// guard is dropped.
+///
+/// [`File::lock_range`]: crate::fs::File::lock_range
+pub struct RangeGuard<'a> {
+    locks: &'a RangeLocks,
+    id: u64,
+    range: Range<u64>,
+}
+
+impl RangeGuard<'_> {
+    /// Returns the locked range.
+    pub fn range(&self) -> Range<u64> {
+        self.range.clone()
+    }
+}
+
+impl Drop for RangeGuard<'_> {
+    fn drop(&mut self) {
+        let mut held = self.locks.held.borrow_mut();
+        if let Some(i) = held.iter().position(|(id, _)| *id == self.id) {
+            held.swap_remove(i);
+        }
+        drop(held);
+        self.locks.released.notify_waiters();
+    }
+}
+
+impl fmt::Debug for RangeGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeGuard")
+            .field("range", &self.range)
+            .finish()
+    }
+}
@@ -0,0 +1,89 @@
+use crate::buf::BoundedBuf;
+use crate::fs::File;
+use std::io;
+use std::path::Path;
+
+// Capacity of the buffer for the first read, doubled whenever it fills up.
+const INITIAL_CAPACITY: usize = 8 * 1024;
+
+/// Reads the entire contents of a file into a bytes vector.
+///
+/// This is a convenience function for opening the file with [`File::open`]
+/// and reading it until the end with [`read_at`], with the buffer growing as
+/// needed.
+///
+/// # Errors
+///
+/// This function will return an error if `path` does not already exist, or
+/// the first error returned by [`read_at`].
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let contents = tokio_uring::fs::read("foo.txt").await?;
+///         println!("read {} bytes", contents.len());
+///         Ok(())
+///     })
+/// }
+/// ```
+///
+/// [`read_at`]: File::read_at
+pub async fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let file = File::open(path).await?;
+    let buf = read_to_end(&file).await?;
+    file.close().await?;
+    Ok(buf)
+}
+
+/// Reads the entire contents of a file into a string.
+///
+/// This works like [`read`], but also checks that the contents of the file
+/// are valid UTF-8.
+///
+/// # Errors
+///
+/// In addition to the errors returned by [`read`], this function will return
+/// an error of the kind [`io::ErrorKind::InvalidData`] if the contents of the
+/// file are not valid UTF-8.
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let contents = tokio_uring::fs::read_to_string("foo.txt").await?;
+///         println!("{}", contents);
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    let buf = read(path).await?;
+    String::from_utf8(buf).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    })
+}
+
+async fn read_to_end(file: &File) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(INITIAL_CAPACITY);
+
+    loop {
+        if buf.len() == buf.capacity() {
+            buf.reserve(buf.capacity());
+        }
+
+        let pos = buf.len();
+        let (res, slice) = file.read_at(buf.slice(pos..), pos as u64).await;
+        buf = slice.into_inner();
+        match res {
+            Ok(0) => return Ok(buf),
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::buf::provided::{BufRing, ProvidedBuf};
+use crate::io::{ReadMulti, SharedFd};
+use crate::runtime::driver::op::{MultiCQEStream, Op};
+use std::future::poll_fn;
+use std::io;
+
+/// Chunks of data read with a multishot read, into the buffers of a
+/// [`BufRing`].
+///
+/// Created by [`PipeReader::read_multi`] and [`Tty::read_multi`]. A single read operation is kept
+/// in flight, which completes each time data is available, taking a buffer
+/// from the ring for it. The chunks are returned by [`next`] in the order
+/// they were read.
+///
+/// The operation stops when the ring has no buffers left, and [`next`]
+/// returns an `ENOBUFS` error. Like other errors, it does not end the
+/// stream: the next call submits a new operation, which succeeds once
+/// buffers have been dropped. Reading multishot requires Linux 6.7.
+///
+/// Dropping the `ReadChunks` cancels the operation in flight. The buffers
+/// of the chunks read and not returned are given back to the ring.
+///
+/// [`PipeReader::read_multi`]: super::PipeReader::read_multi
+/// [`Tty::read_multi`]: crate::io::Tty::read_multi
+/// [`next`]: ReadChunks::next
+pub struct ReadChunks {
+    fd: SharedFd,
+    ring: BufRing,
+    op: Option<Op<ReadMulti, MultiCQEStream>>,
+    done: bool,
+}
+
+impl ReadChunks {
+    pub(crate) fn new(fd: SharedFd, ring: &BufRing) -> ReadChunks {
+        ReadChunks {
+            fd,
+            ring: ring.clone(),
+            op: None,
+            done: false,
+        }
+    }
+
+    /// Returns the next chunk of data, or `None` at the end of the data.
+    ///
+    /// The first call submits the read operation.
+    pub async fn next(&mut self) -> Option<io::Result<ProvidedBuf>> {
+        if self.done {
+            return None;
+        }
+        let op = match &mut self.op {
+            Some(op) => op,
+            None => match Op::read_multi(&self.fd, &self.ring) {
+                Ok(op) => self.op.insert(op),
+                Err(e) => return Some(Err(e)),
+            },
+        };
+
+        let res = poll_fn(|cx| op.poll_next(cx)).await;
+        // The data of the operation is taken with the final completion,
+        // after which a new operation is needed
+        if op.data.is_none() {
+            self.op = None;
+        }
+        match res {
+            Some(Ok(Some(buf))) => Some(Ok(buf)),
+            Some(Err(e)) => Some(Err(e)),
+            Some(Ok(None)) | None => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl Drop for ReadChunks {
+    fn drop(&mut self) {
+        if let Some(op) = &self.op {
+            op.cancel();
+        }
+    }
+}
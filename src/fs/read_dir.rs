@@ -0,0 +1,210 @@
+use crate::fs::{Dir, Metadata};
+use crate::io::SharedFd;
+use crate::runtime::driver::op::Op;
+use std::collections::VecDeque;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::future::{poll_fn, Future};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::task::JoinHandle;
+
+// Size of the buffer the entries are read into by a `getdents64` call.
+const DIRENT_BUF_SIZE: usize = 32 * 1024;
+
+// Offsets of the fields of `struct linux_dirent64`.
+const D_RECLEN: usize = 16;
+const D_NAME: usize = 19;
+
+/// Returns the entries of a directory.
+///
+/// The directory is opened with [`Dir::open`]. There is no io_uring
+/// operation listing a directory, so the entries are read in batches with
+/// the `getdents64` system call on the blocking thread pool. The entries
+/// `.` and `..` are skipped.
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let mut entries = tokio_uring::fs::read_dir("data").await?;
+///         while let Some(entry) = entries.next_entry().await? {
+///             let metadata = entry.metadata().await?;
+///             println!("{}: {} bytes", entry.path().display(), metadata.len());
+///         }
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn read_dir(path: impl AsRef<Path>) -> io::Result<ReadDir> {
+    let path = path.as_ref();
+    let dir = Dir::open(path).await?;
+    ReadDir::new(dir.into_shared_fd(), path.to_owned())
+}
+
+/// The entries of a directory, returned by [`read_dir`].
+pub struct ReadDir {
+    // The directory the entries are looked up relative to.
+    dir: SharedFd,
+    path: PathBuf,
+
+    // Duplicate of the descriptor for the reads on the blocking thread
+    // pool, which remains open if `ReadDir` is dropped during a read.
+    fd: Arc<OwnedFd>,
+
+    // Names read and not returned yet.
+    names: VecDeque<OsString>,
+    read: Option<JoinHandle<io::Result<Vec<OsString>>>>,
+    done: bool,
+}
+
+impl ReadDir {
+    fn new(dir: SharedFd, path: PathBuf) -> io::Result<ReadDir> {
+        let fd = syscall!(fcntl(dir.raw_fd(), libc::F_DUPFD_CLOEXEC, 0))?;
+        Ok(ReadDir {
+            dir,
+            path,
+            // Safety: the descriptor has just been created
+            fd: Arc::new(unsafe { OwnedFd::from_raw_fd(fd) }),
+            names: VecDeque::new(),
+            read: None,
+            done: false,
+        })
+    }
+
+    /// Returns the next entry of the directory, or `None` once all the
+    /// entries have been returned.
+    ///
+    /// If the future is dropped before it resolves, the read in progress
+    /// is resumed by the next call.
+    pub async fn next_entry(&mut self) -> io::Result<Option<DirEntry>> {
+        poll_fn(|cx| self.poll_next_entry(cx)).await
+    }
+
+    fn poll_next_entry(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<DirEntry>>> {
+        loop {
+            if let Some(name) = self.names.pop_front() {
+                return Poll::Ready(Ok(Some(DirEntry {
+                    dir: self.dir.clone(),
+                    path: self.path.join(&name),
+                    name,
+                })));
+            }
+            if self.done {
+                return Poll::Ready(Ok(None));
+            }
+
+            let read = match &mut self.read {
+                Some(read) => read,
+                None => {
+                    let fd = self.fd.clone();
+                    self.read
+                        .insert(crate::spawn_blocking(move || getdents(fd.as_raw_fd())))
+                }
+            };
+            let res = ready!(Pin::new(read).poll(cx));
+            self.read = None;
+            let names = res.map_err(io::Error::other)??;
+            self.done = names.is_empty();
+            self.names.extend(names);
+        }
+    }
+}
+
+/// Yields the entries of [`ReadDir::next_entry`].
+///
+/// Requires the `stream` feature.
+#[cfg(feature = "stream")]
+impl futures_core::Stream for ReadDir {
+    type Item = io::Result<DirEntry>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .poll_next_entry(cx)
+            .map(|res| res.transpose())
+    }
+}
+
+impl fmt::Debug for ReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadDir").field("path", &self.path).finish()
+    }
+}
+
+// Reads the next batch of entry names, skipping `.` and `..`. Returns no
+// names at the end of the directory.
+fn getdents(fd: RawFd) -> io::Result<Vec<OsString>> {
+    let mut buf = vec![0u8; DIRENT_BUF_SIZE];
+    let mut names = Vec::new();
+    // A batch of only `.` and `..` is not the end
+    while names.is_empty() {
+        let n = syscall!(syscall(
+            libc::SYS_getdents64,
+            fd,
+            buf.as_mut_ptr(),
+            buf.len()
+        ))? as usize;
+        if n == 0 {
+            break;
+        }
+        let mut pos = 0;
+        while pos < n {
+            let reclen = u16::from_ne_bytes([buf[pos + D_RECLEN], buf[pos + D_RECLEN + 1]]);
+            // The name is terminated, and padded, with NUL bytes
+            let name = &buf[pos + D_NAME..pos + reclen as usize];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            if name != b"." && name != b".." {
+                names.push(OsStr::from_bytes(name).to_owned());
+            }
+            pos += reclen as usize;
+        }
+    }
+    Ok(names)
+}
+
+/// An entry of a directory, returned by [`ReadDir`].
+pub struct DirEntry {
+    dir: SharedFd,
+    path: PathBuf,
+    name: OsString,
+}
+
+impl DirEntry {
+    /// Returns the path of the entry: the path the directory was read
+    /// from, joined with the name of the entry.
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Returns the name of the entry.
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// Queries the metadata of the entry.
+    ///
+    /// The entry is looked up relative to the directory it has been read
+    /// from, which remains valid if the directory has been moved. Like
+    /// [`std::fs::DirEntry::metadata`], symbolic links are not followed.
+    pub async fn metadata(&self) -> io::Result<Metadata> {
+        let statx = Op::statx(
+            Some(&self.dir),
+            Path::new(&self.name),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )?
+        .await?;
+        Ok(Metadata::from_statx(statx))
+    }
+}
+
+impl fmt::Debug for DirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DirEntry").field(&self.path).finish()
+    }
+}
@@ -0,0 +1,332 @@
+use crate::buf::{BoundedBuf, IoBuf, Slice};
+use crate::fs::File;
+use crate::io::{Read, SharedFd, Write};
+use crate::runtime::driver::op::Op;
+use std::collections::VecDeque;
+use std::io;
+
+const DEFAULT_DEPTH: usize = 4;
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads a file sequentially, keeping reads in flight ahead of the consumer.
+///
+/// The reader submits up to [`depth`] reads of [`chunk_size`] bytes each
+/// for the data following the chunk last returned by [`read`], so that
+/// the latency of the device is overlapped with the processing of the data.
+/// This benefits workloads scanning large files, such as compactions or
+/// backups.
+///
+/// The reader keeps the file open until it is dropped, even if the [`File`]
+/// it was created from is closed.
+///
+/// [`depth`]: SequentialReader::depth
+/// [`chunk_size`]: SequentialReader::chunk_size
+/// [`read`]: SequentialReader::read
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::{File, SequentialReader};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let file = File::open("data.bin").await?;
+///
+///         let mut reader = SequentialReader::new(&file, 0);
+///         reader.depth(8).chunk_size(1024 * 1024);
+///
+///         let mut total = 0;
+///         while let Some(chunk) = reader.read().await? {
+///             total += chunk.len();
+///         }
+///         println!("read {} bytes", total);
+///
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct SequentialReader {
+    fd: SharedFd,
+
+    // Reads in flight with their requested lengths, in the order of their
+    // offsets.
+    in_flight: VecDeque<(Op<Read<Vec<u8>>>, usize)>,
+
+    // Offset of the next chunk to return.
+    pos: u64,
+
+    // Offset of the next read to submit.
+    submit_pos: u64,
+
+    depth: usize,
+    chunk_size: usize,
+    eof: bool,
+}
+
+impl SequentialReader {
+    /// Creates a reader returning the contents of the file starting at the
+    /// offset `pos`.
+    ///
+    /// No reads are submitted until the first call to [`read`].
+    ///
+    /// [`read`]: SequentialReader::read
+    pub fn new(file: &File, pos: u64) -> SequentialReader {
+        SequentialReader {
+            fd: file.shared_fd().clone(),
+            in_flight: VecDeque::new(),
+            pos,
+            submit_pos: pos,
+            depth: DEFAULT_DEPTH,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            eof: false,
+        }
+    }
+
+    /// Sets the maximum number of reads kept in flight.
+    ///
+    /// The default is 4. Values less than 1 are treated as 1.
+    pub fn depth(&mut self, depth: usize) -> &mut Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Sets the number of bytes requested by each read.
+    ///
+    /// The default is 64 KiB. The change applies to reads submitted after
+    /// the call. Values less than 1 are treated as 1.
+    pub fn chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Returns the offset in the file of the next chunk returned by
+    /// [`read`](SequentialReader::read).
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the next chunk of the file, or `None` at the end of the file.
+    ///
+    /// Chunks have the configured chunk size, except near the end of the
+    /// file or when the kernel returns fewer bytes than requested.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the read for the chunk. The read is retried on
+    /// the next call.
+    pub async fn read(&mut self) -> io::Result<Option<Vec<u8>>> {
+        // The reads filling the window are submitted at once, rather than
+        // as the flush policy would
+        crate::runtime::submit_together(self.next_chunk()).await
+    }
+
+    async fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.eof {
+            return Ok(None);
+        }
+
+        self.fill()?;
+
+        // The read is kept in flight until it completes, so that the chunk
+        // is returned by the next call if this future is dropped.
+        let (op, len) = self.in_flight.front_mut().expect("no read in flight");
+        let len = *len;
+        let (res, buf) = op.await;
+        self.in_flight.pop_front();
+
+        match res {
+            Ok(0) => {
+                // Reads past the end of the file will return nothing as well.
+                self.in_flight.clear();
+                self.eof = true;
+                Ok(None)
+            }
+            Ok(n) => {
+                self.pos += n as u64;
+                if n < len {
+                    // The read was short, so the reads in flight do not
+                    // continue where it ended. Discard them and read again.
+                    self.in_flight.clear();
+                    self.submit_pos = self.pos;
+                }
+                Ok(Some(buf))
+            }
+            Err(e) => {
+                self.in_flight.clear();
+                self.submit_pos = self.pos;
+                Err(e)
+            }
+        }
+    }
+
+    // Submits reads until `depth` are in flight.
+    fn fill(&mut self) -> io::Result<()> {
+        while self.in_flight.len() < self.depth {
+            let buf = Vec::with_capacity(self.chunk_size);
+            // The whole capacity of the buffer is requested.
+            let len = buf.capacity();
+            let op = Op::read_at(&self.fd, buf, self.submit_pos)?;
+            self.submit_pos += len as u64;
+            self.in_flight.push_back((op, len));
+        }
+        Ok(())
+    }
+}
+
+/// Writes buffers sequentially to a file, keeping writes in flight.
+///
+/// Each buffer passed to [`write`] is written following the previous one.
+/// Up to [`depth`] writes are kept in flight; [`write`] waits for the
+/// oldest write to complete when the limit is reached. Partial writes are
+/// completed before the next write is waited for. [`flush`] waits for all
+/// writes, and optionally syncs the file to disk.
+///
+/// The writer keeps the file open until it is dropped, even if the [`File`]
+/// it was created from is closed. Writes which have not been flushed when
+/// the writer is dropped are still performed, but their errors are lost.
+///
+/// [`write`]: SequentialWriter::write
+/// [`depth`]: SequentialWriter::depth
+/// [`flush`]: SequentialWriter::flush
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::{File, SequentialWriter};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let file = File::create("data.bin").await?;
+///
+///         let mut writer = SequentialWriter::new(&file, 0);
+///         writer.depth(8).sync_on_flush(true);
+///
+///         for i in 0..1024 {
+///             writer.write(vec![i as u8; 64 * 1024]).await?;
+///         }
+///         writer.flush().await?;
+///
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct SequentialWriter<B: IoBuf = Vec<u8>> {
+    fd: SharedFd,
+
+    // Writes in flight with their offsets, in the order of submission.
+    in_flight: VecDeque<(Op<Write<Slice<B>>>, u64)>,
+
+    // Offset of the next write.
+    pos: u64,
+
+    depth: usize,
+    sync_on_flush: bool,
+}
+
+impl<B: IoBuf> SequentialWriter<B> {
+    /// Creates a writer writing to the file starting at the offset `pos`.
+    pub fn new(file: &File, pos: u64) -> SequentialWriter<B> {
+        SequentialWriter {
+            fd: file.shared_fd().clone(),
+            in_flight: VecDeque::new(),
+            pos,
+            depth: DEFAULT_DEPTH,
+            sync_on_flush: false,
+        }
+    }
+
+    /// Sets the maximum number of writes kept in flight.
+    ///
+    /// The default is 4. Values less than 1 are treated as 1.
+    pub fn depth(&mut self, depth: usize) -> &mut Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Sets whether [`flush`](SequentialWriter::flush) syncs the file to
+    /// disk, as with [`File::sync_all`], once all writes have completed.
+    ///
+    /// The default is `false`.
+    pub fn sync_on_flush(&mut self, sync: bool) -> &mut Self {
+        self.sync_on_flush = sync;
+        self
+    }
+
+    /// Returns the offset in the file at which the next buffer is written.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Submits a write of the buffer following the previously written data.
+    ///
+    /// If the maximum number of writes is in flight, this waits for the
+    /// oldest write to complete first.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of a previously submitted write, in which case the
+    /// buffer is not written. The contents of the file past the failed write
+    /// are unspecified.
+    pub async fn write(&mut self, buf: B) -> io::Result<()> {
+        while self.in_flight.len() >= self.depth {
+            self.complete_oldest().await?;
+        }
+
+        let len = buf.bytes_init();
+        // The slice ends with the data, so that a partial write is
+        // completed up to its end rather than the capacity of the buffer
+        let op = Op::write_at(&self.fd, buf.slice(..len), self.pos)?;
+        self.in_flight.push_back((op, self.pos));
+        self.pos += len as u64;
+        Ok(())
+    }
+
+    /// Waits for all submitted writes to complete, then syncs the file if
+    /// [`sync_on_flush`](SequentialWriter::sync_on_flush) is set.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        while !self.in_flight.is_empty() {
+            self.complete_oldest().await?;
+        }
+
+        if self.sync_on_flush {
+            Op::fsync(&self.fd)?.await?;
+        }
+        Ok(())
+    }
+
+    // Waits for the oldest write, writing the rest of the buffer if the
+    // write was partial. The write is kept in flight until it has been
+    // completed, so that a dropped future leaves no gap in the file.
+    async fn complete_oldest(&mut self) -> io::Result<()> {
+        loop {
+            let (op, pos) = self.in_flight.front_mut().expect("no write in flight");
+            let (res, slice) = (&mut *op).await;
+            let rest = match res {
+                Ok(0) => Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                )),
+                Ok(n) if slice.begin() + n == slice.end() => Ok(None),
+                Ok(n) => {
+                    let begin = slice.begin() + n;
+                    let end = slice.end();
+                    *pos += n as u64;
+                    Op::write_at(&self.fd, slice.into_inner().slice(begin..end), *pos).map(Some)
+                }
+                Err(e) => Err(e),
+            };
+
+            match rest {
+                Ok(Some(rest)) => *op = rest,
+                Ok(None) => {
+                    self.in_flight.pop_front();
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.in_flight.pop_front();
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use crate::io::{PollMulti, SharedFd};
+use crate::runtime::driver::op::{MultiCQEStream, Op};
+use std::collections::HashMap;
+use std::ffi::{CString, OsStr};
+use std::future::poll_fn;
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::task::{Context, Poll};
+
+// Changes reported for watched paths.
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_ATTRIB
+    | libc::IN_DELETE
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_MOVE_SELF;
+
+// Size of the fixed part of `struct inotify_event`.
+const EVENT_HEADER_SIZE: usize = mem::size_of::<libc::inotify_event>();
+
+// Room for at least one event with the longest possible name.
+const EVENT_BUF_SIZE: usize = 4096;
+
+/// Watches files and directories for changes.
+///
+/// The watcher is backed by an inotify instance. A multishot poll on the
+/// ring is kept in flight, completing each time the instance has events,
+/// which are then read without blocking. Events are received with
+/// [`next_event`], or, with the `stream` feature, by using the watcher as a
+/// [`Stream`].
+///
+/// [`next_event`]: Watcher::next_event
+/// [`Stream`]: https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let mut watcher = tokio_uring::fs::watch("config")?;
+///
+///         loop {
+///             let event = watcher.next_event().await?;
+///             println!("{:?}: {}", event.kind(), event.path().display());
+///         }
+///     })
+/// }
+/// ```
+pub struct Watcher {
+    fd: SharedFd,
+
+    // Paths of the watches, by watch descriptor.
+    watches: HashMap<RawFd, PathBuf>,
+
+    // Events read from the inotify instance. Bytes before `pos` have
+    // already been returned.
+    buf: Vec<u8>,
+    pos: usize,
+
+    // The poll in flight, completing when there are events to read.
+    poll: Option<Op<PollMulti, MultiCQEStream>>,
+}
+
+/// An event reported by a [`Watcher`].
+#[derive(Clone, Debug)]
+pub struct WatchEvent {
+    path: PathBuf,
+    mask: u32,
+    cookie: u32,
+}
+
+/// The kind of change reported by a [`WatchEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WatchEventKind {
+    /// A file or directory was created in a watched directory.
+    Create,
+    /// A file was written to.
+    Modify,
+    /// A file opened for writing was closed.
+    CloseWrite,
+    /// Metadata, such as permissions or timestamps, changed.
+    Attrib,
+    /// A file or directory was removed, or the watched path itself was.
+    Remove,
+    /// A file or directory was moved out of a watched directory, or the
+    /// watched path itself was moved.
+    MovedFrom,
+    /// A file or directory was moved into a watched directory.
+    MovedTo,
+    /// Events were lost because the event queue of the kernel overflowed.
+    Overflow,
+}
+
+/// Creates a [`Watcher`] watching the path for changes.
+///
+/// If the path is a directory, changes to the entries of the directory are
+/// reported as well. Subdirectories are not watched recursively; more paths
+/// can be added to the watcher with [`Watcher::add`].
+pub fn watch(path: impl AsRef<Path>) -> io::Result<Watcher> {
+    let mut watcher = Watcher::new()?;
+    watcher.add(path)?;
+    Ok(watcher)
+}
+
+impl Watcher {
+    /// Creates a watcher which does not watch any paths yet.
+    pub fn new() -> io::Result<Watcher> {
+        // Once the events are drained, a read fails with EAGAIN rather
+        // than blocking, and the poll on the ring waits for more
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Watcher {
+            fd: SharedFd::new(fd),
+            watches: HashMap::new(),
+            buf: Vec::with_capacity(EVENT_BUF_SIZE),
+            pos: 0,
+            poll: None,
+        })
+    }
+
+    /// Starts watching another path for changes.
+    pub fn add(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let cpath = CString::new(path.as_os_str().as_bytes())?;
+
+        let wd = unsafe { libc::inotify_add_watch(self.fd.raw_fd(), cpath.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        self.watches.insert(wd, path.to_owned());
+        Ok(())
+    }
+
+    /// Waits for the next change to the watched paths.
+    ///
+    /// If the future is dropped before it resolves, no event is lost: the
+    /// events read in the meantime are returned by the next call.
+    pub async fn next_event(&mut self) -> io::Result<WatchEvent> {
+        poll_fn(|cx| self.poll_next_event(cx)).await
+    }
+
+    fn poll_next_event(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<WatchEvent>> {
+        loop {
+            if let Some(event) = self.parse_event() {
+                return Poll::Ready(Ok(event));
+            }
+
+            self.buf.resize(EVENT_BUF_SIZE, 0);
+            let res = syscall!(read(
+                self.fd.raw_fd(),
+                self.buf.as_mut_ptr() as *mut libc::c_void,
+                self.buf.len()
+            ));
+            match res {
+                Ok(n) => {
+                    self.buf.truncate(n as usize);
+                    self.pos = 0;
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => self.buf.clear(),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+
+            let poll = match &mut self.poll {
+                Some(poll) => poll,
+                None => match Op::poll_multi(&self.fd, libc::POLLIN as u32) {
+                    Ok(poll) => self.poll.insert(poll),
+                    Err(e) => return Poll::Ready(Err(e)),
+                },
+            };
+            let res = ready!(poll.poll_next(cx));
+            // The data of the operation is taken with the final completion,
+            // after which a new poll is needed
+            if poll.data.is_none() {
+                self.poll = None;
+            }
+            if let Some(Err(e)) = res {
+                return Poll::Ready(Err(e));
+            }
+        }
+    }
+
+    // Returns the next buffered event, skipping those which are not reported.
+    fn parse_event(&mut self) -> Option<WatchEvent> {
+        while self.pos + EVENT_HEADER_SIZE <= self.buf.len() {
+            let header = &self.buf[self.pos..self.pos + EVENT_HEADER_SIZE];
+            let field = |i: usize| [header[i], header[i + 1], header[i + 2], header[i + 3]];
+            let wd = i32::from_ne_bytes(field(0));
+            let mask = u32::from_ne_bytes(field(4));
+            let cookie = u32::from_ne_bytes(field(8));
+            let len = u32::from_ne_bytes(field(12)) as usize;
+
+            let name_start = self.pos + EVENT_HEADER_SIZE;
+            // The name is padded with NUL bytes.
+            let name = &self.buf[name_start..name_start + len];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(len)];
+            self.pos = name_start + len;
+
+            if mask & libc::IN_IGNORED != 0 {
+                // The watch has been removed, e.g. because the path was deleted.
+                self.watches.remove(&wd);
+                continue;
+            }
+
+            let path = match self.watches.get(&wd) {
+                Some(path) if name.is_empty() => path.clone(),
+                Some(path) => path.join(OsStr::from_bytes(name)),
+                None => PathBuf::new(),
+            };
+
+            return Some(WatchEvent { path, mask, cookie });
+        }
+
+        None
+    }
+}
+
+/// Yields the events of [`Watcher::next_event`]. The stream does not end.
+///
+/// Requires the `stream` feature.
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Watcher {
+    type Item = io::Result<WatchEvent>;
+
+    fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next_event(cx).map(Some)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        if let Some(poll) = &self.poll {
+            poll.cancel();
+        }
+    }
+}
+
+impl AsRawFd for Watcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.raw_fd()
+    }
+}
+
+impl WatchEvent {
+    /// Returns the path of the changed file or directory.
+    ///
+    /// The path is empty for [`WatchEventKind::Overflow`] events.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the kind of the change.
+    pub fn kind(&self) -> WatchEventKind {
+        let mask = self.mask;
+        if mask & libc::IN_Q_OVERFLOW != 0 {
+            WatchEventKind::Overflow
+        } else if mask & libc::IN_CREATE != 0 {
+            WatchEventKind::Create
+        } else if mask & libc::IN_MODIFY != 0 {
+            WatchEventKind::Modify
+        } else if mask & libc::IN_CLOSE_WRITE != 0 {
+            WatchEventKind::CloseWrite
+        } else if mask & libc::IN_ATTRIB != 0 {
+            WatchEventKind::Attrib
+        } else if mask & (libc::IN_DELETE | libc::IN_DELETE_SELF) != 0 {
+            WatchEventKind::Remove
+        } else if mask & libc::IN_MOVED_TO != 0 {
+            WatchEventKind::MovedTo
+        } else {
+            WatchEventKind::MovedFrom
+        }
+    }
+
+    /// Returns `true` if the event concerns a directory.
+    pub fn is_dir(&self) -> bool {
+        self.mask & libc::IN_ISDIR != 0
+    }
+
+    /// Returns the cookie associating the [`WatchEventKind::MovedFrom`] and
+    /// [`WatchEventKind::MovedTo`] events of the same rename, or zero for
+    /// other events.
+    pub fn cookie(&self) -> u32 {
+        self.cookie
+    }
+}
@@ -0,0 +1,107 @@
+use crate::buf::BoundedBuf;
+use crate::fs::{remove_file, rename, sync_dir, File, OpenOptions};
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Writes a buffer as the entire contents of a file.
+///
+/// This is a convenience function for creating the file with
+/// [`File::create`] and writing the buffer to it with [`write_all_at`].
+/// The file is created if it does not exist, and its contents are replaced
+/// if it does.
+///
+/// # Errors
+///
+/// This function will return an error if the file cannot be created, or the
+/// first error returned by [`write_all_at`].
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         tokio_uring::fs::write("foo.txt", b"Hello, world!".as_slice()).await?;
+///         Ok(())
+///     })
+/// }
+/// ```
+///
+/// [`write_all_at`]: File::write_all_at
+pub async fn write<T: BoundedBuf>(path: impl AsRef<Path>, buf: T) -> io::Result<()> {
+    let file = File::create(path).await?;
+    let (res, _) = file.write_all_at(buf, 0).await;
+    res?;
+    file.close().await
+}
+
+/// Writes a buffer as the entire contents of a file, atomically and
+/// durably.
+///
+/// The data is written to a new temporary file in the same directory, which
+/// is synced to disk and renamed over `path`, and the directory is synced
+/// to make the rename durable. If the system crashes or the write fails,
+/// the file holds either its previous or its new contents, never a part of
+/// them. This is the sequence needed to replace a configuration or a state
+/// file safely.
+///
+/// The file is created with the default permissions, like with
+/// [`File::create`]. The temporary file is named after the file, prefixed
+/// with a dot and suffixed with `.tmp` and a unique number.
+///
+/// # Errors
+///
+/// Returns the first error of creating, writing, syncing or renaming the
+/// temporary file, or of syncing the directory. The temporary file is
+/// removed if it has not been renamed. `path` must name a file, rather
+/// than end with `..` or a root.
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         tokio_uring::fs::write_atomic("app.conf", b"threads = 8\n".as_slice()).await?;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn write_atomic<T: BoundedBuf>(path: impl AsRef<Path>, buf: T) -> io::Result<()> {
+    let path = path.as_ref();
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path does not name a file"))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // Unique among the writes of the process, and between processes
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(format!(
+        ".tmp.{}.{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp_path = dir.join(tmp_name);
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .await?;
+    let (res, _) = file.write_all_sync_at(buf, 0).await;
+    let closed = file.close().await;
+    let res = match res.and(closed) {
+        Ok(()) => rename(&tmp_path, path).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = res {
+        let _ = remove_file(&tmp_path).await;
+        return Err(e);
+    }
+    sync_dir(dir).await
+}
@@ -43,8 +43,7 @@ impl Completable for Accept {
 
     fn complete(self, cqe: op::CqeResult) -> Self::Output {
         let fd = cqe.result?;
-        let fd = SharedFd::new(fd as i32);
-        let socket = Socket { fd };
+        let socket = Socket::from_shared_fd(SharedFd::new(fd as i32));
         let (_, addr) = unsafe {
             socket2::SockAddr::init(move |addr_storage, len| {
                 *addr_storage = self.socketaddr.0.to_owned();
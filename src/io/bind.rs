@@ -0,0 +1,79 @@
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::driver::SqeHeader;
+use crate::runtime::CONTEXT;
+use socket2::SockAddr;
+use std::io;
+
+// Opcodes of Linux 6.11, which the io-uring crate does not define yet.
+pub(crate) const IORING_OP_BIND: u8 = 56;
+pub(crate) const IORING_OP_LISTEN: u8 = 57;
+
+/// Bind a socket
+pub(crate) struct Bind {
+    #[allow(dead_code)]
+    fd: SharedFd,
+    socket_addr: Box<SockAddr>,
+}
+
+impl Op<Bind> {
+    /// Submit a request to bind a socket to an address.
+    pub(crate) fn bind(fd: &SharedFd, socket_addr: SockAddr) -> io::Result<Op<Bind>> {
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                Bind {
+                    fd: fd.clone(),
+                    socket_addr: Box::new(socket_addr),
+                },
+                |bind| {
+                    // The length of the address is passed in `addr2`, which
+                    // shares the place of the offset
+                    SqeHeader::build(
+                        IORING_OP_BIND,
+                        bind.fd.raw_fd(),
+                        bind.socket_addr.len() as u64,
+                        bind.socket_addr.as_ptr() as u64,
+                        0,
+                    )
+                },
+            )
+        })
+    }
+}
+
+impl Completable for Bind {
+    type Output = io::Result<()>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        cqe.result.map(|_| ())
+    }
+}
+
+/// Listen for connections on a socket
+pub(crate) struct Listen {
+    #[allow(dead_code)]
+    fd: SharedFd,
+}
+
+impl Op<Listen> {
+    /// Submit a request to listen for connections, with a queue of up to
+    /// `backlog` pending connections.
+    pub(crate) fn listen(fd: &SharedFd, backlog: libc::c_int) -> io::Result<Op<Listen>> {
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                Listen { fd: fd.clone() },
+                |listen| {
+                    SqeHeader::build(IORING_OP_LISTEN, listen.fd.raw_fd(), 0, 0, backlog as u32)
+                },
+            )
+        })
+    }
+}
+
+impl Completable for Listen {
+    type Output = io::Result<()>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        cqe.result.map(|_| ())
+    }
+}
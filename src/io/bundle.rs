@@ -0,0 +1,121 @@
+use crate::buf::provided::{BufRing, ProvidedBuf, SendRing};
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::driver::SqeHeader;
+use crate::runtime::CONTEXT;
+use io_uring::opcode;
+use std::io;
+
+// Flag of Linux 6.10 in the `ioprio` field of a send or receive, which the
+// io-uring crate does not define yet. With buffer selection, the operation
+// uses as many buffers of the group as it needs, rather than one.
+const IORING_RECVSEND_BUNDLE: u16 = 1 << 4;
+
+/// Receive into as many buffers selected from a buffer ring as the data
+/// available fills
+pub(crate) struct RecvBundle {
+    /// Holds a strong ref to the FD, preventing the file from being closed
+    /// while the operation is in-flight.
+    #[allow(dead_code)]
+    fd: SharedFd,
+
+    ring: BufRing,
+}
+
+impl Op<RecvBundle> {
+    /// Submit a receive on a socket, completing with the data received into
+    /// one or more buffers of `ring`.
+    pub(crate) fn recv_bundle(fd: &SharedFd, ring: &BufRing) -> io::Result<Op<RecvBundle>> {
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                RecvBundle {
+                    fd: fd.clone(),
+                    ring: ring.clone(),
+                },
+                |recv| {
+                    // A length of 0 receives up to the size of the buffers
+                    // the ring has available
+                    let sqe = SqeHeader::build(opcode::Recv::CODE, fd.raw_fd(), 0, 0, 0);
+                    let sqe = SqeHeader::set_buf_group(sqe, recv.ring.bgid());
+                    SqeHeader::set_ioprio(sqe, IORING_RECVSEND_BUNDLE)
+                },
+            )
+        })
+    }
+}
+
+impl RecvBundle {
+    /// Gives the buffers of a completion the receive has been dropped
+    /// before returning back to the ring, keeping the head of the ring in
+    /// step with the kernel.
+    pub(crate) fn discard(&self, cqe: &CqeResult) {
+        if let Ok(n) = cqe.result {
+            drop(self.ring.take_bundle(cqe.flags, n as usize));
+        }
+    }
+}
+
+impl Completable for RecvBundle {
+    /// The buffers filled, in the order of the data, or none at the end of
+    /// the data
+    type Output = io::Result<Vec<ProvidedBuf>>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        // A failed receive leaves the buffers it has selected in the ring
+        let n = cqe.result?;
+        Ok(self.ring.take_bundle(cqe.flags, n as usize))
+    }
+}
+
+/// Send the buffers queued in a ring
+pub(crate) struct SendBundle {
+    /// Holds a strong ref to the FD, preventing the file from being closed
+    /// while the operation is in-flight.
+    #[allow(dead_code)]
+    fd: SharedFd,
+
+    ring: SendRing,
+}
+
+impl Op<SendBundle> {
+    /// Submit a send on a socket of the buffers queued in `ring`.
+    pub(crate) fn send_bundle(fd: &SharedFd, ring: &SendRing) -> io::Result<Op<SendBundle>> {
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                SendBundle {
+                    fd: fd.clone(),
+                    ring: ring.clone(),
+                },
+                |send| {
+                    // A length of 0 sends all of the buffers queued
+                    let sqe = SqeHeader::build(opcode::Send::CODE, fd.raw_fd(), 0, 0, 0);
+                    let sqe = SqeHeader::set_buf_group(sqe, send.ring.bgid());
+                    SqeHeader::set_ioprio(sqe, IORING_RECVSEND_BUNDLE)
+                },
+            )
+        })
+    }
+}
+
+impl SendBundle {
+    /// Frees the buffers consumed by a completion the send has been
+    /// dropped before returning, keeping the head of the ring in step with
+    /// the kernel.
+    pub(crate) fn discard(&self, cqe: &CqeResult) {
+        if let Ok(n) = cqe.result {
+            self.ring.complete_send(n as usize);
+        }
+    }
+}
+
+impl Completable for SendBundle {
+    /// The number of bytes sent, and the data of the last buffer the send
+    /// has consumed without sending it in full
+    type Output = io::Result<(usize, Option<Vec<u8>>)>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        // A failed send leaves the buffers it has selected in the ring
+        let n = cqe.result? as usize;
+        Ok((n, self.ring.complete_send(n)))
+    }
+}
@@ -0,0 +1,142 @@
+use crate::buf::BoundedBuf;
+use crate::io::Socket;
+use std::future::{poll_fn, Future};
+use std::io;
+use std::net::Shutdown;
+use std::pin::pin;
+use std::task::Poll;
+
+// Size of each of the buffers used in a direction of the copy.
+const BUF_SIZE: usize = 64 * 1024;
+
+/// A connected stream socket, which data can be copied from and to.
+///
+/// This trait is sealed: it is implemented for [`TcpStream`] and
+/// [`UnixStream`], and cannot be implemented outside of this crate.
+///
+/// [`TcpStream`]: crate::net::TcpStream
+/// [`UnixStream`]: crate::net::UnixStream
+pub trait Duplex: sealed::Sealed {}
+
+// The trait is public, so that it can bound `Duplex`, but is not nameable
+// outside of the crate.
+#[allow(private_interfaces)]
+pub(crate) mod sealed {
+    pub trait Sealed {
+        fn socket(&self) -> &crate::io::Socket;
+    }
+}
+
+impl<T: sealed::Sealed> Duplex for T {}
+
+/// Copies data in both directions between `a` and `b` until both directions
+/// reach the end of the stream, returning the number of bytes copied from
+/// `a` to `b` and from `b` to `a`.
+///
+/// The directions are copied concurrently. In each direction, the next read
+/// from the source is in flight while the data previously read is written
+/// to the destination. When a direction reaches the end of the stream, the
+/// write side of its destination is shut down, so that half-closed
+/// connections are proxied faithfully, and the other direction goes on.
+///
+/// # Errors
+///
+/// Returns the first error of a read, write or shutdown in either
+/// direction. The copy stops at the error, and the data read and not yet
+/// written is lost.
+///
+/// # Examples
+///
+/// A TCP proxy:
+///
+/// ```no_run
+/// use tokio_uring::net::{TcpListener, TcpStream};
+///
+/// tokio_uring::start(async {
+///     let listener = TcpListener::bind("127.0.0.1:8080".parse().unwrap()).unwrap();
+///
+///     loop {
+///         let (client, _) = listener.accept().await.unwrap();
+///         tokio_uring::spawn(async move {
+///             let server = TcpStream::connect("127.0.0.1:80".parse().unwrap()).await?;
+///             let (sent, received) = tokio_uring::io::copy_bidirectional(&client, &server).await?;
+///             println!("sent {} bytes, received {} bytes", sent, received);
+///             Ok::<_, std::io::Error>(())
+///         });
+///     }
+/// });
+/// ```
+pub async fn copy_bidirectional<A, B>(a: &A, b: &B) -> io::Result<(u64, u64)>
+where
+    A: Duplex + ?Sized,
+    B: Duplex + ?Sized,
+{
+    let mut a_to_b = pin!(copy(a.socket(), b.socket()));
+    let mut b_to_a = pin!(copy(b.socket(), a.socket()));
+    let mut sent = None;
+    let mut received = None;
+
+    poll_fn(|cx| {
+        if sent.is_none() {
+            if let Poll::Ready(n) = a_to_b.as_mut().poll(cx) {
+                sent = Some(n?);
+            }
+        }
+        if received.is_none() {
+            if let Poll::Ready(n) = b_to_a.as_mut().poll(cx) {
+                received = Some(n?);
+            }
+        }
+        match (sent, received) {
+            (Some(sent), Some(received)) => Poll::Ready(Ok((sent, received))),
+            _ => Poll::Pending,
+        }
+    })
+    .await
+}
+
+// Copies one direction, with a read in flight while the data previously
+// read is written.
+async fn copy(src: &Socket, dst: &Socket) -> io::Result<u64> {
+    let mut total = 0;
+    let (res, mut filled) = src.read(Vec::with_capacity(BUF_SIZE)).await;
+    let mut n = res?;
+    let mut spare = Vec::with_capacity(BUF_SIZE);
+
+    while n > 0 {
+        let mut read = pin!(src.read(spare));
+        let mut write = pin!(dst.write_all(filled.slice(..n)));
+        let mut read_done = None;
+        let mut written = None;
+
+        let (res, buf) = poll_fn(|cx| {
+            if written.is_none() {
+                if let Poll::Ready((res, buf)) = write.as_mut().poll(cx) {
+                    if let Err(e) = res {
+                        return Poll::Ready(Err(e));
+                    }
+                    written = Some(buf.into_inner());
+                }
+            }
+            if read_done.is_none() {
+                if let Poll::Ready(read) = read.as_mut().poll(cx) {
+                    read_done = Some(read);
+                }
+            }
+            if written.is_some() && read_done.is_some() {
+                Poll::Ready(Ok(read_done.take().unwrap()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await?;
+
+        total += n as u64;
+        spare = written.take().unwrap();
+        filled = buf;
+        n = res?;
+    }
+
+    dst.shutdown(Shutdown::Write)?;
+    Ok(total)
+}
@@ -0,0 +1,33 @@
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::CONTEXT;
+use std::io;
+
+/// Create a socket
+pub(crate) struct CreateSocket;
+
+impl Op<CreateSocket> {
+    /// Submit a request to create a socket, as with `socket(2)`.
+    pub(crate) fn create_socket(
+        domain: libc::c_int,
+        socket_type: libc::c_int,
+    ) -> io::Result<Op<CreateSocket>> {
+        use io_uring::opcode;
+
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(CreateSocket, |_| {
+                    opcode::Socket::new(domain, socket_type, 0).build()
+                })
+        })
+    }
+}
+
+impl Completable for CreateSocket {
+    type Output = io::Result<SharedFd>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        Ok(SharedFd::new(cqe.result? as _))
+    }
+}
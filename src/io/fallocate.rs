@@ -0,0 +1,39 @@
+use std::io;
+
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::CONTEXT;
+use io_uring::{opcode, types};
+
+pub(crate) struct Fallocate {
+    fd: SharedFd,
+}
+
+impl Op<Fallocate> {
+    pub(crate) fn fallocate(
+        fd: &SharedFd,
+        offset: u64,
+        len: u64,
+        mode: i32,
+    ) -> io::Result<Op<Fallocate>> {
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                Fallocate { fd: fd.clone() },
+                |fallocate| {
+                    opcode::Fallocate64::new(types::Fd(fallocate.fd.raw_fd()), len as _)
+                        .offset64(offset as _)
+                        .mode(mode)
+                        .build()
+                },
+            )
+        })
+    }
+}
+
+impl Completable for Fallocate {
+    type Output = io::Result<()>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        cqe.result.map(|_| ())
+    }
+}
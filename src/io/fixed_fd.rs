@@ -0,0 +1,45 @@
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::driver::SqeHeader;
+use crate::runtime::CONTEXT;
+use io_uring::squeue;
+use std::io;
+use std::os::unix::io::{FromRawFd, OwnedFd};
+
+// Opcode of Linux 6.8, which the io-uring crate does not define yet.
+const IORING_OP_FIXED_FD_INSTALL: u8 = 54;
+
+// Flag of the operation to install the descriptor without O_CLOEXEC.
+const IORING_FIXED_FD_NO_CLOEXEC: u32 = 1;
+
+/// Install a direct descriptor as a regular file descriptor
+pub(crate) struct FixedFdInstall;
+
+impl Op<FixedFdInstall> {
+    /// Submit a request to install the direct descriptor in `slot` of the
+    /// table of registered files into the file table of the process.
+    pub(crate) fn fixed_fd_install(slot: u32, cloexec: bool) -> io::Result<Op<FixedFdInstall>> {
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(FixedFdInstall, |_| {
+                    let flags = if cloexec {
+                        0
+                    } else {
+                        IORING_FIXED_FD_NO_CLOEXEC
+                    };
+                    let sqe = SqeHeader::build(IORING_OP_FIXED_FD_INSTALL, slot as _, 0, 0, 0);
+                    SqeHeader::set_op_flags(sqe, flags).flags(squeue::Flags::FIXED_FILE)
+                })
+        })
+    }
+}
+
+impl Completable for FixedFdInstall {
+    type Output = io::Result<OwnedFd>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        // Safety: the kernel has installed a new descriptor, owned by the
+        // caller
+        Ok(unsafe { OwnedFd::from_raw_fd(cqe.result? as _) })
+    }
+}
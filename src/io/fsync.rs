@@ -3,33 +3,42 @@ use std::io;
 use crate::io::SharedFd;
 use crate::runtime::driver::op::{Completable, CqeResult, Op};
 use crate::runtime::CONTEXT;
-use io_uring::{opcode, types};
+use io_uring::{opcode, squeue, types};
 
 pub(crate) struct Fsync {
     fd: SharedFd,
 }
 
+impl Fsync {
+    pub(crate) fn new(fd: &SharedFd) -> Fsync {
+        Fsync { fd: fd.clone() }
+    }
+
+    pub(crate) fn sqe(&self, flags: types::FsyncFlags) -> squeue::Entry {
+        opcode::Fsync::new(types::Fd(self.fd.raw_fd()))
+            .flags(flags)
+            .build()
+    }
+}
+
 impl Op<Fsync> {
     pub(crate) fn fsync(fd: &SharedFd) -> io::Result<Op<Fsync>> {
         CONTEXT.with(|x| {
             x.handle()
                 .expect("Not in a runtime context")
-                .submit_op(Fsync { fd: fd.clone() }, |fsync| {
-                    opcode::Fsync::new(types::Fd(fsync.fd.raw_fd())).build()
+                .submit_op(Fsync::new(fd), |fsync| {
+                    fsync.sqe(types::FsyncFlags::empty())
                 })
         })
     }
 
     pub(crate) fn datasync(fd: &SharedFd) -> io::Result<Op<Fsync>> {
         CONTEXT.with(|x| {
-            x.handle().expect("Not in a runtime context").submit_op(
-                Fsync { fd: fd.clone() },
-                |fsync| {
-                    opcode::Fsync::new(types::Fd(fsync.fd.raw_fd()))
-                        .flags(types::FsyncFlags::DATASYNC)
-                        .build()
-                },
-            )
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(Fsync::new(fd), |fsync| {
+                    fsync.sqe(types::FsyncFlags::DATASYNC)
+                })
         })
     }
 }
@@ -1,30 +1,63 @@
+//! Utilities for copying data between the I/O resources of this crate, and
+//! the [`Tty`] type of terminals and serial devices.
+
 mod accept;
+pub(crate) use accept::Accept;
+
+mod bind;
+pub(crate) use bind::{IORING_OP_BIND, IORING_OP_LISTEN};
+
+mod bundle;
+pub(crate) use bundle::{RecvBundle, SendBundle};
 
 mod close;
 pub(crate) use close::Close;
 
 mod connect;
 
+mod create_socket;
+
+mod copy;
+pub(crate) use copy::sealed;
+pub use copy::{copy_bidirectional, Duplex};
+
+mod fallocate;
+
+mod fixed_fd;
+
 mod fsync;
 
+mod msg;
+
 mod noop;
 pub(crate) use noop::NoOp;
 
 mod open;
 
+mod poll;
+pub(crate) use poll::PollMulti;
+
 mod read;
+pub(crate) use read::Read;
 
 mod read_fixed;
 
+mod read_multi;
+pub(crate) use read_multi::ReadMulti;
+
 mod readv;
 
 mod recv_from;
 
 mod rename_at;
 
+mod scm_rights;
+pub(crate) use scm_rights::MAX_FDS;
+
 mod send_to;
 
 mod send_zc;
+pub(crate) use send_zc::SendZcNotified;
 
 mod shared_fd;
 pub(crate) use shared_fd::SharedFd;
@@ -32,11 +65,28 @@ pub(crate) use shared_fd::SharedFd;
 mod socket;
 pub(crate) use socket::Socket;
 
+#[cfg(feature = "tokio-io")]
+mod staging;
+#[cfg(feature = "tokio-io")]
+pub(crate) use staging::Staging;
+
+mod splice;
+pub(crate) use splice::SplicePipe;
+
+mod statx;
+
+mod timeout;
+pub(crate) use timeout::Timeout;
+
+mod tty;
+pub use tty::{Termios, Tty};
+
 mod unlink_at;
 
 mod util;
 
 mod write;
+pub(crate) use write::Write;
 
 mod write_fixed;
 
@@ -0,0 +1,201 @@
+use crate::buf::{BoundedBuf, BoundedBufMut};
+use crate::io::SharedFd;
+use crate::net::{RecvMeta, SendMeta, Timestamps, CONTROL_LEN};
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::CONTEXT;
+use crate::BufResult;
+use io_uring::{opcode, squeue, types};
+use socket2::SockAddr;
+use std::io::{IoSlice, IoSliceMut};
+use std::{boxed::Box, io};
+
+pub(crate) struct RecvMsg<T> {
+    fd: SharedFd,
+    buf: T,
+    #[allow(dead_code)]
+    io_slices: Vec<IoSliceMut<'static>>,
+    socket_addr: Box<SockAddr>,
+    #[allow(dead_code)]
+    control: Box<[u64; CONTROL_LEN]>,
+    msghdr: Box<libc::msghdr>,
+}
+
+impl<T: BoundedBufMut> RecvMsg<T> {
+    fn new(fd: &SharedFd, mut buf: T) -> io::Result<RecvMsg<T>> {
+        let mut io_slices = vec![IoSliceMut::new(unsafe {
+            std::slice::from_raw_parts_mut(buf.stable_mut_ptr(), buf.bytes_total())
+        })];
+
+        let socket_addr = Box::new(unsafe { SockAddr::init(|_, _| Ok(()))?.1 });
+        let mut control = Box::new([0u64; CONTROL_LEN]);
+
+        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { std::mem::zeroed() });
+        msghdr.msg_iov = io_slices.as_mut_ptr().cast();
+        msghdr.msg_iovlen = io_slices.len() as _;
+        msghdr.msg_name = socket_addr.as_ptr() as *mut libc::c_void;
+        msghdr.msg_namelen = socket_addr.len();
+        msghdr.msg_control = control.as_mut_ptr().cast();
+        msghdr.msg_controllen = std::mem::size_of_val(&*control) as _;
+
+        Ok(RecvMsg {
+            fd: fd.clone(),
+            buf,
+            io_slices,
+            socket_addr,
+            control,
+            msghdr,
+        })
+    }
+
+    fn sqe(&mut self) -> squeue::Entry {
+        opcode::RecvMsg::new(types::Fd(self.fd.raw_fd()), self.msghdr.as_mut() as *mut _).build()
+    }
+}
+
+impl<T: BoundedBufMut> Op<RecvMsg<T>> {
+    pub(crate) fn recv_msg(fd: &SharedFd, buf: T) -> io::Result<Op<RecvMsg<T>>> {
+        let data = RecvMsg::new(fd, buf)?;
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(data, RecvMsg::sqe)
+        })
+    }
+}
+
+impl<T> Completable for RecvMsg<T>
+where
+    T: BoundedBufMut,
+{
+    type Output = BufResult<(usize, RecvMeta), T>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        let mut buf = self.buf;
+        let socket_addr = self.socket_addr.as_socket();
+        let msghdr = self.msghdr;
+
+        let res = cqe.result.map(|n| {
+            let n = n as usize;
+            // Safety: the kernel wrote `n` bytes to the buffer.
+            unsafe {
+                buf.set_init(n);
+            }
+
+            let mut meta = RecvMeta::new(socket_addr.unwrap());
+            // Safety: the kernel has set the length of the control messages
+            unsafe {
+                meta.parse_control(&msghdr);
+            }
+            (n, meta)
+        });
+
+        (res, buf)
+    }
+}
+
+/// Receives data on a stream socket with its timestamps, which unlike a
+/// datagram has no sender address.
+pub(crate) struct RecvTimestamped<T>(RecvMsg<T>);
+
+impl<T: BoundedBufMut> Op<RecvTimestamped<T>> {
+    pub(crate) fn recv_timestamped(fd: &SharedFd, buf: T) -> io::Result<Op<RecvTimestamped<T>>> {
+        let data = RecvTimestamped(RecvMsg::new(fd, buf)?);
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(data, |recv| recv.0.sqe())
+        })
+    }
+}
+
+impl<T> Completable for RecvTimestamped<T>
+where
+    T: BoundedBufMut,
+{
+    type Output = BufResult<(usize, Timestamps), T>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        let RecvMsg {
+            mut buf, msghdr, ..
+        } = self.0;
+
+        let res = cqe.result.map(|n| {
+            let n = n as usize;
+            // Safety: the kernel wrote `n` bytes to the buffer.
+            unsafe {
+                buf.set_init(n);
+            }
+
+            let mut timestamps = Timestamps::default();
+            // Safety: the kernel has set the length of the control messages
+            unsafe {
+                timestamps.parse_control(&msghdr);
+            }
+            (n, timestamps)
+        });
+
+        (res, buf)
+    }
+}
+
+pub(crate) struct SendMsg<T> {
+    fd: SharedFd,
+    buf: T,
+    #[allow(dead_code)]
+    io_slices: Vec<IoSlice<'static>>,
+    #[allow(dead_code)]
+    socket_addr: Box<SockAddr>,
+    #[allow(dead_code)]
+    control: Box<[u64; CONTROL_LEN]>,
+    msghdr: Box<libc::msghdr>,
+}
+
+impl<T: BoundedBuf> Op<SendMsg<T>> {
+    pub(crate) fn send_msg(fd: &SharedFd, buf: T, meta: &SendMeta) -> io::Result<Op<SendMsg<T>>> {
+        let io_slices = vec![IoSlice::new(unsafe {
+            std::slice::from_raw_parts(buf.stable_ptr(), buf.bytes_init())
+        })];
+
+        let socket_addr = Box::new(SockAddr::from(meta.addr()));
+        let mut control = Box::new([0u64; CONTROL_LEN]);
+        let control_len = meta.write_control(&mut control);
+
+        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { std::mem::zeroed() });
+        msghdr.msg_iov = io_slices.as_ptr() as *mut _;
+        msghdr.msg_iovlen = io_slices.len() as _;
+        msghdr.msg_name = socket_addr.as_ptr() as *mut libc::c_void;
+        msghdr.msg_namelen = socket_addr.len();
+        if control_len > 0 {
+            msghdr.msg_control = control.as_mut_ptr().cast();
+            msghdr.msg_controllen = control_len as _;
+        }
+
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                SendMsg {
+                    fd: fd.clone(),
+                    buf,
+                    io_slices,
+                    socket_addr,
+                    control,
+                    msghdr,
+                },
+                |send_msg| {
+                    opcode::SendMsg::new(
+                        types::Fd(send_msg.fd.raw_fd()),
+                        send_msg.msghdr.as_ref() as *const _,
+                    )
+                    .build()
+                },
+            )
+        })
+    }
+}
+
+impl<T> Completable for SendMsg<T> {
+    type Output = BufResult<usize, T>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        (cqe.result.map(|v| v as usize), self.buf)
+    }
+}
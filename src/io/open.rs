@@ -1,8 +1,10 @@
 use crate::fs::{File, OpenOptions};
+use crate::io::statx::Statx;
 use crate::io::SharedFd;
 
 use crate::runtime::driver::op::{Completable, CqeResult, Op};
 use crate::runtime::CONTEXT;
+use io_uring::squeue;
 use std::ffi::CString;
 use std::io;
 use std::path::Path;
@@ -12,32 +14,64 @@ use std::path::Path;
 pub(crate) struct Open {
     pub(crate) path: CString,
     pub(crate) flags: libc::c_int,
+    mode: libc::mode_t,
 }
 
-impl Op<Open> {
-    /// Submit a request to open a file.
-    pub(crate) fn open(path: &Path, options: &OpenOptions) -> io::Result<Op<Open>> {
-        use io_uring::{opcode, types};
+impl Open {
+    fn new(path: &Path, options: &OpenOptions) -> io::Result<Open> {
         let path = super::util::cstr(path)?;
         let flags = libc::O_CLOEXEC
             | options.access_mode()?
             | options.creation_mode()?
             | (options.custom_flags & !libc::O_ACCMODE);
 
+        Ok(Open {
+            path,
+            flags,
+            mode: options.mode,
+        })
+    }
+
+    fn sqe(&self) -> squeue::Entry {
+        use io_uring::{opcode, types};
+
+        // Get a reference to the memory. The string will be held by the
+        // operation state and will not be accessed again until the operation
+        // completes.
+        let p_ref = self.path.as_c_str().as_ptr();
+
+        opcode::OpenAt::new(types::Fd(libc::AT_FDCWD), p_ref)
+            .flags(self.flags)
+            .mode(self.mode)
+            .build()
+    }
+}
+
+impl Op<Open> {
+    /// Submit a request to open a file.
+    pub(crate) fn open(path: &Path, options: &OpenOptions) -> io::Result<Op<Open>> {
+        let open = Open::new(path, options)?;
+
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(open, |open| open.sqe())
+        })
+    }
+
+    /// Submit a request to open a file, linked with a request to retrieve
+    /// the status of the file at the same path.
+    pub(crate) fn open_with_statx(
+        path: &Path,
+        options: &OpenOptions,
+    ) -> io::Result<(Op<Open>, Op<Statx>)> {
+        let open = Open::new(path, options)?;
+        let statx = Statx::new(None, path, 0)?;
+
         CONTEXT.with(|x| {
             x.handle()
                 .expect("Not in a runtime context")
-                .submit_op(Open { path, flags }, |open| {
-                    // Get a reference to the memory. The string will be held by the
-                    // operation state and will not be accessed again until the operation
-                    // completes.
-                    let p_ref = open.path.as_c_str().as_ptr();
-
-                    opcode::OpenAt::new(types::Fd(libc::AT_FDCWD), p_ref)
-                        .flags(flags)
-                        .mode(options.mode)
-                        .build()
-                })
+                .submit_linked_ops(open, |open| open.sqe(), statx, |statx| statx.sqe())
         })
     }
 }
@@ -46,6 +80,14 @@ impl Completable for Open {
     type Output = io::Result<File>;
 
     fn complete(self, cqe: CqeResult) -> Self::Output {
-        Ok(File::from_shared_fd(SharedFd::new(cqe.result? as _)))
+        let fd = SharedFd::new(cqe.result? as _);
+        if self.flags & libc::O_NONBLOCK != 0 {
+            // The flag is only meant for opening, e.g. a FIFO without
+            // waiting for the other end. The operations on a non-blocking
+            // file would fail with EAGAIN instead of waiting.
+            let flags = syscall!(fcntl(fd.raw_fd(), libc::F_GETFL))?;
+            syscall!(fcntl(fd.raw_fd(), libc::F_SETFL, flags & !libc::O_NONBLOCK))?;
+        }
+        Ok(File::from_shared_fd(fd))
     }
 }
@@ -0,0 +1,75 @@
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, MultiCQEStream, Op, Streamable};
+use crate::runtime::CONTEXT;
+use io_uring::{opcode, types};
+use std::io;
+
+pub(crate) struct Poll {
+    /// Holds a strong ref to the FD, preventing the file from being closed
+    /// while the operation is in-flight.
+    fd: SharedFd,
+}
+
+impl Op<Poll> {
+    /// Waits until the file is ready for the `poll` events in `mask`.
+    pub(crate) fn poll_add(fd: &SharedFd, mask: u32) -> io::Result<Op<Poll>> {
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(Poll { fd: fd.clone() }, |poll| {
+                    opcode::PollAdd::new(types::Fd(poll.fd.raw_fd()), mask).build()
+                })
+        })
+    }
+}
+
+impl Completable for Poll {
+    type Output = io::Result<u32>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        cqe.result
+    }
+}
+
+/// Poll repeatedly for readiness
+pub(crate) struct PollMulti {
+    /// Holds a strong ref to the FD, preventing the file from being closed
+    /// while the operation is in-flight.
+    #[allow(dead_code)]
+    fd: SharedFd,
+}
+
+impl Op<PollMulti, MultiCQEStream> {
+    /// Submit a multishot poll, completing each time the file becomes ready
+    /// for the `poll` events in `mask`.
+    pub(crate) fn poll_multi(
+        fd: &SharedFd,
+        mask: u32,
+    ) -> io::Result<Op<PollMulti, MultiCQEStream>> {
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                PollMulti { fd: fd.clone() },
+                |poll| {
+                    opcode::PollAdd::new(types::Fd(poll.fd.raw_fd()), mask)
+                        .multi(true)
+                        .build()
+                },
+            )
+        })
+    }
+}
+
+impl Completable for PollMulti {
+    /// The events the file is ready for
+    type Output = io::Result<u32>;
+
+    fn complete(mut self, cqe: CqeResult) -> Self::Output {
+        self.next(cqe)
+    }
+}
+
+impl Streamable for PollMulti {
+    fn next(&mut self, cqe: CqeResult) -> Self::Output {
+        cqe.result
+    }
+}
@@ -1,9 +1,10 @@
-use crate::buf::BoundedBufMut;
+use crate::buf::{BoundedBufMut, SubmittedPtr};
 use crate::io::SharedFd;
 use crate::BufResult;
 
 use crate::runtime::driver::op::{Completable, CqeResult, Op};
 use crate::runtime::CONTEXT;
+use io_uring::{opcode, squeue, types};
 use std::io;
 
 pub(crate) struct Read<T> {
@@ -14,27 +15,49 @@ pub(crate) struct Read<T> {
 
     /// Reference to the in-flight buffer.
     pub(crate) buf: T,
+
+    /// The address of the buffer submitted to the kernel.
+    submitted: SubmittedPtr,
+}
+
+impl<T: BoundedBufMut> Read<T> {
+    pub(super) fn new(fd: &SharedFd, buf: T) -> Read<T> {
+        Read {
+            fd: fd.clone(),
+            buf,
+            submitted: SubmittedPtr::default(),
+        }
+    }
+
+    pub(super) fn sqe(&mut self, offset: u64, rw_flags: i32) -> squeue::Entry {
+        // Get raw buffer info
+        let ptr = self.buf.stable_mut_ptr();
+        self.submitted.set(ptr);
+        let len = self.buf.bytes_total();
+
+        opcode::Read::new(types::Fd(self.fd.raw_fd()), ptr, len as _)
+            .offset(offset as _)
+            .rw_flags(rw_flags)
+            .build()
+    }
 }
 
 impl<T: BoundedBufMut> Op<Read<T>> {
     pub(crate) fn read_at(fd: &SharedFd, buf: T, offset: u64) -> io::Result<Op<Read<T>>> {
-        use io_uring::{opcode, types};
+        Op::read_at_with(fd, buf, offset, 0)
+    }
 
+    /// Submits a read with the `RWF_*` flags of `preadv2`.
+    pub(crate) fn read_at_with(
+        fd: &SharedFd,
+        buf: T,
+        offset: u64,
+        rw_flags: i32,
+    ) -> io::Result<Op<Read<T>>> {
         CONTEXT.with(|x| {
-            x.handle().expect("Not in a runtime context").submit_op(
-                Read {
-                    fd: fd.clone(),
-                    buf,
-                },
-                |read| {
-                    // Get raw buffer info
-                    let ptr = read.buf.stable_mut_ptr();
-                    let len = read.buf.bytes_total();
-                    opcode::Read::new(types::Fd(fd.raw_fd()), ptr, len as _)
-                        .offset(offset as _)
-                        .build()
-                },
-            )
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(Read::new(fd, buf), |read| read.sqe(offset, rw_flags))
         })
     }
 }
@@ -50,6 +73,7 @@ where
         let res = cqe.result.map(|v| v as usize);
         // Recover the buffer
         let mut buf = self.buf;
+        self.submitted.check(buf.stable_ptr());
 
         // If the operation was successful, advance the initialized cursor.
         if let Ok(n) = res {
@@ -61,4 +85,8 @@ where
 
         (res, buf)
     }
+
+    fn waits_for_readiness(&self) -> bool {
+        self.fd.is_socket()
+    }
 }
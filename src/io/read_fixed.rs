@@ -1,5 +1,5 @@
 use crate::buf::fixed::FixedBuf;
-use crate::buf::BoundedBufMut;
+use crate::buf::{BoundedBufMut, SubmittedPtr};
 use crate::io::SharedFd;
 use crate::runtime::driver::op::{self, Completable, Op};
 use crate::BufResult;
@@ -15,6 +15,9 @@ pub(crate) struct ReadFixed<T> {
 
     /// The in-flight buffer.
     buf: T,
+
+    /// The address of the buffer submitted to the kernel.
+    submitted: SubmittedPtr,
 }
 
 impl<T> Op<ReadFixed<T>>
@@ -33,10 +36,12 @@ where
                 ReadFixed {
                     fd: fd.clone(),
                     buf,
+                    submitted: SubmittedPtr::default(),
                 },
                 |read_fixed| {
                     // Get raw buffer info
                     let ptr = read_fixed.buf.stable_mut_ptr();
+                    read_fixed.submitted.set(ptr);
                     let len = read_fixed.buf.bytes_total();
                     let buf_index = read_fixed.buf.get_buf().buf_index();
                     opcode::ReadFixed::new(types::Fd(fd.raw_fd()), ptr, len as _, buf_index)
@@ -59,6 +64,7 @@ where
         let res = cqe.result.map(|v| v as usize);
         // Recover the buffer
         let mut buf = self.buf;
+        self.submitted.check(buf.stable_ptr());
 
         // If the operation was successful, advance the initialized cursor.
         if let Ok(n) = res {
@@ -70,4 +76,8 @@ where
 
         (res, buf)
     }
+
+    fn waits_for_readiness(&self) -> bool {
+        self.fd.is_socket()
+    }
 }
@@ -0,0 +1,92 @@
+use crate::buf::provided::{BufRing, ProvidedBuf};
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, MultiCQEStream, Op, Streamable};
+use crate::runtime::driver::SqeHeader;
+use crate::runtime::CONTEXT;
+use io_uring::{opcode, types};
+use std::io;
+
+// Opcode of Linux 6.7, which the io-uring crate does not define yet.
+const IORING_OP_READ_MULTISHOT: u8 = 49;
+
+/// Read or receive repeatedly into buffers selected from a buffer ring
+pub(crate) struct ReadMulti {
+    /// Holds a strong ref to the FD, preventing the file from being closed
+    /// while the operation is in-flight.
+    #[allow(dead_code)]
+    fd: SharedFd,
+
+    ring: BufRing,
+}
+
+impl Op<ReadMulti, MultiCQEStream> {
+    /// Submit a multishot read, completing each time data is read into a
+    /// buffer of `ring`.
+    pub(crate) fn read_multi(
+        fd: &SharedFd,
+        ring: &BufRing,
+    ) -> io::Result<Op<ReadMulti, MultiCQEStream>> {
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                ReadMulti {
+                    fd: fd.clone(),
+                    ring: ring.clone(),
+                },
+                |read| {
+                    // A length of 0 reads up to the size of the buffer
+                    let sqe = SqeHeader::build(IORING_OP_READ_MULTISHOT, fd.raw_fd(), 0, 0, 0);
+                    SqeHeader::set_buf_group(sqe, read.ring.bgid())
+                },
+            )
+        })
+    }
+}
+
+impl Op<ReadMulti, MultiCQEStream> {
+    /// Submit a multishot receive on a socket, completing each time data is
+    /// received into a buffer of `ring`.
+    pub(crate) fn recv_multi(
+        fd: &SharedFd,
+        ring: &BufRing,
+    ) -> io::Result<Op<ReadMulti, MultiCQEStream>> {
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                ReadMulti {
+                    fd: fd.clone(),
+                    ring: ring.clone(),
+                },
+                |read| opcode::RecvMulti::new(types::Fd(fd.raw_fd()), read.ring.bgid()).build(),
+            )
+        })
+    }
+}
+
+impl ReadMulti {
+    /// Gives the buffer of a completion the stream has been dropped before
+    /// returning back to the ring.
+    pub(crate) fn discard(&self, cqe: &CqeResult) {
+        self.ring.recycle(cqe.flags);
+    }
+}
+
+impl Completable for ReadMulti {
+    /// A chunk of data, or `None` at the end of the data
+    type Output = io::Result<Option<ProvidedBuf>>;
+
+    fn complete(mut self, cqe: CqeResult) -> Self::Output {
+        self.next(cqe)
+    }
+}
+
+impl Streamable for ReadMulti {
+    fn next(&mut self, cqe: CqeResult) -> Self::Output {
+        // A buffer reported along with no data is given back right away
+        match cqe.result {
+            Ok(n) => Ok(self.ring.take(cqe.flags, n as usize).filter(|_| n > 0)),
+            Err(e) => {
+                self.ring.recycle(cqe.flags);
+                Err(e)
+            }
+        }
+    }
+}
@@ -89,4 +89,8 @@ where
 
         (res, bufs)
     }
+
+    fn waits_for_readiness(&self) -> bool {
+        self.fd.is_socket()
+    }
 }
@@ -0,0 +1,188 @@
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::CONTEXT;
+use io_uring::{opcode, squeue, types};
+use std::io;
+use std::mem;
+use std::os::unix::io::{FromRawFd, OwnedFd, RawFd};
+
+/// The most file descriptors passed in a message, `SCM_MAX_FD` of the
+/// kernel.
+pub(crate) const MAX_FDS: usize = 253;
+
+// Returns a zeroed buffer for the control message passing `n` descriptors.
+fn control_buf(n: usize) -> Vec<u64> {
+    let space = unsafe { libc::CMSG_SPACE((n * mem::size_of::<RawFd>()) as _) } as usize;
+    vec![0; space.div_ceil(8)]
+}
+
+// The message passing descriptors, with their number as payload to detect
+// descriptors dropped by the kernel.
+struct Message {
+    fd: SharedFd,
+    count: Box<[u8; 4]>,
+    #[allow(dead_code)]
+    iov: Box<libc::iovec>,
+    #[allow(dead_code)]
+    control: Vec<u64>,
+    msghdr: Box<libc::msghdr>,
+}
+
+impl Message {
+    fn new(fd: &SharedFd, count: u32, control: Vec<u64>) -> Message {
+        let mut count = Box::new(count.to_le_bytes());
+        let mut iov = Box::new(libc::iovec {
+            iov_base: count.as_mut_ptr().cast(),
+            iov_len: count.len(),
+        });
+        let mut control = control;
+
+        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { mem::zeroed() });
+        msghdr.msg_iov = iov.as_mut() as *mut _;
+        msghdr.msg_iovlen = 1;
+        msghdr.msg_control = control.as_mut_ptr().cast();
+        msghdr.msg_controllen = (control.len() * 8) as _;
+
+        Message {
+            fd: fd.clone(),
+            count,
+            iov,
+            control,
+            msghdr,
+        }
+    }
+}
+
+/// Sends file descriptors over a Unix socket, with `SCM_RIGHTS`.
+pub(crate) struct SendFds {
+    msg: Message,
+    // Keeps the descriptors open until they have been sent
+    #[allow(dead_code)]
+    fds: Vec<SharedFd>,
+}
+
+impl Op<SendFds> {
+    pub(crate) fn send_fds(fd: &SharedFd, fds: Vec<SharedFd>) -> io::Result<Op<SendFds>> {
+        if fds.is_empty() || fds.len() > MAX_FDS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid number of file descriptors",
+            ));
+        }
+        let mut msg = Message::new(fd, fds.len() as u32, control_buf(fds.len()));
+
+        // Safety: the control buffer has the space for the descriptors
+        unsafe {
+            let raw: Vec<RawFd> = fds.iter().map(SharedFd::raw_fd).collect();
+            let data_len = mem::size_of_val(&raw[..]);
+            let cmsg = libc::CMSG_FIRSTHDR(msg.msghdr.as_ref());
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(data_len as _) as _;
+            std::ptr::copy_nonoverlapping(
+                raw.as_ptr().cast::<u8>(),
+                libc::CMSG_DATA(cmsg),
+                data_len,
+            );
+            msg.msghdr.msg_controllen = libc::CMSG_SPACE(data_len as _) as _;
+        }
+
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(SendFds { msg, fds }, |send| {
+                    opcode::SendMsg::new(
+                        types::Fd(send.msg.fd.raw_fd()),
+                        send.msg.msghdr.as_ref() as *const _,
+                    )
+                    .flags(libc::MSG_NOSIGNAL as _)
+                    .build()
+                })
+        })
+    }
+}
+
+impl Completable for SendFds {
+    type Output = io::Result<()>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        match cqe.result? as usize {
+            n if n == self.msg.count.len() => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to send the file descriptors",
+            )),
+        }
+    }
+}
+
+/// Receives file descriptors sent with [`SendFds`] over a Unix socket.
+pub(crate) struct RecvFds {
+    msg: Message,
+}
+
+impl RecvFds {
+    fn sqe(&mut self) -> squeue::Entry {
+        opcode::RecvMsg::new(
+            types::Fd(self.msg.fd.raw_fd()),
+            self.msg.msghdr.as_mut() as *mut _,
+        )
+        .flags(libc::MSG_CMSG_CLOEXEC as _)
+        .build()
+    }
+}
+
+impl Op<RecvFds> {
+    pub(crate) fn recv_fds(fd: &SharedFd) -> io::Result<Op<RecvFds>> {
+        let msg = Message::new(fd, 0, control_buf(MAX_FDS));
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(RecvFds { msg }, RecvFds::sqe)
+        })
+    }
+}
+
+impl Completable for RecvFds {
+    type Output = io::Result<Vec<OwnedFd>>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        let n = cqe.result? as usize;
+        let msghdr = &self.msg.msghdr;
+
+        // The descriptors are owned first, so that they are closed on errors
+        let mut fds = Vec::new();
+        // Safety: the kernel has set the length of the control messages
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(msghdr.as_ref());
+            while !cmsg.is_null() {
+                if ((*cmsg).cmsg_level, (*cmsg).cmsg_type) == (libc::SOL_SOCKET, libc::SCM_RIGHTS) {
+                    let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                    let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                    for i in 0..len / mem::size_of::<RawFd>() {
+                        fds.push(OwnedFd::from_raw_fd(data.add(i).read_unaligned()));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(msghdr.as_ref(), cmsg);
+            }
+        }
+
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before the file descriptors were received",
+            ));
+        }
+        let count = u32::from_le_bytes(*self.msg.count) as usize;
+        if n != self.msg.count.len()
+            || msghdr.msg_flags & libc::MSG_CTRUNC != 0
+            || fds.len() != count
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "failed to receive the file descriptors",
+            ));
+        }
+        Ok(fds)
+    }
+}
@@ -56,3 +56,61 @@ impl<T> Updateable for SendZc<T> {
         self.bytes += *cqe.result.as_ref().unwrap() as usize;
     }
 }
+
+/// A zero-copy send reporting the send result before the buffer is
+/// released by the kernel.
+pub(crate) struct SendZcNotified<T> {
+    /// Holds a strong ref to the FD, preventing the file from being closed
+    /// while the operation is in-flight.
+    #[allow(dead_code)]
+    fd: SharedFd,
+
+    buf: T,
+
+    /// The result of the send, once reported
+    pub(crate) sent: Option<io::Result<usize>>,
+}
+
+impl<T: BoundedBuf> Op<SendZcNotified<T>, MultiCQEFuture> {
+    pub(crate) fn send_zc_notified(fd: &SharedFd, buf: T) -> io::Result<Self> {
+        use io_uring::{opcode, types};
+
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                SendZcNotified {
+                    fd: fd.clone(),
+                    buf,
+                    sent: None,
+                },
+                |send| {
+                    let ptr = send.buf.stable_ptr();
+                    let len = send.buf.bytes_init();
+
+                    opcode::SendZc::new(types::Fd(fd.raw_fd()), ptr, len as _).build()
+                },
+            )
+        })
+    }
+}
+
+impl<T> Completable for SendZcNotified<T> {
+    type Output = (io::Result<usize>, T);
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        // Without a notification to follow, the final completion carries
+        // the send result
+        let res = match self.sent {
+            Some(res) => res,
+            None => cqe.result.map(|v| v as usize),
+        };
+        (res, self.buf)
+    }
+}
+
+impl<T> Updateable for SendZcNotified<T> {
+    fn update(&mut self, cqe: CqeResult) {
+        // The send result comes first, flagged `more`, the notification
+        // that the buffer is released comes last
+        self.sent = Some(cqe.result.map(|v| v as usize));
+    }
+}
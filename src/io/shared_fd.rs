@@ -1,10 +1,12 @@
 use crate::io::Close;
 use std::future::poll_fn;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::os::unix::io::{FromRawFd, RawFd};
 use std::rc::Rc;
+use std::sync::Arc;
 use std::task::Waker;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use crate::runtime::driver::op::Op;
 use crate::runtime::CONTEXT;
@@ -17,12 +19,23 @@ use crate::runtime::CONTEXT;
 #[derive(Clone)]
 pub(crate) struct SharedFd {
     inner: Rc<Inner>,
+
+    // Permit counted against the limit on operations in flight, held by
+    // the clones of a handle returned by `acquire`, which the operations
+    // keep until they are done.
+    _permit: Option<Rc<OwnedSemaphorePermit>>,
 }
 
 struct Inner {
     // Open file descriptor
     fd: RawFd,
 
+    // Limit on operations in flight, if set
+    limit: RefCell<Option<Arc<Semaphore>>>,
+
+    // Whether the FD is a socket, whose reads wait for data to arrive
+    socket: Cell<bool>,
+
     // Waker to notify when the close operation completes.
     state: RefCell<State>,
 }
@@ -46,16 +59,94 @@ impl SharedFd {
         SharedFd {
             inner: Rc::new(Inner {
                 fd,
+                limit: RefCell::new(None),
+                socket: Cell::new(false),
                 state: RefCell::new(State::Init),
             }),
+            _permit: None,
+        }
+    }
+
+    /// Sets the maximum number of operations in flight on the FD, or
+    /// removes the limit.
+    ///
+    /// Operations already in flight are not counted against a new limit.
+    pub(crate) fn set_max_in_flight(&self, max: Option<usize>) {
+        let limit = max.map(|max| {
+            assert!(
+                max > 0,
+                "the limit of operations in flight must be positive"
+            );
+            Arc::new(Semaphore::new(max))
+        });
+        *self.inner.limit.borrow_mut() = limit;
+    }
+
+    /// Returns a handle to submit an operation with, waiting for the number
+    /// of operations in flight to fall below the limit, if one is set.
+    ///
+    /// The operation counts against the limit until it is dropped.
+    pub(crate) async fn acquire(&self) -> SharedFd {
+        let limit = self.inner.limit.borrow().clone();
+        match limit {
+            Some(limit) => {
+                // The semaphore is never closed
+                let permit = limit.acquire_owned().await.unwrap();
+                self.with_permit(permit)
+            }
+            None => self.clone(),
+        }
+    }
+
+    /// Returns a handle to submit an operation with, as `acquire` does, or
+    /// `None` if the limit has been reached.
+    pub(crate) fn try_acquire(&self) -> Option<SharedFd> {
+        let limit = self.inner.limit.borrow().clone();
+        match limit {
+            Some(limit) => limit
+                .try_acquire_owned()
+                .ok()
+                .map(|permit| self.with_permit(permit)),
+            None => Some(self.clone()),
         }
     }
 
+    fn with_permit(&self, permit: OwnedSemaphorePermit) -> SharedFd {
+        SharedFd {
+            inner: Rc::clone(&self.inner),
+            _permit: Some(Rc::new(permit)),
+        }
+    }
+
+    /// Marks the FD as a socket.
+    pub(crate) fn set_socket(&self) {
+        self.inner.socket.set(true);
+    }
+
+    /// Returns `true` if the FD has been marked as a socket.
+    pub(crate) fn is_socket(&self) -> bool {
+        self.inner.socket.get()
+    }
+
     /// Returns the RawFd
     pub(crate) fn raw_fd(&self) -> RawFd {
         self.inner.fd
     }
 
+    /// Releases the FD without closing it.
+    ///
+    /// If in-flight operations still use the FD, it is closed once they
+    /// complete, and a duplicate is returned instead.
+    pub(crate) fn into_raw_fd(mut self) -> std::io::Result<RawFd> {
+        match Rc::get_mut(&mut self.inner) {
+            Some(inner) => {
+                *RefCell::get_mut(&mut inner.state) = State::Closed;
+                Ok(inner.fd)
+            }
+            None => syscall!(fcntl(self.raw_fd(), libc::F_DUPFD_CLOEXEC, 0)),
+        }
+    }
+
     /// An FD cannot be closed until all in-flight operation have completed.
     /// This prevents bugs where in-flight reads could operate on the incorrect
     /// file descriptor.
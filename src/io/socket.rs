@@ -1,13 +1,18 @@
-use crate::runtime::driver::op::Op;
+use crate::io::{IORING_OP_BIND, IORING_OP_LISTEN};
+use crate::net::{RecvMeta, SendMeta, Timestamping, Timestamps, SO_TIMESTAMPING};
+use crate::runtime::driver::op::{Completable, Op};
+use crate::runtime::CONTEXT;
 use crate::{
     buf::fixed::FixedBuf,
+    buf::provided::{BufRing, ProvidedBuf, SendRing},
     buf::{BoundedBuf, BoundedBufMut, IoBuf, Slice},
     io::SharedFd,
 };
+use socket2::SockRef;
 use std::{
     io,
     net::SocketAddr,
-    os::unix::io::{AsRawFd, IntoRawFd, RawFd},
+    os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
     path::Path,
 };
 
@@ -25,24 +30,45 @@ pub(crate) fn get_domain(socket_addr: SocketAddr) -> libc::c_int {
 }
 
 impl Socket {
-    pub(crate) fn new(socket_addr: SocketAddr, socket_type: libc::c_int) -> io::Result<Socket> {
+    pub(crate) fn new_in_domain(
+        domain: libc::c_int,
+        socket_type: libc::c_int,
+    ) -> io::Result<Socket> {
         let socket_type = socket_type | libc::SOCK_CLOEXEC;
-        let domain = get_domain(socket_addr);
         let fd = socket2::Socket::new(domain.into(), socket_type.into(), None)?.into_raw_fd();
-        let fd = SharedFd::new(fd);
-        Ok(Socket { fd })
+        Ok(Self::from_shared_fd(SharedFd::new(fd)))
     }
 
-    pub(crate) fn new_unix(socket_type: libc::c_int) -> io::Result<Socket> {
-        let socket_type = socket_type | libc::SOCK_CLOEXEC;
-        let domain = libc::AF_UNIX;
-        let fd = socket2::Socket::new(domain.into(), socket_type.into(), None)?.into_raw_fd();
-        let fd = SharedFd::new(fd);
-        Ok(Socket { fd })
+    /// Creates a socket with [`open_in_domain`], in the domain of the
+    /// address.
+    ///
+    /// [`open_in_domain`]: Socket::open_in_domain
+    pub(crate) async fn open(
+        socket_addr: SocketAddr,
+        socket_type: libc::c_int,
+    ) -> io::Result<Socket> {
+        Self::open_in_domain(get_domain(socket_addr), socket_type).await
+    }
+
+    /// Creates a socket with an `IORING_OP_SOCKET` operation, or with the
+    /// system call if the kernel does not support the operation (before
+    /// Linux 5.19).
+    pub(crate) async fn open_in_domain(
+        domain: libc::c_int,
+        socket_type: libc::c_int,
+    ) -> io::Result<Socket> {
+        let fd = ring_or_syscall(
+            io_uring::opcode::Socket::CODE,
+            || Op::create_socket(domain, socket_type | libc::SOCK_CLOEXEC),
+            || Self::new_in_domain(domain, socket_type).map(|socket| socket.fd),
+        )
+        .await?;
+        Ok(Self::from_shared_fd(fd))
     }
 
     pub(crate) async fn write<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<usize, T> {
-        let op = Op::write_at(&self.fd, buf, 0).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::write_at(&fd, buf, 0).unwrap();
         op.await
     }
 
@@ -80,11 +106,50 @@ impl Socket {
         (Ok(()), buf.into_inner())
     }
 
+    pub(crate) async fn request<T: BoundedBuf, U: BoundedBufMut>(
+        &self,
+        buf: T,
+        response: U,
+    ) -> crate::BufResult<usize, (T, U)> {
+        let orig_bounds = buf.bounds();
+        let buf = buf.slice_full();
+        let len = buf.bytes_init();
+
+        let fd = self.fd.acquire().await;
+        let (write, read) = Op::write_then_read(&fd, buf, response).unwrap();
+        let (written, slice) = write.await;
+        let (res, response) = read.await;
+        drop(fd);
+
+        let (res, buf, response) = match written {
+            Ok(n) if n == len => (res, slice.into_inner(), response),
+            Ok(0) => (
+                Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                )),
+                slice.into_inner(),
+                response,
+            ),
+            // The write was short, which canceled the linked read
+            Ok(n) => match self.write_all_slice(slice.slice(n..)).await {
+                (Ok(()), buf) => {
+                    let (res, response) = self.read(response).await;
+                    (res, buf, response)
+                }
+                (Err(e), buf) => (Err(e), buf, response),
+            },
+            Err(e) => (Err(e), slice.into_inner(), response),
+        };
+        (res, (T::from_buf_bounds(buf, orig_bounds), response))
+    }
+
     pub(crate) async fn write_fixed<T>(&self, buf: T) -> crate::BufResult<usize, T>
     where
         T: BoundedBuf<Buf = FixedBuf>,
     {
-        let op = Op::write_fixed_at(&self.fd, buf, 0).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::write_fixed_at(&fd, buf, 0).unwrap();
         op.await
     }
 
@@ -129,7 +194,8 @@ impl Socket {
     }
 
     pub async fn writev<T: IoBuf>(&self, buf: Vec<T>) -> crate::BufResult<usize, Vec<T>> {
-        let op = Op::writev_at(&self.fd, buf, 0).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::writev_at(&fd, buf, 0).unwrap();
         op.await
     }
 
@@ -138,25 +204,61 @@ impl Socket {
         buf: T,
         socket_addr: SocketAddr,
     ) -> crate::BufResult<usize, T> {
-        let op = Op::send_to(&self.fd, buf, socket_addr).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::send_to(&fd, buf, socket_addr).unwrap();
+        op.await
+    }
+
+    pub(crate) async fn send_fds(&self, fds: Vec<SharedFd>) -> io::Result<()> {
+        let fd = self.fd.acquire().await;
+        let op = Op::send_fds(&fd, fds)?;
+        op.await
+    }
+
+    pub(crate) async fn recv_fds(&self) -> io::Result<Vec<OwnedFd>> {
+        let fd = self.fd.acquire().await;
+        let op = Op::recv_fds(&fd)?;
         op.await
     }
 
     pub(crate) async fn send_zc<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<usize, T> {
-        let op = Op::send_zc(&self.fd, buf).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::send_zc(&fd, buf).unwrap();
         op.await
     }
 
     pub(crate) async fn read<T: BoundedBufMut>(&self, buf: T) -> crate::BufResult<usize, T> {
-        let op = Op::read_at(&self.fd, buf, 0).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::read_at(&fd, buf, 0).unwrap();
         op.await
     }
 
+    pub(crate) async fn recv_bundle(&self, ring: &BufRing) -> io::Result<Vec<ProvidedBuf>> {
+        let fd = self.fd.acquire().await;
+        Op::recv_bundle(&fd, ring)?.await
+    }
+
+    pub(crate) async fn send_bundle(&self, ring: &SendRing) -> io::Result<usize> {
+        let fd = self.fd.acquire().await;
+        let (n, unsent) = Op::send_bundle(&fd, ring)?.await?;
+        // The kernel consumes the buffer a short send stops in, so the rest
+        // of it is sent before the buffers queued after it
+        match unsent {
+            Some(data) => {
+                let len = data.len();
+                self.write_all(data).await.0?;
+                Ok(n + len)
+            }
+            None => Ok(n),
+        }
+    }
+
     pub(crate) async fn read_fixed<T>(&self, buf: T) -> crate::BufResult<usize, T>
     where
         T: BoundedBufMut<BufMut = FixedBuf>,
     {
-        let op = Op::read_fixed_at(&self.fd, buf, 0).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::read_fixed_at(&fd, buf, 0).unwrap();
         op.await
     }
 
@@ -164,25 +266,88 @@ impl Socket {
         &self,
         buf: T,
     ) -> crate::BufResult<(usize, SocketAddr), T> {
-        let op = Op::recv_from(&self.fd, buf).unwrap();
+        let fd = self.fd.acquire().await;
+        let op = Op::recv_from(&fd, buf).unwrap();
+        op.await
+    }
+
+    pub(crate) async fn recv_msg<T: BoundedBufMut>(
+        &self,
+        buf: T,
+    ) -> crate::BufResult<(usize, RecvMeta), T> {
+        let fd = self.fd.acquire().await;
+        let op = Op::recv_msg(&fd, buf).unwrap();
+        op.await
+    }
+
+    pub(crate) async fn recv_timestamped<T: BoundedBufMut>(
+        &self,
+        buf: T,
+    ) -> crate::BufResult<(usize, Timestamps), T> {
+        let fd = self.fd.acquire().await;
+        let op = Op::recv_timestamped(&fd, buf).unwrap();
+        op.await
+    }
+
+    pub(crate) async fn send_msg<T: BoundedBuf>(
+        &self,
+        buf: T,
+        meta: &SendMeta,
+    ) -> crate::BufResult<usize, T> {
+        let fd = self.fd.acquire().await;
+        let op = Op::send_msg(&fd, buf, meta).unwrap();
         op.await
     }
 
     pub(crate) async fn accept(&self) -> io::Result<(Socket, Option<SocketAddr>)> {
-        let op = Op::accept(&self.fd)?;
+        let fd = self.fd.acquire().await;
+        let op = Op::accept(&fd)?;
         op.await
     }
 
     pub(crate) async fn connect(&self, socket_addr: socket2::SockAddr) -> io::Result<()> {
-        let op = Op::connect(&self.fd, socket_addr)?;
+        let fd = self.fd.acquire().await;
+        let op = Op::connect(&fd, socket_addr)?;
         op.await
     }
 
-    pub(crate) fn bind(socket_addr: SocketAddr, socket_type: libc::c_int) -> io::Result<Socket> {
+    /// Binds the socket with an `IORING_OP_BIND` operation, or with the
+    /// system call if the kernel does not support the operation (before
+    /// Linux 6.11).
+    pub(crate) async fn bind_addr(&self, socket_addr: socket2::SockAddr) -> io::Result<()> {
+        ring_or_syscall(
+            IORING_OP_BIND,
+            || Op::bind(&self.fd, socket_addr.clone()),
+            || SockRef::from(self).bind(&socket_addr),
+        )
+        .await
+    }
+
+    /// Listens for connections with an `IORING_OP_LISTEN` operation, or
+    /// with the system call if the kernel does not support the operation
+    /// (before Linux 6.11).
+    pub(crate) async fn listen_op(&self, backlog: libc::c_int) -> io::Result<()> {
+        ring_or_syscall(
+            IORING_OP_LISTEN,
+            || Op::listen(&self.fd, backlog),
+            || self.listen(backlog),
+        )
+        .await
+    }
+
+    /// Creates a socket bound to `socket_addr`. If `only_v6` is given, the
+    /// `IPV6_V6ONLY` option of an IPv6 socket is set to it, otherwise the
+    /// socket takes the system default.
+    pub(crate) fn bind(
+        socket_addr: SocketAddr,
+        socket_type: libc::c_int,
+        only_v6: Option<bool>,
+    ) -> io::Result<Socket> {
         Self::bind_internal(
             socket_addr.into(),
             get_domain(socket_addr).into(),
             socket_type.into(),
+            only_v6,
         )
     }
 
@@ -191,37 +356,83 @@ impl Socket {
         socket_type: libc::c_int,
     ) -> io::Result<Socket> {
         let addr = socket2::SockAddr::unix(path.as_ref())?;
-        Self::bind_internal(addr, libc::AF_UNIX.into(), socket_type.into())
+        Self::bind_internal(addr, libc::AF_UNIX.into(), socket_type.into(), None)
     }
 
     pub(crate) fn from_std<T: IntoRawFd>(socket: T) -> Socket {
-        let fd = SharedFd::new(socket.into_raw_fd());
-        Self::from_shared_fd(fd)
+        let fd = socket.into_raw_fd();
+        // Operations on a non-blocking socket would fail with EAGAIN instead
+        // of waiting. This cannot fail with a valid descriptor.
+        if let Ok(flags) = syscall!(fcntl(fd, libc::F_GETFL)) {
+            if flags & libc::O_NONBLOCK != 0 {
+                let _ = syscall!(fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK));
+            }
+        }
+        Self::from_shared_fd(SharedFd::new(fd))
+    }
+
+    pub(crate) fn into_std<T: FromRawFd>(self) -> io::Result<T> {
+        let fd = self.fd.into_raw_fd()?;
+        // Safety: the socket owned the descriptor
+        Ok(unsafe { T::from_raw_fd(fd) })
     }
 
     pub(crate) fn from_shared_fd(fd: SharedFd) -> Socket {
+        fd.set_socket();
         Self { fd }
     }
 
+    /// Creates a socket with [`open`], and binds it like [`bind`].
+    ///
+    /// [`open`]: Socket::open
+    /// [`bind`]: Socket::bind
+    pub(crate) async fn open_bound(
+        socket_addr: SocketAddr,
+        socket_type: libc::c_int,
+        only_v6: Option<bool>,
+    ) -> io::Result<Socket> {
+        let domain = get_domain(socket_addr);
+        let socket = Self::open_in_domain(domain, socket_type).await?;
+        Self::configure(&SockRef::from(&socket), domain.into(), only_v6)?;
+        socket.bind_addr(socket_addr.into()).await?;
+        Ok(socket)
+    }
+
     fn bind_internal(
         socket_addr: socket2::SockAddr,
         domain: socket2::Domain,
         socket_type: socket2::Type,
+        only_v6: Option<bool>,
     ) -> io::Result<Socket> {
         let sys_listener = socket2::Socket::new(domain, socket_type, None)?;
+        Self::configure(&sys_listener, domain, only_v6)?;
+        sys_listener.bind(&socket_addr)?;
+
+        let fd = SharedFd::new(sys_listener.into_raw_fd());
+
+        Ok(Self::from_shared_fd(fd))
+    }
 
-        sys_listener.set_reuse_port(true)?;
+    fn configure(
+        sys_listener: &socket2::Socket,
+        domain: socket2::Domain,
+        only_v6: Option<bool>,
+    ) -> io::Result<()> {
+        // Recent kernels reject SO_REUSEPORT on Unix domain sockets.
+        if domain != socket2::Domain::UNIX {
+            sys_listener.set_reuse_port(true)?;
+        }
         sys_listener.set_reuse_address(true)?;
 
+        if let Some(only_v6) = only_v6.filter(|_| domain == socket2::Domain::IPV6) {
+            sys_listener.set_only_v6(only_v6)?;
+        }
+
         // TODO: config for buffer sizes
         // sys_listener.set_send_buffer_size(send_buf_size)?;
         // sys_listener.set_recv_buffer_size(recv_buf_size)?;
 
-        sys_listener.bind(&socket_addr)?;
-
-        let fd = SharedFd::new(sys_listener.into_raw_fd());
-
-        Ok(Self { fd })
+        Ok(())
     }
 
     pub(crate) fn listen(&self, backlog: libc::c_int) -> io::Result<()> {
@@ -238,6 +449,30 @@ impl Socket {
         socket_ref.shutdown(how)
     }
 
+    pub(crate) fn set_max_in_flight(&self, max: Option<usize>) {
+        self.fd.set_max_in_flight(max)
+    }
+
+    pub(crate) fn set_timestamping(&self, flags: Timestamping) -> io::Result<()> {
+        self.set_int_option(libc::SOL_SOCKET, SO_TIMESTAMPING, flags.bits())
+    }
+
+    pub(crate) fn set_int_option(
+        &self,
+        level: libc::c_int,
+        name: libc::c_int,
+        value: libc::c_int,
+    ) -> io::Result<()> {
+        syscall!(setsockopt(
+            self.as_raw_fd(),
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        ))?;
+        Ok(())
+    }
+
     /// Set the value of the `TCP_NODELAY` option on this socket.
     ///
     /// If set, this option disables the Nagle algorithm. This means that
@@ -256,3 +491,26 @@ impl AsRawFd for Socket {
         self.fd.raw_fd()
     }
 }
+
+// Performs an operation on the ring, or with the equivalent system call if
+// the kernel does not support `opcode`, as probed when the runtime started.
+// The errors of the operation are returned as they are.
+async fn ring_or_syscall<T, O>(
+    opcode: u8,
+    op: impl FnOnce() -> io::Result<Op<O>>,
+    syscall: impl FnOnce() -> io::Result<T>,
+) -> io::Result<T>
+where
+    O: Completable<Output = io::Result<T>> + Unpin + 'static,
+{
+    let supported = CONTEXT.with(|x| {
+        x.handle()
+            .expect("Not in a runtime context")
+            .supports_opcode(opcode)
+    });
+    if supported {
+        op()?.await
+    } else {
+        syscall()
+    }
+}
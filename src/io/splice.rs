@@ -0,0 +1,112 @@
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::CONTEXT;
+use std::io;
+
+pub(crate) struct Splice {
+    // Keep the file descriptors open while the operation is in flight
+    fd_in: SharedFd,
+    fd_out: SharedFd,
+}
+
+impl Op<Splice> {
+    /// Moves up to `len` bytes from `fd_in` to `fd_out`, one of which must
+    /// be a pipe, at the current positions of the descriptors.
+    pub(crate) fn splice(fd_in: &SharedFd, fd_out: &SharedFd, len: u32) -> io::Result<Op<Splice>> {
+        use io_uring::{opcode, types};
+
+        CONTEXT.with(|x| {
+            x.handle().expect("Not in a runtime context").submit_op(
+                Splice {
+                    fd_in: fd_in.clone(),
+                    fd_out: fd_out.clone(),
+                },
+                |splice| {
+                    opcode::Splice::new(
+                        types::Fd(splice.fd_in.raw_fd()),
+                        -1,
+                        types::Fd(splice.fd_out.raw_fd()),
+                        -1,
+                        len,
+                    )
+                    .flags(libc::SPLICE_F_MOVE)
+                    .build()
+                },
+            )
+        })
+    }
+}
+
+impl Completable for Splice {
+    type Output = io::Result<usize>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        cqe.result.map(|n| n as usize)
+    }
+}
+
+/// A pipe through which data is spliced from one descriptor to another,
+/// kept for the whole transfer.
+///
+/// Data which has been moved into the pipe and not out of it, because the
+/// future moving it has been dropped or sending it has failed, stays in the
+/// pipe, and is moved out first on the next call. Each end of the pipe is
+/// used by one operation at a time, so that the next call waits for the
+/// operations of a dropped one to complete.
+pub(crate) struct SplicePipe {
+    rd: SharedFd,
+    wr: SharedFd,
+}
+
+impl SplicePipe {
+    pub(crate) fn new() -> io::Result<SplicePipe> {
+        let mut fds = [0; 2];
+        syscall!(pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC))?;
+        let (rd, wr) = (SharedFd::new(fds[0]), SharedFd::new(fds[1]));
+        rd.set_max_in_flight(Some(1));
+        wr.set_max_in_flight(Some(1));
+        Ok(SplicePipe { rd, wr })
+    }
+
+    // Returns the number of bytes in the pipe
+    fn pending(&self) -> io::Result<usize> {
+        let mut n: libc::c_int = 0;
+        syscall!(ioctl(self.rd.raw_fd(), libc::FIONREAD, &mut n))?;
+        Ok(n as usize)
+    }
+
+    /// Moves up to `len` bytes from `src` to `dst` through the pipe, without
+    /// copying the data to user space. Returns the number of bytes moved to
+    /// `dst`, or 0 at the end of the stream of `src`.
+    ///
+    /// The data left in the pipe by a previous call is moved first. If
+    /// moving the data to `dst` fails after some of it has been moved, the
+    /// partial count is returned, and the rest of the data stays in the
+    /// pipe.
+    pub(crate) async fn splice(
+        &self,
+        src: &SharedFd,
+        dst: &SharedFd,
+        len: u32,
+    ) -> io::Result<usize> {
+        let wr = self.wr.acquire().await;
+        let rd = self.rd.acquire().await;
+
+        let mut queued = self.pending()?.min(len as usize);
+        if queued == 0 {
+            queued = Op::splice(src, &wr, len)?.await?;
+        }
+
+        let mut moved = 0;
+        while moved < queued {
+            match Op::splice(&rd, dst, (queued - moved) as u32)?.await {
+                Ok(0) if moved == 0 => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(0) => break,
+                Ok(n) => moved += n,
+                Err(e) if moved == 0 => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(moved)
+    }
+}
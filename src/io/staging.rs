@@ -0,0 +1,151 @@
+use crate::buf::{BoundedBuf, Slice};
+use crate::io::read::Read;
+use crate::io::write::Write;
+use crate::io::Socket;
+use crate::runtime::driver::op::Op;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::ReadBuf;
+
+// Size of the staging buffers allocated for each direction.
+const STAGING_BUF_SIZE: usize = 8 * 1024;
+
+// Adapts the owned buffer operations of a socket to the poll-based
+// `tokio::io::AsyncRead` and `AsyncWrite` traits.
+//
+// The caller's data is copied to and from internal buffers owned by this
+// struct, which are passed to the kernel while an operation is in flight.
+// At most one read and one write operation is in flight at any time.
+pub(crate) struct Staging {
+    // Data received from the socket. Bytes before `read_pos` have been
+    // consumed by the caller.
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    read_op: Option<Op<Read<Vec<u8>>>>,
+
+    // Buffer for the data accepted from the caller, reused once
+    // a write operation has completed.
+    write_buf: Vec<u8>,
+    write_op: Option<Op<Write<Slice<Vec<u8>>>>>,
+}
+
+impl Staging {
+    pub(crate) fn new() -> Self {
+        Staging {
+            read_buf: Vec::new(),
+            read_pos: 0,
+            read_op: None,
+            write_buf: Vec::new(),
+            write_op: None,
+        }
+    }
+
+    pub(crate) fn poll_read(
+        &mut self,
+        socket: &Socket,
+        cx: &mut Context<'_>,
+        dst: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if let Some(op) = &mut self.read_op {
+                let (res, buf) = ready!(Pin::new(op).poll(cx));
+                self.read_op = None;
+                self.read_buf = buf;
+                self.read_pos = 0;
+                // Zero bytes read signals the end of stream, which is
+                // reported by not filling `dst`.
+                if res? == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+            }
+
+            let available = &self.read_buf[self.read_pos..];
+            if !available.is_empty() {
+                let n = std::cmp::min(available.len(), dst.remaining());
+                dst.put_slice(&available[..n]);
+                self.read_pos += n;
+                return Poll::Ready(Ok(()));
+            }
+
+            if dst.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut buf = mem::take(&mut self.read_buf);
+            buf.clear();
+            buf.reserve(STAGING_BUF_SIZE);
+            self.read_op = Some(Op::read_at(&socket.fd, buf, 0)?);
+        }
+    }
+
+    pub(crate) fn poll_write(
+        &mut self,
+        socket: &Socket,
+        cx: &mut Context<'_>,
+        src: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_flush(socket, cx))?;
+
+        if src.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = std::cmp::min(src.len(), STAGING_BUF_SIZE);
+        let mut buf = mem::take(&mut self.write_buf);
+        buf.extend_from_slice(&src[..n]);
+        self.write_op = Some(Op::write_at(&socket.fd, buf.slice(..), 0)?);
+
+        Poll::Ready(Ok(n))
+    }
+
+    // Resolves when all data accepted by `poll_write` has been written
+    // to the socket.
+    pub(crate) fn poll_flush(
+        &mut self,
+        socket: &Socket,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        while let Some(op) = &mut self.write_op {
+            let (res, slice) = ready!(Pin::new(op).poll(cx));
+            self.write_op = None;
+            let begin = slice.begin();
+            let mut buf = slice.into_inner();
+            match res {
+                Ok(0) => {
+                    buf.clear();
+                    self.write_buf = buf;
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    )));
+                }
+                Ok(n) if begin + n < buf.len() => {
+                    self.write_op = Some(Op::write_at(&socket.fd, buf.slice(begin + n..), 0)?);
+                }
+                Ok(_) => {
+                    buf.clear();
+                    self.write_buf = buf;
+                }
+                Err(e) => {
+                    buf.clear();
+                    self.write_buf = buf;
+                    return Poll::Ready(Err(e));
+                }
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    pub(crate) fn poll_shutdown(
+        &mut self,
+        socket: &Socket,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush(socket, cx))?;
+        Poll::Ready(socket.shutdown(std::net::Shutdown::Write))
+    }
+}
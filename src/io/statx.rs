@@ -0,0 +1,79 @@
+use crate::io::SharedFd;
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::CONTEXT;
+use io_uring::squeue;
+use std::ffi::CString;
+use std::path::Path;
+use std::{io, mem};
+
+/// Retrieves the status of a file.
+pub(crate) struct Statx {
+    // Keeps the directory or file descriptor open while the operation is
+    // in flight.
+    fd: Option<SharedFd>,
+
+    path: CString,
+
+    flags: i32,
+
+    // The buffer is boxed so that its address does not change when the
+    // operation is moved.
+    statx: Box<libc::statx>,
+}
+
+impl Statx {
+    /// Prepares a request to retrieve the status of the file at the path,
+    /// relative to the directory `fd` or the current working directory if
+    /// `fd` is `None`.
+    ///
+    /// An empty path with the `AT_EMPTY_PATH` flag refers to the file `fd`
+    /// itself.
+    pub(crate) fn new(fd: Option<&SharedFd>, path: &Path, flags: i32) -> io::Result<Statx> {
+        Ok(Statx {
+            fd: fd.cloned(),
+            path: super::util::cstr(path)?,
+            flags,
+            // Safety: the structure is plain data, and all zeroes is
+            // a valid value.
+            statx: Box::new(unsafe { mem::zeroed() }),
+        })
+    }
+
+    pub(crate) fn sqe(&mut self) -> squeue::Entry {
+        use io_uring::{opcode, types};
+
+        let dirfd = self.fd.as_ref().map_or(libc::AT_FDCWD, |fd| fd.raw_fd());
+
+        opcode::Statx::new(
+            types::Fd(dirfd),
+            self.path.as_ptr(),
+            &mut *self.statx as *mut libc::statx as *mut types::statx,
+        )
+        .flags(self.flags)
+        .mask(libc::STATX_BASIC_STATS | libc::STATX_BTIME)
+        .build()
+    }
+}
+
+impl Op<Statx> {
+    /// Submit a request to retrieve the status of a file.
+    ///
+    /// See [`Statx::new`] for the meaning of the arguments.
+    pub(crate) fn statx(fd: Option<&SharedFd>, path: &Path, flags: i32) -> io::Result<Op<Statx>> {
+        let statx = Statx::new(fd, path, flags)?;
+
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(statx, |statx| statx.sqe())
+        })
+    }
+}
+
+impl Completable for Statx {
+    type Output = io::Result<libc::statx>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        cqe.result.map(|_| *self.statx)
+    }
+}
@@ -0,0 +1,41 @@
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::CONTEXT;
+use io_uring::{opcode, types};
+use std::io;
+use std::time::Duration;
+
+pub(crate) struct Timeout {
+    // Read by the kernel when the operation is submitted
+    #[allow(dead_code)]
+    timespec: Box<types::Timespec>,
+}
+
+impl Op<Timeout> {
+    /// Submits a timeout which completes once `duration` has elapsed.
+    pub(crate) fn timeout(duration: Duration) -> io::Result<Op<Timeout>> {
+        let timespec = Box::new(
+            types::Timespec::new()
+                .sec(duration.as_secs())
+                .nsec(duration.subsec_nanos()),
+        );
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(Timeout { timespec }, |timeout| {
+                    opcode::Timeout::new(&*timeout.timespec).build()
+                })
+        })
+    }
+}
+
+impl Completable for Timeout {
+    type Output = io::Result<()>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        match cqe.result {
+            // The timeout has elapsed
+            Err(e) if e.raw_os_error() == Some(libc::ETIME) => Ok(()),
+            res => res.map(|_| ()),
+        }
+    }
+}
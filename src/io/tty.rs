@@ -0,0 +1,264 @@
+use crate::buf::provided::BufRing;
+use crate::buf::{BoundedBuf, BoundedBufMut};
+use crate::fs::{File, OpenOptions, ReadChunks};
+use crate::io::Socket;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+
+/// A terminal or a serial device.
+///
+/// Reads and writes are performed by the runtime, without a thread blocked
+/// on the device. The line settings of the device are configured with
+/// [`Termios`], e.g. to switch an interactive terminal to raw mode for the
+/// duration of the program.
+///
+/// # Examples
+///
+/// Echoing the keys typed, until `q`:
+///
+/// ```no_run
+/// use tokio_uring::io::Tty;
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         let tty = Tty::open("/dev/tty").await?;
+///         let saved = tty.set_raw()?;
+///
+///         let mut buf = vec![0; 64];
+///         loop {
+///             let (res, b) = tty.read(buf).await;
+///             let n = res?;
+///             if b[..n].contains(&b'q') {
+///                 break;
+///             }
+///             let (res, b) = tty.write_all(b).await;
+///             res?;
+///             buf = b;
+///         }
+///
+///         tty.set_termios(&saved)
+///     })
+/// }
+/// ```
+pub struct Tty {
+    inner: Socket,
+}
+
+impl Tty {
+    /// Opens a terminal or a serial device for reading and writing.
+    ///
+    /// The device is opened without becoming the controlling terminal of
+    /// the process.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ENOTTY` if the file is not a terminal.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Tty> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(path)
+            .await?;
+        Tty::from_file(file)
+    }
+
+    /// Converts an open file of a terminal or a serial device, such as one
+    /// of a pseudoterminal or a duplicate of the standard input.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ENOTTY` if the file is not a terminal.
+    pub fn from_file(file: File) -> io::Result<Tty> {
+        let tty = Tty {
+            inner: Socket::from_shared_fd(file.into_shared_fd()),
+        };
+        if unsafe { libc::isatty(tty.as_raw_fd()) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(tty)
+    }
+
+    /// Reads some data from the device into the buffer, returning the
+    /// original buffer and quantity of data read.
+    ///
+    /// The read waits for data. In canonical mode, it returns a line at a
+    /// time, and 0 at the end of file character.
+    pub async fn read<T: BoundedBufMut>(&self, buf: T) -> crate::BufResult<usize, T> {
+        self.inner.read(buf).await
+    }
+
+    /// Reads the data from the device as a stream of chunks, into the
+    /// buffers of `ring`, with a single multishot read kept in flight.
+    ///
+    /// See [`ReadChunks`] for the details.
+    pub fn read_multi(&self, ring: &BufRing) -> ReadChunks {
+        ReadChunks::new(self.inner.fd.clone(), ring)
+    }
+
+    /// Writes some data from the buffer to the device, returning the
+    /// original buffer and quantity of data written.
+    pub async fn write<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<usize, T> {
+        self.inner.write(buf).await
+    }
+
+    /// Attempts to write an entire buffer to the device.
+    ///
+    /// This method will continuously call [`write`] until there is no more
+    /// data to be written or an error is returned.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error that [`write`] returns.
+    ///
+    /// [`write`]: Self::write
+    pub async fn write_all<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<(), T> {
+        self.inner.write_all(buf).await
+    }
+
+    /// Returns the current settings of the device.
+    pub fn termios(&self) -> io::Result<Termios> {
+        let mut termios = unsafe { mem::zeroed() };
+        syscall!(tcgetattr(self.as_raw_fd(), &mut termios))?;
+        Ok(Termios(termios))
+    }
+
+    /// Changes the settings of the device, right away.
+    pub fn set_termios(&self, termios: &Termios) -> io::Result<()> {
+        syscall!(tcsetattr(self.as_raw_fd(), libc::TCSANOW, &termios.0))?;
+        Ok(())
+    }
+
+    /// Switches the device to raw mode, returning the previous settings,
+    /// which the device can be switched back to with [`set_termios`].
+    ///
+    /// See [`Termios::make_raw`] for the settings of raw mode.
+    ///
+    /// [`set_termios`]: Tty::set_termios
+    pub fn set_raw(&self) -> io::Result<Termios> {
+        let saved = self.termios()?;
+        let mut raw = saved.clone();
+        raw.make_raw();
+        self.set_termios(&raw)?;
+        Ok(saved)
+    }
+
+    /// Returns the size of the terminal window, as the number of rows and
+    /// columns.
+    pub fn window_size(&self) -> io::Result<(u16, u16)> {
+        let mut size: libc::winsize = unsafe { mem::zeroed() };
+        syscall!(ioctl(self.as_raw_fd(), libc::TIOCGWINSZ, &mut size))?;
+        Ok((size.ws_row, size.ws_col))
+    }
+
+    /// Sets the size of the terminal window, as the number of rows and
+    /// columns, as done by a terminal emulator on the master side of a
+    /// pseudoterminal.
+    pub fn set_window_size(&self, rows: u16, cols: u16) -> io::Result<()> {
+        let size = libc::winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        syscall!(ioctl(self.as_raw_fd(), libc::TIOCSWINSZ, &size))?;
+        Ok(())
+    }
+
+    /// Closes the device.
+    ///
+    /// The method completes once the close operation has completed,
+    /// guaranteeing that resources associated with the device have been
+    /// released.
+    pub async fn close(self) -> io::Result<()> {
+        self.inner.fd.close().await;
+        Ok(())
+    }
+}
+
+impl AsRawFd for Tty {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+/// The settings of a terminal or a serial device.
+///
+/// Returned by [`Tty::termios`], changed with the methods of this type or
+/// through the underlying `libc::termios`, and applied with
+/// [`Tty::set_termios`].
+#[derive(Clone)]
+pub struct Termios(libc::termios);
+
+impl Termios {
+    /// Changes the settings to raw mode: input is available byte by byte,
+    /// without echo, line editing or signal characters, and output is
+    /// written as is. A read waits for at least one byte.
+    pub fn make_raw(&mut self) {
+        unsafe { libc::cfmakeraw(&mut self.0) };
+        self.0.c_cc[libc::VMIN] = 1;
+        self.0.c_cc[libc::VTIME] = 0;
+    }
+
+    /// Sets whether the characters received are echoed back.
+    pub fn set_echo(&mut self, echo: bool) {
+        if echo {
+            self.0.c_lflag |= libc::ECHO;
+        } else {
+            self.0.c_lflag &= !libc::ECHO;
+        }
+    }
+
+    /// Sets the input and output speed of a serial line, in bauds.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `EINVAL` if the speed is not one of the standard speeds,
+    /// such as 9600 or 115200.
+    pub fn set_speed(&mut self, baud: u32) -> io::Result<()> {
+        let speed = match baud {
+            1200 => libc::B1200,
+            2400 => libc::B2400,
+            4800 => libc::B4800,
+            9600 => libc::B9600,
+            19200 => libc::B19200,
+            38400 => libc::B38400,
+            57600 => libc::B57600,
+            115200 => libc::B115200,
+            230400 => libc::B230400,
+            460800 => libc::B460800,
+            921600 => libc::B921600,
+            1000000 => libc::B1000000,
+            2000000 => libc::B2000000,
+            4000000 => libc::B4000000,
+            _ => return Err(io::Error::from_raw_os_error(libc::EINVAL)),
+        };
+        syscall!(cfsetspeed(&mut self.0, speed))?;
+        Ok(())
+    }
+
+    /// Returns the underlying settings.
+    pub fn as_raw(&self) -> &libc::termios {
+        &self.0
+    }
+
+    /// Returns the underlying settings, to be changed.
+    pub fn as_raw_mut(&mut self) -> &mut libc::termios {
+        &mut self.0
+    }
+}
+
+impl fmt::Debug for Termios {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Termios")
+            .field("iflag", &self.0.c_iflag)
+            .field("oflag", &self.0.c_oflag)
+            .field("cflag", &self.0.c_cflag)
+            .field("lflag", &self.0.c_lflag)
+            .finish_non_exhaustive()
+    }
+}
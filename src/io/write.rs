@@ -1,8 +1,18 @@
+use crate::io::fsync::Fsync;
+use crate::io::read::Read;
 use crate::runtime::driver::op::{Completable, CqeResult, Op};
 use crate::runtime::CONTEXT;
-use crate::{buf::BoundedBuf, io::SharedFd, BufResult};
+use crate::{
+    buf::{BoundedBuf, BoundedBufMut, SubmittedPtr},
+    io::SharedFd,
+    BufResult,
+};
+use io_uring::{opcode, squeue, types};
 use std::io;
 
+// The operations of a write linked with a read.
+type WriteThenRead<T, U> = (Op<Write<T>>, Op<Read<U>>);
+
 pub(crate) struct Write<T> {
     /// Holds a strong ref to the FD, preventing the file from being closed
     /// while the operation is in-flight.
@@ -10,33 +20,93 @@ pub(crate) struct Write<T> {
     fd: SharedFd,
 
     buf: T,
+
+    /// The address of the buffer submitted to the kernel.
+    submitted: SubmittedPtr,
+}
+
+impl<T: BoundedBuf> Write<T> {
+    fn new(fd: &SharedFd, buf: T) -> Write<T> {
+        Write {
+            fd: fd.clone(),
+            buf,
+            submitted: SubmittedPtr::default(),
+        }
+    }
+
+    fn sqe(&mut self, offset: u64, rw_flags: i32) -> squeue::Entry {
+        // Get raw buffer info
+        let ptr = self.buf.stable_ptr();
+        self.submitted.set(ptr);
+        let len = self.buf.bytes_init();
+
+        opcode::Write::new(types::Fd(self.fd.raw_fd()), ptr, len as _)
+            .offset(offset as _)
+            .rw_flags(rw_flags)
+            .build()
+    }
 }
 
 impl<T: BoundedBuf> Op<Write<T>> {
     pub(crate) fn write_at(fd: &SharedFd, buf: T, offset: u64) -> io::Result<Op<Write<T>>> {
-        use io_uring::{opcode, types};
+        Op::write_at_with(fd, buf, offset, 0)
+    }
+
+    /// Submits a write with the `RWF_*` flags of `pwritev2`.
+    pub(crate) fn write_at_with(
+        fd: &SharedFd,
+        buf: T,
+        offset: u64,
+        rw_flags: i32,
+    ) -> io::Result<Op<Write<T>>> {
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(Write::new(fd, buf), |write| write.sqe(offset, rw_flags))
+        })
+    }
+
+    /// Submits a write linked with a sync of the file, which is started
+    /// once all of the data has been written.
+    pub(crate) fn write_at_sync(
+        fd: &SharedFd,
+        buf: T,
+        offset: u64,
+        flags: types::FsyncFlags,
+    ) -> io::Result<(Op<Write<T>>, Op<Fsync>)> {
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_linked_ops(
+                    Write::new(fd, buf),
+                    |write| write.sqe(offset, 0),
+                    Fsync::new(fd),
+                    |fsync| fsync.sqe(flags),
+                )
+        })
+    }
 
+    /// Submits a write linked with a read of the same file, which is
+    /// started once all of the data has been written.
+    pub(crate) fn write_then_read<U: BoundedBufMut>(
+        fd: &SharedFd,
+        buf: T,
+        read_buf: U,
+    ) -> io::Result<WriteThenRead<T, U>> {
         CONTEXT.with(|x| {
-            x.handle().expect("Not in a runtime context").submit_op(
-                Write {
-                    fd: fd.clone(),
-                    buf,
-                },
-                |write| {
-                    // Get raw buffer info
-                    let ptr = write.buf.stable_ptr();
-                    let len = write.buf.bytes_init();
-
-                    opcode::Write::new(types::Fd(fd.raw_fd()), ptr, len as _)
-                        .offset(offset as _)
-                        .build()
-                },
-            )
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_linked_ops(
+                    Write::new(fd, buf),
+                    |write| write.sqe(0, 0),
+                    Read::new(fd, read_buf),
+                    |read| read.sqe(0, 0),
+                )
         })
     }
 }
 
-impl<T> Completable for Write<T> {
+impl<T: BoundedBuf> Completable for Write<T> {
     type Output = BufResult<usize, T>;
 
     fn complete(self, cqe: CqeResult) -> Self::Output {
@@ -44,6 +114,7 @@ impl<T> Completable for Write<T> {
         let res = cqe.result.map(|v| v as usize);
         // Recover the buffer
         let buf = self.buf;
+        self.submitted.check(buf.stable_ptr());
 
         (res, buf)
     }
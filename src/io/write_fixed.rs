@@ -1,5 +1,5 @@
 use crate::buf::fixed::FixedBuf;
-use crate::buf::BoundedBuf;
+use crate::buf::{BoundedBuf, SubmittedPtr};
 use crate::io::SharedFd;
 use crate::runtime::driver::op::{self, Completable, Op};
 use crate::BufResult;
@@ -14,6 +14,9 @@ pub(crate) struct WriteFixed<T> {
     fd: SharedFd,
 
     buf: T,
+
+    /// The address of the buffer submitted to the kernel.
+    submitted: SubmittedPtr,
 }
 
 impl<T> Op<WriteFixed<T>>
@@ -32,10 +35,12 @@ where
                 WriteFixed {
                     fd: fd.clone(),
                     buf,
+                    submitted: SubmittedPtr::default(),
                 },
                 |write_fixed| {
                     // Get raw buffer info
                     let ptr = write_fixed.buf.stable_ptr();
+                    write_fixed.submitted.set(ptr);
                     let len = write_fixed.buf.bytes_init();
                     let buf_index = write_fixed.buf.get_buf().buf_index();
                     opcode::WriteFixed::new(types::Fd(fd.raw_fd()), ptr, len as _, buf_index)
@@ -47,7 +52,10 @@ where
     }
 }
 
-impl<T> Completable for WriteFixed<T> {
+impl<T> Completable for WriteFixed<T>
+where
+    T: BoundedBuf<Buf = FixedBuf>,
+{
     type Output = BufResult<usize, T>;
 
     fn complete(self, cqe: op::CqeResult) -> Self::Output {
@@ -55,6 +63,7 @@ impl<T> Completable for WriteFixed<T> {
         let res = cqe.result.map(|v| v as usize);
         // Recover the buffer
         let buf = self.buf;
+        self.submitted.check(buf.stable_ptr());
 
         (res, buf)
     }
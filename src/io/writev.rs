@@ -1,6 +1,8 @@
+use crate::io::fsync::Fsync;
 use crate::runtime::driver::op::{Completable, CqeResult, Op};
 use crate::runtime::CONTEXT;
 use crate::{buf::IoBuf, io::SharedFd, BufResult};
+use io_uring::{squeue, types};
 use libc::iovec;
 use std::io;
 
@@ -16,40 +18,87 @@ pub(crate) struct Writev<T> {
     iovs: Vec<iovec>,
 }
 
-impl<T: IoBuf> Op<Writev<T>> {
-    pub(crate) fn writev_at(
-        fd: &SharedFd,
-        mut bufs: Vec<T>,
-        offset: u64,
-    ) -> io::Result<Op<Writev<T>>> {
-        use io_uring::{opcode, types};
-
+impl<T: IoBuf> Writev<T> {
+    /// Prepares the write of the data of `bufs`, past the first `skip` bytes.
+    fn new(fd: &SharedFd, mut bufs: Vec<T>, mut skip: usize) -> Writev<T> {
         // Build `iovec` objects referring the provided `bufs` for `io_uring::opcode::Readv`.
         let iovs: Vec<iovec> = bufs
             .iter_mut()
-            .map(|b| iovec {
-                iov_base: b.stable_ptr() as *mut libc::c_void,
-                iov_len: b.bytes_init(),
+            .filter_map(|b| {
+                let len = b.bytes_init();
+                if skip >= len {
+                    skip -= len;
+                    return None;
+                }
+                let iov = iovec {
+                    iov_base: unsafe { b.stable_ptr().add(skip) } as *mut libc::c_void,
+                    iov_len: len - skip,
+                };
+                skip = 0;
+                Some(iov)
             })
             .collect();
 
+        Writev {
+            fd: fd.clone(),
+            bufs,
+            iovs,
+        }
+    }
+
+    fn sqe(&self, offset: u64) -> squeue::Entry {
+        use io_uring::opcode;
+
+        opcode::Writev::new(
+            types::Fd(self.fd.raw_fd()),
+            self.iovs.as_ptr(),
+            self.iovs.len() as u32,
+        )
+        .offset(offset as _)
+        .build()
+    }
+}
+
+impl<T: IoBuf> Op<Writev<T>> {
+    pub(crate) fn writev_at(fd: &SharedFd, bufs: Vec<T>, offset: u64) -> io::Result<Op<Writev<T>>> {
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(Writev::new(fd, bufs, 0), |write| write.sqe(offset))
+        })
+    }
+
+    /// Submit a vectored write of the data of `bufs` past the first `skip`
+    /// bytes, resuming a write that has been completed short.
+    pub(crate) fn writev_at_skip(
+        fd: &SharedFd,
+        bufs: Vec<T>,
+        skip: usize,
+        offset: u64,
+    ) -> io::Result<Op<Writev<T>>> {
+        CONTEXT.with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(Writev::new(fd, bufs, skip), |write| write.sqe(offset))
+        })
+    }
+
+    /// Submit a vectored write linked with a data sync of the file, which is
+    /// started once all of the data has been written.
+    pub(crate) fn writev_at_datasync(
+        fd: &SharedFd,
+        bufs: Vec<T>,
+        offset: u64,
+    ) -> io::Result<(Op<Writev<T>>, Op<Fsync>)> {
         CONTEXT.with(|x| {
-            x.handle().expect("Not in a runtime context").submit_op(
-                Writev {
-                    fd: fd.clone(),
-                    bufs,
-                    iovs,
-                },
-                |write| {
-                    opcode::Writev::new(
-                        types::Fd(fd.raw_fd()),
-                        write.iovs.as_ptr(),
-                        write.iovs.len() as u32,
-                    )
-                    .offset(offset as _)
-                    .build()
-                },
-            )
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_linked_ops(
+                    Writev::new(fd, bufs, 0),
+                    |write| write.sqe(offset),
+                    Fsync::new(fd),
+                    |fsync| fsync.sqe(types::FsyncFlags::DATASYNC),
+                )
         })
     }
 }
@@ -55,6 +55,30 @@
 //! will happen in the background. There is no guarantee as to **when** the
 //! implicit close-on-drop operation happens, so it is recommended to explicitly
 //! call `close()`.
+//!
+//! # Metrics
+//!
+//! With the `metrics` feature, the runtime reports metrics through the
+//! [`metrics`] facade, so that they are collected by the installed recorder,
+//! e.g. a Prometheus exporter. The metrics are aggregated over all the
+//! runtimes of the process:
+//!
+//! | Name | Type | Description |
+//! |------|------|-------------|
+//! | `tokio_uring_ops_submitted_total` | counter | Operations submitted |
+//! | `tokio_uring_ops_completed_total` | counter | Operations completed |
+//! | `tokio_uring_ops_failed_total` | counter | Completions with an error |
+//! | `tokio_uring_ops_in_flight` | gauge | Operations submitted and not completed |
+//! | `tokio_uring_op_duration_seconds` | histogram | Time from submission to completion |
+//! | `tokio_uring_bytes_read_total` | counter | Bytes read or received |
+//! | `tokio_uring_bytes_written_total` | counter | Bytes written or sent |
+//! | `tokio_uring_fixed_buffers` | gauge | Buffers of fixed buffer collections |
+//! | `tokio_uring_fixed_buffers_checked_out` | gauge | Fixed buffers in use |
+//! | `tokio_uring_stalls_total` | counter | Stalls detected by the [stall watchdog] |
+//!
+//! [stall watchdog]: Builder::stall_watchdog
+//!
+//! [`metrics`]: https://docs.rs/metrics
 
 #![warn(missing_docs)]
 
@@ -71,18 +95,57 @@ macro_rules! syscall {
 
 #[macro_use]
 mod future;
-mod io;
+pub mod io;
 mod runtime;
 
 pub mod buf;
+#[cfg(feature = "test-util")]
+pub mod fault;
 pub mod fs;
+pub mod limit;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "test-util")]
+pub mod mock;
 pub mod net;
+pub mod op;
+pub mod process;
+pub mod reload;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod task;
+pub mod time;
 
 pub use runtime::spawn;
+pub use runtime::spawn_blocking;
+pub use runtime::with_cancellation;
+pub use runtime::with_op_label;
+pub use runtime::with_personality;
+pub use runtime::with_priority;
+pub use runtime::Batch;
+pub use runtime::Decision;
+pub use runtime::EnterGuard;
+pub use runtime::FlushPolicy;
+pub use runtime::Handle;
+pub use runtime::InflightOp;
+pub use runtime::Personality;
+pub use runtime::Priority;
+pub use runtime::RetryPolicy;
 pub use runtime::Runtime;
+pub use runtime::SpreadSpawner;
+pub use runtime::SqeInfo;
+pub use runtime::SubmitStats;
+pub use runtime::TickStats;
+
+// Items used by the expansions of the macros of the crate
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::runtime::{submit_together, MaybeDone};
+}
 
 use crate::runtime::driver::op::Op;
 use std::future::Future;
+use std::time::Duration;
 
 /// Start an `io_uring` enabled Tokio runtime.
 ///
@@ -161,7 +224,34 @@ pub fn uring_builder() -> io_uring::Builder {
 // #[derive(Clone, Default)]
 pub struct Builder {
     entries: u32,
+    cq_entries: Option<u32>,
+    cq_overflow: CqOverflow,
+    submit_all: bool,
+    retry_partial_submit: bool,
+    file_table: Option<u32>,
+    cancel_on_drop: bool,
+    flush_latency_critical: bool,
+    task_sqe_budget: Option<u32>,
+    flush_policy: FlushPolicy,
+    on_sq_full: Option<runtime::SubmitHook>,
+    on_flush: Option<runtime::SubmitHook>,
+    on_tick: Option<runtime::TickHook>,
+    on_park: Option<runtime::TickHook>,
+    on_unpark: Option<runtime::TickHook>,
+    op_inspector: Option<runtime::OpInspector>,
+    retry_policy: Option<RetryPolicy>,
+    #[cfg(feature = "test-util")]
+    mock: Option<mock::MockHandler>,
+    #[cfg(feature = "test-util")]
+    start_paused: bool,
+    #[cfg(feature = "test-util")]
+    fault_policy: Option<fault::FaultPolicy>,
+    #[cfg(feature = "fallback")]
+    force_fallback: bool,
+    stall_threshold: Option<Duration>,
+    on_stall: Option<runtime::StallCallback>,
     urb: io_uring::Builder,
+    ring: Option<std::sync::Mutex<Option<io_uring::IoUring>>>,
 }
 
 /// Return a Builder to allow setting parameters before calling the start method.
@@ -171,7 +261,34 @@ pub struct Builder {
 pub fn builder() -> Builder {
     Builder {
         entries: 256,
+        cq_entries: None,
+        cq_overflow: CqOverflow::Backpressure,
+        submit_all: false,
+        retry_partial_submit: false,
+        file_table: None,
+        cancel_on_drop: false,
+        flush_latency_critical: true,
+        task_sqe_budget: None,
+        flush_policy: FlushPolicy::adaptive(),
+        on_sq_full: None,
+        on_flush: None,
+        on_tick: None,
+        on_park: None,
+        on_unpark: None,
+        op_inspector: None,
+        retry_policy: None,
+        #[cfg(feature = "test-util")]
+        mock: None,
+        #[cfg(feature = "test-util")]
+        start_paused: false,
+        #[cfg(feature = "test-util")]
+        fault_policy: None,
+        #[cfg(feature = "fallback")]
+        force_fallback: false,
+        stall_threshold: None,
+        on_stall: None,
         urb: io_uring::IoUring::builder(),
+        ring: None,
     }
 }
 
@@ -182,13 +299,439 @@ impl Builder {
     /// The kernel requires the number of completion queue entries to be larger than
     /// the submission queue entries so generally will double the sq entries count.
     ///
-    /// The caller can specify even a larger cq entries count by using the uring_builder
-    /// as shown in the start example below.
+    /// The caller can specify a larger cq entries count with [`cq_entries`].
+    ///
+    /// [`cq_entries`]: Builder::cq_entries
     pub fn entries(&mut self, e: u32) -> &mut Self {
         self.entries = e;
         self
     }
 
+    /// Set number of completion queue entries in uring, independently of the
+    /// number of submission queue entries.
+    ///
+    /// Servers with many operations in flight, e.g. reads on a large number
+    /// of connections, need a completion queue large enough to hold all the
+    /// completions arriving between two runs of the driver. When the
+    /// completion queue overflows, the driver reacts according to the
+    /// [`cq_overflow`] policy.
+    ///
+    /// The kernel will round this up to a power of two, and requires it to
+    /// be at least the number of submission queue entries. This overrides
+    /// `setup_cqsize` set on the [`uring_builder`].
+    ///
+    /// [`cq_overflow`]: Builder::cq_overflow
+    /// [`uring_builder`]: Builder::uring_builder
+    pub fn cq_entries(&mut self, n: u32) -> &mut Self {
+        self.cq_entries = Some(n);
+        self
+    }
+
+    /// Set how the driver reacts to completion queue overflow.
+    ///
+    /// The default is [`CqOverflow::Backpressure`].
+    pub fn cq_overflow(&mut self, policy: CqOverflow) -> &mut Self {
+        self.cq_overflow = policy;
+        self
+    }
+
+    /// Continue submitting a batch of operations when one of them fails to
+    /// be submitted (`IORING_SETUP_SUBMIT_ALL`).
+    ///
+    /// By default, the kernel stops at the first submission queue entry it
+    /// fails to submit, completing its operation with the error. The entries
+    /// following it are left in the submission queue until the next flush.
+    /// With this setting, the kernel submits all the entries, and only the
+    /// failed operations complete with an error.
+    ///
+    /// Requires Linux 5.18 or later; the runtime fails to start on older
+    /// kernels when enabled.
+    pub fn submit_all(&mut self, enable: bool) -> &mut Self {
+        self.submit_all = enable;
+        self
+    }
+
+    /// Retry submitting the remaining entries when the kernel only submits
+    /// a part of the submission queue.
+    ///
+    /// By default, a flush of the submission queue makes a single attempt,
+    /// and the entries which the kernel has not taken are submitted with the
+    /// next flush, when the runtime is about to wait for events. With this
+    /// setting, the flush is repeated as long as the kernel makes progress,
+    /// so the operations queued before a failed entry are not delayed.
+    pub fn retry_partial_submit(&mut self, enable: bool) -> &mut Self {
+        self.retry_partial_submit = enable;
+        self
+    }
+
+    /// Register a table of `size` direct descriptors when the runtime starts.
+    ///
+    /// Direct descriptors are slots in a per-ring table which operations can
+    /// open files and accept connections into, and refer to without the cost
+    /// of looking up a regular file descriptor. The table is registered
+    /// sparse, with all slots empty, and its size cannot be changed after the
+    /// runtime has started, so it should be chosen based on the expected
+    /// number of open files and connections.
+    ///
+    /// Requires Linux 5.19 or later; the runtime fails to start on older
+    /// kernels when set.
+    pub fn register_file_table(&mut self, size: u32) -> &mut Self {
+        self.file_table = Some(size);
+        self
+    }
+
+    /// Cancel operations whose futures are dropped before completion.
+    ///
+    /// By default, when the future of an operation is dropped, e.g. as the
+    /// losing branch of `select!`, the operation keeps running in the
+    /// background until it completes, holding on to its buffer, file
+    /// descriptor and kernel resources. A read from a socket which never
+    /// receives data never completes. With this setting, dropping the
+    /// future submits an `IORING_OP_ASYNC_CANCEL` for the operation, so its
+    /// resources are released promptly.
+    ///
+    /// Note that the operation may have completed in the kernel before the
+    /// cancellation is processed, e.g. a write whose data has been written.
+    pub fn cancel_on_drop(&mut self, enable: bool) -> &mut Self {
+        self.cancel_on_drop = enable;
+        self
+    }
+
+    /// Submit [latency-critical] operations to the kernel as soon as they
+    /// are created, rather than with the next batch.
+    ///
+    /// This saves the latency of waiting for the other tasks to run before
+    /// the batch is submitted, at the cost of a system call per operation.
+    /// This is the default; once disabled, latency-critical operations are
+    /// submitted like those of the normal priority.
+    ///
+    /// [latency-critical]: Priority::LatencyCritical
+    pub fn flush_latency_critical(&mut self, enable: bool) -> &mut Self {
+        self.flush_latency_critical = enable;
+        self
+    }
+
+    /// Set the policy deciding when the entries of new operations are
+    /// submitted to the kernel.
+    ///
+    /// By default, the [adaptive] policy submits the entries early while
+    /// the load is light, and in batches otherwise. [`FlushPolicy::on_park`]
+    /// only submits them when the runtime parks, or when the submission
+    /// queue is full.
+    ///
+    /// [adaptive]: FlushPolicy::adaptive
+    pub fn flush_policy(&mut self, policy: FlushPolicy) -> &mut Self {
+        self.flush_policy = policy;
+        self
+    }
+
+    /// Limit the number of submission queue entries a task can queue in a
+    /// single poll ahead of the other tasks.
+    ///
+    /// Operations are submitted to the kernel in batches. Without a budget,
+    /// a task creating thousands of operations at once fills the submission
+    /// queue, and the operations of the other tasks wait behind them. With a
+    /// budget, the entries a task queues past the budget in a poll are
+    /// deferred to the end of the batch, like [bulk] operations.
+    ///
+    /// The budget applies to the tasks spawned with [`spawn`] and to the
+    /// future run by the runtime. By default, there is no budget.
+    ///
+    /// [bulk]: Priority::Bulk
+    pub fn task_sqe_budget(&mut self, budget: u32) -> &mut Self {
+        self.task_sqe_budget = Some(budget);
+        self
+    }
+
+    /// Set a callback invoked when an operation cannot be queued because
+    /// the submission queue is full.
+    ///
+    /// The queue is then submitted to the kernel to make room, which is a
+    /// stall of the task creating the operation. Frequent stalls suggest
+    /// using more submission queue [`entries`], or smaller batches.
+    ///
+    /// The callback is called on the runtime thread, with the submission
+    /// counters of the runtime. It must not perform io-uring operations.
+    ///
+    /// [`entries`]: Builder::entries
+    pub fn on_sq_full<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&SubmitStats) + Send + Sync + 'static,
+    {
+        self.on_sq_full = Some(std::sync::Arc::new(f));
+        self
+    }
+
+    /// Set a callback invoked when entries have been submitted to the
+    /// kernel.
+    ///
+    /// The callback is called on the runtime thread, with the submission
+    /// counters of the runtime, including the number of entries submitted.
+    /// It must not perform io-uring operations.
+    pub fn on_flush<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&SubmitStats) + Send + Sync + 'static,
+    {
+        self.on_flush = Some(std::sync::Arc::new(f));
+        self
+    }
+
+    /// Set a callback invoked each time the driver has processed the
+    /// completions posted by the kernel.
+    ///
+    /// The callback is called on the runtime thread, with the counters of
+    /// the event loop of the runtime, including the number of completions
+    /// processed. Together with [`on_flush`], [`on_park`] and
+    /// [`on_unpark`], it lets an application monitor the event loop, or
+    /// tune its own batching of operations to the load. It must not perform
+    /// io-uring operations.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let busiest = Arc::new(AtomicUsize::new(0));
+    /// tokio_uring::builder()
+    ///     .on_tick({
+    ///         let busiest = busiest.clone();
+    ///         move |stats| {
+    ///             busiest.fetch_max(stats.completions(), Ordering::Relaxed);
+    ///         }
+    ///     })
+    ///     .on_unpark(|stats| {
+    ///         if stats.park_duration().as_secs() >= 1 {
+    ///             eprintln!("idle for {:?}", stats.park_duration());
+    ///         }
+    ///     })
+    ///     .start(async {
+    ///         // Serve requests
+    ///     });
+    /// println!("most completions at once: {}", busiest.load(Ordering::Relaxed));
+    /// ```
+    ///
+    /// [`on_flush`]: Builder::on_flush
+    /// [`on_park`]: Builder::on_park
+    /// [`on_unpark`]: Builder::on_unpark
+    pub fn on_tick<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&TickStats) + Send + Sync + 'static,
+    {
+        self.on_tick = Some(std::sync::Arc::new(f));
+        self
+    }
+
+    /// Set a callback invoked when the runtime thread is about to park,
+    /// having no task to run, once the queued entries are submitted.
+    ///
+    /// The callback is called on the runtime thread, with the counters of
+    /// the event loop of the runtime, including the number of entries
+    /// submitted before parking. It must not perform io-uring operations.
+    pub fn on_park<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&TickStats) + Send + Sync + 'static,
+    {
+        self.on_park = Some(std::sync::Arc::new(f));
+        self
+    }
+
+    /// Set a callback invoked when the runtime thread is unparked.
+    ///
+    /// The callback is called on the runtime thread, with the counters of
+    /// the event loop of the runtime, including the time it has been
+    /// parked for. It must not perform io-uring operations.
+    pub fn on_unpark<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&TickStats) + Send + Sync + 'static,
+    {
+        self.on_unpark = Some(std::sync::Arc::new(f));
+        self
+    }
+
+    /// Set a hook inspecting each operation before it is submitted, which
+    /// decides whether the operation is submitted.
+    ///
+    /// The hook is passed the opcode, file descriptor, path and label of the
+    /// operation, and can log it or deny it. A denied operation is not
+    /// submitted, and fails with `EPERM`. If one of two linked operations
+    /// is denied, the other one is not submitted either, and fails with
+    /// `ECANCELED`.
+    ///
+    /// The hook is called on the runtime thread, when the operation is
+    /// created. It must not perform io-uring operations.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::Decision;
+    ///
+    /// tokio_uring::builder()
+    ///     .op_inspector(|op| match op.path() {
+    ///         Some(path) if !path.to_bytes().starts_with(b"/srv/") => {
+    ///             eprintln!("denied access to {:?}", path);
+    ///             Decision::Deny
+    ///         }
+    ///         _ => Decision::Allow,
+    ///     })
+    ///     .start(async {
+    ///         // Serve files from /srv only
+    ///     });
+    /// ```
+    pub fn op_inspector<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&SqeInfo<'_>) -> Decision + Send + Sync + 'static,
+    {
+        self.op_inspector = Some(std::sync::Arc::new(f));
+        self
+    }
+
+    /// Submit operations failing with transient errors again, according to
+    /// `policy`.
+    ///
+    /// Errors such as `EAGAIN` from a read with `RWF_NOWAIT`, or `ENOBUFS`
+    /// from a receive when the ring of provided buffers is empty, go away
+    /// when the operation is tried again a little later. With a policy, the
+    /// runtime retries the operations with an exponential backoff, and their
+    /// futures only complete with the error once the retries are exhausted,
+    /// so that the callers do not each need a retry loop. See
+    /// [`RetryPolicy`] for details.
+    ///
+    /// By default, the errors of all operations are returned.
+    pub fn retry_policy(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Complete operations with the results returned by `handler`, instead
+    /// of submitting them to the kernel.
+    ///
+    /// This allows testing code performing I/O with scripted results and
+    /// errors. Each operation is passed to the handler when it is created,
+    /// and completes with the returned result. See the [`mock`] module for
+    /// details.
+    ///
+    /// This method requires the `test-util` feature.
+    #[cfg(feature = "test-util")]
+    pub fn mock_driver<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(&mut mock::MockOp<'_>) -> std::io::Result<u32> + Send + Sync + 'static,
+    {
+        self.mock = Some(std::sync::Arc::new(mock::FnBackend(handler)));
+        self
+    }
+
+    /// Start the runtime with the clock paused, so that time is virtual.
+    ///
+    /// As with the `start_paused` option of Tokio's runtime builder, the
+    /// clock only advances when the runtime has no work to do, and then
+    /// jumps to the next timer, so that sleeps complete instantly. The
+    /// deadlines of [`time::timeout`] follow the virtual clock as well:
+    /// operations are not submitted with linked timeouts, which the kernel
+    /// would measure on the real clock, but are canceled when the virtual
+    /// deadline passes. This allows testing code heavy on sleeps and
+    /// timeouts quickly and deterministically.
+    ///
+    /// Since the clock advances whenever the runtime waits, an operation
+    /// waiting on the kernel for longer than nothing is pending on the
+    /// runtime times out as soon as the runtime is idle.
+    ///
+    /// This method requires the `test-util` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// tokio_uring::builder().start_paused(true).start(async {
+    ///     tokio::time::sleep(Duration::from_secs(3600)).await;
+    /// });
+    /// assert!(start.elapsed() < Duration::from_secs(3600));
+    /// ```
+    #[cfg(feature = "test-util")]
+    pub fn start_paused(&mut self, paused: bool) -> &mut Self {
+        self.start_paused = paused;
+        self
+    }
+
+    /// Inject faults into the operations, failing them or shortening reads
+    /// and writes according to `policy`.
+    ///
+    /// This allows exercising the retry and error paths of an application
+    /// with real files and sockets. See the [`fault`] module for details.
+    ///
+    /// This method requires the `test-util` feature.
+    #[cfg(feature = "test-util")]
+    pub fn fault_injection(&mut self, policy: fault::FaultPolicy) -> &mut Self {
+        self.fault_policy = Some(policy);
+        self
+    }
+
+    /// Perform the operations with the fallback backend, even if io_uring
+    /// is available.
+    ///
+    /// With the `fallback` feature, a runtime whose io_uring instance
+    /// cannot be created because io_uring is not supported by the kernel
+    /// or is blocked, e.g. by seccomp in a container, performs the
+    /// operations with blocking system calls on a pool of threads instead.
+    /// The API is the same, but the operations are slower, and
+    /// [`register_file_table`] and the fixed file operations are not
+    /// supported. Forcing the fallback allows testing an application with
+    /// it. [`Handle::is_fallback`] tells which backend a runtime uses.
+    ///
+    /// [`register_file_table`]: Builder::register_file_table
+    ///
+    /// This method requires the `fallback` feature.
+    #[cfg(feature = "fallback")]
+    pub fn force_fallback(&mut self, force: bool) -> &mut Self {
+        self.force_fallback = force;
+        self
+    }
+
+    /// Enable detection of runtime stalls, reported when the runtime thread
+    /// stays parked for longer than `threshold` while no io-uring operations
+    /// that would wake a task are in flight.
+    ///
+    /// Such a stall is often caused by a bug: an operation which is never
+    /// submitted, or a task which is never woken. However, tasks waiting for
+    /// timers, Tokio resources or channels also park the runtime without
+    /// io-uring operations, so the threshold should be longer than the time
+    /// these are expected to wait.
+    ///
+    /// Stalls are also reported when tasks woken by an event are not polled
+    /// within `threshold`, because another task blocks the runtime thread,
+    /// e.g. with a synchronous system call or a long computation.
+    ///
+    /// The stall is checked by a separate thread, and reported to the
+    /// callback set with [`on_stall`]. With the `metrics` feature, stalls are
+    /// also counted in the `tokio_uring_stalls_total` metric. Without either,
+    /// there is nothing to report stalls to, and building the runtime fails
+    /// with an error of the [`InvalidInput`] kind.
+    ///
+    /// [`InvalidInput`]: std::io::ErrorKind::InvalidInput
+    ///
+    /// [`on_stall`]: Builder::on_stall
+    pub fn stall_watchdog(&mut self, threshold: Duration) -> &mut Self {
+        self.stall_threshold = Some(threshold);
+        self
+    }
+
+    /// Set the callback reporting runtime stalls detected as configured by
+    /// [`stall_watchdog`].
+    ///
+    /// The callback is called on the watchdog thread with the time the
+    /// runtime has been stalled for, once for each stall.
+    ///
+    /// [`stall_watchdog`]: Builder::stall_watchdog
+    pub fn on_stall<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(Duration) + Send + Sync + 'static,
+    {
+        self.on_stall = Some(std::sync::Arc::new(f));
+        self
+    }
+
     /// Replace the default io_uring Builder. This allows the caller to craft the io_uring Builder
     /// using the io_uring crate's Builder API.
     ///
@@ -199,6 +742,56 @@ impl Builder {
         self
     }
 
+    /// Use an io_uring instance created with the `io-uring` crate, instead of
+    /// creating one.
+    ///
+    /// This allows a ring to be configured beyond what the builder methods
+    /// cover, e.g. with restrictions or registrations done before the
+    /// runtime starts. The ring must not have operations in flight, and if
+    /// the kernel polls its submission queue (`IORING_SETUP_SQPOLL`), it
+    /// must support `IORING_FEAT_NODROP`. The options setting up the ring,
+    /// [`entries`], [`cq_entries`], [`submit_all`] and [`uring_builder`],
+    /// are ignored; a table of registered files set with
+    /// [`register_file_table`] is registered with the ring.
+    ///
+    /// The ring is used by the first runtime created with the builder.
+    /// Creating another runtime with it fails.
+    ///
+    /// [`entries`]: Builder::entries
+    /// [`cq_entries`]: Builder::cq_entries
+    /// [`submit_all`]: Builder::submit_all
+    /// [`uring_builder`]: Builder::uring_builder
+    /// [`register_file_table`]: Builder::register_file_table
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use io_uring::{opcode, register::Restriction, IoUring};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let ring = IoUring::builder().setup_r_disabled().build(256)?;
+    ///
+    ///     // Allow the ring to be used for reads and writes only
+    ///     let mut restrictions = [
+    ///         Restriction::sqe_op(opcode::Read::CODE),
+    ///         Restriction::sqe_op(opcode::Write::CODE),
+    ///         Restriction::sqe_op(opcode::Close::CODE),
+    ///         Restriction::sqe_op(opcode::AsyncCancel::CODE),
+    ///     ];
+    ///     ring.submitter().register_restrictions(&mut restrictions)?;
+    ///     ring.submitter().register_enable_rings()?;
+    ///
+    ///     tokio_uring::builder().with_ring(ring).start(async {
+    ///         // ...
+    ///     });
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_ring(&mut self, ring: io_uring::IoUring) -> &mut Self {
+        self.ring = Some(std::sync::Mutex::new(Some(ring)));
+        self
+    }
+
     /// Start an `io_uring` enabled Tokio runtime.
     ///
     /// # Examples
@@ -212,9 +805,7 @@ impl Builder {
     /// fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     tokio_uring::builder()
     ///         .entries(64)
-    ///         .uring_builder(tokio_uring::uring_builder()
-    ///             .setup_cqsize(1024)
-    ///             )
+    ///         .cq_entries(1024)
     ///         .start(async {
     ///             let listener = TcpListener::bind("127.0.0.1:8080").await?;
     ///
@@ -232,6 +823,43 @@ impl Builder {
     }
 }
 
+/// Policy for handling completion queue overflow, set with
+/// [`Builder::cq_overflow`].
+///
+/// When completions arrive faster than the driver processes them, the
+/// completion queue overflows. The kernel then keeps the completions which
+/// do not fit in an internal backlog (`IORING_FEAT_NODROP`), and flushes
+/// them to the completion queue as space becomes available. The policy
+/// decides what happens to new operations while there is a backlog.
+///
+/// Kernels before Linux 5.5 have no backlog, and drop the completions
+/// which overflow the queue. The driver then keeps the completion queue
+/// from overflowing instead: it submits no more entries than the queue has
+/// room for the completions of, holding back the others until completions
+/// have been processed, and the policy does not apply.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CqOverflow {
+    /// Keep submitting operations, letting the kernel backlog grow.
+    ///
+    /// The backlog is kernel memory which is allocated for each overflowed
+    /// completion, and which is charged to the process.
+    Grow,
+
+    /// Before submitting a new operation, process the completions in the
+    /// backlog until it is empty.
+    ///
+    /// This is the default.
+    #[default]
+    Backpressure,
+
+    /// Fail new operations with an `EBUSY` error while there is a backlog.
+    ///
+    /// The operations are not submitted, and complete with the error as
+    /// they would on failure, returning the buffers passed to them.
+    Error,
+}
+
 /// A specialized `Result` type for `io-uring` operations with buffers.
 ///
 /// This type is used as a return value for asynchronous `io-uring` methods that
@@ -281,6 +909,6 @@ pub type BufResult<T, B> = (std::io::Result<T>, B);
 /// }
 /// ```
 pub async fn no_op() -> std::io::Result<()> {
-    let op = Op::<io::NoOp>::no_op().unwrap();
+    let op = Op::<io::NoOp>::no_op()?;
     op.await
 }
@@ -0,0 +1,275 @@
+//! Limits on the I/O performed by tasks.
+//!
+//! A [`RateLimiter`] caps the throughput of the io-uring operations
+//! submitted by futures run through it.
+
+use crate::runtime::driver::SqeHeader;
+use io_uring::{opcode, squeue};
+use std::cell::RefCell;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    // Rate limiters of the future being polled, innermost last.
+    static LIMITERS: RefCell<Vec<RateLimiter>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Throttles the bytes transferred by io-uring operations to a rate.
+///
+/// The limiter is a token bucket: it holds up to a [`burst`] of bytes, and
+/// is refilled at the rate of bytes per second given at creation. The
+/// operations submitted by futures run with [`throttle`] take the bytes
+/// they transfer from the bucket. The bucket can go into debt by the size
+/// of the last operations; a throttled future is not polled again, and so
+/// cannot submit more operations, until the debt has been repaid.
+///
+/// The bytes of reads, writes, vectored reads and writes, sends, receives
+/// and splices are counted. Other operations are not limited.
+///
+/// A limiter can be cloned to throttle several futures, tasks or files
+/// together. Sharing a single limiter for all the background work of a
+/// runtime, such as backups or compactions, caps its total throughput.
+///
+/// [`burst`]: RateLimiter::burst
+/// [`throttle`]: RateLimiter::throttle
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::File;
+/// use tokio_uring::limit::RateLimiter;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let src = File::open("data.db").await?;
+///         let dst = File::create("backup.db").await?;
+///
+///         // Copy at 10 MiB/s at most
+///         let limiter = RateLimiter::new(10 << 20);
+///         limiter
+///             .throttle(async {
+///                 let mut pos = 0;
+///                 loop {
+///                     let (res, buf) = src.read_at(vec![0; 1 << 20], pos).await;
+///                     let n = res?;
+///                     if n == 0 {
+///                         break;
+///                     }
+///                     let (res, _) = dst.write_all_at(buf, pos).await;
+///                     res?;
+///                     pos += n as u64;
+///                 }
+///                 Ok::<_, std::io::Error>(())
+///             })
+///             .await?;
+///
+///         Ok(())
+///     })
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter {
+    bucket: Rc<RefCell<Bucket>>,
+}
+
+struct Bucket {
+    // Bytes per second
+    rate: f64,
+
+    // Maximum number of bytes in the bucket
+    burst: f64,
+
+    // Bytes in the bucket, negative when in debt
+    tokens: f64,
+
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter throttling operations to `bytes_per_second`.
+    ///
+    /// The burst is initially one second worth of bytes, and the bucket
+    /// starts full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` is zero.
+    pub fn new(bytes_per_second: u64) -> RateLimiter {
+        assert!(bytes_per_second > 0, "rate must be positive");
+        let rate = bytes_per_second as f64;
+        RateLimiter {
+            bucket: Rc::new(RefCell::new(Bucket {
+                rate,
+                burst: rate,
+                tokens: rate,
+                refilled: Instant::now(),
+            })),
+        }
+    }
+
+    /// Sets the maximum number of bytes which can be transferred at once
+    /// after the limiter has been idle.
+    ///
+    /// The setting is shared by all clones of the limiter.
+    pub fn burst(&self, bytes: u64) -> &Self {
+        let mut bucket = self.bucket.borrow_mut();
+        bucket.burst = bytes as f64;
+        bucket.tokens = bucket.tokens.min(bucket.burst);
+        self
+    }
+
+    /// Runs a future, throttling the io-uring operations it submits.
+    ///
+    /// Nested calls throttle the operations with all the limiters. The
+    /// operations of tasks spawned by the future are not throttled.
+    pub async fn throttle<F: Future>(&self, future: F) -> F::Output {
+        tokio::pin!(future);
+        let mut delay: Option<Pin<Box<tokio::time::Sleep>>> = None;
+
+        poll_fn(|cx| {
+            loop {
+                if let Some(sleep) = &mut delay {
+                    if sleep.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    delay = None;
+                }
+                match self.bucket.borrow_mut().debt_delay() {
+                    Some(d) => delay = Some(Box::pin(tokio::time::sleep(d))),
+                    None => break,
+                }
+            }
+
+            LIMITERS.with(|limiters| limiters.borrow_mut().push(self.clone()));
+            let _pop = PopLimiter;
+            future.as_mut().poll(cx)
+        })
+        .await
+    }
+
+    /// Waits until `bytes` can be transferred at the rate of the limiter,
+    /// and takes them from the bucket.
+    ///
+    /// This throttles transfers which are not done with operations
+    /// counted by [`throttle`].
+    ///
+    /// [`throttle`]: RateLimiter::throttle
+    pub async fn acquire(&self, bytes: u64) {
+        loop {
+            let delay = {
+                let mut bucket = self.bucket.borrow_mut();
+                match bucket.debt_delay() {
+                    None => {
+                        bucket.tokens -= bytes as f64;
+                        return;
+                    }
+                    Some(d) => d,
+                }
+            };
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bucket = self.bucket.borrow();
+        f.debug_struct("RateLimiter")
+            .field("rate", &bucket.rate)
+            .field("burst", &bucket.burst)
+            .finish()
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.refilled = now;
+    }
+
+    // Returns the time until the debt is repaid, if the bucket is in debt.
+    fn debt_delay(&mut self) -> Option<Duration> {
+        self.refill();
+        if self.tokens < 0.0 {
+            Some(Duration::from_secs_f64(-self.tokens / self.rate))
+        } else {
+            None
+        }
+    }
+}
+
+// Removes the limiter of a throttled future when it returns from poll.
+struct PopLimiter;
+
+impl Drop for PopLimiter {
+    fn drop(&mut self) {
+        LIMITERS.with(|limiters| limiters.borrow_mut().pop());
+    }
+}
+
+/// Takes the bytes transferred by a submitted operation from the limiters
+/// of the future being polled.
+pub(crate) fn charge(sqe: &squeue::Entry) {
+    LIMITERS.with(|limiters| {
+        let limiters = limiters.borrow();
+        if limiters.is_empty() {
+            return;
+        }
+        let bytes = sqe_bytes(sqe) as f64;
+        if bytes == 0.0 {
+            return;
+        }
+        for limiter in limiters.iter() {
+            let mut bucket = limiter.bucket.borrow_mut();
+            bucket.refill();
+            bucket.tokens -= bytes;
+        }
+    })
+}
+
+// Returns the number of bytes an operation transfers, or 0 if it is not
+// counted.
+fn sqe_bytes(sqe: &squeue::Entry) -> u64 {
+    let SqeHeader {
+        opcode: op,
+        addr,
+        len,
+        ..
+    } = SqeHeader::read(sqe);
+
+    match op {
+        opcode::Read::CODE
+        | opcode::Write::CODE
+        | opcode::ReadFixed::CODE
+        | opcode::WriteFixed::CODE
+        | opcode::Send::CODE
+        | opcode::Recv::CODE
+        | opcode::SendZc::CODE
+        | opcode::Splice::CODE => len as u64,
+        // The iovecs are owned by the operation, which has just been
+        // created and is still alive.
+        opcode::Readv::CODE | opcode::Writev::CODE => unsafe {
+            iovecs_len(addr as *const libc::iovec, len as usize)
+        },
+        opcode::SendMsg::CODE | opcode::RecvMsg::CODE => unsafe {
+            let msg = &*(addr as *const libc::msghdr);
+            iovecs_len(msg.msg_iov, msg.msg_iovlen)
+        },
+        _ => 0,
+    }
+}
+
+unsafe fn iovecs_len(iovs: *const libc::iovec, n: usize) -> u64 {
+    if iovs.is_null() {
+        return 0;
+    }
+    std::slice::from_raw_parts(iovs, n)
+        .iter()
+        .map(|iov| iov.iov_len as u64)
+        .sum()
+}
@@ -0,0 +1,79 @@
+//! Reporting of the runtime metrics through the `metrics` facade.
+//!
+//! The metrics are listed in the crate documentation. They are aggregated
+//! over all the runtimes of the process.
+
+use io_uring::opcode;
+use metrics::{counter, gauge, histogram};
+use std::io;
+use std::time::Duration;
+
+/// Records the submission of an operation.
+pub(crate) fn op_submitted() {
+    counter!("tokio_uring_ops_submitted_total").increment(1);
+    gauge!("tokio_uring_ops_in_flight").increment(1.0);
+}
+
+/// Records a completion of an operation. Operations producing multiple
+/// completions count as completed with the last one.
+pub(crate) fn op_completed(opcode: u8, result: &io::Result<u32>, more: bool, age: Duration) {
+    match result {
+        Ok(n) if is_read(opcode) => counter!("tokio_uring_bytes_read_total").increment(*n as u64),
+        Ok(n) if is_write(opcode) => {
+            counter!("tokio_uring_bytes_written_total").increment(*n as u64)
+        }
+        Ok(_) => {}
+        Err(_) => counter!("tokio_uring_ops_failed_total").increment(1),
+    }
+    if !more {
+        counter!("tokio_uring_ops_completed_total").increment(1);
+        histogram!("tokio_uring_op_duration_seconds").record(age);
+    }
+}
+
+/// Records that an operation is no longer in flight.
+pub(crate) fn op_retired() {
+    gauge!("tokio_uring_ops_in_flight").decrement(1.0);
+}
+
+/// Records the allocation or the release of fixed buffers.
+pub(crate) fn fixed_bufs_allocated(count: usize) {
+    gauge!("tokio_uring_fixed_buffers").increment(count as f64);
+}
+
+pub(crate) fn fixed_bufs_released(count: usize) {
+    gauge!("tokio_uring_fixed_buffers").decrement(count as f64);
+}
+
+/// Records the checkout of a fixed buffer.
+pub(crate) fn fixed_buf_checked_out() {
+    gauge!("tokio_uring_fixed_buffers_checked_out").increment(1.0);
+}
+
+/// Records the return of a fixed buffer.
+pub(crate) fn fixed_buf_checked_in() {
+    gauge!("tokio_uring_fixed_buffers_checked_out").decrement(1.0);
+}
+
+/// Records a stall of a runtime detected by the watchdog.
+pub(crate) fn runtime_stalled() {
+    counter!("tokio_uring_stalls_total").increment(1);
+}
+
+fn is_read(code: u8) -> bool {
+    code == opcode::Read::CODE
+        || code == opcode::ReadFixed::CODE
+        || code == opcode::Readv::CODE
+        || code == opcode::Recv::CODE
+        || code == opcode::RecvMsg::CODE
+}
+
+fn is_write(code: u8) -> bool {
+    code == opcode::Write::CODE
+        || code == opcode::WriteFixed::CODE
+        || code == opcode::Writev::CODE
+        || code == opcode::Send::CODE
+        || code == opcode::SendMsg::CODE
+        || code == opcode::SendZc::CODE
+        || code == opcode::SendMsgZc::CODE
+}
@@ -0,0 +1,178 @@
+//! Scripted completions for unit testing.
+//!
+//! A runtime started with [`Builder::mock_driver`] does not submit
+//! operations to the kernel. Instead, each operation is passed to a
+//! handler, which returns its result. The handler can inspect the
+//! operation, fill the buffers of reads, and fail operations with any
+//! error, so that code using the types of this crate can be tested
+//! against scripted I/O, including error paths which are hard to provoke
+//! with real files and sockets.
+//!
+//! No io-uring instance is created for the runtime, so that mocked tests
+//! also run where io_uring is not available. The features which need one,
+//! such as buffer rings and personalities, are not supported.
+//!
+//! This module requires the `test-util` feature.
+//!
+//! [`Builder::mock_driver`]: crate::Builder::mock_driver
+//!
+//! # Examples
+//!
+//! ```
+//! use io_uring::opcode;
+//! use tokio_uring::fs::File;
+//!
+//! tokio_uring::builder()
+//!     .mock_driver(|op| match op.opcode() {
+//!         opcode::OpenAt::CODE => Ok(100),
+//!         opcode::Read::CODE => {
+//!             let buf = op.buf_mut().unwrap();
+//!             buf[..5].copy_from_slice(b"hello");
+//!             Ok(5)
+//!         }
+//!         opcode::Close::CODE => Ok(0),
+//!         _ => Err(std::io::Error::from_raw_os_error(libc::EIO)),
+//!     })
+//!     .start(async {
+//!         let file = File::open("hello.txt").await.unwrap();
+//!         let (res, buf) = file.read_at(vec![0; 16], 0).await;
+//!         assert_eq!(&buf[..res.unwrap()], b"hello");
+//!         file.close().await.unwrap();
+//!     });
+//! ```
+
+use crate::runtime::driver::SqeHeader;
+use io_uring::opcode;
+use std::ffi::CStr;
+use std::io;
+use std::marker::PhantomData;
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+
+/// Backend completing the operations of a mocked runtime.
+pub(crate) trait MockBackend: Send + Sync {
+    /// Handles an operation, returning its result, or `None` if it is
+    /// completed later.
+    fn submit(&self, op: &mut MockOp<'_>) -> Option<io::Result<u32>>;
+
+    /// Cancels an operation which has not been completed.
+    fn cancel(&self, _user_data: u64) {}
+
+    /// Moves the results of the operations completed later to `out`.
+    fn completions(&self, _out: &mut Vec<(u64, io::Result<u32>)>) {}
+}
+
+pub(crate) type MockHandler = Arc<dyn MockBackend>;
+
+/// Backend completing each operation with the result of a function.
+pub(crate) struct FnBackend<F>(pub(crate) F);
+
+impl<F> MockBackend for FnBackend<F>
+where
+    F: Fn(&mut MockOp<'_>) -> io::Result<u32> + Send + Sync,
+{
+    fn submit(&self, op: &mut MockOp<'_>) -> Option<io::Result<u32>> {
+        Some((self.0)(op))
+    }
+}
+
+/// An operation submitted to a mocked runtime.
+///
+/// The buffers of the operation can be accessed while it is being handled.
+pub struct MockOp<'a> {
+    sqe: SqeHeader,
+    _buffers: PhantomData<&'a mut [u8]>,
+}
+
+impl MockOp<'_> {
+    pub(crate) fn new(sqe: SqeHeader) -> Self {
+        MockOp {
+            sqe,
+            _buffers: PhantomData,
+        }
+    }
+
+    #[cfg_attr(not(feature = "sim"), allow(dead_code))]
+    pub(crate) fn sqe(&self) -> &SqeHeader {
+        &self.sqe
+    }
+
+    /// Returns the io_uring opcode of the operation, e.g.
+    /// `io_uring::opcode::Read::CODE`.
+    pub fn opcode(&self) -> u8 {
+        self.sqe.opcode
+    }
+
+    /// Returns the file descriptor the operation is performed on.
+    ///
+    /// For operations not performed on a file descriptor, this is -1, or
+    /// `AT_FDCWD` for operations on paths.
+    pub fn fd(&self) -> RawFd {
+        self.sqe.fd
+    }
+
+    /// Returns the offset of the operation in the file, for reads and
+    /// writes.
+    pub fn offset(&self) -> u64 {
+        self.sqe.off
+    }
+
+    /// Returns the length of the operation, such as the size of the buffer
+    /// of a read or write.
+    pub fn len(&self) -> u32 {
+        self.sqe.len
+    }
+
+    /// Returns `true` if the length of the operation is zero.
+    pub fn is_empty(&self) -> bool {
+        self.sqe.len == 0
+    }
+
+    /// Returns the buffer of the data written by a write or send.
+    ///
+    /// Returns `None` for other operations, including vectored writes.
+    pub fn buf(&self) -> Option<&[u8]> {
+        match self.sqe.opcode {
+            opcode::Write::CODE
+            | opcode::WriteFixed::CODE
+            | opcode::Send::CODE
+            | opcode::SendZc::CODE => Some(unsafe {
+                std::slice::from_raw_parts(self.sqe.addr as *const u8, self.sqe.len as usize)
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the buffer to fill with the data of a read or receive.
+    ///
+    /// Only the bytes reported by the result of the operation are
+    /// considered to be read. Returns `None` for other operations,
+    /// including vectored reads.
+    pub fn buf_mut(&mut self) -> Option<&mut [u8]> {
+        match self.sqe.opcode {
+            opcode::Read::CODE | opcode::ReadFixed::CODE | opcode::Recv::CODE => Some(unsafe {
+                std::slice::from_raw_parts_mut(self.sqe.addr as *mut u8, self.sqe.len as usize)
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the path of an operation on a path, such as opening a file.
+    ///
+    /// For a rename, this is the path of the renamed file.
+    pub fn path(&self) -> Option<&CStr> {
+        // Safety: the operation is being handled, it has not completed.
+        unsafe { self.sqe.path() }
+    }
+}
+
+impl std::fmt::Debug for MockOp<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockOp")
+            .field("opcode", &self.sqe.opcode)
+            .field("fd", &self.sqe.fd)
+            .field("offset", &self.sqe.off)
+            .field("len", &self.sqe.len)
+            .finish()
+    }
+}
@@ -0,0 +1,174 @@
+use crate::net::{TcpListener, UdpSocket, UnixListener};
+use socket2::{Domain, Socket, Type};
+use std::env;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The first descriptor passed by the service manager, `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// A socket passed by systemd with socket activation, returned by
+/// [`from_env`].
+#[non_exhaustive]
+pub enum ActivatedSocket {
+    /// A TCP socket listening for connections, configured by a
+    /// `ListenStream` setting with an IP address or a port.
+    TcpListener(TcpListener),
+
+    /// A Unix socket listening for connections, configured by a
+    /// `ListenStream` setting with a path.
+    UnixListener(UnixListener),
+
+    /// A UDP socket, configured by a `ListenDatagram` setting with an IP
+    /// address or a port.
+    UdpSocket(UdpSocket),
+}
+
+impl ActivatedSocket {
+    fn from_fd(fd: OwnedFd) -> io::Result<ActivatedSocket> {
+        // Safety: the descriptor is owned
+        let socket = unsafe { Socket::from_raw_fd(fd.into_raw_fd()) };
+        let domain = socket.domain()?;
+        let ty = socket.r#type()?;
+        let inet = domain == Domain::IPV4 || domain == Domain::IPV6;
+
+        if ty == Type::STREAM && socket.is_listener()? {
+            if inet {
+                return Ok(ActivatedSocket::TcpListener(TcpListener::from_std(
+                    socket.into(),
+                )));
+            }
+            if domain == Domain::UNIX {
+                return Ok(ActivatedSocket::UnixListener(UnixListener::from_std(
+                    socket.into(),
+                )));
+            }
+        }
+        if ty == Type::DGRAM && inet {
+            return Ok(ActivatedSocket::UdpSocket(UdpSocket::from_std(
+                socket.into(),
+            )));
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "unsupported kind of socket passed by the service manager",
+        ))
+    }
+}
+
+/// Returns the sockets passed to the process by systemd with socket
+/// activation.
+///
+/// The sockets are those of the `.socket` unit activating the service, in
+/// the order of its `Listen*` settings, described by the `LISTEN_PID`,
+/// `LISTEN_FDS` and `LISTEN_FDNAMES` environment variables. The sockets
+/// are only returned by the first call. An empty list is returned if the
+/// process has not been started with socket activation.
+///
+/// The environment is left as is, as modifying it while other threads may
+/// read it is unsound. Child processes ignore the variables, as
+/// `LISTEN_PID` does not match them, but they can be removed for the
+/// children, e.g. with [`Command::env_remove`].
+///
+/// [`Command::env_remove`]: std::process::Command::env_remove
+///
+/// # Errors
+///
+/// Fails if the environment variables are invalid, or if a socket is
+/// neither a TCP or Unix listener nor a UDP socket, e.g. the connection of
+/// a service with `Accept=yes`. All the descriptors passed are closed on
+/// errors.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::net::{self, ActivatedSocket, TcpListener};
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         let listener = match net::from_env()?.pop() {
+///             Some(ActivatedSocket::TcpListener(listener)) => listener,
+///             // Started by hand
+///             _ => TcpListener::bind("127.0.0.1:8080".parse().unwrap())?,
+///         };
+///         loop {
+///             let (stream, _) = listener.accept().await?;
+///             tokio_uring::spawn(async move {
+///                 // Serve the connection
+///                 # drop(stream);
+///             });
+///         }
+///     })
+/// }
+/// ```
+pub fn from_env() -> io::Result<Vec<ActivatedSocket>> {
+    Ok(from_env_named()?
+        .into_iter()
+        .map(|(_, socket)| socket)
+        .collect())
+}
+
+/// Like [`from_env`], but also returns the names of the sockets, set by
+/// the `FileDescriptorName` settings of the socket unit.
+///
+/// The name of a socket defaults to the name of its unit, e.g.
+/// `server.socket`.
+pub fn from_env_named() -> io::Result<Vec<(String, ActivatedSocket)>> {
+    let fds = take_listen_fds()?;
+    let names = env::var("LISTEN_FDNAMES").ok();
+
+    let mut names: Vec<String> = match names {
+        Some(names) => names.split(':').map(str::to_owned).collect(),
+        None => Vec::new(),
+    };
+    names.resize(fds.len(), "unknown".to_owned());
+
+    // Converting only once all are owned closes them on errors
+    names
+        .into_iter()
+        .zip(fds)
+        .map(|(name, fd)| Ok((name, ActivatedSocket::from_fd(fd)?)))
+        .collect()
+}
+
+// Takes ownership of the descriptors passed by the service manager, as
+// `sd_listen_fds` does, only once.
+fn take_listen_fds() -> io::Result<Vec<OwnedFd>> {
+    static TAKEN: AtomicBool = AtomicBool::new(false);
+
+    if TAKEN.swap(true, Ordering::Relaxed) {
+        return Ok(Vec::new());
+    }
+    let pid = env::var("LISTEN_PID").ok();
+    let count = env::var("LISTEN_FDS").ok();
+
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid socket activation environment",
+        )
+    };
+    match pid {
+        Some(pid) if pid.parse::<u32>().map_err(|_| invalid())? == std::process::id() => {}
+        // The variables were meant for another process
+        _ => return Ok(Vec::new()),
+    }
+    let count: RawFd = match count {
+        Some(count) => count.parse().map_err(|_| invalid())?,
+        None => return Ok(Vec::new()),
+    };
+    if count < 0 {
+        return Err(invalid());
+    }
+    let end = LISTEN_FDS_START.checked_add(count).ok_or_else(invalid)?;
+
+    // Safety: the descriptors are passed to the process to own
+    let fds: Vec<OwnedFd> = (LISTEN_FDS_START..end)
+        .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
+        .collect();
+    for fd in &fds {
+        syscall!(fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC))?;
+    }
+    Ok(fds)
+}
@@ -0,0 +1,268 @@
+use crate::net::TcpStream;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::SocketAddr;
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// A pool of outbound TCP connections, keyed by the address they are
+/// connected to.
+///
+/// [`get`] returns a connection to an address, reusing one returned to the
+/// pool if there is a healthy one, or connecting otherwise. Once a request
+/// has been completed, the connection is put back in the pool with
+/// [`release`], to be reused by the next request to the same address. A
+/// [`PooledConn`] which is dropped instead is closed, so that a connection
+/// left in an unknown state, e.g. by an error or a cancelled request, is
+/// never reused.
+///
+/// An idle connection is checked before it is reused: it is closed, and
+/// the next one is tried, if it has been idle for longer than the
+/// [`idle_timeout`], if `TCP_INFO` reports that it is no longer established,
+/// e.g. because the peer has closed it, or if data has arrived on it. The
+/// most recently returned connection is reused first.
+///
+/// The pool can be cloned, and the clones share the connections.
+///
+/// [`get`]: ConnPool::get
+/// [`release`]: PooledConn::release
+/// [`idle_timeout`]: ConnPool::idle_timeout
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio_uring::net::ConnPool;
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         let mut pool = ConnPool::new();
+///         pool.idle_timeout(Duration::from_secs(30)).max_idle(16);
+///
+///         let addr = "127.0.0.1:6379".parse().unwrap();
+///         for _ in 0..10 {
+///             let conn = pool.get(addr).await?;
+///             let (res, _) = conn.request(b"PING\r\n".to_vec(), vec![0; 64]).await;
+///             if res? == 7 {
+///                 // The response has been read in full
+///                 conn.release();
+///             }
+///             // Otherwise, the connection is closed
+///         }
+///         Ok(())
+///     })
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ConnPool {
+    inner: Rc<RefCell<Inner>>,
+}
+
+struct Inner {
+    idle: HashMap<SocketAddr, VecDeque<Idle>>,
+    idle_timeout: Duration,
+    max_idle: usize,
+}
+
+struct Idle {
+    stream: TcpStream,
+    since: Instant,
+}
+
+impl ConnPool {
+    /// Creates an empty pool, keeping up to 8 idle connections to each
+    /// address for up to 90 seconds.
+    pub fn new() -> ConnPool {
+        ConnPool {
+            inner: Rc::new(RefCell::new(Inner {
+                idle: HashMap::new(),
+                idle_timeout: Duration::from_secs(90),
+                max_idle: 8,
+            })),
+        }
+    }
+
+    /// Sets how long a connection can stay idle in the pool. Connections
+    /// idle for longer are closed instead of being reused.
+    pub fn idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.inner.borrow_mut().idle_timeout = timeout;
+        self
+    }
+
+    /// Sets the most idle connections kept for each address. Connections
+    /// returned to the pool beyond this number are closed.
+    pub fn max_idle(&mut self, max: usize) -> &mut Self {
+        self.inner.borrow_mut().max_idle = max;
+        self
+    }
+
+    /// Returns a connection to `addr`, reusing an idle one if there is a
+    /// healthy one, or connecting otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the connection.
+    pub async fn get(&self, addr: SocketAddr) -> io::Result<PooledConn> {
+        if let Some(stream) = self.take_idle(addr) {
+            return Ok(PooledConn::new(self, addr, stream, true));
+        }
+        let stream = TcpStream::connect(addr).await?;
+        Ok(PooledConn::new(self, addr, stream, false))
+    }
+
+    /// Returns the number of idle connections to `addr` in the pool.
+    pub fn idle(&self, addr: SocketAddr) -> usize {
+        self.inner.borrow().idle.get(&addr).map_or(0, VecDeque::len)
+    }
+
+    /// Closes the connections which have been idle for longer than the
+    /// idle timeout.
+    ///
+    /// Such connections are otherwise closed as they are found by [`get`],
+    /// or when connections are returned to the pool.
+    ///
+    /// [`get`]: ConnPool::get
+    pub fn prune(&self) {
+        let mut inner = self.inner.borrow_mut();
+        let idle_timeout = inner.idle_timeout;
+        let now = Instant::now();
+        inner.idle.retain(|_, conns| {
+            conns.retain(|conn| now.duration_since(conn.since) <= idle_timeout);
+            !conns.is_empty()
+        });
+    }
+
+    fn take_idle(&self, addr: SocketAddr) -> Option<TcpStream> {
+        let mut inner = self.inner.borrow_mut();
+        let idle_timeout = inner.idle_timeout;
+        let conns = inner.idle.get_mut(&addr)?;
+        let mut found = None;
+        // Unhealthy connections are dropped, and closed, as they are found
+        while let Some(conn) = conns.pop_back() {
+            if conn.since.elapsed() <= idle_timeout && is_healthy(&conn.stream) {
+                found = Some(conn.stream);
+                break;
+            }
+        }
+        if conns.is_empty() {
+            inner.idle.remove(&addr);
+        }
+        found
+    }
+
+    fn put(&self, addr: SocketAddr, stream: TcpStream) {
+        let mut inner = self.inner.borrow_mut();
+        let idle_timeout = inner.idle_timeout;
+        let max_idle = inner.max_idle;
+        let conns = inner.idle.entry(addr).or_default();
+        let now = Instant::now();
+        conns.retain(|conn| now.duration_since(conn.since) <= idle_timeout);
+        if conns.len() < max_idle {
+            conns.push_back(Idle { stream, since: now });
+        } else if conns.is_empty() {
+            inner.idle.remove(&addr);
+        }
+    }
+}
+
+impl Default for ConnPool {
+    fn default() -> ConnPool {
+        ConnPool::new()
+    }
+}
+
+impl std::fmt::Debug for ConnPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("ConnPool")
+            .field("idle_timeout", &inner.idle_timeout)
+            .field("max_idle", &inner.max_idle)
+            .finish_non_exhaustive()
+    }
+}
+
+// Returns whether an idle connection can be reused: it is still
+// established, and nothing has been received on it.
+fn is_healthy(stream: &TcpStream) -> bool {
+    match stream.tcp_info() {
+        Ok(info) if info.is_established() => {}
+        _ => return false,
+    }
+    let mut byte = 0u8;
+    let res = unsafe {
+        libc::recv(
+            stream.as_raw_fd(),
+            &mut byte as *mut u8 as *mut libc::c_void,
+            1,
+            libc::MSG_PEEK | libc::MSG_DONTWAIT,
+        )
+    };
+    res == -1 && io::Error::last_os_error().raw_os_error() == Some(libc::EAGAIN)
+}
+
+/// A connection of a [`ConnPool`], which goes back to the pool with
+/// [`release`](PooledConn::release), and is closed when dropped.
+///
+/// The connection is used through its [`TcpStream`], which it dereferences
+/// to.
+pub struct PooledConn {
+    pool: ConnPool,
+    addr: SocketAddr,
+    stream: TcpStream,
+    reused: bool,
+}
+
+impl PooledConn {
+    fn new(pool: &ConnPool, addr: SocketAddr, stream: TcpStream, reused: bool) -> PooledConn {
+        PooledConn {
+            pool: pool.clone(),
+            addr,
+            stream,
+            reused,
+        }
+    }
+
+    /// Returns whether the connection has been reused from the pool, rather
+    /// than newly connected.
+    ///
+    /// A request failing on a reused connection may be retried on a new
+    /// one: the peer may have closed the connection while it was idle,
+    /// before the pool could notice.
+    pub fn is_reused(&self) -> bool {
+        self.reused
+    }
+
+    /// Returns the connection to the pool, to be reused.
+    ///
+    /// This is only to be done with the connection in a state the next
+    /// request can start from, e.g. once a response has been read in full.
+    pub fn release(self) {
+        self.pool.put(self.addr, self.stream);
+    }
+
+    /// Takes the connection out of the pool.
+    pub fn into_inner(self) -> TcpStream {
+        self.stream
+    }
+}
+
+impl Deref for PooledConn {
+    type Target = TcpStream;
+
+    fn deref(&self) -> &TcpStream {
+        &self.stream
+    }
+}
+
+impl std::fmt::Debug for PooledConn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledConn")
+            .field("addr", &self.addr)
+            .field("reused", &self.reused)
+            .finish_non_exhaustive()
+    }
+}
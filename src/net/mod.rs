@@ -6,17 +6,54 @@
 //! # Organization
 //!
 //! * [`TcpListener`] and [`TcpStream`] provide functionality for communication over TCP
+//! * [`TcpSocket`] configures a TCP socket before it listens or connects
 //! * [`UdpSocket`] provides functionality for communication over UDP
-
+//! * [`TunTap`] exchanges packets through a virtual network interface
+//! * [`UdpFramed`] sends and receives the frames of a codec as UDP datagrams
+//!   (requires the `codec` feature)
+//! * [`serve`] runs [`tower`] services on accepted TCP connections (requires
+//!   the `tower` feature)
 //!
 //! [`TcpListener`]: TcpListener
 //! [`TcpStream`]: TcpStream
+//! [`TcpSocket`]: TcpSocket
 //! [`UdpSocket`]: UdpSocket
+//! [`TunTap`]: TunTap
+//! [`tower`]: https://docs.rs/tower
 
+mod activation;
+mod conn_pool;
+mod recv_chunks;
+#[cfg(feature = "tower")]
+mod serve;
+mod takeover;
 mod tcp;
+mod timestamp;
+mod tun;
 mod udp;
+#[cfg(feature = "codec")]
+mod udp_framed;
+mod udp_meta;
 mod unix;
+mod write_queue;
+mod zc_notification;
 
-pub use tcp::{TcpListener, TcpStream};
+pub use activation::{from_env, from_env_named, ActivatedSocket};
+pub use conn_pool::{ConnPool, PooledConn};
+pub use recv_chunks::{RecvChunks, RecvEvent};
+#[cfg(feature = "tower")]
+pub use serve::serve;
+pub use takeover::Takeover;
+pub use tcp::{AcceptLoad, Admission, TcpIncoming, TcpInfo, TcpListener, TcpSocket, TcpStream};
+pub(crate) use timestamp::SO_TIMESTAMPING;
+pub use timestamp::{Timestamping, Timestamps};
+pub use tun::TunTap;
 pub use udp::UdpSocket;
+#[cfg(feature = "codec")]
+pub use udp_framed::UdpFramed;
+pub(crate) use udp_meta::CONTROL_LEN;
+pub use udp_meta::{RecvMeta, SendMeta};
 pub use unix::{UnixListener, UnixStream};
+pub use write_queue::WriteQueue;
+pub(crate) use zc_notification::send_zc_notified;
+pub use zc_notification::ZcNotification;
@@ -0,0 +1,111 @@
+use crate::buf::provided::{BufRing, ProvidedBuf};
+use crate::io::{ReadMulti, SharedFd};
+use crate::runtime::driver::op::{MultiCQEStream, Op};
+use std::future::poll_fn;
+use std::io;
+
+/// An event of a [`RecvChunks`] stream.
+#[derive(Debug)]
+pub enum RecvEvent {
+    /// A chunk of data received into a buffer of the ring.
+    Data(ProvidedBuf),
+
+    /// The receive has stopped because the ring has no buffers left.
+    ///
+    /// The data arriving meanwhile is queued by the socket. The receive is
+    /// submitted again once a buffer taken from the ring has been dropped,
+    /// when the stream is next polled.
+    BufferStarved,
+}
+
+/// Chunks of data received with a multishot receive, into the buffers of a
+/// [`BufRing`].
+///
+/// Created by [`TcpStream::recv_multi`] and [`UnixStream::recv_multi`]. A
+/// single receive operation is kept in flight, which completes each time
+/// data arrives, taking a buffer from the ring for it. The chunks are
+/// returned by [`next`] in the order they were received.
+///
+/// When the ring has no buffers left, the kernel stops the operation with
+/// `ENOBUFS`. Rather than returning the error, [`next`] returns a
+/// [`RecvEvent::BufferStarved`] event, and its next call waits for a buffer
+/// to be given back to the ring, then submits the receive again. A server
+/// can use the event to shed load, e.g. by processing the chunks it holds
+/// before reading more. Other errors are returned, and do not end the
+/// stream either: the next call submits a new operation.
+///
+/// Receiving multishot requires Linux 6.0.
+///
+/// Dropping the `RecvChunks` cancels the operation in flight. The buffers
+/// of the chunks received and not returned are given back to the ring.
+///
+/// [`TcpStream::recv_multi`]: crate::net::TcpStream::recv_multi
+/// [`UnixStream::recv_multi`]: crate::net::UnixStream::recv_multi
+/// [`next`]: RecvChunks::next
+pub struct RecvChunks {
+    fd: SharedFd,
+    ring: BufRing,
+    op: Option<Op<ReadMulti, MultiCQEStream>>,
+    starved: bool,
+    done: bool,
+}
+
+impl RecvChunks {
+    pub(crate) fn new(fd: SharedFd, ring: &BufRing) -> RecvChunks {
+        RecvChunks {
+            fd,
+            ring: ring.clone(),
+            op: None,
+            starved: false,
+            done: false,
+        }
+    }
+
+    /// Returns the next event of the stream, or `None` once the peer has
+    /// closed the connection.
+    ///
+    /// The first call submits the receive operation.
+    pub async fn next(&mut self) -> Option<io::Result<RecvEvent>> {
+        if self.done {
+            return None;
+        }
+        if self.starved {
+            self.ring.replenished().await;
+            self.starved = false;
+        }
+        let op = match &mut self.op {
+            Some(op) => op,
+            None => match Op::recv_multi(&self.fd, &self.ring) {
+                Ok(op) => self.op.insert(op),
+                Err(e) => return Some(Err(e)),
+            },
+        };
+
+        let res = poll_fn(|cx| op.poll_next(cx)).await;
+        // The data of the operation is taken with the final completion,
+        // after which a new operation is needed
+        if op.data.is_none() {
+            self.op = None;
+        }
+        match res {
+            Some(Ok(Some(buf))) => Some(Ok(RecvEvent::Data(buf))),
+            Some(Err(e)) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                self.starved = true;
+                Some(Ok(RecvEvent::BufferStarved))
+            }
+            Some(Err(e)) => Some(Err(e)),
+            Some(Ok(None)) | None => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl Drop for RecvChunks {
+    fn drop(&mut self) {
+        if let Some(op) = &self.op {
+            op.cancel();
+        }
+    }
+}
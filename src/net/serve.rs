@@ -0,0 +1,70 @@
+use crate::net::TcpListener;
+use hyper::body::{Body, Incoming};
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use std::error::Error as StdError;
+use std::future::poll_fn;
+use std::io;
+use std::net::SocketAddr;
+use tower_service::Service;
+
+/// Serves HTTP/1 connections accepted by the listener with [`tower`]
+/// services.
+///
+/// For every accepted connection, `make_service` is called with the address
+/// of the peer to produce the service handling the requests received on the
+/// connection. Each connection is driven by hyper in a separate task spawned
+/// on the current `tokio-uring` runtime.
+///
+/// This function only returns if accepting a connection or making a service
+/// fails. Errors on individual connections, such as a client resetting the
+/// connection, are not reported.
+///
+/// Requires the `tower` feature.
+///
+/// [`tower`]: https://docs.rs/tower
+///
+/// # Examples
+///
+/// Serving an [axum](https://docs.rs/axum) application:
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use tokio_uring::net::TcpListener;
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         let app = Router::new().route("/", get(|| async { "Hello, World!" }));
+///
+///         let listener = TcpListener::bind("127.0.0.1:3000".parse().unwrap())?;
+///         tokio_uring::net::serve(listener, app.into_make_service()).await
+///     })
+/// }
+/// ```
+pub async fn serve<M, S, B>(listener: TcpListener, mut make_service: M) -> io::Result<()>
+where
+    M: Service<SocketAddr, Response = S>,
+    M::Error: Into<Box<dyn StdError + Send + Sync>>,
+    S: Service<Request<Incoming>, Response = Response<B>> + Clone + 'static,
+    S::Future: 'static,
+    S::Error: Into<Box<dyn StdError + Send + Sync>>,
+    B: Body + 'static,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    loop {
+        let (stream, peer) = listener.accept().await?;
+
+        poll_fn(|cx| make_service.poll_ready(cx))
+            .await
+            .map_err(io::Error::other)?;
+        let service = make_service.call(peer).await.map_err(io::Error::other)?;
+        let service = TowerToHyperService::new(service);
+
+        crate::spawn(async move {
+            let _ = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
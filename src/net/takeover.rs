@@ -0,0 +1,157 @@
+use crate::io::sealed::Sealed;
+use crate::io::MAX_FDS;
+use crate::net::{TcpListener, UnixListener, UnixStream};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Hands the listening sockets of a server over to a new process, for
+/// upgrades without downtime.
+///
+/// The running process binds a `Takeover` to a Unix socket path, and
+/// keeps serving while waiting in [`hand_over`]. The new process, started
+/// with the upgraded binary, calls [`receive`] with the same path: it
+/// connects to the running process, which sends it the descriptors of its
+/// listeners with `SCM_RIGHTS`, and adopts them as [`TcpListener`]s of its
+/// own runtime. Connections keep being queued by the sockets during the
+/// handover, and none is refused: once `hand_over` returns, the old
+/// process stops accepting, finishes serving the connections it has, and
+/// exits.
+///
+/// The listeners are received in the order they were passed to
+/// `hand_over`. At most 253 listeners can be handed over.
+///
+/// [`hand_over`]: Takeover::hand_over
+/// [`receive`]: Takeover::receive
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use tokio_uring::net::{TcpListener, Takeover};
+///
+/// const CONTROL: &str = "/run/server/takeover.sock";
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         // Take over the listener of a running server, if there is one
+///         let listener = match Takeover::receive(CONTROL).await {
+///             Ok(mut listeners) => listeners.remove(0),
+///             Err(_) => TcpListener::bind("0.0.0.0:8080".parse().unwrap())?,
+///         };
+///         let listener = Rc::new(listener);
+///         let takeover = Takeover::bind(CONTROL)?;
+///
+///         let accepting = listener.clone();
+///         let accept_loop = tokio_uring::spawn(async move {
+///             while let Ok((stream, _)) = accepting.accept().await {
+///                 tokio_uring::spawn(async move {
+///                     // Serve the connection
+///                     # drop(stream);
+///                 });
+///             }
+///         });
+///
+///         takeover.hand_over(&[&listener]).await?;
+///         // The new process accepts the connections from now on
+///         accept_loop.abort();
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct Takeover {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Takeover {
+    /// Binds a `Takeover` to a Unix socket path, where a new process can
+    /// connect to receive the listeners.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the path exists, e.g. left over by a process which has
+    /// exited without handing its listeners over.
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<Takeover> {
+        let path = path.as_ref();
+        Ok(Takeover {
+            listener: UnixListener::bind(path)?,
+            path: path.to_owned(),
+        })
+    }
+
+    /// Waits for a new process to connect, and sends it the descriptors of
+    /// `listeners`.
+    ///
+    /// Resolves once the new process has adopted the listeners. The socket
+    /// path is then removed, so that the new process can bind a `Takeover`
+    /// to it for its own upgrade. If the path cannot be removed, the
+    /// handover still succeeds, and the new process fails to bind to the
+    /// path as if it had been left over.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the listeners cannot be sent, or the new process exits
+    /// before adopting them; the process keeps the listeners, and can call
+    /// `hand_over` again.
+    pub async fn hand_over(&self, listeners: &[&TcpListener]) -> io::Result<()> {
+        if listeners.is_empty() || listeners.len() > MAX_FDS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid number of listeners",
+            ));
+        }
+        let stream = self.listener.accept().await?;
+        let fds = listeners
+            .iter()
+            .map(|listener| listener.socket().fd.clone())
+            .collect();
+        stream.socket().send_fds(fds).await?;
+
+        // The new process acknowledges the listeners once it has adopted
+        // them, then waits for the path to be removed
+        let (res, _) = stream.read(vec![0; 1]).await;
+        if res? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "the new process exited before adopting the listeners",
+            ));
+        }
+        // The listeners belong to the new process now, the handover is not
+        // undone if the path cannot be removed
+        let _ = crate::fs::remove_file(&self.path).await;
+        Ok(())
+    }
+
+    /// Connects to the process bound to `path`, and takes over its
+    /// listeners.
+    ///
+    /// Resolves once the running process has removed the socket path.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no process is bound to the path, or if the listeners are
+    /// not received.
+    pub async fn receive<P: AsRef<Path>>(path: P) -> io::Result<Vec<TcpListener>> {
+        let stream = UnixStream::connect(path).await?;
+        let listeners: Vec<_> = stream
+            .socket()
+            .recv_fds()
+            .await?
+            .into_iter()
+            .map(|fd| TcpListener::from_std(fd.into()))
+            .collect();
+
+        stream.write_all(&b"\x01"[..]).await.0?;
+        let (res, _) = stream.read(vec![0; 1]).await;
+        res?;
+        Ok(listeners)
+    }
+}
+
+impl std::fmt::Debug for Takeover {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Takeover")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
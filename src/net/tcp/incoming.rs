@@ -0,0 +1,325 @@
+use super::TcpStream;
+use crate::io::{Accept, Socket};
+use crate::runtime::driver::op::Op;
+use crate::runtime::CONTEXT;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::{io, net::SocketAddr};
+use tokio::sync::Notify;
+
+/// Connections accepted with several accept operations kept in flight.
+///
+/// Created by [`TcpListener::incoming`]. Each accept operation takes one
+/// connection, and a new operation is submitted in its place on the next
+/// call to [`next`], so a burst of connections is taken by the operations
+/// already in flight rather than waiting for each accept to be submitted in
+/// turn. When several operations have completed by the time [`next`] looks
+/// at them, the connection taken by the operation submitted first is
+/// returned first.
+///
+/// [`next`]: TcpIncoming::next
+///
+/// Dropping the `TcpIncoming` cancels the accept operations in flight.
+///
+/// Under overload, connections can be shed as they are accepted with a hook
+/// set with [`set_admission`].
+///
+/// [`TcpListener::incoming`]: super::TcpListener::incoming
+/// [`set_admission`]: TcpIncoming::set_admission
+pub struct TcpIncoming {
+    socket: Socket,
+    depth: usize,
+    // Accept operations in flight, in the order they were submitted
+    ops: Vec<Op<Accept>>,
+
+    admission: Option<Box<AdmissionHook>>,
+    open: Rc<OpenCount>,
+    deferred: VecDeque<(TcpStream, SocketAddr)>,
+    // The number of open connections when the deferred connections were
+    // last decided on
+    last_open: usize,
+}
+
+type AdmissionHook = dyn FnMut(SocketAddr, &AcceptLoad) -> Admission;
+
+/// What to do with a connection just accepted, as decided by the hook set
+/// with [`TcpIncoming::set_admission`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// Returns the connection from [`TcpIncoming::next`].
+    Accept,
+
+    /// Closes the connection right away, without returning it.
+    Close,
+
+    /// Holds the connection until another connection returned by the
+    /// `TcpIncoming` is closed, then passes it to the hook again.
+    ///
+    /// Deferred connections are passed to the hook in the order they were
+    /// accepted, and stay queued behind the first one deferred again.
+    Defer,
+}
+
+/// The load of the runtime and the listener when a connection is accepted,
+/// passed to the hook set with [`TcpIncoming::set_admission`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcceptLoad {
+    in_flight: usize,
+    open: usize,
+    deferred: usize,
+}
+
+impl AcceptLoad {
+    /// Returns the number of operations in flight on the runtime, including
+    /// the accept operations of the `TcpIncoming`.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// Returns the number of connections returned by the `TcpIncoming`
+    /// which are still open.
+    pub fn open(&self) -> usize {
+        self.open
+    }
+
+    /// Returns the number of connections held with [`Admission::Defer`],
+    /// not including the connection being decided on.
+    pub fn deferred(&self) -> usize {
+        self.deferred
+    }
+}
+
+// The number of connections admitted which are still open, notifying the
+// `TcpIncoming` when one is closed.
+#[derive(Default)]
+struct OpenCount {
+    count: Cell<usize>,
+    closed: Notify,
+}
+
+// Held by an admitted stream, counting it as open until it is dropped.
+pub(crate) struct Admitted(Rc<OpenCount>);
+
+impl Admitted {
+    fn new(open: &Rc<OpenCount>) -> Admitted {
+        open.count.set(open.count.get() + 1);
+        Admitted(open.clone())
+    }
+}
+
+impl Drop for Admitted {
+    fn drop(&mut self) {
+        self.0.count.set(self.0.count.get() - 1);
+        self.0.closed.notify_waiters();
+    }
+}
+
+impl TcpIncoming {
+    pub(super) fn new(socket: Socket, depth: usize) -> TcpIncoming {
+        assert!(
+            depth > 0,
+            "the depth of the accept pipeline must be at least 1"
+        );
+        TcpIncoming {
+            socket,
+            depth,
+            ops: Vec::with_capacity(depth),
+            admission: None,
+            open: Rc::default(),
+            deferred: VecDeque::new(),
+            last_open: 0,
+        }
+    }
+
+    /// Returns the number of accept operations kept in flight.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the number of connections returned by [`next`] which are
+    /// still open.
+    ///
+    /// [`next`]: TcpIncoming::next
+    pub fn open_connections(&self) -> usize {
+        self.open.count.get()
+    }
+
+    /// Sets a hook deciding on each connection accepted whether it is
+    /// returned, closed, or deferred, with the load at the time.
+    ///
+    /// The hook is called synchronously in [`next`], with the address of
+    /// the peer, before any data is read from the connection. Closing the
+    /// connections a server cannot take on sheds the load at the cheapest
+    /// point, while deferring them holds them until a connection is closed.
+    ///
+    /// [`next`]: TcpIncoming::next
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::net::{Admission, TcpListener};
+    ///
+    /// tokio_uring::start(async {
+    ///     let listener = TcpListener::bind("127.0.0.1:8080".parse().unwrap()).unwrap();
+    ///     let mut incoming = listener.incoming(16);
+    ///     incoming.set_admission(|_peer, load| {
+    ///         if load.open() < 10_000 {
+    ///             Admission::Accept
+    ///         } else if load.deferred() < 1_000 {
+    ///             Admission::Defer
+    ///         } else {
+    ///             Admission::Close
+    ///         }
+    ///     });
+    ///
+    ///     loop {
+    ///         let (stream, _) = incoming.next().await.unwrap();
+    ///         tokio_uring::spawn(async move {
+    ///             let _ = stream.write_all(&b"hello\n"[..]).await;
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    pub fn set_admission<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: FnMut(SocketAddr, &AcceptLoad) -> Admission + 'static,
+    {
+        self.admission = Some(Box::new(hook));
+        self
+    }
+
+    /// Accepts the next incoming connection.
+    ///
+    /// The first call submits the accept operations. A failed accept is
+    /// replaced with a new operation on the next call, like a successful
+    /// one.
+    ///
+    /// With a hook set with [`set_admission`], the connections it closes or
+    /// defers are not returned, and the call waits for the next one.
+    ///
+    /// [`set_admission`]: TcpIncoming::set_admission
+    pub async fn next(&mut self) -> io::Result<(TcpStream, SocketAddr)> {
+        // Connections closed between the calls are not notified
+        if !self.deferred.is_empty() && self.open.count.get() < self.last_open {
+            if let Some(conn) = self.admit_deferred() {
+                return Ok(conn);
+            }
+        }
+
+        loop {
+            while self.ops.len() < self.depth {
+                self.ops.push(Op::accept(&self.socket.fd)?);
+            }
+
+            let open = self.open.clone();
+            let mut closed = pin!(open.closed.notified());
+            // Deferred connections are only looked at again once a
+            // connection has been closed
+            closed.as_mut().enable();
+            self.last_open = self.open.count.get();
+            let accepted = poll_fn(|cx| {
+                if let Poll::Ready(res) = self.poll_accept(cx) {
+                    return Poll::Ready(Some(res));
+                }
+                if !self.deferred.is_empty() && closed.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(None);
+                }
+                Poll::Pending
+            })
+            .await;
+
+            let Some(res) = accepted else {
+                if let Some(conn) = self.admit_deferred() {
+                    return Ok(conn);
+                }
+                continue;
+            };
+            let (socket, socket_addr) = res?;
+            let stream = TcpStream::from_socket(socket);
+            let socket_addr =
+                socket_addr.ok_or_else(|| io::Error::other("Could not get socket IP address"))?;
+            match self.admit(socket_addr) {
+                Admission::Accept => return Ok(self.admitted(stream, socket_addr)),
+                Admission::Close => {}
+                Admission::Defer => self.deferred.push_back((stream, socket_addr)),
+            }
+        }
+    }
+
+    // Calls the hook on a connection, accepting it if there is none.
+    fn admit(&mut self, peer: SocketAddr) -> Admission {
+        let Some(hook) = &mut self.admission else {
+            return Admission::Accept;
+        };
+        let load = AcceptLoad {
+            in_flight: CONTEXT.with(|x| {
+                x.handle()
+                    .expect("Not in a runtime context")
+                    .num_operations()
+            }),
+            open: self.open.count.get(),
+            deferred: self.deferred.len(),
+        };
+        hook(peer, &load)
+    }
+
+    // Passes the deferred connections to the hook in order, until one is
+    // accepted or deferred again.
+    fn admit_deferred(&mut self) -> Option<(TcpStream, SocketAddr)> {
+        while let Some((stream, peer)) = self.deferred.pop_front() {
+            match self.admit(peer) {
+                Admission::Accept => return Some(self.admitted(stream, peer)),
+                Admission::Close => {}
+                Admission::Defer => {
+                    self.deferred.push_front((stream, peer));
+                    break;
+                }
+            }
+        }
+        None
+    }
+
+    fn admitted(&mut self, mut stream: TcpStream, peer: SocketAddr) -> (TcpStream, SocketAddr) {
+        stream.admitted = Some(Admitted::new(&self.open));
+        self.last_open = self.open.count.get();
+        (stream, peer)
+    }
+
+    fn poll_accept(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(Socket, Option<SocketAddr>)>> {
+        for i in 0..self.ops.len() {
+            if let Poll::Ready(res) = Pin::new(&mut self.ops[i]).poll(cx) {
+                self.ops.remove(i);
+                return Poll::Ready(res);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl fmt::Debug for TcpIncoming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpIncoming")
+            .field("depth", &self.depth)
+            .field("open", &self.open.count.get())
+            .field("deferred", &self.deferred.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for TcpIncoming {
+    fn drop(&mut self) {
+        // A connection accepted by an operation completing after the drop
+        // is closed by the driver.
+        for op in &self.ops {
+            op.cancel();
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt;
+use std::io;
+use std::mem::{self, offset_of};
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+// The kernel's `struct tcp_info`, with the fields up to Linux 5.0. The
+// definition of the libc crate for glibc lacks the later fields.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct RawTcpInfo {
+    state: u8,
+    ca_state: u8,
+    retransmits: u8,
+    probes: u8,
+    backoff: u8,
+    options: u8,
+    wscale: u8,
+    app_limited: u8,
+    rto: u32,
+    ato: u32,
+    snd_mss: u32,
+    rcv_mss: u32,
+    unacked: u32,
+    sacked: u32,
+    lost: u32,
+    retrans: u32,
+    fackets: u32,
+    last_data_sent: u32,
+    last_ack_sent: u32,
+    last_data_recv: u32,
+    last_ack_recv: u32,
+    pmtu: u32,
+    rcv_ssthresh: u32,
+    rtt: u32,
+    rttvar: u32,
+    snd_ssthresh: u32,
+    snd_cwnd: u32,
+    advmss: u32,
+    reordering: u32,
+    rcv_rtt: u32,
+    rcv_space: u32,
+    total_retrans: u32,
+    pacing_rate: u64,
+    max_pacing_rate: u64,
+    bytes_acked: u64,
+    bytes_received: u64,
+    segs_out: u32,
+    segs_in: u32,
+    notsent_bytes: u32,
+    min_rtt: u32,
+    data_segs_in: u32,
+    data_segs_out: u32,
+    delivery_rate: u64,
+    busy_time: u64,
+    rwnd_limited: u64,
+    sndbuf_limited: u64,
+    delivered: u32,
+    delivered_ce: u32,
+    bytes_sent: u64,
+    bytes_retrans: u64,
+    dsack_dups: u32,
+    reord_seen: u32,
+}
+
+// The state of an established connection, not defined by libc for Linux.
+const TCP_ESTABLISHED: u8 = 1;
+
+// Returns a field of the kernel's structure, if the kernel has filled it in.
+macro_rules! optional {
+    ($info:expr, $field:ident) => {
+        if $info.has(offset_of!(RawTcpInfo, $field) + mem::size_of_val(&$info.raw.$field)) {
+            Some($info.raw.$field)
+        } else {
+            None
+        }
+    };
+}
+
+/// Statistics of a TCP connection, reported by the kernel with the
+/// `TCP_INFO` socket option.
+///
+/// Returned by [`TcpStream::tcp_info`]. The statistics which older kernels
+/// do not report are optional: they are `None` if the running kernel does
+/// not report them.
+///
+/// [`TcpStream::tcp_info`]: crate::net::TcpStream::tcp_info
+#[derive(Clone, Copy)]
+pub struct TcpInfo {
+    raw: RawTcpInfo,
+    len: usize,
+}
+
+impl TcpInfo {
+    pub(crate) fn get(fd: RawFd) -> io::Result<TcpInfo> {
+        let mut raw = RawTcpInfo::default();
+        let mut len = mem::size_of::<RawTcpInfo>() as libc::socklen_t;
+        syscall!(getsockopt(
+            fd,
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            &mut raw as *mut RawTcpInfo as *mut libc::c_void,
+            &mut len,
+        ))?;
+        Ok(TcpInfo {
+            raw,
+            len: len as usize,
+        })
+    }
+
+    // Returns whether the kernel has filled in the field ending at `end`.
+    fn has(&self, end: usize) -> bool {
+        self.len >= end
+    }
+
+    /// Returns whether the connection is established: it is not being set
+    /// up, and neither end has closed it.
+    pub fn is_established(&self) -> bool {
+        self.raw.state == TCP_ESTABLISHED
+    }
+
+    /// Returns the smoothed round-trip time.
+    pub fn rtt(&self) -> Duration {
+        Duration::from_micros(self.raw.rtt.into())
+    }
+
+    /// Returns the variance of the round-trip time.
+    pub fn rtt_var(&self) -> Duration {
+        Duration::from_micros(self.raw.rttvar.into())
+    }
+
+    /// Returns the minimum round-trip time observed on the connection.
+    pub fn min_rtt(&self) -> Option<Duration> {
+        optional!(self, min_rtt).map(|us| Duration::from_micros(us.into()))
+    }
+
+    /// Returns the retransmission timeout.
+    pub fn rto(&self) -> Duration {
+        Duration::from_micros(self.raw.rto.into())
+    }
+
+    /// Returns the congestion window, in segments.
+    pub fn snd_cwnd(&self) -> u32 {
+        self.raw.snd_cwnd
+    }
+
+    /// Returns the slow start threshold, in segments.
+    pub fn snd_ssthresh(&self) -> u32 {
+        self.raw.snd_ssthresh
+    }
+
+    /// Returns the maximum segment size for sending.
+    pub fn snd_mss(&self) -> u32 {
+        self.raw.snd_mss
+    }
+
+    /// Returns the path MTU.
+    pub fn pmtu(&self) -> u32 {
+        self.raw.pmtu
+    }
+
+    /// Returns the number of segments sent and not yet acknowledged.
+    pub fn unacked(&self) -> u32 {
+        self.raw.unacked
+    }
+
+    /// Returns the number of segments considered lost.
+    pub fn lost(&self) -> u32 {
+        self.raw.lost
+    }
+
+    /// Returns the number of consecutive retransmissions of the current
+    /// unacknowledged segment, reset when it is acknowledged.
+    pub fn retransmits(&self) -> u8 {
+        self.raw.retransmits
+    }
+
+    /// Returns the total number of segments retransmitted on the
+    /// connection.
+    pub fn total_retrans(&self) -> u32 {
+        self.raw.total_retrans
+    }
+
+    /// Returns the pacing rate, in bytes per second.
+    pub fn pacing_rate(&self) -> Option<u64> {
+        optional!(self, pacing_rate)
+    }
+
+    /// Returns the estimated delivery rate of the connection, in bytes per
+    /// second.
+    pub fn delivery_rate(&self) -> Option<u64> {
+        optional!(self, delivery_rate)
+    }
+
+    /// Returns whether the delivery rate was measured while the sender was
+    /// limited by the application rather than the network.
+    pub fn delivery_rate_app_limited(&self) -> Option<bool> {
+        optional!(self, delivery_rate).map(|_| self.raw.app_limited & 1 != 0)
+    }
+
+    /// Returns the number of bytes sent, including retransmissions.
+    pub fn bytes_sent(&self) -> Option<u64> {
+        optional!(self, bytes_sent)
+    }
+
+    /// Returns the number of bytes retransmitted.
+    pub fn bytes_retrans(&self) -> Option<u64> {
+        optional!(self, bytes_retrans)
+    }
+
+    /// Returns the number of bytes acknowledged by the peer.
+    pub fn bytes_acked(&self) -> Option<u64> {
+        optional!(self, bytes_acked)
+    }
+
+    /// Returns the number of bytes received.
+    pub fn bytes_received(&self) -> Option<u64> {
+        optional!(self, bytes_received)
+    }
+
+    /// Returns the number of bytes written by the application and not yet
+    /// sent.
+    pub fn notsent_bytes(&self) -> Option<u32> {
+        optional!(self, notsent_bytes)
+    }
+
+    /// Returns the number of segments sent, including retransmissions.
+    pub fn segs_out(&self) -> Option<u32> {
+        optional!(self, segs_out)
+    }
+
+    /// Returns the number of segments received.
+    pub fn segs_in(&self) -> Option<u32> {
+        optional!(self, segs_in)
+    }
+}
+
+impl fmt::Debug for TcpInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpInfo")
+            .field("established", &self.is_established())
+            .field("rtt", &self.rtt())
+            .field("rtt_var", &self.rtt_var())
+            .field("min_rtt", &self.min_rtt())
+            .field("rto", &self.rto())
+            .field("snd_cwnd", &self.snd_cwnd())
+            .field("snd_ssthresh", &self.snd_ssthresh())
+            .field("snd_mss", &self.snd_mss())
+            .field("pmtu", &self.pmtu())
+            .field("unacked", &self.unacked())
+            .field("lost", &self.lost())
+            .field("retransmits", &self.retransmits())
+            .field("total_retrans", &self.total_retrans())
+            .field("pacing_rate", &self.pacing_rate())
+            .field("delivery_rate", &self.delivery_rate())
+            .field("bytes_sent", &self.bytes_sent())
+            .field("bytes_retrans", &self.bytes_retrans())
+            .field("bytes_acked", &self.bytes_acked())
+            .field("bytes_received", &self.bytes_received())
+            .finish_non_exhaustive()
+    }
+}
@@ -1,4 +1,4 @@
-use super::TcpStream;
+use super::{TcpIncoming, TcpStream};
 use crate::io::Socket;
 use std::{io, net::SocketAddr};
 
@@ -47,12 +47,72 @@ impl TcpListener {
     ///
     /// Binding with a port number of 0 will request that the OS assigns a port
     /// to this listener.
+    ///
+    /// If the address is an IPv6 address, the socket takes the system
+    /// default of the `IPV6_V6ONLY` option, set by the `net.ipv6.bindv6only`
+    /// sysctl. With the usual default, the socket is dual-stack: bound to
+    /// the unspecified address `[::]`, it also accepts IPv4 connections,
+    /// with the IPv4 addresses mapped to IPv6 (`::ffff:a.b.c.d`). Use
+    /// [`bind_with_only_v6`] to choose.
+    ///
+    /// [`bind_with_only_v6`]: TcpListener::bind_with_only_v6
     pub fn bind(addr: SocketAddr) -> io::Result<Self> {
-        let socket = Socket::bind(addr, libc::SOCK_STREAM)?;
+        Self::bind_internal(addr, None)
+    }
+
+    /// Creates a new TcpListener like [`bind`], setting the `IPV6_V6ONLY`
+    /// option of an IPv6 socket to `only_v6` before it is bound.
+    ///
+    /// With `only_v6` set, the listener only accepts IPv6 connections, and
+    /// another socket can bind the same port for IPv4. Otherwise it is
+    /// dual-stack, whatever the system default is. The option does not
+    /// apply to IPv4 addresses, and is ignored for them.
+    ///
+    /// [`bind`]: TcpListener::bind
+    pub fn bind_with_only_v6(addr: SocketAddr, only_v6: bool) -> io::Result<Self> {
+        Self::bind_internal(addr, Some(only_v6))
+    }
+
+    fn bind_internal(addr: SocketAddr, only_v6: Option<bool>) -> io::Result<Self> {
+        let socket = Socket::bind(addr, libc::SOCK_STREAM, only_v6)?;
         socket.listen(1024)?;
         Ok(TcpListener { inner: socket })
     }
 
+    pub(super) fn from_socket(inner: Socket) -> TcpListener {
+        TcpListener { inner }
+    }
+
+    pub(crate) fn socket(&self) -> &Socket {
+        &self.inner
+    }
+
+    /// Creates a new `TcpListener` from a listener created with the standard
+    /// library, or received from elsewhere, e.g. from systemd with socket
+    /// activation.
+    ///
+    /// The listener must be listening for connections.
+    ///
+    /// The socket is switched to blocking mode if it is non-blocking, e.g.
+    /// if it comes from a Tokio socket: the operations of this crate wait for
+    /// the socket to be ready in the kernel.
+    pub fn from_std(listener: std::net::TcpListener) -> TcpListener {
+        TcpListener {
+            inner: Socket::from_std(listener),
+        }
+    }
+
+    /// Converts the listener into a `std::net::TcpListener`.
+    ///
+    /// This allows the socket to be used outside the runtime, e.g. handed
+    /// off to another process at shutdown. The returned socket is in
+    /// blocking mode. If operations on the socket are still in flight, they
+    /// keep the socket open until they complete, and a duplicate of the file
+    /// descriptor is returned.
+    pub fn into_std(self) -> io::Result<std::net::TcpListener> {
+        self.inner.into_std()
+    }
+
     /// Returns the local address that this listener is bound to.
     ///
     /// This can be useful, for example, when binding to port 0 to
@@ -91,10 +151,69 @@ impl TcpListener {
     /// [`TcpStream`]: struct@crate::net::TcpStream
     pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
         let (socket, socket_addr) = self.inner.accept().await?;
-        let stream = TcpStream { inner: socket };
-        let socket_addr = socket_addr.ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "Could not get socket IP address")
-        })?;
+        let stream = TcpStream::from_socket(socket);
+        let socket_addr =
+            socket_addr.ok_or_else(|| io::Error::other("Could not get socket IP address"))?;
         Ok((stream, socket_addr))
     }
+
+    /// Returns a pipeline accepting connections with `depth` accept
+    /// operations kept in flight.
+    ///
+    /// With several accepts in flight, a burst of connections does not wait
+    /// for each accept to be submitted after the previous one completes.
+    /// This helps on kernels without multishot accept. The pipeline keeps
+    /// the listening socket open until it is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::buf::BoundedBuf;
+    /// use tokio_uring::net::TcpListener;
+    ///
+    /// tokio_uring::start(async {
+    ///     let listener = TcpListener::bind("127.0.0.1:8080".parse().unwrap()).unwrap();
+    ///     let mut incoming = listener.incoming(16);
+    ///
+    ///     loop {
+    ///         let (stream, _) = incoming.next().await.unwrap();
+    ///         tokio_uring::spawn(async move {
+    ///             let (res, buf) = stream.read(vec![0; 4096]).await;
+    ///             let n = res.unwrap();
+    ///             stream.write_all(buf.slice(..n)).await.0.unwrap();
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    pub fn incoming(&self, depth: usize) -> TcpIncoming {
+        TcpIncoming::new(self.inner.clone(), depth)
+    }
+}
+
+/// Adopts a socket configured with `socket2`, like [`TcpListener::from_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl From<socket2::Socket> for TcpListener {
+    fn from(socket: socket2::Socket) -> Self {
+        TcpListener {
+            inner: Socket::from_std(socket),
+        }
+    }
+}
+
+/// Converts the listener into a `socket2::Socket`, like [`TcpListener::into_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl std::convert::TryFrom<TcpListener> for socket2::Socket {
+    type Error = io::Error;
+
+    fn try_from(listener: TcpListener) -> io::Result<Self> {
+        listener.inner.into_std()
+    }
 }
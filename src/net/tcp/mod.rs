@@ -1,5 +1,14 @@
+mod incoming;
+pub use incoming::{AcceptLoad, Admission, TcpIncoming};
+
+mod info;
+pub use info::TcpInfo;
+
 mod listener;
 pub use listener::TcpListener;
 
+mod socket;
+pub use socket::TcpSocket;
+
 mod stream;
 pub use stream::TcpStream;
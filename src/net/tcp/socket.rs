@@ -0,0 +1,287 @@
+use super::{TcpListener, TcpStream};
+use crate::io::Socket;
+use socket2::SockRef;
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// A TCP socket which has not been converted to a [`TcpListener`] or a
+/// [`TcpStream`] yet.
+///
+/// A `TcpSocket` allows the socket options to be set before the socket is
+/// bound and listens, or connects. [`TcpListener::bind`] and
+/// [`TcpStream::connect`] use the default options.
+///
+/// # Examples
+///
+/// Listening on IPv6 only, to have another listener accept IPv4 connections
+/// on the same port:
+///
+/// ```no_run
+/// use tokio_uring::net::TcpSocket;
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         let socket = TcpSocket::new_v6()?;
+///         socket.set_only_v6(true)?;
+///         socket.set_reuseaddr(true)?;
+///         socket.bind("[::]:8080".parse().unwrap())?;
+///         let listener = socket.listen(1024)?;
+///
+///         let (stream, peer) = listener.accept().await?;
+///         assert!(peer.is_ipv6());
+///         Ok(())
+///     })
+/// }
+/// ```
+///
+/// The sockets of servers setting up many sockets, or of clients opening
+/// many connections, can be created, bound and set listening with io-uring
+/// operations instead of system calls, with the asynchronous methods
+/// [`open_v4`], [`bind_async`] and [`listen_async`].
+///
+/// [`open_v4`]: TcpSocket::open_v4
+/// [`bind_async`]: TcpSocket::bind_async
+/// [`listen_async`]: TcpSocket::listen_async
+pub struct TcpSocket {
+    inner: Socket,
+}
+
+impl TcpSocket {
+    /// Creates a new IPv4 socket.
+    pub fn new_v4() -> io::Result<TcpSocket> {
+        Self::new(libc::AF_INET)
+    }
+
+    /// Creates a new IPv6 socket.
+    ///
+    /// The socket takes the system default of the `IPV6_V6ONLY` option, set
+    /// by the `net.ipv6.bindv6only` sysctl. Call [`set_only_v6`] to control
+    /// whether it is dual-stack.
+    ///
+    /// [`set_only_v6`]: TcpSocket::set_only_v6
+    pub fn new_v6() -> io::Result<TcpSocket> {
+        Self::new(libc::AF_INET6)
+    }
+
+    /// Creates a new IPv4 socket with an io-uring operation.
+    ///
+    /// This is the asynchronous counterpart of [`new_v4`], which avoids a
+    /// system call per socket. On kernels without the operation (before
+    /// Linux 5.19), the socket is created with the system call.
+    ///
+    /// [`new_v4`]: TcpSocket::new_v4
+    pub async fn open_v4() -> io::Result<TcpSocket> {
+        Self::open(libc::AF_INET).await
+    }
+
+    /// Creates a new IPv6 socket with an io-uring operation.
+    ///
+    /// This is the asynchronous counterpart of [`new_v6`]; see
+    /// [`open_v4`].
+    ///
+    /// [`new_v6`]: TcpSocket::new_v6
+    /// [`open_v4`]: TcpSocket::open_v4
+    pub async fn open_v6() -> io::Result<TcpSocket> {
+        Self::open(libc::AF_INET6).await
+    }
+
+    fn new(domain: libc::c_int) -> io::Result<TcpSocket> {
+        Ok(TcpSocket {
+            inner: Socket::new_in_domain(domain, libc::SOCK_STREAM)?,
+        })
+    }
+
+    async fn open(domain: libc::c_int) -> io::Result<TcpSocket> {
+        Ok(TcpSocket {
+            inner: Socket::open_in_domain(domain, libc::SOCK_STREAM).await?,
+        })
+    }
+
+    /// Sets the value of the `SO_REUSEADDR` option on the socket.
+    pub fn set_reuseaddr(&self, reuseaddr: bool) -> io::Result<()> {
+        SockRef::from(self).set_reuse_address(reuseaddr)
+    }
+
+    /// Gets the value of the `SO_REUSEADDR` option on the socket.
+    pub fn reuseaddr(&self) -> io::Result<bool> {
+        SockRef::from(self).reuse_address()
+    }
+
+    /// Sets the value of the `SO_REUSEPORT` option on the socket.
+    pub fn set_reuseport(&self, reuseport: bool) -> io::Result<()> {
+        SockRef::from(self).set_reuse_port(reuseport)
+    }
+
+    /// Gets the value of the `SO_REUSEPORT` option on the socket.
+    pub fn reuseport(&self) -> io::Result<bool> {
+        SockRef::from(self).reuse_port()
+    }
+
+    /// Steers the connections of the `SO_REUSEPORT` group of the socket by
+    /// the CPU receiving them, attaching the classic BPF program returning
+    /// the number of the CPU modulo `sockets`.
+    ///
+    /// The sockets of a group are numbered in the order they listen, from
+    /// 0. With one runtime per CPU, each accepting on the socket numbered
+    /// as the CPU its thread is pinned to, a connection is accepted on the
+    /// CPU which has processed its packets. `sockets` is the number of
+    /// sockets of the group.
+    ///
+    /// The program is attached to the group, and must be set on the first
+    /// socket of the group, before it listens: the other sockets join the
+    /// group as they listen.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `EINVAL` if `sockets` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::net::{TcpListener, TcpSocket};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let cpus = std::thread::available_parallelism()?.get();
+    ///     let mut listeners = Vec::new();
+    ///     for cpu in 0..cpus {
+    ///         let socket = TcpSocket::new_v4()?;
+    ///         socket.set_reuseport(true)?;
+    ///         if cpu == 0 {
+    ///             socket.set_reuseport_cbpf(cpus as u32)?;
+    ///         }
+    ///         socket.bind("0.0.0.0:8080".parse().unwrap())?;
+    ///         listeners.push(socket.listen(1024)?.into_std()?);
+    ///     }
+    ///
+    ///     let threads: Vec<_> = listeners
+    ///         .into_iter()
+    ///         .map(|listener| {
+    ///             std::thread::spawn(move || -> std::io::Result<()> {
+    ///                 // Pin the thread to its CPU, e.g. with `sched_setaffinity`
+    ///                 tokio_uring::start(async {
+    ///                     let listener = TcpListener::from_std(listener);
+    ///                     loop {
+    ///                         let (stream, _) = listener.accept().await?;
+    ///                         # drop(stream);
+    ///                     }
+    ///                 })
+    ///             })
+    ///         })
+    ///         .collect();
+    ///     for thread in threads {
+    ///         thread.join().unwrap()?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_reuseport_cbpf(&self, sockets: u32) -> io::Result<()> {
+        // Not defined by libc for Linux
+        const SO_ATTACH_REUSEPORT_CBPF: libc::c_int = 51;
+
+        if sockets == 0 {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        let insn = |code: u32, k: u32| libc::sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        };
+        let mut filter = [
+            // A = the number of the CPU
+            insn(
+                libc::BPF_LD | libc::BPF_W | libc::BPF_ABS,
+                (libc::SKF_AD_OFF + libc::SKF_AD_CPU) as u32,
+            ),
+            // A %= sockets
+            insn(libc::BPF_ALU | libc::BPF_MOD | libc::BPF_K, sockets),
+            // Return A, the index of the socket
+            insn(libc::BPF_RET | libc::BPF_A, 0),
+        ];
+        let prog = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_mut_ptr(),
+        };
+        syscall!(setsockopt(
+            self.as_raw_fd(),
+            libc::SOL_SOCKET,
+            SO_ATTACH_REUSEPORT_CBPF,
+            &prog as *const libc::sock_fprog as *const libc::c_void,
+            std::mem::size_of::<libc::sock_fprog>() as libc::socklen_t,
+        ))?;
+        Ok(())
+    }
+
+    /// Sets the value of the `IPV6_V6ONLY` option on an IPv6 socket.
+    ///
+    /// If set, the socket only sends and receives IPv6 traffic. Otherwise it
+    /// is dual-stack: bound to the unspecified address `[::]`, it also
+    /// accepts IPv4 connections, with the IPv4 addresses mapped to IPv6
+    /// (`::ffff:a.b.c.d`). The option must be set before the socket is bound.
+    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
+        SockRef::from(self).set_only_v6(only_v6)
+    }
+
+    /// Gets the value of the `IPV6_V6ONLY` option on an IPv6 socket.
+    pub fn only_v6(&self) -> io::Result<bool> {
+        SockRef::from(self).only_v6()
+    }
+
+    /// Binds the socket to an address.
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<()> {
+        SockRef::from(self).bind(&addr.into())
+    }
+
+    /// Binds the socket to an address with an io-uring operation.
+    ///
+    /// This is the asynchronous counterpart of [`bind`]. On kernels without
+    /// the operation (before Linux 6.11), the socket is bound with the
+    /// system call.
+    ///
+    /// [`bind`]: TcpSocket::bind
+    pub async fn bind_async(&self, addr: SocketAddr) -> io::Result<()> {
+        self.inner.bind_addr(addr.into()).await
+    }
+
+    /// Returns the local address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        SockRef::from(self)
+            .local_addr()?
+            .as_socket()
+            .ok_or_else(|| io::Error::other("the socket address is not an IP address"))
+    }
+
+    /// Converts the socket into a listener, listening for connections with
+    /// a queue of up to `backlog` pending connections.
+    pub fn listen(self, backlog: u32) -> io::Result<TcpListener> {
+        let backlog = backlog.min(libc::c_int::MAX as u32) as libc::c_int;
+        self.inner.listen(backlog)?;
+        Ok(TcpListener::from_socket(self.inner))
+    }
+
+    /// Converts the socket into a listener with an io-uring operation.
+    ///
+    /// This is the asynchronous counterpart of [`listen`]. On kernels
+    /// without the operation (before Linux 6.11), the socket listens with
+    /// the system call.
+    ///
+    /// [`listen`]: TcpSocket::listen
+    pub async fn listen_async(self, backlog: u32) -> io::Result<TcpListener> {
+        let backlog = backlog.min(libc::c_int::MAX as u32) as libc::c_int;
+        self.inner.listen_op(backlog).await?;
+        Ok(TcpListener::from_socket(self.inner))
+    }
+
+    /// Connects the socket to a remote host, converting it into a stream.
+    pub async fn connect(self, addr: SocketAddr) -> io::Result<TcpStream> {
+        self.inner.connect(addr.into()).await?;
+        Ok(TcpStream::from_socket(self.inner))
+    }
+}
+
+impl AsRawFd for TcpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
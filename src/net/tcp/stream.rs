@@ -1,13 +1,23 @@
 use std::{
+    cell::OnceCell,
     io,
     net::SocketAddr,
     os::unix::prelude::{AsRawFd, FromRawFd, RawFd},
 };
 
+use super::incoming::Admitted;
+use crate::buf::provided::{BufRing, ProvidedBuf, SendRing};
+use crate::net::{RecvChunks, TcpInfo, Timestamping, Timestamps, ZcNotification};
 use crate::{
     buf::fixed::FixedBuf,
     buf::{BoundedBuf, BoundedBufMut, IoBuf},
-    io::{SharedFd, Socket},
+    io::{SharedFd, Socket, SplicePipe},
+};
+#[cfg(feature = "tokio-io")]
+use {
+    crate::io::Staging,
+    std::pin::Pin,
+    std::task::{Context, Poll},
 };
 
 /// A TCP stream between a local and a remote socket.
@@ -40,17 +50,67 @@ use crate::{
 /// [`listener`]: crate::net::TcpListener
 pub struct TcpStream {
     pub(super) inner: Socket,
+
+    // Counts the stream as open in the `TcpIncoming` which admitted it
+    pub(super) admitted: Option<Admitted>,
+
+    // The pipe of `splice_to`, created on first use
+    splice_pipe: OnceCell<SplicePipe>,
+
+    #[cfg(feature = "tokio-io")]
+    staging: Staging,
 }
 
 impl TcpStream {
     /// Opens a TCP connection to a remote host at the given `SocketAddr`
     pub async fn connect(addr: SocketAddr) -> io::Result<TcpStream> {
-        let socket = Socket::new(addr, libc::SOCK_STREAM)?;
+        let socket = Socket::open(addr, libc::SOCK_STREAM).await?;
         socket.connect(socket2::SockAddr::from(addr)).await?;
-        let tcp_stream = TcpStream { inner: socket };
+        let tcp_stream = TcpStream::from_socket(socket);
         Ok(tcp_stream)
     }
 
+    /// Opens a TCP connection to a remote host given by its name and port.
+    ///
+    /// The host name is resolved on tokio's blocking thread pool, so the
+    /// runtime is not stalled by the lookup. Each of the resolved addresses
+    /// is tried in turn until a connection succeeds; if none does, the error
+    /// of the last attempt is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::net::TcpStream;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     tokio_uring::start(async {
+    ///         let stream = TcpStream::connect_host("localhost", 8080).await?;
+    ///
+    ///         let (result, _) = stream.write(b"hello world!".as_slice()).await;
+    ///         result?;
+    ///
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn connect_host(host: &str, port: u16) -> io::Result<TcpStream> {
+        let mut last_err = None;
+
+        for addr in tokio::net::lookup_host((host, port)).await? {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_err = Some(e),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any address",
+            )
+        }))
+    }
+
     /// Creates new `TcpStream` from a previously bound `std::net::TcpStream`.
     ///
     /// This function is intended to be used to wrap a TCP stream from the
@@ -61,13 +121,39 @@ impl TcpStream {
     /// This can be used in conjunction with socket2's `Socket` interface to
     /// configure a socket before it's handed off, such as setting options like
     /// `reuse_address` or binding to multiple addresses.
+    ///
+    /// The socket is switched to blocking mode if it is non-blocking, e.g.
+    /// if it comes from a Tokio socket: the operations of this crate wait for
+    /// the socket to be ready in the kernel.
     pub fn from_std(socket: std::net::TcpStream) -> Self {
         let inner = Socket::from_std(socket);
-        Self { inner }
+        Self::from_socket(inner)
+    }
+
+    /// Converts the socket into a `std::net::TcpStream`.
+    ///
+    /// This allows the socket to be used outside the runtime, e.g. handed
+    /// off to another process at shutdown. The returned socket is in
+    /// blocking mode. If operations on the socket are still in flight, they
+    /// keep the socket open until they complete, and a duplicate of the file
+    /// descriptor is returned.
+    ///
+    /// Data buffered by the [`AsyncRead`] implementation of the stream is
+    /// lost.
+    ///
+    /// [`AsyncRead`]: tokio::io::AsyncRead
+    pub fn into_std(self) -> io::Result<std::net::TcpStream> {
+        self.inner.into_std()
     }
 
     pub(crate) fn from_socket(inner: Socket) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            admitted: None,
+            splice_pipe: OnceCell::new(),
+            #[cfg(feature = "tokio-io")]
+            staging: Staging::new(),
+        }
     }
 
     /// Read some data from the stream into the buffer, returning the original buffer and
@@ -76,6 +162,18 @@ impl TcpStream {
         self.inner.read(buf).await
     }
 
+    /// Like [`read`], but also returns the timestamps of the data received,
+    /// if timestamping has been enabled with [`set_timestamping`].
+    ///
+    /// [`read`]: Self::read
+    /// [`set_timestamping`]: Self::set_timestamping
+    pub async fn read_timestamped<T: BoundedBufMut>(
+        &self,
+        buf: T,
+    ) -> crate::BufResult<(usize, Timestamps), T> {
+        self.inner.recv_timestamped(buf).await
+    }
+
     /// Like [`read`], but using a pre-mapped buffer
     /// registered with [`FixedBufRegistry`].
     ///
@@ -94,6 +192,209 @@ impl TcpStream {
         self.inner.read_fixed(buf).await
     }
 
+    /// Receives the data of the stream as a stream of chunks, into the
+    /// buffers of `ring`.
+    ///
+    /// A single multishot receive is kept in flight, rather than submitting
+    /// a read for each chunk, and it is submitted again when the ring runs
+    /// out of buffers and some are given back. See [`RecvChunks`] for the
+    /// details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::buf::provided::BufRing;
+    /// use tokio_uring::net::{RecvEvent, TcpStream};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let ring = BufRing::builder(0).entries(64).buf_len(4096).build()?;
+    ///         let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await?;
+    ///         let mut chunks = stream.recv_multi(&ring);
+    ///         while let Some(event) = chunks.next().await {
+    ///             match event? {
+    ///                 RecvEvent::Data(chunk) => println!("{:?}", &chunk[..]),
+    ///                 RecvEvent::BufferStarved => eprintln!("out of buffers"),
+    ///             }
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub fn recv_multi(&self, ring: &BufRing) -> RecvChunks {
+        RecvChunks::new(self.inner.fd.clone(), ring)
+    }
+
+    /// Receives the data available on the stream into one or more buffers
+    /// of `ring`, with a single operation.
+    ///
+    /// Unlike a receive into a single buffer, which is limited to the size
+    /// of the buffer, the receive fills as many buffers as the data already
+    /// received needs, and the ring has available, and returns them in the
+    /// order of the data; each buffer is filled in full but the last. No
+    /// buffers are returned at the end of the stream.
+    ///
+    /// The buffers are those of `ring`, given back to it when dropped, as
+    /// the kernel picks the buffers of the ring for the data.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ENOBUFS` if the ring has no buffer available, and with
+    /// `EINVAL` if the kernel does not support bundles, which requires
+    /// Linux 6.10.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::buf::provided::BufRing;
+    /// use tokio_uring::net::TcpStream;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     tokio_uring::start(async {
+    ///         let ring = BufRing::builder(0).entries(64).buf_len(4096).build()?;
+    ///         let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await?;
+    ///         loop {
+    ///             let bufs = stream.recv_bundle(&ring).await?;
+    ///             if bufs.is_empty() {
+    ///                 break;
+    ///             }
+    ///             for buf in &bufs {
+    ///                 println!("{:?}", &buf[..]);
+    ///             }
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn recv_bundle(&self, ring: &BufRing) -> io::Result<Vec<ProvidedBuf>> {
+        self.inner.recv_bundle(ring).await
+    }
+
+    /// Sends the buffers queued in `ring`, with a single operation,
+    /// returning the number of bytes sent.
+    ///
+    /// The buffers are sent in the order they were pushed, and are dropped
+    /// once sent. Buffers pushed while the send is in progress may or may
+    /// not be part of it, and are sent by the next call otherwise. The
+    /// kernel has no multishot send: each call sends what is queued.
+    ///
+    /// If the send stops within a buffer, the rest of it is written before
+    /// returning, so that the data stays in order as long as the ring is
+    /// not sent on concurrently.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ENOBUFS` if no buffer is queued, and with `EINVAL` if
+    /// the kernel does not support bundles, which requires Linux 6.10. The
+    /// buffers stay queued on errors.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::buf::provided::SendRing;
+    /// use tokio_uring::net::TcpStream;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     tokio_uring::start(async {
+    ///         let ring = SendRing::new(0, 64)?;
+    ///         let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await?;
+    ///         for i in 0..10 {
+    ///             ring.push(format!("response {}\n", i).into_bytes()).unwrap();
+    ///         }
+    ///         stream.send_bundle(&ring).await?;
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn send_bundle(&self, ring: &SendRing) -> io::Result<usize> {
+        self.inner.send_bundle(ring).await
+    }
+
+    /// Sends data on the stream without copying it, returning as soon as
+    /// the send result is reported, along with a [`ZcNotification`]
+    /// resolving to the buffer once the kernel has released it.
+    ///
+    /// The data is transmitted from the buffer after the send has completed,
+    /// and the buffer is only returned when the kernel notifies that it no
+    /// longer uses it. Awaiting the notification separately allows more
+    /// sends to be submitted in the meantime. As with `write`, the send can
+    /// be partial.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::net::TcpStream;
+    ///
+    /// tokio_uring::start(async {
+    ///     let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await.unwrap();
+    ///
+    ///     let (res, first) = stream.send_zc_notified(vec![1; 65536]).await;
+    ///     res.unwrap();
+    ///     // Send the next buffer while the first one is still in use
+    ///     let (res, second) = stream.send_zc_notified(vec![2; 65536]).await;
+    ///     res.unwrap();
+    ///
+    ///     // The buffers can be reused now
+    ///     let (first, second) = (first.await, second.await);
+    /// });
+    /// ```
+    pub async fn send_zc_notified<T: BoundedBuf>(
+        &self,
+        buf: T,
+    ) -> (io::Result<usize>, ZcNotification<T>) {
+        crate::net::send_zc_notified(&self.inner.fd, buf).await
+    }
+
+    /// Moves up to `len` bytes received on this stream to the stream `dst`,
+    /// without copying the data to user space.
+    ///
+    /// The data is spliced from the socket into a pipe, and from the pipe
+    /// into `dst`, entirely in the kernel. This suits proxies forwarding
+    /// traffic they never need to inspect. Returns the number of bytes
+    /// moved, which can be less than `len`: a single call moves at most the
+    /// capacity of the pipe, 64 KiB by default. A return value of 0 means
+    /// that the end of the stream has been reached.
+    ///
+    /// The stream keeps the pipe for all the calls. Data received from this
+    /// stream and not yet sent, because the future has been dropped or
+    /// sending has failed, stays in the pipe and is sent first by the next
+    /// call, so that no data is lost. It is lost if the stream is dropped or
+    /// converted with [`into_std`].
+    ///
+    /// [`into_std`]: TcpStream::into_std
+    ///
+    /// # Errors
+    ///
+    /// If sending to `dst` fails after part of the data has been sent, the
+    /// number of bytes sent is returned, and the error is returned by the
+    /// next call.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::net::{TcpListener, TcpStream};
+    ///
+    /// tokio_uring::start(async {
+    ///     let listener = TcpListener::bind("127.0.0.1:8080".parse().unwrap()).unwrap();
+    ///     let (client, _) = listener.accept().await.unwrap();
+    ///     let backend = TcpStream::connect("127.0.0.1:80".parse().unwrap()).await.unwrap();
+    ///
+    ///     // Forward the request stream to the backend
+    ///     while client.splice_to(&backend, 65536).await.unwrap() > 0 {}
+    ///     backend.shutdown(std::net::Shutdown::Write).unwrap();
+    /// });
+    /// ```
+    pub async fn splice_to(&self, dst: &TcpStream, len: u32) -> io::Result<usize> {
+        let pipe = match self.splice_pipe.get() {
+            Some(pipe) => pipe,
+            None => {
+                let _ = self.splice_pipe.set(SplicePipe::new()?);
+                self.splice_pipe.get().unwrap()
+            }
+        };
+        pipe.splice(&self.inner.fd, &dst.inner.fd, len).await
+    }
+
     /// Write some data to the stream from the buffer, returning the original buffer and
     /// quantity of data written.
     pub async fn write<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<usize, T> {
@@ -154,6 +455,48 @@ impl TcpStream {
         self.inner.write_all(buf).await
     }
 
+    /// Writes the whole of a request, then reads its response into
+    /// `response`, returning both buffers and the size of the response read.
+    ///
+    /// The write and the read are submitted together, linked so that the
+    /// read is started by the kernel as soon as the request has been
+    /// written, without a round trip through the task. This suits simple
+    /// protocols where a client waits for the response to each request.
+    ///
+    /// If the request is only partly written, the rest of it is written,
+    /// then the response is read, as [`write_all`] and [`read`] would. A
+    /// response longer than the buffer is returned in part, as with
+    /// `read`; the rest of it is read with `read`.
+    ///
+    /// [`write_all`]: Self::write_all
+    /// [`read`]: Self::read
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::net::TcpStream;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     tokio_uring::start(async {
+    ///         let stream = TcpStream::connect("127.0.0.1:6379".parse().unwrap()).await?;
+    ///
+    ///         let (res, (_, response)) = stream
+    ///             .request(b"PING\r\n".to_vec(), vec![0; 64])
+    ///             .await;
+    ///         let n = res?;
+    ///         println!("{}", String::from_utf8_lossy(&response[..n]));
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    pub async fn request<T: BoundedBuf, U: BoundedBufMut>(
+        &self,
+        buf: T,
+        response: U,
+    ) -> crate::BufResult<usize, (T, U)> {
+        self.inner.request(buf, response).await
+    }
+
     /// Like [`write`], but using a pre-mapped buffer
     /// registered with [`FixedBufRegistry`].
     ///
@@ -220,6 +563,25 @@ impl TcpStream {
         self.inner.writev(buf).await
     }
 
+    /// Returns the statistics of the connection reported by the kernel,
+    /// such as the round-trip time, the congestion window and the number
+    /// of retransmissions.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::net::TcpStream;
+    ///
+    /// tokio_uring::start(async {
+    ///     let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await.unwrap();
+    ///     let info = stream.tcp_info().unwrap();
+    ///     println!("rtt: {:?}, cwnd: {}", info.rtt(), info.snd_cwnd());
+    /// });
+    /// ```
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        TcpInfo::get(self.inner.as_raw_fd())
+    }
+
     /// Shuts down the read, write, or both halves of this connection.
     ///
     /// This function will cause all pending and future I/O on the specified portions to return
@@ -228,6 +590,28 @@ impl TcpStream {
         self.inner.shutdown(how)
     }
 
+    /// Sets the timestamps generated for the data received, with the
+    /// `SO_TIMESTAMPING` option. They are returned by [`read_timestamped`].
+    ///
+    /// [`read_timestamped`]: Self::read_timestamped
+    pub fn set_timestamping(&self, flags: Timestamping) -> io::Result<()> {
+        self.inner.set_timestamping(flags)
+    }
+
+    /// Limits the number of operations on this connection in flight at a time, or
+    /// removes the limit with `None`.
+    ///
+    /// See [`File::set_max_in_flight`] for the details.
+    ///
+    /// [`File::set_max_in_flight`]: crate::fs::File::set_max_in_flight
+    ///
+    /// # Panics
+    ///
+    /// Panics if the limit is 0.
+    pub fn set_max_in_flight(&self, max: Option<usize>) {
+        self.inner.set_max_in_flight(max)
+    }
+
     /// Sets the value of the TCP_NODELAY option on this socket.
     ///
     /// If set, this option disables the Nagle algorithm. This means that segments are always sent
@@ -250,3 +634,86 @@ impl AsRawFd for TcpStream {
         self.inner.as_raw_fd()
     }
 }
+
+/// Reads from the stream through an internal staging buffer.
+///
+/// The data is received into a buffer owned by the stream and then copied
+/// into the buffer provided by the caller. Prefer [`read`] when the extra
+/// copy matters.
+///
+/// Requires the `tokio-io` feature.
+///
+/// [`read`]: TcpStream::read
+#[cfg(feature = "tokio-io")]
+impl tokio::io::AsyncRead for TcpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.staging.poll_read(&this.inner, cx, buf)
+    }
+}
+
+/// Writes to the stream through an internal staging buffer.
+///
+/// The data is copied into a buffer owned by the stream, which is then
+/// written in the background. A successful `poll_write` only means that
+/// the data has been accepted; errors writing it are reported by subsequent
+/// calls. Use `poll_flush` to wait until all accepted data has been written.
+///
+/// Note that the inherent methods `write_all` and `shutdown` take precedence
+/// over the `AsyncWriteExt` methods of the same name in method call syntax.
+///
+/// Requires the `tokio-io` feature.
+#[cfg(feature = "tokio-io")]
+impl tokio::io::AsyncWrite for TcpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.staging.poll_write(&this.inner, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.staging.poll_flush(&this.inner, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.staging.poll_shutdown(&this.inner, cx)
+    }
+}
+
+/// Adopts a socket configured with `socket2`, like [`TcpStream::from_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl From<socket2::Socket> for TcpStream {
+    fn from(socket: socket2::Socket) -> Self {
+        TcpStream::from_socket(Socket::from_std(socket))
+    }
+}
+
+/// Converts the stream into a `socket2::Socket`, like [`TcpStream::into_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl std::convert::TryFrom<TcpStream> for socket2::Socket {
+    type Error = io::Error;
+
+    fn try_from(stream: TcpStream) -> io::Result<Self> {
+        stream.inner.into_std()
+    }
+}
+
+#[allow(private_interfaces)]
+impl crate::io::sealed::Sealed for TcpStream {
+    fn socket(&self) -> &Socket {
+        &self.inner
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+// The socket option and the control message of timestamping, the same
+// number on most architectures.
+pub(crate) const SO_TIMESTAMPING: libc::c_int = 37;
+const SCM_TIMESTAMPING: libc::c_int = SO_TIMESTAMPING;
+
+// Flags of `SO_TIMESTAMPING`.
+const SOF_TIMESTAMPING_RX_HARDWARE: u32 = 1 << 2;
+const SOF_TIMESTAMPING_RX_SOFTWARE: u32 = 1 << 3;
+const SOF_TIMESTAMPING_SOFTWARE: u32 = 1 << 4;
+const SOF_TIMESTAMPING_RAW_HARDWARE: u32 = 1 << 6;
+
+/// The timestamps generated for the data received on a socket, set with
+/// the `set_timestamping` method of the socket types.
+///
+/// Flags can be combined with the `|` operator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Timestamping(u32);
+
+impl Timestamping {
+    /// Timestamps taken by the kernel when the packets are received.
+    pub const RX_SOFTWARE: Timestamping =
+        Timestamping(SOF_TIMESTAMPING_RX_SOFTWARE | SOF_TIMESTAMPING_SOFTWARE);
+
+    /// Timestamps taken by the network adapter when the packets are
+    /// received, in the time of the clock of the adapter.
+    ///
+    /// The adapter must support timestamping, and have it enabled with the
+    /// `SIOCSHWTSTAMP` request, e.g. with `hwstamp_ctl` or by a PTP daemon.
+    pub const RX_HARDWARE: Timestamping =
+        Timestamping(SOF_TIMESTAMPING_RX_HARDWARE | SOF_TIMESTAMPING_RAW_HARDWARE);
+
+    /// Returns the set with no flags, which disables timestamping.
+    pub const fn empty() -> Timestamping {
+        Timestamping(0)
+    }
+
+    /// Returns `true` if all flags in `other` are set in `self`.
+    pub const fn contains(self, other: Timestamping) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub(crate) fn bits(self) -> libc::c_int {
+        self.0 as libc::c_int
+    }
+}
+
+impl std::ops::BitOr for Timestamping {
+    type Output = Timestamping;
+
+    fn bitor(self, rhs: Timestamping) -> Timestamping {
+        Timestamping(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Timestamping {
+    fn bitor_assign(&mut self, rhs: Timestamping) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The timestamps of received data, as enabled with [`Timestamping`].
+///
+/// The timestamps are durations since the Unix epoch: of the system clock
+/// for software timestamps, and of the clock of the network adapter for
+/// hardware timestamps. A timestamp is `None` if it has not been enabled,
+/// or was not generated for the data.
+///
+/// For a stream socket, the timestamps are those of the last packet of the
+/// data received.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timestamps {
+    software: Option<Duration>,
+    hardware: Option<Duration>,
+}
+
+impl Timestamps {
+    /// Returns the time the data was received by the kernel.
+    pub fn software(&self) -> Option<Duration> {
+        self.software
+    }
+
+    /// Returns the time the data was received by the network adapter.
+    pub fn hardware(&self) -> Option<Duration> {
+        self.hardware
+    }
+
+    /// Fills in the timestamps from a control message, returning `false` if
+    /// it is not a timestamping message.
+    ///
+    /// # Safety
+    ///
+    /// The control message must be valid, as set by the kernel.
+    pub(crate) unsafe fn parse_cmsg(&mut self, cmsg: *const libc::cmsghdr) -> bool {
+        if ((*cmsg).cmsg_level, (*cmsg).cmsg_type) != (libc::SOL_SOCKET, SCM_TIMESTAMPING) {
+            return false;
+        }
+        // The kernel's `struct scm_timestamping`: the software timestamp,
+        // a deprecated one, and the raw hardware timestamp
+        let ts = (libc::CMSG_DATA(cmsg) as *const [libc::timespec; 3]).read_unaligned();
+        self.software = to_duration(&ts[0]);
+        self.hardware = to_duration(&ts[2]);
+        true
+    }
+
+    /// Fills in the timestamps from the control messages of a `msghdr`
+    /// filled by `recvmsg`.
+    ///
+    /// # Safety
+    ///
+    /// The control buffer of `msghdr` must be valid, with the length set
+    /// by the kernel.
+    pub(crate) unsafe fn parse_control(&mut self, msghdr: &libc::msghdr) {
+        let mut cmsg = libc::CMSG_FIRSTHDR(msghdr);
+        while !cmsg.is_null() {
+            self.parse_cmsg(cmsg);
+            cmsg = libc::CMSG_NXTHDR(msghdr, cmsg);
+        }
+    }
+}
+
+// Converts a timestamp, which is zero if it was not generated.
+fn to_duration(ts: &libc::timespec) -> Option<Duration> {
+    if ts.tv_sec == 0 && ts.tv_nsec == 0 {
+        None
+    } else {
+        Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+    }
+}
@@ -0,0 +1,234 @@
+use crate::buf::{BoundedBuf, BoundedBufMut};
+use crate::fs::OpenOptions;
+use crate::io::Socket;
+use std::ffi::CStr;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+// Requests of the TUN driver, `_IOW('T', nr, int)`.
+const TUNSETIFF: libc::c_ulong = 0x4004_54ca;
+const TUNSETPERSIST: libc::c_ulong = 0x4004_54cb;
+const TUNSETOWNER: libc::c_ulong = 0x4004_54cc;
+
+// Flags of TUNSETIFF.
+const IFF_TUN: libc::c_short = 0x0001;
+const IFF_TAP: libc::c_short = 0x0002;
+const IFF_NO_PI: libc::c_short = 0x1000;
+
+// The kernel's `struct ifreq`, with the members of the union used here.
+#[repr(C)]
+struct IfReq {
+    name: [libc::c_char; libc::IFNAMSIZ],
+    data: IfReqData,
+}
+
+#[repr(C)]
+union IfReqData {
+    flags: libc::c_short,
+    mtu: libc::c_int,
+    // The size of the union, set by its largest member `struct ifmap`
+    _pad: [u64; 3],
+}
+
+impl IfReq {
+    fn new(name: &str) -> io::Result<IfReq> {
+        if name.len() >= libc::IFNAMSIZ || name.contains('\0') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid interface name",
+            ));
+        }
+        let mut req = IfReq {
+            name: [0; libc::IFNAMSIZ],
+            data: IfReqData { _pad: [0; 3] },
+        };
+        for (dst, src) in req.name.iter_mut().zip(name.bytes()) {
+            *dst = src as libc::c_char;
+        }
+        Ok(req)
+    }
+
+    fn name(&self) -> String {
+        // Safety: the name is NUL terminated, by `new` or by the kernel
+        let name = unsafe { CStr::from_ptr(self.name.as_ptr()) };
+        name.to_string_lossy().into_owned()
+    }
+}
+
+/// A TUN or TAP virtual network interface.
+///
+/// A TUN interface exchanges IP packets with the application, and a TAP
+/// interface Ethernet frames. Each read returns one packet or frame sent
+/// to the interface by the kernel, and each write passes one to the kernel
+/// as if received by the interface. The packets are exchanged without the
+/// protocol information header.
+///
+/// Creating an interface requires the `CAP_NET_ADMIN` capability, as does
+/// attaching to an existing persistent interface, unless its owner has been
+/// set with [`set_owner`]. The interface is removed once it is closed,
+/// unless it has been made persistent with [`set_persist`].
+///
+/// [`set_owner`]: TunTap::set_owner
+/// [`set_persist`]: TunTap::set_persist
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::net::TunTap;
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         let tun = TunTap::tun("tun%d").await?;
+///         tun.set_mtu(1400)?;
+///         tun.set_up(true)?;
+///         println!("created {}", tun.name());
+///
+///         let mut buf = vec![0; 1500];
+///         loop {
+///             let (res, b) = tun.read(buf).await;
+///             let n = res?;
+///             println!("packet of {} bytes", n);
+///             buf = b;
+///         }
+///     })
+/// }
+/// ```
+pub struct TunTap {
+    inner: Socket,
+    name: String,
+}
+
+impl TunTap {
+    /// Creates a TUN interface, or attaches to the existing interface of
+    /// this name.
+    ///
+    /// The name may contain a `%d`, which the kernel replaces with the
+    /// first number giving a name not in use. An empty name stands for
+    /// `tun%d`. The name chosen is returned by [`name`].
+    ///
+    /// [`name`]: TunTap::name
+    pub async fn tun(name: &str) -> io::Result<TunTap> {
+        TunTap::open(name, IFF_TUN).await
+    }
+
+    /// Creates a TAP interface, or attaches to the existing interface of
+    /// this name.
+    ///
+    /// The name is chosen as for [`tun`], with `tap%d` standing for an
+    /// empty name.
+    ///
+    /// [`tun`]: TunTap::tun
+    pub async fn tap(name: &str) -> io::Result<TunTap> {
+        TunTap::open(name, IFF_TAP).await
+    }
+
+    async fn open(name: &str, flags: libc::c_short) -> io::Result<TunTap> {
+        let mut req = IfReq::new(name)?;
+        req.data.flags = flags | IFF_NO_PI;
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/net/tun")
+            .await?;
+        let inner = Socket::from_shared_fd(file.into_shared_fd());
+        syscall!(ioctl(inner.as_raw_fd(), TUNSETIFF as _, &mut req))?;
+
+        Ok(TunTap {
+            inner,
+            name: req.name(),
+        })
+    }
+
+    /// Returns the name of the interface.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Reads a packet sent to the interface into the buffer, returning the
+    /// original buffer and the size of the packet.
+    ///
+    /// The read waits for a packet. A packet larger than the buffer is
+    /// truncated.
+    pub async fn read<T: BoundedBufMut>(&self, buf: T) -> crate::BufResult<usize, T> {
+        self.inner.read(buf).await
+    }
+
+    /// Passes the packet in the buffer to the kernel, as if received by the
+    /// interface, returning the original buffer and the size written.
+    pub async fn write<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<usize, T> {
+        self.inner.write(buf).await
+    }
+
+    /// Sets whether the interface persists once it is closed.
+    pub fn set_persist(&self, persist: bool) -> io::Result<()> {
+        syscall!(ioctl(
+            self.inner.as_raw_fd(),
+            TUNSETPERSIST as _,
+            libc::c_ulong::from(persist),
+        ))?;
+        Ok(())
+    }
+
+    /// Sets the user allowed to attach to the interface without the
+    /// `CAP_NET_ADMIN` capability.
+    pub fn set_owner(&self, uid: u32) -> io::Result<()> {
+        syscall!(ioctl(
+            self.inner.as_raw_fd(),
+            TUNSETOWNER as _,
+            libc::c_ulong::from(uid),
+        ))?;
+        Ok(())
+    }
+
+    /// Sets the MTU of the interface.
+    pub fn set_mtu(&self, mtu: u32) -> io::Result<()> {
+        let mut req = IfReq::new(&self.name)?;
+        req.data.mtu = mtu as libc::c_int;
+        if_ioctl(libc::SIOCSIFMTU, &mut req)
+    }
+
+    /// Brings the interface up or down.
+    pub fn set_up(&self, up: bool) -> io::Result<()> {
+        let mut req = IfReq::new(&self.name)?;
+        if_ioctl(libc::SIOCGIFFLAGS, &mut req)?;
+        // Safety: the kernel has set the flags
+        let flags = unsafe { req.data.flags };
+        let up_flag = libc::IFF_UP as libc::c_short;
+        req.data.flags = if up {
+            flags | up_flag
+        } else {
+            flags & !up_flag
+        };
+        if_ioctl(libc::SIOCSIFFLAGS, &mut req)
+    }
+
+    /// Closes the interface.
+    ///
+    /// The method completes once the close operation has completed,
+    /// guaranteeing that resources associated with the interface have been
+    /// released.
+    pub async fn close(self) -> io::Result<()> {
+        self.inner.fd.close().await;
+        Ok(())
+    }
+}
+
+// Performs a request on the interface of `req` through a socket, as the
+// settings of an interface are not reachable through the TUN device.
+fn if_ioctl(request: libc::c_ulong, req: &mut IfReq) -> io::Result<()> {
+    let fd = syscall!(socket(
+        libc::AF_INET,
+        libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+        0
+    ))?;
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+    syscall!(ioctl(socket.as_raw_fd(), request as _, req as *mut IfReq))?;
+    Ok(())
+}
+
+impl AsRawFd for TunTap {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
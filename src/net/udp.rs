@@ -1,3 +1,4 @@
+use super::{RecvMeta, SendMeta, Timestamping, ZcNotification};
 use crate::{
     buf::fixed::FixedBuf,
     buf::{BoundedBuf, BoundedBufMut},
@@ -6,7 +7,7 @@ use crate::{
 use socket2::SockAddr;
 use std::{
     io,
-    net::SocketAddr,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
     os::unix::prelude::{AsRawFd, FromRawFd, RawFd},
 };
 
@@ -92,8 +93,34 @@ pub struct UdpSocket {
 
 impl UdpSocket {
     /// Creates a new UDP socket and attempt to bind it to the addr provided.
+    ///
+    /// If the address is an IPv6 address, the socket takes the system
+    /// default of the `IPV6_V6ONLY` option, set by the `net.ipv6.bindv6only`
+    /// sysctl. With the usual default, the socket is dual-stack: bound to
+    /// the unspecified address `[::]`, it also receives IPv4 traffic, with
+    /// the IPv4 addresses mapped to IPv6 (`::ffff:a.b.c.d`). Use
+    /// [`bind_with_only_v6`] to choose.
+    ///
+    /// [`bind_with_only_v6`]: UdpSocket::bind_with_only_v6
     pub async fn bind(socket_addr: SocketAddr) -> io::Result<UdpSocket> {
-        let socket = Socket::bind(socket_addr, libc::SOCK_DGRAM)?;
+        let socket = Socket::open_bound(socket_addr, libc::SOCK_DGRAM, None).await?;
+        Ok(UdpSocket { inner: socket })
+    }
+
+    /// Creates a new UDP socket like [`bind`], setting the `IPV6_V6ONLY`
+    /// option of an IPv6 socket to `only_v6` before it is bound.
+    ///
+    /// With `only_v6` set, the socket only receives IPv6 traffic, and
+    /// another socket can bind the same port for IPv4. Otherwise it is
+    /// dual-stack, whatever the system default is. The option does not
+    /// apply to IPv4 addresses, and is ignored for them.
+    ///
+    /// [`bind`]: UdpSocket::bind
+    pub async fn bind_with_only_v6(
+        socket_addr: SocketAddr,
+        only_v6: bool,
+    ) -> io::Result<UdpSocket> {
+        let socket = Socket::open_bound(socket_addr, libc::SOCK_DGRAM, Some(only_v6)).await?;
         Ok(UdpSocket { inner: socket })
     }
 
@@ -136,6 +163,10 @@ impl UdpSocket {
     /// configure a socket before it's handed off, such as setting options like
     /// `reuse_address` or binding to multiple addresses.
     ///
+    /// The socket is switched to blocking mode if it is non-blocking, e.g.
+    /// if it comes from a Tokio socket: the operations of this crate wait for
+    /// the socket to be ready in the kernel.
+    ///
     /// # Example
     ///
     /// ```
@@ -179,6 +210,17 @@ impl UdpSocket {
         Self { inner }
     }
 
+    /// Converts the socket into a `std::net::UdpSocket`.
+    ///
+    /// This allows the socket to be used outside the runtime, e.g. handed
+    /// off to another process at shutdown. The returned socket is in
+    /// blocking mode. If operations on the socket are still in flight, they
+    /// keep the socket open until they complete, and a duplicate of the file
+    /// descriptor is returned.
+    pub fn into_std(self) -> io::Result<std::net::UdpSocket> {
+        self.inner.into_std()
+    }
+
     pub(crate) fn from_socket(inner: Socket) -> Self {
         Self { inner }
     }
@@ -220,6 +262,21 @@ impl UdpSocket {
         self.inner.send_zc(buf).await
     }
 
+    /// Like [`send_zc`], but returns as soon as the send result is reported,
+    /// along with a [`ZcNotification`] resolving to the buffer once the
+    /// kernel has released it.
+    ///
+    /// This allows submitting more sends before the buffer of this one can
+    /// be reused.
+    ///
+    /// [`send_zc`]: UdpSocket::send_zc
+    pub async fn send_zc_notified<T: BoundedBuf>(
+        &self,
+        buf: T,
+    ) -> (io::Result<usize>, ZcNotification<T>) {
+        super::send_zc_notified(&self.inner.fd, buf).await
+    }
+
     /// Receives a single datagram message on the socket. On success, returns
     /// the number of bytes read and the origin.
     pub async fn recv_from<T: BoundedBufMut>(
@@ -229,6 +286,214 @@ impl UdpSocket {
         self.inner.recv_from(buf).await
     }
 
+    /// Receives a single datagram message on the socket, with its metadata.
+    ///
+    /// On success, returns the number of bytes read and the [`RecvMeta`] of
+    /// the datagram: the address of the sender, and the destination address
+    /// and the ECN codepoint of the datagram if their reception has been
+    /// enabled with [`set_recv_pktinfo`] and [`set_recv_ecn`].
+    ///
+    /// [`set_recv_pktinfo`]: UdpSocket::set_recv_pktinfo
+    /// [`set_recv_ecn`]: UdpSocket::set_recv_ecn
+    pub async fn recv_msg<T: BoundedBufMut>(
+        &self,
+        buf: T,
+    ) -> crate::BufResult<(usize, RecvMeta), T> {
+        self.inner.recv_msg(buf).await
+    }
+
+    /// Sends data on the socket to the destination of `meta`, with the
+    /// source address, interface and ECN codepoint set in `meta`. On
+    /// success, returns the number of bytes written.
+    ///
+    /// See [`SendMeta`] for an example of replying from the address a
+    /// datagram was received on.
+    pub async fn send_msg<T: BoundedBuf>(
+        &self,
+        buf: T,
+        meta: &SendMeta,
+    ) -> crate::BufResult<usize, T> {
+        self.inner.send_msg(buf, meta).await
+    }
+
+    /// Sets whether the destination address and the interface of the
+    /// datagrams are received, with the `IP_PKTINFO` option on an IPv4
+    /// socket, or the `IPV6_RECVPKTINFO` option on an IPv6 socket.
+    ///
+    /// They are returned by [`recv_msg`] in the [`RecvMeta`] of the datagrams.
+    /// A server bound to the unspecified address needs them to reply from
+    /// the address the request was sent to.
+    ///
+    /// [`recv_msg`]: UdpSocket::recv_msg
+    pub fn set_recv_pktinfo(&self, enable: bool) -> io::Result<()> {
+        match self.local_addr()? {
+            SocketAddr::V4(_) => {
+                self.inner
+                    .set_int_option(libc::IPPROTO_IP, libc::IP_PKTINFO, enable as _)
+            }
+            SocketAddr::V6(_) => {
+                self.inner
+                    .set_int_option(libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, enable as _)
+            }
+        }
+    }
+
+    /// Sets the timestamps generated for the datagrams received, with the
+    /// `SO_TIMESTAMPING` option.
+    ///
+    /// They are returned by [`recv_msg`] in the [`RecvMeta`] of the datagrams.
+    ///
+    /// [`recv_msg`]: UdpSocket::recv_msg
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::net::{Timestamping, UdpSocket};
+    ///
+    /// tokio_uring::start(async {
+    ///     let socket = UdpSocket::bind("0.0.0.0:319".parse().unwrap()).await.unwrap();
+    ///     socket.set_timestamping(Timestamping::RX_SOFTWARE | Timestamping::RX_HARDWARE).unwrap();
+    ///
+    ///     let (res, _) = socket.recv_msg(vec![0; 1500]).await;
+    ///     let (_, meta) = res.unwrap();
+    ///     println!("received at {:?}", meta.timestamps().hardware());
+    /// });
+    /// ```
+    pub fn set_timestamping(&self, flags: Timestamping) -> io::Result<()> {
+        self.inner.set_timestamping(flags)
+    }
+
+    /// Sets whether the traffic class of the datagrams is received, with the
+    /// `IP_RECVTOS` option on an IPv4 socket, or the `IPV6_RECVTCLASS`
+    /// option on an IPv6 socket.
+    ///
+    /// The ECN codepoint of the traffic class is returned by [`recv_msg`] in
+    /// the [`RecvMeta`] of the datagrams. On a dual-stack socket, the option
+    /// is set for both IP versions.
+    ///
+    /// [`recv_msg`]: UdpSocket::recv_msg
+    pub fn set_recv_ecn(&self, enable: bool) -> io::Result<()> {
+        if let SocketAddr::V6(_) = self.local_addr()? {
+            self.inner
+                .set_int_option(libc::IPPROTO_IPV6, libc::IPV6_RECVTCLASS, enable as _)?;
+        }
+        self.inner
+            .set_int_option(libc::IPPROTO_IP, libc::IP_RECVTOS, enable as _)
+    }
+
+    /// Joins the IPv4 multicast group `multiaddr`, receiving the datagrams
+    /// sent to it by any source, with the `IP_ADD_MEMBERSHIP` option.
+    ///
+    /// `interface` is the address of the local interface to join the group
+    /// on; with [`Ipv4Addr::UNSPECIFIED`], the system picks the interface.
+    pub fn join_multicast_v4(&self, multiaddr: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        socket2::SockRef::from(self).join_multicast_v4(&multiaddr, &interface)
+    }
+
+    /// Leaves the IPv4 multicast group `multiaddr` joined with
+    /// [`join_multicast_v4`], with the `IP_DROP_MEMBERSHIP` option.
+    ///
+    /// [`join_multicast_v4`]: UdpSocket::join_multicast_v4
+    pub fn leave_multicast_v4(&self, multiaddr: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        socket2::SockRef::from(self).leave_multicast_v4(&multiaddr, &interface)
+    }
+
+    /// Joins the IPv6 multicast group `multiaddr` on the interface of index
+    /// `interface`, or on one picked by the system with 0, with the
+    /// `IPV6_ADD_MEMBERSHIP` option.
+    pub fn join_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
+        socket2::SockRef::from(self).join_multicast_v6(multiaddr, interface)
+    }
+
+    /// Leaves the IPv6 multicast group `multiaddr` joined with
+    /// [`join_multicast_v6`], with the `IPV6_DROP_MEMBERSHIP` option.
+    ///
+    /// [`join_multicast_v6`]: UdpSocket::join_multicast_v6
+    pub fn leave_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
+        socket2::SockRef::from(self).leave_multicast_v6(multiaddr, interface)
+    }
+
+    /// Joins the source-specific IPv4 multicast group `group`, receiving
+    /// only the datagrams sent to it by `source`, with the
+    /// `IP_ADD_SOURCE_MEMBERSHIP` option.
+    ///
+    /// The group is usually in the `232.0.0.0/8` range reserved for
+    /// source-specific multicast. Further sources of the same group are
+    /// added by joining again. `interface` is the address of the local
+    /// interface to join the group on; with [`Ipv4Addr::UNSPECIFIED`], the
+    /// system picks the interface.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::Ipv4Addr;
+    /// use tokio_uring::net::UdpSocket;
+    ///
+    /// tokio_uring::start(async {
+    ///     let socket = UdpSocket::bind("0.0.0.0:5000".parse().unwrap()).await.unwrap();
+    ///     socket
+    ///         .join_ssm_v4(
+    ///             Ipv4Addr::new(232, 1, 1, 1),
+    ///             Ipv4Addr::new(10, 0, 0, 7),
+    ///             Ipv4Addr::UNSPECIFIED,
+    ///         )
+    ///         .unwrap();
+    ///
+    ///     let (res, buf) = socket.recv_from(vec![0; 1500]).await;
+    ///     let (n, source) = res.unwrap();
+    ///     println!("{} bytes from {}", n, source);
+    /// });
+    /// ```
+    pub fn join_ssm_v4(
+        &self,
+        group: Ipv4Addr,
+        source: Ipv4Addr,
+        interface: Ipv4Addr,
+    ) -> io::Result<()> {
+        socket2::SockRef::from(self).join_ssm_v4(&source, &group, &interface)
+    }
+
+    /// Stops receiving the datagrams sent by `source` to the group `group`
+    /// joined with [`join_ssm_v4`], with the `IP_DROP_SOURCE_MEMBERSHIP`
+    /// option. The group is left once its last source is dropped.
+    ///
+    /// [`join_ssm_v4`]: UdpSocket::join_ssm_v4
+    pub fn leave_ssm_v4(
+        &self,
+        group: Ipv4Addr,
+        source: Ipv4Addr,
+        interface: Ipv4Addr,
+    ) -> io::Result<()> {
+        socket2::SockRef::from(self).leave_ssm_v4(&source, &group, &interface)
+    }
+
+    /// Sets the local interface the IPv4 multicast datagrams are sent
+    /// from, by its address, with the `IP_MULTICAST_IF` option.
+    pub fn set_multicast_if_v4(&self, interface: Ipv4Addr) -> io::Result<()> {
+        socket2::SockRef::from(self).set_multicast_if_v4(&interface)
+    }
+
+    /// Sets whether the IPv4 multicast datagrams sent are looped back to the
+    /// sockets of the local host which joined the group, with the
+    /// `IP_MULTICAST_LOOP` option. It is enabled by default.
+    pub fn set_multicast_loop_v4(&self, on: bool) -> io::Result<()> {
+        socket2::SockRef::from(self).set_multicast_loop_v4(on)
+    }
+
+    /// Sets the time-to-live of the IPv4 multicast datagrams sent, with the
+    /// `IP_MULTICAST_TTL` option. The default of 1 keeps them in the local
+    /// network.
+    pub fn set_multicast_ttl_v4(&self, ttl: u32) -> io::Result<()> {
+        socket2::SockRef::from(self).set_multicast_ttl_v4(ttl)
+    }
+
+    /// Sets whether the IPv6 multicast datagrams sent are looped back to the
+    /// sockets of the local host which joined the group, with the
+    /// `IPV6_MULTICAST_LOOP` option. It is enabled by default.
+    pub fn set_multicast_loop_v6(&self, on: bool) -> io::Result<()> {
+        socket2::SockRef::from(self).set_multicast_loop_v6(on)
+    }
+
     /// Read a packet of data from the socket into the buffer, returning the original buffer and
     /// quantity of data read.
     pub async fn read<T: BoundedBufMut>(&self, buf: T) -> crate::BufResult<usize, T> {
@@ -284,6 +549,20 @@ impl UdpSocket {
     pub fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
         self.inner.shutdown(how)
     }
+
+    /// Limits the number of operations on this socket in flight at a time, or
+    /// removes the limit with `None`.
+    ///
+    /// See [`File::set_max_in_flight`] for the details.
+    ///
+    /// [`File::set_max_in_flight`]: crate::fs::File::set_max_in_flight
+    ///
+    /// # Panics
+    ///
+    /// Panics if the limit is 0.
+    pub fn set_max_in_flight(&self, max: Option<usize>) {
+        self.inner.set_max_in_flight(max)
+    }
 }
 
 impl FromRawFd for UdpSocket {
@@ -297,3 +576,25 @@ impl AsRawFd for UdpSocket {
         self.inner.as_raw_fd()
     }
 }
+
+/// Adopts a socket configured with `socket2`, like [`UdpSocket::from_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl From<socket2::Socket> for UdpSocket {
+    fn from(socket: socket2::Socket) -> Self {
+        UdpSocket::from_socket(Socket::from_std(socket))
+    }
+}
+
+/// Converts the socket into a `socket2::Socket`, like [`UdpSocket::into_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl std::convert::TryFrom<UdpSocket> for socket2::Socket {
+    type Error = io::Error;
+
+    fn try_from(socket: UdpSocket) -> io::Result<Self> {
+        socket.inner.into_std()
+    }
+}
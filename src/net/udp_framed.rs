@@ -0,0 +1,173 @@
+use super::UdpSocket;
+use crate::io::Socket;
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio_util::codec::{Decoder, Encoder};
+
+// Large enough for any UDP datagram over IPv4 or IPv6 without jumbograms
+const RECV_CAPACITY: usize = 64 * 1024;
+
+type RecvFuture = Pin<Box<dyn Future<Output = crate::BufResult<(usize, SocketAddr), BytesMut>>>>;
+type SendFuture = Pin<Box<dyn Future<Output = crate::BufResult<usize, Bytes>>>>;
+
+/// A [`Stream`] and [`Sink`] of frames sent as UDP datagrams, encoded and
+/// decoded with a [`tokio-util`] codec.
+///
+/// Each datagram received is decoded into the frames it contains, which
+/// are yielded with the address of the sender. Each frame sent is encoded
+/// into a datagram of its own, sent to the address paired with the frame.
+/// The `UdpFramed` manages the buffers passed to the kernel, so the
+/// protocol implementation only deals with frames and addresses.
+///
+/// Requires the `codec` feature.
+///
+/// [`Stream`]: futures_core::Stream
+/// [`Sink`]: futures_sink::Sink
+/// [`tokio-util`]: https://docs.rs/tokio-util
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures::{SinkExt, StreamExt};
+/// use tokio_uring::net::{UdpFramed, UdpSocket};
+/// use tokio_util::codec::BytesCodec;
+///
+/// tokio_uring::start(async {
+///     let socket = UdpSocket::bind("127.0.0.1:5353".parse().unwrap()).await.unwrap();
+///     let mut framed = UdpFramed::new(socket, BytesCodec::new());
+///
+///     // Echo the datagrams back to their senders
+///     while let Some(Ok((frame, addr))) = framed.next().await {
+///         framed.send((frame.freeze(), addr)).await.unwrap();
+///     }
+/// });
+/// ```
+pub struct UdpFramed<C> {
+    socket: Socket,
+    codec: C,
+    // The buffer of the datagram being decoded, with its sender, or of the
+    // next datagram if the buffer is idle
+    rd: Option<BytesMut>,
+    rd_addr: Option<SocketAddr>,
+    recv: Option<RecvFuture>,
+    wr: BytesMut,
+    send: Option<SendFuture>,
+}
+
+impl<C> UdpFramed<C> {
+    /// Creates a `UdpFramed` sending and receiving the frames of `codec` on
+    /// `socket`.
+    pub fn new(socket: UdpSocket, codec: C) -> UdpFramed<C> {
+        UdpFramed {
+            socket: socket.inner,
+            codec,
+            rd: Some(BytesMut::with_capacity(RECV_CAPACITY)),
+            rd_addr: None,
+            recv: None,
+            wr: BytesMut::new(),
+            send: None,
+        }
+    }
+
+    /// Returns a reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Consumes the `UdpFramed`, returning the socket.
+    ///
+    /// Frames decoded from the last datagram received and not yet returned
+    /// are lost, as are the operations in flight.
+    pub fn into_inner(self) -> UdpSocket {
+        UdpSocket { inner: self.socket }
+    }
+
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(send) = &mut self.send {
+            let (res, _) = ready!(send.as_mut().poll(cx));
+            self.send = None;
+            res?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<C: Decoder + Unpin> Stream for UdpFramed<C>
+where
+    C::Error: From<io::Error>,
+{
+    type Item = Result<(C::Item, SocketAddr), C::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            // Yield the frames left in the last datagram
+            if let (Some(rd), Some(addr)) = (&mut this.rd, this.rd_addr) {
+                if let Some(frame) = this.codec.decode_eof(rd)? {
+                    return Poll::Ready(Some(Ok((frame, addr))));
+                }
+                this.rd_addr = None;
+            }
+
+            let recv = match &mut this.recv {
+                Some(recv) => recv,
+                None => {
+                    let mut buf = this.rd.take().expect("no buffer to receive into");
+                    buf.clear();
+                    buf.reserve(RECV_CAPACITY);
+                    let socket = this.socket.clone();
+                    this.recv
+                        .insert(Box::pin(async move { socket.recv_from(buf).await }))
+                }
+            };
+            let (res, buf) = ready!(recv.as_mut().poll(cx));
+            this.recv = None;
+            this.rd = Some(buf);
+            let (_, addr) = res?;
+            this.rd_addr = Some(addr);
+        }
+    }
+}
+
+impl<I, C: Encoder<I> + Unpin> Sink<(I, SocketAddr)> for UdpFramed<C>
+where
+    C::Error: From<io::Error>,
+{
+    type Error = C::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // One datagram is sent at a time
+        self.get_mut().poll_send(cx).map_err(Into::into)
+    }
+
+    fn start_send(self: Pin<&mut Self>, (frame, addr): (I, SocketAddr)) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        assert!(this.send.is_none(), "start_send called without poll_ready");
+
+        this.codec.encode(frame, &mut this.wr)?;
+        let buf = this.wr.split().freeze();
+        let socket = this.socket.clone();
+        this.send = Some(Box::pin(async move { socket.send_to(buf, addr).await }));
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send(cx).map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
@@ -0,0 +1,270 @@
+use super::Timestamps;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+// Space for a packet info message and a traffic class message of either
+// IP version, and a timestamping message, as u64 to align the headers.
+pub(crate) const CONTROL_LEN: usize = 24;
+
+/// The metadata of a datagram received with [`UdpSocket::recv_msg`].
+///
+/// [`UdpSocket::recv_msg`]: super::UdpSocket::recv_msg
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecvMeta {
+    addr: SocketAddr,
+    dst_ip: Option<IpAddr>,
+    ifindex: Option<u32>,
+    ecn: Option<u8>,
+    timestamps: Timestamps,
+}
+
+impl RecvMeta {
+    /// Returns the address of the sender.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the destination address of the datagram.
+    ///
+    /// This is the local address the datagram was sent to, which tells
+    /// apart the addresses of a socket bound to the unspecified address.
+    /// It is only available if reception of packet info has been enabled
+    /// with [`UdpSocket::set_recv_pktinfo`]. An IPv4 destination of a
+    /// dual-stack socket is returned as an IPv4-mapped IPv6 address.
+    ///
+    /// [`UdpSocket::set_recv_pktinfo`]: super::UdpSocket::set_recv_pktinfo
+    pub fn dst_ip(&self) -> Option<IpAddr> {
+        self.dst_ip
+    }
+
+    /// Returns the index of the interface the datagram was received on.
+    ///
+    /// Like [`dst_ip`], it is only available if reception of packet info
+    /// has been enabled.
+    ///
+    /// [`dst_ip`]: RecvMeta::dst_ip
+    pub fn ifindex(&self) -> Option<u32> {
+        self.ifindex
+    }
+
+    /// Returns the ECN codepoint of the datagram, the two low bits of the
+    /// traffic class.
+    ///
+    /// It is only available if reception of the traffic class has been
+    /// enabled with [`UdpSocket::set_recv_ecn`].
+    ///
+    /// [`UdpSocket::set_recv_ecn`]: super::UdpSocket::set_recv_ecn
+    pub fn ecn(&self) -> Option<u8> {
+        self.ecn
+    }
+
+    /// Returns the timestamps of the datagram.
+    ///
+    /// They are only available if timestamping has been enabled with
+    /// [`UdpSocket::set_timestamping`].
+    ///
+    /// [`UdpSocket::set_timestamping`]: super::UdpSocket::set_timestamping
+    pub fn timestamps(&self) -> Timestamps {
+        self.timestamps
+    }
+
+    pub(crate) fn new(addr: SocketAddr) -> RecvMeta {
+        RecvMeta {
+            addr,
+            dst_ip: None,
+            ifindex: None,
+            ecn: None,
+            timestamps: Timestamps::default(),
+        }
+    }
+
+    /// Fills in the metadata from the control messages of a `msghdr`
+    /// filled by `recvmsg`.
+    ///
+    /// # Safety
+    ///
+    /// The control buffer of `msghdr` must be valid, with the length set
+    /// by the kernel.
+    pub(crate) unsafe fn parse_control(&mut self, msghdr: &libc::msghdr) {
+        let mut cmsg = libc::CMSG_FIRSTHDR(msghdr);
+        while !cmsg.is_null() {
+            let data = libc::CMSG_DATA(cmsg);
+            match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+                (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                    let info = (data as *const libc::in_pktinfo).read_unaligned();
+                    let addr = Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr));
+                    self.dst_ip = Some(addr.into());
+                    self.ifindex = Some(info.ipi_ifindex as u32);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                    let info = (data as *const libc::in6_pktinfo).read_unaligned();
+                    self.dst_ip = Some(Ipv6Addr::from(info.ipi6_addr.s6_addr).into());
+                    self.ifindex = Some(info.ipi6_ifindex);
+                }
+                (libc::IPPROTO_IP, libc::IP_TOS) => {
+                    self.ecn = Some(data.read() & 0b11);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => {
+                    let tclass = (data as *const libc::c_int).read_unaligned();
+                    self.ecn = Some(tclass as u8 & 0b11);
+                }
+                _ => {
+                    self.timestamps.parse_cmsg(cmsg);
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(msghdr, cmsg);
+        }
+    }
+}
+
+/// The destination and the options of a datagram sent with
+/// [`UdpSocket::send_msg`].
+///
+/// [`UdpSocket::send_msg`]: super::UdpSocket::send_msg
+///
+/// # Examples
+///
+/// Replying from the address a request was sent to:
+///
+/// ```no_run
+/// use tokio_uring::buf::BoundedBuf;
+/// use tokio_uring::net::{SendMeta, UdpSocket};
+///
+/// tokio_uring::start(async {
+///     let socket = UdpSocket::bind("0.0.0.0:5353".parse().unwrap()).await.unwrap();
+///     socket.set_recv_pktinfo(true).unwrap();
+///
+///     let (res, buf) = socket.recv_msg(vec![0; 1500]).await;
+///     let (n, meta) = res.unwrap();
+///     let (res, _) = socket.send_msg(buf.slice(..n), &SendMeta::reply_to(&meta)).await;
+///     res.unwrap();
+/// });
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendMeta {
+    addr: SocketAddr,
+    src_ip: Option<IpAddr>,
+    ifindex: Option<u32>,
+    ecn: Option<u8>,
+}
+
+impl SendMeta {
+    /// Creates the metadata of a datagram sent to `addr`, with the source
+    /// address and the options chosen by the kernel.
+    pub fn new(addr: SocketAddr) -> SendMeta {
+        SendMeta {
+            addr,
+            src_ip: None,
+            ifindex: None,
+            ecn: None,
+        }
+    }
+
+    /// Creates the metadata of a reply to a received datagram: sent to its
+    /// sender, from the address and through the interface it was received
+    /// on, if these are known.
+    pub fn reply_to(meta: &RecvMeta) -> SendMeta {
+        SendMeta {
+            addr: meta.addr,
+            src_ip: meta.dst_ip,
+            ifindex: meta.ifindex,
+            ecn: None,
+        }
+    }
+
+    /// Sets the source address of the datagram.
+    ///
+    /// The address must be one of the local addresses of the host, unless
+    /// the socket has the `IP_FREEBIND` or `IP_TRANSPARENT` option set.
+    pub fn src_ip(&mut self, ip: IpAddr) -> &mut Self {
+        self.src_ip = Some(ip);
+        self
+    }
+
+    /// Sets the index of the interface the datagram is sent through.
+    pub fn ifindex(&mut self, ifindex: u32) -> &mut Self {
+        self.ifindex = Some(ifindex);
+        self
+    }
+
+    /// Sets the ECN codepoint of the datagram. Only the two low bits of
+    /// `ecn` are used.
+    pub fn ecn(&mut self, ecn: u8) -> &mut Self {
+        self.ecn = Some(ecn & 0b11);
+        self
+    }
+
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Writes the control messages setting the options into `control`,
+    /// returning their length.
+    pub(crate) fn write_control(&self, control: &mut [u64; CONTROL_LEN]) -> usize {
+        let mut msghdr: libc::msghdr = unsafe { mem::zeroed() };
+        msghdr.msg_control = control.as_mut_ptr().cast();
+        msghdr.msg_controllen = mem::size_of_val(control) as _;
+        let mut len = 0;
+
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msghdr);
+            let mut push = |level, ty, data: &[u8]| {
+                (*cmsg).cmsg_level = level;
+                (*cmsg).cmsg_type = ty;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(data.len() as _) as _;
+                std::ptr::copy_nonoverlapping(data.as_ptr(), libc::CMSG_DATA(cmsg), data.len());
+                len += libc::CMSG_SPACE(data.len() as _) as usize;
+                cmsg = libc::CMSG_NXTHDR(&msghdr, cmsg);
+            };
+
+            // The messages follow the IP version of the destination
+            if self.src_ip.is_some() || self.ifindex.is_some() {
+                match self.addr {
+                    SocketAddr::V4(_) => {
+                        let mut info: libc::in_pktinfo = mem::zeroed();
+                        info.ipi_ifindex = self.ifindex.unwrap_or(0) as _;
+                        if let Some(IpAddr::V4(ip)) = self.src_ip.map(to_canonical) {
+                            info.ipi_spec_dst.s_addr = u32::from(ip).to_be();
+                        }
+                        push(libc::IPPROTO_IP, libc::IP_PKTINFO, as_bytes(&info));
+                    }
+                    SocketAddr::V6(_) => {
+                        let mut info: libc::in6_pktinfo = mem::zeroed();
+                        info.ipi6_ifindex = self.ifindex.unwrap_or(0);
+                        if let Some(ip) = self.src_ip {
+                            info.ipi6_addr.s6_addr = to_v6(ip).octets();
+                        }
+                        push(libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, as_bytes(&info));
+                    }
+                }
+            }
+            if let Some(ecn) = self.ecn {
+                // IPv4 traffic of a dual-stack socket takes the IPv4 option
+                let tclass = ecn as libc::c_int;
+                match to_canonical(self.addr.ip()) {
+                    IpAddr::V4(_) => push(libc::IPPROTO_IP, libc::IP_TOS, as_bytes(&tclass)),
+                    IpAddr::V6(_) => push(libc::IPPROTO_IPV6, libc::IPV6_TCLASS, as_bytes(&tclass)),
+                }
+            }
+        }
+        len
+    }
+}
+
+fn as_bytes<T>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+fn to_canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+fn to_v6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    }
+}
@@ -52,6 +52,32 @@ impl UnixListener {
         Ok(UnixListener { inner: socket })
     }
 
+    /// Creates a new `UnixListener` from a listener created with the standard
+    /// library, or received from elsewhere, e.g. from systemd with socket
+    /// activation.
+    ///
+    /// The listener must be listening for connections.
+    ///
+    /// The socket is switched to blocking mode if it is non-blocking, e.g.
+    /// if it comes from a Tokio socket: the operations of this crate wait for
+    /// the socket to be ready in the kernel.
+    pub fn from_std(listener: std::os::unix::net::UnixListener) -> UnixListener {
+        UnixListener {
+            inner: Socket::from_std(listener),
+        }
+    }
+
+    /// Converts the listener into a `std::os::unix::net::UnixListener`.
+    ///
+    /// This allows the socket to be used outside the runtime, e.g. handed
+    /// off to another process at shutdown. The returned socket is in
+    /// blocking mode. If operations on the socket are still in flight, they
+    /// keep the socket open until they complete, and a duplicate of the file
+    /// descriptor is returned.
+    pub fn into_std(self) -> io::Result<std::os::unix::net::UnixListener> {
+        self.inner.into_std()
+    }
+
     /// Returns the local address that this listener is bound to.
     ///
     /// # Examples
@@ -90,7 +116,31 @@ impl UnixListener {
     /// [`UnixStream`]: struct@crate::net::UnixStream
     pub async fn accept(&self) -> io::Result<UnixStream> {
         let (socket, _) = self.inner.accept().await?;
-        let stream = UnixStream { inner: socket };
+        let stream = UnixStream::from_socket(socket);
         Ok(stream)
     }
 }
+
+/// Adopts a socket configured with `socket2`, like [`UnixListener::from_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl From<socket2::Socket> for UnixListener {
+    fn from(socket: socket2::Socket) -> Self {
+        UnixListener {
+            inner: Socket::from_std(socket),
+        }
+    }
+}
+
+/// Converts the listener into a `socket2::Socket`, like [`UnixListener::into_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl std::convert::TryFrom<UnixListener> for socket2::Socket {
+    type Error = io::Error;
+
+    fn try_from(listener: UnixListener) -> io::Result<Self> {
+        listener.inner.into_std()
+    }
+}
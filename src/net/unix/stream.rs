@@ -1,7 +1,9 @@
 use crate::{
     buf::fixed::FixedBuf,
+    buf::provided::{BufRing, ProvidedBuf, SendRing},
     buf::{BoundedBuf, BoundedBufMut, IoBuf},
     io::{SharedFd, Socket},
+    net::{RecvChunks, Timestamping, Timestamps},
 };
 use socket2::SockAddr;
 use std::{
@@ -9,6 +11,12 @@ use std::{
     os::unix::prelude::{AsRawFd, FromRawFd, RawFd},
     path::Path,
 };
+#[cfg(feature = "tokio-io")]
+use {
+    crate::io::Staging,
+    std::pin::Pin,
+    std::task::{Context, Poll},
+};
 
 /// A Unix stream between two local sockets on a Unix OS.
 ///
@@ -40,6 +48,9 @@ use std::{
 /// [`listener`]: crate::net::UnixListener
 pub struct UnixStream {
     pub(super) inner: Socket,
+
+    #[cfg(feature = "tokio-io")]
+    staging: Staging,
 }
 
 impl UnixStream {
@@ -47,9 +58,9 @@ impl UnixStream {
     /// `UnixListener` or equivalent listening on the corresponding Unix domain socket
     /// to successfully connect and return a `UnixStream`.
     pub async fn connect<P: AsRef<Path>>(path: P) -> io::Result<UnixStream> {
-        let socket = Socket::new_unix(libc::SOCK_STREAM)?;
+        let socket = Socket::open_in_domain(libc::AF_UNIX, libc::SOCK_STREAM).await?;
         socket.connect(SockAddr::unix(path)?).await?;
-        let unix_stream = UnixStream { inner: socket };
+        let unix_stream = UnixStream::from_socket(socket);
         Ok(unix_stream)
     }
 
@@ -63,13 +74,37 @@ impl UnixStream {
     /// This can be used in conjunction with socket2's `Socket` interface to
     /// configure a socket before it's handed off, such as setting options like
     /// `reuse_address` or binding to multiple addresses.
+    ///
+    /// The socket is switched to blocking mode if it is non-blocking, e.g.
+    /// if it comes from a Tokio socket: the operations of this crate wait for
+    /// the socket to be ready in the kernel.
     pub fn from_std(socket: std::os::unix::net::UnixStream) -> UnixStream {
         let inner = Socket::from_std(socket);
-        Self { inner }
+        Self::from_socket(inner)
+    }
+
+    /// Converts the socket into a `std::os::unix::net::UnixStream`.
+    ///
+    /// This allows the socket to be used outside the runtime, e.g. handed
+    /// off to another process at shutdown. The returned socket is in
+    /// blocking mode. If operations on the socket are still in flight, they
+    /// keep the socket open until they complete, and a duplicate of the file
+    /// descriptor is returned.
+    ///
+    /// Data buffered by the [`AsyncRead`] implementation of the stream is
+    /// lost.
+    ///
+    /// [`AsyncRead`]: tokio::io::AsyncRead
+    pub fn into_std(self) -> io::Result<std::os::unix::net::UnixStream> {
+        self.inner.into_std()
     }
 
     pub(crate) fn from_socket(inner: Socket) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            #[cfg(feature = "tokio-io")]
+            staging: Staging::new(),
+        }
     }
 
     /// Read some data from the stream into the buffer, returning the original buffer and
@@ -78,6 +113,18 @@ impl UnixStream {
         self.inner.read(buf).await
     }
 
+    /// Like [`read`], but also returns the timestamps of the data received,
+    /// if timestamping has been enabled with [`set_timestamping`].
+    ///
+    /// [`read`]: Self::read
+    /// [`set_timestamping`]: Self::set_timestamping
+    pub async fn read_timestamped<T: BoundedBufMut>(
+        &self,
+        buf: T,
+    ) -> crate::BufResult<(usize, Timestamps), T> {
+        self.inner.recv_timestamped(buf).await
+    }
+
     /// Like [`read`], but using a pre-mapped buffer
     /// registered with [`FixedBufRegistry`].
     ///
@@ -96,6 +143,38 @@ impl UnixStream {
         self.inner.read_fixed(buf).await
     }
 
+    /// Receives the data of the stream as a stream of chunks, into the
+    /// buffers of `ring`.
+    ///
+    /// A single multishot receive is kept in flight, and it is submitted
+    /// again when the ring runs out of buffers and some are given back. See
+    /// [`RecvChunks`] for the details.
+    pub fn recv_multi(&self, ring: &BufRing) -> RecvChunks {
+        RecvChunks::new(self.inner.fd.clone(), ring)
+    }
+
+    /// Receives the data available on the stream into one or more buffers
+    /// of `ring`, with a single operation.
+    ///
+    /// The buffers are returned in the order of the data, and none are
+    /// returned at the end of the stream. See [`TcpStream::recv_bundle`]
+    /// for the details.
+    ///
+    /// [`TcpStream::recv_bundle`]: crate::net::TcpStream::recv_bundle
+    pub async fn recv_bundle(&self, ring: &BufRing) -> io::Result<Vec<ProvidedBuf>> {
+        self.inner.recv_bundle(ring).await
+    }
+
+    /// Sends the buffers queued in `ring`, with a single operation,
+    /// returning the number of bytes sent.
+    ///
+    /// See [`TcpStream::send_bundle`] for the details.
+    ///
+    /// [`TcpStream::send_bundle`]: crate::net::TcpStream::send_bundle
+    pub async fn send_bundle(&self, ring: &SendRing) -> io::Result<usize> {
+        self.inner.send_bundle(ring).await
+    }
+
     /// Write some data to the stream from the buffer, returning the original buffer and
     /// quantity of data written.
     pub async fn write<T: BoundedBuf>(&self, buf: T) -> crate::BufResult<usize, T> {
@@ -119,6 +198,20 @@ impl UnixStream {
         self.inner.write_all(buf).await
     }
 
+    /// Writes the whole of a request, then reads its response into
+    /// `response`, with the read linked to the write.
+    ///
+    /// See [`TcpStream::request`] for the details.
+    ///
+    /// [`TcpStream::request`]: crate::net::TcpStream::request
+    pub async fn request<T: BoundedBuf, U: BoundedBufMut>(
+        &self,
+        buf: T,
+        response: U,
+    ) -> crate::BufResult<usize, (T, U)> {
+        self.inner.request(buf, response).await
+    }
+
     /// Like [`write`], but using a pre-mapped buffer
     /// registered with [`FixedBufRegistry`].
     ///
@@ -192,6 +285,33 @@ impl UnixStream {
     pub fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
         self.inner.shutdown(how)
     }
+
+    /// Sets the timestamps generated for the data received, with the
+    /// `SO_TIMESTAMPING` option. They are returned by [`read_timestamped`].
+    ///
+    /// Linux accepts the option on Unix stream sockets, but does not
+    /// timestamp the data passed through them, so the timestamps returned
+    /// are always `None`. The method is there for code generic over the
+    /// stream types.
+    ///
+    /// [`read_timestamped`]: Self::read_timestamped
+    pub fn set_timestamping(&self, flags: Timestamping) -> io::Result<()> {
+        self.inner.set_timestamping(flags)
+    }
+
+    /// Limits the number of operations on this connection in flight at a time, or
+    /// removes the limit with `None`.
+    ///
+    /// See [`File::set_max_in_flight`] for the details.
+    ///
+    /// [`File::set_max_in_flight`]: crate::fs::File::set_max_in_flight
+    ///
+    /// # Panics
+    ///
+    /// Panics if the limit is 0.
+    pub fn set_max_in_flight(&self, max: Option<usize>) {
+        self.inner.set_max_in_flight(max)
+    }
 }
 
 impl FromRawFd for UnixStream {
@@ -205,3 +325,86 @@ impl AsRawFd for UnixStream {
         self.inner.as_raw_fd()
     }
 }
+
+/// Reads from the stream through an internal staging buffer.
+///
+/// The data is received into a buffer owned by the stream and then copied
+/// into the buffer provided by the caller. Prefer [`read`] when the extra
+/// copy matters.
+///
+/// Requires the `tokio-io` feature.
+///
+/// [`read`]: UnixStream::read
+#[cfg(feature = "tokio-io")]
+impl tokio::io::AsyncRead for UnixStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.staging.poll_read(&this.inner, cx, buf)
+    }
+}
+
+/// Writes to the stream through an internal staging buffer.
+///
+/// The data is copied into a buffer owned by the stream, which is then
+/// written in the background. A successful `poll_write` only means that
+/// the data has been accepted; errors writing it are reported by subsequent
+/// calls. Use `poll_flush` to wait until all accepted data has been written.
+///
+/// Note that the inherent methods `write_all` and `shutdown` take precedence
+/// over the `AsyncWriteExt` methods of the same name in method call syntax.
+///
+/// Requires the `tokio-io` feature.
+#[cfg(feature = "tokio-io")]
+impl tokio::io::AsyncWrite for UnixStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.staging.poll_write(&this.inner, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.staging.poll_flush(&this.inner, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.staging.poll_shutdown(&this.inner, cx)
+    }
+}
+
+/// Adopts a socket configured with `socket2`, like [`UnixStream::from_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl From<socket2::Socket> for UnixStream {
+    fn from(socket: socket2::Socket) -> Self {
+        UnixStream::from_socket(Socket::from_std(socket))
+    }
+}
+
+/// Converts the stream into a `socket2::Socket`, like [`UnixStream::into_std`].
+///
+/// This requires the `socket2` feature.
+#[cfg(feature = "socket2")]
+impl std::convert::TryFrom<UnixStream> for socket2::Socket {
+    type Error = io::Error;
+
+    fn try_from(stream: UnixStream) -> io::Result<Self> {
+        stream.inner.into_std()
+    }
+}
+
+#[allow(private_interfaces)]
+impl crate::io::sealed::Sealed for UnixStream {
+    fn socket(&self) -> &Socket {
+        &self.inner
+    }
+}
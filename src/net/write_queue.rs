@@ -0,0 +1,324 @@
+use crate::io::{Duplex, Socket};
+use std::cell::RefCell;
+use std::future::poll_fn;
+use std::io;
+use std::rc::Rc;
+use std::task::{Poll, Waker};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// A queue coalescing small writes to a stream into vectored writes.
+///
+/// Protocols exchanging many small messages, such as the replies of a
+/// key-value store, would submit a write for each of them. The messages
+/// passed to [`write`] and [`enqueue`] are instead collected, and written
+/// together with a single `writev`, as soon as the previous one has
+/// completed. A [`deadline`] can be set to hold a group back for more
+/// messages, and a [`watermark`] to write it as soon as it is large enough.
+///
+/// The queue can be cloned to write from multiple tasks. The messages are
+/// written in the order they are queued in, each one in full. Writing to
+/// the stream directly while messages are queued interleaves the data.
+///
+/// If a write fails, the stream is in an unknown state: the error is
+/// returned for all the messages in the group, and for all the messages
+/// queued later.
+///
+/// [`write`]: WriteQueue::write
+/// [`enqueue`]: WriteQueue::enqueue
+/// [`deadline`]: WriteQueue::deadline
+/// [`watermark`]: WriteQueue::watermark
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio_uring::net::{TcpListener, WriteQueue};
+///
+/// tokio_uring::start(async {
+///     let listener = TcpListener::bind("127.0.0.1:6379".parse().unwrap()).unwrap();
+///     let (stream, _) = listener.accept().await.unwrap();
+///
+///     let queue = WriteQueue::new(&stream);
+///     queue.deadline(Duration::from_micros(200)).watermark(16 * 1024);
+///
+///     let mut buf = vec![0; 4096];
+///     loop {
+///         let (res, b) = stream.read(buf).await;
+///         buf = b;
+///         if res.unwrap() == 0 {
+///             break;
+///         }
+///         // Reply to each pipelined command without waiting for the write
+///         queue.enqueue(b"+OK\r\n".to_vec()).unwrap();
+///     }
+///     queue.flush().await.unwrap();
+/// });
+/// ```
+#[derive(Clone)]
+pub struct WriteQueue {
+    inner: Rc<RefCell<Inner>>,
+    // Woken when the watermark is reached during the deadline
+    full: Rc<Notify>,
+}
+
+struct Inner {
+    socket: Socket,
+
+    // Messages of the group being collected, and their total length.
+    pending: Vec<Vec<u8>>,
+    pending_len: usize,
+
+    // Number of the group being collected. Groups are numbered from 1.
+    group: u64,
+
+    // Number of the last group written.
+    written: u64,
+
+    // Whether the flush task is running.
+    flushing: bool,
+
+    // Error of a failed write.
+    error: Option<io::Error>,
+
+    deadline: Duration,
+    watermark: usize,
+
+    // Tasks waiting for their group to be written.
+    waiters: Vec<Waker>,
+}
+
+impl WriteQueue {
+    /// Creates a queue writing to `stream`.
+    ///
+    /// The queue keeps the stream open until it, and all its clones, are
+    /// dropped.
+    pub fn new<T: Duplex>(stream: &T) -> WriteQueue {
+        WriteQueue {
+            inner: Rc::new(RefCell::new(Inner {
+                socket: stream.socket().clone(),
+                pending: Vec::new(),
+                pending_len: 0,
+                group: 1,
+                written: 0,
+                flushing: false,
+                error: None,
+                deadline: Duration::ZERO,
+                watermark: usize::MAX,
+                waiters: Vec::new(),
+            })),
+            full: Rc::new(Notify::new()),
+        }
+    }
+
+    /// Sets the time a group is held back for to collect more messages,
+    /// counted from the first message of the group.
+    ///
+    /// The default is zero, meaning that the messages are written as soon
+    /// as the previous write has completed, so that the size of the groups
+    /// adapts to the load. The setting is shared by all clones of the
+    /// queue.
+    pub fn deadline(&self, deadline: Duration) -> &Self {
+        self.inner.borrow_mut().deadline = deadline;
+        self
+    }
+
+    /// Sets the number of bytes at which a group is written without
+    /// waiting for the [`deadline`].
+    ///
+    /// By default, there is no watermark. The setting is shared by all
+    /// clones of the queue.
+    ///
+    /// [`deadline`]: WriteQueue::deadline
+    pub fn watermark(&self, bytes: usize) -> &Self {
+        self.inner.borrow_mut().watermark = bytes;
+        self
+    }
+
+    /// Returns the number of bytes queued and not yet being written.
+    pub fn pending(&self) -> usize {
+        self.inner.borrow().pending_len
+    }
+
+    /// Queues a message, resolving once it has been written to the stream.
+    ///
+    /// If the future is dropped before it resolves, the message is still
+    /// written.
+    pub async fn write(&self, data: Vec<u8>) -> io::Result<()> {
+        let group = self.push(data)?;
+        self.wait_written(group).await
+    }
+
+    /// Queues a message, without waiting for it to be written.
+    ///
+    /// The message is written in the background; [`flush`] waits for it.
+    /// There is no limit on the amount of data queued.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of a previous write which has failed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    ///
+    /// [`flush`]: WriteQueue::flush
+    pub fn enqueue(&self, data: Vec<u8>) -> io::Result<()> {
+        self.push(data).map(drop)
+    }
+
+    /// Writes the messages queued, without waiting for the deadline, and
+    /// resolves once all of them have been written.
+    pub async fn flush(&self) -> io::Result<()> {
+        let group = {
+            let inner = self.inner.borrow();
+            if let Some(e) = &inner.error {
+                return Err(copy_error(e));
+            }
+            if inner.pending.is_empty() {
+                // The groups being written, if any
+                inner.group - 1
+            } else {
+                self.full.notify_one();
+                inner.group
+            }
+        };
+        self.wait_written(group).await
+    }
+
+    // Queues a message, returning the number of its group.
+    fn push(&self, data: Vec<u8>) -> io::Result<u64> {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(e) = &inner.error {
+            return Err(copy_error(e));
+        }
+        if data.is_empty() {
+            return Ok(inner.group - 1);
+        }
+
+        inner.pending_len += data.len();
+        inner.pending.push(data);
+        if inner.pending_len >= inner.watermark {
+            self.full.notify_one();
+        }
+        if !inner.flushing {
+            inner.flushing = true;
+            crate::spawn(flush(self.inner.clone(), self.full.clone()));
+        }
+        Ok(inner.group)
+    }
+
+    async fn wait_written(&self, group: u64) -> io::Result<()> {
+        poll_fn(|cx| {
+            let mut inner = self.inner.borrow_mut();
+            if inner.written >= group {
+                return Poll::Ready(Ok(()));
+            }
+            if let Some(e) = &inner.error {
+                return Poll::Ready(Err(copy_error(e)));
+            }
+            inner.waiters.push(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl std::fmt::Debug for WriteQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("WriteQueue")
+            .field("pending", &inner.pending_len)
+            .field("deadline", &inner.deadline)
+            .field("watermark", &inner.watermark)
+            .finish_non_exhaustive()
+    }
+}
+
+// Writes the collected groups until there are no more messages.
+async fn flush(inner: Rc<RefCell<Inner>>, full: Rc<Notify>) {
+    loop {
+        let (deadline, watermark) = {
+            let inner = inner.borrow();
+            (inner.deadline, inner.watermark)
+        };
+        if !deadline.is_zero() && inner.borrow().pending_len < watermark {
+            let _ = tokio::time::timeout_at(Instant::now() + deadline, full.notified()).await;
+        }
+
+        let (socket, messages, group) = {
+            let mut inner = inner.borrow_mut();
+            if inner.pending.is_empty() {
+                inner.flushing = false;
+                return;
+            }
+
+            let messages = std::mem::take(&mut inner.pending);
+            inner.pending_len = 0;
+            let group = inner.group;
+            inner.group += 1;
+            (inner.socket.clone(), messages, group)
+        };
+
+        let res = write_all(&socket, messages).await;
+
+        let mut inner = inner.borrow_mut();
+        let failed = res.is_err();
+        match res {
+            Ok(()) => inner.written = group,
+            Err(e) => inner.error = Some(e),
+        }
+        for waker in inner.waiters.drain(..) {
+            waker.wake();
+        }
+        if failed {
+            inner.flushing = false;
+            return;
+        }
+    }
+}
+
+// Writes all the messages, with as few vectored writes as possible.
+async fn write_all(socket: &Socket, mut messages: Vec<Vec<u8>>) -> io::Result<()> {
+    while !messages.is_empty() {
+        let rest = messages.split_off(messages.len().min(libc::UIO_MAXIOV as usize));
+        let (res, mut written) = socket.writev(messages).await;
+        let mut n = match res {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ));
+            }
+            Ok(n) => n,
+            Err(e) => return Err(e),
+        };
+
+        // Drop what has been written, keeping the rest of a partial write
+        let done = written
+            .iter()
+            .take_while(|message| {
+                let fits = message.len() <= n;
+                if fits {
+                    n -= message.len();
+                }
+                fits
+            })
+            .count();
+        written.drain(..done);
+        if let Some(first) = written.first_mut() {
+            first.drain(..n);
+        }
+        written.extend(rest);
+        messages = written;
+    }
+    Ok(())
+}
+
+fn copy_error(e: &io::Error) -> io::Error {
+    match e.raw_os_error() {
+        Some(code) => io::Error::from_raw_os_error(code),
+        None => io::Error::new(e.kind(), e.to_string()),
+    }
+}
@@ -0,0 +1,95 @@
+use crate::buf::BoundedBuf;
+use crate::io::{SendZcNotified, SharedFd};
+use crate::runtime::driver::op::{MultiCQEFuture, Op};
+use std::future::{poll_fn, Future};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A future resolving to the buffer of a zero-copy send, once the kernel
+/// has released it.
+///
+/// Returned by `send_zc_notified` methods, such as
+/// [`TcpStream::send_zc_notified`]. The data of a zero-copy send is
+/// transmitted from the buffer after the send has completed, so the buffer
+/// cannot be reused until the kernel notifies that it is no longer in use.
+/// Awaiting the notification separately from the send result allows the
+/// caller to submit more sends in the meantime.
+///
+/// Dropping the `ZcNotification` does not release the buffer early: it is
+/// kept by the runtime until the notification arrives, and then dropped.
+///
+/// [`TcpStream::send_zc_notified`]: crate::net::TcpStream::send_zc_notified
+pub struct ZcNotification<T: 'static> {
+    state: State<T>,
+}
+
+enum State<T: 'static> {
+    Pending(Op<SendZcNotified<T>, MultiCQEFuture>),
+    Released(Option<T>),
+}
+
+impl<T: 'static> ZcNotification<T> {
+    /// Returns whether the kernel has released the buffer, so that awaiting
+    /// the notification completes without waiting.
+    ///
+    /// The state is updated when the notification is polled.
+    pub fn is_released(&self) -> bool {
+        matches!(self.state, State::Released(_))
+    }
+}
+
+impl<T: BoundedBuf> Future for ZcNotification<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let this = self.get_mut();
+        match &mut this.state {
+            State::Pending(op) => {
+                let (_, buf) = ready!(Pin::new(op).poll(cx));
+                this.state = State::Released(None);
+                Poll::Ready(buf)
+            }
+            State::Released(buf) => {
+                Poll::Ready(buf.take().expect("ZcNotification polled after completion"))
+            }
+        }
+    }
+}
+
+/// Sends the buffer with a zero-copy send, returning the result as soon as
+/// it is reported, along with the notification of the buffer release.
+pub(crate) async fn send_zc_notified<T: BoundedBuf>(
+    fd: &SharedFd,
+    buf: T,
+) -> (io::Result<usize>, ZcNotification<T>) {
+    let mut op = Op::send_zc_notified(fd, buf).unwrap();
+
+    let done = poll_fn(|cx| match Pin::new(&mut op).poll(cx) {
+        Poll::Ready(done) => Poll::Ready(Some(done)),
+        Poll::Pending => match &mut op.data {
+            Some(send) if send.sent.is_some() => Poll::Ready(None),
+            _ => Poll::Pending,
+        },
+    })
+    .await;
+
+    match done {
+        // No notification follows, the buffer is released
+        Some((res, buf)) => (
+            res,
+            ZcNotification {
+                state: State::Released(Some(buf)),
+            },
+        ),
+        None => {
+            let res = op.data.as_mut().unwrap().sent.take().unwrap();
+            (
+                res,
+                ZcNotification {
+                    state: State::Pending(op),
+                },
+            )
+        }
+    }
+}
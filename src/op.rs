@@ -0,0 +1,182 @@
+//! Operations defined outside this crate.
+//!
+//! The [`Operation`] trait allows a crate to submit io-uring operations
+//! which `tokio-uring` does not provide, such as new opcodes or
+//! `IORING_OP_URING_CMD` commands of a device driver, to the ring of the
+//! current runtime. The operations are tracked by the runtime like its own:
+//! [`submit`] returns a future resolving to the output of the operation when
+//! it completes, and the resources of an operation whose future is dropped
+//! are kept until the kernel is done with it.
+//!
+//! With the fallback backend, operations whose opcode it does not emulate
+//! fail with `EOPNOTSUPP`.
+
+use crate::runtime::driver::op::{Completable, CqeResult, Op};
+use crate::runtime::CONTEXT;
+use io_uring::squeue;
+use std::future::Future;
+use std::io;
+use std::os::unix::io::OwnedFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// An io-uring operation, owning the resources it uses while it is in
+/// flight.
+///
+/// # Safety
+///
+/// The submission queue entry built by [`build`] must be valid for the
+/// kernel to perform until the operation completes:
+///
+/// * Memory the entry points to must be owned by the operation and must
+///   not move when the operation value is moved, e.g. be a heap allocation
+///   such as the buffer of a `Vec<u8>`. The operation value is moved after
+///   the entry is built, and it is dropped only once the operation has
+///   completed, even if its future is dropped before.
+/// * Other resources, such as file descriptors, must stay valid until the
+///   operation completes.
+/// * The entry must not set the `IO_LINK`, `IO_HARDLINK` or
+///   `CQE_SKIP_SUCCESS` flags. Its user data is set by the runtime.
+///
+/// [`build`]: Operation::build
+///
+/// # Examples
+///
+/// ```no_run
+/// use io_uring::{opcode, squeue, types};
+/// use std::io;
+/// use std::os::unix::io::RawFd;
+/// use tokio_uring::op::{self, Operation};
+///
+/// struct Read {
+///     fd: RawFd,
+///     buf: Vec<u8>,
+/// }
+///
+/// unsafe impl Operation for Read {
+///     type Output = io::Result<Vec<u8>>;
+///
+///     fn build(&mut self) -> squeue::Entry {
+///         let len = self.buf.capacity() as u32;
+///         opcode::Read::new(types::Fd(self.fd), self.buf.as_mut_ptr(), len).build()
+///     }
+///
+///     fn complete(mut self, result: io::Result<u32>, _flags: u32) -> Self::Output {
+///         let n = result?;
+///         unsafe { self.buf.set_len(n as usize) };
+///         Ok(self.buf)
+///     }
+/// }
+///
+/// tokio_uring::start(async {
+///     let data = op::submit(Read { fd: 0, buf: Vec::with_capacity(4096) })
+///         .unwrap()
+///         .await
+///         .unwrap();
+///     println!("read {} bytes from stdin", data.len());
+/// });
+/// ```
+pub unsafe trait Operation: 'static {
+    /// The output of the operation.
+    type Output;
+
+    /// Builds the submission queue entry of the operation.
+    ///
+    /// This is called once, when the operation is submitted.
+    fn build(&mut self) -> squeue::Entry;
+
+    /// Produces the output of the operation from the result and the flags
+    /// of its completion queue entry.
+    ///
+    /// A negative result of the entry is converted to the error.
+    fn complete(self, result: io::Result<u32>, flags: u32) -> Self::Output;
+}
+
+/// Submits an operation to the ring of the current runtime.
+///
+/// The operation is queued for submission when this is called. Awaiting the
+/// returned future is not necessary for the operation to be performed.
+///
+/// # Errors
+///
+/// Fails if the operation cannot be queued, like the operations provided by
+/// this crate, e.g. with the `Error` [`CqOverflow`] policy.
+///
+/// [`CqOverflow`]: crate::CqOverflow
+///
+/// # Panics
+///
+/// This function panics if called outside the context of a `tokio-uring`
+/// runtime.
+pub fn submit<T: Operation>(op: T) -> io::Result<Submitted<T>> {
+    CONTEXT
+        .with(|x| {
+            x.handle()
+                .expect("Not in a runtime context")
+                .submit_op(External(op), |op| op.0.build())
+        })
+        .map(Submitted)
+}
+
+/// Installs the direct descriptor in `slot` of the table of registered files
+/// as a regular file descriptor of the process.
+///
+/// Operations submitted with [`submit`] can open files and accept
+/// connections into the table registered with
+/// [`Builder::register_file_table`], as direct descriptors which are only
+/// usable by io-uring operations. This makes a file or socket opened that
+/// way usable by code outside the ring, e.g. to pass it to a child process.
+/// The direct descriptor stays in the table. The new descriptor has the
+/// close-on-exec flag set if `cloexec` is `true`.
+///
+/// Requires Linux 6.8 or later.
+///
+/// [`Builder::register_file_table`]: crate::Builder::register_file_table
+///
+/// # Errors
+///
+/// Fails with `EBADF` if the slot is empty, and with `EINVAL` on kernels
+/// without the operation.
+///
+/// # Panics
+///
+/// This function panics if called outside the context of a `tokio-uring`
+/// runtime.
+pub async fn install_fixed_fd(slot: u32, cloexec: bool) -> io::Result<OwnedFd> {
+    Op::fixed_fd_install(slot, cloexec)?.await
+}
+
+/// Future of a submitted [`Operation`], returned by [`submit`].
+///
+/// Dropping the future does not cancel the operation, unless the runtime is
+/// configured to with [`Builder::cancel_on_drop`].
+///
+/// [`Builder::cancel_on_drop`]: crate::Builder::cancel_on_drop
+pub struct Submitted<T: Operation>(Op<External<T>>);
+
+impl<T: Operation> Future for Submitted<T> {
+    type Output = T::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+impl<T: Operation> std::fmt::Debug for Submitted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Submitted").finish_non_exhaustive()
+    }
+}
+
+// Adapts an operation to the driver. The operation value is never pinned.
+struct External<T>(T);
+
+impl<T> Unpin for External<T> {}
+
+impl<T: Operation> Completable for External<T> {
+    type Output = T::Output;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        self.0.complete(cqe.result, cqe.flags)
+    }
+}
@@ -0,0 +1,160 @@
+//! Waiting for child processes.
+//!
+//! A [`Child`] is a process spawned with the standard library's
+//! [`Command`], which the runtime waits for through a pidfd, a file
+//! descriptor referring to the process. No signal handler or helper thread
+//! is needed, and signals sent through the pidfd cannot reach another
+//! process which has reused the process ID. This requires Linux 5.3 or
+//! later.
+//!
+//! [`Command`]: std::process::Command
+
+use crate::io::SharedFd;
+use crate::runtime::driver::op::Op;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::process::{Command, ExitStatus};
+use std::ptr;
+
+/// Spawns a command as a child process.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::process::Command;
+/// use tokio_uring::process;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let mut child = process::spawn(Command::new("sleep").arg("1"))?;
+///         let status = child.wait().await?;
+///         println!("exited with {}", status);
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn spawn(command: &mut Command) -> io::Result<Child> {
+    Child::from_std(command.spawn()?)
+}
+
+/// A child process, which can be waited for asynchronously.
+///
+/// Like a [`std::process::Child`], dropping a `Child` does not kill or reap
+/// the process.
+pub struct Child {
+    inner: std::process::Child,
+    pidfd: SharedFd,
+    status: Option<ExitStatus>,
+}
+
+impl Child {
+    /// Converts a child process spawned with the standard library.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the pidfd cannot be opened, e.g. with `ENOSYS` on kernels
+    /// before 5.3.
+    pub fn from_std(child: std::process::Child) -> io::Result<Child> {
+        let pidfd = unsafe { libc::syscall(libc::SYS_pidfd_open, child.id(), 0) };
+        if pidfd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // The pidfd is close-on-exec
+        Ok(Child {
+            inner: child,
+            pidfd: SharedFd::new(pidfd as RawFd),
+            status: None,
+        })
+    }
+
+    /// Returns the OS-assigned process identifier of the child.
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Returns the standard library handle of the child, giving access to
+    /// its standard input and output.
+    pub fn as_std_mut(&mut self) -> &mut std::process::Child {
+        &mut self.inner
+    }
+
+    /// Waits for the child to exit, and returns its exit status.
+    ///
+    /// The child is reaped. Once it has exited, this method keeps returning
+    /// its exit status. The standard input of the child is closed before
+    /// waiting, so that a child reading it does not wait for more input.
+    ///
+    /// # Cancel safety
+    ///
+    /// The method can be called again if its future is dropped, the exit
+    /// status of the child is not lost.
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        drop(self.inner.stdin.take());
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            // The pidfd becomes readable when the process exits
+            Op::poll_add(&self.pidfd, libc::POLLIN as u32)?.await?;
+        }
+    }
+
+    /// Returns the exit status of the child if it has exited, reaping it,
+    /// or `None` if it is still running.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = self.inner.try_wait()?;
+        }
+        Ok(self.status)
+    }
+
+    /// Sends a signal to the child.
+    ///
+    /// Unlike `kill(2)` with the process ID, this cannot signal another
+    /// process if the child has been reaped and its process ID reused.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ESRCH` if the child has exited.
+    pub fn signal(&self, signal: libc::c_int) -> io::Result<()> {
+        let res = unsafe {
+            libc::syscall(
+                libc::SYS_pidfd_send_signal,
+                self.pidfd.raw_fd(),
+                signal,
+                ptr::null::<libc::siginfo_t>(),
+                0,
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Kills the child with `SIGKILL`.
+    ///
+    /// This does not wait for the child to exit, [`wait`] can be used for
+    /// that.
+    ///
+    /// [`wait`]: Child::wait
+    pub fn kill(&self) -> io::Result<()> {
+        self.signal(libc::SIGKILL)
+    }
+}
+
+impl AsRawFd for Child {
+    /// Returns the pidfd of the child.
+    fn as_raw_fd(&self) -> RawFd {
+        self.pidfd.raw_fd()
+    }
+}
+
+impl std::fmt::Debug for Child {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Child")
+            .field("id", &self.id())
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
+}
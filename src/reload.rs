@@ -0,0 +1,249 @@
+//! Reloading the configuration of a daemon on `SIGHUP`.
+//!
+//! A [`Watcher`] receives the reload signals through a signalfd, a file
+//! descriptor the signals are read from, so that no signal handler runs
+//! and the requests are handled by a task like any other event. Each
+//! signal received is returned by [`Watcher::next`] as a [`Reload`], or,
+//! with the `stream` feature, by using the watcher as a `Stream`.
+//!
+//! Work which must not overlap a reload, e.g. requests using the current
+//! configuration, holds a [`Guard`] entered with [`Watcher::enter`]. A
+//! reload can [`quiesce`] the work: it waits for the guards held to be
+//! dropped, and holds back new ones until the reload is done.
+//!
+//! [`quiesce`]: Reload::quiesce
+
+use crate::io::{PollMulti, SharedFd};
+use crate::runtime::driver::op::{MultiCQEStream, Op};
+use std::io;
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::sync::Arc;
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+/// Receives reload requests as signals, and coordinates them with the work
+/// in progress.
+///
+/// The signals are blocked for the thread creating the watcher, so that
+/// they are not handled with their default action, which terminates the
+/// process for `SIGHUP`. Threads created afterwards inherit the blocked
+/// signals, but threads created before do not: the watcher should be
+/// created on the main thread before any other, or the signals blocked in
+/// all threads. The signals stay blocked when the watcher is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use tokio_uring::net::TcpListener;
+/// use tokio_uring::reload::Watcher;
+///
+/// fn main() -> std::io::Result<()> {
+///     let watcher = Rc::new(Watcher::new()?);
+///     tokio_uring::start(async {
+///         let reloads = watcher.clone();
+///         tokio_uring::spawn(async move {
+///             while let Ok(reload) = reloads.next().await {
+///                 let _quiesced = reload.quiesce().await;
+///                 // Read the configuration again
+///             }
+///         });
+///
+///         let listener = TcpListener::bind("127.0.0.1:8080".parse().unwrap())?;
+///         loop {
+///             let (stream, _) = listener.accept().await?;
+///             let guard = watcher.enter().await;
+///             tokio_uring::spawn(async move {
+///                 // Serve the request with the current configuration
+///                 # drop(stream);
+///                 drop(guard);
+///             });
+///         }
+///     })
+/// }
+/// ```
+pub struct Watcher {
+    fd: SharedFd,
+    work: Arc<RwLock<()>>,
+
+    // The poll in flight for the `Stream` implementation, completing when
+    // there are signals to read.
+    poll: Option<Op<PollMulti, MultiCQEStream>>,
+}
+
+impl Watcher {
+    /// Creates a watcher receiving `SIGHUP`.
+    pub fn new() -> io::Result<Watcher> {
+        Watcher::with_signals(&[libc::SIGHUP])
+    }
+
+    /// Creates a watcher receiving the given signals, e.g. `SIGHUP` and
+    /// `SIGUSR1` for different kinds of reloads.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `EINVAL` if a signal is invalid, or cannot be blocked,
+    /// like `SIGKILL`.
+    pub fn with_signals(signals: &[libc::c_int]) -> io::Result<Watcher> {
+        let mut set: libc::sigset_t = unsafe { mem::zeroed() };
+        unsafe { libc::sigemptyset(&mut set) };
+        for &signal in signals {
+            if signal == libc::SIGKILL || signal == libc::SIGSTOP {
+                return Err(io::Error::from_raw_os_error(libc::EINVAL));
+            }
+            syscall!(sigaddset(&mut set, signal))?;
+        }
+
+        let res = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) };
+        if res != 0 {
+            return Err(io::Error::from_raw_os_error(res));
+        }
+        let fd = syscall!(signalfd(-1, &set, libc::SFD_NONBLOCK | libc::SFD_CLOEXEC))?;
+        Ok(Watcher {
+            fd: SharedFd::new(fd),
+            work: Arc::new(RwLock::new(())),
+            poll: None,
+        })
+    }
+
+    /// Waits for the next reload request.
+    ///
+    /// Signals received while no task waits are kept, and returned by the
+    /// next call; the same signal received several times before that is
+    /// returned once.
+    pub async fn next(&self) -> io::Result<Reload> {
+        loop {
+            if let Some(signal) = read_signal(self.fd.raw_fd())? {
+                return Ok(Reload {
+                    signal,
+                    work: self.work.clone(),
+                });
+            }
+            Op::poll_add(&self.fd, libc::POLLIN as _)?.await?;
+        }
+    }
+
+    /// Enters work which must not overlap a reload, returning a guard
+    /// which leaves it when dropped.
+    ///
+    /// Waits while a reload quiesces the work, or is in progress.
+    pub async fn enter(&self) -> Guard {
+        Guard {
+            _guard: self.work.clone().read_owned().await,
+        }
+    }
+}
+
+/// Yields the reload requests of [`Watcher::next`]. The stream does not
+/// end.
+///
+/// Requires the `stream` feature.
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Watcher {
+    type Item = io::Result<Reload>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match read_signal(this.fd.raw_fd()) {
+                Ok(Some(signal)) => {
+                    return std::task::Poll::Ready(Some(Ok(Reload {
+                        signal,
+                        work: this.work.clone(),
+                    })))
+                }
+                Ok(None) => {}
+                Err(e) => return std::task::Poll::Ready(Some(Err(e))),
+            }
+
+            let poll = match &mut this.poll {
+                Some(poll) => poll,
+                None => match Op::poll_multi(&this.fd, libc::POLLIN as u32) {
+                    Ok(poll) => this.poll.insert(poll),
+                    Err(e) => return std::task::Poll::Ready(Some(Err(e))),
+                },
+            };
+            let res = ready!(poll.poll_next(cx));
+            // The data of the operation is taken with the final completion,
+            // after which a new poll is needed
+            if poll.data.is_none() {
+                this.poll = None;
+            }
+            if let Some(Err(e)) = res {
+                return std::task::Poll::Ready(Some(Err(e)));
+            }
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        if let Some(poll) = &self.poll {
+            poll.cancel();
+        }
+    }
+}
+
+impl std::fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watcher")
+            .field("fd", &self.fd.raw_fd())
+            .finish()
+    }
+}
+
+// Reads a signal from a non-blocking signalfd, if one is pending. The read
+// is done in the thread of the runtime, which also receives the signals
+// sent to that thread alone.
+fn read_signal(fd: RawFd) -> io::Result<Option<libc::c_int>> {
+    let mut info: libc::signalfd_siginfo = unsafe { mem::zeroed() };
+    let len = mem::size_of_val(&info);
+    match syscall!(read(fd, ptr::addr_of_mut!(info).cast(), len)) {
+        Ok(_) => Ok(Some(info.ssi_signo as libc::c_int)),
+        Err(e) if e.raw_os_error() == Some(libc::EAGAIN) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A reload request, returned by [`Watcher::next`].
+#[derive(Debug)]
+pub struct Reload {
+    signal: libc::c_int,
+    work: Arc<RwLock<()>>,
+}
+
+impl Reload {
+    /// Returns the signal requesting the reload.
+    pub fn signal(&self) -> libc::c_int {
+        self.signal
+    }
+
+    /// Waits for the work entered with [`Watcher::enter`] to be left, and
+    /// holds back new work until the returned value is dropped.
+    ///
+    /// New work is held back as soon as the reload starts to quiesce, so
+    /// that the reload is not delayed by work entered meanwhile.
+    pub async fn quiesce(&self) -> Quiesced {
+        Quiesced {
+            _guard: self.work.clone().write_owned().await,
+        }
+    }
+}
+
+/// Work which must not overlap a reload, entered with [`Watcher::enter`]
+/// and left when dropped.
+#[derive(Debug)]
+pub struct Guard {
+    _guard: OwnedRwLockReadGuard<()>,
+}
+
+/// Holds back new work while a reload is in progress, returned by
+/// [`Reload::quiesce`].
+#[derive(Debug)]
+pub struct Quiesced {
+    _guard: OwnedRwLockWriteGuard<()>,
+}
@@ -32,6 +32,11 @@ impl RuntimeContext {
         *guard = None;
     }
 
+    /// Replaces the driver, returning the previous one.
+    pub(crate) fn replace_handle(&self, handle: Option<Handle>) -> Option<Handle> {
+        std::mem::replace(&mut *self.driver.borrow_mut(), handle)
+    }
+
     /// Check if driver is initialized
     pub(crate) fn is_set(&self) -> bool {
         self.driver
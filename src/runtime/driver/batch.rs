@@ -0,0 +1,231 @@
+use crate::runtime::CONTEXT;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A group of operations submitted to the kernel together.
+///
+/// The futures of the operations, e.g. those returned by
+/// [`File::read_at`], are added with [`push`], and run with [`run`], which
+/// resolves to their outputs once all have completed. The operations the
+/// futures create when first polled are queued together and submitted
+/// with a single `io_uring_enter` call, rather than in the order and the
+/// batches the runtime would otherwise submit them in. This saves system
+/// calls for groups of small operations, and bounds the delay between the
+/// first and the last operation of the group reaching the kernel.
+///
+/// All the operations are submitted together as long as they fit in the
+/// submission queue; a larger group is submitted in as many calls as
+/// needed. Operations created by the futures after their first poll, such
+/// as the second read of a `read_exact_at`, are submitted as usual.
+///
+/// The [`join_ops!`] macro does the same for futures of different types.
+///
+/// [`File::read_at`]: crate::fs::File::read_at
+/// [`push`]: Batch::push
+/// [`run`]: Batch::run
+/// [`join_ops!`]: crate::join_ops
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::File;
+/// use tokio_uring::Batch;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let file = File::open("index.db").await?;
+///
+///         let mut batch = Batch::new();
+///         for offset in [0, 4096, 65536, 1 << 20] {
+///             batch.push(file.read_at(vec![0; 512], offset));
+///         }
+///         for (res, buf) in batch.run().await {
+///             let n = res?;
+///             println!("{:?}", &buf[..n]);
+///         }
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct Batch<F: Future> {
+    futures: Vec<MaybeDone<F>>,
+}
+
+impl<F: Future> Batch<F> {
+    /// Creates an empty batch.
+    pub fn new() -> Batch<F> {
+        Batch {
+            futures: Vec::new(),
+        }
+    }
+
+    /// Adds the future of an operation to the batch.
+    pub fn push(&mut self, future: F) -> &mut Self {
+        self.futures.push(MaybeDone::new(future));
+        self
+    }
+
+    /// Returns the number of futures in the batch.
+    pub fn len(&self) -> usize {
+        self.futures.len()
+    }
+
+    /// Returns `true` if the batch has no futures.
+    pub fn is_empty(&self) -> bool {
+        self.futures.is_empty()
+    }
+
+    /// Submits the operations of the batch together, and waits for all of
+    /// them to complete, returning their outputs in the order the futures
+    /// were added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub async fn run(mut self) -> Vec<F::Output> {
+        submit_together(poll_fn(|cx| {
+            let mut done = true;
+            for future in &mut self.futures {
+                done &= future.poll(cx).is_ready();
+            }
+            if done {
+                Poll::Ready(self.futures.iter_mut().map(MaybeDone::take).collect())
+            } else {
+                Poll::Pending
+            }
+        }))
+        .await
+    }
+}
+
+impl<F: Future> Default for Batch<F> {
+    fn default() -> Self {
+        Batch::new()
+    }
+}
+
+impl<F: Future> std::fmt::Debug for Batch<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Batch")
+            .field("len", &self.futures.len())
+            .finish()
+    }
+}
+
+/// Waits for the futures of several operations, submitting the operations
+/// they create together, and returns a tuple of their outputs.
+///
+/// The macro evaluates to a future, which completes once all of the futures
+/// have completed. Like [`Batch`], which takes any number of futures of the
+/// same type, it guarantees that the operations are submitted with a single
+/// `io_uring_enter` call, if they fit in the submission queue.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::File;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let data = File::open("data").await?;
+///         let log = File::create("log").await?;
+///
+///         let ((read, buf), (written, _)) = tokio_uring::join_ops!(
+///             data.read_at(vec![0; 4096], 0),
+///             log.write_at(b"read\n".to_vec(), 0),
+///         )
+///         .await;
+///         println!("read {} bytes, logged {}", read?, written?);
+///         Ok(())
+///     })
+/// }
+/// ```
+#[macro_export]
+macro_rules! join_ops {
+    // Each future is paired with a count of `_` patterns, skipping the
+    // futures before it in the tuple
+    (@ { ( $($count:tt)* ) $( ( $($skip:tt)* ) $e:expr, )* } $head:expr, $($tail:expr,)*) => {
+        $crate::join_ops!(@ { ( $($count)* _ ) $( ( $($skip)* ) $e, )* ( $($count)* ) $head, } $($tail,)*)
+    };
+    (@ { ( $($count:tt)* ) $( ( $($skip:tt)* ) $e:expr, )* }) => {{
+        let mut futures = ( $( $crate::__private::MaybeDone::new($e), )* );
+        $crate::__private::submit_together(::std::future::poll_fn(move |cx| {
+            let mut done = true;
+            $(
+                let ( $($skip,)* future, .. ) = &mut futures;
+                done &= future.poll(cx).is_ready();
+            )*
+            if done {
+                ::std::task::Poll::Ready(( $({
+                    let ( $($skip,)* future, .. ) = &mut futures;
+                    future.take()
+                },)* ))
+            } else {
+                ::std::task::Poll::Pending
+            }
+        }))
+    }};
+    ($($e:expr),+ $(,)?) => {
+        $crate::join_ops!(@ { () } $($e,)+)
+    };
+}
+
+/// Runs a future, submitting the operations it creates in its first poll
+/// together at the end of the poll.
+#[doc(hidden)]
+pub async fn submit_together<F: Future>(future: F) -> F::Output {
+    let handle = CONTEXT.with(|x| x.handle().expect("Not in a runtime context"));
+    let mut future = std::pin::pin!(future);
+    let mut first = true;
+
+    poll_fn(|cx| {
+        if !std::mem::take(&mut first) {
+            return future.as_mut().poll(cx);
+        }
+        // Ends the batch even if the future panics
+        struct EndBatch<'a>(&'a crate::runtime::driver::Handle);
+        impl Drop for EndBatch<'_> {
+            fn drop(&mut self) {
+                // If the submission fails, the operations whose entries
+                // could not be queued complete with the error, and the
+                // others are left queued for the next submission.
+                let _ = self.0.end_batch();
+            }
+        }
+
+        handle.begin_batch();
+        let _end = EndBatch(&handle);
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// A future of a batch, and its output once it has completed.
+#[doc(hidden)]
+pub enum MaybeDone<F: Future> {
+    Pending(Pin<Box<F>>),
+    Done(F::Output),
+    Taken,
+}
+
+impl<F: Future> MaybeDone<F> {
+    pub fn new(future: F) -> MaybeDone<F> {
+        MaybeDone::Pending(Box::pin(future))
+    }
+
+    pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let MaybeDone::Pending(future) = self {
+            let output = ready!(future.as_mut().poll(cx));
+            *self = MaybeDone::Done(output);
+        }
+        Poll::Ready(())
+    }
+
+    pub fn take(&mut self) -> F::Output {
+        match std::mem::replace(self, MaybeDone::Taken) {
+            MaybeDone::Done(output) => output,
+            _ => panic!("the output of the future has already been taken"),
+        }
+    }
+}
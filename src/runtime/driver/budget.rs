@@ -0,0 +1,38 @@
+use std::cell::Cell;
+use std::future::{poll_fn, Future};
+
+thread_local! {
+    // Entries submitted by the task being polled.
+    static USED: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Runs a task, counting the entries it submits in each poll against the
+/// budget.
+pub(crate) async fn budgeted<F: Future>(future: F) -> F::Output {
+    tokio::pin!(future);
+
+    poll_fn(|cx| {
+        let _restore = RestoreUsed(USED.with(|used| used.replace(0)));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// Counts `n` entries submitted by the current task, returning `false` if
+/// they exceed the budget of the poll.
+pub(crate) fn consume(budget: Option<u32>, n: u32) -> bool {
+    USED.with(|used| {
+        let total = used.get().saturating_add(n);
+        used.set(total);
+        budget.is_none_or(|budget| total <= budget)
+    })
+}
+
+// Restores the count of the enclosing task when the task returns from poll.
+struct RestoreUsed(u32);
+
+impl Drop for RestoreUsed {
+    fn drop(&mut self) {
+        USED.with(|used| used.set(self.0));
+    }
+}
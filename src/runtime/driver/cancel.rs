@@ -0,0 +1,200 @@
+use crate::runtime::CONTEXT;
+use io_uring::types;
+use std::cell::{Cell, RefCell};
+use std::future::{poll_fn, Future};
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+thread_local! {
+    // Cancellation scope of the future being polled.
+    static SCOPE: RefCell<Option<Rc<Scope>>> = const { RefCell::new(None) };
+}
+
+/// A cancellation scope, entered with [`with_cancellation`],
+/// [`crate::time::timeout`] or [`crate::time::with_deadline`].
+pub(crate) struct Scope {
+    cancelled: Cell<bool>,
+    deadline: Option<Deadline>,
+    parent: Option<Rc<Scope>>,
+}
+
+struct Deadline {
+    at: Instant,
+
+    // The deadline on the CLOCK_MONOTONIC clock, for linked timeouts.
+    // The kernel reads it when the operations are submitted, which is done
+    // while they hold a reference to the scope.
+    timespec: Box<types::Timespec>,
+}
+
+impl Scope {
+    pub(crate) fn new(deadline: Option<Instant>) -> Rc<Scope> {
+        Rc::new(Scope {
+            cancelled: Cell::new(false),
+            deadline: deadline.map(Deadline::at),
+            parent: Scope::current(),
+        })
+    }
+
+    /// Returns the scope operations are currently submitted in.
+    pub(crate) fn current() -> Option<Rc<Scope>> {
+        SCOPE.with(|scope| scope.borrow().clone())
+    }
+
+    /// Returns `true` if this scope, or a scope it is nested in, has been
+    /// cancelled.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.get() || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+
+    /// Returns `true` if the deadline of this scope, or of a scope it is
+    /// nested in, has passed.
+    pub(crate) fn is_timed_out(&self) -> bool {
+        self.deadline
+            .as_ref()
+            .is_some_and(|d| d.at <= Instant::now())
+            || self.parent.as_ref().is_some_and(|p| p.is_timed_out())
+    }
+
+    /// Returns the earliest of the deadlines of this scope and the scopes
+    /// it is nested in.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.earliest_deadline().map(|d| d.at)
+    }
+
+    /// Returns the deadline for the linked timeout of the operations
+    /// submitted in this scope, which is the earliest deadline, as for
+    /// [`deadline`](Scope::deadline).
+    pub(crate) fn link_timeout(&self) -> Option<&types::Timespec> {
+        self.earliest_deadline().map(|d| &*d.timespec)
+    }
+
+    fn earliest_deadline(&self) -> Option<&Deadline> {
+        let parent = self.parent.as_ref().and_then(|p| p.earliest_deadline());
+        match (&self.deadline, parent) {
+            (Some(own), Some(parent)) if parent.at < own.at => Some(parent),
+            (Some(own), _) => Some(own),
+            (None, parent) => parent,
+        }
+    }
+
+    /// Returns `true` if this scope is `scope` or is nested in it.
+    pub(crate) fn is_within(&self, scope: &Scope) -> bool {
+        std::ptr::eq(self, scope) || self.parent.as_ref().is_some_and(|p| p.is_within(scope))
+    }
+}
+
+impl Deadline {
+    fn at(at: Instant) -> Deadline {
+        let mut now = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+        // A deadline which has passed is kept as is, for the operations to
+        // time out as soon as they are submitted
+        let timeout = at.saturating_duration_since(Instant::now());
+        let monotonic = Duration::new(now.tv_sec as u64, now.tv_nsec as u32) + timeout;
+
+        Deadline {
+            at,
+            timespec: Box::new(
+                types::Timespec::new()
+                    .sec(monotonic.as_secs())
+                    .nsec(monotonic.subsec_nanos()),
+            ),
+        }
+    }
+}
+
+/// Runs a future, cancelling the io-uring operations it submits once the
+/// `cancelled` future completes.
+///
+/// When `cancelled` completes, the operations submitted by `future` which are
+/// still in flight are canceled with `IORING_OP_ASYNC_CANCEL`, and the
+/// operations it submits afterwards are canceled as soon as they are
+/// submitted. Canceled operations complete with an error, typically of the
+/// `ECANCELED` or `EINTR` error codes, so that `future` can tear down its
+/// state through the usual error paths, and its buffers are returned once
+/// the kernel has released them. `future` itself is not dropped: it is
+/// still polled to completion.
+///
+/// Operations which were already completed by the kernel are not affected,
+/// and the operations of tasks spawned by `future` are not canceled. Nested
+/// calls are canceled along with the enclosing ones.
+///
+/// Any future can be used as the cancellation signal, such as the one
+/// returned by `tokio_util::sync::CancellationToken::cancelled`, a timer, or
+/// a channel receiver.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio_uring::net::TcpStream;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await?;
+///
+///         // Give up on the request after a second. The read fails, and the
+///         // buffer is returned.
+///         let (res, buf) = tokio_uring::with_cancellation(
+///             tokio::time::sleep(Duration::from_secs(1)),
+///             async {
+///                 let (res, _) = stream.write_all(b"request".to_vec()).await;
+///                 if let Err(e) = res {
+///                     return (Err(e), vec![]);
+///                 }
+///                 stream.read(vec![0; 4096]).await
+///             },
+///         )
+///         .await;
+///
+///         println!("{:?} {:?}", res, buf);
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn with_cancellation<C, F>(cancelled: C, future: F) -> F::Output
+where
+    C: Future<Output = ()>,
+    F: Future,
+{
+    scoped(Scope::new(None), cancelled, future).await
+}
+
+/// Runs `future` in `scope`, cancelling the scope once `cancelled`
+/// completes.
+pub(crate) async fn scoped<C, F>(scope: Rc<Scope>, cancelled: C, future: F) -> F::Output
+where
+    C: Future<Output = ()>,
+    F: Future,
+{
+    tokio::pin!(cancelled, future);
+
+    poll_fn(|cx| {
+        if !scope.cancelled.get() && cancelled.as_mut().poll(cx).is_ready() {
+            scope.cancelled.set(true);
+            CONTEXT.with(|x| {
+                x.handle()
+                    .expect("Not in a runtime context")
+                    .cancel_scope(&scope)
+            });
+        }
+
+        let _restore = RestoreScope(SCOPE.with(|current| current.replace(Some(scope.clone()))));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+// Restores the previous scope when the future returns from poll.
+struct RestoreScope(Option<Rc<Scope>>);
+
+impl Drop for RestoreScope {
+    fn drop(&mut self) {
+        SCOPE.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
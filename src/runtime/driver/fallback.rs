@@ -0,0 +1,477 @@
+//! Blocking implementation of the operations, used when io_uring is not
+//! available.
+//!
+//! Each chain of linked entries is performed by a worker thread, with the
+//! system calls the kernel would make for them. Operations waiting for a
+//! file descriptor to become ready poll it, so that they can be canceled
+//! while they wait. Completions are reported through an eventfd, which the
+//! runtime watches in place of the ring.
+
+use crate::io::{IORING_OP_BIND, IORING_OP_LISTEN};
+use crate::runtime::driver::SqeHeader;
+use io_uring::{opcode, squeue};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+// Maximum number of worker threads.
+const MAX_THREADS: usize = 512;
+
+// Time after which an idle worker thread exits.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Interval at which operations waiting for readiness check whether they
+// have been canceled.
+const CANCEL_POLL_INTERVAL: libc::c_int = 50;
+
+/// Returns `true` if creating a ring failed because io_uring is not
+/// supported by the kernel or is blocked, e.g. by seccomp.
+pub(crate) fn is_unavailable(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(libc::ENOSYS) | Some(libc::EPERM) | Some(libc::EACCES)
+    )
+}
+
+pub(crate) struct Fallback {
+    shared: Arc<Shared>,
+
+    // Cancellation flags of the operations in flight, by user data.
+    inflight: HashMap<u64, Arc<AtomicBool>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    work: Condvar,
+
+    // Becomes readable when there are completions to collect.
+    event: OwnedFd,
+}
+
+struct State {
+    jobs: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+    shutdown: bool,
+    completions: Vec<(u64, io::Result<u32>)>,
+}
+
+struct Job {
+    chain: Vec<(SqeHeader, Arc<AtomicBool>)>,
+}
+
+impl Fallback {
+    pub(crate) fn new() -> io::Result<Fallback> {
+        let event = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if event < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Fallback {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    jobs: VecDeque::new(),
+                    threads: 0,
+                    idle: 0,
+                    shutdown: false,
+                    completions: Vec::new(),
+                }),
+                work: Condvar::new(),
+                event: unsafe { OwnedFd::from_raw_fd(event) },
+            }),
+            inflight: HashMap::new(),
+        })
+    }
+
+    /// Starts performing a chain of linked entries.
+    pub(crate) fn submit(&mut self, sqes: &[squeue::Entry]) {
+        let chain = sqes
+            .iter()
+            .map(SqeHeader::read)
+            // Linked timeouts are not operations, deadlines are enforced
+            // by canceling the operations instead
+            .filter(|sqe| sqe.user_data != u64::MAX)
+            .map(|sqe| {
+                let cancelled = Arc::new(AtomicBool::new(false));
+                self.inflight.insert(sqe.user_data, cancelled.clone());
+                (sqe, cancelled)
+            })
+            .collect();
+
+        let mut state = self.shared.lock();
+        state.jobs.push_back(Job { chain });
+        if state.idle < state.jobs.len() && state.threads < MAX_THREADS {
+            state.threads += 1;
+            let shared = self.shared.clone();
+            std::thread::Builder::new()
+                .name("tokio-uring-fallback".into())
+                .spawn(move || shared.run_worker())
+                .expect("failed to spawn a fallback worker thread");
+        }
+        drop(state);
+        self.shared.work.notify_one();
+    }
+
+    /// Cancels an operation, if it has not been started or is waiting for
+    /// its file descriptor to become ready.
+    pub(crate) fn cancel(&self, user_data: u64) {
+        if let Some(cancelled) = self.inflight.get(&user_data) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Moves the results of the completed operations to `out`.
+    pub(crate) fn completions(&mut self, out: &mut Vec<(u64, io::Result<u32>)>) {
+        let mut count = 0u64;
+        unsafe {
+            libc::read(
+                self.shared.event.as_raw_fd(),
+                &mut count as *mut u64 as *mut libc::c_void,
+                8,
+            );
+        }
+        out.append(&mut self.shared.lock().completions);
+        for (user_data, _) in out.iter() {
+            self.inflight.remove(user_data);
+        }
+    }
+
+    /// Blocks until there are completions to collect, or the timeout
+    /// expires.
+    pub(crate) fn wait(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let timeout = timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as libc::c_int);
+        let mut pollfd = libc::pollfd {
+            fd: self.shared.event.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut pollfd, 1, timeout) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl AsRawFd for Fallback {
+    fn as_raw_fd(&self) -> RawFd {
+        self.shared.event.as_raw_fd()
+    }
+}
+
+impl Drop for Fallback {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.work.notify_all();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn run_worker(&self) {
+        let mut state = self.lock();
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                drop(state);
+                let results = job.run();
+                state = self.lock();
+                state.completions.extend(results);
+                let one = 1u64;
+                unsafe {
+                    libc::write(
+                        self.event.as_raw_fd(),
+                        &one as *const u64 as *const libc::c_void,
+                        8,
+                    );
+                }
+                continue;
+            }
+            if state.shutdown {
+                break;
+            }
+
+            state.idle += 1;
+            let (guard, wait) = self
+                .work
+                .wait_timeout(state, IDLE_TIMEOUT)
+                .unwrap_or_else(|e| e.into_inner());
+            state = guard;
+            state.idle -= 1;
+            if wait.timed_out() && state.jobs.is_empty() {
+                break;
+            }
+        }
+        state.threads -= 1;
+    }
+}
+
+impl Job {
+    fn run(self) -> Vec<(u64, io::Result<u32>)> {
+        let mut results = Vec::with_capacity(self.chain.len());
+        let mut broken = false;
+        for (sqe, cancelled) in self.chain {
+            let res = if broken || cancelled.load(Ordering::Relaxed) {
+                Err(io::Error::from_raw_os_error(libc::ECANCELED))
+            } else {
+                // Safety: the buffers of the operation are valid until it
+                // completes, as they would be for the kernel.
+                unsafe { perform(&sqe, &cancelled) }
+            };
+            // A failed or short operation breaks the chain
+            broken = match &res {
+                Ok(n) => unsafe { expected_len(&sqe) }.is_some_and(|len| (*n as usize) < len),
+                Err(_) => true,
+            };
+            results.push((sqe.user_data, res));
+        }
+        results
+    }
+}
+
+// Returns the number of bytes an operation is expected to transfer, if it
+// transfers data.
+unsafe fn expected_len(sqe: &SqeHeader) -> Option<usize> {
+    match sqe.opcode {
+        opcode::Read::CODE
+        | opcode::ReadFixed::CODE
+        | opcode::Write::CODE
+        | opcode::WriteFixed::CODE
+        | opcode::Recv::CODE
+        | opcode::Send::CODE
+        | opcode::SendZc::CODE => Some(sqe.len as usize),
+        opcode::Readv::CODE | opcode::Writev::CODE => Some(
+            std::slice::from_raw_parts(sqe.addr as *const libc::iovec, sqe.len as usize)
+                .iter()
+                .map(|iov| iov.iov_len)
+                .sum(),
+        ),
+        _ => None,
+    }
+}
+
+// Waits until `fd` is ready for `events`, or the operation is canceled.
+// Returns the events `fd` is ready for.
+fn wait_ready(
+    fd: RawFd,
+    events: libc::c_short,
+    cancelled: &AtomicBool,
+) -> io::Result<libc::c_short> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    };
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(io::Error::from_raw_os_error(libc::ECANCELED));
+        }
+        match unsafe { libc::poll(&mut pollfd, 1, CANCEL_POLL_INTERVAL) } {
+            0 => continue,
+            n if n > 0 => return Ok(pollfd.revents),
+            _ => {
+                let e = io::Error::last_os_error();
+                if e.raw_os_error() != Some(libc::EINTR) {
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+// Performs a read or write at the offset. Like the kernel does for
+// io_uring, the offset is ignored for files which cannot seek, such as
+// sockets and pipes.
+fn at_offset(offset: libc::off_t, f: impl Fn(libc::off_t) -> isize) -> i64 {
+    let res = f(offset);
+    if res < 0 && offset >= 0 && io::Error::last_os_error().raw_os_error() == Some(libc::ESPIPE) {
+        return f(-1) as i64;
+    }
+    res as i64
+}
+
+fn cvt(res: i64) -> io::Result<u32> {
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res as u32)
+    }
+}
+
+// Performs an operation with the equivalent system call.
+unsafe fn perform(sqe: &SqeHeader, cancelled: &AtomicBool) -> io::Result<u32> {
+    let unsupported_flags = squeue::Flags::FIXED_FILE | squeue::Flags::BUFFER_SELECT;
+    if sqe.flags & unsupported_flags.bits() != 0 {
+        return Err(io::Error::from_raw_os_error(libc::EOPNOTSUPP));
+    }
+
+    let fd = sqe.fd;
+    let addr = sqe.addr as *mut libc::c_void;
+    let len = sqe.len as usize;
+    // An offset of -1 stands for the file position
+    let offset = sqe.off as libc::off_t;
+    let flags = sqe.op_flags as libc::c_int;
+
+    let res = match sqe.opcode {
+        opcode::Nop::CODE => 0,
+        opcode::Read::CODE | opcode::ReadFixed::CODE => {
+            wait_ready(fd, libc::POLLIN, cancelled)?;
+            at_offset(offset, |offset| {
+                if flags != 0 {
+                    let iov = libc::iovec {
+                        iov_base: addr,
+                        iov_len: len,
+                    };
+                    libc::preadv2(fd, &iov, 1, offset, flags)
+                } else if offset < 0 {
+                    libc::read(fd, addr, len)
+                } else {
+                    libc::pread(fd, addr, len, offset)
+                }
+            })
+        }
+        opcode::Write::CODE | opcode::WriteFixed::CODE => {
+            wait_ready(fd, libc::POLLOUT, cancelled)?;
+            at_offset(offset, |offset| {
+                if flags != 0 {
+                    let iov = libc::iovec {
+                        iov_base: addr,
+                        iov_len: len,
+                    };
+                    libc::pwritev2(fd, &iov, 1, offset, flags)
+                } else if offset < 0 {
+                    libc::write(fd, addr, len)
+                } else {
+                    libc::pwrite(fd, addr, len, offset)
+                }
+            })
+        }
+        opcode::Readv::CODE => {
+            wait_ready(fd, libc::POLLIN, cancelled)?;
+            at_offset(offset, |offset| {
+                libc::preadv2(fd, addr as *const libc::iovec, len as _, offset, flags)
+            })
+        }
+        opcode::Writev::CODE => {
+            wait_ready(fd, libc::POLLOUT, cancelled)?;
+            at_offset(offset, |offset| {
+                libc::pwritev2(fd, addr as *const libc::iovec, len as _, offset, flags)
+            })
+        }
+        opcode::Recv::CODE => {
+            wait_ready(fd, libc::POLLIN, cancelled)?;
+            libc::recv(fd, addr, len, flags) as i64
+        }
+        opcode::Send::CODE | opcode::SendZc::CODE => {
+            wait_ready(fd, libc::POLLOUT, cancelled)?;
+            libc::send(fd, addr, len, flags | libc::MSG_NOSIGNAL) as i64
+        }
+        opcode::RecvMsg::CODE => {
+            wait_ready(fd, libc::POLLIN, cancelled)?;
+            libc::recvmsg(fd, addr as *mut libc::msghdr, flags) as i64
+        }
+        opcode::SendMsg::CODE => {
+            wait_ready(fd, libc::POLLOUT, cancelled)?;
+            libc::sendmsg(fd, addr as *const libc::msghdr, flags | libc::MSG_NOSIGNAL) as i64
+        }
+        opcode::Accept::CODE => {
+            wait_ready(fd, libc::POLLIN, cancelled)?;
+            libc::accept4(
+                fd,
+                addr as *mut libc::sockaddr,
+                sqe.off as *mut libc::socklen_t,
+                flags,
+            ) as i64
+        }
+        opcode::Socket::CODE => {
+            libc::socket(fd, sqe.off as libc::c_int, sqe.len as libc::c_int) as i64
+        }
+        IORING_OP_BIND => libc::bind(
+            fd,
+            addr as *const libc::sockaddr,
+            sqe.off as libc::socklen_t,
+        ) as i64,
+        IORING_OP_LISTEN => libc::listen(fd, sqe.len as libc::c_int) as i64,
+        opcode::Connect::CODE => libc::connect(
+            fd,
+            addr as *const libc::sockaddr,
+            sqe.off as libc::socklen_t,
+        ) as i64,
+        opcode::Splice::CODE => {
+            // The input offset is passed in place of the address
+            let mut off_in = sqe.addr as libc::loff_t;
+            let mut off_out = sqe.off as libc::loff_t;
+            let off_in = if off_in < 0 {
+                std::ptr::null_mut()
+            } else {
+                &mut off_in as *mut libc::loff_t
+            };
+            let off_out = if off_out < 0 {
+                std::ptr::null_mut()
+            } else {
+                &mut off_out as *mut libc::loff_t
+            };
+            wait_ready(sqe.fd_in, libc::POLLIN, cancelled)?;
+            libc::splice(sqe.fd_in, off_in, fd, off_out, len, sqe.op_flags) as i64
+        }
+        opcode::PollAdd::CODE => {
+            wait_ready(fd, sqe.op_flags as libc::c_short, cancelled)? as libc::c_ushort as i64
+        }
+        opcode::Fsync::CODE => {
+            if sqe.op_flags & io_uring::types::FsyncFlags::DATASYNC.bits() != 0 {
+                libc::fdatasync(fd) as i64
+            } else {
+                libc::fsync(fd) as i64
+            }
+        }
+        opcode::Fallocate64::CODE => {
+            // The length is passed in place of the address, and the mode in
+            // place of the length
+            libc::fallocate64(
+                fd,
+                sqe.len as libc::c_int,
+                offset,
+                sqe.addr as libc::off64_t,
+            ) as i64
+        }
+        opcode::OpenAt::CODE => libc::openat(
+            fd,
+            addr as *const libc::c_char,
+            flags,
+            sqe.len as libc::c_uint,
+        ) as i64,
+        opcode::OpenAt2::CODE => libc::syscall(
+            libc::SYS_openat2,
+            fd,
+            addr as *const libc::c_char,
+            sqe.off as *const libc::open_how,
+            len,
+        ),
+        opcode::Close::CODE => libc::close(fd) as i64,
+        opcode::Statx::CODE => libc::statx(
+            fd,
+            addr as *const libc::c_char,
+            flags,
+            sqe.len,
+            sqe.off as *mut libc::statx,
+        ) as i64,
+        opcode::UnlinkAt::CODE => libc::unlinkat(fd, addr as *const libc::c_char, flags) as i64,
+        opcode::RenameAt::CODE => libc::syscall(
+            libc::SYS_renameat2,
+            fd,
+            addr as *const libc::c_char,
+            sqe.len as libc::c_int,
+            sqe.off as *const libc::c_char,
+            sqe.op_flags,
+        ),
+        opcode::MkDirAt::CODE => {
+            libc::mkdirat(fd, addr as *const libc::c_char, sqe.len as libc::mode_t) as i64
+        }
+        _ => return Err(io::Error::from_raw_os_error(libc::EOPNOTSUPP)),
+    };
+    cvt(res)
+}
@@ -0,0 +1,177 @@
+use io_uring::opcode;
+use std::time::Duration;
+
+/// When the runtime submits the entries of new operations to the kernel,
+/// set with [`Builder::flush_policy`].
+///
+/// The entries are queued as the operations are created, and the queue is
+/// submitted when the runtime has no more tasks to run and parks, or when
+/// it is full. Submitting them together saves system calls, but an
+/// operation waits for the tasks polled after its own to be submitted.
+///
+/// The adaptive policy, the default, submits the queue as soon as an entry
+/// is added while the load is light: few operations are in flight, and
+/// they have recently completed quickly. Once more operations are in
+/// flight, or they take longer to complete, which is a sign that the kernel
+/// or the devices are saturated, the entries are batched again, and the
+/// queue is submitted when the runtime parks, or once it holds a batch of
+/// the maximum size. The time an operation takes to complete is measured
+/// from its creation, and averaged over the recent operations.
+///
+/// The operations of a [`Batch`] or of [`join_ops!`] are submitted
+/// together whatever the policy.
+///
+/// [`Builder::flush_policy`]: crate::Builder::flush_policy
+/// [`Batch`]: crate::Batch
+/// [`join_ops!`]: crate::join_ops
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio_uring::FlushPolicy;
+///
+/// let mut policy = FlushPolicy::adaptive();
+/// policy
+///     .shallow_depth(16)
+///     .latency_target(Duration::from_micros(200))
+///     .max_batch(64);
+///
+/// tokio_uring::builder().flush_policy(policy).start(async {
+///     // Operations are submitted early while the load is light
+/// });
+/// ```
+#[derive(Clone, Debug)]
+pub struct FlushPolicy {
+    adaptive: bool,
+    shallow_depth: usize,
+    latency_target: Duration,
+    max_batch: usize,
+}
+
+impl FlushPolicy {
+    /// Creates the adaptive policy, submitting the entries early while up
+    /// to 8 operations are in flight and they complete within 100
+    /// microseconds on average, and in batches of up to 32 entries
+    /// otherwise.
+    pub fn adaptive() -> FlushPolicy {
+        FlushPolicy {
+            adaptive: true,
+            shallow_depth: 8,
+            latency_target: Duration::from_micros(100),
+            max_batch: 32,
+        }
+    }
+
+    /// Creates a policy submitting the entries only when the runtime parks,
+    /// or when the queue is full.
+    ///
+    /// This makes the fewest system calls, at the cost of the latency of
+    /// the operations while the load is light.
+    pub fn on_park() -> FlushPolicy {
+        FlushPolicy {
+            adaptive: false,
+            ..FlushPolicy::adaptive()
+        }
+    }
+
+    /// Sets the number of operations in flight up to which the entries are
+    /// submitted early.
+    pub fn shallow_depth(&mut self, depth: usize) -> &mut Self {
+        self.shallow_depth = depth;
+        self
+    }
+
+    /// Sets the average time the operations must complete within for the
+    /// entries to be submitted early.
+    pub fn latency_target(&mut self, latency: Duration) -> &mut Self {
+        self.latency_target = latency;
+        self
+    }
+
+    /// Sets the number of queued entries which are submitted without
+    /// waiting for the runtime to park, when the entries are batched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_batch` is 0.
+    pub fn max_batch(&mut self, max_batch: usize) -> &mut Self {
+        assert!(max_batch > 0, "the maximum batch must not be empty");
+        self.max_batch = max_batch;
+        self
+    }
+}
+
+impl Default for FlushPolicy {
+    fn default() -> FlushPolicy {
+        FlushPolicy::adaptive()
+    }
+}
+
+/// The state of the flush policy of a driver.
+pub(crate) struct Flusher {
+    policy: FlushPolicy,
+
+    // Moving average of the recent completion latencies
+    latency: Duration,
+}
+
+impl Flusher {
+    pub(crate) fn new(policy: &FlushPolicy) -> Flusher {
+        Flusher {
+            policy: policy.clone(),
+            latency: Duration::ZERO,
+        }
+    }
+
+    /// Returns `true` if the completion latencies are measured.
+    pub(crate) fn measures(&self) -> bool {
+        self.policy.adaptive
+    }
+
+    /// Adds the latency of a completed operation to the average.
+    ///
+    /// The operations waiting for a file to become ready are not sampled:
+    /// see [`waits_for_readiness`].
+    pub(crate) fn record(&mut self, latency: Duration) {
+        // An operation stalled in the kernel counts as slow, without
+        // keeping the average high for long after.
+        let latency = latency.min(self.policy.latency_target * 4);
+        self.latency = (self.latency * 7 + latency) / 8;
+    }
+
+    /// Returns `true` if the queue, holding `queued` entries, is to be
+    /// submitted now, with `in_flight` operations in flight.
+    pub(crate) fn flushes_early(&self, queued: usize, in_flight: usize) -> bool {
+        if !self.policy.adaptive {
+            return false;
+        }
+        queued >= self.policy.max_batch
+            || (in_flight <= self.policy.shallow_depth
+                && self.latency <= self.policy.latency_target)
+    }
+}
+
+/// Returns `true` for the opcodes of operations waiting for events, such as
+/// connections or data arriving on sockets, which can take arbitrarily long
+/// whatever the load of the kernel.
+///
+/// Reads of sockets, submitted with the opcodes of file reads, are told by
+/// [`Completable::waits_for_readiness`] instead.
+///
+/// [`Completable::waits_for_readiness`]: super::op::Completable::waits_for_readiness
+pub(crate) fn waits_for_readiness(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        opcode::Accept::CODE
+            | opcode::Connect::CODE
+            | opcode::Recv::CODE
+            | opcode::RecvMsg::CODE
+            | opcode::Send::CODE
+            | opcode::SendMsg::CODE
+            | opcode::SendZc::CODE
+            | opcode::SendMsgZc::CODE
+            | opcode::PollAdd::CODE
+            | opcode::Timeout::CODE
+    )
+}
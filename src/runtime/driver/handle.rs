@@ -12,15 +12,22 @@
 //! The weak handle should be used by anything which is stored in the driver or does not need to
 //! keep the driver alive for it's duration.
 
-use io_uring::{cqueue, squeue};
+use io_uring::{cqueue, opcode, squeue, types, IoUring};
 use std::cell::RefCell;
 use std::io;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::rc::{Rc, Weak};
 use std::task::{Context, Poll};
-
-use crate::buf::fixed::FixedBuffers;
-use crate::runtime::driver::op::{Completable, Lifecycle, MultiCQEFuture, Op, Updateable};
+use std::time::Instant;
+
+use crate::buf::fixed::{registration_error, FixedBufPool, FixedBuffers};
+use crate::runtime::driver::inflight::{InflightOp, OpInfo};
+use crate::runtime::driver::op::{
+    discard, Completable, CqeKind, Lifecycle, MultiCQEFuture, MultiCQEStream, Op, Streamable,
+    Updateable,
+};
+use crate::runtime::driver::personality::Personality;
+use crate::runtime::driver::priority::Priority;
 use crate::runtime::driver::Driver;
 
 #[derive(Clone)]
@@ -40,12 +47,79 @@ impl Handle {
         })
     }
 
+    /// Returns `true` if both handles refer to the same driver.
+    pub(crate) fn ptr_eq(&self, other: &Handle) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
     pub(crate) fn tick(&self) {
         self.inner.borrow_mut().tick()
     }
 
-    pub(crate) fn flush(&self) -> io::Result<usize> {
-        self.inner.borrow_mut().uring.submit()
+    pub(crate) fn shutdown(&self, deadline: Instant) {
+        self.inner.borrow_mut().shutdown(deadline)
+    }
+
+    pub(crate) fn dump_inflight(&self) -> Vec<InflightOp> {
+        self.inner.borrow().dump_inflight()
+    }
+
+    pub(crate) fn num_operations(&self) -> usize {
+        self.inner.borrow().num_operations()
+    }
+
+    pub(crate) fn cancel_scope(&self, scope: &super::cancel::Scope) {
+        // Failing to submit the cancellations leaves the operations to run
+        // to completion, which is what they would do without cancellation.
+        let _ = self.inner.borrow_mut().cancel_scope(scope);
+    }
+
+    pub(crate) fn cancel_op<T, CqeType>(&self, op: &Op<T, CqeType>) {
+        let mut driver = self.inner.borrow_mut();
+        // A multishot operation is still in flight while its completions
+        // are listed
+        if let Some((
+            Lifecycle::Submitted | Lifecycle::Waiting(_) | Lifecycle::CompletionList(_),
+            _,
+        )) = driver.ops.get_mut(op.index)
+        {
+            // Failing to submit the cancellation leaves the operation to run
+            // to completion.
+            let _ = driver.cancel_op(op.index);
+        }
+    }
+
+    #[cfg(feature = "fallback")]
+    pub(crate) fn is_fallback(&self) -> bool {
+        self.inner.borrow().is_fallback()
+    }
+
+    pub(crate) fn retry_notify(&self) -> Option<Rc<tokio::sync::Notify>> {
+        self.inner.borrow().retry_notify()
+    }
+
+    pub(crate) fn resubmit_due(&self) -> Option<tokio::time::Instant> {
+        self.inner.borrow_mut().resubmit_due()
+    }
+
+    pub(crate) fn has_pending_ops(&self) -> bool {
+        self.inner.borrow().has_pending_ops()
+    }
+
+    pub(crate) fn begin_batch(&self) {
+        self.inner.borrow_mut().begin_batch()
+    }
+
+    pub(crate) fn end_batch(&self) -> io::Result<()> {
+        self.inner.borrow_mut().end_batch()
+    }
+
+    pub(crate) fn park(&self) -> io::Result<()> {
+        self.inner.borrow_mut().park()
+    }
+
+    pub(crate) fn unpark(&self) {
+        self.inner.borrow_mut().unpark()
     }
 
     pub(crate) fn register_buffers(
@@ -54,10 +128,14 @@ impl Handle {
     ) -> io::Result<()> {
         let mut driver = self.inner.borrow_mut();
 
-        driver
-            .uring
-            .submitter()
-            .register_buffers(buffers.borrow().iovecs())?;
+        // The fallback backend uses the addresses of the buffers directly
+        if let Some(uring) = &driver.uring {
+            let buffers = buffers.borrow();
+            uring
+                .submitter()
+                .register_buffers(buffers.iovecs())
+                .map_err(|e| registration_error(e, buffers.iovecs()))?;
+        }
 
         driver.fixed_buffers = Some(buffers);
         Ok(())
@@ -71,17 +149,58 @@ impl Handle {
 
         if let Some(currently_registered) = &driver.fixed_buffers {
             if Rc::ptr_eq(&buffers, currently_registered) {
-                driver.uring.submitter().unregister_buffers()?;
+                if let Some(uring) = &driver.uring {
+                    uring.submitter().unregister_buffers()?;
+                }
                 driver.fixed_buffers = None;
                 return Ok(());
             }
         }
-        Err(io::Error::new(
-            io::ErrorKind::Other,
+        Err(io::Error::other(
             "fixed buffers are not currently registered",
         ))
     }
 
+    pub(crate) fn set_default_pool(&self, pool: Option<FixedBufPool>) -> Option<FixedBufPool> {
+        std::mem::replace(&mut self.inner.borrow_mut().default_pool, pool)
+    }
+
+    pub(crate) fn default_pool(&self) -> Option<FixedBufPool> {
+        self.inner.borrow().default_pool.clone()
+    }
+
+    pub(crate) fn register_personality(&self) -> io::Result<Personality> {
+        match &self.inner.borrow().uring {
+            Some(uring) => Ok(Personality(uring.submitter().register_personality()?)),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "personalities are not supported by the fallback backend",
+            )),
+        }
+    }
+
+    pub(crate) fn unregister_personality(&self, personality: Personality) -> io::Result<()> {
+        match &self.inner.borrow().uring {
+            Some(uring) => uring.submitter().unregister_personality(personality.0),
+            None => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+        }
+    }
+
+    pub(crate) fn supports_opcode(&self, opcode: u8) -> bool {
+        self.inner.borrow().supports_opcode(opcode)
+    }
+
+    pub(crate) unsafe fn with_ring<R>(&self, f: impl FnOnce(&IoUring) -> R) -> io::Result<R> {
+        let driver = self.inner.borrow_mut();
+        match &driver.uring {
+            Some(uring) => Ok(f(uring)),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "io_uring is not available",
+            )),
+        }
+    }
+
     /// Submit an operation to uring.
     ///
     /// `state` is stored during the operation tracking any state submitted to
@@ -89,26 +208,183 @@ impl Handle {
     pub(crate) fn submit_op<T, S, F>(&self, mut data: T, f: F) -> io::Result<Op<T, S>>
     where
         T: Completable,
+        S: CqeKind,
         F: FnOnce(&mut T) -> squeue::Entry,
     {
         let mut driver = self.inner.borrow_mut();
+        let overflow = driver.check_cq_overflow()?;
         let index = driver.ops.insert();
 
         // Configure the SQE
-        let sqe = f(&mut data).user_data(index as _);
+        let mut sqe = Personality::apply(f(&mut data).user_data(index as _));
+        let priority = Priority::current();
+        let mut info = OpInfo::new(&sqe);
+        if data.waits_for_readiness() {
+            info.set_waits_for_readiness();
+        }
+        let allowed = driver.allows(&sqe, &info);
+        let cancelled = info.is_cancelled();
+        let link_timeout = info
+            .link_timeout()
+            .filter(|_| driver.uses_link_timeouts())
+            .map(|timespec| {
+                opcode::LinkTimeout::new(timespec)
+                    .flags(types::TimeoutFlags::ABS)
+                    .build()
+                    .user_data(u64::MAX)
+            });
+        driver.ops.set_info(index, info);
 
         // Create the operation
         let op = Op::new(self.into(), data, index);
 
+        if let Some(errno) = overflow {
+            driver.fail_op(index, errno);
+            return Ok(op);
+        }
+        if !allowed {
+            driver.fail_op(index, libc::EPERM);
+            return Ok(op);
+        }
+        let chain = |sqe: squeue::Entry| match &link_timeout {
+            None => vec![sqe],
+            Some(timeout) => vec![sqe.flags(squeue::Flags::IO_LINK), timeout.clone()],
+        };
+        if let Some(retries) = &mut driver.retries {
+            // An operation bounded by a linked timeout is not retried, as the
+            // timeout would start over with each attempt, nor one completing
+            // multiple times, whose final error, e.g. the `ENOBUFS` ending a
+            // multishot receive, is reported to its stream.
+            if link_timeout.is_none() && S::SINGLE {
+                // The entry is kept before a fault shortens it
+                retries.track(index, &sqe, priority);
+            }
+        }
+        if let Some(errno) = driver.inject_fault(&mut sqe) {
+            driver.fail_op(index, errno);
+            return Ok(op);
+        }
+        crate::limit::charge(&sqe);
+
         // Push the new operation
-        while unsafe { driver.uring.submission().push(&sqe).is_err() } {
-            // If the submission queue is full, flush it to the kernel
-            driver.submit()?;
+        driver.push(chain(sqe), priority)?;
+
+        if cancelled {
+            driver.cancel_op(index)?;
         }
 
         Ok(op)
     }
 
+    /// Submit two operations to uring, linked so that the second one is only
+    /// started once the first one has completed successfully.
+    ///
+    /// If the first operation fails, the second one completes with
+    /// `ECANCELED`. Both entries are pushed to the submission queue together,
+    /// so they are always submitted to the kernel in the same batch.
+    pub(crate) fn submit_linked_ops<T, U, F, G>(
+        &self,
+        mut first: T,
+        f: F,
+        mut second: U,
+        g: G,
+    ) -> io::Result<(Op<T>, Op<U>)>
+    where
+        T: Completable,
+        U: Completable,
+        F: FnOnce(&mut T) -> squeue::Entry,
+        G: FnOnce(&mut U) -> squeue::Entry,
+    {
+        let mut driver = self.inner.borrow_mut();
+        let overflow = driver.check_cq_overflow()?;
+        let first_index = driver.ops.insert();
+        let second_index = driver.ops.insert();
+
+        // Configure the SQEs
+        let mut sqes = [
+            Personality::apply(
+                f(&mut first)
+                    .flags(squeue::Flags::IO_LINK)
+                    .user_data(first_index as _),
+            ),
+            Personality::apply(g(&mut second).user_data(second_index as _)),
+        ];
+        let mut info = OpInfo::new(&sqes[0]);
+        if first.waits_for_readiness() {
+            info.set_waits_for_readiness();
+        }
+        let mut second_info = OpInfo::new(&sqes[1]);
+        if second.waits_for_readiness() {
+            second_info.set_waits_for_readiness();
+        }
+        let cancelled = info.is_cancelled();
+        let link_timeout = info
+            .link_timeout()
+            .filter(|_| driver.uses_link_timeouts())
+            .map(|timespec| {
+                opcode::LinkTimeout::new(timespec)
+                    .flags(types::TimeoutFlags::ABS)
+                    .build()
+                    .user_data(u64::MAX)
+            });
+        let allowed = [
+            driver.allows(&sqes[0], &info),
+            driver.allows(&sqes[1], &second_info),
+        ];
+        driver.ops.set_info(first_index, info);
+        driver.ops.set_info(second_index, second_info);
+
+        // Create the operations
+        let ops = (
+            Op::new(self.into(), first, first_index),
+            Op::new(self.into(), second, second_index),
+        );
+
+        if let Some(errno) = overflow {
+            driver.fail_op(first_index, errno);
+            driver.fail_op(second_index, libc::ECANCELED);
+            return Ok(ops);
+        }
+        if allowed != [true, true] {
+            // Neither operation is submitted if one of them is denied
+            for &(index, allowed) in &[(first_index, allowed[0]), (second_index, allowed[1])] {
+                let errno = if allowed {
+                    libc::ECANCELED
+                } else {
+                    libc::EPERM
+                };
+                driver.fail_op(index, errno);
+            }
+            return Ok(ops);
+        }
+        // An injected failure of either operation fails the first one, and
+        // cancels the second one as the kernel would
+        let fault = driver.inject_fault(&mut sqes[0]);
+        if let Some(errno) = fault.or_else(|| driver.inject_fault(&mut sqes[1])) {
+            driver.fail_op(first_index, errno);
+            driver.fail_op(second_index, libc::ECANCELED);
+            return Ok(ops);
+        }
+        crate::limit::charge(&sqes[0]);
+        crate::limit::charge(&sqes[1]);
+
+        // Push the linked entries, the timeout, if any, bounding the chain
+        // from its end
+        let mut chain = sqes.to_vec();
+        if let Some(timeout) = link_timeout {
+            chain[1] = chain[1].clone().flags(squeue::Flags::IO_LINK);
+            chain.push(timeout);
+        }
+        driver.push(chain, Priority::current())?;
+
+        if cancelled {
+            // The second operation is canceled along with the first one
+            driver.cancel_op(first_index)?;
+        }
+
+        Ok(ops)
+    }
+
     pub(crate) fn poll_op<T>(&self, op: &mut Op<T>, cx: &mut Context<'_>) -> Poll<T::Output>
     where
         T: Unpin + 'static + Completable,
@@ -215,6 +491,66 @@ impl Handle {
         }
     }
 
+    pub(crate) fn poll_stream_op<T>(
+        &self,
+        op: &mut Op<T, MultiCQEStream>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<T::Output>>
+    where
+        T: Unpin + 'static + Streamable,
+    {
+        use std::mem;
+
+        let mut driver = self.inner.borrow_mut();
+
+        let (lifecycle, completions) = match driver.ops.get_mut(op.index) {
+            Some(val) => val,
+            // The final completion has been returned
+            None => return Poll::Ready(None),
+        };
+
+        match mem::replace(lifecycle, Lifecycle::Submitted) {
+            Lifecycle::Submitted => {
+                *lifecycle = Lifecycle::Waiting(cx.waker().clone());
+                Poll::Pending
+            }
+            Lifecycle::Waiting(waker) if !waker.will_wake(cx.waker()) => {
+                *lifecycle = Lifecycle::Waiting(cx.waker().clone());
+                Poll::Pending
+            }
+            Lifecycle::Waiting(waker) => {
+                *lifecycle = Lifecycle::Waiting(waker);
+                Poll::Pending
+            }
+            Lifecycle::Ignored(..) => unreachable!(),
+            Lifecycle::Completed(cqe) => {
+                driver.ops.remove(op.index);
+                op.index = usize::MAX;
+                Poll::Ready(Some(op.data.take().unwrap().complete(cqe)))
+            }
+            Lifecycle::CompletionList(indices) => {
+                // Take the completions one at a time. Once the list is
+                // empty, the op is back to the submitted state
+                let cqe = {
+                    let mut list = indices.into_list(completions);
+                    let cqe = list.pop().expect("invalid internal state");
+                    if !list.is_empty() {
+                        *lifecycle = Lifecycle::CompletionList(list.into_indices());
+                    }
+                    cqe
+                };
+                if cqueue::more(cqe.flags) {
+                    Poll::Ready(Some(op.data.as_mut().unwrap().next(cqe)))
+                } else {
+                    // The final completion is the last one of the list
+                    driver.ops.remove(op.index);
+                    op.index = usize::MAX;
+                    Poll::Ready(Some(op.data.take().unwrap().complete(cqe)))
+                }
+            }
+        }
+    }
+
     pub(crate) fn remove_op<T, CqeType>(&self, op: &mut Op<T, CqeType>) {
         use std::mem;
 
@@ -232,20 +568,35 @@ impl Handle {
         match mem::replace(lifecycle, Lifecycle::Submitted) {
             Lifecycle::Submitted | Lifecycle::Waiting(_) => {
                 *lifecycle = Lifecycle::Ignored(Box::new(op.data.take()));
+                driver.ops.ignored += 1;
+                if driver.cancel_on_drop {
+                    // If the cancellation cannot be submitted, the operation
+                    // runs to completion as it would without it.
+                    let _ = driver.cancel_op(op.index);
+                }
             }
-            Lifecycle::Completed(..) => {
+            Lifecycle::Completed(cqe) => {
+                discard(&op.data, &cqe);
                 driver.ops.remove(op.index);
             }
             Lifecycle::CompletionList(indices) => {
                 // Deallocate list entries, recording if more CQE's are expected
                 let more = {
                     let mut list = indices.into_list(completions);
-                    cqueue::more(list.peek_end().unwrap().flags)
-                    // Dropping list deallocates the list entries
+                    let more = cqueue::more(list.peek_end().unwrap().flags);
+                    // Consuming list deallocates the list entries
+                    for cqe in list {
+                        discard(&op.data, &cqe);
+                    }
+                    more
                 };
                 if more {
                     // If more are expected, we have to keep the op around
                     *lifecycle = Lifecycle::Ignored(Box::new(op.data.take()));
+                    driver.ops.ignored += 1;
+                    if driver.cancel_on_drop {
+                        let _ = driver.cancel_op(op.index);
+                    }
                 } else {
                     driver.ops.remove(op.index);
                 }
@@ -261,11 +612,20 @@ impl WeakHandle {
             inner: self.inner.upgrade()?,
         })
     }
+
+    /// Returns `true` if the handle refers to the driver of the runtime of
+    /// the current thread.
+    pub(crate) fn is_current(&self) -> bool {
+        crate::runtime::CONTEXT.with(|x| {
+            x.handle()
+                .is_some_and(|handle| Rc::as_ptr(&handle.inner) == self.inner.as_ptr())
+        })
+    }
 }
 
 impl AsRawFd for Handle {
     fn as_raw_fd(&self) -> RawFd {
-        self.inner.borrow().uring.as_raw_fd()
+        self.inner.borrow().as_raw_fd()
     }
 }
 
@@ -0,0 +1,202 @@
+use crate::runtime::driver::SqeHeader;
+use io_uring::squeue;
+use std::ffi::CStr;
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Callback invoked by the driver on a submission event.
+pub(crate) type SubmitHook = Arc<dyn Fn(&SubmitStats) + Send + Sync>;
+
+/// Callback invoked by the driver on an event of its loop.
+pub(crate) type TickHook = Arc<dyn Fn(&TickStats) + Send + Sync>;
+
+/// Callback deciding whether an operation is submitted.
+pub(crate) type OpInspector = Arc<dyn Fn(&SqeInfo<'_>) -> Decision + Send + Sync>;
+
+/// Counters of the submissions of a runtime, passed to the
+/// [`on_sq_full`] and [`on_flush`] hooks.
+///
+/// [`on_sq_full`]: crate::Builder::on_sq_full
+/// [`on_flush`]: crate::Builder::on_flush
+#[derive(Clone, Debug, Default)]
+pub struct SubmitStats {
+    pub(crate) submitted: usize,
+    pub(crate) flushes: u64,
+    pub(crate) total_submitted: u64,
+    pub(crate) sq_full: u64,
+}
+
+impl SubmitStats {
+    /// Returns the number of entries submitted to the kernel by the flush
+    /// being reported, or 0 for other events.
+    pub fn submitted(&self) -> usize {
+        self.submitted
+    }
+
+    /// Returns the number of flushes which submitted entries to the kernel
+    /// since the runtime started.
+    pub fn flushes(&self) -> u64 {
+        self.flushes
+    }
+
+    /// Returns the number of entries submitted to the kernel since the
+    /// runtime started.
+    pub fn total_submitted(&self) -> u64 {
+        self.total_submitted
+    }
+
+    /// Returns the number of times an entry could not be queued because
+    /// the submission queue was full, since the runtime started.
+    pub fn sq_full(&self) -> u64 {
+        self.sq_full
+    }
+}
+
+/// Counters of the event loop of a runtime, passed to the [`on_tick`],
+/// [`on_park`] and [`on_unpark`] hooks.
+///
+/// [`on_tick`]: crate::Builder::on_tick
+/// [`on_park`]: crate::Builder::on_park
+/// [`on_unpark`]: crate::Builder::on_unpark
+#[derive(Clone, Debug, Default)]
+pub struct TickStats {
+    pub(crate) completions: usize,
+    pub(crate) submitted: usize,
+    pub(crate) park_duration: Duration,
+    pub(crate) ticks: u64,
+    pub(crate) total_completions: u64,
+    pub(crate) parks: u64,
+}
+
+impl TickStats {
+    /// Returns the number of completions processed by the tick being
+    /// reported, or 0 for other events.
+    pub fn completions(&self) -> usize {
+        self.completions
+    }
+
+    /// Returns the number of entries submitted to the kernel by the flush
+    /// preceding the park being reported, or 0 for other events.
+    pub fn submitted(&self) -> usize {
+        self.submitted
+    }
+
+    /// Returns the time the runtime thread has been parked for, for the
+    /// unpark being reported, or zero for other events.
+    pub fn park_duration(&self) -> Duration {
+        self.park_duration
+    }
+
+    /// Returns the number of times the driver has processed completions
+    /// since the runtime started.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Returns the number of completions processed since the runtime
+    /// started.
+    pub fn total_completions(&self) -> u64 {
+        self.total_completions
+    }
+
+    /// Returns the number of times the runtime thread has parked since the
+    /// runtime started.
+    pub fn parks(&self) -> u64 {
+        self.parks
+    }
+}
+
+/// An operation about to be submitted, passed to the [`op_inspector`]
+/// hook.
+///
+/// [`op_inspector`]: crate::Builder::op_inspector
+pub struct SqeInfo<'a> {
+    sqe: SqeHeader,
+    label: Option<&'a str>,
+}
+
+impl<'a> SqeInfo<'a> {
+    pub(crate) fn new(sqe: &squeue::Entry, label: Option<&'a str>) -> SqeInfo<'a> {
+        SqeInfo {
+            sqe: SqeHeader::read(sqe),
+            label,
+        }
+    }
+
+    /// Returns the io_uring opcode of the operation, e.g.
+    /// `io_uring::opcode::Read::CODE`.
+    pub fn opcode(&self) -> u8 {
+        self.sqe.opcode
+    }
+
+    /// Returns the file descriptor the operation is performed on.
+    ///
+    /// If [`is_fixed_fd`] returns `true`, this is an index into the table of
+    /// registered files rather than a file descriptor. For operations not
+    /// performed on a file descriptor, this is -1, or `AT_FDCWD` for
+    /// operations on paths relative to the current directory.
+    ///
+    /// [`is_fixed_fd`]: SqeInfo::is_fixed_fd
+    pub fn fd(&self) -> RawFd {
+        self.sqe.fd
+    }
+
+    /// Returns `true` if the operation is performed on a registered file.
+    pub fn is_fixed_fd(&self) -> bool {
+        self.sqe.flags & squeue::Flags::FIXED_FILE.bits() != 0
+    }
+
+    /// Returns the path of an operation on a path, such as opening a file.
+    ///
+    /// For a rename or a link, this is the path of the existing file, and
+    /// [`target_path`] returns the new path.
+    ///
+    /// [`target_path`]: SqeInfo::target_path
+    pub fn path(&self) -> Option<&CStr> {
+        // Safety: the operation has not been submitted yet.
+        unsafe { self.sqe.path() }
+    }
+
+    /// Returns the new path of a rename or a link.
+    ///
+    /// A policy on the paths written to must check both paths of these
+    /// operations: a file renamed into a directory is written there.
+    pub fn target_path(&self) -> Option<&CStr> {
+        // Safety: the operation has not been submitted yet.
+        unsafe { self.sqe.target_path() }
+    }
+
+    /// Returns the label of the operation, set with [`with_op_label`].
+    ///
+    /// [`with_op_label`]: crate::with_op_label
+    pub fn label(&self) -> Option<&str> {
+        self.label
+    }
+}
+
+impl std::fmt::Debug for SqeInfo<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqeInfo")
+            .field("opcode", &self.opcode())
+            .field("fd", &self.fd())
+            .field("fixed_fd", &self.is_fixed_fd())
+            .field("path", &self.path())
+            .field("target_path", &self.target_path())
+            .field("label", &self.label)
+            .finish()
+    }
+}
+
+/// The decision of the [`op_inspector`] hook on an operation.
+///
+/// [`op_inspector`]: crate::Builder::op_inspector
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Decision {
+    /// The operation is submitted.
+    Allow,
+
+    /// The operation is not submitted, and fails with `EPERM`.
+    Deny,
+}
@@ -0,0 +1,375 @@
+use crate::runtime::driver::cancel::Scope;
+use io_uring::{opcode, squeue, types};
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::fmt;
+use std::future::{poll_fn, Future};
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    // Label of the operations submitted by the future being polled.
+    static LABEL: RefCell<Option<Rc<str>>> = const { RefCell::new(None) };
+}
+
+/// Information recorded when an operation is submitted.
+pub(crate) struct OpInfo {
+    opcode: u8,
+    flags: u8,
+    fd: RawFd,
+    submitted: Instant,
+    label: Option<Rc<str>>,
+    scope: Option<Rc<Scope>>,
+    waits_for_readiness: bool,
+}
+
+impl OpInfo {
+    pub(crate) fn new(sqe: &squeue::Entry) -> OpInfo {
+        let header = SqeHeader::read(sqe);
+        OpInfo {
+            opcode: header.opcode,
+            flags: header.flags,
+            fd: header.fd,
+            submitted: Instant::now(),
+            label: LABEL.with(|label| label.borrow().clone()),
+            scope: Scope::current(),
+            waits_for_readiness: super::flush::waits_for_readiness(header.opcode),
+        }
+    }
+
+    /// Records that the operation waits for its file to become ready,
+    /// which its opcode does not tell.
+    pub(crate) fn set_waits_for_readiness(&mut self) {
+        self.waits_for_readiness = true;
+    }
+
+    /// Returns `true` if the operation waits for its file to become ready,
+    /// so that the time it takes does not measure the load of the kernel.
+    pub(crate) fn waits_for_readiness(&self) -> bool {
+        self.waits_for_readiness
+    }
+
+    /// Returns the opcode of the operation.
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub(crate) fn opcode(&self) -> u8 {
+        self.opcode
+    }
+
+    /// Returns the time elapsed since the operation was submitted.
+    pub(crate) fn age(&self) -> Duration {
+        self.submitted.elapsed()
+    }
+
+    /// Returns the label of the operation.
+    pub(crate) fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns `true` if the operation was submitted in a cancelled scope.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.scope.as_ref().is_some_and(|s| s.is_cancelled())
+    }
+
+    /// Returns `true` if the deadline of a scope the operation was
+    /// submitted in has passed.
+    pub(crate) fn is_timed_out(&self) -> bool {
+        self.scope.as_ref().is_some_and(|s| s.is_timed_out())
+    }
+
+    /// Returns the deadline of the linked timeout to submit with the
+    /// operation, if any.
+    pub(crate) fn link_timeout(&self) -> Option<&types::Timespec> {
+        self.scope.as_ref()?.link_timeout()
+    }
+
+    /// Returns `true` if the operation was submitted in `scope`, or in a
+    /// scope nested in it.
+    pub(crate) fn is_within(&self, scope: &Scope) -> bool {
+        self.scope.as_ref().is_some_and(|s| s.is_within(scope))
+    }
+
+    pub(crate) fn inflight_op(&self, abandoned: bool) -> InflightOp {
+        InflightOp {
+            opcode: self.opcode,
+            fd: self.fd,
+            fixed_fd: self.flags & squeue::Flags::FIXED_FILE.bits() != 0,
+            age: self.age(),
+            label: self.label.clone(),
+            abandoned,
+        }
+    }
+}
+
+/// The common fields of a submission queue entry.
+pub(crate) struct SqeHeader {
+    pub(crate) opcode: u8,
+    pub(crate) flags: u8,
+    pub(crate) fd: RawFd,
+    #[cfg_attr(
+        not(any(feature = "test-util", feature = "fallback")),
+        allow(dead_code)
+    )]
+    pub(crate) off: u64,
+    pub(crate) addr: u64,
+    pub(crate) len: u32,
+    #[cfg_attr(not(any(feature = "sim", feature = "fallback")), allow(dead_code))]
+    pub(crate) op_flags: u32,
+    pub(crate) user_data: u64,
+    #[cfg_attr(not(feature = "fallback"), allow(dead_code))]
+    pub(crate) fd_in: RawFd,
+}
+
+impl SqeHeader {
+    /// Returns the path of an operation on a path, such as opening a file.
+    ///
+    /// For a rename or a link, this is the path of the existing file.
+    ///
+    /// # Safety
+    ///
+    /// The operation of the entry must not have completed, so that the
+    /// path it refers to is still valid.
+    pub(crate) unsafe fn path(&self) -> Option<&CStr> {
+        match self.opcode {
+            opcode::OpenAt::CODE
+            | opcode::OpenAt2::CODE
+            | opcode::Statx::CODE
+            | opcode::UnlinkAt::CODE
+            | opcode::MkDirAt::CODE
+            | opcode::RenameAt::CODE
+            | opcode::LinkAt::CODE => Some(CStr::from_ptr(self.addr as *const libc::c_char)),
+            _ => None,
+        }
+    }
+
+    /// Returns the second path of an operation on two paths: the new path
+    /// of a rename or a link.
+    ///
+    /// # Safety
+    ///
+    /// As for [`path`], the operation of the entry must not have completed.
+    ///
+    /// [`path`]: SqeHeader::path
+    pub(crate) unsafe fn target_path(&self) -> Option<&CStr> {
+        match self.opcode {
+            // The second path is in `addr2`, which shares the field of the offset
+            opcode::RenameAt::CODE | opcode::LinkAt::CODE => {
+                Some(CStr::from_ptr(self.off as *const libc::c_char))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn read(sqe: &squeue::Entry) -> SqeHeader {
+        // The io-uring crate does not expose the fields of an entry, but
+        // `squeue::Entry` is a `repr(C)` wrapper of the kernel's
+        // `struct io_uring_sqe`, which starts with:
+        //
+        //     __u8  opcode;
+        //     __u8  flags;
+        //     __u16 ioprio;
+        //     __s32 fd;
+        //     __u64 off;
+        //     __u64 addr;
+        //     __u32 len;
+        //     __u32 op_flags;
+        //     __u64 user_data;
+        //     __u16 buf_index;
+        //     __u16 personality;
+        //     __s32 splice_fd_in;
+        let bytes = sqe as *const squeue::Entry as *const u8;
+        unsafe {
+            SqeHeader {
+                opcode: *bytes,
+                flags: *bytes.add(1),
+                fd: (bytes.add(4) as *const RawFd).read_unaligned(),
+                off: (bytes.add(8) as *const u64).read_unaligned(),
+                addr: (bytes.add(16) as *const u64).read_unaligned(),
+                len: (bytes.add(24) as *const u32).read_unaligned(),
+                op_flags: (bytes.add(28) as *const u32).read_unaligned(),
+                user_data: (bytes.add(32) as *const u64).read_unaligned(),
+                fd_in: (bytes.add(44) as *const RawFd).read_unaligned(),
+            }
+        }
+    }
+
+    /// Builds an entry of an operation the io-uring crate has no builder
+    /// for, from the fields used by most operations.
+    pub(crate) fn build(opcode: u8, fd: RawFd, off: u64, addr: u64, len: u32) -> squeue::Entry {
+        // Start from an entry with the other fields zeroed. The layout is
+        // described in `read`.
+        let mut sqe = io_uring::opcode::Nop::new().build();
+        let bytes = &mut sqe as *mut squeue::Entry as *mut u8;
+        unsafe {
+            *bytes = opcode;
+            (bytes.add(4) as *mut RawFd).write_unaligned(fd);
+            (bytes.add(8) as *mut u64).write_unaligned(off);
+            (bytes.add(16) as *mut u64).write_unaligned(addr);
+            (bytes.add(24) as *mut u32).write_unaligned(len);
+        }
+        sqe
+    }
+
+    /// Sets the flags specific to the opcode of an entry built with
+    /// [`build`].
+    ///
+    /// [`build`]: SqeHeader::build
+    pub(crate) fn set_op_flags(mut sqe: squeue::Entry, flags: u32) -> squeue::Entry {
+        // The layout is described in `read`
+        let bytes = &mut sqe as *mut squeue::Entry as *mut u8;
+        unsafe { (bytes.add(28) as *mut u32).write_unaligned(flags) };
+        sqe
+    }
+
+    /// Sets the buffer group an entry built with [`build`] selects its
+    /// buffer from, along with the `BUFFER_SELECT` flag.
+    ///
+    /// [`build`]: SqeHeader::build
+    pub(crate) fn set_buf_group(sqe: squeue::Entry, group: u16) -> squeue::Entry {
+        // The layout is described in `read`
+        let mut sqe = sqe.flags(squeue::Flags::BUFFER_SELECT);
+        let bytes = &mut sqe as *mut squeue::Entry as *mut u8;
+        unsafe { (bytes.add(40) as *mut u16).write_unaligned(group) };
+        sqe
+    }
+
+    /// Sets the `ioprio` field of an entry, which some operations use for
+    /// flags of their own, such as those of sends and receives.
+    pub(crate) fn set_ioprio(mut sqe: squeue::Entry, ioprio: u16) -> squeue::Entry {
+        // The layout is described in `read`
+        let bytes = &mut sqe as *mut squeue::Entry as *mut u8;
+        unsafe { (bytes.add(2) as *mut u16).write_unaligned(ioprio) };
+        sqe
+    }
+
+    /// Sets the length of an entry, such as the size of the buffer of a
+    /// read or write.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn set_len(sqe: &mut squeue::Entry, len: u32) {
+        // The layout is described in `read`
+        let bytes = sqe as *mut squeue::Entry as *mut u8;
+        unsafe { (bytes.add(24) as *mut u32).write_unaligned(len) }
+    }
+}
+
+/// An operation submitted to the kernel which has not completed yet,
+/// as returned by [`Handle::dump_inflight`].
+///
+/// [`Handle::dump_inflight`]: crate::Handle::dump_inflight
+#[derive(Clone)]
+pub struct InflightOp {
+    opcode: u8,
+    fd: RawFd,
+    fixed_fd: bool,
+    age: Duration,
+    label: Option<Rc<str>>,
+    abandoned: bool,
+}
+
+impl InflightOp {
+    /// Returns the io_uring opcode of the operation, as defined by the
+    /// `IORING_OP_*` constants of the kernel.
+    pub fn opcode(&self) -> u8 {
+        self.opcode
+    }
+
+    /// Returns the file descriptor the operation was submitted on, if any.
+    ///
+    /// If [`is_fixed_fd`] returns `true`, this is an index into the table of
+    /// registered files rather than a file descriptor.
+    ///
+    /// [`is_fixed_fd`]: InflightOp::is_fixed_fd
+    pub fn fd(&self) -> Option<RawFd> {
+        if self.fd < 0 {
+            None
+        } else {
+            Some(self.fd)
+        }
+    }
+
+    /// Returns `true` if the operation refers to a registered file.
+    pub fn is_fixed_fd(&self) -> bool {
+        self.fixed_fd
+    }
+
+    /// Returns the time elapsed since the operation was submitted.
+    pub fn age(&self) -> Duration {
+        self.age
+    }
+
+    /// Returns the label of the operation, set with [`with_op_label`].
+    ///
+    /// [`with_op_label`]: crate::with_op_label
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns `true` if the future of the operation has been dropped, and
+    /// the runtime is waiting for the operation to complete in order to
+    /// release its resources.
+    pub fn is_abandoned(&self) -> bool {
+        self.abandoned
+    }
+}
+
+impl fmt::Debug for InflightOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InflightOp")
+            .field("opcode", &self.opcode)
+            .field("fd", &self.fd())
+            .field("fixed_fd", &self.fixed_fd)
+            .field("age", &self.age)
+            .field("label", &self.label())
+            .field("abandoned", &self.abandoned)
+            .finish()
+    }
+}
+
+/// Runs a future, labeling the operations it submits.
+///
+/// The label is reported for the operations in the list returned by
+/// [`Handle::dump_inflight`], which helps to find out what a task is
+/// waiting for when it does not make progress. Labels of nested calls
+/// replace the outer labels. The operations of tasks spawned by the future
+/// are not labeled.
+///
+/// [`Handle::dump_inflight`]: crate::Handle::dump_inflight
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::File;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let file = File::open("hello.txt").await?;
+///
+///         let (res, buf) = tokio_uring::with_op_label("read header", async {
+///             file.read_at(vec![0; 512], 0).await
+///         })
+///         .await;
+///         res?;
+///
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn with_op_label<F: Future>(label: impl Into<Rc<str>>, future: F) -> F::Output {
+    let label = Some(label.into());
+    tokio::pin!(future);
+
+    poll_fn(|cx| {
+        let _restore = RestoreLabel(LABEL.with(|current| current.replace(label.clone())));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+// Restores the previous label when the labeled future returns from poll.
+struct RestoreLabel(Option<Rc<str>>);
+
+impl Drop for RestoreLabel {
+    fn drop(&mut self) {
+        LABEL.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
@@ -1,29 +1,142 @@
-use crate::buf::fixed::FixedBuffers;
+use crate::buf::fixed::{FixedBufPool, FixedBuffers};
 use crate::runtime::driver::op::Lifecycle;
+use crate::CqOverflow;
 use io_uring::opcode::AsyncCancel;
-use io_uring::IoUring;
+use io_uring::{squeue, types, IoUring};
 use slab::Slab;
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::io;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::rc::Rc;
-
+use std::time::{Duration, Instant};
+
+pub use batch::Batch;
+#[doc(hidden)]
+pub use batch::{submit_together, MaybeDone};
+pub(crate) use budget::budgeted;
+pub use cancel::with_cancellation;
+pub(crate) use cancel::{scoped, Scope};
+pub use flush::FlushPolicy;
 pub(crate) use handle::*;
-
+pub use hooks::{Decision, SqeInfo, SubmitStats, TickStats};
+pub(crate) use hooks::{OpInspector, SubmitHook, TickHook};
+pub(crate) use inflight::SqeHeader;
+pub use inflight::{with_op_label, InflightOp};
+pub use personality::{with_personality, Personality};
+pub use priority::{with_priority, Priority};
+pub use retry::RetryPolicy;
+
+// Not exported by the io-uring crate.
+const IORING_ENTER_GETEVENTS: u32 = 1;
+
+mod batch;
+mod budget;
+mod cancel;
+#[cfg(feature = "fallback")]
+mod fallback;
+mod flush;
 mod handle;
+mod hooks;
+mod inflight;
 pub(crate) mod op;
+mod personality;
+mod priority;
+mod retry;
 
 pub(crate) struct Driver {
     /// In-flight operations
     ops: Ops,
 
-    /// IoUring bindings
-    pub(crate) uring: IoUring,
+    /// IoUring bindings, absent if the operations are performed by the
+    /// fallback or the mock backend
+    pub(crate) uring: Option<IoUring>,
+
+    /// Opcodes supported by the kernel, probed when the runtime starts.
+    /// Absent without a ring, the backends replacing it performing any
+    /// operation they are passed.
+    probe: Option<io_uring::Probe>,
 
     /// Reference to the currently registered buffers.
     /// Ensures that the buffers are not dropped until
     /// after the io-uring runtime has terminated.
     pub(crate) fixed_buffers: Option<Rc<RefCell<dyn FixedBuffers>>>,
+
+    /// Pool the pooled I/O methods check buffers out of
+    pub(crate) default_pool: Option<FixedBufPool>,
+
+    /// Policy for new operations while the completion queue is overflown
+    cq_overflow: CqOverflow,
+
+    /// Number of submitted entries whose completions have not been reaped,
+    /// tracked if the kernel drops the completions overflowing the
+    /// completion queue (without `IORING_FEAT_NODROP`). No more entries are
+    /// then submitted than the queue has room for the completions of.
+    unreaped: Option<usize>,
+
+    /// Whether partial submissions are retried
+    retry_partial_submit: bool,
+
+    /// Whether operations are canceled when their futures are dropped
+    pub(crate) cancel_on_drop: bool,
+
+    /// Entries of bulk operations, held back until the next flush. Each
+    /// element is a chain of linked entries.
+    deferred: VecDeque<Vec<squeue::Entry>>,
+
+    /// Number of batches being collected, and the chains of entries queued
+    /// by their operations, submitted together when the outermost batch ends
+    batch_depth: usize,
+    batched: Vec<Vec<squeue::Entry>>,
+
+    /// Whether latency-critical operations are submitted immediately
+    flush_latency_critical: bool,
+
+    /// Number of entries a task can queue for submission in a poll
+    task_sqe_budget: Option<u32>,
+
+    /// Policy submitting the queued entries before the runtime parks
+    flusher: flush::Flusher,
+
+    /// Submission counters, and the hooks they are reported to
+    stats: SubmitStats,
+    on_sq_full: Option<SubmitHook>,
+    on_flush: Option<SubmitHook>,
+
+    /// Counters of the event loop, and the hooks they are reported to
+    tick_stats: TickStats,
+    on_tick: Option<TickHook>,
+    on_park: Option<TickHook>,
+    on_unpark: Option<TickHook>,
+    parked_at: Option<Instant>,
+
+    /// Hook deciding whether operations are submitted
+    op_inspector: Option<OpInspector>,
+
+    /// Operations submitted again when they fail with transient errors
+    pub(crate) retries: Option<retry::Retries>,
+
+    /// Handler completing the operations instead of the kernel
+    #[cfg(feature = "test-util")]
+    mock: Option<crate::mock::MockHandler>,
+
+    /// Event polled by a mocked runtime in place of the ring. The mock
+    /// backend completes the operations as they are pushed or canceled, so
+    /// it is never signaled.
+    #[cfg(feature = "test-util")]
+    mock_fd: Option<std::os::unix::io::OwnedFd>,
+
+    /// Whether the clock of the runtime is virtual
+    #[cfg(feature = "test-util")]
+    start_paused: bool,
+
+    /// Faults injected into the operations
+    #[cfg(feature = "test-util")]
+    faults: Option<crate::fault::Injector>,
+
+    /// Thread pool performing the operations if io_uring is not available
+    #[cfg(feature = "fallback")]
+    fallback: Option<fallback::Fallback>,
 }
 
 struct Ops {
@@ -33,34 +146,249 @@ struct Ops {
 
     /// Received but unserviced Op completions
     completions: Slab<op::Completion>,
+
+    /// Information on the submitted operations, by lifecycle index
+    info: Vec<Option<inflight::OpInfo>>,
+
+    /// Number of operations in flight, and of those among them whose
+    /// futures have been dropped
+    in_flight: usize,
+    ignored: usize,
 }
 
 impl Driver {
     pub(crate) fn new(b: &crate::Builder) -> io::Result<Driver> {
-        let uring = b.urb.build(b.entries)?;
+        // No operations are submitted to the kernel by a mocked runtime,
+        // which so runs where io_uring is not available
+        #[cfg(feature = "test-util")]
+        if b.mock.is_some() {
+            return Driver::with_ring(b, None);
+        }
+
+        #[cfg(feature = "fallback")]
+        if b.force_fallback {
+            return Driver::with_ring(b, None);
+        }
+
+        let uring = match Driver::setup_ring(b) {
+            Ok(uring) => uring,
+            // Fixed files cannot be emulated, the runtime needs a ring then
+            #[cfg(feature = "fallback")]
+            Err(e) if fallback::is_unavailable(&e) && b.file_table.is_none() => {
+                return Driver::with_ring(b, None);
+            }
+            Err(e) => return Err(e),
+        };
+        Driver::with_ring(b, Some(uring))
+    }
+
+    fn setup_ring(b: &crate::Builder) -> io::Result<IoUring> {
+        let uring = match &b.ring {
+            Some(ring) => ring.lock().unwrap().take().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the ring passed to Builder::with_ring is used by another runtime",
+                )
+            })?,
+            None => {
+                let mut urb = b.urb.clone();
+                if let Some(cq_entries) = b.cq_entries {
+                    urb.setup_cqsize(cq_entries);
+                }
+                if b.submit_all {
+                    urb.setup_submit_all();
+                }
+                urb.build(b.entries)?
+            }
+        };
+
+        if !uring.params().is_feature_nodrop() && uring.params().is_setup_sqpoll() {
+            // Without the overflow backlog, the completions overflowing the
+            // queue are lost. The driver holds back the entries which could
+            // overflow it, which the kernel thread polling the submission
+            // queue would take anyway.
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "IORING_SETUP_SQPOLL requires IORING_FEAT_NODROP",
+            ));
+        }
+
+        if let Some(size) = b.file_table {
+            uring.submitter().register_files_sparse(size)?;
+        }
+
+        Ok(uring)
+    }
+
+    fn with_ring(b: &crate::Builder, uring: Option<IoUring>) -> io::Result<Driver> {
+        #[cfg(feature = "fallback")]
+        let fallback = match uring {
+            Some(_) => None,
+            #[cfg(feature = "test-util")]
+            None if b.mock.is_some() => None,
+            None if b.file_table.is_some() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the fallback backend does not support a table of registered files",
+                ));
+            }
+            None => Some(fallback::Fallback::new()?),
+        };
+
+        // Kernels before Linux 5.6 cannot be probed, and support none of
+        // the operations the probe is consulted on
+        let probe = uring.as_ref().map(|uring| {
+            let mut probe = io_uring::Probe::new();
+            match uring.submitter().register_probe(&mut probe) {
+                Ok(()) => probe,
+                Err(_) => io_uring::Probe::new(),
+            }
+        });
+
+        let unreaped = uring
+            .as_ref()
+            .filter(|uring| !uring.params().is_feature_nodrop())
+            .map(|_| 0);
 
         Ok(Driver {
             ops: Ops::new(),
             uring,
+            probe,
             fixed_buffers: None,
+            default_pool: None,
+            cq_overflow: b.cq_overflow,
+            unreaped,
+            retry_partial_submit: b.retry_partial_submit,
+            cancel_on_drop: b.cancel_on_drop,
+            deferred: VecDeque::new(),
+            batch_depth: 0,
+            batched: Vec::new(),
+            flush_latency_critical: b.flush_latency_critical,
+            task_sqe_budget: b.task_sqe_budget,
+            flusher: flush::Flusher::new(&b.flush_policy),
+            stats: SubmitStats::default(),
+            on_sq_full: b.on_sq_full.clone(),
+            on_flush: b.on_flush.clone(),
+            tick_stats: TickStats::default(),
+            on_tick: b.on_tick.clone(),
+            on_park: b.on_park.clone(),
+            on_unpark: b.on_unpark.clone(),
+            parked_at: None,
+            op_inspector: b.op_inspector.clone(),
+            retries: b.retry_policy.as_ref().map(retry::Retries::new),
+            #[cfg(feature = "test-util")]
+            mock: b.mock.clone(),
+            #[cfg(feature = "test-util")]
+            mock_fd: match b.mock {
+                Some(_) => {
+                    let fd = syscall!(eventfd(0, libc::EFD_CLOEXEC))?;
+                    // Safety: the descriptor has just been created
+                    Some(unsafe { std::os::unix::io::FromRawFd::from_raw_fd(fd) })
+                }
+                None => None,
+            },
+            #[cfg(feature = "test-util")]
+            start_paused: b.start_paused,
+            #[cfg(feature = "test-util")]
+            faults: b.fault_policy.as_ref().map(crate::fault::Injector::new),
+            #[cfg(feature = "fallback")]
+            fallback,
         })
     }
 
-    fn wait(&self) -> io::Result<usize> {
-        self.uring.submit_and_wait(1)
+    /// Returns the ring.
+    ///
+    /// Panics if the operations are performed by the fallback backend, in
+    /// which case the callers must not get here.
+    fn ring(&mut self) -> &mut IoUring {
+        self.uring.as_mut().expect("io_uring is not available")
+    }
+
+    /// Returns `true` if the deadlines of timeouts are enforced by the kernel
+    /// with linked timeouts.
+    ///
+    /// The kernel measures them on the real clock, so they are not used
+    /// with a virtual clock; the deadlines are then only enforced by
+    /// canceling the operations on the runtime's timer.
+    pub(crate) fn uses_link_timeouts(&self) -> bool {
+        #[cfg(feature = "test-util")]
+        if self.start_paused {
+            return false;
+        }
+        true
+    }
+
+    /// Returns `true` if operations with `opcode` can be submitted.
+    pub(crate) fn supports_opcode(&self, opcode: u8) -> bool {
+        self.probe
+            .as_ref()
+            .is_none_or(|probe| probe.is_supported(opcode))
+    }
+
+    /// Returns `true` if the operations are performed by the fallback
+    /// backend.
+    #[cfg(feature = "fallback")]
+    pub(crate) fn is_fallback(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    fn wait(&mut self) -> io::Result<usize> {
+        // The mock backend completes the operations it is passed, or those
+        // canceled, without waiting
+        #[cfg(feature = "test-util")]
+        if self.mock.is_some() {
+            return Ok(0);
+        }
+
+        #[cfg(feature = "fallback")]
+        if let Some(fallback) = &self.fallback {
+            fallback.wait(None)?;
+            return Ok(0);
+        }
+
+        if self.unreaped.is_some() {
+            // Submit only the entries the completion queue has room for
+            self.submit()?;
+            return unsafe {
+                self.ring()
+                    .submitter()
+                    .enter::<libc::sigset_t>(0, 1, IORING_ENTER_GETEVENTS, None)
+            };
+        }
+
+        self.ring().submit_and_wait(1)
     }
 
-    // only used in tests rn
-    #[allow(unused)]
-    pub(super) fn num_operations(&self) -> usize {
+    pub(crate) fn num_operations(&self) -> usize {
         self.ops.lifecycle.len()
     }
 
     pub(crate) fn tick(&mut self) {
-        let mut cq = self.uring.completion();
+        #[cfg(feature = "test-util")]
+        if let Some(mock) = self.mock.clone() {
+            self.complete_mocked_later(&mock);
+            return;
+        }
+
+        #[cfg(feature = "fallback")]
+        if let Some(fallback) = &mut self.fallback {
+            let mut completions = Vec::new();
+            fallback.completions(&mut completions);
+            let count = completions.len();
+            for (user_data, result) in completions {
+                self.complete(user_data as usize, op::CqeResult { result, flags: 0 });
+            }
+            self.report_tick(count);
+            return;
+        }
+
+        let uring = self.uring.as_mut().expect("io_uring is not available");
+        let mut cq = uring.completion();
         cq.sync();
 
+        let mut count = 0;
         for cqe in cq {
+            count += 1;
             if cqe.user_data() == u64::MAX {
                 // Result of the cancellation action. There isn't anything we
                 // need to do here. We must wait for the CQE for the operation
@@ -68,51 +396,129 @@ impl Driver {
                 continue;
             }
 
-            let index = cqe.user_data() as _;
+            let index: usize = cqe.user_data() as _;
 
-            self.ops.complete(index, cqe.into());
+            if self.flusher.measures() && !io_uring::cqueue::more(cqe.flags()) {
+                if let Some(info) = self.ops.info.get(index).and_then(Option::as_ref) {
+                    if !info.waits_for_readiness() {
+                        self.flusher.record(info.age());
+                    }
+                }
+            }
+            complete(&mut self.ops, self.retries.as_mut(), index, cqe.into());
+        }
+        if let Some(unreaped) = &mut self.unreaped {
+            *unreaped = unreaped.saturating_sub(count);
         }
+        self.report_tick(count);
     }
 
-    pub(crate) fn submit(&mut self) -> io::Result<()> {
-        loop {
-            match self.uring.submit() {
-                Ok(_) => {
-                    self.uring.submission().sync();
-                    return Ok(());
-                }
-                Err(ref e) if e.raw_os_error() == Some(libc::EBUSY) => {
-                    self.tick();
-                }
-                Err(e) if e.raw_os_error() != Some(libc::EINTR) => {
-                    return Err(e);
+    fn report_tick(&mut self, completions: usize) {
+        self.tick_stats.ticks += 1;
+        self.tick_stats.total_completions += completions as u64;
+        if let Some(hook) = &self.on_tick {
+            let stats = TickStats {
+                completions,
+                ..self.tick_stats.clone()
+            };
+            hook(&stats);
+        }
+    }
+
+    /// Submits the queued entries before the runtime thread parks, and
+    /// reports the park.
+    pub(crate) fn park(&mut self) -> io::Result<()> {
+        let total_submitted = self.stats.total_submitted;
+        let res = self.flush();
+        self.tick_stats.parks += 1;
+        if let Some(hook) = &self.on_park {
+            let stats = TickStats {
+                submitted: (self.stats.total_submitted - total_submitted) as usize,
+                ..self.tick_stats.clone()
+            };
+            hook(&stats);
+        }
+        if self.on_unpark.is_some() {
+            self.parked_at = Some(Instant::now());
+        }
+        res
+    }
+
+    /// Reports that the runtime thread has been unparked.
+    pub(crate) fn unpark(&mut self) {
+        if let (Some(hook), Some(parked_at)) = (&self.on_unpark, self.parked_at.take()) {
+            let stats = TickStats {
+                park_duration: parked_at.elapsed(),
+                ..self.tick_stats.clone()
+            };
+            hook(&stats);
+        }
+    }
+
+    fn complete(&mut self, index: usize, cqe: op::CqeResult) {
+        complete(&mut self.ops, self.retries.as_mut(), index, cqe)
+    }
+
+    /// Returns the notification of operations scheduled for a retry, if the
+    /// runtime has a retry policy.
+    pub(crate) fn retry_notify(&self) -> Option<Rc<tokio::sync::Notify>> {
+        self.retries.as_ref().map(retry::Retries::notify)
+    }
+
+    /// Submits again the operations due for a retry, and returns the time
+    /// the next one is due at.
+    pub(crate) fn resubmit_due(&mut self) -> Option<tokio::time::Instant> {
+        let (due, next) = self.retries.as_mut()?.take_due();
+        for mut retry in due {
+            if matches!(
+                self.ops.lifecycle.get(retry.index),
+                Some(Lifecycle::Ignored(..))
+            ) {
+                // The future has been dropped while waiting for the retry
+                self.fail_op(retry.index, retry.errno);
+                continue;
+            }
+            if let Some(errno) = self.inject_fault(&mut retry.sqe) {
+                self.fail_op(retry.index, errno);
+                continue;
+            }
+            if let Err(e) = self.push(vec![retry.sqe], retry.priority) {
+                if let Some(retries) = &mut self.retries {
+                    retries.untrack(retry.index);
                 }
-                _ => continue,
+                let cqe = op::CqeResult {
+                    result: Err(e),
+                    flags: 0,
+                };
+                self.ops.complete(retry.index, cqe);
             }
         }
+        next
     }
-}
 
-impl AsRawFd for Driver {
-    fn as_raw_fd(&self) -> RawFd {
-        self.uring.as_raw_fd()
-    }
-}
+    /// Cancels all operations in flight.
+    ///
+    /// After this, all lifecycles are either Completed or Ignored; the
+    /// Ignored ones are removed as their completions arrive.
+    fn cancel_all(&mut self) {
+        // Operations waiting for a retry are not in flight, they complete
+        // with the error they failed with
+        if let Some(retries) = &mut self.retries {
+            for index in retries.waiting() {
+                let errno = retries.take_waiting(index).unwrap();
+                let result = Err(io::Error::from_raw_os_error(errno));
+                self.ops.complete(index, op::CqeResult { result, flags: 0 });
+            }
+        }
 
-/// Drop the driver, cancelling any in-progress ops and waiting for them to terminate.
-///
-/// This first cancels all ops and then waits for them to be moved to the completed lifecycle phase.
-///
-/// It is possible for this to be run without previously dropping the runtime, but this should only
-/// be possible in the case of [`std::process::exit`].
-///
-/// This depends on us knowing when ops are completed and done firing.
-/// When multishot ops are added (support exists but none are implemented), a way to know if such
-/// an op is finished MUST be added, otherwise our shutdown process is unsound.
-impl Drop for Driver {
-    fn drop(&mut self) {
         // get all ops in flight for cancellation
-        while !self.uring.submission().is_empty() {
+        self.push_all_deferred()
+            .expect("Internal error when dropping driver");
+        while self
+            .uring
+            .as_mut()
+            .is_some_and(|uring| !uring.submission().is_empty())
+        {
             self.submit().expect("Internal error when dropping driver");
         }
 
@@ -133,34 +539,616 @@ impl Drop for Driver {
                             result: Ok(0),
                             flags: 0,
                         });
+                    } else {
+                        self.ops.ignored += 1;
                     }
                 }
 
-                _ => {
+                prev => {
                     // All other states need cancelling.
                     // The mem::replace means these are now marked Ignored.
+                    if !matches!(prev, Lifecycle::Ignored(..)) {
+                        self.ops.ignored += 1;
+                    }
                 }
             }
         }
 
+        #[cfg(feature = "test-util")]
+        if let Some(mock) = self.mock.clone() {
+            let ignored: Vec<usize> = self
+                .ops
+                .lifecycle
+                .iter()
+                .filter(|(_, cycle)| matches!(cycle, Lifecycle::Ignored(..)))
+                .map(|(id, _)| id)
+                .collect();
+            for id in ignored {
+                mock.cancel(id as u64);
+            }
+            self.complete_mocked_later(&mock);
+            return;
+        }
+
+        #[cfg(feature = "fallback")]
+        if let Some(fallback) = &self.fallback {
+            for (id, cycle) in self.ops.lifecycle.iter() {
+                if let Lifecycle::Ignored(..) = cycle {
+                    fallback.cancel(id as u64);
+                }
+            }
+            return;
+        }
+
         // Submit cancellation for all ops marked Ignored
-        for (id, cycle) in self.ops.lifecycle.iter_mut() {
-            if let Lifecycle::Ignored(..) = cycle {
-                unsafe {
-                    while self
-                        .uring
-                        .submission()
-                        .push(&AsyncCancel::new(id as u64).build().user_data(u64::MAX))
-                        .is_err()
-                    {
-                        self.uring
-                            .submit_and_wait(1)
-                            .expect("Internal error when dropping driver");
+        let ignored: Vec<usize> = self
+            .ops
+            .lifecycle
+            .iter()
+            .filter(|(_, cycle)| matches!(cycle, Lifecycle::Ignored(..)))
+            .map(|(id, _)| id)
+            .collect();
+        for id in ignored {
+            let sqe = AsyncCancel::new(id as u64).build().user_data(u64::MAX);
+            while unsafe { self.ring().submission().push(&sqe).is_err() } {
+                self.submit().expect("Internal error when dropping driver");
+            }
+        }
+    }
+
+    /// Cancels all operations in flight and waits for their completions
+    /// until the deadline.
+    ///
+    /// The resources of operations which have not completed by the deadline
+    /// are leaked, as the kernel may still access them. This allows the
+    /// driver to be dropped without waiting for them.
+    pub(crate) fn shutdown(&mut self, deadline: Instant) {
+        self.cancel_all();
+
+        loop {
+            let ignored = self
+                .ops
+                .lifecycle
+                .iter()
+                .any(|(_, cycle)| matches!(cycle, Lifecycle::Ignored(..)));
+            if !ignored {
+                break;
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                let mut leaked = Vec::new();
+                for (id, cycle) in self.ops.lifecycle.iter_mut() {
+                    if let Lifecycle::Ignored(data) = std::mem::replace(
+                        cycle,
+                        Lifecycle::Completed(op::CqeResult {
+                            result: Ok(0),
+                            flags: 0,
+                        }),
+                    ) {
+                        std::mem::forget(data);
+                        leaked.push(id);
                     }
                 }
+                // The leaked operations are no longer accounted as in flight
+                for id in leaked {
+                    self.ops.in_flight -= 1;
+                    self.ops.ignored -= 1;
+                    self.ops.clear_info(id);
+                }
+                break;
+            }
+
+            let remaining = deadline - now;
+            #[cfg(feature = "test-util")]
+            if self.mock.is_some() {
+                // Wait for the operations the mock backend completes later
+                std::thread::sleep(Duration::from_millis(1).min(remaining));
+                self.tick();
+                continue;
+            }
+
+            #[cfg(feature = "fallback")]
+            if let Some(fallback) = &self.fallback {
+                let _ = fallback.wait(Some(remaining));
+                self.tick();
+                continue;
             }
+
+            let timeout = types::Timespec::new()
+                .sec(remaining.as_secs())
+                .nsec(remaining.subsec_nanos());
+            let args = types::SubmitArgs::new().timespec(&timeout);
+            match self.ring().submitter().submit_with_args(1, &args) {
+                Ok(_) => {}
+                Err(e)
+                    if e.raw_os_error() == Some(libc::ETIME)
+                        || e.raw_os_error() == Some(libc::EINTR) => {}
+                Err(_) => {
+                    // Waiting with a timeout is not supported by the kernel,
+                    // poll for completions instead. The cancellations are
+                    // still to be submitted.
+                    let _ = self.submit();
+                    std::thread::sleep(Duration::from_millis(1).min(remaining));
+                }
+            }
+            self.tick();
+        }
+    }
+
+    /// Applies the overflow policy before a new operation is submitted.
+    /// Returns the error code the operation must fail with, if any.
+    pub(crate) fn check_cq_overflow(&mut self) -> io::Result<Option<i32>> {
+        let overflown = self
+            .uring
+            .as_mut()
+            .is_some_and(|uring| uring.submission().cq_overflow());
+        if !overflown {
+            return Ok(None);
         }
 
+        match self.cq_overflow {
+            CqOverflow::Grow => Ok(None),
+            CqOverflow::Backpressure => {
+                while self.ring().submission().cq_overflow() {
+                    self.tick();
+                    // Let the kernel move the backlog to the emptied queue,
+                    // without submitting anything.
+                    match unsafe {
+                        self.ring().submitter().enter::<libc::sigset_t>(
+                            0,
+                            0,
+                            IORING_ENTER_GETEVENTS,
+                            None,
+                        )
+                    } {
+                        Ok(_) => {}
+                        Err(e) if e.raw_os_error() == Some(libc::EINTR) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                self.tick();
+                Ok(None)
+            }
+            CqOverflow::Error => Ok(Some(libc::EBUSY)),
+        }
+    }
+
+    /// Returns `true` if the operation inspector, if any, allows the
+    /// submission of an entry.
+    pub(crate) fn allows(&self, sqe: &squeue::Entry, info: &inflight::OpInfo) -> bool {
+        match &self.op_inspector {
+            Some(inspect) => inspect(&SqeInfo::new(sqe, info.label())) == Decision::Allow,
+            None => true,
+        }
+    }
+
+    /// Injects a fault into the operation of an entry, if fault injection
+    /// is enabled. Returns the error code the operation must fail with, if
+    /// any.
+    #[cfg_attr(not(feature = "test-util"), allow(unused_variables))]
+    pub(crate) fn inject_fault(&mut self, sqe: &mut squeue::Entry) -> Option<i32> {
+        #[cfg(feature = "test-util")]
+        if let Some(faults) = &mut self.faults {
+            return faults.inject(sqe);
+        }
+        None
+    }
+
+    /// Completes an operation which has not been submitted with an error.
+    pub(crate) fn fail_op(&mut self, index: usize, errno: i32) {
+        let result = Err(io::Error::from_raw_os_error(errno));
+        self.complete(index, op::CqeResult { result, flags: 0 });
+    }
+
+    /// Queues a chain of linked entries for submission.
+    ///
+    /// Entries exceeding the budget of the current task are deferred like
+    /// those of bulk operations.
+    pub(crate) fn push(&mut self, sqes: Vec<squeue::Entry>, priority: Priority) -> io::Result<()> {
+        #[cfg(feature = "test-util")]
+        if let Some(mock) = self.mock.clone() {
+            self.complete_mocked(&mock, &sqes);
+            return Ok(());
+        }
+
+        #[cfg(feature = "fallback")]
+        if let Some(fallback) = &mut self.fallback {
+            fallback.submit(&sqes);
+            return Ok(());
+        }
+
+        if self.batch_depth > 0 {
+            self.batched.push(sqes);
+            return Ok(());
+        }
+
+        let within_budget = budget::consume(self.task_sqe_budget, sqes.len() as u32);
+        if priority == Priority::Bulk || !within_budget {
+            self.deferred.push_back(sqes);
+            return Ok(());
+        }
+
+        while unsafe { self.ring().submission().push_multiple(&sqes).is_err() } {
+            self.stats.sq_full += 1;
+            if let Some(hook) = &self.on_sq_full {
+                self.stats.submitted = 0;
+                hook(&self.stats);
+            }
+            // If the submission queue is full, flush it to the kernel
+            self.submit()?;
+        }
+
+        let latency_critical = priority == Priority::LatencyCritical && self.flush_latency_critical;
+        let queued = self.ring().submission().len();
+        if latency_critical || self.flusher.flushes_early(queued, self.ops.lifecycle.len()) {
+            self.submit()?;
+        }
+        Ok(())
+    }
+
+    /// Passes a chain of entries to the mock backend, completing the
+    /// operations it returns the results of.
+    #[cfg(feature = "test-util")]
+    fn complete_mocked(&mut self, mock: &crate::mock::MockHandler, sqes: &[squeue::Entry]) {
+        let mut failed = false;
+        for sqe in sqes {
+            let sqe = SqeHeader::read(sqe);
+            if sqe.user_data == u64::MAX {
+                // Linked timeouts are not operations
+                continue;
+            }
+            let index = sqe.user_data as usize;
+            let result = if failed {
+                // The rest of a chain is canceled when an operation fails
+                Some(Err(io::Error::from_raw_os_error(libc::ECANCELED)))
+            } else {
+                mock.submit(&mut crate::mock::MockOp::new(sqe))
+            };
+            if let Some(result) = result {
+                failed = result.is_err();
+                self.complete(index, op::CqeResult { result, flags: 0 });
+            }
+            self.complete_mocked_later(mock);
+        }
+    }
+
+    /// Completes the operations the mock backend has completed since it was
+    /// passed them.
+    #[cfg(feature = "test-util")]
+    fn complete_mocked_later(&mut self, mock: &crate::mock::MockHandler) {
+        let mut completions = Vec::new();
+        mock.completions(&mut completions);
+        for (user_data, result) in completions {
+            self.complete(user_data as usize, op::CqeResult { result, flags: 0 });
+        }
+    }
+
+    /// Moves the deferred entries which fit into the submission queue.
+    ///
+    /// Returns `false` if there was no room for any.
+    fn push_deferred(&mut self) -> bool {
+        let mut pushed = false;
+        while let Some(sqes) = self.deferred.front() {
+            let uring = self.uring.as_mut().expect("io_uring is not available");
+            if unsafe { uring.submission().push_multiple(sqes).is_err() } {
+                break;
+            }
+            self.deferred.pop_front();
+            pushed = true;
+        }
+        pushed
+    }
+
+    /// Moves all the deferred entries into the submission queue, submitting
+    /// the queue to make room for them.
+    fn push_all_deferred(&mut self) -> io::Result<()> {
+        while !self.deferred.is_empty() {
+            if !self.push_deferred() {
+                self.submit()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Submits all queued entries, including the deferred ones, to the
+    /// kernel.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        loop {
+            self.push_deferred();
+            self.submit()?;
+            if self.deferred.is_empty() {
+                return Ok(());
+            }
+            if !self.push_deferred() {
+                // The kernel has not consumed any entries, e.g. it is
+                // polling the queue; try again with the next flush.
+                return Ok(());
+            }
+        }
+    }
+
+    /// Starts collecting the entries of new operations, to be submitted
+    /// together by [`end_batch`].
+    ///
+    /// [`end_batch`]: Driver::end_batch
+    pub(crate) fn begin_batch(&mut self) {
+        self.batch_depth += 1;
+    }
+
+    /// Ends a batch. When the outermost batch ends, the entries collected
+    /// are queued together and submitted to the kernel.
+    ///
+    /// On an error, the operations of the entries which could not be
+    /// queued have been completed with it.
+    pub(crate) fn end_batch(&mut self) -> io::Result<()> {
+        self.batch_depth -= 1;
+        if self.batch_depth > 0 || self.batched.is_empty() {
+            return Ok(());
+        }
+        self.push_batched()?;
+        self.submit()
+    }
+
+    /// Moves the batched entries into the submission queue, all at once:
+    /// if they do not fit next to the entries already queued, these are
+    /// submitted first.
+    ///
+    /// If a submission fails, the operations of the entries which could
+    /// not be queued are completed with the error, which is returned.
+    fn push_batched(&mut self) -> io::Result<()> {
+        if self.batched.is_empty() {
+            return Ok(());
+        }
+        let mut batched = std::mem::take(&mut self.batched).into_iter();
+        let len: usize = batched.as_slice().iter().map(Vec::len).sum();
+        let room = {
+            let sq = self.ring().submission();
+            sq.capacity() - sq.len()
+        };
+        if room < len {
+            if let Err(e) = self.submit() {
+                self.fail_chains(batched, &e);
+                return Err(e);
+            }
+        }
+        while let Some(sqes) = batched.next() {
+            // A batch larger than the queue is submitted in parts
+            while unsafe { self.ring().submission().push_multiple(&sqes).is_err() } {
+                if let Err(e) = self.submit() {
+                    self.fail_chains(std::iter::once(sqes).chain(batched), &e);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Completes the operations of chains of entries which have not been
+    /// queued with an error.
+    fn fail_chains(&mut self, chains: impl Iterator<Item = Vec<squeue::Entry>>, err: &io::Error) {
+        for sqe in chains.flatten() {
+            let user_data = SqeHeader::read(&sqe).user_data;
+            if user_data == u64::MAX {
+                // Linked timeouts and cancellations are not operations
+                continue;
+            }
+            let result = Err(match err.raw_os_error() {
+                Some(errno) => io::Error::from_raw_os_error(errno),
+                None => io::Error::new(err.kind(), err.to_string()),
+            });
+            self.complete(user_data as usize, op::CqeResult { result, flags: 0 });
+        }
+    }
+
+    /// Cancels an operation in flight.
+    pub(crate) fn cancel_op(&mut self, index: usize) -> io::Result<()> {
+        if let Some(retries) = &mut self.retries {
+            if retries.take_waiting(index).is_some() {
+                // The operation is waiting for a retry, not in flight
+                let result = Err(io::Error::from_raw_os_error(libc::ECANCELED));
+                self.ops.complete(index, op::CqeResult { result, flags: 0 });
+                return Ok(());
+            }
+        }
+
+        #[cfg(feature = "test-util")]
+        if let Some(mock) = self.mock.clone() {
+            mock.cancel(index as u64);
+            self.complete_mocked_later(&mock);
+            return Ok(());
+        }
+
+        #[cfg(feature = "fallback")]
+        if let Some(fallback) = &self.fallback {
+            fallback.cancel(index as u64);
+            return Ok(());
+        }
+
+        // The operation may be batched or deferred, the cancellation must
+        // come after it
+        self.push_batched()?;
+        self.push_all_deferred()?;
+
+        let sqe = AsyncCancel::new(index as u64).build().user_data(u64::MAX);
+        while unsafe { self.ring().submission().push(&sqe).is_err() } {
+            self.submit()?;
+        }
+        Ok(())
+    }
+
+    /// Cancels the operations in flight which were submitted in `scope`.
+    pub(crate) fn cancel_scope(&mut self, scope: &cancel::Scope) -> io::Result<()> {
+        let to_cancel: Vec<usize> = self
+            .ops
+            .lifecycle
+            .iter()
+            .filter(|(index, cycle)| {
+                !matches!(cycle, Lifecycle::Completed(_))
+                    && self
+                        .ops
+                        .info
+                        .get(*index)
+                        .and_then(Option::as_ref)
+                        .is_some_and(|info| info.is_within(scope))
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        for index in to_cancel {
+            self.cancel_op(index)?;
+        }
+        self.submit()
+    }
+
+    /// Returns `true` if there are operations in flight whose completion
+    /// will wake a task.
+    pub(crate) fn has_pending_ops(&self) -> bool {
+        self.ops.in_flight > self.ops.ignored
+    }
+
+    /// Lists the operations which have been submitted and have not
+    /// completed yet.
+    pub(crate) fn dump_inflight(&self) -> Vec<InflightOp> {
+        self.ops
+            .lifecycle
+            .iter()
+            .filter_map(|(index, cycle)| {
+                let abandoned = match cycle {
+                    Lifecycle::Completed(_) => return None,
+                    Lifecycle::Ignored(_) => true,
+                    _ => false,
+                };
+                let info = self.ops.info.get(index)?.as_ref()?;
+                Some(info.inflight_op(abandoned))
+            })
+            .collect()
+    }
+
+    fn report_flush(&mut self, submitted: usize) {
+        if submitted == 0 {
+            return;
+        }
+        self.stats.flushes += 1;
+        self.stats.total_submitted += submitted as u64;
+        if let Some(hook) = &self.on_flush {
+            self.stats.submitted = submitted;
+            hook(&self.stats);
+        }
+    }
+
+    pub(crate) fn submit(&mut self) -> io::Result<()> {
+        if self.uring.is_none() {
+            // The fallback backend starts the operations as they are pushed
+            return Ok(());
+        }
+
+        loop {
+            let queued = self.ring().submission().len();
+            let res = match self.cq_room() {
+                Some(room) => self.submit_within(queued, room),
+                None => self.ring().submit(),
+            };
+            if let Ok(submitted) = res {
+                self.report_flush(submitted);
+                if let Some(unreaped) = &mut self.unreaped {
+                    *unreaped += submitted;
+                }
+            }
+            match res {
+                Ok(submitted)
+                    if self.retry_partial_submit && 0 < submitted && submitted < queued =>
+                {
+                    // The kernel stopped at an entry it failed to submit.
+                    // Its operation completes with the error, submit the
+                    // rest of the queue.
+                    self.ring().submission().sync();
+                }
+                Ok(_) => {
+                    self.ring().submission().sync();
+                    return Ok(());
+                }
+                Err(ref e) if e.raw_os_error() == Some(libc::EBUSY) => {
+                    self.tick();
+                }
+                Err(e) if e.raw_os_error() != Some(libc::EINTR) => {
+                    return Err(e);
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    /// Returns the number of entries which can be submitted without their
+    /// completions overflowing the completion queue, if the kernel drops
+    /// the completions which overflow it.
+    fn cq_room(&mut self) -> Option<usize> {
+        let unreaped = self.unreaped?;
+        let cq_entries = self.ring().params().cq_entries() as usize;
+        Some(cq_entries.saturating_sub(unreaped))
+    }
+
+    /// Submits up to `room` of the `queued` entries, leaving the rest in
+    /// the submission queue. If the queue is full and there is no room,
+    /// waits for a completion to make room.
+    fn submit_within(&mut self, queued: usize, mut room: usize) -> io::Result<usize> {
+        if room == 0 && queued == self.ring().submission().capacity() {
+            unsafe {
+                self.ring().submitter().enter::<libc::sigset_t>(
+                    0,
+                    1,
+                    IORING_ENTER_GETEVENTS,
+                    None,
+                )?;
+            }
+            self.tick();
+            room = self.cq_room().unwrap_or(queued);
+        }
+        let to_submit = queued.min(room);
+        if to_submit == 0 {
+            return Ok(0);
+        }
+        unsafe {
+            self.ring()
+                .submitter()
+                .enter::<libc::sigset_t>(to_submit as u32, 0, 0, None)
+        }
+    }
+}
+
+impl AsRawFd for Driver {
+    fn as_raw_fd(&self) -> RawFd {
+        #[cfg(feature = "test-util")]
+        if let Some(fd) = &self.mock_fd {
+            return fd.as_raw_fd();
+        }
+
+        #[cfg(feature = "fallback")]
+        if let Some(fallback) = &self.fallback {
+            return fallback.as_raw_fd();
+        }
+
+        self.uring
+            .as_ref()
+            .expect("io_uring is not available")
+            .as_raw_fd()
+    }
+}
+
+/// Drop the driver, cancelling any in-progress ops and waiting for them to terminate.
+///
+/// This first cancels all ops and then waits for them to be moved to the completed lifecycle phase.
+///
+/// It is possible for this to be run without previously dropping the runtime, but this should only
+/// be possible in the case of [`std::process::exit`].
+///
+/// This depends on us knowing when ops are completed and done firing.
+/// When multishot ops are added (support exists but none are implemented), a way to know if such
+/// an op is finished MUST be added, otherwise our shutdown process is unsound.
+impl Drop for Driver {
+    fn drop(&mut self) {
+        self.cancel_all();
+
         // Wait until all Lifetimes have been removed from the slab.
         //
         // Ignored entries will be removed from the Lifecycle slab
@@ -196,11 +1184,29 @@ impl Drop for Driver {
     }
 }
 
+/// Completes an operation, unless it has failed with a transient error and
+/// is submitted again according to the retry policy.
+fn complete(ops: &mut Ops, retries: Option<&mut retry::Retries>, index: usize, cqe: op::CqeResult) {
+    if let Some(retries) = retries {
+        if !io_uring::cqueue::more(cqe.flags) {
+            let ignored = matches!(ops.lifecycle.get(index), Some(Lifecycle::Ignored(..)));
+            match &cqe.result {
+                Err(e) if !ignored && retries.schedule(index, e) => return,
+                _ => retries.untrack(index),
+            }
+        }
+    }
+    ops.complete(index, cqe);
+}
+
 impl Ops {
     fn new() -> Ops {
         Ops {
             lifecycle: Slab::with_capacity(64),
             completions: Slab::with_capacity(64),
+            info: Vec::new(),
+            in_flight: 0,
+            ignored: 0,
         }
     }
 
@@ -213,19 +1219,66 @@ impl Ops {
 
     // Insert a new operation
     fn insert(&mut self) -> usize {
+        self.in_flight += 1;
         self.lifecycle.insert(op::Lifecycle::Submitted)
     }
 
+    // Record the information on a submitted operation
+    fn set_info(&mut self, index: usize, info: inflight::OpInfo) {
+        if self.info.len() <= index {
+            self.info.resize_with(index + 1, || None);
+        }
+        self.info[index] = Some(info);
+        #[cfg(feature = "metrics")]
+        crate::metrics::op_submitted();
+    }
+
     // Remove an operation
     fn remove(&mut self, index: usize) {
         self.lifecycle.remove(index);
+        self.clear_info(index);
     }
 
-    fn complete(&mut self, index: usize, cqe: op::CqeResult) {
+    fn complete(&mut self, index: usize, mut cqe: op::CqeResult) {
+        let more = io_uring::cqueue::more(cqe.flags);
+        if let Err(e) = &cqe.result {
+            let timed_out = e.raw_os_error() == Some(libc::ECANCELED)
+                && self
+                    .info
+                    .get(index)
+                    .and_then(Option::as_ref)
+                    .is_some_and(|info| info.is_timed_out());
+            if timed_out {
+                // Canceled by the deadline of a timeout
+                cqe.result = Err(io::Error::from_raw_os_error(libc::ETIMEDOUT));
+            }
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(info) = self.info.get(index).and_then(Option::as_ref) {
+            crate::metrics::op_completed(info.opcode(), &cqe.result, more, info.age());
+        }
+        let ignored = matches!(self.lifecycle[index], Lifecycle::Ignored(..));
         let completions = &mut self.completions;
         if self.lifecycle[index].complete(completions, cqe) {
             self.lifecycle.remove(index);
         }
+        if !more {
+            // The operation is no longer in flight
+            self.in_flight -= 1;
+            if ignored {
+                self.ignored -= 1;
+            }
+            self.clear_info(index);
+        }
+    }
+
+    fn clear_info(&mut self, index: usize) {
+        if let Some(info) = self.info.get_mut(index) {
+            if info.take().is_some() {
+                #[cfg(feature = "metrics")]
+                crate::metrics::op_retired();
+            }
+        }
     }
 }
 
@@ -392,6 +1445,86 @@ mod test {
         release();
     }
 
+    #[test]
+    fn submissions_held_back_without_nodrop() {
+        let mut driver = Driver::new(&crate::builder()).unwrap();
+        let cq_entries = driver.ring().params().cq_entries() as usize;
+
+        // Account as on a kernel dropping the completions which overflow
+        // the queue, with room for one more completion
+        driver.unreaped = Some(cq_entries - 1);
+        let nop = io_uring::opcode::Nop::new().build().user_data(u64::MAX);
+        for _ in 0..2 {
+            unsafe { driver.ring().submission().push(&nop).unwrap() };
+        }
+        driver.submit().unwrap();
+        assert_eq!(driver.ring().submission().len(), 1);
+        assert_eq!(driver.unreaped, Some(cq_entries));
+
+        // Reaping the completion makes room for the other entry
+        driver.wait().unwrap();
+        driver.tick();
+        assert_eq!(driver.unreaped, Some(cq_entries - 1));
+        driver.submit().unwrap();
+        assert_eq!(driver.ring().submission().len(), 0);
+
+        driver.wait().unwrap();
+        driver.tick();
+        assert_eq!(driver.unreaped, Some(cq_entries - 1));
+    }
+
+    #[test]
+    fn partial_submit_retried() {
+        for &retry in [false, true].iter() {
+            let mut driver = Driver::new(crate::builder().retry_partial_submit(retry)).unwrap();
+
+            // The kernel stops submitting at an entry it fails to
+            // initialize, here one with an unknown opcode
+            let nop = io_uring::opcode::Nop::new().build().user_data(u64::MAX);
+            let mut invalid = nop.clone();
+            unsafe { *(&mut invalid as *mut io_uring::squeue::Entry as *mut u8) = u8::MAX };
+            for entry in [&nop, &invalid, &nop, &nop].iter() {
+                unsafe { driver.ring().submission().push(entry).unwrap() };
+            }
+            driver.submit().unwrap();
+
+            let left = if retry { 0 } else { 2 };
+            assert_eq!(driver.ring().submission().len(), left);
+            assert_eq!(driver.stats.total_submitted, 4 - left as u64);
+        }
+    }
+
+    #[test]
+    fn batched_ops_failed_on_submit_error() {
+        let mut driver = Driver::new(crate::builder().entries(4)).unwrap();
+        let nop = io_uring::opcode::Nop::new().build().user_data(u64::MAX);
+        let capacity = driver.ring().submission().capacity();
+        for _ in 0..capacity {
+            unsafe { driver.ring().submission().push(&nop).unwrap() };
+        }
+
+        // The submission fails with the ring replaced by another file,
+        // while the batched entries do not fit in the queue
+        let ring_fd = driver.as_raw_fd();
+        let ring = syscall!(dup(ring_fd)).unwrap();
+        let null = std::fs::File::open("/dev/null").unwrap();
+        syscall!(dup2(null.as_raw_fd(), ring_fd)).unwrap();
+
+        let index = driver.ops.insert();
+        driver.begin_batch();
+        let sqes = vec![nop.clone().user_data(index as u64)];
+        driver.push(sqes, Priority::Normal).unwrap();
+        let res = driver.end_batch();
+
+        syscall!(dup2(ring, ring_fd)).unwrap();
+        syscall!(close(ring)).unwrap();
+        assert!(res.is_err());
+        match driver.ops.lifecycle.remove(index) {
+            op::Lifecycle::Completed(cqe) => assert!(cqe.result.is_err()),
+            _ => panic!("the batched operation has not completed"),
+        }
+    }
+
     fn init() -> (Op<Rc<()>>, Rc<()>) {
         let driver = Driver::new(&crate::builder()).unwrap();
         let data = Rc::new(());
@@ -40,10 +40,38 @@ pub(crate) struct SingleCQE;
 /// which combined resolve to a single Future value
 pub(crate) struct MultiCQEFuture;
 
+/// A Marker for Operations producing a stream of values, one for each
+/// completion event
+pub(crate) struct MultiCQEStream;
+
+/// Tells whether the operations of a marker type complete with a single
+/// completion event
+pub(crate) trait CqeKind {
+    const SINGLE: bool;
+}
+
+impl CqeKind for SingleCQE {
+    const SINGLE: bool = true;
+}
+
+impl CqeKind for MultiCQEFuture {
+    const SINGLE: bool = false;
+}
+
+impl CqeKind for MultiCQEStream {
+    const SINGLE: bool = false;
+}
+
 pub(crate) trait Completable {
     type Output;
     /// `complete` will be called for cqe's do not have the `more` flag set
     fn complete(self, cqe: CqeResult) -> Self::Output;
+
+    /// Returns `true` if the operation waits for its file to become ready,
+    /// as a read of a socket does, rather than on the I/O itself.
+    fn waits_for_readiness(&self) -> bool {
+        false
+    }
 }
 
 pub(crate) trait Updateable: Completable {
@@ -52,6 +80,13 @@ pub(crate) trait Updateable: Completable {
     fn update(&mut self, cqe: CqeResult);
 }
 
+pub(crate) trait Streamable: Completable {
+    /// `next` will be called for cqe's which have the `more` flag set,
+    /// producing the next value of the stream. The final value is produced
+    /// by `complete`.
+    fn next(&mut self, cqe: CqeResult) -> Self::Output;
+}
+
 pub(crate) enum Lifecycle {
     /// The operation has been submitted to uring and is currently in-flight
     Submitted,
@@ -61,7 +96,7 @@ pub(crate) enum Lifecycle {
 
     /// The submitter no longer has interest in the operation result. The state
     /// must be passed to the driver and held until the operation completes.
-    Ignored(Box<dyn std::any::Any>),
+    Ignored(#[allow(dead_code)] Box<dyn std::any::Any>),
 
     /// The operation has completed with a single cqe result
     Completed(CqeResult),
@@ -105,6 +140,18 @@ where
     }
 }
 
+impl<T, CqeType> Op<T, CqeType> {
+    /// Requests the cancellation of the operation, if it is in flight.
+    ///
+    /// The operation completes as usual, with `ECANCELED` if the
+    /// cancellation has taken effect.
+    pub(crate) fn cancel(&self) {
+        if let Some(driver) = self.driver.upgrade() {
+            driver.cancel_op(self);
+        }
+    }
+}
+
 impl<T> Future for Op<T, SingleCQE>
 where
     T: Unpin + 'static + Completable,
@@ -112,10 +159,16 @@ where
     type Output = T::Output;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.driver
+        // Each completion consumes the budget of the task, so that a task
+        // awaiting a flood of completed operations yields to other tasks.
+        let coop = ready!(tokio::task::coop::poll_proceed(cx));
+        let output = ready!(self
+            .driver
             .upgrade()
             .expect("Not in runtime context")
-            .poll_op(self.get_mut(), cx)
+            .poll_op(self.get_mut(), cx));
+        coop.made_progress();
+        Poll::Ready(output)
     }
 }
 
@@ -126,10 +179,32 @@ where
     type Output = T::Output;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.driver
+        let coop = ready!(tokio::task::coop::poll_proceed(cx));
+        let output = ready!(self
+            .driver
             .upgrade()
             .expect("Not in runtime context")
-            .poll_multishot_op(self.get_mut(), cx)
+            .poll_multishot_op(self.get_mut(), cx));
+        coop.made_progress();
+        Poll::Ready(output)
+    }
+}
+
+impl<T> Op<T, MultiCQEStream>
+where
+    T: Unpin + 'static + Streamable,
+{
+    /// Polls for the value of the next completion, returning `None` once
+    /// the final one has been returned.
+    pub(crate) fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<T::Output>> {
+        let coop = ready!(tokio::task::coop::poll_proceed(cx));
+        let output = ready!(self
+            .driver
+            .upgrade()
+            .expect("Not in runtime context")
+            .poll_stream_op(self, cx));
+        coop.made_progress();
+        Poll::Ready(output)
     }
 }
 
@@ -146,6 +221,25 @@ impl<T, CqeType> Drop for Op<T, CqeType> {
     }
 }
 
+/// Releases what the kernel has allocated for an operation whose result
+/// is not going to be used: the connection accepted by a dropped accept
+/// operation is closed rather than leaked, the provided buffers filled by
+/// a dropped multishot read or bundle receive are given back to their ring,
+/// and the queued buffers consumed by a dropped bundle send are freed.
+pub(crate) fn discard(data: &dyn std::any::Any, cqe: &CqeResult) {
+    if data.is::<Option<crate::io::Accept>>() {
+        if let Ok(fd) = cqe.result {
+            unsafe { libc::close(fd as _) };
+        }
+    } else if let Some(Some(read)) = data.downcast_ref::<Option<crate::io::ReadMulti>>() {
+        read.discard(cqe);
+    } else if let Some(Some(recv)) = data.downcast_ref::<Option<crate::io::RecvBundle>>() {
+        recv.discard(cqe);
+    } else if let Some(Some(send)) = data.downcast_ref::<Option<crate::io::SendBundle>>() {
+        send.discard(cqe);
+    }
+}
+
 impl Lifecycle {
     pub(crate) fn complete(&mut self, completions: &mut Slab<Completion>, cqe: CqeResult) -> bool {
         use std::mem;
@@ -168,6 +262,9 @@ impl Lifecycle {
             }
 
             lifecycle @ Lifecycle::Ignored(..) => {
+                if let Lifecycle::Ignored(data) = &lifecycle {
+                    discard(&**data, &cqe);
+                }
                 if io_uring::cqueue::more(cqe.flags) {
                     // Not yet complete. The Op has been dropped, so we can drop the CQE
                     // but we must keep the lifecycle alive until no more CQE's expected
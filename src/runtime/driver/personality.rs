@@ -0,0 +1,94 @@
+use io_uring::squeue;
+use std::cell::Cell;
+use std::future::{poll_fn, Future};
+
+thread_local! {
+    // Personality of the operations submitted by the future being polled.
+    static PERSONALITY: Cell<Option<Personality>> = const { Cell::new(None) };
+}
+
+/// Credentials registered with the kernel, with which io-uring operations
+/// can be performed.
+///
+/// A personality is registered with [`Handle::register_personality`], which
+/// records the credentials of the thread at the time of the call. Operations
+/// submitted within [`with_personality`] are performed with these
+/// credentials, rather than the current credentials of the process. This
+/// allows a server to register a personality, drop its privileges, and
+/// still perform selected privileged operations.
+///
+/// [`Handle::register_personality`]: crate::Handle::register_personality
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Personality(pub(crate) u16);
+
+impl Personality {
+    /// Returns the id the kernel has assigned to the personality.
+    pub fn id(&self) -> u16 {
+        self.0
+    }
+
+    /// Sets the personality of the operations currently submitted on an
+    /// entry.
+    pub(crate) fn apply(sqe: squeue::Entry) -> squeue::Entry {
+        match PERSONALITY.with(Cell::get) {
+            Some(personality) => sqe.personality(personality.0),
+            None => sqe,
+        }
+    }
+}
+
+/// Runs a future, performing the io-uring operations it creates with the
+/// credentials of `personality`.
+///
+/// Nested calls replace the personality. The operations of tasks spawned by
+/// the future are performed with the credentials of the process. Operations
+/// fail with `EINVAL` if the personality has been unregistered.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::File;
+/// use tokio_uring::Handle;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         // Register the credentials while the process is privileged
+///         let admin = Handle::current().register_personality()?;
+///
+///         if unsafe { libc::setuid(65534) } != 0 {
+///             return Err(std::io::Error::last_os_error().into());
+///         }
+///
+///         // Still allowed to read the protected file
+///         let secrets = tokio_uring::with_personality(admin, async {
+///             File::open("/etc/shadow").await
+///         })
+///         .await?;
+///
+///         // Not allowed any more
+///         assert!(File::open("/etc/shadow").await.is_err());
+///
+///         secrets.close().await?;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn with_personality<F: Future>(personality: Personality, future: F) -> F::Output {
+    tokio::pin!(future);
+
+    poll_fn(|cx| {
+        let _restore =
+            RestorePersonality(PERSONALITY.with(|current| current.replace(Some(personality))));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+// Restores the previous personality when the future returns from poll.
+struct RestorePersonality(Option<Personality>);
+
+impl Drop for RestorePersonality {
+    fn drop(&mut self) {
+        PERSONALITY.with(|current| current.set(self.0));
+    }
+}
@@ -0,0 +1,93 @@
+use std::cell::Cell;
+use std::future::{poll_fn, Future};
+
+thread_local! {
+    // Priority of the operations submitted by the future being polled.
+    static PRIORITY: Cell<Priority> = const { Cell::new(Priority::Normal) };
+}
+
+/// The priority class of io-uring operations, set with [`with_priority`].
+///
+/// Operations are submitted to the kernel in batches, when the runtime is
+/// about to wait for events. The priority decides the order of the
+/// operations in a batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Priority {
+    /// Latency-critical operations, such as small reads serving a request.
+    ///
+    /// These are submitted to the kernel immediately, along with the
+    /// entries queued before them, without waiting for the batch to be
+    /// complete, unless [`Builder::flush_latency_critical`] is disabled.
+    ///
+    /// [`Builder::flush_latency_critical`]: crate::Builder::flush_latency_critical
+    LatencyCritical,
+
+    /// Operations queued for submission in the order they are created.
+    ///
+    /// This is the default.
+    #[default]
+    Normal,
+
+    /// Bulk operations, such as large writes done in the background.
+    ///
+    /// These are held back until the batch is submitted, and placed after
+    /// all the other operations in it, so that a large number of bulk
+    /// operations cannot delay the other ones.
+    Bulk,
+}
+
+impl Priority {
+    /// Returns the priority of the operations currently submitted.
+    pub(crate) fn current() -> Priority {
+        PRIORITY.with(Cell::get)
+    }
+}
+
+/// Runs a future, submitting the io-uring operations it creates with the
+/// given priority.
+///
+/// Nested calls replace the priority. The operations of tasks spawned by the
+/// future have the normal priority.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::fs::File;
+/// use tokio_uring::Priority;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let log = File::create("compacted.log").await?;
+///
+///         tokio_uring::spawn(tokio_uring::with_priority(Priority::Bulk, async move {
+///             for i in 0..1024 {
+///                 let (res, _) = log.write_all_at(vec![0; 1 << 20], i << 20).await;
+///                 res.unwrap();
+///             }
+///         }));
+///
+///         // Operations of the other tasks are not delayed by the writes
+///
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn with_priority<F: Future>(priority: Priority, future: F) -> F::Output {
+    tokio::pin!(future);
+
+    poll_fn(|cx| {
+        let _restore = RestorePriority(PRIORITY.with(|current| current.replace(priority)));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+// Restores the previous priority when the future returns from poll.
+struct RestorePriority(Priority);
+
+impl Drop for RestorePriority {
+    fn drop(&mut self) {
+        PRIORITY.with(|current| current.set(self.0));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::runtime::driver::priority::Priority;
+use crate::runtime::driver::SqeHeader;
+use io_uring::squeue;
+use std::collections::HashMap;
+use std::io;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Which failed operations are submitted again by the runtime, set with
+/// [`Builder::retry_policy`].
+///
+/// An operation completing with one of the transient errors of the policy
+/// is submitted again after a delay, instead of returning the error: the
+/// delay starts at the initial backoff, and doubles with each attempt up to
+/// the maximum backoff. Once the operation has been retried the maximum
+/// number of times, the error is returned.
+///
+/// By default, the transient errors are `EAGAIN`, as returned by reads
+/// with `RWF_NOWAIT` which would block, `ENOBUFS`, as returned by reads and
+/// receives with provided buffers when the ring is empty, and `EINTR`.
+///
+/// Only operations submitted alone are retried: the operations linked with
+/// others, e.g. by [`File::write_all_sync_at`], or bounded by a deadline
+/// with [`time::timeout`], return their errors. Neither are multishot
+/// operations, nor an operation whose future has been dropped.
+///
+/// [`Builder::retry_policy`]: crate::Builder::retry_policy
+/// [`File::write_all_sync_at`]: crate::fs::File::write_all_sync_at
+/// [`time::timeout`]: crate::time::timeout
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio_uring::RetryPolicy;
+///
+/// let mut policy = RetryPolicy::new();
+/// policy
+///     .max_retries(8)
+///     .backoff(Duration::from_micros(100), Duration::from_millis(50));
+///
+/// tokio_uring::builder().retry_policy(policy).start(async {
+///     // Reads failing with EAGAIN are retried by the runtime
+/// });
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    errnos: Vec<i32>,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    opcodes: Option<Vec<u8>>,
+}
+
+impl RetryPolicy {
+    /// Creates a policy retrying operations failing with `EAGAIN`,
+    /// `ENOBUFS` or `EINTR` up to 5 times, with a backoff starting at 1
+    /// millisecond and up to 100 milliseconds.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            errnos: vec![libc::EAGAIN, libc::ENOBUFS, libc::EINTR],
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+            opcodes: None,
+        }
+    }
+
+    /// Sets the error codes of the failures which are retried.
+    pub fn errors(&mut self, errnos: &[i32]) -> &mut Self {
+        self.errnos = errnos.to_vec();
+        self
+    }
+
+    /// Sets the number of times an operation is retried before its error
+    /// is returned.
+    pub fn max_retries(&mut self, max_retries: u32) -> &mut Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry, and the longest delay the
+    /// doubling delays of the following retries grow to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is longer than `max`.
+    pub fn backoff(&mut self, initial: Duration, max: Duration) -> &mut Self {
+        assert!(
+            initial <= max,
+            "the initial backoff must not exceed the maximum"
+        );
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Retries only operations with the given opcodes, e.g.
+    /// `io_uring::opcode::Read::CODE`.
+    ///
+    /// By default, all operations are retried.
+    pub fn opcodes(&mut self, opcodes: &[u8]) -> &mut Self {
+        self.opcodes = Some(opcodes.to_vec());
+        self
+    }
+
+    // Returns the delay before the retry following `attempts` retries.
+    fn backoff_delay(&self, attempts: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempts).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |d| d.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new()
+    }
+}
+
+// An operation which may be submitted again.
+struct Tracked {
+    sqe: squeue::Entry,
+    priority: Priority,
+    attempts: u32,
+    // Set while the operation waits to be submitted again
+    due: Option<(Instant, i32)>,
+}
+
+/// The operations of a runtime which are retried according to its policy.
+pub(crate) struct Retries {
+    policy: RetryPolicy,
+    ops: HashMap<usize, Tracked>,
+    // Woken when an operation is scheduled for a retry
+    notify: Rc<Notify>,
+}
+
+impl Retries {
+    pub(crate) fn new(policy: &RetryPolicy) -> Retries {
+        Retries {
+            policy: policy.clone(),
+            ops: HashMap::new(),
+            notify: Rc::new(Notify::new()),
+        }
+    }
+
+    pub(crate) fn notify(&self) -> Rc<Notify> {
+        self.notify.clone()
+    }
+
+    /// Records the entry of an operation, if the policy applies to it.
+    pub(crate) fn track(&mut self, index: usize, sqe: &squeue::Entry, priority: Priority) {
+        if let Some(opcodes) = &self.policy.opcodes {
+            if !opcodes.contains(&SqeHeader::read(sqe).opcode) {
+                return;
+            }
+        }
+        let tracked = Tracked {
+            sqe: sqe.clone(),
+            priority,
+            attempts: 0,
+            due: None,
+        };
+        self.ops.insert(index, tracked);
+    }
+
+    /// Stops tracking an operation which has completed.
+    pub(crate) fn untrack(&mut self, index: usize) {
+        self.ops.remove(&index);
+    }
+
+    /// Schedules a retry of an operation which has failed with `err`.
+    /// Returns `false` if the operation is not retried.
+    pub(crate) fn schedule(&mut self, index: usize, err: &io::Error) -> bool {
+        let errno = match err.raw_os_error() {
+            Some(errno) if self.policy.errnos.contains(&errno) => errno,
+            _ => return false,
+        };
+        let tracked = match self.ops.get_mut(&index) {
+            Some(tracked) if tracked.attempts < self.policy.max_retries => tracked,
+            _ => return false,
+        };
+        let delay = self.policy.backoff_delay(tracked.attempts);
+        tracked.attempts += 1;
+        tracked.due = Some((Instant::now() + delay, errno));
+        self.notify.notify_one();
+        true
+    }
+
+    /// Removes an operation waiting for a retry, returning the error it
+    /// failed with.
+    pub(crate) fn take_waiting(&mut self, index: usize) -> Option<i32> {
+        let (_, errno) = self.ops.get(&index)?.due?;
+        self.ops.remove(&index);
+        Some(errno)
+    }
+
+    /// Returns the indices of the operations waiting for a retry.
+    pub(crate) fn waiting(&self) -> Vec<usize> {
+        self.ops
+            .iter()
+            .filter(|(_, tracked)| tracked.due.is_some())
+            .map(|(&index, _)| index)
+            .collect()
+    }
+
+    /// Takes the operations due for a retry, and returns them with the
+    /// time the next one is due at.
+    pub(crate) fn take_due(&mut self) -> (Vec<Due>, Option<Instant>) {
+        let now = Instant::now();
+        let mut due = Vec::new();
+        let mut next = None;
+        for (&index, tracked) in self.ops.iter_mut() {
+            match tracked.due {
+                Some((at, errno)) if at <= now => {
+                    tracked.due = None;
+                    due.push(Due {
+                        index,
+                        sqe: tracked.sqe.clone(),
+                        priority: tracked.priority,
+                        errno,
+                    });
+                }
+                Some((at, _)) => next = Some(next.map_or(at, |next: Instant| next.min(at))),
+                None => {}
+            }
+        }
+        (due, next)
+    }
+}
+
+/// An operation due to be submitted again.
+pub(crate) struct Due {
+    pub(crate) index: usize,
+    pub(crate) sqe: squeue::Entry,
+    pub(crate) priority: Priority,
+    // The error the operation failed with
+    pub(crate) errno: i32,
+}
@@ -0,0 +1,217 @@
+use crate::runtime::driver::{self, InflightOp, Personality};
+use crate::runtime::CONTEXT;
+use std::io;
+use std::marker::PhantomData;
+
+/// A handle to a `tokio-uring` runtime.
+///
+/// The handle gives access to the state of the io-uring driver of the
+/// runtime. It is obtained with [`Handle::current`] from within the runtime,
+/// or with [`Runtime::handle`].
+///
+/// [`Runtime::handle`]: crate::Runtime::handle
+#[derive(Clone)]
+pub struct Handle {
+    pub(crate) inner: driver::Handle,
+}
+
+impl Handle {
+    /// Returns a handle to the runtime of the current thread.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if called outside the context of a `tokio-uring`
+    /// runtime.
+    pub fn current() -> Handle {
+        Handle {
+            inner: CONTEXT.with(|x| x.handle().expect("Not in a runtime context")),
+        }
+    }
+
+    /// Returns a handle to the runtime of the current thread, or `None` if
+    /// called outside the context of a `tokio-uring` runtime.
+    ///
+    /// This allows libraries to check whether they run on a `tokio-uring`
+    /// runtime, and fall back to other means of I/O if they do not.
+    pub fn try_current() -> Option<Handle> {
+        let inner = CONTEXT.try_with(|x| x.handle()).ok().flatten()?;
+        Some(Handle { inner })
+    }
+
+    /// Enters the context of the runtime on the current thread, so that
+    /// [`Handle::current`] returns this handle and io-uring operations can
+    /// be created, until the returned guard is dropped.
+    ///
+    /// This allows code called outside of the runtime, such as a callback
+    /// of a library, to create operations with a stashed handle. The
+    /// operations are submitted to the kernel and completed when the
+    /// runtime next runs, so they must be awaited by a future run on the
+    /// runtime. The context entered before is restored when the guard is
+    /// dropped; guards must be dropped in the reverse order of their
+    /// creation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tokio_uring::Handle;
+    ///
+    /// let rt = tokio_uring::Runtime::new(&tokio_uring::builder()).unwrap();
+    /// let handle = rt.handle();
+    /// assert!(Handle::try_current().is_none());
+    ///
+    /// let _guard = handle.enter();
+    /// assert!(Handle::try_current().is_some());
+    /// rt.block_on(async {
+    ///     tokio_uring::no_op().await.unwrap();
+    /// });
+    /// ```
+    pub fn enter(&self) -> EnterGuard<'_> {
+        let prev = CONTEXT.with(|x| x.replace_handle(Some(self.inner.clone())));
+        EnterGuard {
+            prev,
+            _handle: PhantomData,
+        }
+    }
+
+    /// Returns the operations which have been submitted to the kernel and
+    /// have not completed yet.
+    ///
+    /// This is a debugging aid: when a future never resolves, the list shows
+    /// which operations it, or the tasks it waits for, are blocked on, and
+    /// for how long. Operations can be labeled with [`with_op_label`].
+    ///
+    /// [`with_op_label`]: crate::with_op_label
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use tokio_uring::Handle;
+    ///
+    /// tokio_uring::start(async {
+    ///     tokio_uring::spawn(async {
+    ///         loop {
+    ///             tokio::time::sleep(Duration::from_secs(10)).await;
+    ///             for op in Handle::current().dump_inflight() {
+    ///                 if op.age() > Duration::from_secs(10) {
+    ///                     eprintln!("stuck operation: {:?}", op);
+    ///                 }
+    ///             }
+    ///         }
+    ///     });
+    ///
+    ///     // Run the application
+    /// });
+    /// ```
+    pub fn dump_inflight(&self) -> Vec<InflightOp> {
+        self.inner.dump_inflight()
+    }
+
+    /// Registers the credentials of the current thread as a personality,
+    /// with which operations can be performed later.
+    ///
+    /// See [`with_personality`] for how to use it. The personality stays
+    /// registered until it is unregistered, or the runtime is dropped.
+    ///
+    /// [`with_personality`]: crate::with_personality
+    ///
+    /// # Errors
+    ///
+    /// Fails if the kernel does not support personalities, or with the
+    /// fallback backend.
+    pub fn register_personality(&self) -> io::Result<Personality> {
+        self.inner.register_personality()
+    }
+
+    /// Unregisters a personality.
+    ///
+    /// Operations submitted with the personality afterwards fail with
+    /// `EINVAL`.
+    pub fn unregister_personality(&self, personality: Personality) -> io::Result<()> {
+        self.inner.unregister_personality(personality)
+    }
+
+    /// Calls `f` with the io_uring instance of the runtime.
+    ///
+    /// This gives access to the registration calls of the `io-uring` crate
+    /// which this crate does not provide methods for, through
+    /// [`IoUring::submitter`]. The runtime is not accessible while `f` runs:
+    /// creating operations, or using the other methods of the handle, in
+    /// `f` panics.
+    ///
+    /// [`IoUring::submitter`]: io_uring::IoUring::submitter
+    ///
+    /// # Safety
+    ///
+    /// The caller must not change the state of the ring which the runtime
+    /// relies on: it must not submit entries to the ring, and must not
+    /// unregister or update the buffers, files, personalities or other
+    /// resources registered by the runtime, or used by operations in flight.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the fallback backend, or a mocked runtime, which have no
+    /// io_uring instance.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tokio_uring::Handle;
+    ///
+    /// tokio_uring::start(async {
+    ///     // Limit the kernel threads performing blocking operations
+    ///     let mut max_workers = [1, 1];
+    ///     unsafe {
+    ///         Handle::current().with_ring(|ring| {
+    ///             ring.submitter().register_iowq_max_workers(&mut max_workers)
+    ///         })
+    ///     }
+    ///     .unwrap()
+    ///     .unwrap();
+    /// });
+    /// ```
+    pub unsafe fn with_ring<R>(&self, f: impl FnOnce(&io_uring::IoUring) -> R) -> io::Result<R> {
+        self.inner.with_ring(f)
+    }
+
+    /// Returns `true` if the runtime performs the operations with the
+    /// fallback backend, because io_uring is not available.
+    ///
+    /// See [`Builder::force_fallback`] for details.
+    ///
+    /// [`Builder::force_fallback`]: crate::Builder::force_fallback
+    ///
+    /// This method requires the `fallback` feature.
+    #[cfg(feature = "fallback")]
+    pub fn is_fallback(&self) -> bool {
+        self.inner.is_fallback()
+    }
+}
+
+/// A guard keeping the context of a runtime entered, returned by
+/// [`Handle::enter`].
+///
+/// The context entered before is restored when the guard is dropped.
+pub struct EnterGuard<'a> {
+    prev: Option<driver::Handle>,
+    _handle: PhantomData<&'a Handle>,
+}
+
+impl Drop for EnterGuard<'_> {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        let _ = CONTEXT.try_with(|x| x.replace_handle(prev));
+    }
+}
+
+impl std::fmt::Debug for EnterGuard<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnterGuard").finish_non_exhaustive()
+    }
+}
+
+impl std::fmt::Debug for Handle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Handle").finish_non_exhaustive()
+    }
+}
@@ -2,16 +2,32 @@ use std::future::Future;
 use std::io;
 use std::mem::ManuallyDrop;
 use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::io::unix::AsyncFd;
 use tokio::task::LocalSet;
 
 mod context;
 pub(crate) mod driver;
+mod handle;
+mod spread;
+mod watchdog;
 
 pub(crate) use context::RuntimeContext;
+pub use driver::{
+    submit_together, with_cancellation, with_op_label, with_personality, with_priority, Batch,
+    Decision, FlushPolicy, InflightOp, MaybeDone, Personality, Priority, RetryPolicy, SqeInfo,
+    SubmitStats, TickStats,
+};
+pub(crate) use driver::{OpInspector, SubmitHook, TickHook};
+pub use handle::{EnterGuard, Handle};
+pub use spread::SpreadSpawner;
+pub(crate) use watchdog::StallCallback;
+use watchdog::Watchdog;
 
 thread_local! {
-    pub(crate) static CONTEXT: RuntimeContext = RuntimeContext::new();
+    pub(crate) static CONTEXT: RuntimeContext = const { RuntimeContext::new() };
 }
 
 /// The Runtime executor
@@ -24,6 +40,9 @@ pub struct Runtime {
 
     /// Tokio runtime, always current-thread
     rt: ManuallyDrop<tokio::runtime::Runtime>,
+
+    /// Stall detection, if enabled
+    watchdog: Option<Watchdog>,
 }
 
 /// Spawns a new asynchronous task, returning a [`JoinHandle`] for it.
@@ -53,23 +72,102 @@ pub struct Runtime {
 /// });
 /// ```
 pub fn spawn<T: Future + 'static>(task: T) -> tokio::task::JoinHandle<T::Output> {
-    tokio::task::spawn_local(task)
+    tokio::task::spawn_local(driver::budgeted(watchdog::tracked(task)))
+}
+
+/// Runs a blocking function on a dedicated thread, returning a
+/// [`JoinHandle`] for its result.
+///
+/// The runtime executes all tasks, and drives the io-uring, on a single
+/// thread. Any task blocking that thread, e.g. with a synchronous system
+/// call or a long computation such as compression, stalls all other tasks
+/// and the completion of their operations. Such work should be moved to
+/// another thread with this function.
+///
+/// The function runs on the blocking thread pool of the underlying tokio
+/// runtime, outside of the `tokio-uring` context: it cannot use the
+/// operations of this crate nor [`spawn`] tasks, and it must be `Send`.
+/// The returned handle can be awaited by any task on the runtime.
+///
+/// When the runtime is shut down, it waits for blocking functions which
+/// have started running to complete.
+///
+/// This function must be called from the context of a `tokio-uring` runtime.
+///
+/// [`JoinHandle`]: tokio::task::JoinHandle
+///
+/// # Examples
+///
+/// ```no_run
+/// tokio_uring::start(async {
+///     let sum = tokio_uring::spawn_blocking(|| {
+///         // Some CPU-heavy work
+///         (0..1_000_000u64).sum::<u64>()
+///     })
+///     .await
+///     .unwrap();
+///
+///     println!("sum: {}", sum);
+/// });
+/// ```
+pub fn spawn_blocking<F, R>(f: F) -> tokio::task::JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
 }
 
 impl Runtime {
     /// Create a new tokio_uring runtime on the current thread
     pub fn new(b: &crate::Builder) -> io::Result<Runtime> {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .on_thread_park(|| {
+        let watchdog = match b.stall_threshold {
+            Some(threshold) => {
+                let reporter = watchdog::reporter(b.on_stall.clone()).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the stall watchdog has nothing to report to: \
+                         set on_stall, or enable the metrics feature",
+                    )
+                })?;
+                Some(Watchdog::new(threshold, reporter))
+            }
+            None => None,
+        };
+        let stall_state = watchdog.as_ref().map(Watchdog::state);
+
+        let mut rt = tokio::runtime::Builder::new_current_thread();
+        rt.on_thread_park({
+            let stall_state = stall_state.clone();
+            move || {
                 CONTEXT.with(|x| {
-                    let _ = x
+                    let driver = x
                         .handle()
-                        .expect("Internal error, driver context not present when invoking hooks")
-                        .flush();
+                        .expect("Internal error, driver context not present when invoking hooks");
+                    let _ = driver.park();
+                    if let Some(state) = &stall_state {
+                        state.park(driver.has_pending_ops());
+                    }
                 });
-            })
-            .enable_all()
-            .build()?;
+            }
+        });
+        if stall_state.is_some() || b.on_unpark.is_some() {
+            rt.on_thread_unpark(move || {
+                if let Some(state) = &stall_state {
+                    state.unpark();
+                }
+                // The runtime is also unparked while it is built, before
+                // the driver is set
+                CONTEXT.with(|x| {
+                    if let Some(driver) = x.handle() {
+                        driver.unpark();
+                    }
+                });
+            });
+        }
+        #[cfg(feature = "test-util")]
+        rt.start_paused(b.start_paused);
+        let rt = rt.enable_all().build()?;
 
         let rt = ManuallyDrop::new(rt);
 
@@ -95,7 +193,35 @@ impl Runtime {
 
         local.spawn_local(drive);
 
-        Ok(Runtime { local, rt, driver })
+        // Submits the operations scheduled for a retry when they are due
+        if let Some(notify) = driver.retry_notify() {
+            local.spawn_local(async move {
+                loop {
+                    let next =
+                        CONTEXT.with(|cx| cx.with_handle_mut(|driver| driver.resubmit_due()));
+                    match next {
+                        Some(deadline) => {
+                            let _ = tokio::time::timeout_at(deadline, notify.notified()).await;
+                        }
+                        None => notify.notified().await,
+                    }
+                }
+            });
+        }
+
+        Ok(Runtime {
+            local,
+            driver,
+            rt,
+            watchdog,
+        })
+    }
+
+    /// Returns a handle to the runtime.
+    pub fn handle(&self) -> Handle {
+        Handle {
+            inner: self.driver.clone(),
+        }
     }
 
     /// Runs a future to completion on the current runtime
@@ -103,18 +229,42 @@ impl Runtime {
     where
         F: Future,
     {
-        struct ContextGuard;
+        struct ContextGuard(bool);
 
         impl Drop for ContextGuard {
             fn drop(&mut self) {
-                CONTEXT.with(|cx| cx.unset_driver());
+                if self.0 {
+                    CONTEXT.with(|cx| cx.unset_driver());
+                }
             }
         }
 
-        CONTEXT.with(|cx| cx.set_handle(self.driver.clone()));
+        // The runtime may have been entered with `Handle::enter`
+        let entered = CONTEXT.with(|cx| cx.handle().is_some_and(|h| h.ptr_eq(&self.driver)));
+        if !entered {
+            CONTEXT.with(|cx| cx.set_handle(self.driver.clone()));
+        }
+
+        let _guard = ContextGuard(!entered);
 
-        let _guard = ContextGuard;
+        // Restores the stall detection state of an enclosing runtime
+        struct WatchdogGuard(Option<Option<Arc<watchdog::State>>>);
 
+        impl Drop for WatchdogGuard {
+            fn drop(&mut self) {
+                if let Some(prev) = self.0.take() {
+                    watchdog::set_current(prev);
+                }
+            }
+        }
+
+        let _watchdog = WatchdogGuard(
+            self.watchdog
+                .as_ref()
+                .map(|watchdog| watchdog::set_current(Some(watchdog.state()))),
+        );
+
+        let future = driver::budgeted(watchdog::tracked(future));
         tokio::pin!(future);
 
         let res = self
@@ -126,6 +276,51 @@ impl Runtime {
 
         res
     }
+
+    /// Shuts down the runtime, waiting at most `duration` for operations in
+    /// flight to terminate.
+    ///
+    /// All tasks are dropped, and all operations in flight, including
+    /// multishot operations, are canceled. The runtime then waits for the
+    /// kernel to complete the canceled operations, and for the functions
+    /// started with [`spawn_blocking`] to return, but no longer than
+    /// `duration` in total.
+    ///
+    /// Dropping the runtime waits for all operations indefinitely. With this
+    /// method, operations which have not terminated by the deadline are
+    /// abandoned instead: as the kernel may still access their buffers, the
+    /// memory of those buffers is leaked.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// let rt = tokio_uring::Runtime::new(&tokio_uring::builder()).unwrap();
+    /// rt.block_on(async {
+    ///     // ...
+    /// });
+    /// rt.shutdown_timeout(Duration::from_secs(5));
+    /// ```
+    pub fn shutdown_timeout(self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+
+        let mut this = ManuallyDrop::new(self);
+        // Safety: the fields are dropped or moved out once, and `Runtime::drop`
+        // is not run.
+        let (rt, driver, watchdog) = unsafe {
+            ManuallyDrop::drop(&mut this.local);
+            (
+                ManuallyDrop::take(&mut this.rt),
+                ptr::read(&this.driver),
+                ptr::read(&this.watchdog),
+            )
+        };
+        drop(watchdog);
+
+        rt.shutdown_timeout(deadline.saturating_duration_since(Instant::now()));
+        driver.shutdown(deadline);
+    }
 }
 
 impl Drop for Runtime {
@@ -147,13 +342,13 @@ mod test {
     #[test]
     fn block_on() {
         let rt = Runtime::new(&builder()).unwrap();
-        rt.block_on(async move { () });
+        rt.block_on(async move {});
     }
 
     #[test]
     fn block_on_twice() {
         let rt = Runtime::new(&builder()).unwrap();
-        rt.block_on(async move { () });
-        rt.block_on(async move { () });
+        rt.block_on(async move {});
+        rt.block_on(async move {});
     }
 }
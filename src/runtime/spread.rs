@@ -0,0 +1,198 @@
+use std::future::Future;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Spreads tasks across a set of worker threads, each running a
+/// `tokio-uring` runtime.
+///
+/// A runtime runs all of its tasks on one thread. A server accepting
+/// connections on one runtime and spawning their tasks there processes all
+/// of them on the accepting thread. With a `SpreadSpawner`, the accepting
+/// task hands each connection to one of the worker runtimes instead, which
+/// picks the next worker in turn, or the one running the fewest tasks if
+/// [`least_loaded`] is set.
+///
+/// The task is created on the worker by a `Send` closure, so that the
+/// task itself, and the resources it uses, need not be `Send`. Resources
+/// tied to a runtime, such as the [`TcpStream`] of an accepted connection,
+/// must be converted to a standard type to be moved to the worker, with
+/// [`TcpStream::into_std`], and back with [`TcpStream::from_std`].
+///
+/// The spawner can be cloned, and clones use the same workers. The workers
+/// stop once all clones have been dropped, dropping the tasks still running
+/// on them.
+///
+/// [`least_loaded`]: SpreadSpawner::least_loaded
+/// [`TcpStream`]: crate::net::TcpStream
+/// [`TcpStream::into_std`]: crate::net::TcpStream::into_std
+/// [`TcpStream::from_std`]: crate::net::TcpStream::from_std
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::net::{TcpListener, TcpStream};
+/// use tokio_uring::SpreadSpawner;
+///
+/// let spawner = SpreadSpawner::new(4).unwrap();
+///
+/// tokio_uring::start(async {
+///     let listener = TcpListener::bind("0.0.0.0:8080".parse().unwrap()).unwrap();
+///     loop {
+///         let (stream, _) = listener.accept().await.unwrap();
+///         let stream = stream.into_std().unwrap();
+///         spawner.spawn(move || async move {
+///             let stream = TcpStream::from_std(stream);
+///             let (res, _) = stream.write_all(b"hello\n".to_vec()).await;
+///             res.unwrap();
+///         });
+///     }
+/// });
+/// ```
+#[derive(Clone)]
+pub struct SpreadSpawner {
+    workers: Arc<[Worker]>,
+    next: Arc<AtomicUsize>,
+    least_loaded: bool,
+}
+
+struct Worker {
+    jobs: UnboundedSender<Job>,
+    // Number of tasks spawned on the worker which have not completed
+    load: Arc<AtomicUsize>,
+}
+
+// Decrements the load of a worker when its task completes or is dropped.
+struct LoadGuard(Arc<AtomicUsize>);
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl SpreadSpawner {
+    /// Starts `threads` worker threads, each running a runtime with the
+    /// default settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first runtime which could not be created.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is 0.
+    pub fn new(threads: usize) -> io::Result<SpreadSpawner> {
+        assert!(threads > 0, "a spawner needs at least one worker");
+
+        let mut workers = Vec::with_capacity(threads);
+        for i in 0..threads {
+            let (jobs, mut rx) = unbounded_channel::<Job>();
+            let (ready_tx, ready_rx) = mpsc::channel();
+            thread::Builder::new()
+                .name(format!("tokio-uring-worker-{}", i))
+                .spawn(move || {
+                    let rt = match super::Runtime::new(&crate::builder()) {
+                        Ok(rt) => rt,
+                        Err(e) => {
+                            let _ = ready_tx.send(Err(e));
+                            return;
+                        }
+                    };
+                    let _ = ready_tx.send(Ok(()));
+                    rt.block_on(async move {
+                        while let Some(job) = rx.recv().await {
+                            job();
+                        }
+                    });
+                })?;
+            ready_rx
+                .recv()
+                .map_err(|_| io::Error::other("the worker thread has panicked"))??;
+
+            workers.push(Worker {
+                jobs,
+                load: Arc::new(AtomicUsize::new(0)),
+            });
+        }
+
+        Ok(SpreadSpawner {
+            workers: workers.into(),
+            next: Arc::new(AtomicUsize::new(0)),
+            least_loaded: false,
+        })
+    }
+
+    /// Spawns the tasks on the worker running the fewest tasks, instead of
+    /// on each worker in turn.
+    ///
+    /// This evens out the load when the tasks vary in length, e.g. when some
+    /// connections are long-lived. The setting applies to this spawner and
+    /// the clones made from it afterwards.
+    pub fn least_loaded(&mut self, enabled: bool) -> &mut Self {
+        self.least_loaded = enabled;
+        self
+    }
+
+    /// Returns the number of worker threads.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Spawns a task on one of the workers.
+    ///
+    /// `f` is called on the worker thread to create the task, which is run
+    /// on the runtime of the worker as if spawned with [`spawn`].
+    ///
+    /// [`spawn`]: crate::spawn
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker has stopped, because its thread has panicked.
+    pub fn spawn<F, Fut>(&self, f: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let worker = &self.workers[self.pick()];
+        worker.load.fetch_add(1, Ordering::Relaxed);
+        let load = LoadGuard(worker.load.clone());
+
+        let job: Job = Box::new(move || {
+            super::spawn(async move {
+                let _load = load;
+                f().await;
+            });
+        });
+        if worker.jobs.send(job).is_err() {
+            panic!("the worker thread has stopped");
+        }
+    }
+
+    // Returns the index of the worker to spawn the next task on.
+    fn pick(&self) -> usize {
+        if self.least_loaded {
+            let start = self.next.fetch_add(1, Ordering::Relaxed);
+            // Ties are broken in turn, so that idle workers share the tasks
+            (0..self.workers.len())
+                .map(|i| (start + i) % self.workers.len())
+                .min_by_key(|&i| self.workers[i].load.load(Ordering::Relaxed))
+                .unwrap()
+        } else {
+            self.next.fetch_add(1, Ordering::Relaxed) % self.workers.len()
+        }
+    }
+}
+
+impl std::fmt::Debug for SpreadSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpreadSpawner")
+            .field("workers", &self.workers.len())
+            .field("least_loaded", &self.least_loaded)
+            .finish()
+    }
+}
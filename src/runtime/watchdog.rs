@@ -0,0 +1,247 @@
+use std::cell::RefCell;
+use std::future::{poll_fn, Future};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Wake, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    // Stall detection state of the runtime running on the thread, if any.
+    static CURRENT: RefCell<Option<Arc<State>>> = const { RefCell::new(None) };
+}
+
+/// Callback invoked when the runtime stalls, with the time it has been
+/// stalled for.
+pub(crate) type StallCallback = Arc<dyn Fn(Duration) + Send + Sync>;
+
+/// Detects the runtime thread parking without any io-uring operations in
+/// flight for longer than a threshold, or not polling any of the tasks
+/// ready to run for as long, e.g. because a task blocks the thread.
+///
+/// The state is updated by the park hooks of the runtime thread, and
+/// checked by a watchdog thread, as the runtime thread cannot observe
+/// itself while it is parked.
+pub(crate) struct Watchdog {
+    state: Arc<State>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+pub(crate) struct State {
+    epoch: Instant,
+
+    // Time since `epoch` the runtime parked without operations in flight,
+    // plus one, or zero while it runs or has operations in flight.
+    stalled_since: AtomicU64,
+
+    // Number of tasks woken and not polled since, and the time since
+    // `epoch` a task was last polled, plus one.
+    runnable: AtomicUsize,
+    polled_at: AtomicU64,
+
+    stop: AtomicBool,
+}
+
+impl Watchdog {
+    pub(crate) fn new(threshold: Duration, callback: StallCallback) -> Watchdog {
+        let state = Arc::new(State {
+            epoch: Instant::now(),
+            stalled_since: AtomicU64::new(0),
+            runnable: AtomicUsize::new(0),
+            polled_at: AtomicU64::new(1),
+            stop: AtomicBool::new(false),
+        });
+
+        let thread = {
+            let state = state.clone();
+            thread::Builder::new()
+                .name("tokio-uring-watchdog".into())
+                .spawn(move || state.watch(threshold, callback))
+                .expect("failed to spawn the watchdog thread")
+        };
+
+        Watchdog {
+            state,
+            thread: Some(thread),
+        }
+    }
+
+    pub(crate) fn state(&self) -> Arc<State> {
+        self.state.clone()
+    }
+}
+
+/// Returns the callback reporting stalls to `on_stall` and, with the
+/// `metrics` feature, to the metrics, or `None` if there is nothing to report
+/// them to.
+pub(crate) fn reporter(on_stall: Option<StallCallback>) -> Option<StallCallback> {
+    #[cfg(feature = "metrics")]
+    {
+        Some(Arc::new(move |stalled| {
+            crate::metrics::runtime_stalled();
+            if let Some(on_stall) = &on_stall {
+                on_stall(stalled);
+            }
+        }))
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    on_stall
+}
+
+/// Sets the state of the runtime running on the thread, returning the
+/// previous one.
+pub(crate) fn set_current(state: Option<Arc<State>>) -> Option<Arc<State>> {
+    CURRENT.with(|current| current.replace(state))
+}
+
+/// Runs a task, counting it as runnable while it is woken and waits to be
+/// polled, if the runtime running it detects stalls.
+pub(crate) async fn tracked<F: Future>(future: F) -> F::Output {
+    tokio::pin!(future);
+    let mut task = TaskGuard(None);
+
+    poll_fn(|cx| {
+        let Some(state) = CURRENT.with(|current| current.borrow().clone()) else {
+            return future.as_mut().poll(cx);
+        };
+        let task = task.0.get_or_insert_with(|| {
+            Arc::new(TrackedTask {
+                state: state.clone(),
+                status: AtomicU8::new(IDLE),
+                waker: Mutex::new(cx.waker().clone()),
+            })
+        });
+        task.polled(cx.waker());
+        state.polled();
+        let waker = Waker::from(task.clone());
+        future
+            .as_mut()
+            .poll(&mut std::task::Context::from_waker(&waker))
+    })
+    .await
+}
+
+// The status of a tracked task: waiting for an event, woken and waiting
+// to be polled, or completed or dropped, which wakers outliving the task
+// cannot change.
+const IDLE: u8 = 0;
+const RUNNABLE: u8 = 1;
+const DONE: u8 = 2;
+
+// A task tracked by the watchdog, waking the task it wraps.
+struct TrackedTask {
+    state: Arc<State>,
+    status: AtomicU8,
+    waker: Mutex<Waker>,
+}
+
+impl TrackedTask {
+    fn polled(&self, waker: &Waker) {
+        if self
+            .status
+            .compare_exchange(RUNNABLE, IDLE, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            self.state.runnable.fetch_sub(1, Ordering::Relaxed);
+        }
+        let mut current = self.waker.lock().unwrap();
+        if !current.will_wake(waker) {
+            *current = waker.clone();
+        }
+    }
+}
+
+impl Wake for TrackedTask {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if self
+            .status
+            .compare_exchange(IDLE, RUNNABLE, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            self.state.runnable.fetch_add(1, Ordering::Relaxed);
+        }
+        self.waker.lock().unwrap().wake_by_ref();
+    }
+}
+
+// Stops counting a task when it completes or is dropped.
+struct TaskGuard(Option<Arc<TrackedTask>>);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if let Some(task) = &self.0 {
+            if task.status.swap(DONE, Ordering::Relaxed) == RUNNABLE {
+                task.state.runnable.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl State {
+    // Called when a task is polled.
+    fn polled(&self) {
+        let now = self.epoch.elapsed().as_nanos() as u64 + 1;
+        self.polled_at.store(now, Ordering::Relaxed);
+    }
+
+    /// Called when the runtime thread parks.
+    pub(crate) fn park(&self, ops_in_flight: bool) {
+        let since = if ops_in_flight {
+            0
+        } else {
+            self.epoch.elapsed().as_nanos() as u64 + 1
+        };
+        self.stalled_since.store(since, Ordering::Relaxed);
+    }
+
+    /// Called when the runtime thread unparks.
+    pub(crate) fn unpark(&self) {
+        self.stalled_since.store(0, Ordering::Relaxed);
+    }
+
+    fn watch(&self, threshold: Duration, callback: StallCallback) {
+        let interval = (threshold / 4).max(Duration::from_millis(1));
+        // The stall last reported, to report each stall once.
+        let mut reported = 0;
+
+        while !self.stop.load(Ordering::Relaxed) {
+            thread::park_timeout(interval);
+
+            // Parked with nothing in flight, or with tasks ready to run
+            // which have not been polled
+            let since = match self.stalled_since.load(Ordering::Relaxed) {
+                0 if self.runnable.load(Ordering::Relaxed) > 0 => {
+                    self.polled_at.load(Ordering::Relaxed)
+                }
+                since => since,
+            };
+            if since == 0 || since == reported {
+                continue;
+            }
+
+            let stalled = self
+                .epoch
+                .elapsed()
+                .saturating_sub(Duration::from_nanos(since - 1));
+            if stalled >= threshold {
+                reported = since;
+                callback(stalled);
+            }
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,562 @@
+//! Deterministic simulation of files and sockets.
+//!
+//! A runtime started with [`Sim::run`] does not submit operations to the
+//! kernel, but performs them on in-memory files and sockets. Time is
+//! virtual: the clock of the runtime is paused, and advances to the next
+//! timer whenever all tasks are waiting, so that sleeps and timeouts
+//! complete instantly. Operations can be made to fail at random with
+//! [`Sim::fail_with`]. The failures are drawn from a generator seeded by
+//! the seed of the simulation, so that a failing run can be reproduced
+//! from its seed.
+//!
+//! The simulation supports opening, reading, writing, syncing, closing,
+//! renaming and removing files, and retrieving their metadata. Paths are
+//! compared as given, relative to the current directory; directories are
+//! not simulated. Sockets are simulated as connected pairs of streams,
+//! created with [`Sim::tcp_pair`] and [`Sim::unix_pair`], which can be
+//! read and written. Listeners, datagram sockets and socket options are
+//! not simulated. Other operations fail with `EOPNOTSUPP`.
+//!
+//! This module requires the `sim` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use tokio_uring::fs::File;
+//! use tokio_uring::sim::Sim;
+//!
+//! let sim = Sim::new(42);
+//! sim.write_file("config.txt", "hello");
+//!
+//! sim.run(async {
+//!     let file = File::open("config.txt").await.unwrap();
+//!     let (res, buf) = file.read_at(vec![0; 16], 0).await;
+//!     assert_eq!(&buf[..res.unwrap()], b"hello");
+//!     file.close().await.unwrap();
+//! });
+//! ```
+
+use crate::mock::{MockBackend, MockOp};
+use crate::net::{TcpStream, UnixStream};
+use io_uring::opcode;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{CStr, OsStr};
+use std::future::Future;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Simulated file descriptors are allocated above the range of real ones.
+const FIRST_FD: RawFd = 1 << 30;
+
+/// A simulated environment of files and sockets.
+///
+/// The environment is shared by the clones of a `Sim`, and outlives the
+/// runtimes run in it, so that the files written by a run can be checked
+/// after it.
+#[derive(Clone)]
+pub struct Sim {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    rng: u64,
+    fault: Option<(f64, i32)>,
+
+    // Contents of the files, indexed by inode number.
+    inodes: Vec<Vec<u8>>,
+    paths: HashMap<PathBuf, usize>,
+
+    fds: HashMap<RawFd, Fd>,
+    next_fd: RawFd,
+
+    // Reads of streams waiting for data.
+    pending: Vec<PendingRead>,
+    completed: Vec<(u64, io::Result<u32>)>,
+}
+
+enum Fd {
+    File {
+        ino: usize,
+        read: bool,
+        write: bool,
+        append: bool,
+    },
+    Stream {
+        peer: RawFd,
+        incoming: VecDeque<u8>,
+        peer_closed: bool,
+    },
+}
+
+struct PendingRead {
+    user_data: u64,
+    fd: RawFd,
+    bufs: Vec<(u64, usize)>,
+}
+
+impl Sim {
+    /// Creates an empty environment, with the seed of the generator of
+    /// failures.
+    pub fn new(seed: u64) -> Sim {
+        Sim {
+            state: Arc::new(Mutex::new(State {
+                // The generator must not start at zero
+                rng: (seed ^ 0x9e37_79b9_7f4a_7c15).max(1),
+                fault: None,
+                inodes: Vec::new(),
+                paths: HashMap::new(),
+                fds: HashMap::new(),
+                next_fd: FIRST_FD,
+                pending: Vec::new(),
+                completed: Vec::new(),
+            })),
+        }
+    }
+
+    /// Creates a file with the given contents, replacing the contents of the
+    /// file if it exists.
+    pub fn write_file(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) {
+        let mut state = self.state();
+        let ino = state.create(path.as_ref());
+        state.inodes[ino] = contents.as_ref().to_vec();
+    }
+
+    /// Returns the contents of a file, or `None` if it does not exist.
+    pub fn read_file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        let state = self.state();
+        let ino = *state.paths.get(path.as_ref())?;
+        Some(state.inodes[ino].clone())
+    }
+
+    /// Makes operations fail with the error `errno`, with the given
+    /// probability between 0 and 1.
+    ///
+    /// Closing a file descriptor never fails. A probability of zero turns
+    /// the failures off.
+    pub fn fail_with(&self, probability: f64, errno: i32) -> &Self {
+        self.state().fault = Some((probability, errno));
+        self
+    }
+
+    /// Creates a pair of connected TCP streams.
+    ///
+    /// Data written to one stream can be read from the other. Once a
+    /// stream is closed, reads of the other one return 0 bytes, and writes
+    /// fail with `EPIPE`. The streams must be used in [`run`].
+    ///
+    /// [`run`]: Sim::run
+    pub fn tcp_pair(&self) -> (TcpStream, TcpStream) {
+        let (a, b) = self.state().stream_pair();
+        // Safety: the descriptors are only used by the simulation.
+        unsafe { (TcpStream::from_raw_fd(a), TcpStream::from_raw_fd(b)) }
+    }
+
+    /// Creates a pair of connected Unix streams.
+    ///
+    /// See [`tcp_pair`] for the behavior of the streams.
+    ///
+    /// [`tcp_pair`]: Sim::tcp_pair
+    pub fn unix_pair(&self) -> (UnixStream, UnixStream) {
+        let (a, b) = self.state().stream_pair();
+        // Safety: the descriptors are only used by the simulation.
+        unsafe { (UnixStream::from_raw_fd(a), UnixStream::from_raw_fd(b)) }
+    }
+
+    /// Starts a runtime in the environment, and runs a future to completion
+    /// on it.
+    ///
+    /// The clock of the runtime starts paused. See [`crate::start`] for
+    /// the rest of the behavior of the runtime.
+    pub fn run<F: Future>(&self, future: F) -> F::Output {
+        let mut builder = crate::builder();
+        builder.mock = Some(Arc::new(self.clone()));
+        builder.start_paused = true;
+        builder.start(future)
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for Sim {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state();
+        f.debug_struct("Sim")
+            .field("files", &state.paths.keys().collect::<Vec<_>>())
+            .field("open_fds", &state.fds.len())
+            .finish()
+    }
+}
+
+impl MockBackend for Sim {
+    fn submit(&self, op: &mut MockOp<'_>) -> Option<io::Result<u32>> {
+        let mut state = self.state();
+        if op.opcode() != opcode::Close::CODE {
+            if let Some((probability, errno)) = state.fault {
+                if state.random() < probability {
+                    return Some(Err(io::Error::from_raw_os_error(errno)));
+                }
+            }
+        }
+        // Safety: the buffers of the operation are valid until it
+        // completes, as they would be for the kernel.
+        unsafe { state.submit(op) }
+    }
+
+    fn cancel(&self, user_data: u64) {
+        let mut state = self.state();
+        if let Some(i) = state
+            .pending
+            .iter()
+            .position(|read| read.user_data == user_data)
+        {
+            state.pending.remove(i);
+            state.completed.push((
+                user_data,
+                Err(io::Error::from_raw_os_error(libc::ECANCELED)),
+            ));
+        }
+    }
+
+    fn completions(&self, out: &mut Vec<(u64, io::Result<u32>)>) {
+        out.append(&mut self.state().completed);
+    }
+}
+
+impl State {
+    fn random(&mut self) -> f64 {
+        // xorshift64*
+        let mut x = self.rng;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.rng = x;
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn create(&mut self, path: &Path) -> usize {
+        if let Some(&ino) = self.paths.get(path) {
+            return ino;
+        }
+        self.inodes.push(Vec::new());
+        let ino = self.inodes.len() - 1;
+        self.paths.insert(path.to_path_buf(), ino);
+        ino
+    }
+
+    fn allocate_fd(&mut self, fd: Fd) -> RawFd {
+        let n = self.next_fd;
+        self.next_fd += 1;
+        self.fds.insert(n, fd);
+        n
+    }
+
+    fn stream_pair(&mut self) -> (RawFd, RawFd) {
+        let a = self.next_fd;
+        let b = a + 1;
+        for peer in [b, a] {
+            self.allocate_fd(Fd::Stream {
+                peer,
+                incoming: VecDeque::new(),
+                peer_closed: false,
+            });
+        }
+        (a, b)
+    }
+
+    unsafe fn submit(&mut self, op: &MockOp<'_>) -> Option<io::Result<u32>> {
+        let sqe = op.sqe();
+        match sqe.opcode {
+            opcode::Nop::CODE => Some(Ok(0)),
+            opcode::OpenAt::CODE => Some(self.open(sqe.fd, path(sqe.addr), sqe.op_flags as i32)),
+            opcode::Close::CODE => Some(self.close(sqe.fd)),
+            opcode::Fsync::CODE => Some(match self.fds.get(&sqe.fd) {
+                Some(_) => Ok(0),
+                None => Err(errno(libc::EBADF)),
+            }),
+            opcode::Read::CODE | opcode::Recv::CODE => {
+                let bufs = vec![(sqe.addr, sqe.len as usize)];
+                self.read(sqe.user_data, sqe.fd, bufs, sqe.off)
+            }
+            opcode::Readv::CODE => {
+                let bufs = iovecs(sqe.addr, sqe.len);
+                self.read(sqe.user_data, sqe.fd, bufs, sqe.off)
+            }
+            opcode::Write::CODE | opcode::Send::CODE => {
+                let data = std::slice::from_raw_parts(sqe.addr as *const u8, sqe.len as usize);
+                Some(self.write(sqe.fd, data, sqe.off))
+            }
+            opcode::Writev::CODE => {
+                let data: Vec<u8> = iovecs(sqe.addr, sqe.len)
+                    .into_iter()
+                    .flat_map(|(addr, len)| std::slice::from_raw_parts(addr as *const u8, len))
+                    .copied()
+                    .collect();
+                Some(self.write(sqe.fd, &data, sqe.off))
+            }
+            opcode::Statx::CODE => {
+                let res = self.statx(sqe.fd, path(sqe.addr), sqe.op_flags as i32);
+                Some(res.map(|statx| {
+                    (sqe.off as *mut libc::statx).write_unaligned(statx);
+                    0
+                }))
+            }
+            opcode::UnlinkAt::CODE => {
+                Some(self.unlink(sqe.fd, path(sqe.addr), sqe.op_flags as i32))
+            }
+            opcode::RenameAt::CODE => Some(self.rename(
+                sqe.fd,
+                path(sqe.addr),
+                sqe.len as RawFd,
+                path(sqe.off),
+                sqe.op_flags,
+            )),
+            _ => Some(Err(errno(libc::EOPNOTSUPP))),
+        }
+    }
+
+    fn open(&mut self, dirfd: RawFd, path: PathBuf, flags: i32) -> io::Result<u32> {
+        if dirfd != libc::AT_FDCWD || flags & libc::O_DIRECTORY != 0 {
+            return Err(errno(libc::EOPNOTSUPP));
+        }
+        let ino = match self.paths.get(&path) {
+            Some(_) if flags & libc::O_CREAT != 0 && flags & libc::O_EXCL != 0 => {
+                return Err(errno(libc::EEXIST));
+            }
+            Some(&ino) => ino,
+            None if flags & libc::O_CREAT != 0 => self.create(&path),
+            None => return Err(errno(libc::ENOENT)),
+        };
+        let (read, write) = match flags & libc::O_ACCMODE {
+            libc::O_RDONLY => (true, false),
+            libc::O_WRONLY => (false, true),
+            _ => (true, true),
+        };
+        if write && flags & libc::O_TRUNC != 0 {
+            self.inodes[ino].clear();
+        }
+        let fd = self.allocate_fd(Fd::File {
+            ino,
+            read,
+            write,
+            append: flags & libc::O_APPEND != 0,
+        });
+        Ok(fd as u32)
+    }
+
+    fn close(&mut self, fd: RawFd) -> io::Result<u32> {
+        match self.fds.remove(&fd) {
+            Some(Fd::Stream { peer, .. }) => {
+                if let Some(Fd::Stream { peer_closed, .. }) = self.fds.get_mut(&peer) {
+                    *peer_closed = true;
+                }
+                self.complete_reads(peer);
+                Ok(0)
+            }
+            Some(Fd::File { .. }) => Ok(0),
+            None => Err(errno(libc::EBADF)),
+        }
+    }
+
+    unsafe fn read(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        bufs: Vec<(u64, usize)>,
+        offset: u64,
+    ) -> Option<io::Result<u32>> {
+        match self.fds.get_mut(&fd) {
+            Some(Fd::File { ino, read, .. }) => {
+                if !*read {
+                    return Some(Err(errno(libc::EBADF)));
+                }
+                if offset == u64::MAX {
+                    // The file position is not simulated
+                    return Some(Err(errno(libc::EOPNOTSUPP)));
+                }
+                let data = &self.inodes[*ino];
+                let start = (offset as usize).min(data.len());
+                Some(Ok(copy_to(&bufs, &data[start..]) as u32))
+            }
+            Some(Fd::Stream { .. }) => {
+                self.pending.push(PendingRead {
+                    user_data,
+                    fd,
+                    bufs,
+                });
+                self.complete_reads(fd);
+                None
+            }
+            None => Some(Err(errno(libc::EBADF))),
+        }
+        .or_else(|| {
+            // Return the result of a read completed right away
+            let i = self
+                .completed
+                .iter()
+                .position(|(done, _)| *done == user_data)?;
+            Some(self.completed.remove(i).1)
+        })
+    }
+
+    // Completes the pending reads of a stream which can be completed.
+    fn complete_reads(&mut self, fd: RawFd) {
+        let (incoming, peer_closed) = match self.fds.get_mut(&fd) {
+            Some(Fd::Stream {
+                incoming,
+                peer_closed,
+                ..
+            }) => (incoming, *peer_closed),
+            _ => return,
+        };
+        let mut i = 0;
+        while i < self.pending.len() {
+            if self.pending[i].fd != fd {
+                i += 1;
+                continue;
+            }
+            if incoming.is_empty() && !peer_closed {
+                break;
+            }
+            let read = self.pending.remove(i);
+            let (data, _) = incoming.as_slices();
+            // Safety: the buffers of pending reads stay valid until the
+            // reads complete.
+            let n = unsafe { copy_to(&read.bufs, data) };
+            incoming.drain(..n);
+            self.completed.push((read.user_data, Ok(n as u32)));
+        }
+    }
+
+    fn write(&mut self, fd: RawFd, data: &[u8], offset: u64) -> io::Result<u32> {
+        match self.fds.get(&fd) {
+            Some(&Fd::File {
+                ino, write, append, ..
+            }) => {
+                if !write {
+                    return Err(errno(libc::EBADF));
+                }
+                if offset == u64::MAX && !append {
+                    return Err(errno(libc::EOPNOTSUPP));
+                }
+                let file = &mut self.inodes[ino];
+                let start = if append { file.len() } else { offset as usize };
+                let end = start + data.len();
+                if file.len() < end {
+                    file.resize(end, 0);
+                }
+                file[start..end].copy_from_slice(data);
+                Ok(data.len() as u32)
+            }
+            Some(&Fd::Stream {
+                peer,
+                peer_closed: false,
+                ..
+            }) => {
+                if let Some(Fd::Stream { incoming, .. }) = self.fds.get_mut(&peer) {
+                    incoming.extend(data);
+                }
+                self.complete_reads(peer);
+                Ok(data.len() as u32)
+            }
+            Some(Fd::Stream { .. }) => Err(errno(libc::EPIPE)),
+            None => Err(errno(libc::EBADF)),
+        }
+    }
+
+    fn statx(&self, dirfd: RawFd, path: PathBuf, flags: i32) -> io::Result<libc::statx> {
+        let (ino, size, mode) = if path.as_os_str().is_empty() && flags & libc::AT_EMPTY_PATH != 0 {
+            match self.fds.get(&dirfd) {
+                Some(&Fd::File { ino, .. }) => (ino, self.inodes[ino].len(), libc::S_IFREG | 0o644),
+                Some(Fd::Stream { incoming, .. }) => (0, incoming.len(), libc::S_IFSOCK | 0o777),
+                None => return Err(errno(libc::EBADF)),
+            }
+        } else if dirfd != libc::AT_FDCWD {
+            return Err(errno(libc::EOPNOTSUPP));
+        } else {
+            match self.paths.get(&path) {
+                Some(&ino) => (ino, self.inodes[ino].len(), libc::S_IFREG | 0o644),
+                None => return Err(errno(libc::ENOENT)),
+            }
+        };
+
+        // Safety: the structure is plain data, and all zeroes is a valid
+        // value.
+        let mut statx: libc::statx = unsafe { std::mem::zeroed() };
+        statx.stx_mask = libc::STATX_TYPE
+            | libc::STATX_MODE
+            | libc::STATX_NLINK
+            | libc::STATX_INO
+            | libc::STATX_SIZE
+            | libc::STATX_BLOCKS;
+        statx.stx_blksize = 4096;
+        statx.stx_nlink = 1;
+        statx.stx_mode = mode as u16;
+        statx.stx_ino = ino as u64 + 1;
+        statx.stx_size = size as u64;
+        statx.stx_blocks = (size as u64).div_ceil(512);
+        Ok(statx)
+    }
+
+    fn unlink(&mut self, dirfd: RawFd, path: PathBuf, flags: i32) -> io::Result<u32> {
+        if dirfd != libc::AT_FDCWD || flags & libc::AT_REMOVEDIR != 0 {
+            return Err(errno(libc::EOPNOTSUPP));
+        }
+        match self.paths.remove(&path) {
+            Some(_) => Ok(0),
+            None => Err(errno(libc::ENOENT)),
+        }
+    }
+
+    fn rename(
+        &mut self,
+        olddirfd: RawFd,
+        oldpath: PathBuf,
+        newdirfd: RawFd,
+        newpath: PathBuf,
+        flags: u32,
+    ) -> io::Result<u32> {
+        if olddirfd != libc::AT_FDCWD || newdirfd != libc::AT_FDCWD || flags != 0 {
+            return Err(errno(libc::EOPNOTSUPP));
+        }
+        match self.paths.remove(&oldpath) {
+            Some(ino) => {
+                self.paths.insert(newpath, ino);
+                Ok(0)
+            }
+            None => Err(errno(libc::ENOENT)),
+        }
+    }
+}
+
+fn errno(code: i32) -> io::Error {
+    io::Error::from_raw_os_error(code)
+}
+
+unsafe fn path(addr: u64) -> PathBuf {
+    let path = CStr::from_ptr(addr as *const libc::c_char);
+    PathBuf::from(OsStr::from_bytes(path.to_bytes()))
+}
+
+unsafe fn iovecs(addr: u64, count: u32) -> Vec<(u64, usize)> {
+    std::slice::from_raw_parts(addr as *const libc::iovec, count as usize)
+        .iter()
+        .map(|iov| (iov.iov_base as u64, iov.iov_len))
+        .collect()
+}
+
+// Copies data to the buffers in order, returning the number of bytes copied.
+unsafe fn copy_to(bufs: &[(u64, usize)], mut data: &[u8]) -> usize {
+    let mut copied = 0;
+    for &(addr, len) in bufs {
+        let n = len.min(data.len());
+        std::ptr::copy_nonoverlapping(data.as_ptr(), addr as *mut u8, n);
+        copied += n;
+        data = &data[n..];
+    }
+    copied
+}
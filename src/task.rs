@@ -0,0 +1,126 @@
+//! Utilities for managing tasks on the `tokio-uring` runtime.
+
+use std::fmt;
+use std::future::Future;
+use tokio::task::{AbortHandle, JoinError, JoinSet};
+
+/// A collection of tasks spawned on the current `tokio-uring` runtime.
+///
+/// This is the counterpart of [`tokio::task::JoinSet`] for the tasks of this
+/// runtime, which are not required to be `Send`. It can be used, for
+/// example, to keep track of the connection tasks of a server, so that they
+/// can be waited for or aborted on shutdown.
+///
+/// All tasks in the set are aborted when the set is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio_uring::net::TcpListener;
+/// use tokio_uring::task::LocalJoinSet;
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         let listener = TcpListener::bind("127.0.0.1:8080".parse().unwrap())?;
+///         let mut connections = LocalJoinSet::new();
+///
+///         for _ in 0..100 {
+///             let (stream, _) = listener.accept().await?;
+///             connections.spawn(async move {
+///                 let (res, _) = stream.write_all(b"hello".as_slice()).await;
+///                 res
+///             });
+///
+///             // Reap finished connections
+///             while let Some(res) = connections.try_join_next() {
+///                 if let Ok(Err(e)) = res {
+///                     eprintln!("connection error: {}", e);
+///                 }
+///             }
+///         }
+///
+///         connections.abort_all();
+///         Ok(())
+///     })
+/// }
+/// ```
+pub struct LocalJoinSet<T> {
+    inner: JoinSet<T>,
+}
+
+impl<T: 'static> LocalJoinSet<T> {
+    /// Creates a new, empty set.
+    pub fn new() -> Self {
+        LocalJoinSet {
+            inner: JoinSet::new(),
+        }
+    }
+
+    /// Returns the number of tasks in the set.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the set contains no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Spawns a task on the current runtime and adds it to the set,
+    /// returning a handle which can be used to abort the task.
+    ///
+    /// This function must be called from the context of a `tokio-uring`
+    /// runtime.
+    pub fn spawn<F>(&mut self, task: F) -> AbortHandle
+    where
+        F: Future<Output = T> + 'static,
+    {
+        self.inner.spawn_local(task)
+    }
+
+    /// Waits for one of the tasks in the set to complete and returns its
+    /// output, removing it from the set.
+    ///
+    /// Returns `None` if the set is empty. An error is returned if the task
+    /// panicked or was aborted.
+    pub async fn join_next(&mut self) -> Option<Result<T, JoinError>> {
+        self.inner.join_next().await
+    }
+
+    /// Returns the output of a completed task without waiting, removing it
+    /// from the set.
+    ///
+    /// Returns `None` if the set is empty or no task has completed yet.
+    pub fn try_join_next(&mut self) -> Option<Result<T, JoinError>> {
+        self.inner.try_join_next()
+    }
+
+    /// Aborts all tasks in the set.
+    ///
+    /// The tasks are removed from the set once [`join_next`] returns their
+    /// cancellation errors.
+    ///
+    /// [`join_next`]: LocalJoinSet::join_next
+    pub fn abort_all(&mut self) {
+        self.inner.abort_all()
+    }
+
+    /// Aborts all tasks in the set and waits for them to finish.
+    pub async fn shutdown(&mut self) {
+        self.inner.shutdown().await
+    }
+}
+
+impl<T: 'static> Default for LocalJoinSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for LocalJoinSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalJoinSet")
+            .field("len", &self.inner.len())
+            .finish()
+    }
+}
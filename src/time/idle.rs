@@ -0,0 +1,201 @@
+use crate::buf::{BoundedBuf, BoundedBufMut};
+use crate::io::{Duplex, Socket, Timeout};
+use crate::runtime::driver::op::Op;
+use std::cell::{Cell, RefCell};
+use std::future::{poll_fn, Future};
+use std::net::Shutdown;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// A stream shut down once it has been idle for a given duration.
+///
+/// Each read or write completed through the wrapper resets the idle
+/// period. When the period elapses with no read or write completed, both
+/// directions of the stream are shut down: reads in flight and later reads
+/// return 0, as at the end of the stream, and writes fail with a broken
+/// pipe error. [`is_expired`] tells this apart from the peer closing the
+/// connection.
+///
+/// The period is timed with a single `IORING_OP_TIMEOUT` operation, kept in
+/// flight by a task spawned on the runtime. The reads and writes only
+/// record the time they complete at; when the timeout elapses early, it is
+/// submitted again for the rest of the period.
+///
+/// [`is_expired`]: IdleTimeout::is_expired
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio_uring::net::TcpListener;
+/// use tokio_uring::time::IdleTimeout;
+///
+/// tokio_uring::start(async {
+///     let listener = TcpListener::bind("127.0.0.1:8080".parse().unwrap()).unwrap();
+///
+///     loop {
+///         let (stream, _) = listener.accept().await.unwrap();
+///         let stream = IdleTimeout::new(stream, Duration::from_secs(60));
+///         tokio_uring::spawn(async move {
+///             let mut buf = vec![0; 4096];
+///             loop {
+///                 let (res, b) = stream.read(buf).await;
+///                 match res {
+///                     Ok(0) | Err(_) => break,
+///                     Ok(_) => {}
+///                 }
+///                 buf = b;
+///             }
+///             if stream.is_expired() {
+///                 println!("closed an idle connection");
+///             }
+///         });
+///     }
+/// });
+/// ```
+pub struct IdleTimeout<T> {
+    inner: T,
+    state: Rc<State>,
+    _timer: Timer,
+}
+
+struct State {
+    period: Duration,
+    last_active: Cell<Instant>,
+    expired: Cell<bool>,
+    stopped: Cell<bool>,
+
+    // The timeout in flight, cancelled when the wrapper is dropped
+    op: RefCell<Option<Op<Timeout>>>,
+}
+
+impl<T: Duplex> IdleTimeout<T> {
+    /// Wraps a stream, shutting it down once no read or write has
+    /// completed through the wrapper for `period`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub fn new(inner: T, period: Duration) -> IdleTimeout<T> {
+        let state = Rc::new(State {
+            period,
+            last_active: Cell::new(Instant::now()),
+            expired: Cell::new(false),
+            stopped: Cell::new(false),
+            op: RefCell::new(None),
+        });
+        crate::spawn(run_timer(state.clone(), inner.socket().clone()));
+        IdleTimeout {
+            inner,
+            _timer: Timer(state.clone()),
+            state,
+        }
+    }
+
+    /// Reads some data from the stream into the buffer, like the `read`
+    /// method of the stream, resetting the idle period once it completes.
+    pub async fn read<B: BoundedBufMut>(&self, buf: B) -> crate::BufResult<usize, B> {
+        let res = self.inner.socket().read(buf).await;
+        self.touch();
+        res
+    }
+
+    /// Writes some data from the buffer to the stream, like the `write`
+    /// method of the stream, resetting the idle period once it completes.
+    pub async fn write<B: BoundedBuf>(&self, buf: B) -> crate::BufResult<usize, B> {
+        let res = self.inner.socket().write(buf).await;
+        self.touch();
+        res
+    }
+
+    /// Writes all of the buffer to the stream, like the `write_all` method
+    /// of the stream, resetting the idle period once it completes.
+    pub async fn write_all<B: BoundedBuf>(&self, buf: B) -> crate::BufResult<(), B> {
+        let res = self.inner.socket().write_all(buf).await;
+        self.touch();
+        res
+    }
+
+    /// Returns `true` if the stream has been shut down for being idle.
+    pub fn is_expired(&self) -> bool {
+        self.state.expired.get()
+    }
+
+    /// Returns the idle period.
+    pub fn period(&self) -> Duration {
+        self.state.period
+    }
+
+    /// Returns the wrapped stream, for the operations which do not reset
+    /// the idle period.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Stops timing the idle period, and returns the wrapped stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn touch(&self) {
+        self.state.last_active.set(Instant::now());
+    }
+}
+
+impl<T> std::fmt::Debug for IdleTimeout<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdleTimeout")
+            .field("period", &self.state.period)
+            .field("expired", &self.state.expired.get())
+            .finish_non_exhaustive()
+    }
+}
+
+// Stops the timer task when the wrapper is dropped or unwrapped.
+struct Timer(Rc<State>);
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.0.stopped.set(true);
+        if let Some(op) = &*self.0.op.borrow() {
+            op.cancel();
+        }
+    }
+}
+
+async fn run_timer(state: Rc<State>, socket: Socket) {
+    while !state.stopped.get() {
+        let deadline = state.last_active.get() + state.period;
+        let now = Instant::now();
+        if deadline <= now {
+            state.expired.set(true);
+            let _ = socket.shutdown(Shutdown::Both);
+            return;
+        }
+        sleep(&state, deadline - now).await;
+    }
+}
+
+// Waits with a timeout operation, or with a Tokio timer on a runtime which
+// does not support them.
+async fn sleep(state: &State, duration: Duration) {
+    let op = match Op::timeout(duration) {
+        Ok(op) => op,
+        Err(_) => return tokio::time::sleep(duration).await,
+    };
+    *state.op.borrow_mut() = Some(op);
+    let res = poll_fn(|cx| {
+        let mut op = state.op.borrow_mut();
+        Pin::new(op.as_mut().unwrap()).poll(cx)
+    })
+    .await;
+    state.op.borrow_mut().take();
+
+    if let Err(e) = res {
+        if matches!(e.raw_os_error(), Some(libc::EINVAL | libc::EOPNOTSUPP)) {
+            tokio::time::sleep(duration).await;
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Utilities for tracking time.
+//!
+//! This module provides a timeout and a deadline for futures performing
+//! io-uring operations, which cancel the operations themselves when they
+//! elapse, and an idle timeout for streams.
+//! Timers and the other time utilities are provided by [`tokio::time`].
+
+mod idle;
+pub use idle::IdleTimeout;
+
+use crate::runtime::driver::{scoped, Scope};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+pub use tokio::time::error::Elapsed;
+
+/// Requires a future to complete before `duration` has elapsed, cancelling
+/// its io-uring operations at the deadline.
+///
+/// As with `tokio::time::timeout`, the future is dropped and [`Elapsed`]
+/// is returned if it has not completed by the deadline, whatever it is
+/// waiting for, be it an operation, a channel or a lock. In addition, each
+/// of the operations it submits is bounded by a linked timeout
+/// (`IORING_OP_LINK_TIMEOUT`), so the kernel cancels it at the deadline even
+/// if the runtime is busy, rather than leaving it in flight after the future
+/// is dropped. Operations submitted after the deadline fail immediately.
+/// The operations of tasks spawned by the future are not affected. Nested
+/// timeouts end at the earliest of their deadlines.
+///
+/// Since the kernel and the runtime measure the deadline separately, an
+/// operation canceled by the kernel may complete before the runtime notices
+/// the deadline: the future then completes, with an error of the
+/// [`TimedOut`] kind from the operation. To keep the buffers of the
+/// canceled operations in any case, use [`with_deadline`], which lets the
+/// future complete.
+///
+/// [`TimedOut`]: std::io::ErrorKind::TimedOut
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio_uring::net::TcpStream;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     tokio_uring::start(async {
+///         let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await?;
+///
+///         let buf = vec![0; 4096];
+///         let res = tokio_uring::time::timeout(Duration::from_secs(5), stream.read(buf)).await;
+///         match res {
+///             Ok((Ok(n), buf)) => println!("{:?}", &buf[..n]),
+///             Ok((Err(e), _)) if e.kind() != std::io::ErrorKind::TimedOut => return Err(e.into()),
+///             _ => println!("timed out"),
+///         }
+///
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    let deadline = Instant::now() + duration;
+    tokio::time::timeout_at(deadline, with_deadline(deadline, future)).await
+}
+
+/// Runs a future performing io-uring operations, cancelling the operations
+/// which have not completed by `deadline`.
+///
+/// Each of the operations submitted by the future, however deeply nested in
+/// the calls it makes, is submitted with a linked timeout ending at the
+/// deadline. A deadline set for a request so bounds all of the I/O done to
+/// serve it, without passing durations down to each call, and the time left
+/// can be looked up with [`deadline`], e.g. to pass it on to a remote
+/// service.
+///
+/// Unlike [`timeout`], the future is not dropped at the deadline but polled
+/// to completion: the canceled operations complete with an error of the
+/// [`TimedOut`] kind, returning their buffers, so the buffers are back in
+/// the hands of the caller by the deadline. Waiting for anything else, such
+/// as a channel or a Tokio resource, is not interrupted. A deadline nested
+/// in another ends at the earliest of the two.
+///
+/// [`TimedOut`]: std::io::ErrorKind::TimedOut
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio::time::Instant;
+/// use tokio_uring::net::TcpStream;
+///
+/// async fn query(stream: &TcpStream) -> std::io::Result<Vec<u8>> {
+///     // Bounded by the deadline of the caller
+///     let (res, _) = stream.write_all(b"query".to_vec()).await;
+///     res?;
+///     let (res, mut buf) = stream.read(vec![0; 4096]).await;
+///     buf.truncate(res?);
+///     Ok(buf)
+/// }
+///
+/// fn main() -> std::io::Result<()> {
+///     tokio_uring::start(async {
+///         let stream = TcpStream::connect("127.0.0.1:8080".parse().unwrap()).await?;
+///         let deadline = Instant::now() + Duration::from_millis(200);
+///         let response = tokio_uring::time::with_deadline(deadline, query(&stream)).await?;
+///         println!("{:?}", response);
+///         Ok(())
+///     })
+/// }
+/// ```
+pub async fn with_deadline<F: Future>(deadline: Instant, future: F) -> F::Output {
+    scoped(
+        Scope::new(Some(deadline)),
+        tokio::time::sleep_until(deadline),
+        future,
+    )
+    .await
+}
+
+/// Returns the deadline of the io-uring operations of the future being
+/// polled, set by the innermost [`timeout`] or [`with_deadline`] it runs
+/// in, or the earliest one if they are nested. Returns `None` outside of
+/// them, and in the tasks they spawn.
+pub fn deadline() -> Option<Instant> {
+    Scope::current()?.deadline()
+}
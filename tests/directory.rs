@@ -6,3 +6,57 @@ fn basic_remove_dir() {
         assert!(std::fs::metadata(temp_dir.path()).is_err());
     });
 }
+
+#[test]
+fn sync_dir() {
+    tokio_uring::start(async {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("file"), b"data").unwrap();
+        tokio_uring::fs::sync_dir(temp_dir.path()).await.unwrap();
+
+        let err = tokio_uring::fs::sync_dir(temp_dir.path().join("file"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+    });
+}
+
+#[test]
+fn read_dir_entries() {
+    tokio_uring::start(async {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("file"), b"data").unwrap();
+        std::fs::create_dir(temp_dir.path().join("subdir")).unwrap();
+        std::os::unix::fs::symlink("file", temp_dir.path().join("link")).unwrap();
+        // More entries than fit in one batch of the reads
+        for i in 0..2000 {
+            std::fs::write(temp_dir.path().join(format!("many{:04}", i)), b"").unwrap();
+        }
+
+        let mut entries = tokio_uring::fs::read_dir(temp_dir.path()).await.unwrap();
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            let name = entry.file_name().into_string().unwrap();
+            assert_eq!(entry.path(), temp_dir.path().join(&name));
+
+            let metadata = entry.metadata().await.unwrap();
+            match name.as_str() {
+                "file" => {
+                    assert!(metadata.is_file());
+                    assert_eq!(metadata.len(), 4);
+                }
+                "subdir" => assert!(metadata.is_dir()),
+                // The link is not followed
+                "link" => assert!(metadata.is_symlink()),
+                _ => assert!(metadata.is_file()),
+            }
+            names.push(name);
+        }
+        assert!(entries.next_entry().await.unwrap().is_none());
+
+        names.sort();
+        assert_eq!(names.len(), 2003);
+        assert_eq!(names[..3], ["file", "link", "many0000"]);
+        assert_eq!(names[2002], "subdir");
+    });
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "fallback")]
+
+use std::time::Duration;
+use tokio_uring::buf::BoundedBuf;
+use tokio_uring::fs::{File, LogWriter};
+use tokio_uring::net::{TcpListener, TcpStream};
+use tokio_uring::Handle;
+
+#[test]
+fn file_io() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data");
+
+    tokio_uring::builder().force_fallback(true).start(async {
+        assert!(Handle::current().is_fallback());
+
+        let file = File::create(&path).await.unwrap();
+        file.write_all_at(&b"hello world"[..], 0).await.0.unwrap();
+        file.sync_all().await.unwrap();
+        assert_eq!(file.metadata().await.unwrap().len(), 11);
+        file.close().await.unwrap();
+
+        // Linked operations
+        let file = tokio_uring::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .await
+            .unwrap();
+        let log = LogWriter::new(&file, 11);
+        assert_eq!(log.append(b"!".to_vec()).await.unwrap(), 11);
+        drop(log);
+        file.close().await.unwrap();
+
+        let file = File::open(&path).await.unwrap();
+        let (res, buf) = file.read_at(vec![0; 32], 6).await;
+        assert_eq!(&buf[..res.unwrap()], b"world!");
+        file.close().await.unwrap();
+    });
+}
+
+#[test]
+fn tcp_and_cancellation() {
+    tokio_uring::builder().force_fallback(true).start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio_uring::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (res, buf) = stream.read(vec![0; 16]).await;
+            stream.write_all(buf.slice(..res.unwrap())).await.0.unwrap();
+
+            // Nothing more is sent, the read waits until it is canceled
+            let res =
+                tokio_uring::time::timeout(Duration::from_millis(100), stream.read(vec![0; 16]))
+                    .await;
+            if let Ok((res, _)) = res {
+                assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+            }
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(&b"ping"[..]).await.0.unwrap();
+        let (res, buf) = stream.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"ping");
+
+        server.await.unwrap();
+    });
+}
+
+#[test]
+fn watch() {
+    tokio_uring::builder().force_fallback(true).start(async {
+        let dir = tempfile::tempdir().unwrap();
+        let mut watcher = tokio_uring::fs::watch(dir.path()).unwrap();
+
+        // The poll completes once on this backend, and is submitted again
+        for name in ["a", "b"].iter() {
+            let path = dir.path().join(name);
+            let create = tokio_uring::spawn({
+                let path = path.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    std::fs::write(path, b"").unwrap();
+                }
+            });
+            while watcher.next_event().await.unwrap().path() != path {}
+            create.await.unwrap();
+        }
+    });
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "test-util")]
+
+use io_uring::opcode;
+use std::io::Write;
+use tempfile::NamedTempFile;
+use tokio_uring::fault::FaultPolicy;
+use tokio_uring::fs::File;
+use tokio_uring::net::UdpSocket;
+
+const DATA: &[u8] = b"the quick brown fox jumps over the lazy dog";
+
+#[test]
+fn injected_errors() {
+    let mut tempfile = NamedTempFile::new().unwrap();
+    tempfile.write_all(DATA).unwrap();
+
+    let mut policy = FaultPolicy::new();
+    policy
+        .fail(1.0, &[libc::EIO])
+        .opcodes(&[opcode::Read::CODE]);
+
+    tokio_uring::builder().fault_injection(policy).start(async {
+        // Operations of other opcodes are not affected
+        let file = File::open(tempfile.path()).await.unwrap();
+
+        let (res, _) = file.read_at(vec![0; 64], 0).await;
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EIO));
+        file.close().await.unwrap();
+    });
+}
+
+#[test]
+fn injected_short_io() {
+    let tempfile = NamedTempFile::new().unwrap();
+
+    let mut policy = FaultPolicy::new();
+    policy.short_io(1.0).seed(1);
+
+    tokio_uring::builder().fault_injection(policy).start(async {
+        let file = File::create(tempfile.path()).await.unwrap();
+        let (res, _) = file.write_at(DATA.to_vec(), 0).await;
+        let n = res.unwrap();
+        assert!(n > 0 && n < DATA.len());
+
+        // Writing all the data takes more writes, but succeeds
+        let (res, _) = file.write_all_at(DATA.to_vec(), 0).await;
+        res.unwrap();
+        // As does a write linked with a sync
+        let (res, _) = file
+            .write_all_sync_at(DATA.to_vec(), DATA.len() as u64)
+            .await;
+        res.unwrap();
+        file.close().await.unwrap();
+        assert_eq!(std::fs::read(tempfile.path()).unwrap(), DATA.repeat(2));
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let (res, buf) = file.read_at(vec![0; 64], 0).await;
+        let n = res.unwrap();
+        assert!(n > 0 && n < DATA.len());
+        assert_eq!(&buf[..n], &DATA[..n]);
+    });
+}
+
+#[test]
+fn datagrams_not_shortened() {
+    let mut policy = FaultPolicy::new();
+    policy.short_io(1.0).seed(1);
+
+    tokio_uring::builder().fault_injection(policy).start(async {
+        let a = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        a.connect(b.local_addr().unwrap()).await.unwrap();
+        b.connect(a.local_addr().unwrap()).await.unwrap();
+
+        let (res, _) = a.write(DATA.to_vec()).await;
+        assert_eq!(res.unwrap(), DATA.len());
+        let (res, buf) = b.read(vec![0; 64]).await;
+        assert_eq!(&buf[..res.unwrap()], DATA);
+    });
+}
@@ -140,3 +140,182 @@ fn slicing() {
 fn tempfile() -> NamedTempFile {
     NamedTempFile::new().unwrap()
 }
+
+#[test]
+fn memlock_accounting() {
+    use tokio_uring::buf::fixed::{memlock_limit, FixedBufPool};
+
+    tokio_uring::start(async {
+        let page_size = 4096;
+        let sizes = [100, 5000, 3 * page_size];
+        let registry = FixedBufRegistry::new(sizes.iter().map(|&n| Vec::with_capacity(n)));
+        let pool = FixedBufPool::new(sizes.iter().map(|&n| Vec::with_capacity(n)));
+
+        for usage in [registry.memlock_usage(), pool.memlock_usage()] {
+            // Each buffer is charged for the pages it spans
+            let total: usize = sizes.iter().sum();
+            assert_eq!(usage % page_size as u64, 0);
+            assert!(usage >= total as u64);
+            assert!(usage <= (total + 2 * page_size * sizes.len()) as u64);
+        }
+
+        if let Some(limit) = memlock_limit().unwrap() {
+            assert!(limit > 0);
+        }
+    });
+}
+
+#[test]
+fn numa_bound_buffers() {
+    use tokio_uring::buf::fixed::{current_numa_node, FixedBufPool};
+
+    let mut tempfile = tempfile();
+    tempfile.write_all(HELLO).unwrap();
+
+    tokio_uring::start(async {
+        let node = current_numa_node().unwrap();
+
+        let pool = FixedBufPool::new(std::iter::repeat_with(|| Vec::with_capacity(4096)).take(2));
+        match pool.bind_to_numa_node(node) {
+            Ok(()) => {}
+            // NUMA policies may not be supported or allowed in the sandbox
+            Err(e) if matches!(e.raw_os_error(), Some(libc::ENOSYS) | Some(libc::EPERM)) => {}
+            Err(e) => panic!("failed to bind the buffers: {}", e),
+        }
+        pool.register().unwrap();
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let buf = pool.try_next(4096).unwrap();
+        let (res, buf) = file.read_fixed_at(buf, 0).await;
+        assert_eq!(&buf[..res.unwrap()], HELLO);
+    });
+}
+
+#[test]
+fn sharded_pool_per_runtime() {
+    use tokio_uring::buf::fixed::ShardedBufPool;
+
+    let mut tempfile = tempfile();
+    tempfile.write_all(HELLO).unwrap();
+    let path = tempfile.path().to_path_buf();
+
+    let pool = ShardedBufPool::new([(4096, 1)]);
+
+    let workers: Vec<_> = (0..2)
+        .map(|_| {
+            let pool = pool.clone();
+            let path = path.clone();
+            std::thread::spawn(move || {
+                tokio_uring::start(async move {
+                    let file = File::open(&path).await.unwrap();
+
+                    // Each runtime has its own buffer, shared by the calls
+                    let buf = pool.try_next(4096).unwrap().unwrap();
+                    assert!(pool.try_next(4096).unwrap().is_none());
+
+                    let (res, buf) = file.read_fixed_at(buf, 0).await;
+                    assert_eq!(&buf[..res.unwrap()], HELLO);
+                })
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    // A new runtime on the same thread gets a new pool
+    for _ in 0..2 {
+        tokio_uring::start(async {
+            assert!(pool.try_next(4096).unwrap().is_some());
+        });
+    }
+}
+
+#[test]
+fn default_pool_io() {
+    use tokio_uring::buf::fixed::FixedBufPool;
+
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = tokio_uring::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tempfile.path())
+            .await
+            .unwrap();
+
+        let err = file.read_at_pooled(4, 0).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        let pool = FixedBufPool::new([8, 32].iter().map(|&n| Vec::with_capacity(n)));
+        pool.register().unwrap();
+        assert!(pool.install_default().is_none());
+
+        let n = file.write_at_pooled(HELLO, 0).await.unwrap();
+        assert_eq!(n, HELLO.len());
+
+        // The smallest buffer that fits is used
+        let head = file.read_at_pooled(4, 0).await.unwrap();
+        assert_eq!(head.bytes_total(), 8);
+        assert_eq!(&head[..], b"hell");
+        let word = file.read_at_pooled(5, 6).await.unwrap();
+        assert_eq!(word.bytes_total(), 32);
+        assert_eq!(&word[..], b"world");
+
+        let err = file.read_at_pooled(64, 0).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        // With all buffers checked out, the read waits for one
+        let read = file.read_at_pooled(2, 0);
+        tokio::pin!(read);
+        assert!(futures::poll!(read.as_mut()).is_pending());
+        mem::drop(word);
+        assert_eq!(&read.await.unwrap()[..], b"he");
+
+        assert!(FixedBufPool::uninstall_default().is_some());
+        assert!(FixedBufPool::default_pool().is_none());
+    });
+}
+
+#[cfg(feature = "debug-buffers")]
+#[test]
+fn poisoned_on_check_in() {
+    let mut tempfile = tempfile();
+    tempfile.write_all(HELLO).unwrap();
+
+    tokio_uring::start(async {
+        let file = File::open(tempfile.path()).await.unwrap();
+        let buffers = FixedBufRegistry::new([Vec::with_capacity(32)]);
+        buffers.register().unwrap();
+
+        let (res, buf) = file.read_fixed_at(buffers.check_out(0).unwrap(), 0).await;
+        assert_eq!(res.unwrap(), HELLO.len());
+        mem::drop(buf);
+
+        // The initialized data is kept, the rest of the buffer is poisoned
+        let buf = buffers.check_out(0).unwrap();
+        let bytes = unsafe { std::slice::from_raw_parts(buf.stable_ptr(), buf.bytes_total()) };
+        assert_eq!(&bytes[..HELLO.len()], HELLO);
+        assert!(bytes[HELLO.len()..].iter().all(|&b| b == 0xa5));
+    });
+}
+
+#[cfg(feature = "debug-buffers")]
+#[test]
+#[should_panic(expected = "fixed buffer 0 was written to at offset 20 after it was checked in")]
+fn write_after_check_in() {
+    use tokio_uring::buf::BoundedBufMut;
+
+    tokio_uring::start(async {
+        let buffers = FixedBufRegistry::new([Vec::with_capacity(32)]);
+
+        let mut buf = buffers.check_out(0).unwrap();
+        let ptr = buf.stable_mut_ptr();
+        mem::drop(buf);
+
+        // Simulates an operation the kernel still performs on the buffer
+        unsafe { ptr.add(20).write(0) };
+        buffers.check_out(0);
+    });
+}
@@ -1,5 +1,9 @@
+// `drop_open` discards an open future without polling it on purpose.
+#![allow(clippy::let_underscore_future)]
+
 use std::{
     io::prelude::*,
+    os::unix::fs::{FileExt, MetadataExt},
     os::unix::io::{AsRawFd, FromRawFd, RawFd},
 };
 
@@ -65,6 +69,367 @@ fn basic_write() {
     });
 }
 
+#[test]
+fn append() {
+    tokio_uring::start(async {
+        let mut tempfile = tempfile();
+        tempfile.write_all(HELLO).unwrap();
+
+        let file = tokio_uring::fs::OpenOptions::new()
+            .append(true)
+            .open(tempfile.path())
+            .await
+            .unwrap();
+
+        let ops = (0..10).map(|_| file.append(HELLO));
+        for (res, _) in futures::future::join_all(ops).await {
+            assert_eq!(res.unwrap(), HELLO.len());
+        }
+
+        let file = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(file, HELLO.repeat(11));
+    });
+}
+
+#[test]
+fn sequential_reader() {
+    tokio_uring::start(async {
+        let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        let mut tempfile = tempfile();
+        tempfile.write_all(&data).unwrap();
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let mut reader = tokio_uring::fs::SequentialReader::new(&file, 10);
+        reader.depth(3).chunk_size(4096);
+
+        let mut contents = Vec::new();
+        while let Some(chunk) = reader.read().await.unwrap() {
+            contents.extend_from_slice(&chunk);
+        }
+        assert_eq!(contents, &data[10..]);
+        assert_eq!(reader.position(), data.len() as u64);
+        assert!(reader.read().await.unwrap().is_none());
+    });
+}
+
+#[test]
+fn sequential_writer() {
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        let mut writer = tokio_uring::fs::SequentialWriter::new(&file, 0);
+        writer.depth(2).sync_on_flush(true);
+        for i in 0..10u8 {
+            writer.write(vec![i; 1000]).await.unwrap();
+        }
+        writer.flush().await.unwrap();
+        assert_eq!(writer.position(), 10_000);
+
+        let expected: Vec<u8> = (0..10u8).flat_map(|i| vec![i; 1000]).collect();
+        assert_eq!(std::fs::read(tempfile.path()).unwrap(), expected);
+    });
+}
+
+#[test]
+fn sequential_writer_spare_capacity() {
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        // Only the initialized bytes are written, not the capacity
+        let mut writer = tokio_uring::fs::SequentialWriter::new(&file, 0);
+        for i in 0..3u8 {
+            let mut buf = Vec::with_capacity(4096);
+            buf.extend_from_slice(&[i; 1000]);
+            writer.write(buf).await.unwrap();
+        }
+        writer.flush().await.unwrap();
+        assert_eq!(writer.position(), 3000);
+
+        let expected: Vec<u8> = (0..3u8).flat_map(|i| vec![i; 1000]).collect();
+        assert_eq!(std::fs::read(tempfile.path()).unwrap(), expected);
+    });
+}
+
+#[test]
+fn sequential_reader_dropped_read() {
+    tokio_uring::start(async {
+        let data: Vec<u8> = (0..25_000u32).flat_map(u32::to_le_bytes).collect();
+        let mut tempfile = tempfile();
+        tempfile.write_all(&data).unwrap();
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let mut reader = tokio_uring::fs::SequentialReader::new(&file, 0);
+        reader.depth(3).chunk_size(4096);
+
+        {
+            let read = std::pin::pin!(reader.read());
+            assert!(futures::poll!(read).is_pending());
+        }
+
+        let mut contents = Vec::new();
+        while let Some(chunk) = reader.read().await.unwrap() {
+            contents.extend_from_slice(&chunk);
+        }
+        assert_eq!(contents, data);
+    });
+}
+
+#[test]
+fn sequential_writer_dropped_write() {
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        let mut writer = tokio_uring::fs::SequentialWriter::new(&file, 0);
+        writer.depth(1);
+        writer.write(vec![0; 1000]).await.unwrap();
+        {
+            let write = std::pin::pin!(writer.write(vec![1; 1000]));
+            assert!(futures::poll!(write).is_pending());
+        }
+        writer.write(vec![2; 1000]).await.unwrap();
+        writer.flush().await.unwrap();
+
+        let mut expected = vec![0; 1000];
+        expected.extend_from_slice(&[2; 1000]);
+        assert_eq!(std::fs::read(tempfile.path()).unwrap(), expected);
+    });
+}
+
+#[test]
+fn read_ranges() {
+    tokio_uring::start(async {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let mut tempfile = tempfile();
+        tempfile.write_all(&data).unwrap();
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let results = file
+            .read_ranges(vec![(5000, 100), (0, 10), (9990, 100)])
+            .await;
+
+        let results: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(results[0], &data[5000..5100]);
+        assert_eq!(results[1], &data[..10]);
+        assert_eq!(results[2], &data[9990..]);
+    });
+}
+
+#[test]
+fn reads_submitted_in_one_flush() {
+    use std::sync::{Arc, Mutex};
+
+    let flushes = Arc::new(Mutex::new(Vec::new()));
+    tokio_uring::builder()
+        .on_flush({
+            let flushes = flushes.clone();
+            move |stats| flushes.lock().unwrap().push(stats.submitted())
+        })
+        .start(async {
+            let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+            let mut tempfile = tempfile();
+            tempfile.write_all(&data).unwrap();
+            let file = File::open(tempfile.path()).await.unwrap();
+
+            flushes.lock().unwrap().clear();
+            let results = file
+                .read_ranges(vec![(0, 10), (5000, 10), (9000, 10)])
+                .await;
+            assert!(results.iter().all(Result::is_ok));
+            assert_eq!(*flushes.lock().unwrap(), [3]);
+
+            flushes.lock().unwrap().clear();
+            let mut reader = tokio_uring::fs::SequentialReader::new(&file, 0);
+            reader.depth(4).chunk_size(4096);
+            reader.read().await.unwrap().unwrap();
+            assert_eq!(*flushes.lock().unwrap(), [4]);
+        });
+}
+
+#[test]
+fn max_in_flight() {
+    use std::rc::Rc;
+    use std::time::Duration;
+    use tokio_uring::Handle;
+
+    tokio_uring::start(async {
+        let (rx, tx) = nix::unistd::pipe().unwrap();
+        let file = Rc::new(unsafe { File::from_raw_fd(rx) });
+        let mut tx = unsafe { std::fs::File::from_raw_fd(tx) };
+        file.set_max_in_flight(Some(2));
+
+        let reads: Vec<_> = (0..5)
+            .map(|_| {
+                let file = Rc::clone(&file);
+                tokio_uring::spawn(async move { file.read_at(vec![0; 1], 0).await.0 })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(Handle::current().dump_inflight().len(), 2);
+
+        // A completed read makes room for one of the waiting reads
+        tx.write_all(b"a").unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(Handle::current().dump_inflight().len(), 2);
+
+        tx.write_all(b"bcde").unwrap();
+        for read in reads {
+            assert_eq!(read.await.unwrap().unwrap(), 1);
+        }
+
+        // Ranges past the limit are read once earlier ones complete
+        let data: Vec<u8> = (0..100u8).collect();
+        let mut tempfile = tempfile();
+        tempfile.write_all(&data).unwrap();
+        let file = File::open(tempfile.path()).await.unwrap();
+        file.set_max_in_flight(Some(1));
+        let results = file.read_ranges(vec![(50, 10), (0, 10), (90, 20)]).await;
+        let results: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(results[0], &data[50..60]);
+        assert_eq!(results[1], &data[..10]);
+        assert_eq!(results[2], &data[90..]);
+    });
+}
+
+#[test]
+fn open_buffered() {
+    use tokio_uring::fs::{AccessPattern, OpenOptions};
+
+    tokio_uring::start(async {
+        let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        let tempfile = tempfile();
+
+        let mut file =
+            File::open_buffered(tempfile.path(), OpenOptions::new().read(true).write(true))
+                .await
+                .unwrap();
+        assert_eq!(file.access_pattern(), AccessPattern::Sequential);
+        for part in data.chunks(3000) {
+            file.write_all(part).await.unwrap();
+        }
+        assert_eq!(file.position(), data.len() as u64);
+        file.flush().await.unwrap();
+
+        file.seek(0).await.unwrap();
+        let mut contents = Vec::new();
+        assert_eq!(file.read_to_end(&mut contents).await.unwrap(), data.len());
+        assert_eq!(contents, data);
+
+        // A write after a read goes at the cursor
+        let mut file = File::open_buffered(
+            tempfile.path(),
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .access_pattern(AccessPattern::Random),
+        )
+        .await
+        .unwrap();
+        file.seek(50_000).await.unwrap();
+        let mut buf = [0; 10];
+        assert_eq!(file.read(&mut buf).await.unwrap(), 10);
+        assert_eq!(buf, data[50_000..50_010]);
+        file.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        assert_eq!(file.read(&mut buf).await.unwrap(), 5);
+        assert_eq!(buf, data[50_015..50_020]);
+
+        let file = file.into_inner().await.unwrap();
+        let (res, buf) = file.read_at(vec![0; 5], 50_010).await;
+        assert_eq!(res.unwrap(), 5);
+        assert_eq!(buf, b"hello");
+    });
+}
+
+#[test]
+fn buffered_file_random_reads_nothing_ahead() {
+    use tokio_uring::fs::{AccessPattern, OpenOptions};
+
+    tokio_uring::start(async {
+        let mut tempfile = tempfile();
+        tempfile.write_all(&[0; 100]).unwrap();
+
+        let mut file = File::open_buffered(
+            tempfile.path(),
+            OpenOptions::new()
+                .read(true)
+                .access_pattern(AccessPattern::Random),
+        )
+        .await
+        .unwrap();
+        let mut buf = [1; 10];
+        assert_eq!(file.read(&mut buf).await.unwrap(), 10);
+        assert_eq!(buf, [0; 10]);
+
+        // Data changed past the cursor is read as it is now
+        tempfile.as_file().write_all_at(&[2; 90], 10).unwrap();
+        let mut buf = [1; 100];
+        assert_eq!(file.read(&mut buf).await.unwrap(), 90);
+        assert_eq!(buf[..90], [2; 90]);
+        assert_eq!(file.position(), 100);
+        assert_eq!(file.read(&mut buf).await.unwrap(), 0);
+    });
+}
+
+#[test]
+fn log_writer() {
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+        let log = tokio_uring::fs::LogWriter::new(&file, 0);
+
+        let tasks: Vec<_> = (0..20u8)
+            .map(|i| {
+                let log = log.clone();
+                tokio_uring::spawn(async move { log.append(vec![i; 100]).await })
+            })
+            .collect();
+
+        let mut offsets = Vec::new();
+        for task in tasks {
+            offsets.push(task.await.unwrap().unwrap());
+        }
+        assert_eq!(log.position(), 2000);
+
+        let contents = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(contents.len(), 2000);
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let offset = offset as usize;
+            assert_eq!(contents[offset..offset + 100], [i as u8; 100]);
+        }
+    });
+}
+
+#[test]
+fn log_writer_large_group() {
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+        let log = tokio_uring::fs::LogWriter::new(&file, 0);
+
+        // The records appended while the first one is committed are
+        // committed together, in more than one vectored write
+        let first = tokio_uring::spawn({
+            let log = log.clone();
+            async move { log.append(vec![0xff; 4]).await }
+        });
+        tokio::task::yield_now().await;
+        let offsets =
+            futures::future::join_all((0..3000u32).map(|i| log.append(i.to_le_bytes().to_vec())))
+                .await;
+        assert_eq!(first.await.unwrap().unwrap(), 0);
+
+        let contents = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(contents.len(), 4 + 3000 * 4);
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let offset = offset.unwrap() as usize;
+            assert_eq!(contents[offset..offset + 4], (i as u32).to_le_bytes());
+        }
+    });
+}
+
 #[test]
 fn vectored_read() {
     tokio_uring::start(async {
@@ -81,6 +446,28 @@ fn vectored_read() {
     });
 }
 
+#[test]
+fn vectored_read_exact() {
+    tokio_uring::start(async {
+        let data = HELLO.repeat(1000);
+        let mut tempfile = tempfile();
+        tempfile.write_all(&data).unwrap();
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let mut head = Vec::with_capacity(10);
+        head.extend_from_slice(&data[..3]);
+        let bufs = vec![head, Vec::with_capacity(5000), Vec::with_capacity(8990)];
+        let (res, bufs) = file.readv_exact_at(bufs, 3).await;
+        res.unwrap();
+        assert_eq!(bufs.concat(), data);
+
+        let bufs = vec![Vec::with_capacity(10), Vec::with_capacity(data.len())];
+        let (res, bufs) = file.readv_exact_at(bufs, 0).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(bufs.concat(), data);
+    });
+}
+
 #[test]
 fn vectored_write() {
     tokio_uring::start(async {
@@ -98,6 +485,28 @@ fn vectored_write() {
     });
 }
 
+#[test]
+fn vectored_write_all() {
+    tokio_uring::start(async {
+        let data = HELLO.repeat(1000);
+        let tempfile = tempfile();
+
+        let file = File::create(tempfile.path()).await.unwrap();
+        let bufs = vec![
+            data[..7].to_vec(),
+            Vec::new(),
+            data[7..9000].to_vec(),
+            data[9000..].to_vec(),
+        ];
+        let (res, bufs) = file.writev_all_at(bufs, 0).await;
+        res.unwrap();
+        assert_eq!(bufs.len(), 4);
+
+        let file = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(file, data);
+    });
+}
+
 #[test]
 fn basic_write_all() {
     tokio_uring::start(async {
@@ -114,6 +523,294 @@ fn basic_write_all() {
     });
 }
 
+#[test]
+fn write_all_sync() {
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        let data = HELLO.repeat(1000);
+        let (res, data) = file.write_all_sync_at(data, 0).await;
+        res.unwrap();
+        let (res, _) = file
+            .write_all_datasync_at(HELLO.to_vec().slice(6..), data.len() as u64)
+            .await;
+        res.unwrap();
+
+        let mut expected = data;
+        expected.extend_from_slice(&HELLO[6..]);
+        assert_eq!(std::fs::read(tempfile.path()).unwrap(), expected);
+    });
+}
+
+#[test]
+fn lock_range() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = Rc::new(File::create(tempfile.path()).await.unwrap());
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let first = file.lock_range(0..100).await;
+        assert_eq!(first.range(), 0..100);
+        let waiter = {
+            let (file, order) = (file.clone(), order.clone());
+            tokio_uring::spawn(async move {
+                let _guard = file.lock_range(50..150).await;
+                order.borrow_mut().push("waiter");
+            })
+        };
+        tokio::task::yield_now().await;
+
+        // A range waited for is not granted ahead of the waiting request,
+        // while a disjoint range is
+        assert!(file.try_lock_range(120..130).is_none());
+        assert!(file.try_lock_range(99..99).is_none());
+        let disjoint = file.try_lock_range(200..300).unwrap();
+        order.borrow_mut().push("disjoint");
+
+        tokio::task::yield_now().await;
+        assert_eq!(*order.borrow(), ["disjoint"]);
+        drop(first);
+        waiter.await.unwrap();
+        assert_eq!(*order.borrow(), ["disjoint", "waiter"]);
+
+        drop(disjoint);
+        assert!(file.try_lock_range(0..300).is_some());
+
+        // A request given up on is no longer waited for
+        let held = file.lock_range(0..10).await;
+        let timeout = std::time::Duration::from_millis(10);
+        assert!(tokio::time::timeout(timeout, file.lock_range(5..15))
+            .await
+            .is_err());
+        assert!(file.try_lock_range(10..15).is_some());
+        drop(held);
+    });
+}
+
+#[test]
+fn read_write_flags() {
+    use tokio_uring::fs::{OpenOptions, RwFlags};
+
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tempfile.path())
+            .await
+            .unwrap();
+
+        let data = HELLO.repeat(1000);
+        let (res, data) = file.write_all_at_with(data, 0, RwFlags::DSYNC).await;
+        res.unwrap();
+        assert_eq!(std::fs::read(tempfile.path()).unwrap(), data);
+
+        // The data just written is in the page cache
+        let (res, buf) = file
+            .read_at_with(Vec::with_capacity(64), 0, RwFlags::NOWAIT)
+            .await;
+        assert_eq!(&buf[..res.unwrap()], &data[..64]);
+
+        // Uncached I/O is only supported by some kernels and file systems
+        let flags = RwFlags::DONTCACHE | RwFlags::DSYNC;
+        assert!(flags.contains(RwFlags::DONTCACHE));
+        let (res, _) = file.write_at_with(&b"uncached"[..], 0, flags).await;
+        match res {
+            Ok(n) => {
+                assert_eq!(n, 8);
+                let (res, buf) = file.read_at_with(vec![0; 8], 0, RwFlags::DONTCACHE).await;
+                res.unwrap();
+                assert_eq!(buf, b"uncached");
+            }
+            Err(e) => assert_eq!(e.raw_os_error(), Some(libc::EOPNOTSUPP)),
+        }
+    });
+}
+
+#[cfg(feature = "stable-deref")]
+#[test]
+fn owned_buffers() {
+    use std::sync::Arc;
+    use tokio_uring::buf::OwnedBuf;
+
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        let shared: Arc<[u8]> = Arc::from(HELLO);
+        let (res, buf) = file.write_all_at(OwnedBuf::new(shared), 0).await;
+        res.unwrap();
+        assert_eq!(Arc::strong_count(&buf.into_inner()), 1);
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let boxed: Box<[u8]> = vec![0; 20].into_boxed_slice();
+        let (res, buf) = file.read_at(OwnedBuf::new(boxed), 0).await;
+        let n = res.unwrap();
+        assert_eq!(n, HELLO.len());
+        assert_eq!(&buf[..n], HELLO);
+        assert_eq!(buf.len(), 20);
+    });
+}
+
+#[cfg(feature = "digest")]
+#[test]
+fn hash_range() {
+    use sha2::{Digest, Sha256};
+
+    tokio_uring::start(async {
+        // Several chunks, the last one partial
+        let data: Vec<u8> = (0..700_000u32).map(|i| (i % 251) as u8).collect();
+        let mut tempfile = tempfile();
+        tempfile.write_all(&data).unwrap();
+        let file = File::open(tempfile.path()).await.unwrap();
+
+        let digest = file
+            .hash_range::<Sha256>(0, data.len() as u64)
+            .await
+            .unwrap();
+        assert_eq!(digest, Sha256::digest(&data));
+
+        let digest = file.hash_range::<Sha256>(1000, 300_000).await.unwrap();
+        assert_eq!(digest, Sha256::digest(&data[1000..301_000]));
+
+        let digest = file.hash_range::<Sha256>(5, 0).await.unwrap();
+        assert_eq!(digest, Sha256::digest(b""));
+
+        let err = file
+            .hash_range::<Sha256>(1, data.len() as u64)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    });
+}
+
+#[cfg(all(feature = "flate2", feature = "zstd"))]
+#[test]
+fn compression_round_trip() {
+    use tokio_uring::fs::{CompressWriter, Compression, DecompressReader, SequentialReader};
+
+    tokio_uring::start(async {
+        // Compressible, and large enough for several writes and chunks
+        let data: Vec<u8> = (0..300_000u32)
+            .flat_map(|i| format!("line {}\n", i % 1000).into_bytes())
+            .collect();
+
+        for format in [Compression::Gzip, Compression::Zlib, Compression::Zstd] {
+            let tempfile = tempfile();
+            let file = File::create(tempfile.path()).await.unwrap();
+            let writer = tokio_uring::fs::SequentialWriter::new(&file, 0);
+            let mut writer = CompressWriter::new(writer, format, 6).unwrap();
+            for piece in data.chunks(10_000) {
+                writer.write(piece).await.unwrap();
+            }
+            let writer = writer.finish().await.unwrap();
+            assert!(writer.position() < data.len() as u64 / 10);
+
+            let file = File::open(tempfile.path()).await.unwrap();
+            let mut reader = SequentialReader::new(&file, 0);
+            reader.chunk_size(4096);
+            let mut reader = DecompressReader::new(reader, format).unwrap();
+            let mut contents = Vec::new();
+            while let Some(piece) = reader.read().await.unwrap() {
+                assert!(!piece.is_empty());
+                contents.extend_from_slice(&piece);
+            }
+            assert!(contents == data, "{:?}", format);
+
+            // A truncated stream fails at the end of the file
+            let len = std::fs::metadata(tempfile.path()).unwrap().len();
+            tempfile.as_file().set_len(len / 2).unwrap();
+            let file = File::open(tempfile.path()).await.unwrap();
+            let reader = SequentialReader::new(&file, 0);
+            let mut reader = DecompressReader::new(reader, format).unwrap();
+            let err = loop {
+                match reader.read().await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => panic!("truncated {:?} stream decoded in full", format),
+                    Err(e) => break e,
+                }
+            };
+            let kind = match format {
+                Compression::Gzip => std::io::ErrorKind::InvalidInput,
+                _ => std::io::ErrorKind::UnexpectedEof,
+            };
+            assert_eq!(err.kind(), kind, "{:?}", format);
+        }
+
+        // Data not in the format
+        let mut tempfile = tempfile();
+        tempfile.write_all(b"not compressed").unwrap();
+        let file = File::open(tempfile.path()).await.unwrap();
+        let reader = SequentialReader::new(&file, 0);
+        let mut reader = DecompressReader::new(reader, Compression::Gzip).unwrap();
+        assert!(reader.read().await.is_err());
+    });
+}
+
+#[cfg(feature = "zerocopy")]
+#[test]
+fn typed_views() {
+    use tokio_uring::buf::view;
+    use zerocopy::little_endian::{U16, U32};
+    use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
+
+    #[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+    #[repr(C)]
+    struct Record {
+        id: U32,
+        len: U16,
+    }
+
+    #[derive(FromBytes, Immutable, KnownLayout)]
+    #[repr(C)]
+    struct Aligned {
+        value: u64,
+    }
+
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        let mut buf = vec![0; 18];
+        for (i, record) in view::view_mut::<[Record]>(&mut buf)
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+        {
+            record.id.set(i as u32);
+            record.len.set(100 + i as u16);
+        }
+        file.write_all_at(buf, 0).await.0.unwrap();
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let (res, buf) = file.read_at(Vec::with_capacity(20), 0).await;
+        assert_eq!(res.unwrap(), 18);
+
+        let records = view::view::<[Record]>(&buf).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].id.get(), 2);
+        assert_eq!(records[2].len.get(), 102);
+
+        let (first, rest) = view::view_prefix::<Record>(&buf).unwrap();
+        assert_eq!(first.len.get(), 100);
+        assert_eq!(rest.len(), 12);
+
+        let err = view::view::<Record>(&buf).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // A `u64` at an odd address is misaligned
+        let buf = vec![0u8; 16];
+        let start = 1 - buf.as_ptr() as usize % 2;
+        let slice = buf.slice(start..start + 8);
+        let err = view::view::<Aligned>(&slice).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    });
+}
+
 #[test]
 fn cancel_read() {
     tokio_uring::start(async {
@@ -221,6 +918,119 @@ fn rename() {
     })
 }
 
+#[test]
+fn fallocate() {
+    use std::os::unix::fs::MetadataExt;
+    use tokio_uring::fs::FallocateFlags;
+
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        file.fallocate(0, 64 * 1024, FallocateFlags::empty())
+            .await
+            .unwrap();
+        let metadata = std::fs::metadata(tempfile.path()).unwrap();
+        assert_eq!(metadata.len(), 64 * 1024);
+        let allocated = metadata.blocks();
+
+        file.write_all_at(HELLO.repeat(1000), 0).await.0.unwrap();
+        file.fallocate(0, 16 * 1024, FallocateFlags::PUNCH_HOLE)
+            .await
+            .unwrap();
+        let metadata = std::fs::metadata(tempfile.path()).unwrap();
+        assert_eq!(metadata.len(), 64 * 1024);
+        assert!(metadata.blocks() < allocated);
+
+        let contents = std::fs::read(tempfile.path()).unwrap();
+        assert!(contents[..16 * 1024].iter().all(|&b| b == 0));
+    });
+}
+
+#[test]
+fn sparse_extents() {
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        const MB: u64 = 1024 * 1024;
+        file.write_all_at(vec![1; 4096], 0).await.0.unwrap();
+        file.write_all_at(vec![2; 4096], MB).await.0.unwrap();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(tempfile.path())
+            .unwrap()
+            .set_len(2 * MB)
+            .unwrap();
+
+        assert_eq!(file.seek_hole(0).await.unwrap(), Some(4096));
+        assert_eq!(file.seek_data(4096).await.unwrap(), Some(MB));
+        assert_eq!(file.seek_data(MB + 4096).await.unwrap(), None);
+        assert_eq!(file.seek_hole(MB + 4096).await.unwrap(), Some(MB + 4096));
+        assert_eq!(file.seek_hole(2 * MB).await.unwrap(), None);
+
+        let mut extents = file.extents().unwrap();
+        let mut found = Vec::new();
+        while let Some(extent) = extents.next().await {
+            found.push(extent.unwrap());
+        }
+        assert_eq!(found, vec![0..4096, MB..MB + 4096]);
+
+        // The lookups leave the offset of the file where it was
+        let fd = file.as_raw_fd();
+        assert_eq!(unsafe { libc::lseek(fd, 0, libc::SEEK_CUR) }, 0);
+    });
+}
+
+#[test]
+fn map_readonly() {
+    use tokio_uring::fs::Advice;
+
+    tokio_uring::start(async {
+        let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        let mut source = tempfile();
+        source.write_all(&data).unwrap();
+
+        let file = File::open(source.path()).await.unwrap();
+        let map = unsafe { file.map_readonly(5000, 50_000) }.unwrap();
+        map.advise(Advice::Sequential).unwrap();
+        map.advise_range(10_000, 100, Advice::WillNeed).unwrap();
+        assert_eq!(map.len(), 50_000);
+        assert_eq!(&map[..], &data[5000..55_000]);
+        file.close().await.unwrap();
+
+        let copy = tempfile();
+        let file = File::create(copy.path()).await.unwrap();
+        let (res, map) = file.write_all_at(map, 0).await;
+        res.unwrap();
+        assert_eq!(std::fs::read(copy.path()).unwrap(), &map[..]);
+    });
+}
+
+#[test]
+fn rename_with_flags() {
+    use tokio_uring::fs::RenameFlags;
+
+    tokio_uring::start(async {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        std::fs::write(&a, b"a").unwrap();
+        std::fs::write(&b, b"b").unwrap();
+
+        let err = tokio_uring::fs::rename_with(&a, &b, RenameFlags::NOREPLACE)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+        tokio_uring::fs::rename_with(&a, &b, RenameFlags::EXCHANGE)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&a).unwrap(), b"b");
+        assert_eq!(std::fs::read(&b).unwrap(), b"a");
+    })
+}
+
 #[test]
 fn read_fixed() {
     tokio_uring::start(async {
@@ -281,6 +1091,95 @@ fn write_fixed() {
     });
 }
 
+#[test]
+fn whole_file_read_write() {
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        // Larger than the initial read buffer, so it has to grow.
+        let data = HELLO.repeat(1000);
+
+        tokio_uring::fs::write(tempfile.path(), data.clone())
+            .await
+            .unwrap();
+
+        let contents = tokio_uring::fs::read(tempfile.path()).await.unwrap();
+        assert_eq!(contents, data);
+
+        let contents = tokio_uring::fs::read_to_string(tempfile.path())
+            .await
+            .unwrap();
+        assert_eq!(contents.as_bytes(), data);
+    });
+}
+
+#[test]
+fn atomic_write() {
+    tokio_uring::start(async {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.conf");
+        std::fs::write(&path, b"old").unwrap();
+
+        tokio_uring::fs::write_atomic(&path, HELLO).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), HELLO);
+        // No temporary file is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // A failed write leaves the file as it was
+        let missing = dir.path().join("missing").join("app.conf");
+        assert!(tokio_uring::fs::write_atomic(&missing, HELLO)
+            .await
+            .is_err());
+        let err = tokio_uring::fs::write_atomic(dir.path().join(".."), HELLO)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(std::fs::read(&path).unwrap(), HELLO);
+    });
+}
+
+#[test]
+fn metadata() {
+    tokio_uring::start(async {
+        let mut tempfile = tempfile();
+        tempfile.write_all(HELLO).unwrap();
+
+        let std_metadata = std::fs::metadata(tempfile.path()).unwrap();
+
+        let file = File::open(tempfile.path()).await.unwrap();
+        let metadata = file.metadata().await.unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), HELLO.len() as u64);
+        assert_eq!(metadata.permissions().mode(), std_metadata.mode());
+        assert_eq!(
+            metadata.modified().unwrap(),
+            std_metadata.modified().unwrap()
+        );
+        assert_eq!(metadata.ino(), std_metadata.ino());
+        assert_eq!(metadata.dev(), std_metadata.dev());
+
+        let dir = tempfile.path().parent().unwrap();
+        let metadata = tokio_uring::fs::symlink_metadata(dir).await.unwrap();
+        assert!(metadata.is_dir());
+    });
+}
+
+#[test]
+fn open_with_metadata() {
+    tokio_uring::start(async {
+        let mut tempfile = tempfile();
+        tempfile.write_all(HELLO).unwrap();
+
+        let (file, metadata) = File::open_with_metadata(tempfile.path()).await.unwrap();
+        assert_eq!(metadata.len(), HELLO.len() as u64);
+        read_hello(&file).await;
+
+        let err = File::open_with_metadata(tempfile.path().with_extension("missing"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    });
+}
+
 fn tempfile() -> NamedTempFile {
     NamedTempFile::new().unwrap()
 }
@@ -310,4 +1209,39 @@ fn assert_invalid_fd(fd: RawFd) {
         Err(ref e) if e.raw_os_error() == Some(libc::EBADF) => {}
         res => panic!("{:?}", res),
     }
+
+    // The descriptor is not open, don't let `File` attempt to close it.
+    std::mem::forget(f);
+}
+
+#[test]
+fn rate_limited_writes() {
+    use std::time::{Duration, Instant};
+    use tokio_uring::limit::RateLimiter;
+
+    tokio_uring::start(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).await.unwrap();
+
+        let limiter = RateLimiter::new(10_000);
+        limiter.burst(1000);
+
+        let start = Instant::now();
+        limiter
+            .throttle(async {
+                for i in 0..5 {
+                    let (res, _) = file.write_all_at(vec![b'x'; 1000], i * 1000).await;
+                    res.unwrap();
+                }
+            })
+            .await;
+
+        // The first write is done in the burst. The other ones put the
+        // limiter in debt, which takes 100ms to repay each time.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(300), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+
+        assert_eq!(std::fs::read(tempfile.path()).unwrap().len(), 5000);
+    });
 }
@@ -0,0 +1,82 @@
+use tokio_uring::fs::WatchEventKind;
+
+#[test]
+fn watch_directory() {
+    tokio_uring::start(async {
+        let dir = tempfile::tempdir().unwrap();
+        let mut watcher = tokio_uring::fs::watch(dir.path()).unwrap();
+
+        let path = dir.path().join("file");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let event = watcher.next_event().await.unwrap();
+        assert_eq!(event.kind(), WatchEventKind::Create);
+        assert_eq!(event.path(), path);
+        assert!(!event.is_dir());
+
+        std::fs::remove_file(&path).unwrap();
+
+        loop {
+            let event = watcher.next_event().await.unwrap();
+            assert_eq!(event.path(), path);
+            if event.kind() == WatchEventKind::Remove {
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn watch_events_after_waiting() {
+    use std::time::Duration;
+
+    tokio_uring::start(async {
+        let dir = tempfile::tempdir().unwrap();
+        let mut watcher = tokio_uring::fs::watch(dir.path()).unwrap();
+
+        // Each file is created while the watcher waits for events
+        for i in 0..3 {
+            let path = dir.path().join(format!("file{}", i));
+            let create = tokio_uring::spawn({
+                let path = path.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    std::fs::File::create(path).unwrap();
+                }
+            });
+            let event = watcher.next_event().await.unwrap();
+            assert_eq!(event.kind(), WatchEventKind::Create);
+            assert_eq!(event.path(), path);
+            create.await.unwrap();
+            while let Ok(Ok(event)) =
+                tokio::time::timeout(Duration::from_millis(10), watcher.next_event()).await
+            {
+                assert_eq!(event.path(), path);
+            }
+        }
+    });
+}
+
+#[cfg(feature = "stream")]
+#[test]
+fn watch_stream() {
+    use futures::StreamExt;
+
+    tokio_uring::start(async {
+        let dir = tempfile::tempdir().unwrap();
+        let mut watcher = tokio_uring::fs::watch(dir.path()).unwrap();
+
+        // A pending poll is kept when the future is dropped
+        {
+            let next = std::pin::pin!(watcher.next_event());
+            assert!(futures::poll!(next).is_pending());
+        }
+
+        let path = dir.path().join("file");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let event = watcher.next().await.unwrap().unwrap();
+        assert_eq!(event.kind(), WatchEventKind::Create);
+        assert_eq!(event.path(), path);
+    });
+}
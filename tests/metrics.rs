@@ -0,0 +1,124 @@
+#![cfg(feature = "metrics")]
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use std::collections::HashMap;
+use std::io::Write;
+use tokio_uring::buf::fixed::FixedBufRegistry;
+use tokio_uring::fs::File;
+
+#[test]
+fn runtime_metrics() {
+    let mut tempfile = tempfile::NamedTempFile::new().unwrap();
+    tempfile.write_all(b"hello world").unwrap();
+
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let mut values = HashMap::new();
+
+    metrics::with_local_recorder(&recorder, || {
+        tokio_uring::start(async {
+            let file = File::open(tempfile.path()).await.unwrap();
+            let (res, _) = file.read_at(vec![0; 32], 0).await;
+            assert_eq!(res.unwrap(), 11);
+            let (res, _) = file.write_at(&b"hello"[..], 0).await;
+            assert!(res.unwrap_err().raw_os_error() == Some(libc::EBADF));
+
+            let registry = FixedBufRegistry::new([Vec::with_capacity(16)]);
+            registry.register().unwrap();
+            let buf = registry.check_out(0).unwrap();
+            let (res, buf) = file.read_fixed_at(buf, 6).await;
+            assert_eq!(res.unwrap(), 5);
+
+            update(&mut values, &snapshotter);
+            assert_eq!(
+                values["tokio_uring_fixed_buffers"],
+                DebugValue::Gauge(1.0.into())
+            );
+            assert_eq!(
+                values["tokio_uring_fixed_buffers_checked_out"],
+                DebugValue::Gauge(1.0.into())
+            );
+            drop(buf);
+
+            file.close().await.unwrap();
+        })
+    });
+
+    update(&mut values, &snapshotter);
+    assert_eq!(
+        values["tokio_uring_ops_submitted_total"],
+        DebugValue::Counter(5)
+    );
+    assert_eq!(
+        values["tokio_uring_ops_completed_total"],
+        DebugValue::Counter(5)
+    );
+    assert_eq!(
+        values["tokio_uring_ops_failed_total"],
+        DebugValue::Counter(1)
+    );
+    assert_eq!(
+        values["tokio_uring_ops_in_flight"],
+        DebugValue::Gauge(0.0.into())
+    );
+    assert_eq!(
+        values["tokio_uring_bytes_read_total"],
+        DebugValue::Counter(16)
+    );
+    assert_eq!(
+        values["tokio_uring_fixed_buffers_checked_out"],
+        DebugValue::Gauge(0.0.into())
+    );
+    match &values["tokio_uring_op_duration_seconds"] {
+        DebugValue::Histogram(samples) => assert_eq!(samples.len(), 5),
+        other => panic!("unexpected value {:?}", other),
+    }
+}
+
+#[test]
+fn leaked_operations_retired() {
+    use std::time::Duration;
+
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let mut values = HashMap::new();
+
+    metrics::with_local_recorder(&recorder, || {
+        let rt = tokio_uring::Runtime::new(&tokio_uring::builder()).unwrap();
+        rt.block_on(async {
+            let listener =
+                tokio_uring::net::TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+            tokio_uring::spawn(async move {
+                let _ = listener.accept().await;
+            });
+            tokio::task::yield_now().await;
+        });
+        // The accept is leaked rather than waited for
+        rt.shutdown_timeout(Duration::ZERO);
+    });
+
+    update(&mut values, &snapshotter);
+    assert_eq!(
+        values["tokio_uring_ops_in_flight"],
+        DebugValue::Gauge(0.0.into())
+    );
+}
+
+// Taking a snapshot resets the metrics, their values are accumulated.
+fn update(values: &mut HashMap<String, DebugValue>, snapshotter: &Snapshotter) {
+    for (key, _, _, value) in snapshotter.snapshot().into_vec() {
+        let name = key.key().name().to_string();
+        let value = match (values.remove(&name), value) {
+            (Some(DebugValue::Counter(prev)), DebugValue::Counter(n)) => {
+                DebugValue::Counter(prev + n)
+            }
+            (Some(DebugValue::Gauge(prev)), DebugValue::Gauge(x)) => DebugValue::Gauge(prev + x),
+            (Some(DebugValue::Histogram(mut prev)), DebugValue::Histogram(samples)) => {
+                prev.extend(samples);
+                DebugValue::Histogram(prev)
+            }
+            (_, value) => value,
+        };
+        values.insert(name, value);
+    }
+}
@@ -0,0 +1,168 @@
+#![cfg(feature = "test-util")]
+
+use io_uring::opcode;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio_uring::fs::File;
+
+#[test]
+fn scripted_file_io() {
+    let written = Arc::new(Mutex::new(Vec::new()));
+
+    tokio_uring::builder()
+        .mock_driver({
+            let written = written.clone();
+            move |op| match op.opcode() {
+                opcode::OpenAt::CODE => {
+                    assert_eq!(op.path().unwrap().to_str().unwrap(), "data.txt");
+                    Ok(100)
+                }
+                opcode::Read::CODE => {
+                    assert_eq!(op.fd(), 100);
+                    assert_eq!(op.offset(), 10);
+                    let buf = op.buf_mut().unwrap();
+                    buf[..5].copy_from_slice(b"hello");
+                    Ok(5)
+                }
+                opcode::Write::CODE if op.offset() == 0 => {
+                    written.lock().unwrap().extend_from_slice(op.buf().unwrap());
+                    Ok(op.len())
+                }
+                opcode::Write::CODE => Err(io::Error::from_raw_os_error(libc::ENOSPC)),
+                opcode::Close::CODE => Ok(0),
+                _ => panic!("unexpected operation {:?}", op),
+            }
+        })
+        .start(async {
+            let file = File::open("data.txt").await.unwrap();
+
+            let (res, buf) = file.read_at(vec![0; 16], 10).await;
+            assert_eq!(&buf[..res.unwrap()], b"hello");
+
+            let (res, _) = file.write_at(b"world".to_vec(), 0).await;
+            assert_eq!(res.unwrap(), 5);
+
+            let (res, _) = file.write_at(b"full".to_vec(), 5).await;
+            assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::ENOSPC));
+
+            file.close().await.unwrap();
+        });
+
+    assert_eq!(*written.lock().unwrap(), b"world");
+}
+
+#[test]
+fn retry_policy() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    use tokio_uring::RetryPolicy;
+
+    let attempts = Arc::new(AtomicU32::new(0));
+    let mut policy = RetryPolicy::new();
+    policy
+        .max_retries(3)
+        .backoff(Duration::from_millis(1), Duration::from_millis(4));
+
+    tokio_uring::builder()
+        .retry_policy(policy)
+        .mock_driver({
+            let attempts = attempts.clone();
+            move |op| match op.opcode() {
+                opcode::OpenAt::CODE | opcode::Close::CODE => Ok(100),
+                // Succeeds on the third attempt
+                opcode::Read::CODE if op.offset() == 0 => {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                        Err(io::Error::from_raw_os_error(libc::EAGAIN))
+                    } else {
+                        Ok(op.len())
+                    }
+                }
+                opcode::Read::CODE if op.offset() == 1 => {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    Err(io::Error::from_raw_os_error(libc::ENOBUFS))
+                }
+                opcode::Read::CODE => {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    Err(io::Error::from_raw_os_error(libc::EAGAIN))
+                }
+                opcode::Write::CODE => {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    Err(io::Error::from_raw_os_error(libc::EIO))
+                }
+                _ => panic!("unexpected operation {:?}", op),
+            }
+        })
+        .start(async {
+            let file = File::open("data.txt").await.unwrap();
+
+            let (res, _) = file.read_at(vec![0; 16], 0).await;
+            assert_eq!(res.unwrap(), 16);
+            assert_eq!(attempts.swap(0, Ordering::SeqCst), 3);
+
+            // The retries are exhausted
+            let (res, _) = file.read_at(vec![0; 16], 1).await;
+            assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::ENOBUFS));
+            assert_eq!(attempts.swap(0, Ordering::SeqCst), 4);
+
+            // Not a transient error
+            let (res, _) = file.write_at(vec![0; 16], 0).await;
+            assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EIO));
+            assert_eq!(attempts.swap(0, Ordering::SeqCst), 1);
+
+            // A read dropped while waiting for a retry is not submitted again
+            let read = file.read_at(vec![0; 16], 2);
+            let res = tokio::time::timeout(Duration::from_millis(2), read).await;
+            assert!(res.is_err());
+            let dropped_at = attempts.load(Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            assert_eq!(attempts.load(Ordering::SeqCst), dropped_at);
+
+            // A read bounded by a deadline is not retried
+            attempts.store(0, Ordering::SeqCst);
+            let read = file.read_at(vec![0; 16], 3);
+            let (res, _) = tokio_uring::time::timeout(Duration::from_secs(1), read)
+                .await
+                .unwrap();
+            assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EAGAIN));
+            assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+            file.close().await.unwrap();
+        });
+}
+
+#[test]
+fn mock_without_ring() {
+    tokio_uring::builder()
+        .mock_driver(|op| match op.opcode() {
+            opcode::Nop::CODE => Ok(0),
+            _ => panic!("unexpected operation {:?}", op),
+        })
+        .start(async {
+            // No io_uring instance is created
+            let res = unsafe { tokio_uring::Handle::current().with_ring(|_| ()) };
+            assert_eq!(res.unwrap_err().kind(), io::ErrorKind::Unsupported);
+
+            tokio_uring::no_op().await.unwrap();
+        });
+}
+
+#[test]
+fn bind_error_not_retried_with_syscall() {
+    use tokio_uring::net::TcpSocket;
+
+    tokio_uring::builder()
+        .mock_driver(|op| match op.opcode() {
+            // IORING_OP_BIND
+            56 => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+            opcode::Close::CODE => Ok(0),
+            _ => panic!("unexpected operation {:?}", op),
+        })
+        .start(async {
+            // The error of the operation is returned, rather than taken for
+            // a kernel without the operation
+            let socket = TcpSocket::new_v4().unwrap();
+            let res = socket.bind_async("127.0.0.1:0".parse().unwrap()).await;
+            assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EINVAL));
+            assert!(socket.local_addr().is_ok_and(|addr| addr.port() == 0));
+        });
+}
@@ -0,0 +1,1362 @@
+use std::io::{Read, Write};
+use tokio_uring::net::{TcpListener, TcpStream, UdpSocket, UnixListener, UnixStream};
+
+#[test]
+fn tcp_std_conversions() {
+    tokio_uring::start(async {
+        // A non-blocking listener, as Tokio's `into_std` returns
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        std_listener.set_nonblocking(true).unwrap();
+        let addr = std_listener.local_addr().unwrap();
+        let listener = TcpListener::from_std(std_listener);
+
+        let client = tokio_uring::spawn(async move { TcpStream::connect(addr).await.unwrap() });
+        let (stream, _) = listener.accept().await.unwrap();
+        let client = client.await.unwrap();
+
+        // Hand the connection over to blocking code
+        let mut std_stream = stream.into_std().unwrap();
+        client.write_all(&b"ping"[..]).await.0.unwrap();
+        let mut buf = [0; 4];
+        std_stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // And back
+        let stream = TcpStream::from_std(std_stream);
+        stream.write_all(&b"pong"[..]).await.0.unwrap();
+        let (res, buf) = client.read(vec![0; 4]).await;
+        assert_eq!(&buf[..res.unwrap()], b"pong");
+
+        let std_listener = listener.into_std().unwrap();
+        assert_eq!(std_listener.local_addr().unwrap(), addr);
+    });
+}
+
+#[test]
+fn tcp_connect_host() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // "localhost" may resolve to `::1` first, which is not listened on
+        let client =
+            tokio_uring::spawn(async move { TcpStream::connect_host("localhost", port).await });
+        let (stream, _) = listener.accept().await.unwrap();
+        let client = client.await.unwrap().unwrap();
+
+        client.write_all(&b"ping"[..]).await.0.unwrap();
+        let (res, buf) = stream.read(vec![0; 4]).await;
+        assert_eq!(&buf[..res.unwrap()], b"ping");
+    });
+}
+
+#[test]
+fn tcp_connect_host_unresolved() {
+    tokio_uring::start(async {
+        // The `.invalid` top-level domain is never resolved
+        assert!(TcpStream::connect_host("tokio-uring.invalid", 80)
+            .await
+            .is_err());
+    });
+}
+
+#[test]
+fn udp_std_conversions() {
+    tokio_uring::start(async {
+        let socket = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = socket.local_addr().unwrap();
+        let std_socket = socket.into_std().unwrap();
+        assert_eq!(std_socket.local_addr().unwrap(), addr);
+
+        let other = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        other.send_to(&b"hello"[..], addr).await.0.unwrap();
+        let mut buf = [0; 16];
+        let (n, _) = std_socket.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"hello");
+    });
+}
+
+#[test]
+fn unix_std_conversions() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sock");
+
+    tokio_uring::start(async {
+        let listener =
+            UnixListener::from_std(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let client = tokio_uring::spawn({
+            let path = path.clone();
+            async move { UnixStream::connect(&path).await.unwrap() }
+        });
+        let stream = listener.accept().await.unwrap();
+        let client = client.await.unwrap();
+
+        let mut std_stream = stream.into_std().unwrap();
+        std_stream.write_all(b"hello").unwrap();
+        let (res, buf) = client.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"hello");
+
+        let std_listener = listener.into_std().unwrap();
+        assert_eq!(
+            std_listener.local_addr().unwrap().as_pathname(),
+            Some(path.as_path())
+        );
+    });
+}
+
+#[test]
+fn socket_activation() {
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::process::CommandExt;
+    use tokio_uring::net::{self, ActivatedSocket};
+
+    // The test runs again in a child process, passed the sockets as
+    // systemd would
+    if std::env::var_os("TOKIO_URING_ACTIVATED").is_some() {
+        tokio_uring::start(async {
+            let mut sockets = net::from_env_named().unwrap();
+            // The sockets are only returned once
+            assert!(std::env::var_os("LISTEN_FDS").is_some());
+            assert!(net::from_env().unwrap().is_empty());
+
+            let names: Vec<_> = sockets.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(names, ["web", "dns"]);
+            assert!(matches!(sockets[1].1, ActivatedSocket::UdpSocket(_)));
+            match sockets.remove(0).1 {
+                ActivatedSocket::TcpListener(listener) => {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    stream.write_all(&b"activated"[..]).await.0.unwrap();
+                }
+                _ => panic!("not a TCP listener"),
+            }
+        });
+        return;
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    // Duplicated out of the way of the descriptors they are passed as
+    let fds = [listener.as_raw_fd(), udp.as_raw_fd()]
+        .map(|fd| unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 100) });
+
+    let mut child = unsafe {
+        std::process::Command::new("/bin/sh")
+            .arg("-c")
+            .arg("LISTEN_PID=$$ exec \"$0\" \"$@\"")
+            .arg(std::env::current_exe().unwrap())
+            .args(["--exact", "socket_activation", "--quiet"])
+            .env("TOKIO_URING_ACTIVATED", "1")
+            .env("LISTEN_FDS", "2")
+            .env("LISTEN_FDNAMES", "web:dns")
+            .stdout(std::process::Stdio::null())
+            .pre_exec(move || {
+                for (i, &fd) in fds.iter().enumerate() {
+                    if libc::dup2(fd, 3 + i as i32) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            })
+            .spawn()
+            .unwrap()
+    };
+
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    let mut received = String::new();
+    stream.read_to_string(&mut received).unwrap();
+    assert_eq!(received, "activated");
+    assert!(child.wait().unwrap().success());
+    for fd in fds {
+        unsafe { libc::close(fd) };
+    }
+}
+
+#[cfg(feature = "socket2")]
+#[test]
+fn socket2_conversions() {
+    use socket2::{Domain, Socket, Type};
+    use std::convert::TryFrom;
+
+    tokio_uring::start(async {
+        let socket = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+        socket.set_reuse_address(true).unwrap();
+        socket
+            .bind(
+                &"127.0.0.1:0"
+                    .parse::<std::net::SocketAddr>()
+                    .unwrap()
+                    .into(),
+            )
+            .unwrap();
+        socket.listen(16).unwrap();
+        let listener = TcpListener::from(socket);
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio_uring::spawn(async move { TcpStream::connect(addr).await.unwrap() });
+        let (stream, _) = listener.accept().await.unwrap();
+        let client = client.await.unwrap();
+
+        let socket = Socket::try_from(stream).unwrap();
+        socket.set_nodelay(true).unwrap();
+        assert!(socket.nodelay().unwrap());
+        let stream = TcpStream::from(socket);
+        stream.write_all(&b"hello"[..]).await.0.unwrap();
+        let (res, buf) = client.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"hello");
+
+        let socket = Socket::try_from(listener).unwrap();
+        assert!(socket.reuse_address().unwrap());
+    });
+}
+
+#[test]
+fn dual_stack_and_v6_only() {
+    use tokio_uring::net::TcpSocket;
+
+    tokio_uring::start(async {
+        // Dual-stack, whatever the system default is
+        let listener = TcpListener::bind_with_only_v6("[::]:0".parse().unwrap(), false).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = tokio_uring::spawn(async move {
+            TcpStream::connect(([127, 0, 0, 1], port).into())
+                .await
+                .unwrap()
+        });
+        let (_stream, peer) = listener.accept().await.unwrap();
+        assert!(peer.is_ipv6());
+        client.await.unwrap();
+
+        // IPv6 only
+        let socket = TcpSocket::new_v6().unwrap();
+        socket.set_only_v6(true).unwrap();
+        assert!(socket.only_v6().unwrap());
+        socket.bind("[::]:0".parse().unwrap()).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let _listener = socket.listen(16).unwrap();
+        let err = TcpStream::connect(([127, 0, 0, 1], port).into())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+
+        // Another socket can take the port for IPv4
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind(([127, 0, 0, 1], port).into()).unwrap();
+        let listener = socket.listen(16).unwrap();
+        let client = tokio_uring::spawn(async move {
+            TcpSocket::new_v4()
+                .unwrap()
+                .connect(([127, 0, 0, 1], port).into())
+                .await
+                .unwrap()
+        });
+        let (_stream, peer) = listener.accept().await.unwrap();
+        assert!(peer.is_ipv4());
+        client.await.unwrap();
+    });
+}
+
+#[test]
+fn udp_only_v6() {
+    tokio_uring::start(async {
+        let only_v6 = |socket: &UdpSocket| socket2::SockRef::from(socket).only_v6().unwrap();
+
+        let socket = UdpSocket::bind_with_only_v6("[::]:0".parse().unwrap(), true)
+            .await
+            .unwrap();
+        assert!(only_v6(&socket));
+
+        let socket = UdpSocket::bind_with_only_v6("[::]:0".parse().unwrap(), false)
+            .await
+            .unwrap();
+        assert!(!only_v6(&socket));
+
+        // The option is ignored for IPv4
+        UdpSocket::bind_with_only_v6("127.0.0.1:0".parse().unwrap(), true)
+            .await
+            .unwrap();
+    });
+}
+
+#[test]
+fn reuseport_cbpf_steering() {
+    use std::time::Duration;
+    use tokio_uring::net::TcpSocket;
+
+    tokio_uring::start(async {
+        let err = TcpSocket::new_v4()
+            .unwrap()
+            .set_reuseport_cbpf(0)
+            .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+        // With a single socket counted, the first one listening accepts all
+        // the connections
+        let mut addr = "127.0.0.1:0".parse().unwrap();
+        let mut listeners = Vec::new();
+        for i in 0..3 {
+            let socket = TcpSocket::new_v4().unwrap();
+            socket.set_reuseport(true).unwrap();
+            if i == 0 {
+                socket.set_reuseport_cbpf(1).unwrap();
+            }
+            socket.bind(addr).unwrap();
+            addr = socket.local_addr().unwrap();
+            listeners.push(socket.listen(16).unwrap());
+        }
+
+        for _ in 0..8 {
+            let _client = TcpStream::connect(addr).await.unwrap();
+            listeners[0].accept().await.unwrap();
+        }
+        for listener in &listeners[1..] {
+            let accepted = tokio::time::timeout(Duration::from_millis(20), listener.accept()).await;
+            assert!(accepted.is_err());
+        }
+    });
+}
+
+#[test]
+fn incoming_pipeline() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming(8);
+        assert_eq!(incoming.depth(), 8);
+
+        let clients: Vec<_> = (0..32)
+            .map(|_| tokio_uring::spawn(async move { TcpStream::connect(addr).await.unwrap() }))
+            .collect();
+        let mut peers = std::collections::HashSet::new();
+        for _ in 0..32 {
+            let (_stream, peer) = incoming.next().await.unwrap();
+            assert!(peers.insert(peer));
+        }
+        for client in clients {
+            client.await.unwrap();
+        }
+
+        // Once the accepts in flight are canceled, connections are left to
+        // the listener
+        drop(incoming);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let client = tokio_uring::spawn(async move { TcpStream::connect(addr).await.unwrap() });
+        let (_stream, peer) = listener.accept().await.unwrap();
+        assert!(!peers.contains(&peer));
+        client.await.unwrap();
+    });
+}
+
+#[test]
+fn copy_bidirectional_proxy() {
+    use std::net::Shutdown;
+
+    async fn read_to_end(stream: &TcpStream) -> Vec<u8> {
+        let mut data = Vec::new();
+        loop {
+            let (res, buf) = stream.read(vec![0; 4096]).await;
+            let n = res.unwrap();
+            if n == 0 {
+                return data;
+            }
+            data.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    tokio_uring::start(async {
+        let server = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let proxy = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+
+        // Replies once the request is complete
+        let server = tokio_uring::spawn(async move {
+            let (stream, _) = server.accept().await.unwrap();
+            let request = read_to_end(&stream).await;
+            assert_eq!(request.len(), 300_000);
+            stream.write_all(&b"done"[..]).await.0.unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+        });
+        let proxy = tokio_uring::spawn(async move {
+            let (client, _) = proxy.accept().await.unwrap();
+            let upstream = TcpStream::connect(server_addr).await.unwrap();
+            tokio_uring::io::copy_bidirectional(&client, &upstream)
+                .await
+                .unwrap()
+        });
+
+        let client = TcpStream::connect(proxy_addr).await.unwrap();
+        let request: Vec<u8> = (0..300_000).map(|i| i as u8).collect();
+        client.write_all(request).await.0.unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        assert_eq!(read_to_end(&client).await, b"done");
+
+        server.await.unwrap();
+        assert_eq!(proxy.await.unwrap(), (300_000, 4));
+    });
+}
+
+#[test]
+fn splice_between_streams() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let a = TcpStream::connect(addr).await.unwrap();
+        let (a_peer, _) = listener.accept().await.unwrap();
+        let b = TcpStream::connect(addr).await.unwrap();
+        let (b_peer, _) = listener.accept().await.unwrap();
+
+        a.write_all(&b"spliced"[..]).await.0.unwrap();
+        a.shutdown(std::net::Shutdown::Write).unwrap();
+        assert_eq!(a_peer.splice_to(&b, 4).await.unwrap(), 4);
+        assert_eq!(a_peer.splice_to(&b, 1024).await.unwrap(), 3);
+        assert_eq!(a_peer.splice_to(&b, 1024).await.unwrap(), 0);
+
+        let (res, buf) = b_peer.read(vec![0; 16]).await;
+        let mut received = buf[..res.unwrap()].to_vec();
+        if received.len() < 7 {
+            let (res, buf) = b_peer.read(vec![0; 16]).await;
+            received.extend_from_slice(&buf[..res.unwrap()]);
+        }
+        assert_eq!(received, b"spliced");
+    });
+}
+
+#[test]
+fn splice_dropped() {
+    use std::os::unix::io::AsRawFd;
+    use std::time::Duration;
+
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let a = TcpStream::connect(addr).await.unwrap();
+        let (a_peer, _) = listener.accept().await.unwrap();
+        let b = TcpStream::connect(addr).await.unwrap();
+        let (b_peer, _) = listener.accept().await.unwrap();
+
+        // Fill the socket buffers of `b`, for the data to wait in the pipe
+        let filler = [0u8; 4096];
+        let mut filled = 0;
+        loop {
+            let n = unsafe {
+                libc::send(
+                    b.as_raw_fd(),
+                    filler.as_ptr() as *const _,
+                    filler.len(),
+                    libc::MSG_DONTWAIT,
+                )
+            };
+            if n < 0 {
+                break;
+            }
+            filled += n as usize;
+        }
+
+        a.write_all(&b"spliced"[..]).await.0.unwrap();
+        a.shutdown(std::net::Shutdown::Write).unwrap();
+        let res = tokio::time::timeout(Duration::from_millis(20), a_peer.splice_to(&b, 1024)).await;
+        assert!(res.is_err());
+
+        let reader = tokio_uring::spawn(async move {
+            let mut received = Vec::new();
+            loop {
+                let (res, buf) = b_peer.read(vec![0; 65536]).await;
+                match res.unwrap() {
+                    0 => return received,
+                    n => received.extend_from_slice(&buf[..n]),
+                }
+            }
+        });
+
+        // No data is lost
+        while a_peer.splice_to(&b, 1024).await.unwrap() > 0 {}
+        b.shutdown(std::net::Shutdown::Write).unwrap();
+        let received = reader.await.unwrap();
+        assert_eq!(received.len(), filled + 7);
+        assert_eq!(&received[filled..], b"spliced");
+    });
+}
+
+#[test]
+fn incoming_submission_order() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming(4);
+
+        // Submit the accepts, then complete them all before looking at them
+        {
+            let next = std::pin::pin!(incoming.next());
+            assert!(futures::poll!(next).is_pending());
+        }
+        let clients: Vec<_> = (0..4)
+            .map(|_| std::net::TcpStream::connect(addr).unwrap())
+            .collect();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        for client in &clients {
+            let (_stream, peer) = incoming.next().await.unwrap();
+            assert_eq!(peer, client.local_addr().unwrap());
+        }
+    });
+}
+
+#[test]
+fn incoming_admission() {
+    use tokio_uring::net::Admission;
+
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming(4);
+        // One connection at a time, with one more waiting
+        incoming.set_admission(|_, load| {
+            assert!(load.in_flight() >= 1);
+            if load.open() < 1 {
+                Admission::Accept
+            } else if load.deferred() < 1 {
+                Admission::Defer
+            } else {
+                Admission::Close
+            }
+        });
+
+        let mut clients: Vec<_> = (0..3)
+            .map(|_| std::net::TcpStream::connect(addr).unwrap())
+            .collect();
+        let (first, peer) = incoming.next().await.unwrap();
+        assert_eq!(incoming.open_connections(), 1);
+        let first_client = clients.iter().position(|c| c.local_addr().unwrap() == peer);
+        clients.remove(first_client.unwrap());
+
+        tokio_uring::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            drop(first);
+        });
+        // The deferred connection is taken once the first is closed
+        let (_second, peer) = incoming.next().await.unwrap();
+        assert_eq!(incoming.open_connections(), 1);
+        let (admitted, closed): (Vec<_>, Vec<_>) = clients
+            .into_iter()
+            .partition(|c| c.local_addr().unwrap() == peer);
+        assert_eq!(admitted.len(), 1);
+        // The last connection has been closed on acceptance
+        let mut buf = [0; 1];
+        assert!(matches!((&closed[0]).read(&mut buf), Ok(0) | Err(_)));
+    });
+}
+
+#[test]
+fn incoming_admission_closed_between_calls() {
+    use std::time::Duration;
+    use tokio_uring::net::Admission;
+
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming(4);
+        incoming.set_admission(|_, load| {
+            if load.open() < 1 {
+                Admission::Accept
+            } else {
+                Admission::Defer
+            }
+        });
+
+        let _first_client = std::net::TcpStream::connect(addr).unwrap();
+        let (first, _) = incoming.next().await.unwrap();
+        let _second_client = std::net::TcpStream::connect(addr).unwrap();
+        let next = tokio::time::timeout(Duration::from_millis(50), incoming.next()).await;
+        assert!(next.is_err());
+
+        // Closed while no call is waiting
+        drop(first);
+        let next = tokio::time::timeout(Duration::from_secs(5), incoming.next()).await;
+        let (_second, _) = next.unwrap().unwrap();
+        assert_eq!(incoming.open_connections(), 1);
+    });
+}
+
+#[cfg(feature = "codec")]
+#[test]
+fn udp_framed_lines() {
+    use futures::{SinkExt, StreamExt};
+    use tokio_uring::net::UdpFramed;
+    use tokio_util::codec::LinesCodec;
+
+    tokio_uring::start(async {
+        let a = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let a_addr = a.local_addr().unwrap();
+        let b_addr = b.local_addr().unwrap();
+        let mut a = UdpFramed::new(a, LinesCodec::new());
+        let mut b = UdpFramed::new(b, LinesCodec::new());
+
+        a.send(("hello".to_string(), b_addr)).await.unwrap();
+        assert_eq!(
+            b.next().await.unwrap().unwrap(),
+            ("hello".to_string(), a_addr)
+        );
+
+        // The frames of a datagram are yielded in turn
+        let b = b.into_inner();
+        b.send_to(&b"one\ntwo\n"[..], a_addr).await.0.unwrap();
+        assert_eq!(a.next().await.unwrap().unwrap().0, "one");
+        assert_eq!(a.next().await.unwrap().unwrap().0, "two");
+    });
+}
+
+#[test]
+fn udp_pktinfo_and_ecn() {
+    use std::net::{IpAddr, Ipv4Addr};
+    use tokio_uring::net::SendMeta;
+
+    tokio_uring::start(async {
+        for server_addr in ["0.0.0.0:0", "[::]:0"] {
+            let server = UdpSocket::bind(server_addr.parse().unwrap()).await.unwrap();
+            server.set_recv_pktinfo(true).unwrap();
+            server.set_recv_ecn(true).unwrap();
+            let port = server.local_addr().unwrap().port();
+
+            let client = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+                .await
+                .unwrap();
+            client.set_recv_ecn(true).unwrap();
+            let mut meta = SendMeta::new(([127, 0, 0, 1], port).into());
+            meta.ecn(0b10);
+            client.send_msg(&b"ping"[..], &meta).await.0.unwrap();
+
+            let (res, buf) = server.recv_msg(vec![0; 16]).await;
+            let (n, meta) = res.unwrap();
+            assert_eq!(&buf[..n], b"ping");
+            assert_eq!(meta.ecn(), Some(0b10));
+            assert!(meta.ifindex().is_some());
+            let dst_ip = meta.dst_ip().unwrap();
+            let dst_v4 = match dst_ip {
+                IpAddr::V4(ip) => ip,
+                IpAddr::V6(ip) => ip.to_ipv4_mapped().unwrap(),
+            };
+            assert_eq!(dst_v4, Ipv4Addr::LOCALHOST);
+
+            // The reply comes from the address the request was sent to
+            let mut reply = SendMeta::reply_to(&meta);
+            reply.ecn(0b01);
+            server.send_msg(&b"pong"[..], &reply).await.0.unwrap();
+            let (res, buf) = client.recv_msg(vec![0; 16]).await;
+            let (n, meta) = res.unwrap();
+            assert_eq!(&buf[..n], b"pong");
+            assert_eq!(meta.addr(), ([127, 0, 0, 1], port).into());
+            assert_eq!(meta.ecn(), Some(0b01));
+            assert!(meta.dst_ip().is_none());
+        }
+    });
+}
+
+#[test]
+fn receive_timestamps() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tokio_uring::net::Timestamping;
+
+    tokio_uring::start(async {
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+        let server = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        server.set_timestamping(Timestamping::RX_SOFTWARE).unwrap();
+        // The kernel turns on timestamping of the packets in a deferred
+        // work item when no other socket has it enabled
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let client = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        client.send_to(&b"ping"[..], addr).await.0.unwrap();
+
+        let (res, _) = server.recv_msg(vec![0; 16]).await;
+        let (_, meta) = res.unwrap();
+        let received = meta.timestamps().software().unwrap();
+        assert!(received >= before);
+        assert!(meta.timestamps().hardware().is_none());
+
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio_uring::spawn(async move { TcpStream::connect(addr).await.unwrap() });
+        let (stream, _) = listener.accept().await.unwrap();
+        let client = client.await.unwrap();
+        stream.set_timestamping(Timestamping::RX_SOFTWARE).unwrap();
+
+        client.write_all(&b"ping"[..]).await.0.unwrap();
+        let (res, buf) = stream.read_timestamped(vec![0; 16]).await;
+        let (n, timestamps) = res.unwrap();
+        assert_eq!(&buf[..n], b"ping");
+        assert!(timestamps.software().unwrap() >= received);
+
+        // Without timestamping, there are none
+        stream.write_all(&b"pong"[..]).await.0.unwrap();
+        let (res, _) = client.read_timestamped(vec![0; 16]).await;
+        let (_, timestamps) = res.unwrap();
+        assert_eq!(timestamps, Default::default());
+        // Unix streams accept the option, with no timestamps generated
+
+        let (a, b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let (a, mut b) = (UnixStream::from_std(a), b);
+        a.set_timestamping(Timestamping::RX_SOFTWARE).unwrap();
+        b.write_all(b"ping").unwrap();
+        let (res, _) = a.read_timestamped(vec![0; 16]).await;
+        let (n, timestamps) = res.unwrap();
+        assert_eq!(n, 4);
+        assert_eq!(timestamps, Default::default());
+    });
+}
+
+#[test]
+fn source_specific_multicast() {
+    use std::net::Ipv4Addr;
+
+    tokio_uring::start(async {
+        let group = Ipv4Addr::new(232, 1, 1, 1);
+        let local = Ipv4Addr::LOCALHOST;
+        let receiver = UdpSocket::bind("0.0.0.0:0".parse().unwrap()).await.unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        receiver.join_ssm_v4(group, local, local).unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        sender.set_multicast_if_v4(local).unwrap();
+        sender.set_multicast_loop_v4(true).unwrap();
+        sender.set_multicast_ttl_v4(1).unwrap();
+        let dest = (group, port).into();
+        sender.send_to(&b"tick"[..], dest).await.0.unwrap();
+
+        let (res, buf) = receiver.recv_from(vec![0; 16]).await;
+        let (n, from) = res.unwrap();
+        assert_eq!(&buf[..n], b"tick");
+        assert_eq!(from, sender.local_addr().unwrap());
+
+        receiver.leave_ssm_v4(group, local, local).unwrap();
+        // The group has been left with its only source
+        assert!(receiver.leave_ssm_v4(group, local, local).is_err());
+    });
+}
+
+#[test]
+fn idle_timeout() {
+    use std::time::{Duration, Instant};
+    use tokio_uring::time::IdleTimeout;
+
+    tokio_uring::start(async {
+        let (a, b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = IdleTimeout::new(UnixStream::from_std(a), Duration::from_millis(100));
+        let b = UnixStream::from_std(b);
+
+        // Activity within the period keeps the stream open
+        for _ in 0..4 {
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            b.write_all(&b"ping"[..]).await.0.unwrap();
+            let (res, _) = a.read(vec![0; 16]).await;
+            assert_eq!(res.unwrap(), 4);
+        }
+        assert!(!a.is_expired());
+
+        let idle_since = Instant::now();
+        let (res, _) = a.read(vec![0; 16]).await;
+        assert_eq!(res.unwrap(), 0);
+        assert!(a.is_expired());
+        assert!(idle_since.elapsed() >= Duration::from_millis(90));
+        let (res, _) = b.read(vec![0; 16]).await;
+        assert_eq!(res.unwrap(), 0);
+    });
+}
+
+#[test]
+fn recv_bundle() {
+    use tokio_uring::buf::provided::BufRing;
+
+    tokio_uring::start(async {
+        let ring = BufRing::builder(8).entries(8).buf_len(16).build().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        // Giving back buffers out of order, so that the entries of the ring
+        // do not follow the order of the buffer IDs
+        client.write_all(&[0u8; 40][..]).await.0.unwrap();
+        let mut held = Vec::new();
+        let mut len = 0;
+        while len < 40 {
+            let bufs = server.recv_bundle(&ring).await.unwrap();
+            // Each buffer is filled in full but the last
+            let (last, full) = bufs.split_last().unwrap();
+            assert!(full.iter().all(|buf| buf.len() == 16));
+            len += full.len() * 16 + last.len();
+            held.extend(bufs);
+        }
+        assert!(held.len() >= 3);
+        held.reverse();
+        drop(held);
+
+        let data: Vec<u8> = (0..100).collect();
+        client.write_all(data.clone()).await.0.unwrap();
+        let mut received = Vec::new();
+        while received.len() < data.len() {
+            let bufs = server.recv_bundle(&ring).await.unwrap();
+            assert!(!bufs.is_empty());
+            for buf in &bufs {
+                received.extend_from_slice(buf);
+            }
+        }
+        assert_eq!(received, data);
+
+        drop(client);
+        assert!(server.recv_bundle(&ring).await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn recv_bundle_dropped() {
+    use std::time::Duration;
+    use tokio_uring::buf::provided::BufRing;
+
+    tokio_uring::start(async {
+        let ring = BufRing::builder(8).entries(8).buf_len(16).build().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        // The receive is dropped in flight, and completes once data arrives
+        let res = tokio::time::timeout(Duration::from_millis(20), server.recv_bundle(&ring)).await;
+        assert!(res.is_err());
+        client.write_all(&[0u8; 40][..]).await.0.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The buffers it has consumed are back in the ring, and the next
+        // receives pick the buffers following them. The data it has left
+        // in the socket is skipped.
+        for round in 1..4u8 {
+            let data = vec![round; 40];
+            client.write_all(data.clone()).await.0.unwrap();
+            let mut received: Vec<u8> = Vec::new();
+            while received.len() < data.len() {
+                let bufs = server.recv_bundle(&ring).await.unwrap();
+                for buf in &bufs {
+                    received.extend(buf.iter().filter(|&&b| b != 0));
+                }
+            }
+            assert_eq!(received, data);
+        }
+    });
+}
+
+#[test]
+fn send_bundle() {
+    use tokio_uring::buf::provided::SendRing;
+
+    tokio_uring::start(async {
+        let ring = SendRing::new(9, 4).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let err = server.send_bundle(&ring).await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOBUFS));
+
+        let mut expected = Vec::new();
+        for round in 0..3u8 {
+            for i in 0..4u8 {
+                let data = vec![round * 4 + i; usize::from(i) + 1];
+                expected.extend_from_slice(&data);
+                ring.push(data).unwrap();
+            }
+            // The ring is full
+            assert_eq!(ring.push(vec![0]), Err(vec![0]));
+            assert_eq!(ring.len(), 4);
+
+            assert_eq!(server.send_bundle(&ring).await.unwrap(), 10);
+            assert!(ring.is_empty());
+        }
+
+        let mut received = Vec::new();
+        while received.len() < expected.len() {
+            let (res, buf) = client.read(vec![0; 64]).await;
+            received.extend_from_slice(&buf[..res.unwrap()]);
+        }
+        assert_eq!(received, expected);
+    });
+}
+
+#[test]
+fn send_bundle_dropped() {
+    use std::os::unix::io::AsRawFd;
+    use std::time::Duration;
+    use tokio_uring::buf::provided::SendRing;
+
+    tokio_uring::start(async {
+        let ring = SendRing::new(9, 8).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        // Fill the socket buffers, for the send to wait
+        let filler = [0u8; 4096];
+        let mut filled = 0;
+        loop {
+            let n = unsafe {
+                libc::send(
+                    server.as_raw_fd(),
+                    filler.as_ptr() as *const _,
+                    filler.len(),
+                    libc::MSG_DONTWAIT,
+                )
+            };
+            if n < 0 {
+                break;
+            }
+            filled += n as usize;
+        }
+
+        // The send is dropped in flight, and completes once the peer reads
+        for _ in 0..4 {
+            ring.push(vec![1; 65536]).unwrap();
+        }
+        let res = tokio::time::timeout(Duration::from_millis(20), server.send_bundle(&ring)).await;
+        assert!(res.is_err());
+
+        let reader = tokio_uring::spawn(async move {
+            let mut received = Vec::new();
+            loop {
+                let (res, buf) = client.read(vec![0; 65536]).await;
+                match res.unwrap() {
+                    0 => return received,
+                    n => received.extend_from_slice(&buf[..n]),
+                }
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The buffers it has consumed are freed, and the next send picks the
+        // buffers queued after them
+        ring.push(vec![2; 100]).unwrap();
+        ring.push(vec![2; 100]).unwrap();
+        assert_eq!(server.send_bundle(&ring).await.unwrap(), 200);
+        server.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let received = reader.await.unwrap();
+        assert!(received[..filled].iter().all(|&b| b == 0));
+        let (sent, last) = received[filled..].split_at(received.len() - filled - 200);
+        assert!(sent.iter().all(|&b| b == 1));
+        assert_eq!(last, [2; 200]);
+    });
+}
+
+#[test]
+fn recv_multi_buffer_starved() {
+    use std::time::Duration;
+    use tokio_uring::buf::provided::BufRing;
+    use tokio_uring::net::RecvEvent;
+
+    tokio_uring::start(async {
+        let ring = BufRing::builder(7).entries(2).buf_len(64).build().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let mut chunks = server.recv_multi(&ring);
+
+        // Holding both buffers of the ring
+        let mut held = Vec::new();
+        for data in [&b"one"[..], b"two"] {
+            client.write_all(data).await.0.unwrap();
+            match chunks.next().await.unwrap().unwrap() {
+                RecvEvent::Data(chunk) => {
+                    assert_eq!(&chunk[..], data);
+                    held.push(chunk);
+                }
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+
+        client.write_all(&b"three"[..]).await.0.unwrap();
+        let event = chunks.next().await.unwrap().unwrap();
+        assert!(matches!(event, RecvEvent::BufferStarved));
+
+        // No buffer has been given back
+        let next = tokio::time::timeout(Duration::from_millis(20), chunks.next()).await;
+        assert!(next.is_err());
+
+        // The receive resumes with the data queued meanwhile
+        held.remove(0);
+        match chunks.next().await.unwrap().unwrap() {
+            RecvEvent::Data(chunk) => assert_eq!(&chunk[..], b"three"),
+            event => panic!("unexpected {:?}", event),
+        }
+
+        drop(held);
+        drop(client);
+        assert!(chunks.next().await.is_none());
+    });
+}
+
+#[test]
+fn recv_multi_buffer_starved_retry_policy() {
+    use std::time::Duration;
+    use tokio_uring::buf::provided::BufRing;
+    use tokio_uring::net::RecvEvent;
+    use tokio_uring::RetryPolicy;
+
+    let mut policy = RetryPolicy::new();
+    policy.backoff(Duration::from_secs(1), Duration::from_secs(1));
+
+    tokio_uring::builder().retry_policy(policy).start(async {
+        let ring = BufRing::builder(7).entries(1).buf_len(64).build().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let mut chunks = server.recv_multi(&ring);
+
+        client.write_all(&b"one"[..]).await.0.unwrap();
+        let held = chunks.next().await.unwrap().unwrap();
+        assert!(matches!(held, RecvEvent::Data(_)));
+
+        // The `ENOBUFS` ending the receive is not retried
+        client.write_all(&b"two"[..]).await.0.unwrap();
+        let event = tokio::time::timeout(Duration::from_millis(500), chunks.next())
+            .await
+            .unwrap();
+        assert!(matches!(event.unwrap().unwrap(), RecvEvent::BufferStarved));
+    });
+}
+
+#[test]
+fn write_queue_coalescing() {
+    use std::time::Duration;
+    use tokio_uring::net::WriteQueue;
+
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let queue = WriteQueue::new(&client);
+        let mut expected = Vec::new();
+        for i in 0..2000 {
+            let message = format!("+{}\r\n", i).into_bytes();
+            expected.extend_from_slice(&message);
+            queue.enqueue(message).unwrap();
+        }
+        assert_eq!(queue.pending(), expected.len());
+        queue.flush().await.unwrap();
+        assert_eq!(queue.pending(), 0);
+
+        let mut received = Vec::new();
+        while received.len() < expected.len() {
+            let (res, buf) = server.read(vec![0; 4096]).await;
+            received.extend_from_slice(&buf[..res.unwrap()]);
+        }
+        assert_eq!(received, expected);
+
+        // A group reaching the watermark is written before the deadline
+        queue.deadline(Duration::from_secs(60)).watermark(8);
+        let write = queue.write(b"12345678".to_vec());
+        tokio::time::timeout(Duration::from_secs(5), write)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Smaller groups are held back until the deadline
+        queue.deadline(Duration::from_millis(50));
+        let clone = queue.clone();
+        let write = tokio_uring::spawn(async move { clone.write(b"9".to_vec()).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(queue.pending(), 1);
+        write.await.unwrap().unwrap();
+        assert_eq!(queue.pending(), 0);
+
+        let mut buf = Vec::new();
+        while buf.len() < 9 {
+            let (res, b) = server.read(vec![0; 16]).await;
+            buf.extend_from_slice(&b[..res.unwrap()]);
+        }
+        assert_eq!(buf, b"123456789");
+    });
+}
+
+#[test]
+fn request_response() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let handle = tokio_uring::spawn(async move {
+            let mut buf = vec![0; 64];
+            loop {
+                let (res, b) = server.read(buf).await;
+                let n = res.unwrap();
+                if n == 0 {
+                    break;
+                }
+                let mut reply = b"re: ".to_vec();
+                reply.extend_from_slice(&b[..n]);
+                server.write_all(reply).await.0.unwrap();
+                buf = b;
+            }
+        });
+
+        let mut response = vec![0; 64];
+        for request in [&b"get a"[..], b"get bc"] {
+            let (res, (sent, buf)) = client.request(request.to_vec(), response).await;
+            let n = res.unwrap();
+            assert_eq!(sent, request);
+            assert_eq!(&buf[..n], [&b"re: "[..], request].concat());
+            response = buf;
+        }
+
+        drop(client);
+        handle.await.unwrap();
+    });
+}
+
+#[test]
+fn takeover_hands_listeners_over() {
+    use tokio_uring::net::Takeover;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("takeover.sock");
+
+    tokio_uring::start(async {
+        let first = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let second = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addrs = [first.local_addr().unwrap(), second.local_addr().unwrap()];
+
+        let takeover = Takeover::bind(&path).unwrap();
+        assert!(Takeover::bind(&path).is_err());
+        let old = tokio_uring::spawn(async move {
+            takeover.hand_over(&[&first, &second]).await.unwrap();
+        });
+
+        let listeners = Takeover::receive(&path).await.unwrap();
+        old.await.unwrap();
+        assert!(!path.exists());
+        let received: Vec<_> = listeners.iter().map(|l| l.local_addr().unwrap()).collect();
+        assert_eq!(received, addrs);
+
+        // The old listeners are closed, and the received ones accept
+        let client = TcpStream::connect(addrs[1]).await.unwrap();
+        let (stream, _) = listeners[1].accept().await.unwrap();
+        client.write_all(&b"upgraded"[..]).await.0.unwrap();
+        let (res, buf) = stream.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"upgraded");
+
+        // The path is free for the next upgrade
+        Takeover::bind(&path).unwrap();
+    });
+}
+
+#[test]
+fn takeover_path_already_removed() {
+    use tokio_uring::net::{Takeover, UnixStream};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("takeover.sock");
+
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let takeover = Takeover::bind(&path).unwrap();
+        let old = tokio_uring::spawn(async move { takeover.hand_over(&[&listener]).await });
+
+        // The listeners are acknowledged after the path has gone
+        let stream = UnixStream::connect(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        stream.write_all(&b"\x01"[..]).await.0.unwrap();
+        old.await.unwrap().unwrap();
+    });
+}
+
+#[test]
+fn conn_pool_reuse() {
+    use std::os::unix::io::AsRawFd;
+    use std::time::Duration;
+    use tokio_uring::net::ConnPool;
+
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut pool = ConnPool::new();
+        pool.idle_timeout(Duration::from_millis(50));
+
+        let conn = pool.get(addr).await.unwrap();
+        assert!(!conn.is_reused());
+        let fd = conn.as_raw_fd();
+        let (server, _) = listener.accept().await.unwrap();
+        conn.release();
+        assert_eq!(pool.idle(addr), 1);
+
+        // The idle connection is reused
+        let conn = pool.get(addr).await.unwrap();
+        assert!(conn.is_reused());
+        assert_eq!(conn.as_raw_fd(), fd);
+        assert_eq!(pool.idle(addr), 0);
+        conn.release();
+
+        // A connection closed by the peer is not
+        drop(server);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let conn = pool.get(addr).await.unwrap();
+        assert!(!conn.is_reused());
+        let (_server, _) = listener.accept().await.unwrap();
+        conn.release();
+
+        // Neither is a connection idle for too long
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        pool.prune();
+        assert_eq!(pool.idle(addr), 0);
+        let conn = pool.get(addr).await.unwrap();
+        assert!(!conn.is_reused());
+        let (_server, _) = listener.accept().await.unwrap();
+
+        // A dropped connection is closed
+        drop(conn);
+        assert_eq!(pool.idle(addr), 0);
+    });
+}
+
+#[test]
+fn tcp_info_statistics() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        client.write_all(vec![7; 10_000]).await.0.unwrap();
+        let mut received = 0;
+        while received < 10_000 {
+            let (res, _) = server.read(vec![0; 10_000]).await;
+            received += res.unwrap();
+        }
+
+        let info = client.tcp_info().unwrap();
+        assert!(info.snd_cwnd() > 0);
+        assert!(info.snd_mss() > 0);
+        assert!(info.rto() > std::time::Duration::ZERO);
+        if let Some(sent) = info.bytes_sent() {
+            assert!(sent >= 10_000);
+        }
+        if let Some(received) = server.tcp_info().unwrap().bytes_received() {
+            assert_eq!(received, 10_000);
+        }
+    });
+}
+
+#[test]
+fn send_zc_notified_pipelined() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let (res, first) = client.send_zc_notified(vec![1u8; 4096]).await;
+        assert_eq!(res.unwrap(), 4096);
+        let (res, second) = client.send_zc_notified(vec![2u8; 4096]).await;
+        assert_eq!(res.unwrap(), 4096);
+
+        let mut received = Vec::new();
+        while received.len() < 8192 {
+            let (res, buf) = server.read(vec![0; 8192]).await;
+            received.extend_from_slice(&buf[..res.unwrap()]);
+        }
+        assert!(received[..4096].iter().all(|&b| b == 1));
+        assert!(received[4096..].iter().all(|&b| b == 2));
+
+        assert_eq!(first.await, vec![1u8; 4096]);
+        let second = second.await;
+        assert_eq!(second.len(), 4096);
+
+        // UDP sockets support it too
+        let a = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        a.connect(b.local_addr().unwrap()).await.unwrap();
+        let (res, notification) = a.send_zc_notified(&b"datagram"[..]).await;
+        assert_eq!(res.unwrap(), 8);
+        notification.await;
+        let (res, buf) = b.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"datagram");
+    });
+}
+
+#[test]
+fn sockets_created_by_the_ring() {
+    use std::os::unix::io::AsRawFd;
+
+    let cloexec = |fd| unsafe { libc::fcntl(fd, libc::F_GETFD) } & libc::FD_CLOEXEC != 0;
+
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (accepted, _) = listener.accept().await.unwrap();
+        assert!(cloexec(stream.as_raw_fd()));
+
+        let (res, _) = stream.write_all(b"ping".to_vec()).await;
+        res.unwrap();
+        let (res, buf) = accepted.read(vec![0; 4]).await;
+        assert_eq!(&buf[..res.unwrap()], b"ping");
+
+        let socket = UdpSocket::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        assert!(socket.local_addr().unwrap().port() != 0);
+        assert!(cloexec(socket.as_raw_fd()));
+    });
+}
+
+#[test]
+fn listener_set_up_by_the_ring() {
+    use tokio_uring::net::TcpSocket;
+
+    tokio_uring::start(async {
+        let socket = TcpSocket::open_v4().await.unwrap();
+        socket.set_reuseaddr(true).unwrap();
+        socket
+            .bind_async("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = socket.local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+        let listener = socket.listen_async(16).await.unwrap();
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (accepted, _) = listener.accept().await.unwrap();
+        let (res, _) = accepted.write_all(b"pong".to_vec()).await;
+        res.unwrap();
+        let (res, buf) = stream.read(vec![0; 4]).await;
+        assert_eq!(&buf[..res.unwrap()], b"pong");
+    });
+}
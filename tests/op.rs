@@ -0,0 +1,110 @@
+use io_uring::{opcode, squeue, types};
+use std::io::{self, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use tokio_uring::op::{self, Operation};
+
+struct Read {
+    fd: RawFd,
+    buf: Vec<u8>,
+}
+
+unsafe impl Operation for Read {
+    type Output = io::Result<Vec<u8>>;
+
+    fn build(&mut self) -> squeue::Entry {
+        let len = self.buf.capacity() as u32;
+        opcode::Read::new(types::Fd(self.fd), self.buf.as_mut_ptr(), len).build()
+    }
+
+    fn complete(mut self, result: io::Result<u32>, _flags: u32) -> Self::Output {
+        let n = result?;
+        unsafe { self.buf.set_len(n as usize) };
+        Ok(self.buf)
+    }
+}
+
+#[test]
+fn external_read_operation() {
+    let mut tempfile = tempfile::tempfile().unwrap();
+    tempfile.write_all(b"hello world").unwrap();
+
+    tokio_uring::start(async {
+        let fd = tempfile.as_raw_fd();
+        let data = op::submit(Read {
+            fd,
+            buf: Vec::with_capacity(32),
+        })
+        .unwrap()
+        .await
+        .unwrap();
+        assert_eq!(data, b"hello world");
+
+        // The error of the completion is passed on
+        let err = op::submit(Read {
+            fd: -1,
+            buf: Vec::with_capacity(32),
+        })
+        .unwrap()
+        .await
+        .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+
+        // Dropping the future keeps the buffer until the operation completes
+        drop(
+            op::submit(Read {
+                fd,
+                buf: Vec::with_capacity(32),
+            })
+            .unwrap(),
+        );
+        tokio_uring::no_op().await.unwrap();
+    });
+}
+
+struct OpenDirect {
+    path: std::ffi::CString,
+    slot: u32,
+}
+
+unsafe impl Operation for OpenDirect {
+    type Output = io::Result<()>;
+
+    fn build(&mut self) -> squeue::Entry {
+        let slot = types::DestinationSlot::try_from_slot_target(self.slot).unwrap();
+        opcode::OpenAt::new(types::Fd(libc::AT_FDCWD), self.path.as_ptr())
+            .file_index(Some(slot))
+            .build()
+    }
+
+    fn complete(self, result: io::Result<u32>, _flags: u32) -> Self::Output {
+        result.map(|_| ())
+    }
+}
+
+#[test]
+fn install_direct_descriptor() {
+    use std::io::Read;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut tempfile = tempfile::NamedTempFile::new().unwrap();
+    tempfile.write_all(b"hello world").unwrap();
+    let path = std::ffi::CString::new(tempfile.path().as_os_str().as_bytes()).unwrap();
+
+    tokio_uring::builder().register_file_table(4).start(async {
+        op::submit(OpenDirect { path, slot: 2 })
+            .unwrap()
+            .await
+            .unwrap();
+
+        let fd = op::install_fixed_fd(2, true).await.unwrap();
+        let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) };
+        assert_ne!(flags & libc::FD_CLOEXEC, 0);
+        let mut data = String::new();
+        std::fs::File::from(fd).read_to_string(&mut data).unwrap();
+        assert_eq!(data, "hello world");
+
+        // An empty slot has no descriptor to install
+        let err = op::install_fixed_fd(3, false).await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    });
+}
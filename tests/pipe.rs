@@ -0,0 +1,139 @@
+use std::os::unix::fs::OpenOptionsExt;
+use tokio_uring::buf::provided::BufRing;
+use tokio_uring::fs::{self, OpenOptions, PipeReader, PipeWriter};
+
+#[test]
+fn fifo_read_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fifo");
+
+    tokio_uring::start(async {
+        fs::mkfifo(&path, 0o600).await.unwrap();
+        let err = fs::mkfifo(&path, 0o600).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+        // Without a reader, a non-blocking open for writing fails
+        let err = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&path)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.raw_os_error(), Some(libc::ENXIO));
+
+        // Both opens wait for the other end
+        let writer = tokio_uring::spawn({
+            let path = path.clone();
+            async move { PipeWriter::open(&path).await.unwrap() }
+        });
+        let reader = PipeReader::open(&path).await.unwrap();
+        let writer = writer.await.unwrap();
+
+        // The read waits for the data
+        let read = tokio_uring::spawn(async move {
+            let (res, buf) = reader.read(vec![0; 16]).await;
+            assert_eq!(&buf[..res.unwrap()], b"hello");
+            reader
+        });
+        tokio::task::yield_now().await;
+        writer.write_all(&b"hello"[..]).await.0.unwrap();
+        let reader = read.await.unwrap();
+
+        writer.close().await.unwrap();
+        let (res, _) = reader.read(vec![0; 16]).await;
+        assert_eq!(res.unwrap(), 0);
+        reader.close().await.unwrap();
+    });
+}
+
+#[test]
+fn fifo_opened_non_blocking() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fifo");
+
+    tokio_uring::start(async {
+        fs::mkfifo(&path, 0o600).await.unwrap();
+
+        // Opened without waiting for a writer
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&path)
+            .await
+            .unwrap();
+        let reader = PipeReader::from_file(file);
+        let writer = PipeWriter::open(&path).await.unwrap();
+
+        // The read waits rather than failing with EAGAIN
+        let read = tokio_uring::spawn(async move { reader.read(vec![0; 16]).await });
+        tokio::task::yield_now().await;
+        writer.write_all(&b"data"[..]).await.0.unwrap();
+        let (res, buf) = read.await.unwrap();
+        assert_eq!(&buf[..res.unwrap()], b"data");
+    });
+}
+
+#[test]
+fn fifo_open_waits_for_other_end() {
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fifo");
+
+    tokio_uring::start(async {
+        fs::mkfifo(&path, 0o600).await.unwrap();
+
+        // The open waits for a writer
+        let open = PipeReader::open(&path);
+        let res = tokio::time::timeout(Duration::from_millis(10), open).await;
+        assert!(res.is_err());
+
+        // The dropped open still counts as a reader, and completes once
+        // there is a writer
+        let file = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&path)
+            .await
+            .unwrap();
+        let writer = PipeWriter::from_file(file);
+
+        // The file it has opened is closed, leaving no reader
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let (res, _) = writer.write(&b"lost"[..]).await;
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EPIPE));
+    });
+}
+
+#[test]
+fn anonymous_pipe() {
+    tokio_uring::start(async {
+        let (reader, writer) = fs::pipe().unwrap();
+        writer.write_all(&b"ping"[..]).await.0.unwrap();
+        drop(writer);
+        let (res, buf) = reader.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"ping");
+    });
+}
+
+#[test]
+fn read_multi_chunks() {
+    tokio_uring::start(async {
+        let ring = BufRing::builder(0).entries(2).buf_len(64).build().unwrap();
+        let (reader, writer) = fs::pipe().unwrap();
+        let mut chunks = reader.read_multi(&ring);
+
+        // More chunks than buffers, which are reused once dropped
+        for i in 0..8 {
+            let data = format!("chunk {}", i);
+            writer.write_all(data.clone().into_bytes()).await.0.unwrap();
+            let chunk = chunks.next().await.unwrap().unwrap();
+            assert_eq!(&chunk[..], data.as_bytes());
+        }
+
+        writer.close().await.unwrap();
+        assert!(chunks.next().await.is_none());
+        assert!(chunks.next().await.is_none());
+    });
+}
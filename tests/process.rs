@@ -0,0 +1,52 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Stdio};
+use tokio_uring::process;
+
+#[test]
+fn wait_for_exit() {
+    tokio_uring::start(async {
+        let mut child =
+            process::spawn(Command::new("sh").args(["-c", "sleep 0.1; exit 3"])).unwrap();
+        assert!(child.try_wait().unwrap().is_none());
+
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        // The status is kept once the child is reaped
+        assert_eq!(child.wait().await.unwrap().code(), Some(3));
+        assert_eq!(child.try_wait().unwrap().unwrap().code(), Some(3));
+    });
+}
+
+#[test]
+fn kill_child() {
+    tokio_uring::start(async {
+        let mut child = process::spawn(
+            Command::new("cat")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null()),
+        )
+        .unwrap();
+
+        child.kill().unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+
+        // The pidfd does not refer to another process
+        let err = child.signal(libc::SIGTERM).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    });
+}
+
+#[test]
+fn wait_closes_stdin() {
+    tokio_uring::start(async {
+        let mut child = process::spawn(
+            Command::new("cat")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null()),
+        )
+        .unwrap();
+        assert!(child.wait().await.unwrap().success());
+    });
+}
@@ -0,0 +1,91 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio_uring::reload::Watcher;
+
+// Sends a signal to the thread of the runtime, which has blocked it
+fn raise(signal: libc::c_int) {
+    assert_eq!(
+        unsafe { libc::pthread_kill(libc::pthread_self(), signal) },
+        0
+    );
+}
+
+#[test]
+fn reload_quiesces_work() {
+    tokio_uring::start(async {
+        let watcher = Rc::new(Watcher::with_signals(&[libc::SIGHUP, libc::SIGUSR1]).unwrap());
+
+        // A signal received before waiting is kept
+        raise(libc::SIGUSR1);
+        let reload = watcher.next().await.unwrap();
+        assert_eq!(reload.signal(), libc::SIGUSR1);
+
+        // Waiting for a signal
+        let next = tokio_uring::spawn({
+            let watcher = watcher.clone();
+            async move { watcher.next().await.unwrap() }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!next.is_finished());
+        raise(libc::SIGHUP);
+        let reload = next.await.unwrap();
+        assert_eq!(reload.signal(), libc::SIGHUP);
+
+        // The reload waits for the work entered
+        let guard = watcher.enter().await;
+        let reloaded = Rc::new(Cell::new(false));
+        let reloading = tokio_uring::spawn({
+            let reloaded = reloaded.clone();
+            async move {
+                let quiesced = reload.quiesce().await;
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                reloaded.set(true);
+                drop(quiesced);
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!reloading.is_finished());
+
+        // New work waits for the reload
+        let entering = tokio_uring::spawn({
+            let watcher = watcher.clone();
+            let reloaded = reloaded.clone();
+            async move {
+                let _guard = watcher.enter().await;
+                assert!(reloaded.get());
+            }
+        });
+        drop(guard);
+        reloading.await.unwrap();
+        entering.await.unwrap();
+    });
+}
+
+#[test]
+fn unblockable_signal() {
+    let err = Watcher::with_signals(&[libc::SIGKILL]).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+}
+
+#[cfg(feature = "stream")]
+#[test]
+fn reload_stream() {
+    use futures::StreamExt;
+
+    tokio_uring::start(async {
+        let mut watcher = Watcher::with_signals(&[libc::SIGUSR2]).unwrap();
+
+        // A pending poll is kept when the future is dropped
+        {
+            let next = std::pin::pin!(StreamExt::next(&mut watcher));
+            assert!(futures::poll!(next).is_pending());
+        }
+
+        for _ in 0..2 {
+            raise(libc::SIGUSR2);
+            let reload = StreamExt::next(&mut watcher).await.unwrap().unwrap();
+            assert_eq!(reload.signal(), libc::SIGUSR2);
+        }
+    });
+}
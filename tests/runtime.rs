@@ -34,3 +34,1011 @@ fn spawn_a_task() {
         assert_eq!(2, *cell.borrow());
     });
 }
+
+#[test]
+fn spawn_blocking_runs_on_another_thread() {
+    tokio_uring::start(async {
+        let runtime_thread = std::thread::current().id();
+        let thread = tokio_uring::spawn_blocking(|| std::thread::current().id())
+            .await
+            .unwrap();
+        assert_ne!(thread, runtime_thread);
+    });
+}
+
+#[test]
+fn local_join_set() {
+    use std::rc::Rc;
+    use tokio_uring::task::LocalJoinSet;
+
+    tokio_uring::start(async {
+        let mut set = LocalJoinSet::new();
+        for i in 0..5 {
+            // Rc makes the task !Send
+            let i = Rc::new(i);
+            set.spawn(async move { *i });
+        }
+        set.spawn(std::future::pending());
+        assert_eq!(set.len(), 6);
+
+        let mut sum = 0;
+        for _ in 0..5 {
+            sum += set.join_next().await.unwrap().unwrap();
+        }
+        assert_eq!(sum, 10);
+
+        set.abort_all();
+        assert!(set.join_next().await.unwrap().unwrap_err().is_cancelled());
+        assert!(set.join_next().await.is_none());
+    });
+}
+
+#[test]
+fn shutdown_timeout_cancels_operations() {
+    use std::time::{Duration, Instant};
+
+    let rt = tokio_uring::Runtime::new(&tokio_uring::builder()).unwrap();
+    rt.block_on(async {
+        let listener = tokio_uring::net::TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        tokio_uring::spawn(async move {
+            // Never completes unless canceled
+            let _ = listener.accept().await;
+        });
+        tokio::task::yield_now().await;
+    });
+
+    let start = Instant::now();
+    rt.shutdown_timeout(Duration::from_secs(10));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn cq_overflow_policies() {
+    use tokio_uring::CqOverflow;
+
+    // With 4 completion queue entries, submitting 32 operations at once
+    // overflows the completion queue.
+    fn run(policy: CqOverflow) -> Vec<std::io::Result<()>> {
+        tokio_uring::builder()
+            .entries(4)
+            .cq_entries(4)
+            .cq_overflow(policy)
+            .start(async { futures::future::join_all((0..32).map(|_| tokio_uring::no_op())).await })
+    }
+
+    assert!(run(CqOverflow::Backpressure).iter().all(Result::is_ok));
+    assert!(run(CqOverflow::Grow).iter().all(Result::is_ok));
+
+    let results = run(CqOverflow::Error);
+    assert!(results.iter().any(Result::is_ok));
+    assert!(results
+        .iter()
+        .any(|res| matches!(res, Err(e) if e.raw_os_error() == Some(libc::EBUSY))));
+}
+
+#[test]
+fn cq_overflow_error_returns_buffers() {
+    use std::os::unix::io::FromRawFd;
+    use tokio_uring::fs::File;
+    use tokio_uring::CqOverflow;
+
+    let (rx, tx) = nix::unistd::pipe().unwrap();
+    let _rx = unsafe { std::fs::File::from_raw_fd(rx) };
+    let results = tokio_uring::builder()
+        .entries(4)
+        .cq_entries(4)
+        .cq_overflow(CqOverflow::Error)
+        .start(async {
+            let file = unsafe { File::from_raw_fd(tx) };
+            let writes = (0..32u8).map(|i| file.write_at(vec![i], 0));
+            futures::future::join_all(writes).await
+        });
+
+    assert!(results.iter().any(|(res, _)| res.is_ok()));
+    assert!(results
+        .iter()
+        .any(|(res, _)| matches!(res, Err(e) if e.raw_os_error() == Some(libc::EBUSY))));
+    for (i, (_, buf)) in results.iter().enumerate() {
+        assert_eq!(buf, &[i as u8]);
+    }
+}
+
+#[test]
+fn submit_all_and_retry_partial_submit() {
+    tokio_uring::builder()
+        .entries(4)
+        .submit_all(true)
+        .retry_partial_submit(true)
+        .start(async {
+            let results = futures::future::join_all((0..32).map(|_| tokio_uring::no_op())).await;
+            assert!(results.iter().all(Result::is_ok));
+        });
+}
+
+#[test]
+fn register_file_table() {
+    tokio_uring::builder()
+        .register_file_table(1024)
+        .start(async {
+            tokio_uring::no_op().await.unwrap();
+        });
+}
+
+#[test]
+fn dump_inflight_operations() {
+    use std::os::unix::io::FromRawFd;
+    use std::time::Duration;
+    use tokio_uring::fs::File;
+    use tokio_uring::Handle;
+
+    tokio_uring::start(async {
+        let (rx, _tx) = nix::unistd::pipe().unwrap();
+        let file = unsafe { File::from_raw_fd(rx) };
+
+        assert!(Handle::current().dump_inflight().is_empty());
+
+        let read = tokio_uring::with_op_label("pipe read", file.read_at(vec![0; 16], 0));
+        tokio::select! {
+            _ = read => panic!("read completed"),
+            _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+        }
+
+        let ops = Handle::current().dump_inflight();
+        assert_eq!(ops.len(), 1);
+        let op = &ops[0];
+        assert_eq!(op.opcode(), io_uring::opcode::Read::CODE);
+        assert_eq!(op.fd(), Some(rx));
+        assert!(!op.is_fixed_fd());
+        assert!(op.age() >= Duration::from_millis(20));
+        assert_eq!(op.label(), Some("pipe read"));
+        assert!(op.is_abandoned());
+    });
+}
+
+#[test]
+fn stall_watchdog_reports_stall() {
+    use std::os::unix::io::FromRawFd;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let stalls = Arc::new(AtomicUsize::new(0));
+
+    tokio_uring::builder()
+        .stall_watchdog(Duration::from_millis(20))
+        .on_stall({
+            let stalls = stalls.clone();
+            move |_| {
+                stalls.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .start(async {
+            // Waiting for io-uring operations is not a stall
+            let (rx, _tx) = nix::unistd::pipe().unwrap();
+            let file = unsafe { tokio_uring::fs::File::from_raw_fd(rx) };
+            tokio::select! {
+                _ = file.read_at(vec![0; 16], 0) => panic!("read completed"),
+                _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+            }
+            assert_eq!(stalls.load(Ordering::Relaxed), 0);
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert_eq!(stalls.load(Ordering::Relaxed), 1);
+        });
+}
+
+#[test]
+fn stall_watchdog_reports_blocked_thread() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let stalls = Arc::new(AtomicUsize::new(0));
+
+    tokio_uring::builder()
+        .stall_watchdog(Duration::from_millis(20))
+        .on_stall({
+            let stalls = stalls.clone();
+            move |_| {
+                stalls.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .start(async {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let waiting = tokio_uring::spawn(rx);
+            tokio::task::yield_now().await;
+
+            // The task woken is not polled while the thread is blocked
+            tokio_uring::spawn(async move {
+                tx.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(100));
+            })
+            .await
+            .unwrap();
+            waiting.await.unwrap().unwrap();
+            assert_eq!(stalls.load(Ordering::Relaxed), 1);
+        });
+}
+
+#[cfg(not(feature = "metrics"))]
+#[test]
+fn stall_watchdog_requires_reporter() {
+    let mut builder = tokio_uring::builder();
+    builder.stall_watchdog(std::time::Duration::from_millis(20));
+    let err = tokio_uring::Runtime::new(&builder).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn with_cancellation_cancels_operations() {
+    use std::os::unix::io::FromRawFd;
+    use std::time::Duration;
+    use tokio_uring::fs::File;
+
+    tokio_uring::start(async {
+        let (rx, _tx) = nix::unistd::pipe().unwrap();
+        let file = unsafe { File::from_raw_fd(rx) };
+
+        let (res, buf) = tokio_uring::with_cancellation(
+            tokio::time::sleep(Duration::from_millis(10)),
+            file.read_at(vec![0; 16], 0),
+        )
+        .await;
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::ECANCELED));
+        assert_eq!(buf.len(), 16);
+
+        // Operations submitted after the cancellation are canceled as well
+        let (res, _) = tokio_uring::with_cancellation(async {}, async {
+            tokio::task::yield_now().await;
+            file.read_at(vec![0; 16], 0).await
+        })
+        .await;
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::ECANCELED));
+    });
+}
+
+#[test]
+fn cancel_on_drop() {
+    use std::os::unix::io::FromRawFd;
+    use std::time::Duration;
+    use tokio_uring::fs::File;
+    use tokio_uring::Handle;
+
+    fn run(cancel_on_drop: bool) -> usize {
+        tokio_uring::builder()
+            .cancel_on_drop(cancel_on_drop)
+            .start(async {
+                let (rx, _tx) = nix::unistd::pipe().unwrap();
+                let file = unsafe { File::from_raw_fd(rx) };
+
+                tokio::select! {
+                    _ = file.read_at(vec![0; 16], 0) => panic!("read completed"),
+                    _ = tokio::time::sleep(Duration::from_millis(10)) => {}
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+
+                Handle::current().dump_inflight().len()
+            })
+    }
+
+    assert_eq!(run(false), 1);
+    assert_eq!(run(true), 0);
+}
+
+#[test]
+fn timeout_cancels_operations() {
+    use std::os::unix::io::FromRawFd;
+    use std::time::{Duration, Instant};
+    use tokio_uring::fs::File;
+
+    tokio_uring::start(async {
+        let (rx, tx) = nix::unistd::pipe().unwrap();
+        let file = unsafe { File::from_raw_fd(rx) };
+
+        let start = Instant::now();
+        let res =
+            tokio_uring::time::timeout(Duration::from_millis(20), file.read_at(vec![0; 16], 0))
+                .await;
+        // The read is either dropped or canceled by the kernel first
+        if let Ok((res, buf)) = res {
+            assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+            assert_eq!(buf.len(), 16);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // Operations completing before the deadline are not affected
+        nix::unistd::write(tx, b"hello").unwrap();
+        let (res, buf) =
+            tokio_uring::time::timeout(Duration::from_secs(1), file.read_at(vec![0; 16], 0))
+                .await
+                .unwrap();
+        assert_eq!(&buf[..res.unwrap()], b"hello");
+    });
+}
+
+#[test]
+fn timeout_interrupts_other_waits() {
+    use std::time::{Duration, Instant};
+
+    tokio_uring::start(async {
+        let notify = tokio::sync::Notify::new();
+        let (_tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+        let start = Instant::now();
+        let res = tokio_uring::time::timeout(Duration::from_millis(20), notify.notified()).await;
+        assert!(res.is_err());
+        let res = tokio_uring::time::timeout(Duration::from_millis(20), rx).await;
+        assert!(res.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    });
+}
+
+#[test]
+fn timeout_bounds_linked_operations() {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio_uring::net::TcpStream;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (_server, _) = listener.accept().unwrap();
+
+    let flushes = Arc::new(Mutex::new(Vec::new()));
+    tokio_uring::builder()
+        .on_flush({
+            let flushes = flushes.clone();
+            move |stats| flushes.lock().unwrap().push(stats.submitted())
+        })
+        .start(async {
+            let stream = TcpStream::from_std(client);
+
+            let start = Instant::now();
+            let request = stream.request(b"ping".to_vec(), vec![0; 16]);
+            let res = tokio_uring::time::timeout(Duration::from_millis(20), request).await;
+            if let Ok((res, _)) = res {
+                assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+            }
+            assert!(start.elapsed() >= Duration::from_millis(20));
+            assert!(start.elapsed() < Duration::from_secs(1));
+        });
+
+    // The write and the read are submitted with the linked timeout
+    assert_eq!(flushes.lock().unwrap()[0], 3);
+}
+
+#[test]
+fn with_deadline_bounds_nested_operations() {
+    use std::os::unix::io::FromRawFd;
+    use std::time::Duration;
+    use tokio::time::Instant;
+    use tokio_uring::fs::File;
+    use tokio_uring::time::{deadline, with_deadline};
+
+    async fn read(file: &File) -> std::io::Result<usize> {
+        file.read_at(vec![0; 16], 0).await.0
+    }
+
+    tokio_uring::start(async {
+        let (rx, _tx) = nix::unistd::pipe().unwrap();
+        let file = unsafe { File::from_raw_fd(rx) };
+        assert_eq!(deadline(), None);
+
+        let start = Instant::now();
+        let at = start + Duration::from_millis(20);
+        let res = with_deadline(at, async {
+            assert_eq!(deadline(), Some(at));
+            // A later deadline does not extend the enclosing one
+            with_deadline(at + Duration::from_secs(10), async {
+                assert_eq!(deadline(), Some(at));
+                read(&file).await
+            })
+            .await
+        })
+        .await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // Operations submitted past the deadline time out right away
+        let res = with_deadline(start, read(&file)).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    });
+}
+
+#[test]
+fn with_deadline_links_earliest_timeout() {
+    use std::os::unix::io::FromRawFd;
+    use std::time::Duration;
+    use tokio::time::Instant;
+    use tokio_uring::fs::File;
+    use tokio_uring::time::with_deadline;
+
+    tokio_uring::start(async {
+        let (rx, tx) = nix::unistd::pipe().unwrap();
+        let file = unsafe { File::from_raw_fd(rx) };
+
+        // The runtime is blocked past the outer deadline, and data arrives
+        // in the meantime: only the kernel can time out the read.
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            nix::unistd::write(tx, b"hello").unwrap();
+        });
+        tokio_uring::spawn(async { std::thread::sleep(Duration::from_millis(100)) });
+
+        let start = Instant::now();
+        let read = file.read_at(vec![0; 16], 0);
+        let (res, _) = with_deadline(
+            start + Duration::from_millis(20),
+            with_deadline(start + Duration::from_secs(10), read),
+        )
+        .await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        writer.join().unwrap();
+    });
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn timeout_with_paused_clock() {
+    use std::os::unix::io::FromRawFd;
+    use std::time::{Duration, Instant};
+    use tokio_uring::fs::File;
+
+    let start = Instant::now();
+    tokio_uring::builder().start_paused(true).start(async {
+        let (rx, _tx) = nix::unistd::pipe().unwrap();
+        let file = unsafe { File::from_raw_fd(rx) };
+
+        let virtual_start = tokio::time::Instant::now();
+        let res =
+            tokio_uring::time::timeout(Duration::from_secs(3600), file.read_at(vec![0; 16], 0))
+                .await;
+        assert!(res.is_err());
+        assert!(virtual_start.elapsed() >= Duration::from_secs(3600));
+
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+    });
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn completions_consume_coop_budget() {
+    use std::future::{poll_fn, Future};
+    use std::pin::Pin;
+    use std::task::Poll;
+
+    tokio_uring::builder().entries(1024).start(async {
+        let task = tokio_uring::spawn(async {
+            let mut ops: Vec<Pin<Box<dyn Future<Output = _>>>> = (0..1000)
+                .map(|_| Box::pin(tokio_uring::no_op()) as _)
+                .collect();
+            let mut done = vec![false; ops.len()];
+            let mut max_per_poll = 0;
+
+            poll_fn(|cx| {
+                let mut completed = 0;
+                for (op, done) in ops.iter_mut().zip(&mut done) {
+                    if !*done && op.as_mut().poll(cx).is_ready() {
+                        *done = true;
+                        completed += 1;
+                    }
+                }
+                max_per_poll = max_per_poll.max(completed);
+                if done.iter().all(|&d| d) {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+            max_per_poll
+        });
+
+        // Tokio's budget is 128 per poll of a task
+        let max_per_poll = task.await.unwrap();
+        assert!(
+            max_per_poll <= 128,
+            "{} completions in a poll",
+            max_per_poll
+        );
+    });
+}
+
+#[test]
+fn enter_runtime_context() {
+    use tokio_uring::Handle;
+
+    assert!(Handle::try_current().is_none());
+    let rt = tokio_uring::Runtime::new(&tokio_uring::builder()).unwrap();
+    let handle = rt.handle();
+    rt.block_on(async {
+        assert!(Handle::try_current().is_some());
+    });
+    assert!(Handle::try_current().is_none());
+
+    // The runtime can run futures while its context is entered
+    let guard = handle.enter();
+    let other = tokio_uring::Runtime::new(&tokio_uring::builder()).unwrap();
+    {
+        let other_handle = other.handle();
+        let _inner = other_handle.enter();
+        assert!(Handle::try_current().is_some());
+    }
+    rt.block_on(tokio_uring::no_op()).unwrap();
+    drop(guard);
+    assert!(Handle::try_current().is_none());
+}
+
+#[test]
+fn bulk_operations_are_submitted_last() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use tokio_uring::Priority;
+
+    tokio_uring::start(async {
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let mut tasks = Vec::new();
+        for (priority, name) in [
+            (Priority::Bulk, "bulk"),
+            (Priority::Bulk, "bulk"),
+            (Priority::Normal, "normal"),
+            (Priority::LatencyCritical, "latency-critical"),
+        ] {
+            let order = order.clone();
+            tasks.push(tokio_uring::spawn(tokio_uring::with_priority(
+                priority,
+                async move {
+                    tokio_uring::no_op().await.unwrap();
+                    order.borrow_mut().push(name);
+                },
+            )));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(
+            *order.borrow(),
+            ["normal", "latency-critical", "bulk", "bulk"]
+        );
+    });
+}
+
+#[test]
+fn task_sqe_budget() {
+    use std::os::unix::io::FromRawFd;
+    use std::rc::Rc;
+    use tokio_uring::fs::File;
+
+    tokio_uring::builder().task_sqe_budget(2).start(async {
+        let (rx, tx) = nix::unistd::pipe().unwrap();
+        let tx = Rc::new(unsafe { File::from_raw_fd(tx) });
+
+        let greedy = tokio_uring::spawn({
+            let tx = tx.clone();
+            async move {
+                let writes = (0..5).map(|_| tx.write_at(b"a".to_vec(), 0));
+                futures::future::join_all(writes).await
+            }
+        });
+        let other = tokio_uring::spawn({
+            let tx = tx.clone();
+            async move { tx.write_at(b"b".to_vec(), 0).await }
+        });
+        greedy.await.unwrap();
+        other.await.unwrap().0.unwrap();
+
+        // The writes of the greedy task past its budget are submitted after
+        // the write of the other task
+        let mut buf = [0; 16];
+        let n = nix::unistd::read(rx, &mut buf).unwrap();
+        assert_eq!(&buf[..n], b"aabaaa");
+    });
+}
+
+#[test]
+fn submission_hooks() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    let sq_full = Arc::new(AtomicU64::new(0));
+    let submitted = Arc::new(AtomicU64::new(0));
+
+    tokio_uring::builder()
+        .entries(4)
+        .on_sq_full({
+            let sq_full = sq_full.clone();
+            move |stats| sq_full.store(stats.sq_full(), Ordering::Relaxed)
+        })
+        .on_flush({
+            let submitted = submitted.clone();
+            move |stats| {
+                assert!(stats.submitted() > 0);
+                submitted.store(stats.total_submitted(), Ordering::Relaxed)
+            }
+        })
+        .start(async {
+            let results = futures::future::join_all((0..32).map(|_| tokio_uring::no_op())).await;
+            assert!(results.iter().all(Result::is_ok));
+        });
+
+    assert!(sq_full.load(Ordering::Relaxed) > 0);
+    assert_eq!(submitted.load(Ordering::Relaxed), 32);
+}
+
+#[test]
+fn event_loop_hooks() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct Seen {
+        completions: u64,
+        submitted: usize,
+        parked: Duration,
+        ticks: u64,
+        parks: u64,
+    }
+    let seen = Arc::new(Mutex::new(Seen::default()));
+
+    tokio_uring::builder()
+        // The entries are all submitted when parking
+        .flush_policy(tokio_uring::FlushPolicy::on_park())
+        .on_tick({
+            let seen = seen.clone();
+            move |stats| {
+                let mut seen = seen.lock().unwrap();
+                seen.completions += stats.completions() as u64;
+                assert_eq!(seen.completions, stats.total_completions());
+                seen.ticks = stats.ticks();
+            }
+        })
+        .on_park({
+            let seen = seen.clone();
+            move |stats| {
+                let mut seen = seen.lock().unwrap();
+                seen.submitted += stats.submitted();
+                seen.parks = stats.parks();
+            }
+        })
+        .on_unpark({
+            let seen = seen.clone();
+            move |stats| seen.lock().unwrap().parked += stats.park_duration()
+        })
+        .start(async {
+            let results = futures::future::join_all((0..8).map(|_| tokio_uring::no_op())).await;
+            assert!(results.iter().all(Result::is_ok));
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        });
+
+    let seen = seen.lock().unwrap();
+    assert!(seen.completions >= 8);
+    assert!(seen.ticks > 0);
+    assert_eq!(seen.submitted, 8);
+    assert!(seen.parks > 0);
+    assert!(seen.parked >= Duration::from_millis(20));
+}
+
+#[test]
+fn adaptive_flush_policy() {
+    use std::sync::{Arc, Mutex};
+    use tokio_uring::FlushPolicy;
+
+    fn flushes(policy: Option<FlushPolicy>, ops: usize) -> Vec<usize> {
+        let flushes = Arc::new(Mutex::new(Vec::new()));
+        let mut builder = tokio_uring::builder();
+        if let Some(policy) = policy {
+            builder.flush_policy(policy);
+        }
+        builder
+            .on_flush({
+                let flushes = flushes.clone();
+                move |stats| flushes.lock().unwrap().push(stats.submitted())
+            })
+            .start(async {
+                let results =
+                    futures::future::join_all((0..ops).map(|_| tokio_uring::no_op())).await;
+                assert!(results.iter().all(Result::is_ok));
+            });
+        let flushes = flushes.lock().unwrap().clone();
+        flushes
+    }
+
+    // Submitted as they are created while few are in flight
+    assert_eq!(flushes(None, 3), [1, 1, 1]);
+    assert_eq!(flushes(Some(FlushPolicy::on_park()), 3), [3]);
+
+    // Batched once more are in flight
+    let mut policy = FlushPolicy::adaptive();
+    policy.shallow_depth(1).max_batch(2);
+    assert_eq!(flushes(Some(policy), 6), [1, 2, 2, 1]);
+}
+
+#[test]
+fn adaptive_flush_policy_ignores_socket_waits() {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    let (a, mut b) = std::os::unix::net::UnixStream::pair().unwrap();
+    let writer = std::thread::spawn(move || {
+        for _ in 0..16 {
+            std::thread::sleep(Duration::from_millis(2));
+            b.write_all(b"x").unwrap();
+        }
+    });
+
+    let flushes = Arc::new(Mutex::new(Vec::new()));
+    tokio_uring::builder()
+        .on_flush({
+            let flushes = flushes.clone();
+            move |stats| flushes.lock().unwrap().push(stats.submitted())
+        })
+        .start(async {
+            // Reads waiting for data take long, but the kernel is not loaded
+            let stream = tokio_uring::net::UnixStream::from_std(a);
+            for _ in 0..16 {
+                let (res, _) = stream.read(vec![0; 1]).await;
+                assert_eq!(res.unwrap(), 1);
+            }
+
+            flushes.lock().unwrap().clear();
+            let results = futures::future::join_all((0..3).map(|_| tokio_uring::no_op())).await;
+            assert!(results.iter().all(Result::is_ok));
+        });
+    writer.join().unwrap();
+
+    // Still submitted as they are created
+    assert_eq!(flushes.lock().unwrap()[..3], [1, 1, 1]);
+}
+
+#[test]
+fn latency_critical_submitted_immediately() {
+    use std::sync::{Arc, Mutex};
+    use tokio_uring::{FlushPolicy, Priority};
+
+    fn flushes(priority: Priority) -> Vec<usize> {
+        let flushes = Arc::new(Mutex::new(Vec::new()));
+        tokio_uring::builder()
+            .flush_policy(FlushPolicy::on_park())
+            .on_flush({
+                let flushes = flushes.clone();
+                move |stats| flushes.lock().unwrap().push(stats.submitted())
+            })
+            .start(tokio_uring::with_priority(priority, async {
+                let results = futures::future::join_all((0..3).map(|_| tokio_uring::no_op())).await;
+                assert!(results.iter().all(Result::is_ok));
+            }));
+        let flushes = flushes.lock().unwrap().clone();
+        flushes
+    }
+
+    assert_eq!(flushes(Priority::LatencyCritical), [1, 1, 1]);
+    assert_eq!(flushes(Priority::Normal), [3]);
+}
+
+#[test]
+fn batches_submitted_together() {
+    use std::sync::{Arc, Mutex};
+    use tokio_uring::{Batch, Priority};
+
+    let flushes = Arc::new(Mutex::new(Vec::new()));
+
+    tokio_uring::builder()
+        .flush_latency_critical(true)
+        .on_flush({
+            let flushes = flushes.clone();
+            move |stats| flushes.lock().unwrap().push(stats.submitted())
+        })
+        .start(tokio_uring::with_priority(
+            Priority::LatencyCritical,
+            async {
+                // Each operation would be submitted on its own
+                let (a, b, c) = tokio_uring::join_ops!(
+                    tokio_uring::no_op(),
+                    tokio_uring::no_op(),
+                    tokio_uring::no_op(),
+                )
+                .await;
+                a.unwrap();
+                b.unwrap();
+                c.unwrap();
+
+                let mut batch = Batch::new();
+                for _ in 0..5 {
+                    batch.push(tokio_uring::no_op());
+                }
+                assert_eq!(batch.len(), 5);
+                let results = batch.run().await;
+                assert!(results.iter().all(Result::is_ok));
+
+                tokio_uring::no_op().await.unwrap();
+            },
+        ));
+
+    assert_eq!(*flushes.lock().unwrap(), [3, 5, 1]);
+}
+
+#[test]
+fn operations_with_personality() {
+    use tokio_uring::fs::File;
+    use tokio_uring::Handle;
+
+    let tempfile = tempfile::NamedTempFile::new().unwrap();
+
+    tokio_uring::start(async {
+        let handle = Handle::current();
+        let personality = handle.register_personality().unwrap();
+
+        let file = tokio_uring::with_personality(personality, File::open(tempfile.path()))
+            .await
+            .unwrap();
+        file.close().await.unwrap();
+
+        handle.unregister_personality(personality).unwrap();
+        let err = tokio_uring::with_personality(personality, File::open(tempfile.path()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+        // Operations outside the scope are not affected
+        File::open(tempfile.path()).await.unwrap();
+    });
+}
+
+#[test]
+fn op_inspector_denies_operations() {
+    use std::sync::{Arc, Mutex};
+    use tokio_uring::fs::File;
+    use tokio_uring::Decision;
+
+    let dir = tempfile::tempdir().unwrap();
+    let allowed = dir.path().join("allowed");
+    let denied = dir.path().join("denied");
+    std::fs::write(&allowed, b"hello").unwrap();
+    std::fs::write(&denied, b"hello").unwrap();
+
+    let inspected = Arc::new(Mutex::new(Vec::new()));
+
+    tokio_uring::builder()
+        .op_inspector({
+            let inspected = inspected.clone();
+            move |op| {
+                inspected.lock().unwrap().push(op.opcode());
+                let denied = [op.path(), op.target_path()]
+                    .iter()
+                    .flatten()
+                    .any(|path| path.to_bytes().ends_with(b"denied"));
+                if denied {
+                    Decision::Deny
+                } else {
+                    Decision::Allow
+                }
+            }
+        })
+        .start(async {
+            let err = File::open(&denied).await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+            let file = File::open(&allowed).await.unwrap();
+            let (res, buf) = file.read_at(vec![0; 16], 0).await;
+            assert_eq!(&buf[..res.unwrap()], b"hello");
+            file.close().await.unwrap();
+
+            // Renaming into a denied path is denied
+            let err = tokio_uring::fs::rename(&allowed, dir.path().join("renamed-denied"))
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        });
+
+    use io_uring::opcode;
+    assert_eq!(
+        *inspected.lock().unwrap(),
+        [
+            opcode::OpenAt::CODE,
+            opcode::OpenAt::CODE,
+            opcode::Read::CODE,
+            opcode::Close::CODE,
+            opcode::RenameAt::CODE
+        ]
+    );
+    assert!(allowed.exists());
+}
+
+#[test]
+fn runtime_with_existing_ring() {
+    use io_uring::{opcode, register::Restriction, IoUring};
+
+    let ring = IoUring::builder().setup_r_disabled().build(8).unwrap();
+    let mut restrictions = [Restriction::sqe_op(opcode::Nop::CODE)];
+    ring.submitter()
+        .register_restrictions(&mut restrictions)
+        .unwrap();
+    ring.submitter().register_enable_rings().unwrap();
+
+    let mut builder = tokio_uring::builder();
+    builder.with_ring(ring);
+    builder.start(async {
+        tokio_uring::no_op().await.unwrap();
+
+        // Operations other than no-op are restricted
+        let res = tokio_uring::fs::File::open("/dev/null").await;
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EACCES));
+    });
+
+    // The ring has been used up
+    let err = tokio_uring::Runtime::new(&builder).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn access_ring_from_handle() {
+    tokio_uring::start(async {
+        let handle = tokio_uring::Handle::current();
+        let mut max_workers = [0, 0];
+        unsafe {
+            handle.with_ring(|ring| ring.submitter().register_iowq_max_workers(&mut max_workers))
+        }
+        .unwrap()
+        .unwrap();
+        assert!(max_workers[1] > 0);
+
+        tokio_uring::no_op().await.unwrap();
+    });
+}
+
+#[test]
+fn spread_spawner_distributes_tasks() {
+    use std::collections::HashMap;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tokio_uring::SpreadSpawner;
+
+    let spawner = SpreadSpawner::new(3).unwrap();
+    assert_eq!(spawner.workers(), 3);
+
+    // Round-robin: each worker gets the same number of tasks
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..9 {
+        let tx = tx.clone();
+        spawner.spawn(move || async move {
+            tokio_uring::no_op().await.unwrap();
+            tx.send(std::thread::current().id()).unwrap();
+        });
+    }
+    let mut counts = HashMap::new();
+    for _ in 0..9 {
+        let id = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        *counts.entry(id).or_insert(0) += 1;
+    }
+    assert_eq!(counts.len(), 3);
+    assert!(counts.values().all(|&n| n == 3));
+
+    // Least loaded: the idle workers get the new tasks. The completed
+    // tasks report before they are dropped, so let them finish first.
+    std::thread::sleep(Duration::from_millis(50));
+    let mut spawner = spawner.clone();
+    spawner.least_loaded(true);
+    let (busy_tx, busy_rx) = mpsc::channel();
+    spawner.spawn(move || async move {
+        busy_tx.send(std::thread::current().id()).unwrap();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    });
+    let busy = busy_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    for _ in 0..2 {
+        let tx = tx.clone();
+        spawner.spawn(move || async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            tx.send(std::thread::current().id()).unwrap();
+        });
+    }
+    let a = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    let b = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_ne!(a, busy);
+    assert_ne!(b, busy);
+    assert_ne!(a, b);
+}
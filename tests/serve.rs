@@ -0,0 +1,33 @@
+#![cfg(feature = "tower")]
+
+use axum::{routing::get, Router};
+use tokio_uring::net::{TcpListener, TcpStream};
+
+#[test]
+fn serve_axum_router() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let app = Router::new().route("/", get(|| async { "Hello, World!" }));
+        tokio_uring::spawn(tokio_uring::net::serve(listener, app.into_make_service()));
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let request = b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+        let (res, _) = stream.write_all(request.as_slice()).await;
+        res.unwrap();
+
+        let mut response = Vec::new();
+        loop {
+            let (res, buf) = stream.read(Vec::with_capacity(4096)).await;
+            if res.unwrap() == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf);
+        }
+
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nHello, World!"));
+    });
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "sim")]
+
+use std::time::{Duration, Instant};
+use tokio_uring::fs::{self, File, OpenOptions};
+use tokio_uring::sim::Sim;
+
+#[test]
+fn in_memory_files() {
+    let sim = Sim::new(1);
+    sim.write_file("input.txt", "hello");
+
+    sim.run(async {
+        let (res, buf) = File::open("input.txt")
+            .await
+            .unwrap()
+            .read_at(vec![0; 16], 1)
+            .await;
+        assert_eq!(&buf[..res.unwrap()], b"ello");
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open("output.txt")
+            .await
+            .unwrap();
+        file.write_all_at(&b"world"[..], 0).await.0.unwrap();
+        file.sync_all().await.unwrap();
+        assert_eq!(file.metadata().await.unwrap().len(), 5);
+        file.close().await.unwrap();
+
+        fs::rename("output.txt", "renamed.txt").await.unwrap();
+        fs::remove_file("input.txt").await.unwrap();
+        let err = File::open("input.txt").await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    });
+
+    assert_eq!(sim.read_file("renamed.txt").unwrap(), b"world");
+    assert_eq!(sim.read_file("output.txt"), None);
+}
+
+#[test]
+fn streams_with_virtual_time() {
+    let sim = Sim::new(1);
+    let start = Instant::now();
+
+    sim.run(async {
+        let (a, b) = sim.tcp_pair();
+
+        let reader = tokio_uring::spawn(async move {
+            let (res, buf) = b.read(vec![0; 16]).await;
+            assert_eq!(&buf[..res.unwrap()], b"ping");
+
+            // Nothing more is written
+            let res =
+                tokio_uring::time::timeout(Duration::from_secs(60), b.read(vec![0; 16])).await;
+            if let Ok((res, _)) = res {
+                assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+            }
+
+            let (res, _) = b.read(vec![0; 16]).await;
+            assert_eq!(res.unwrap(), 0);
+        });
+
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        a.write_all(&b"ping"[..]).await.0.unwrap();
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        drop(a);
+        reader.await.unwrap();
+    });
+
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn reproducible_failures() {
+    fn run(seed: u64) -> Vec<bool> {
+        let sim = Sim::new(seed);
+        sim.fail_with(0.5, libc::EIO);
+        sim.run(async {
+            let file = loop {
+                match OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .open("data")
+                    .await
+                {
+                    Ok(file) => break file,
+                    Err(e) => assert_eq!(e.raw_os_error(), Some(libc::EIO)),
+                }
+            };
+            let mut results = Vec::new();
+            for _ in 0..32 {
+                let (res, _) = file.write_at(vec![0; 8], 0).await;
+                results.push(res.is_ok());
+            }
+            results
+        })
+    }
+
+    let results = run(7);
+    assert!(results.contains(&true));
+    assert!(results.contains(&false));
+    assert_eq!(run(7), results);
+}
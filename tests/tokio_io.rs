@@ -0,0 +1,54 @@
+#![cfg(feature = "tokio-io")]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_uring::net::{TcpListener, TcpStream};
+
+#[test]
+fn tcp_stream_read_write() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let data = b"hello world".repeat(2000);
+        let expected = data.clone();
+
+        let task = tokio_uring::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            // Inherent methods of the same name take precedence over
+            // the extension traits.
+            AsyncWriteExt::write_all(&mut stream, &data).await.unwrap();
+            AsyncWriteExt::shutdown(&mut stream).await.unwrap();
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, expected);
+
+        task.await.unwrap();
+    });
+}
+
+#[test]
+fn tcp_stream_small_reads() {
+    tokio_uring::start(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let task = tokio_uring::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (res, _) = stream.write_all(b"ping pong".as_slice()).await;
+            res.unwrap();
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping ");
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+
+        task.await.unwrap();
+    });
+}
@@ -0,0 +1,58 @@
+use std::ffi::CStr;
+use std::os::unix::io::FromRawFd;
+use tokio_uring::fs::File;
+use tokio_uring::io::Tty;
+
+// Opens a pseudoterminal, returning its master side and the path of its
+// slave side.
+fn openpty() -> (Tty, String) {
+    unsafe {
+        let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC);
+        assert!(fd >= 0);
+        assert_eq!(libc::grantpt(fd), 0);
+        assert_eq!(libc::unlockpt(fd), 0);
+        let path = CStr::from_ptr(libc::ptsname(fd))
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let master = Tty::from_file(File::from_std(std::fs::File::from_raw_fd(fd))).unwrap();
+        (master, path)
+    }
+}
+
+#[test]
+fn pty_raw_mode() {
+    tokio_uring::start(async {
+        let (master, path) = openpty();
+        let tty = Tty::open(&path).await.unwrap();
+
+        master.set_window_size(24, 80).unwrap();
+        assert_eq!(tty.window_size().unwrap(), (24, 80));
+
+        let saved = tty.set_raw().unwrap();
+
+        // Input is available without a newline, and is not echoed
+        master.write_all(&b"abc"[..]).await.0.unwrap();
+        let (res, buf) = tty.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"abc");
+
+        // Output is not translated
+        tty.write_all(&b"xyz\n"[..]).await.0.unwrap();
+        let (res, buf) = master.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"xyz\n");
+
+        tty.set_termios(&saved).unwrap();
+        tty.write_all(&b"xyz\n"[..]).await.0.unwrap();
+        let (res, buf) = master.read(vec![0; 16]).await;
+        assert_eq!(&buf[..res.unwrap()], b"xyz\r\n");
+    });
+}
+
+#[test]
+fn not_a_terminal() {
+    tokio_uring::start(async {
+        let file = tempfile::tempfile().unwrap();
+        let err = Tty::from_file(File::from_std(file)).err().unwrap();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTTY));
+    });
+}
@@ -0,0 +1,33 @@
+use tokio_uring::net::TunTap;
+
+#[test]
+fn tun_interface() {
+    tokio_uring::start(async {
+        let tun = match TunTap::tun("turing%d").await {
+            Ok(tun) => tun,
+            // Creating an interface needs privileges the tests may not have
+            Err(e) if matches!(e.raw_os_error(), Some(libc::EPERM | libc::ENOENT)) => return,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(tun.name().starts_with("turing"));
+        assert!(!tun.name().contains('%'));
+
+        tun.set_mtu(1280).unwrap();
+        tun.set_up(true).unwrap();
+        let mtu = std::fs::read_to_string(format!("/sys/class/net/{}/mtu", tun.name())).unwrap();
+        assert_eq!(mtu.trim(), "1280");
+
+        // A minimal IPv4 header, passed to the kernel as a received packet
+        let mut packet = vec![0u8; 20];
+        packet[0] = 0x45;
+        packet[3] = 20;
+        packet[8] = 64;
+        packet[12..16].copy_from_slice(&[10, 0, 0, 1]);
+        packet[16..20].copy_from_slice(&[10, 0, 0, 2]);
+        let (res, _) = tun.write(packet).await;
+        assert_eq!(res.unwrap(), 20);
+
+        tun.set_up(false).unwrap();
+        tun.close().await.unwrap();
+    });
+}